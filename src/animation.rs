@@ -0,0 +1,235 @@
+// Inline playback for animated GIF and WebP previews.
+//
+// Unlike the video player this needs no ffmpeg: files come through egui's bytes loaders, so the
+// disk image cache serves repeat views, and frames are decoded with the image crate on a worker
+// thread. Only animations drawn in the last frame stay decoded, so scrolling past one frees its
+// textures; whether the reader paused or played it is remembered for when it comes back.
+use anyhow::{bail, Result};
+use eframe::egui::{self, load::BytesPoll};
+use image::{AnimationDecoder, ImageFormat};
+use std::{
+    collections::HashMap,
+    io::Cursor,
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+
+// Per animation, about 50 frames at 640x360; longer animations loop over the frames that fit
+const MAX_DECODED_BYTES: usize = 48 * 1024 * 1024;
+const DEFAULT_DELAY: Duration = Duration::from_millis(100);  // For frames asking for next to none, as browsers do
+
+type Frames = Vec<(egui::TextureHandle, Duration)>;
+type Decoded = Arc<Mutex<Option<Result<Frames, String>>>>;
+
+enum Slot {
+    Fetching,
+    Decoding(Decoded),
+    Ready(Animation),
+    Failed,
+}
+
+struct Animation {
+    frames: Frames,
+    position: Duration,  // Into the loop
+    last_tick: Instant,
+}
+
+impl Animation {
+    // The frame to draw now and how long it stays up, moving the clock on only while playing
+    fn tick(&mut self, now: Instant, playing: bool) -> (&egui::TextureHandle, Duration) {
+        if playing {
+            self.position += now.saturating_duration_since(self.last_tick);
+        }
+        self.last_tick = now;
+        let delays: Vec<Duration> = self.frames.iter().map(|(_, delay)| *delay).collect();
+        let (index, remaining) = frame_at(&delays, &mut self.position);
+        (&self.frames[index].0, remaining)
+    }
+}
+
+#[derive(Default)]
+pub struct Animations {
+    choices: HashMap<String, bool>,      // Played or paused by the reader, by URL; the rest follow autoplay
+    loaded: HashMap<String, (Slot, u64)>,  // With the frame each was last drawn in
+}
+
+impl Animations {
+    // Draws the animation at `url`, or `still` with a play button until it's wanted. `size` is
+    // what Reddit says the animation measures, for holding its place while it loads.
+    pub fn show(&mut self, ui: &mut egui::Ui, url: &str, still: Option<&str>, size: [u32; 2], autoplay: bool, max_size: egui::Vec2) {
+        let playing = self.choices.get(url).copied().unwrap_or(autoplay);
+        if !playing && !self.loaded.contains_key(url) {
+            show_still(ui, still, max_size);
+            if ui.button("▶ Play GIF").clicked() {
+                self.play(url);
+            }
+            return;
+        }
+
+        let (slot, shown) = self.loaded.entry(url.to_string()).or_insert((Slot::Fetching, 0));
+        *shown = ui.ctx().frame_nr();
+        if let Slot::Fetching = slot {
+            match ui.ctx().try_load_bytes(url) {
+                Ok(BytesPoll::Ready { bytes, .. }) => {
+                    let decoded = Decoded::default();
+                    let result = decoded.clone();
+                    let bytes = bytes.to_vec();
+                    let ctx = ui.ctx().clone();
+                    thread::spawn(move || {
+                        let frames = decode(&bytes).map(|frames| {
+                            frames.into_iter()
+                                .map(|(image, delay)| (ctx.load_texture("animation-frame", image, egui::TextureOptions::LINEAR), delay))
+                                .collect()
+                        });
+                        *result.lock().unwrap() = Some(frames.map_err(|e| e.to_string()));
+                        ctx.request_repaint();
+                    });
+                    *slot = Slot::Decoding(decoded);
+                }
+                Ok(BytesPoll::Pending { .. }) => {}
+                Err(_) => *slot = Slot::Failed,
+            }
+        }
+        if let Slot::Decoding(decoded) = slot {
+            let result = decoded.lock().unwrap().take();
+            match result {
+                Some(Ok(frames)) => {
+                    *slot = Slot::Ready(Animation { frames, position: Duration::ZERO, last_tick: Instant::now() });
+                }
+                Some(Err(_)) => *slot = Slot::Failed,
+                None => {}
+            }
+        }
+
+        match slot {
+            Slot::Ready(animation) => {
+                let animated = animation.frames.len() > 1;
+                let (texture, remaining) = animation.tick(Instant::now(), playing);
+                ui.add(egui::Image::new(texture).fit_to_original_size(1.0).max_size(max_size));
+                if !animated {
+                    return;
+                }
+                if playing {
+                    ui.ctx().request_repaint_after(remaining);
+                }
+                let (label, hover) = if playing { ("⏸", "Pause") } else { ("▶", "Play") };
+                if ui.button(label).on_hover_text(hover).clicked() {
+                    self.choices.insert(url.to_string(), !playing);
+                }
+            }
+            // Stills saved with a GIF or WebP name end up here too, and look no different
+            Slot::Failed if still.is_some() => show_still(ui, still, max_size),
+            Slot::Failed => {
+                ui.label(egui::RichText::new("This animation couldn't be played; open the link to see it.").weak());
+            }
+            Slot::Fetching | Slot::Decoding(_) => {
+                let scale = (max_size.x / size[0].max(1) as f32).min(max_size.y / size[1].max(1) as f32).min(1.0);
+                let placeholder = egui::vec2(size[0] as f32 * scale, size[1] as f32 * scale).max(egui::Vec2::splat(32.0));
+                ui.allocate_ui(placeholder, |ui| {
+                    ui.centered_and_justified(|ui| ui.spinner());
+                });
+            }
+        }
+    }
+
+    pub fn play(&mut self, url: &str) {
+        self.choices.insert(url.to_string(), true);
+    }
+
+    // Frees the frames of animations that have scrolled out of view
+    pub fn drop_unseen(&mut self, frame_nr: u64) {
+        self.loaded.retain(|_, (_, shown)| *shown + 1 >= frame_nr);
+    }
+}
+
+fn show_still(ui: &mut egui::Ui, still: Option<&str>, max_size: egui::Vec2) {
+    if let Some(still) = still {
+        ui.add(egui::Image::new(still).fit_to_original_size(1.0).max_size(max_size));
+    }
+}
+
+// Every frame of a GIF or animated WebP with how long it's shown
+fn decode(bytes: &[u8]) -> Result<Vec<(egui::ColorImage, Duration)>> {
+    let frames = match image::guess_format(bytes)? {
+        ImageFormat::Gif => image::codecs::gif::GifDecoder::new(Cursor::new(bytes))?.into_frames(),
+        ImageFormat::WebP => image::codecs::webp::WebPDecoder::new(Cursor::new(bytes))?.into_frames(),
+        format => bail!("Not an animation format: {:?}", format),
+    };
+    let mut decoded = Vec::new();
+    let mut total = 0;
+    for frame in frames {
+        let frame = match frame {
+            Ok(frame) => frame,
+            // A truncated file still plays what arrived
+            Err(_) if !decoded.is_empty() => break,
+            Err(e) => return Err(e.into()),
+        };
+        let (numer, denom) = frame.delay().numer_denom_ms();
+        let delay = Duration::from_millis((numer / denom.max(1)) as u64);
+        let buffer = frame.into_buffer();
+        total += buffer.len();
+        if total > MAX_DECODED_BYTES && !decoded.is_empty() {
+            break;
+        }
+        let size = [buffer.width() as usize, buffer.height() as usize];
+        decoded.push((
+            egui::ColorImage::from_rgba_unmultiplied(size, &buffer),
+            if delay <= Duration::from_millis(10) { DEFAULT_DELAY } else { delay },
+        ));
+    }
+    if decoded.is_empty() {
+        bail!("No frames to play");
+    }
+    Ok(decoded)
+}
+
+// Which frame is up at `position` into the loop, and for how much longer; wraps `position` back
+// into the first loop so it never grows without bound
+fn frame_at(delays: &[Duration], position: &mut Duration) -> (usize, Duration) {
+    let total: Duration = delays.iter().sum();
+    if total.is_zero() {
+        return (0, DEFAULT_DELAY);
+    }
+    *position = Duration::from_nanos((position.as_nanos() % total.as_nanos()) as u64);
+    let mut start = Duration::ZERO;
+    for (index, delay) in delays.iter().enumerate() {
+        if *position < start + *delay {
+            return (index, start + *delay - *position);
+        }
+        start += *delay;
+    }
+    (delays.len() - 1, DEFAULT_DELAY)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{codecs::gif::GifEncoder, Delay, Frame, RgbaImage};
+
+    #[test]
+    fn decodes_and_steps_through_frames() {
+        let mut gif = Vec::new();
+        {
+            let mut encoder = GifEncoder::new(&mut gif);
+            for (shade, millis) in [(0, 50), (255, 0)] {
+                let image = RgbaImage::from_pixel(4, 2, image::Rgba([shade, shade, shade, 255]));
+                encoder.encode_frame(Frame::from_parts(image, 0, 0, Delay::from_numer_denom_ms(millis, 1))).unwrap();
+            }
+        }
+        let frames = decode(&gif).unwrap();
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0].0.size, [4, 2]);
+        assert_eq!(frames[0].1, Duration::from_millis(50));
+        assert_eq!(frames[1].0.pixels[0], egui::Color32::WHITE);
+        assert_eq!(frames[1].1, DEFAULT_DELAY);
+        assert!(decode(b"not an image").is_err());
+
+        let delays = [Duration::from_millis(50), Duration::from_millis(100)];
+        let mut position = Duration::from_millis(20);
+        assert_eq!(frame_at(&delays, &mut position), (0, Duration::from_millis(30)));
+        position = Duration::from_millis(360);
+        assert_eq!(frame_at(&delays, &mut position), (1, Duration::from_millis(90)));
+        assert_eq!(position, Duration::from_millis(60));
+    }
+}
@@ -0,0 +1,337 @@
+// Recent network calls, for the request inspector.
+//
+// Every Reddit API request goes through `send` here, which also logs it, and every image the disk
+// cache serves is recorded as well, so when a feed won't load the inspector shows what actually
+// happened on the wire: which endpoint, what came back, how long it took and how much rate limit was left.
+use eframe::egui;
+use crate::replay;
+use reqwest::{header::HeaderMap, StatusCode};
+use std::{
+    collections::{HashSet, VecDeque},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+const CAPACITY: usize = 200;      // Calls kept; older ones fall off the end
+const BODY_PREVIEW: usize = 8192;  // Bytes of each response body kept while recording bodies
+
+static LOG: ApiLog = ApiLog::new();
+
+pub fn log() -> &'static ApiLog {
+    &LOG
+}
+
+#[derive(Debug, Clone)]
+pub struct Call {
+    pub id: u64,
+    pub at: Instant,
+    pub method: String,
+    pub url: String,
+    pub status: Option<u16>,     // None when no response came back, or the disk cache answered
+    pub error: Option<String>,   // Why the request failed before a response arrived
+    pub latency: Duration,
+    pub rate_limit: Option<String>,  // Reddit's X-Ratelimit headers, when sent
+    pub cached: Option<bool>,    // Whether the disk cache or a replayed session answered; None for live API calls
+    pub body: Option<String>,    // Start of the response body, when recording bodies
+}
+
+impl Call {
+    pub fn new(method: &str, url: &str, latency: Duration) -> Self {
+        Call {
+            id: 0,
+            at: Instant::now(),
+            method: method.to_string(),
+            url: url.to_string(),
+            status: None,
+            error: None,
+            latency,
+            rate_limit: None,
+            cached: None,
+            body: None,
+        }
+    }
+
+    fn failed(&self) -> bool {
+        self.error.is_some() || self.status.is_some_and(|status| status >= 400)
+    }
+}
+
+pub struct ApiLog {
+    calls: Mutex<VecDeque<Call>>,
+    next_id: AtomicU64,
+    record_bodies: AtomicBool,
+}
+
+impl ApiLog {
+    const fn new() -> Self {
+        ApiLog {
+            calls: Mutex::new(VecDeque::new()),
+            next_id: AtomicU64::new(1),
+            record_bodies: AtomicBool::new(false),
+        }
+    }
+
+    pub fn record(&self, mut call: Call) {
+        call.id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let mut calls = self.calls.lock().unwrap();
+        calls.push_back(call);
+        if calls.len() > CAPACITY {
+            calls.pop_front();
+        }
+    }
+
+    // Newest first
+    pub fn calls(&self) -> Vec<Call> {
+        self.calls.lock().unwrap().iter().rev().cloned().collect()
+    }
+
+    pub fn clear(&self) {
+        self.calls.lock().unwrap().clear();
+    }
+
+    pub fn recording_bodies(&self) -> bool {
+        self.record_bodies.load(Ordering::Relaxed)
+    }
+
+    pub fn set_recording_bodies(&self, record: bool) {
+        self.record_bodies.store(record, Ordering::Relaxed);
+    }
+}
+
+// Sends a request and records it. With body recording on, or a session being recorded, the
+// body is read here and handed back in a rebuilt response so callers can still parse it as usual.
+pub async fn send(client: &reqwest::Client, request: reqwest::RequestBuilder) -> reqwest::Result<reqwest::Response> {
+    let request = request.build()?;
+    let (method, url) = (request.method().to_string(), request.url().clone());
+    let started = Instant::now();
+
+    if let Some((status, body)) = replay::answer(&method, &url) {
+        let mut call = Call::new(&method, url.as_str(), started.elapsed());
+        call.status = Some(status);
+        call.cached = Some(true);
+        if LOG.recording_bodies() {
+            call.body = Some(body_preview(body.as_bytes()));
+        }
+        finish(call);
+        let mut replayed = http::Response::new(body);
+        *replayed.status_mut() = StatusCode::from_u16(status).unwrap_or(StatusCode::NOT_FOUND);
+        return Ok(replayed.into());
+    }
+
+    let result = client.execute(request).await;
+    let mut call = Call::new(&method, url.as_str(), started.elapsed());
+
+    let response = match result {
+        Ok(response) => response,
+        Err(e) => {
+            call.error = Some(e.to_string());
+            finish(call);
+            return Err(e);
+        }
+    };
+    call.status = Some(response.status().as_u16());
+    call.rate_limit = rate_limit_headers(response.headers());
+
+    // Token responses are never kept; they carry the credentials for the session
+    if !(LOG.recording_bodies() || replay::recording()) || url.path().ends_with("/access_token") {
+        finish(call);
+        return Ok(response);
+    }
+    let (status, version, headers) = (response.status(), response.version(), response.headers().clone());
+    let bytes = match response.bytes().await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            call.error = Some(e.to_string());
+            finish(call);
+            return Err(e);
+        }
+    };
+    if LOG.recording_bodies() {
+        call.body = Some(body_preview(&bytes));
+    }
+    finish(call);
+    replay::record(&method, &url, status.as_u16(), &bytes);
+
+    let mut rebuilt = http::Response::new(bytes);
+    *rebuilt.status_mut() = status;
+    *rebuilt.version_mut() = version;
+    *rebuilt.headers_mut() = headers;
+    Ok(rebuilt.into())
+}
+
+// Logs a finished API call and keeps it for the inspector
+fn finish(call: Call) {
+    let millis = call.latency.as_millis() as u64;
+    match (&call.error, call.status) {
+        (Some(error), _) => tracing::warn!(method = %call.method, url = %call.url, millis, error = %error, "Request failed"),
+        (None, Some(status)) if status >= 400 => tracing::warn!(method = %call.method, url = %call.url, status, millis, "Request failed"),
+        (None, status) => tracing::debug!(method = %call.method, url = %call.url, status, millis, replayed = call.cached.is_some(), "Request"),
+    }
+    LOG.record(call);
+}
+
+// e.g. "587 left · 13 used · resets in 412s"
+fn rate_limit_headers(headers: &HeaderMap) -> Option<String> {
+    let header = |name: &str| headers.get(name)?.to_str().ok()?.trim().parse::<f64>().ok();
+    let remaining = header("x-ratelimit-remaining")?;
+    let mut parts = vec![format!("{} left", remaining as u32)];
+    if let Some(used) = header("x-ratelimit-used") {
+        parts.push(format!("{} used", used as u32));
+    }
+    if let Some(reset) = header("x-ratelimit-reset") {
+        parts.push(format!("resets in {}s", reset as u32));
+    }
+    Some(parts.join(" · "))
+}
+
+// JSON is pretty-printed so it's readable in the preview; anything else is shown as it came
+fn body_preview(bytes: &[u8]) -> String {
+    let text = serde_json::from_slice::<serde_json::Value>(bytes).ok()
+        .and_then(|json| serde_json::to_string_pretty(&json).ok())
+        .unwrap_or_else(|| String::from_utf8_lossy(bytes).into_owned());
+    if text.len() <= BODY_PREVIEW {
+        return text;
+    }
+    let mut end = BODY_PREVIEW;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    format!("{}\n… ({} bytes in all)", &text[..end], text.len())
+}
+
+fn ago(elapsed: Duration) -> String {
+    match elapsed.as_secs() {
+        seconds @ 0..=59 => format!("{}s ago", seconds),
+        seconds @ 60..=3599 => format!("{}m ago", seconds / 60),
+        seconds => format!("{}h ago", seconds / 3600),
+    }
+}
+
+// Debug window listing the log, newest first
+#[derive(Default)]
+pub struct Inspector {
+    expanded: HashSet<u64>,  // Calls whose body preview is open
+    errors_only: bool,
+}
+
+impl Inspector {
+    // False once the window is closed
+    pub fn show(&mut self, ctx: &egui::Context) -> bool {
+        let mut open = true;
+        egui::Window::new("API requests")
+            .open(&mut open)
+            .default_size([760.0, 420.0])
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    let mut record_bodies = LOG.recording_bodies();
+                    if ui.checkbox(&mut record_bodies, "Record response bodies")
+                        .on_hover_text("Keep the start of each API response for the body preview")
+                        .changed() {
+                        LOG.set_recording_bodies(record_bodies);
+                    }
+                    ui.checkbox(&mut self.errors_only, "Failures only");
+                    if ui.button("Clear").clicked() {
+                        LOG.clear();
+                        self.expanded.clear();
+                    }
+                });
+                ui.separator();
+
+                let calls: Vec<Call> = LOG.calls().into_iter()
+                    .filter(|call| !self.errors_only || call.failed())
+                    .collect();
+                if calls.is_empty() {
+                    ui.label(egui::RichText::new("No requests yet").weak());
+                    return;
+                }
+                egui::ScrollArea::vertical().auto_shrink([false, false]).show(ui, |ui| {
+                    for call in &calls {
+                        self.render_call(ui, call);
+                    }
+                });
+            });
+        // Keep the "ago" column and new calls current while the window is up
+        ctx.request_repaint_after(Duration::from_secs(1));
+        open
+    }
+
+    fn render_call(&mut self, ui: &mut egui::Ui, call: &Call) {
+        ui.horizontal(|ui| {
+            let status = match (call.status, &call.error, call.cached) {
+                (Some(status), _, _) => egui::RichText::new(status.to_string()),
+                (None, Some(_), _) => egui::RichText::new("failed"),
+                (None, None, Some(true)) => egui::RichText::new("disk"),
+                (None, None, _) => egui::RichText::new("—"),
+            };
+            let status = if call.failed() { status.color(ui.visuals().error_fg_color) } else { status };
+            ui.add_sized([56.0, 18.0], egui::Label::new(egui::RichText::new(ago(call.at.elapsed())).weak()));
+            ui.add_sized([40.0, 18.0], egui::Label::new(egui::RichText::new(&call.method).monospace()));
+            ui.add_sized([48.0, 18.0], egui::Label::new(status));
+            ui.add_sized([60.0, 18.0], egui::Label::new(format!("{} ms", call.latency.as_millis())));
+            if let Some(cached) = call.cached {
+                ui.label(egui::RichText::new(if cached { "cache hit" } else { "cache miss" }).weak());
+            }
+            if call.body.is_some() {
+                let expanded = self.expanded.contains(&call.id);
+                if ui.selectable_label(expanded, "Body").clicked() {
+                    if expanded {
+                        self.expanded.remove(&call.id);
+                    } else {
+                        self.expanded.insert(call.id);
+                    }
+                }
+            }
+            let url = ui.add(egui::Label::new(egui::RichText::new(&call.url).monospace()).truncate(true));
+            let details: Vec<&str> = [call.rate_limit.as_deref(), call.error.as_deref()].into_iter().flatten().collect();
+            if !details.is_empty() {
+                url.on_hover_text(details.join("\n"));
+            }
+        });
+        if let Some(body) = call.body.as_ref().filter(|_| self.expanded.contains(&call.id)) {
+            egui::ScrollArea::vertical().id_source(call.id).max_height(240.0).show(ui, |ui| {
+                ui.add(egui::TextEdit::multiline(&mut body.as_str())
+                    .code_editor()
+                    .desired_width(f32::INFINITY));
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    #[test]
+    fn keeps_the_most_recent_calls() {
+        let log = ApiLog::new();
+        for i in 0..CAPACITY + 5 {
+            log.record(Call::new("GET", &format!("https://oauth.reddit.com/{}", i), Duration::ZERO));
+        }
+        let calls = log.calls();
+        assert_eq!(calls.len(), CAPACITY);
+        assert_eq!(calls[0].url, format!("https://oauth.reddit.com/{}", CAPACITY + 4));
+        assert_eq!(calls[CAPACITY - 1].url, "https://oauth.reddit.com/5");
+    }
+
+    #[test]
+    fn summarizes_rate_limit_headers() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(rate_limit_headers(&headers), None);
+        headers.insert("x-ratelimit-remaining", HeaderValue::from_static("587.0"));
+        headers.insert("x-ratelimit-used", HeaderValue::from_static("13"));
+        headers.insert("x-ratelimit-reset", HeaderValue::from_static("412"));
+        assert_eq!(rate_limit_headers(&headers).as_deref(), Some("587 left · 13 used · resets in 412s"));
+    }
+
+    #[test]
+    fn previews_bodies() {
+        assert_eq!(body_preview(br#"{"kind":"Listing"}"#), "{\n  \"kind\": \"Listing\"\n}");
+        assert_eq!(body_preview(b"<html>"), "<html>");
+        let long = "é".repeat(BODY_PREVIEW);
+        assert!(body_preview(long.as_bytes()).ends_with(&format!("… ({} bytes in all)", long.len())));
+    }
+}
@@ -0,0 +1,137 @@
+// Opt-in archive of chosen subreddits' top and new listings, kept in the local database.
+//
+// Every few hours a background job (`RedditApp::start_archive`) fetches each chosen subreddit's
+// top posts of the day and its newest posts, one request at a time with a pause between them.
+// When the rate limit budget gets down to what browsing needs, it waits for the window to reset.
+// Posts are kept for good, so the archive grows into a personal record, and the newest ones
+// stand in for a subreddit's feed when it's opened offline. Each listing is compared with the
+// snapshot before it, so the archive window can show what turned up and what dropped off since.
+use crate::Post;
+use eframe::egui;
+use std::{
+    collections::HashSet,
+    time::{Duration, Instant},
+};
+
+pub const RESERVE: u32 = 150;                        // Requests left to browsing; below this the job waits for the reset
+pub const PAUSE: Duration = Duration::from_secs(2);  // Between requests, so a run never arrives as a burst
+pub const LIMIT: u32 = 100;                          // Posts per listing, a single page
+pub const INTERVALS: [u32; 3] = [1, 6, 24];          // Hours between runs offered in settings
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Listing {
+    #[default]
+    Top,  // Top of the day
+    New,
+}
+
+impl Listing {
+    pub const ALL: [Listing; 2] = [Listing::Top, Listing::New];
+
+    pub fn api_name(self) -> &'static str {
+        match self {
+            Listing::Top => "top",
+            Listing::New => "new",
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Listing::Top => "Top today",
+            Listing::New => "New",
+        }
+    }
+}
+
+// What changed in a listing since it was last archived
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Change {
+    pub subreddit: String,
+    pub listing: Listing,
+    pub added: Vec<String>,  // Titles of posts the last snapshot didn't have
+    pub dropped: usize,      // Posts it had that are gone now
+    pub first: bool,         // Nothing earlier to compare with
+}
+
+// `previous` holds the fullnames in the last snapshot, if there was one
+pub fn diff(subreddit: &str, listing: Listing, previous: Option<&[String]>, current: &[Post]) -> Change {
+    let Some(previous) = previous else {
+        return Change { subreddit: subreddit.to_string(), listing, first: true, ..Change::default() };
+    };
+    let before: HashSet<&str> = previous.iter().map(String::as_str).collect();
+    let now: HashSet<&str> = current.iter().map(|post| post.name.as_str()).collect();
+    Change {
+        subreddit: subreddit.to_string(),
+        listing,
+        added: current.iter().filter(|post| !before.contains(post.name.as_str())).map(|post| post.title.clone()).collect(),
+        dropped: before.difference(&now).count(),
+        first: false,
+    }
+}
+
+#[derive(Default)]
+pub struct Status {
+    pub running: bool,
+    pub done: usize,                   // Listings fetched this run
+    pub total: usize,
+    pub resumes_at: Option<Instant>,   // Waiting for the rate limit window to reset
+    pub cancelled: bool,               // Stop asked for; the run ends before its next listing
+    pub changes: Vec<Change>,          // From the last finished run
+    pub finished_at: Option<i64>,
+    pub archived: usize,               // Posts in the archive
+}
+
+// The changes from the last run; false once the window is closed
+pub fn show(ctx: &egui::Context, status: &Status) -> bool {
+    let mut open = true;
+    egui::Window::new("Archive")
+        .open(&mut open)
+        .default_width(460.0)
+        .show(ctx, |ui| {
+            ui.label(egui::RichText::new(format!("{} posts archived", status.archived)).weak());
+            if status.changes.is_empty() {
+                ui.label(egui::RichText::new("Changes show here after the next run.").weak());
+                return;
+            }
+            ui.separator();
+            egui::ScrollArea::vertical().max_height(400.0).show(ui, |ui| {
+                for change in &status.changes {
+                    let heading = format!("r/{} · {}", change.subreddit, change.listing.label());
+                    if change.first {
+                        ui.label(format!("{}: first snapshot", heading));
+                        continue;
+                    }
+                    let summary = format!("{}: {} new, {} dropped off", heading, change.added.len(), change.dropped);
+                    if change.added.is_empty() {
+                        ui.label(summary);
+                        continue;
+                    }
+                    egui::CollapsingHeader::new(summary)
+                        .id_source(("archive_change", &change.subreddit, change.listing.api_name()))
+                        .show(ui, |ui| {
+                            for title in &change.added {
+                                ui.label(format!("+ {}", title));
+                            }
+                        });
+                }
+            });
+        });
+    open
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compares_with_the_last_snapshot() {
+        let post = |name: &str, title: &str| -> Post {
+            serde_json::from_value(serde_json::json!({"name": name, "title": title})).unwrap()
+        };
+        let current = [post("t3_a", "Kept"), post("t3_c", "Fresh")];
+        let previous = ["t3_a".to_string(), "t3_b".to_string()];
+        let change = diff("rust", Listing::New, Some(&previous), &current);
+        assert_eq!((change.added.as_slice(), change.dropped, change.first), (["Fresh".to_string()].as_slice(), 1, false));
+        assert!(diff("rust", Listing::Top, None, &current).first);
+    }
+}
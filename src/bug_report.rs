@@ -0,0 +1,195 @@
+// Bug report bundles: one zip with what's needed to make sense of a problem report, for
+// attaching to a GitHub issue.
+//
+// It holds the app version and platform, the errors shown this session, the request
+// inspector's trace and the debug console's recent log events. Requests go in as method, URL,
+// status and timing only: response bodies are left out, and so is every query value, since
+// those say what the reader was browsing. Secrets that turn up anywhere else (tokens, passwords
+// and client secrets in log lines or error messages) are redacted.
+use crate::api_log::Call;
+use crate::logging::Entry;
+use anyhow::{Context, Result};
+use regex::Regex;
+use std::collections::VecDeque;
+use std::io::Write;
+use std::path::Path;
+use std::sync::OnceLock;
+use std::time::Instant;
+
+const ERRORS_KEPT: usize = 100;
+const REDACTED: &str = "[redacted]";
+const SECRETS: &[&str] = &["access_token", "refresh_token", "id_token", "password", "passwd", "client_secret", "code", "state", "modhash", "token"];
+
+// Errors the reader has been shown this session, oldest first
+#[derive(Default)]
+pub struct ErrorLog {
+    entries: VecDeque<(i64, String)>,
+    showing: Option<String>,
+}
+
+impl ErrorLog {
+    // Called every frame with the error on screen; each one is logged once, when it appears
+    pub fn note(&mut self, showing: Option<String>, now: i64) {
+        if showing == self.showing {
+            return;
+        }
+        if let Some(message) = &showing {
+            tracing::error!(shown = %message, "Error shown");
+            self.entries.push_back((now, message.clone()));
+            if self.entries.len() > ERRORS_KEPT {
+                self.entries.pop_front();
+            }
+        }
+        self.showing = showing;
+    }
+}
+
+// e.g. "rustle-bug-report-1711029900.zip"
+pub fn file_name(now: i64) -> String {
+    format!("rustle-bug-report-{}.zip", now)
+}
+
+pub fn write(path: &Path, errors: &ErrorLog, calls: &[Call], events: &[Entry], now: i64) -> Result<()> {
+    let file = std::fs::File::create(path).with_context(|| format!("Failed to create {}", path.display()))?;
+    let mut zip = zip::ZipWriter::new(file);
+    let options = zip::write::FileOptions::default();
+    for (name, contents) in [
+        ("report.txt", summary(now)),
+        ("errors.log", error_lines(errors)),
+        ("requests.log", request_lines(calls, Instant::now())),
+        ("debug.log", events.iter().map(|event| scrub(&event.line()) + "\n").collect()),
+    ] {
+        zip.start_file(name, options)?;
+        zip.write_all(contents.as_bytes())?;
+    }
+    zip.finish().context("Failed to finish the bug report")?;
+    Ok(())
+}
+
+fn summary(now: i64) -> String {
+    let mut features = Vec::new();
+    if cfg!(feature = "video") {
+        features.push("video");
+    }
+    if cfg!(feature = "notifications") {
+        features.push("notifications");
+    }
+    format!(
+        "{} {}\nOS: {} {}{}\nFeatures: {}\nCreated: {}\n",
+        crate::APP_NAME,
+        env!("CARGO_PKG_VERSION"),
+        std::env::consts::OS,
+        std::env::consts::ARCH,
+        os_release().map(|name| format!(" ({})", name)).unwrap_or_default(),
+        if features.is_empty() { "none".to_string() } else { features.join(", ") },
+        now,
+    )
+}
+
+// The distribution on Linux, e.g. "Fedora Linux 40"; other platforms only get the OS family
+fn os_release() -> Option<String> {
+    let release = std::fs::read_to_string("/etc/os-release").ok()?;
+    let name = release.lines().find_map(|line| line.strip_prefix("PRETTY_NAME="))?;
+    Some(name.trim_matches('"').to_string())
+}
+
+fn error_lines(errors: &ErrorLog) -> String {
+    if errors.entries.is_empty() {
+        return "No errors this session\n".to_string();
+    }
+    errors.entries.iter().map(|(at, message)| format!("{} {}\n", at, scrub(message))).collect()
+}
+
+// Oldest first, like a log, with each call's age when the bundle was made
+fn request_lines(calls: &[Call], now: Instant) -> String {
+    if calls.is_empty() {
+        return "No requests recorded\n".to_string();
+    }
+    let mut text = String::new();
+    for call in calls.iter().rev() {
+        let status = match (&call.error, call.status) {
+            (Some(error), _) => format!("failed: {}", scrub(error)),
+            (None, Some(status)) => status.to_string(),
+            (None, None) => "-".to_string(),
+        };
+        text.push_str(&format!(
+            "{}s ago {} {} {} {}ms{}{}\n",
+            now.saturating_duration_since(call.at).as_secs(),
+            call.method,
+            without_query_values(&call.url),
+            status,
+            call.latency.as_millis(),
+            if call.cached == Some(true) { " cached" } else { "" },
+            call.rate_limit.as_ref().map(|limit| format!(" [{}]", limit)).unwrap_or_default(),
+        ));
+    }
+    text
+}
+
+// For free text that might quote a URL or a bit of JSON
+fn scrub(text: &str) -> String {
+    redact(&without_query_values(text))
+}
+
+// e.g. "https://oauth.reddit.com/r/rust/hot?limit=&after=": which parameters were sent, not what
+fn without_query_values(text: &str) -> String {
+    static QUERY: OnceLock<Regex> = OnceLock::new();
+    let query = QUERY.get_or_init(|| Regex::new(r"([?&][^=&#\s]+=)[^&#\s)]*").unwrap());
+    query.replace_all(text, "${1}").into_owned()
+}
+
+// Blanks the values of secret-looking query parameters, form fields and JSON keys
+fn redact(text: &str) -> String {
+    static PATTERNS: OnceLock<[Regex; 2]> = OnceLock::new();
+    let [query, json] = PATTERNS.get_or_init(|| {
+        let names = SECRETS.join("|");
+        [
+            Regex::new(&format!(r"(?i)([?&](?:{})=)[^&#\s]*", names)).unwrap(),
+            Regex::new(&format!(r#"(?i)("(?:{})"\s*:\s*)"(?:[^"\\]|\\.)*""#, names)).unwrap(),
+        ]
+    });
+    let text = query.replace_all(text, format!("${{1}}{}", REDACTED));
+    json.replace_all(&text, format!("${{1}}\"{}\"", REDACTED)).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+    use std::time::Duration;
+
+    #[test]
+    fn bundles_redacted_logs() {
+        assert_eq!(
+            redact("https://www.reddit.com/api/v1/authorize?client_id=abc&state=xyz&code=secret#_"),
+            "https://www.reddit.com/api/v1/authorize?client_id=abc&state=[redacted]&code=[redacted]#_",
+        );
+        assert_eq!(redact(r#"{"refresh_token": "r-1\"2", "name": "alice"}"#), r#"{"refresh_token": "[redacted]", "name": "alice"}"#);
+
+        let mut errors = ErrorLog::default();
+        errors.note(Some("Error fetching posts: 503".to_string()), 10);
+        errors.note(Some("Error fetching posts: 503".to_string()), 11);
+        errors.note(None, 12);
+        errors.note(Some("Error fetching posts: 503".to_string()), 13);
+        assert_eq!(error_lines(&errors), "10 Error fetching posts: 503\n13 Error fetching posts: 503\n");
+
+        let mut call = Call::new("GET", "https://oauth.reddit.com/r/rust/hot?raw_json=1&after=t3_x&token=t0p", Duration::from_millis(120));
+        call.status = Some(200);
+        call.body = Some(r#"{"modhash": "m0d", "title": "Something personal"}"#.to_string());
+        let mut failed = Call::new("GET", "https://i.redd.it/a.jpg?s=sig", Duration::from_millis(5));
+        failed.error = Some("error sending request for url (https://i.redd.it/a.jpg?s=sig)".to_string());
+        let path = std::env::temp_dir().join(file_name(std::process::id() as i64));
+        write(&path, &errors, &[failed, call], &[], 20).unwrap();
+
+        let mut zip = zip::ZipArchive::new(std::fs::File::open(&path).unwrap()).unwrap();
+        let mut requests = String::new();
+        zip.by_name("requests.log").unwrap().read_to_string(&mut requests).unwrap();
+        assert!(requests.contains("GET https://oauth.reddit.com/r/rust/hot?raw_json=&after=&token= 200 120ms"));
+        assert!(requests.contains("failed: error sending request for url (https://i.redd.it/a.jpg?s=) 5ms"));
+        assert!(!requests.contains("m0d") && !requests.contains("personal") && !requests.contains("sig"));
+        let mut report = String::new();
+        zip.by_name("report.txt").unwrap().read_to_string(&mut report).unwrap();
+        assert!(report.starts_with(&format!("Rustle {}\nOS: ", env!("CARGO_PKG_VERSION"))));
+        std::fs::remove_file(path).unwrap();
+    }
+}
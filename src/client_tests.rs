@@ -0,0 +1,329 @@
+// RedditClient against a local mock of Reddit's API, using the same fixtures as the parser tests.
+use super::*;
+use mockito::{Matcher, Server, ServerGuard};
+
+const LISTING_FIXTURE: &str = include_str!("../tests/fixtures/listing.json");
+const LAST_PAGE: &str = r#"{"kind": "Listing", "data": {"after": null, "children": [
+    {"kind": "t3", "data": {"name": "t3_last", "id": "last", "title": "The end", "subreddit": "rust"}}
+]}}"#;
+
+// A signed-in client whose requests all go to `server`
+fn client(server: &ServerGuard) -> RedditClient {
+    let mut client = RedditClient::new().unwrap();
+    client.oauth_host = server.url();
+    client.www_host = server.url();
+    client.access_token = Some("token".to_string());
+    client
+}
+
+fn raw_json_only() -> Matcher {
+    Matcher::Regex("^raw_json=1$".to_string())
+}
+
+// A feed's first page, `limit` posts long
+fn first_page(limit: u32) -> Matcher {
+    Matcher::Regex(format!("^raw_json=1&limit={}$", limit))
+}
+
+#[tokio::test]
+async fn authenticates_with_password() {
+    let mut server = Server::new_async().await;
+    let token = server.mock("POST", "/api/v1/access_token")
+        .match_header("authorization", "Basic YXBwOnNlY3JldA==")  // app:secret
+        .match_body(Matcher::AllOf(vec![
+            Matcher::UrlEncoded("grant_type".to_string(), "password".to_string()),
+            Matcher::UrlEncoded("username".to_string(), "ferris".to_string()),
+        ]))
+        .with_body(r#"{"access_token": "abc", "token_type": "bearer", "expires_in": 3600}"#)
+        .create_async().await;
+
+    let mut client = client(&server);
+    client.access_token = None;
+    client.authenticate("app", "secret", "ferris", "hunter2").await.unwrap();
+    token.assert_async().await;
+    assert_eq!(client.access_token.as_deref(), Some("abc"));
+    assert!(!client.token_expiring());
+}
+
+#[tokio::test]
+async fn reports_rejected_passwords() {
+    let mut server = Server::new_async().await;
+    // Reddit says so in a 200 response
+    server.mock("POST", "/api/v1/access_token")
+        .with_body(r#"{"error": "invalid_grant"}"#)
+        .create_async().await;
+
+    let mut client = client(&server);
+    let error = client.authenticate("app", "secret", "ferris", "wrong").await.unwrap_err();
+    assert!(format!("{:#}", error).contains("invalid_grant"));
+}
+
+#[tokio::test]
+async fn fetches_the_home_feed() {
+    let mut server = Server::new_async().await;
+    let feed = server.mock("GET", "/")
+        .match_header("authorization", "Bearer token")
+        .match_query(first_page(50))
+        .with_body(LISTING_FIXTURE)
+        .create_async().await;
+
+    let (posts, after) = client(&server).get_home_feed(None, 50).await.unwrap();
+    feed.assert_async().await;
+    assert_eq!(posts.len(), 3);
+    assert_eq!(after.as_deref(), Some("t3_1c2v3b4"));
+}
+
+#[tokio::test]
+async fn pages_through_a_subreddit() {
+    let mut server = Server::new_async().await;
+    server.mock("GET", "/r/rust")
+        .match_query(first_page(25))
+        .with_body(LISTING_FIXTURE)
+        .create_async().await;
+    server.mock("GET", "/r/rust")
+        .match_query(Matcher::UrlEncoded("after".to_string(), "t3_1c2v3b4".to_string()))
+        .with_body(LAST_PAGE)
+        .create_async().await;
+
+    let client = client(&server);
+    let (first, after) = client.get_subreddit_posts("rust", None, None, 25).await.unwrap();
+    let (second, end) = client.get_subreddit_posts("rust", None, after.as_deref(), 25).await.unwrap();
+    assert_eq!(first.len(), 3);
+    assert_eq!(second[0].name, "t3_last");
+    assert_eq!(end, None);
+}
+
+#[tokio::test]
+async fn fetches_saved_posts() {
+    let mut server = Server::new_async().await;
+    let saved = server.mock("GET", "/user/ferris/saved")
+        .match_query(Matcher::Regex("^raw_json=1&type=links&limit=100$".to_string()))
+        .with_body(LAST_PAGE)
+        .create_async().await;
+
+    let mut client = client(&server);
+    assert!(client.get_feed(SAVED, None, None, 100).await.is_err());
+    client.username = "ferris".to_string();
+    let (posts, after) = client.get_feed(SAVED, None, None, 100).await.unwrap();
+    saved.assert_async().await;
+    assert_eq!(posts[0].name, "t3_last");
+    assert_eq!(after, None);
+}
+
+#[tokio::test]
+async fn loads_more_replies_in_the_threads_order() {
+    let mut server = Server::new_async().await;
+    let more_children = server.mock("GET", "/api/morechildren")
+        .match_query(Matcher::AllOf(vec![
+            Matcher::UrlEncoded("link_id".to_string(), "t3_p1".to_string()),
+            Matcher::UrlEncoded("children".to_string(), "c1,c2".to_string()),
+            Matcher::UrlEncoded("sort".to_string(), "new".to_string()),
+        ]))
+        .with_body(r#"{"json": {"errors": [], "data": {"things": [
+            {"kind": "t1", "data": {"id": "c1", "name": "t1_c1", "parent_id": "t3_p1", "body": "First", "replies": ""}}
+        ]}}}"#)
+        .create_async().await;
+
+    let more = comments::MoreComments {
+        name: "t1_m".to_string(),
+        parent_id: "t3_p1".to_string(),
+        count: 2,
+        children: vec!["c1".to_string(), "c2".to_string()],
+        ..Default::default()
+    };
+    let things = client(&server).get_more_children("t3_p1", &more, "new").await.unwrap();
+    more_children.assert_async().await;
+    assert!(matches!(&things[..], [comments::CommentThing::Comment(comment)] if comment.body == "First"));
+}
+
+#[tokio::test]
+async fn uses_public_endpoints_without_a_token() {
+    let mut server = Server::new_async().await;
+    server.mock("GET", "/r/rust.json")
+        .match_query(first_page(25))
+        .with_body(LISTING_FIXTURE)
+        .create_async().await;
+
+    let mut client = client(&server);
+    client.access_token = None;
+    client.public = true;
+    let (posts, _) = client.get_subreddit_posts("rust", None, None, 25).await.unwrap();
+    assert_eq!(posts.len(), 3);
+}
+
+#[tokio::test]
+async fn sorts_failures_by_kind() {
+    let mut server = Server::new_async().await;
+    server.mock("GET", "/r/garbled").match_query(Matcher::Any).with_body("<html>Reddit is down</html>").create_async().await;
+    server.mock("GET", "/r/revoked").match_query(Matcher::Any).with_status(401).create_async().await;
+    server.mock("GET", "/r/busy").match_query(Matcher::Any).with_status(429).with_header("retry-after", "30").create_async().await;
+    server.mock("GET", "/r/broken").match_query(Matcher::Any).with_status(503).create_async().await;
+
+    let client = client(&server);
+    let fetch = |subreddit: &'static str| {
+        let client = client.clone();
+        async move { RustleError::classify(&client.get_subreddit_posts(subreddit, None, None, 25).await.unwrap_err()) }
+    };
+    assert!(matches!(fetch("garbled").await, RustleError::Parse(_)));
+    assert!(matches!(fetch("revoked").await, RustleError::Auth { failure: AuthFailure::Ended, .. }));
+    assert!(matches!(fetch("busy").await, RustleError::RateLimited { retry_after } if retry_after > Instant::now() + Duration::from_secs(25)));
+    let broken = fetch("broken").await;
+    assert!(matches!(broken, RustleError::Api { code: 503, .. }));
+    assert!(broken.retryable());
+}
+
+#[tokio::test]
+async fn autocompletes_names() {
+    let mut server = Server::new_async().await;
+    server.mock("GET", "/api/subreddit_autocomplete_v2")
+        .match_query(Matcher::UrlEncoded("include_profiles".to_string(), "true".to_string()))
+        .with_body(r#"{"kind": "Listing", "data": {"children": [
+            {"kind": "t5", "data": {"display_name": "rust"}},
+            {"kind": "t5", "data": {"display_name": "u_ferris"}},
+            {"kind": "t2", "data": {"name": "ferris"}}
+        ]}}"#)
+        .create_async().await;
+
+    let client = client(&server);
+    assert_eq!(client.autocomplete("fer", true, false).await.unwrap(), ["ferris"]);
+}
+
+#[tokio::test]
+async fn polls_the_inbox_without_marking_it_read() {
+    let mut server = Server::new_async().await;
+    let unread = server.mock("GET", "/message/unread")
+        .match_query(Matcher::UrlEncoded("mark".to_string(), "false".to_string()))
+        .with_body(r#"{"kind": "Listing", "data": {"children": [
+            {"kind": "t4", "data": {"name": "t4_dm", "author": "ferris", "subject": "Hi", "body": "Hello", "was_comment": false}}
+        ]}}"#)
+        .create_async().await;
+
+    let messages = client(&server).get_unread().await.unwrap();
+    unread.assert_async().await;
+    assert_eq!(messages[0].summary(), "Message from u/ferris");
+}
+
+#[tokio::test]
+async fn moderates_posts_and_reads_the_queue() {
+    let mut server = Server::new_async().await;
+    let queue = server.mock("GET", "/r/mod/about/modqueue")
+        .match_query(Matcher::UrlEncoded("only".to_string(), "links".to_string()))
+        .with_body(LAST_PAGE)
+        .create_async().await;
+    let spam = server.mock("POST", "/api/remove")
+        .match_query(raw_json_only())
+        .match_body(Matcher::AllOf(vec![
+            Matcher::UrlEncoded("id".to_string(), "t3_last".to_string()),
+            Matcher::UrlEncoded("spam".to_string(), "true".to_string()),
+        ]))
+        .create_async().await;
+    server.mock("POST", "/api/distinguish").match_query(Matcher::Any).with_status(403).create_async().await;
+
+    let client = client(&server);
+    let (posts, _) = client.get_feed(moderation::QUEUE, None, None, 25).await.unwrap();
+    queue.assert_async().await;
+    client.remove(&posts[0].name, true).await.unwrap();
+    spam.assert_async().await;
+    assert!(matches!(RustleError::classify(&client.distinguish("t3_last", true).await.unwrap_err()), RustleError::Api { code: 403, .. }));
+}
+
+#[tokio::test]
+async fn looks_up_trending_subreddits() {
+    let mut server = Server::new_async().await;
+    server.mock("GET", "/api/trending_subreddits")
+        .match_query(raw_json_only())
+        .with_body(r#"{"subreddit_names": ["rust", "pics"], "comment_count": 3}"#)
+        .create_async().await;
+    let info = server.mock("GET", "/api/info")
+        .match_query(Matcher::UrlEncoded("sr_name".to_string(), "rust,pics".to_string()))
+        .with_body(r#"{"kind": "Listing", "data": {"children": [
+            {"kind": "t5", "data": {"display_name": "rust", "subscribers": 345678}},
+            {"kind": "t5", "data": {"display_name": "pics", "subscribers": 31000000}}
+        ]}}"#)
+        .create_async().await;
+
+    let subreddits = client(&server).get_trending_subreddits().await.unwrap();
+    info.assert_async().await;
+    let names: Vec<&str> = subreddits.iter().map(|about| about.display_name.as_str()).collect();
+    assert_eq!(names, ["rust", "pics"]);
+}
+
+#[tokio::test]
+async fn submits_posts_with_flair() {
+    let mut server = Server::new_async().await;
+    server.mock("GET", "/api/v1/rust/post_requirements")
+        .match_query(raw_json_only())
+        .with_body(r#"{"is_flair_required": true, "title_required_strings": null, "body_restriction_policy": "notAllowed"}"#)
+        .create_async().await;
+    server.mock("GET", "/r/rust/api/link_flair_v2")
+        .match_query(raw_json_only())
+        .with_body(r#"[{"id": "f1", "text": "Help", "text_editable": false}, {"id": "m1", "text": "Announcement", "mod_only": true}]"#)
+        .create_async().await;
+    let submit = server.mock("POST", "/api/submit")
+        .match_query(raw_json_only())
+        .match_body(Matcher::AllOf(vec![
+            Matcher::UrlEncoded("sr".to_string(), "rust".to_string()),
+            Matcher::UrlEncoded("kind".to_string(), "link".to_string()),
+            Matcher::UrlEncoded("url".to_string(), "https://github.com/rust-lang/rust".to_string()),
+            Matcher::UrlEncoded("flair_id".to_string(), "f1".to_string()),
+        ]))
+        .with_body(r#"{"json": {"errors": [], "data": {"url": "https://www.reddit.com/r/rust/comments/abc/x/", "name": "t3_abc"}}}"#)
+        .create_async().await;
+
+    let client = client(&server);
+    let mut draft = submit::Draft::new("rust".to_string());
+    draft.requirements = Some(client.get_post_requirements("rust").await.unwrap());
+    draft.flairs = client.get_link_flairs("rust").await.unwrap();
+    assert_eq!(draft.flairs.len(), 1);
+    draft.kind = submit::Kind::Link;
+    draft.title = "Rust".to_string();
+    draft.url = "https://github.com/rust-lang/rust".to_string();
+    assert_eq!(draft.problems()[0].field, submit::Field::Flair);
+    draft.flair = draft.flairs.first().cloned();
+    assert!(draft.problems().is_empty());
+
+    let url = client.submit_post(&draft.saved()).await.unwrap();
+    submit.assert_async().await;
+    assert_eq!(url, "https://www.reddit.com/r/rust/comments/abc/x/");
+}
+
+#[tokio::test]
+async fn fetches_the_signed_in_account() {
+    let mut server = Server::new_async().await;
+    let mock = server.mock("GET", "/api/v1/me")
+        .match_query(raw_json_only())
+        .match_header("authorization", "Bearer token")
+        .with_body(r#"{"name": "ferris", "link_karma": 42, "comment_karma": 7, "inbox_count": 1, "is_gold": false}"#)
+        .create_async().await;
+
+    let me = client(&server).get_me().await.unwrap();
+    assert_eq!((me.name.as_str(), me.link_karma, me.comment_karma, me.inbox_count), ("ferris", 42, 7, 1));
+    mock.assert_async().await;
+}
+
+#[tokio::test]
+async fn opts_in_to_a_quarantined_subreddit() {
+    let mut server = Server::new_async().await;
+    server.mock("GET", "/r/edgy")
+        .match_query(first_page(25))
+        .with_status(403)
+        .with_body(r#"{"reason": "quarantined", "quarantine_message": "Viewer discretion", "message": "Forbidden", "error": 403}"#)
+        .create_async().await;
+    let opt_in = server.mock("POST", "/api/quarantine_optin")
+        .match_query(raw_json_only())
+        .match_body(Matcher::AllOf(vec![
+            Matcher::UrlEncoded("accept".to_string(), "true".to_string()),
+            Matcher::UrlEncoded("sr_name".to_string(), "edgy".to_string()),
+        ]))
+        .with_body("{}")
+        .create_async().await;
+
+    let client = client(&server);
+    let error = client.get_subreddit_posts("edgy", None, None, 25).await.unwrap_err();
+    assert!(matches!(
+        RustleError::classify(&error).context("Error fetching posts"),
+        RustleError::Restricted { restriction: subreddit::Restriction::Quarantined { .. }, .. }
+    ));
+    client.opt_in_quarantine("edgy").await.unwrap();
+    opt_in.assert_async().await;
+}
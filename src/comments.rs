@@ -0,0 +1,692 @@
+// Comment tree models and the bookkeeping that decides which part of a thread is drawn.
+//
+// Large threads can hold thousands of comments nested dozens of levels deep, so the view
+// only expands a bounded number of levels and children per node up front. Everything else
+// sits behind "show more" / "continue this thread" rows the reader can open on demand.
+// Replies Reddit left out of the response altogether are fetched with /api/morechildren when
+// their "load more" row is clicked, and spliced in where the row was.
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::{
+    collections::{HashMap, HashSet},
+    ops::Range,
+};
+
+// How many nesting levels are shown before a "continue this thread" row
+pub const MAX_VISIBLE_DEPTH: usize = 6;
+// How many children of a node are shown at first, and per "show more" click
+pub const CHILDREN_PAGE: usize = 10;
+// Most ids /api/morechildren takes at once; the rest stay behind a new "load more" row
+pub const MORE_CHILDREN_BATCH: usize = 100;
+// Threads with at least this many rows get a minimap beside the scroll area
+pub const MINIMAP_MIN_ROWS: usize = 40;
+
+const ROOT_KEY: &str = "root";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "data")]
+pub enum CommentThing {
+    #[serde(rename = "t1")]
+    Comment(Comment),
+    #[serde(rename = "more")]
+    More(MoreComments),
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+#[allow(dead_code)]  // Not every field is surfaced in the UI yet
+pub struct Comment {
+    pub id: String,
+    pub name: String,
+    pub parent_id: String,
+    pub author: String,
+    #[serde(with = "crate::html")]
+    pub body: String,
+    pub score: i32,
+    pub score_hidden: bool,
+    pub created_utc: f64,
+    pub depth: u32,
+    pub stickied: bool,
+    pub is_submitter: bool,
+    pub distinguished: Option<String>,  // "moderator" or "admin" when posted in an official capacity
+    pub locked: bool,
+    pub removed: bool,  // Only reported to the subreddit's moderators
+    pub likes: Option<bool>,
+    #[serde(deserialize_with = "deserialize_replies")]
+    pub replies: Vec<CommentThing>,
+}
+
+// Placeholder for children Reddit didn't include in the response
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+#[allow(dead_code)]
+pub struct MoreComments {
+    pub id: String,
+    pub name: String,
+    pub parent_id: String,
+    pub count: u32,
+    pub depth: u32,
+    pub children: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CommentListing {
+    pub data: CommentListingData,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CommentListingData {
+    pub children: Vec<CommentThing>,
+}
+
+// /api/morechildren answers with the comments as a flat list, each pointing at its parent
+#[derive(Debug, Deserialize)]
+pub struct MoreChildrenResponse {
+    json: MoreChildrenJson,
+}
+
+#[derive(Debug, Deserialize)]
+struct MoreChildrenJson {
+    #[serde(default)]
+    errors: Vec<Vec<serde_json::Value>>,
+    data: Option<MoreChildrenData>,
+}
+
+#[derive(Debug, Deserialize)]
+struct MoreChildrenData {
+    things: Vec<CommentThing>,
+}
+
+impl MoreChildrenResponse {
+    pub fn into_things(self) -> Result<Vec<CommentThing>> {
+        if let Some(error) = self.json.errors.first() {
+            let message = error.get(1).and_then(|m| m.as_str()).unwrap_or("unknown error");
+            return Err(anyhow!("Reddit couldn't load the replies: {}", message));
+        }
+        Ok(self.json.data.map(|data| data.things).unwrap_or_default())
+    }
+}
+
+// Reddit sends `"replies": ""` for leaf comments and a full listing otherwise; the offline
+// store writes the children back out as a plain array
+fn deserialize_replies<'de, D>(deserializer: D) -> Result<Vec<CommentThing>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value = serde_json::Value::deserialize(deserializer)?;
+    if value.is_array() {
+        return serde_json::from_value(value).map_err(serde::de::Error::custom);
+    }
+    if !value.is_object() {
+        return Ok(Vec::new());
+    }
+    serde_json::from_value::<CommentListing>(value)
+        .map(|listing| listing.data.children)
+        .map_err(serde::de::Error::custom)
+}
+
+impl Comment {
+    pub fn author_deleted(&self) -> bool {
+        crate::is_deleted_marker(&self.author)
+    }
+
+    pub fn body_deleted(&self) -> bool {
+        crate::is_deleted_marker(&self.body)
+    }
+}
+
+// Total number of comments below these nodes, including ones Reddit hasn't sent yet
+pub fn count_descendants(things: &[CommentThing]) -> usize {
+    things.iter()
+        .map(|thing| match thing {
+            CommentThing::Comment(comment) => 1 + count_descendants(&comment.replies),
+            CommentThing::More(more) => more.count as usize,
+        })
+        .sum()
+}
+
+// Every loaded comment paired with the comment it replies to, oldest first
+pub fn chronological(roots: &[CommentThing]) -> Vec<(&Comment, Option<&Comment>)> {
+    fn collect<'a>(things: &'a [CommentThing], parent: Option<&'a Comment>, out: &mut Vec<(&'a Comment, Option<&'a Comment>)>) {
+        for thing in things {
+            if let CommentThing::Comment(comment) = thing {
+                out.push((comment, parent));
+                collect(&comment.replies, Some(comment), out);
+            }
+        }
+    }
+
+    let mut all = Vec::new();
+    collect(roots, None, &mut all);
+    all.sort_by(|a, b| a.0.created_utc.total_cmp(&b.0.created_utc));
+    all
+}
+
+// Splice a freshly posted comment in as the first child of `parent` (a t3_ post or t1_ comment
+// fullname) so it shows up without refetching the thread. Returns false if the parent isn't loaded
+pub fn insert_reply(roots: &mut Vec<CommentThing>, parent: &str, reply: Comment) -> bool {
+    fn insert(things: &mut [CommentThing], parent: &str, reply: &mut Option<Comment>) -> bool {
+        for thing in things {
+            if let CommentThing::Comment(comment) = thing {
+                if comment.name == parent {
+                    if let Some(reply) = reply.take() {
+                        comment.replies.insert(0, CommentThing::Comment(reply));
+                    }
+                    return true;
+                }
+                if insert(&mut comment.replies, parent, reply) {
+                    return true;
+                }
+            }
+        }
+        false
+    }
+
+    if parent.starts_with("t3_") {
+        roots.insert(0, CommentThing::Comment(reply));
+        return true;
+    }
+    insert(roots, parent, &mut Some(reply))
+}
+
+// Puts what /api/morechildren sent for `more` where its row was, under the same parent, with a
+// new stub for any ids beyond the batch that was asked for. Returns false if the row is gone.
+pub fn expand_more(roots: &mut Vec<CommentThing>, more: &MoreComments, fetched: Vec<CommentThing>) -> bool {
+    let siblings = if more.parent_id.starts_with("t3_") {
+        Some(roots)
+    } else {
+        find_mut(roots, &more.parent_id).map(|parent| &mut parent.replies)
+    };
+    let Some(siblings) = siblings else {
+        return false;
+    };
+    let Some(index) = siblings.iter().position(|thing| matches!(thing, CommentThing::More(stub) if stub.name == more.name)) else {
+        return false;
+    };
+
+    let mut replacement = nest(fetched);
+    let rest = more.children.get(MORE_CHILDREN_BATCH..).unwrap_or_default();
+    if !rest.is_empty() {
+        let loaded = count_descendants(&replacement) as u32;
+        replacement.push(CommentThing::More(MoreComments {
+            count: more.count.saturating_sub(loaded).max(rest.len() as u32),
+            children: rest.to_vec(),
+            ..more.clone()
+        }));
+    }
+    siblings.splice(index..=index, replacement);
+    true
+}
+
+// Hangs each flat thing under its parent when that's among them; the rest are returned as siblings
+fn nest(flat: Vec<CommentThing>) -> Vec<CommentThing> {
+    let mut top: Vec<CommentThing> = Vec::new();
+    for thing in flat {
+        let parent = match &thing {
+            CommentThing::Comment(comment) => comment.parent_id.clone(),
+            CommentThing::More(more) => more.parent_id.clone(),
+        };
+        match find_mut(&mut top, &parent) {
+            Some(parent) => parent.replies.push(thing),
+            None => top.push(thing),
+        }
+    }
+    top
+}
+
+pub fn find_mut<'a>(things: &'a mut [CommentThing], name: &str) -> Option<&'a mut Comment> {
+    for thing in things {
+        if let CommentThing::Comment(comment) = thing {
+            if comment.name == name {
+                return Some(comment);
+            }
+            if let Some(found) = find_mut(&mut comment.replies, name) {
+                return Some(found);
+            }
+        }
+    }
+    None
+}
+
+// Reply being written in the comment view
+pub struct Draft {
+    pub parent: String,  // Fullname of the post or comment being replied to
+    pub text: String,
+    pub preview: bool,
+    pub submitting: bool,
+    pub error: Option<String>,
+    pub correction: Option<Range<usize>>,  // Right-clicked misspelling
+    pub mention_choice: Option<usize>,  // Highlighted name while u/ and r/ suggestions are showing
+}
+
+// A u/ or r/ reference being typed just before the cursor
+#[derive(Debug, PartialEq)]
+pub struct Mention {
+    pub user: bool,     // u/ rather than r/
+    pub start: usize,   // Char index where the reference starts
+    pub query: String,  // The name so far
+}
+
+impl Mention {
+    // e.g. "r/ru"
+    pub fn typed(&self) -> String {
+        format!("{}/{}", if self.user { "u" } else { "r" }, self.query)
+    }
+}
+
+// Markdown link to a user or subreddit, e.g. "[r/rust](https://www.reddit.com/r/rust)"
+pub fn mention_link(user: bool, name: &str) -> String {
+    if user {
+        format!("[u/{0}](https://www.reddit.com/user/{0})", name)
+    } else {
+        format!("[r/{0}](https://www.reddit.com/r/{0})", name)
+    }
+}
+
+impl Draft {
+    pub fn new(parent: String) -> Self {
+        Draft { parent, text: String::new(), preview: false, submitting: false, error: None, correction: None, mention_choice: None }
+    }
+
+    // Put `snippet` at the char index `cursor`, returning where the cursor ends up
+    pub fn insert(&mut self, cursor: usize, snippet: &str) -> usize {
+        let cursor = cursor.min(self.text.chars().count());
+        let at = self.text.char_indices().nth(cursor).map_or(self.text.len(), |(index, _)| index);
+        self.text.insert_str(at, snippet);
+        cursor + snippet.chars().count()
+    }
+
+    // Swap the byte range `range` for `replacement`, returning the char index just after it
+    pub fn replace(&mut self, range: Range<usize>, replacement: &str) -> usize {
+        self.text.replace_range(range.clone(), replacement);
+        self.text[..range.start].chars().count() + replacement.chars().count()
+    }
+
+    // The u/ or r/ reference, with or without a leading slash, that ends at `cursor`
+    pub fn mention(&self, cursor: usize) -> Option<Mention> {
+        let chars: Vec<char> = self.text.chars().collect();
+        if cursor > chars.len() {
+            return None;
+        }
+        let start = chars[..cursor].iter().rposition(|c| c.is_whitespace()).map_or(0, |index| index + 1);
+        let word: String = chars[start..cursor].iter().collect();
+        let word = word.strip_prefix('/').unwrap_or(&word);
+        let user = match word.get(..2)?.to_lowercase().as_str() {
+            "u/" => true,
+            "r/" => false,
+            _ => return None,
+        };
+        let query = &word[2..];
+        query.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '-')
+            .then(|| Mention { user, start, query: query.to_string() })
+    }
+
+    // Swap the reference being typed for a link to `name`, returning where the cursor ends up
+    pub fn complete_mention(&mut self, mention: &Mention, cursor: usize, name: &str) -> usize {
+        let at = |index: usize| self.text.char_indices().nth(index).map_or(self.text.len(), |(at, _)| at);
+        let range = at(mention.start)..at(cursor);
+        self.replace(range, &format!("{} ", mention_link(mention.user, name)))
+    }
+
+    // Swap a "/name " just typed before `cursor` for the snippet `lookup` finds under that
+    // name, returning where the cursor ends up
+    pub fn expand_snippet<'a>(&mut self, cursor: usize, lookup: impl Fn(&str) -> Option<&'a str>) -> Option<usize> {
+        let chars: Vec<char> = self.text.chars().collect();
+        if cursor == 0 || cursor > chars.len() || chars[cursor - 1] != ' ' {
+            return None;
+        }
+        let start = chars[..cursor - 1].iter().rposition(|c| c.is_whitespace()).map_or(0, |index| index + 1);
+        let word: String = chars[start..cursor - 1].iter().collect();
+        let snippet = lookup(word.strip_prefix('/').filter(|name| !name.is_empty())?)?;
+        self.text = chars[..start].iter().chain(snippet.chars().collect::<Vec<_>>().iter()).chain(&chars[cursor..]).collect();
+        Some(start + snippet.chars().count())
+    }
+}
+
+// Offset of the neighbouring top-level comment when stepping from `current`, if there is one
+pub fn jump_target(root_offsets: &[f32], current: f32, forward: bool) -> Option<f32> {
+    // Small tolerance so landing exactly on a comment counts as being at it
+    const EPSILON: f32 = 1.0;
+    if forward {
+        root_offsets.iter().copied().find(|&y| y > current + EPSILON)
+    } else {
+        root_offsets.iter().copied().rev().find(|&y| y < current - EPSILON)
+    }
+}
+
+// Minimap shading: how many comments start in each of `bands` equal slices of the content
+pub fn density(comment_offsets: &[f32], content_height: f32, bands: usize) -> Vec<usize> {
+    let mut counts = vec![0; bands];
+    if bands == 0 || content_height <= 0.0 {
+        return counts;
+    }
+    for &y in comment_offsets {
+        let band = (y / content_height * bands as f32) as usize;
+        counts[band.min(bands - 1)] += 1;
+    }
+    counts
+}
+
+// Scroll offset that centres the viewport on the point `fraction` of the way down the content
+pub fn minimap_offset(fraction: f32, content_height: f32, viewport_height: f32) -> f32 {
+    let max = (content_height - viewport_height).max(0.0);
+    (fraction.clamp(0.0, 1.0) * content_height - viewport_height / 2.0).clamp(0.0, max)
+}
+
+// One line of the flattened, visible part of the tree
+pub enum Row<'a> {
+    Comment { comment: &'a Comment, depth: usize },
+    // Children of `parent` beyond the current page
+    ShowMore { parent: String, hidden: usize, depth: usize },
+    // Subtree cut off by the depth limit
+    ContinueThread { id: String, hidden: usize, depth: usize },
+    // Children Reddit left out of the response
+    NotLoaded { more: &'a MoreComments, depth: usize },
+    // Reader mode: every comment in posting order, with its parent kept for quoting
+    Linear { comment: &'a Comment, parent: Option<&'a Comment> },
+}
+
+impl Row<'_> {
+    // Stable identity used to remember measured row heights between frames
+    pub fn key(&self) -> String {
+        match self {
+            Row::Comment { comment, .. } => comment.id.clone(),
+            Row::ShowMore { parent, .. } => format!("show-more:{}", parent),
+            Row::ContinueThread { id, .. } => format!("continue:{}", id),
+            Row::NotLoaded { more, .. } => format!("more:{}", more.id),
+            Row::Linear { comment, .. } => format!("linear:{}", comment.id),
+        }
+    }
+}
+
+// Expand/collapse requests collected while drawing and applied afterwards
+pub enum TreeAction {
+    ToggleCollapsed(String),
+    ShowMore(String),
+    ContinueThread(String),
+    ToggleQuote(String),
+    LoadMore(MoreComments),  // Fetched by the caller; the row shows a spinner meanwhile
+}
+
+#[derive(Default)]
+pub struct TreeView {
+    shown_children: HashMap<String, usize>,  // Parent key -> how many children are visible
+    continued: HashSet<String>,              // Comments opened past the depth limit
+    collapsed: HashSet<String>,
+    pub row_heights: HashMap<String, f32>,   // Measured last time each row was on screen
+    pub scroll_to: Option<f32>,              // Offset to jump to on the next frame
+    pub linear: bool,                        // Flat, chronological reader mode
+    quoted: HashSet<String>,                 // Reader-mode comments showing their parent inline
+    loading_more: HashSet<String>,           // "Load more" stubs being fetched, by fullname
+}
+
+impl TreeView {
+    pub fn apply(&mut self, action: TreeAction) {
+        match action {
+            TreeAction::ToggleCollapsed(id) => self.toggle_collapsed(&id),
+            TreeAction::ShowMore(parent) => self.show_more(&parent),
+            TreeAction::ContinueThread(id) => self.continue_thread(&id),
+            TreeAction::ToggleQuote(id) => {
+                if !self.quoted.remove(&id) {
+                    self.quoted.insert(id);
+                }
+            }
+            TreeAction::LoadMore(more) => {
+                self.loading_more.insert(more.name);
+            }
+        }
+    }
+
+    pub fn is_loading_more(&self, name: &str) -> bool {
+        self.loading_more.contains(name)
+    }
+
+    pub fn finish_loading_more(&mut self, name: &str) {
+        self.loading_more.remove(name);
+    }
+
+    pub fn show_more(&mut self, parent: &str) {
+        *self.shown_children.entry(parent.to_string()).or_insert(CHILDREN_PAGE) += CHILDREN_PAGE;
+    }
+
+    pub fn continue_thread(&mut self, id: &str) {
+        self.continued.insert(id.to_string());
+    }
+
+    pub fn toggle_collapsed(&mut self, id: &str) {
+        if !self.collapsed.remove(id) {
+            self.collapsed.insert(id.to_string());
+        }
+    }
+
+    pub fn is_collapsed(&self, id: &str) -> bool {
+        self.collapsed.contains(id)
+    }
+
+    pub fn is_quoted(&self, id: &str) -> bool {
+        self.quoted.contains(id)
+    }
+
+    pub fn rows<'a>(&self, roots: &'a [CommentThing]) -> Vec<Row<'a>> {
+        if self.linear {
+            return chronological(roots).into_iter()
+                .map(|(comment, parent)| Row::Linear { comment, parent })
+                .collect();
+        }
+
+        let mut rows = Vec::new();
+        self.push_children(&mut rows, ROOT_KEY, roots, 0, MAX_VISIBLE_DEPTH);
+        rows
+    }
+
+    fn push_children<'a>(&self, rows: &mut Vec<Row<'a>>, parent: &str, children: &'a [CommentThing], depth: usize, levels_left: usize) {
+        let limit = self.shown_children.get(parent).copied().unwrap_or(CHILDREN_PAGE);
+        for thing in children.iter().take(limit) {
+            match thing {
+                CommentThing::Comment(comment) => {
+                    rows.push(Row::Comment { comment, depth });
+                    if comment.replies.is_empty() || self.is_collapsed(&comment.id) {
+                        continue;
+                    }
+                    if levels_left > 1 {
+                        self.push_children(rows, &comment.id, &comment.replies, depth + 1, levels_left - 1);
+                    } else if self.continued.contains(&comment.id) {
+                        self.push_children(rows, &comment.id, &comment.replies, depth + 1, MAX_VISIBLE_DEPTH);
+                    } else {
+                        rows.push(Row::ContinueThread {
+                            id: comment.id.clone(),
+                            hidden: count_descendants(&comment.replies),
+                            depth: depth + 1,
+                        });
+                    }
+                }
+                CommentThing::More(more) => {
+                    if more.count > 0 {
+                        rows.push(Row::NotLoaded { more, depth });
+                    }
+                }
+            }
+        }
+        if children.len() > limit {
+            rows.push(Row::ShowMore {
+                parent: parent.to_string(),
+                hidden: children.len() - limit,
+                depth,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn comment(id: &str, replies: Vec<CommentThing>) -> CommentThing {
+        CommentThing::Comment(Comment { id: id.to_string(), name: format!("t1_{}", id), replies, ..Default::default() })
+    }
+
+    // A single chain of `length` comments, each replying to the previous one
+    fn chain(length: usize) -> Vec<CommentThing> {
+        (0..length).rev().fold(Vec::new(), |replies, i| vec![comment(&format!("c{}", i), replies)])
+    }
+
+    fn keys(rows: &[Row]) -> Vec<String> {
+        rows.iter().map(|row| row.key()).collect()
+    }
+
+    #[test]
+    fn parses_replies_as_listing_or_empty_string() {
+        let json = r#"{"kind": "t1", "data": {"id": "a", "body": "hi", "replies": {
+            "kind": "Listing", "data": {"children": [
+                {"kind": "t1", "data": {"id": "b", "body": "leaf", "replies": ""}},
+                {"kind": "more", "data": {"id": "m", "count": 4, "children": ["x", "y"]}}
+            ]}
+        }}}"#;
+        let thing: CommentThing = serde_json::from_str(json).unwrap();
+        let CommentThing::Comment(root) = thing else { panic!("expected a comment") };
+        assert_eq!(root.replies.len(), 2);
+        assert_eq!(count_descendants(&root.replies), 5);
+    }
+
+    #[test]
+    fn limits_children_per_node() {
+        let roots: Vec<_> = (0..25).map(|i| comment(&format!("c{}", i), Vec::new())).collect();
+        let mut view = TreeView::default();
+        let rows = view.rows(&roots);
+        assert_eq!(rows.len(), CHILDREN_PAGE + 1);
+        assert!(matches!(rows.last(), Some(Row::ShowMore { hidden: 15, .. })));
+
+        view.show_more(ROOT_KEY);
+        view.show_more(ROOT_KEY);
+        assert_eq!(view.rows(&roots).len(), 25);
+    }
+
+    #[test]
+    fn limits_depth_until_thread_is_continued() {
+        let roots = chain(10);
+        let mut view = TreeView::default();
+        let rows = view.rows(&roots);
+        assert_eq!(rows.len(), MAX_VISIBLE_DEPTH + 1);
+        assert_eq!(rows.last().unwrap().key(), format!("continue:c{}", MAX_VISIBLE_DEPTH - 1));
+
+        view.continue_thread(&format!("c{}", MAX_VISIBLE_DEPTH - 1));
+        assert_eq!(keys(&view.rows(&roots)).len(), 10);
+    }
+
+    #[test]
+    fn jumps_between_top_level_offsets() {
+        let offsets = [0.0, 120.0, 480.0];
+        assert_eq!(jump_target(&offsets, 0.0, true), Some(120.0));
+        assert_eq!(jump_target(&offsets, 200.0, true), Some(480.0));
+        assert_eq!(jump_target(&offsets, 480.0, true), None);
+        assert_eq!(jump_target(&offsets, 480.0, false), Some(120.0));
+        assert_eq!(jump_target(&offsets, 200.0, false), Some(120.0));
+        assert_eq!(jump_target(&offsets, 0.0, false), None);
+    }
+
+    #[test]
+    fn maps_minimap_bands_and_clicks() {
+        assert_eq!(density(&[0.0, 10.0, 120.0, 390.0, 400.0], 400.0, 4), vec![2, 1, 0, 2]);
+        assert_eq!(density(&[10.0], 0.0, 4), vec![0; 4]);
+        assert_eq!(minimap_offset(0.5, 1000.0, 200.0), 400.0);
+        assert_eq!(minimap_offset(0.0, 1000.0, 200.0), 0.0);
+        assert_eq!(minimap_offset(1.0, 1000.0, 200.0), 800.0);
+        assert_eq!(minimap_offset(0.5, 100.0, 200.0), 0.0);
+    }
+
+    #[test]
+    fn linear_mode_orders_by_time_and_keeps_parents() {
+        let mut late_reply = Comment { id: "reply".into(), created_utc: 30.0, ..Default::default() };
+        late_reply.replies = Vec::new();
+        let root_a = Comment { id: "a".into(), created_utc: 10.0, replies: vec![CommentThing::Comment(late_reply)], ..Default::default() };
+        let root_b = Comment { id: "b".into(), created_utc: 20.0, ..Default::default() };
+        let roots = vec![CommentThing::Comment(root_a), CommentThing::Comment(root_b)];
+
+        let ordered = chronological(&roots);
+        let ids: Vec<_> = ordered.iter().map(|(comment, _)| comment.id.as_str()).collect();
+        assert_eq!(ids, vec!["a", "b", "reply"]);
+        assert_eq!(ordered[2].1.map(|parent| parent.id.as_str()), Some("a"));
+
+        let view = TreeView { linear: true, ..Default::default() };
+        assert_eq!(keys(&view.rows(&roots)), vec!["linear:a", "linear:b", "linear:reply"]);
+    }
+
+    #[test]
+    fn inserts_replies_under_post_or_comment() {
+        let mut roots = chain(3);
+        let reply = |id: &str| Comment { id: id.into(), name: format!("t1_{}", id), ..Default::default() };
+
+        assert!(insert_reply(&mut roots, "t1_c1", reply("new")));
+        assert!(insert_reply(&mut roots, "t3_post", reply("top")));
+        assert!(!insert_reply(&mut roots, "t1_missing", reply("lost")));
+
+        let view = TreeView::default();
+        assert_eq!(keys(&view.rows(&roots)), ["top", "c0", "c1", "new", "c2"]);
+    }
+
+    #[test]
+    fn inserts_and_expands_snippets() {
+        let mut draft = Draft::new("t3_a".to_string());
+        draft.text = "Thanks! /sig more".to_string();
+        let lookup = |name: &str| (name == "sig").then_some("— Ferris");
+        assert_eq!(draft.expand_snippet(8, lookup), None);  // No space typed after it yet
+        assert_eq!(draft.expand_snippet(13, lookup), Some(16));
+        assert_eq!(draft.text, "Thanks! — Ferrismore");
+
+        draft.text = "/nope ".to_string();
+        assert_eq!(draft.expand_snippet(6, lookup), None);
+        assert_eq!(draft.insert(0, "é "), 2);
+        assert_eq!(draft.text, "é /nope ");
+        assert_eq!(draft.insert(100, "!"), 9);
+        assert_eq!(draft.text, "é /nope !");
+    }
+
+    #[test]
+    fn completes_mentions() {
+        let mut draft = Draft::new("t3_a".to_string());
+        draft.text = "ask /u/fer or r/ru".to_string();
+        assert_eq!(draft.mention(10), Some(Mention { user: true, start: 4, query: "fer".to_string() }));
+        assert_eq!(draft.mention(3), None);
+        assert_eq!(draft.mention(18).map(|mention| mention.typed()).as_deref(), Some("r/ru"));
+
+        let mention = draft.mention(18).unwrap();
+        let cursor = draft.complete_mention(&mention, 18, "rust");
+        assert_eq!(draft.text, "ask /u/fer or [r/rust](https://www.reddit.com/r/rust) ");
+        assert_eq!(cursor, draft.text.chars().count());
+    }
+
+    #[test]
+    fn collapsed_comments_hide_their_subtree() {
+        let roots = chain(3);
+        let mut view = TreeView::default();
+        view.toggle_collapsed("c1");
+        assert_eq!(keys(&view.rows(&roots)), vec!["c0", "c1"]);
+        view.toggle_collapsed("c1");
+        assert_eq!(view.rows(&roots).len(), 3);
+    }
+    #[test]
+    fn splices_loaded_replies_in_place_of_their_stub() {
+        let ids: Vec<String> = (0..MORE_CHILDREN_BATCH + 1).map(|i| format!("k{}", i)).collect();
+        let more = MoreComments { id: "m".to_string(), name: "t1_m".to_string(), parent_id: "t1_a".to_string(), count: 150, depth: 1, children: ids };
+        let mut roots = vec![comment("a", vec![comment("b", vec![]), CommentThing::More(more.clone())])];
+        let response: MoreChildrenResponse = serde_json::from_str(r#"{"json": {"errors": [], "data": {"things": [
+            {"kind": "t1", "data": {"id": "x", "name": "t1_x", "parent_id": "t1_a", "replies": ""}},
+            {"kind": "t1", "data": {"id": "y", "name": "t1_y", "parent_id": "t1_x", "replies": ""}}
+        ]}}}"#).unwrap();
+        assert!(expand_more(&mut roots, &more, response.into_things().unwrap()));
+
+        let CommentThing::Comment(a) = &roots[0] else { panic!() };
+        assert_eq!(keys(&TreeView::default().rows(&a.replies)), ["b", "x", "y", "more:m"]);
+        let CommentThing::More(rest) = &a.replies[2] else { panic!() };
+        assert_eq!((rest.count, rest.children.as_slice()), (148, [format!("k{}", MORE_CHILDREN_BATCH)].as_slice()));
+        // The last batch leaves no stub behind
+        let rest = rest.clone();
+        assert!(expand_more(&mut roots, &rest, Vec::new()));
+        let CommentThing::Comment(a) = &roots[0] else { panic!() };
+        assert_eq!(a.replies.len(), 2);
+        assert!(!expand_more(&mut roots, &rest, Vec::new()));
+
+        let rejected: MoreChildrenResponse = serde_json::from_str(r#"{"json": {"errors": [["TOO_LONG", "too many ids", "children"]]}}"#).unwrap();
+        assert_eq!(rejected.into_things().unwrap_err().to_string(), "Reddit couldn't load the replies: too many ids");
+    }
+}
@@ -0,0 +1,63 @@
+// Bundled sample content for trying the UI before any Reddit credentials are set up.
+//
+// The sample is a handful of posts across a few subreddits plus comment threads for some of
+// them, in the same shape the API returns, so it goes through the normal rendering paths.
+use crate::{comments::CommentThing, Post};
+use serde::Deserialize;
+use std::collections::HashMap;
+
+const SAMPLE: &str = include_str!("../assets/demo.json");
+
+#[derive(Deserialize)]
+struct Sample {
+    posts: Vec<Post>,
+    comments: HashMap<String, Vec<CommentThing>>,  // Keyed by post id
+}
+
+fn sample() -> Sample {
+    serde_json::from_str(SAMPLE).expect("bundled demo data is valid")
+}
+
+// "home" is every sample post, anything else just that subreddit's
+pub fn posts(feed: &str) -> Vec<Post> {
+    sample().posts.into_iter()
+        .filter(|post| feed == "home" || post.subreddit.eq_ignore_ascii_case(feed))
+        .collect()
+}
+
+pub fn subreddits() -> Vec<String> {
+    let mut subreddits: Vec<String> = Vec::new();
+    for post in sample().posts {
+        if !subreddits.iter().any(|name| name.eq_ignore_ascii_case(&post.subreddit)) {
+            subreddits.push(post.subreddit);
+        }
+    }
+    subreddits
+}
+
+pub fn comments(post_id: &str) -> Vec<CommentThing> {
+    sample().comments.remove(post_id).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bundled_sample_parses() {
+        let home = posts("home");
+        assert!(home.len() > 4);
+        assert!(posts("RUST").iter().all(|post| post.subreddit == "rust"));
+        assert!(subreddits().contains(&"science".to_string()));
+
+        // Every comment thread belongs to a sample post
+        for id in sample().comments.keys() {
+            assert!(home.iter().any(|post| &post.id == id), "no post for comments on {}", id);
+        }
+        let thread = comments("demo01");
+        let [CommentThing::Comment(first), ..] = thread.as_slice() else {
+            panic!("expected comments on demo01");
+        };
+        assert_eq!(first.replies.len(), 1);
+    }
+}
@@ -0,0 +1,281 @@
+// Digest of the best posts from the reader's subscriptions over the last day or week.
+//
+// A background job (`RedditApp::generate_digest`) asks each subscribed subreddit for its top
+// posts of the period, one request at a time. When the rate limit budget gets down to what
+// browsing needs, the job waits for the window to reset rather than competing with the feed.
+// The result is read in its own window and can be exported to Markdown or HTML.
+use crate::Post;
+use eframe::egui;
+use std::time::Instant;
+
+pub const PER_SUBREDDIT: usize = 5;  // Posts kept from each subreddit
+pub const RESERVE: u32 = 100;        // Requests left to browsing; below this the job waits for the reset
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Period {
+    #[default]
+    Day,
+    Week,
+}
+
+impl Period {
+    pub const ALL: [Period; 2] = [Period::Day, Period::Week];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Period::Day => "Last day",
+            Period::Week => "Last week",
+        }
+    }
+
+    // The `t` parameter of top listings
+    pub fn api_name(self) -> &'static str {
+        match self {
+            Period::Day => "day",
+            Period::Week => "week",
+        }
+    }
+
+    fn title(self) -> &'static str {
+        match self {
+            Period::Day => "Daily digest",
+            Period::Week => "Weekly digest",
+        }
+    }
+
+    fn seconds(self) -> i64 {
+        match self {
+            Period::Day => 86_400,
+            Period::Week => 7 * 86_400,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    Markdown,
+    Html,
+}
+
+impl Format {
+    pub fn extension(self) -> &'static str {
+        match self {
+            Format::Markdown => "md",
+            Format::Html => "html",
+        }
+    }
+}
+
+pub struct Section {
+    pub subreddit: String,
+    pub posts: Vec<Post>,  // Highest score first
+}
+
+pub struct Digest {
+    pub period: Period,
+    pub generated_at: i64,
+    pub sections: Vec<Section>,  // Ordered by each subreddit's best post
+    pub skipped: Vec<String>,    // Subreddits whose listing couldn't be fetched
+}
+
+impl Digest {
+    // `fetched` holds each subreddit's top listing for the period
+    pub fn new(period: Period, generated_at: i64, fetched: Vec<(String, Vec<Post>)>, skipped: Vec<String>) -> Self {
+        let cutoff = (generated_at - period.seconds()) as f64;
+        let mut sections: Vec<Section> = fetched.into_iter()
+            .filter_map(|(subreddit, posts)| {
+                // Announcements pinned for months aren't news
+                let mut posts: Vec<Post> = posts.into_iter()
+                    .filter(|post| post.created_utc >= cutoff && !post.stickied)
+                    .collect();
+                posts.sort_by_key(|post| std::cmp::Reverse(post.score));
+                posts.truncate(PER_SUBREDDIT);
+                (!posts.is_empty()).then_some(Section { subreddit, posts })
+            })
+            .collect();
+        sections.sort_by_key(|section| std::cmp::Reverse(section.posts[0].score));
+        Digest { period, generated_at, sections, skipped }
+    }
+
+    pub fn file_name(&self, format: Format) -> String {
+        format!("digest-{}-{}.{}", self.period.api_name(), self.generated_at, format.extension())
+    }
+
+    fn subtitle(&self) -> String {
+        format!("Top posts from {} subreddits, {}", self.sections.len(), crate::profile::cake_day(self.generated_at as f64))
+    }
+
+    pub fn render(&self, format: Format) -> String {
+        match format {
+            Format::Markdown => self.to_markdown(),
+            Format::Html => self.to_html(),
+        }
+    }
+
+    fn to_markdown(&self) -> String {
+        let mut text = format!("# {}\n\n{}\n", self.period.title(), self.subtitle());
+        for section in &self.sections {
+            text.push_str(&format!("\n## r/{}\n\n", section.subreddit));
+            for post in &section.posts {
+                let title = post.title.replace('[', "\\[").replace(']', "\\]");
+                text.push_str(&format!("- [{}]({}) — {} points, {} comments\n", title, post.link(), post.score, post.num_comments));
+            }
+        }
+        text
+    }
+
+    fn to_html(&self) -> String {
+        let mut html = format!(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{title}</title>\n</head>\n<body>\n<h1>{title}</h1>\n<p>{}</p>\n",
+            escape(&self.subtitle()),
+            title = self.period.title(),
+        );
+        for section in &self.sections {
+            html.push_str(&format!("<h2>r/{}</h2>\n<ul>\n", escape(&section.subreddit)));
+            for post in &section.posts {
+                html.push_str(&format!("<li><a href=\"{}\">{}</a> — {} points, {} comments</li>\n",
+                    escape(&post.link()), escape(&post.title), post.score, post.num_comments));
+            }
+            html.push_str("</ul>\n");
+        }
+        html.push_str("</body>\n</html>\n");
+        html
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+// How far the background job has got
+#[derive(Default)]
+pub struct Progress {
+    pub done: usize,
+    pub total: usize,
+    pub resumes_at: Option<Instant>,  // Waiting out the rate limit window until then
+    pub cancelled: bool,
+}
+
+pub enum Request {
+    Generate(Period),
+    Cancel,
+    Export(Format),
+}
+
+// Window showing the latest digest, with controls to make a new one
+#[derive(Default)]
+pub struct Viewer {
+    period: Period,
+}
+
+impl Viewer {
+    // False once the window is closed
+    pub fn show(&mut self, ctx: &egui::Context, digest: Option<&Digest>, progress: Option<&Progress>, subscriptions: usize, requests: &mut Vec<Request>) -> bool {
+        let mut open = true;
+        egui::Window::new("Digest")
+            .open(&mut open)
+            .default_size([560.0, 480.0])
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    for period in Period::ALL {
+                        ui.selectable_value(&mut self.period, period, period.label());
+                    }
+                    ui.add_space(8.0);
+                    match progress {
+                        Some(progress) => {
+                            ui.spinner();
+                            let status = match progress.resumes_at {
+                                Some(at) => format!("Paused for the rate limit, resuming in {}s", at.saturating_duration_since(Instant::now()).as_secs()),
+                                None => format!("{} of {} subreddits", progress.done, progress.total),
+                            };
+                            ui.label(egui::RichText::new(status).weak());
+                            if ui.button("Cancel").clicked() {
+                                requests.push(Request::Cancel);
+                            }
+                        }
+                        None => {
+                            let button = ui.add_enabled(subscriptions > 0, egui::Button::new("Generate"));
+                            if button.on_disabled_hover_text("No subscriptions loaded yet").clicked() {
+                                requests.push(Request::Generate(self.period));
+                            }
+                        }
+                    }
+                });
+                ui.separator();
+
+                let Some(digest) = digest else {
+                    ui.label(egui::RichText::new(format!("Collects the top posts from your {} subscriptions", subscriptions)).weak());
+                    return;
+                };
+                ui.horizontal(|ui| {
+                    ui.label(egui::RichText::new(format!("{}: {}", digest.period.title(), digest.subtitle())).strong());
+                    if ui.small_button("Export Markdown").clicked() {
+                        requests.push(Request::Export(Format::Markdown));
+                    }
+                    if ui.small_button("Export HTML").clicked() {
+                        requests.push(Request::Export(Format::Html));
+                    }
+                });
+                if !digest.skipped.is_empty() {
+                    ui.label(egui::RichText::new(format!("Couldn't load {} subreddits", digest.skipped.len())).weak())
+                        .on_hover_text(digest.skipped.join(", "));
+                }
+                egui::ScrollArea::vertical().auto_shrink([false, false]).show(ui, |ui| {
+                    for section in &digest.sections {
+                        ui.add_space(6.0);
+                        ui.label(egui::RichText::new(format!("r/{}", section.subreddit)).strong());
+                        for post in &section.posts {
+                            ui.horizontal(|ui| {
+                                ui.add_sized([56.0, 18.0], egui::Label::new(egui::RichText::new(post.score.to_string()).weak()));
+                                ui.hyperlink_to(&post.title, post.link())
+                                    .on_hover_text(format!("{} comments", post.num_comments));
+                            });
+                        }
+                    }
+                });
+            });
+        // Keep the progress and the rate limit countdown current while the window is up
+        if progress.is_some() {
+            ctx.request_repaint_after(std::time::Duration::from_secs(1));
+        }
+        open
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn post(title: &str, score: i32, created_utc: f64) -> Post {
+        serde_json::from_value(serde_json::json!({
+            "title": title,
+            "score": score,
+            "created_utc": created_utc,
+            "permalink": format!("/r/test/comments/{}/", score),
+        })).unwrap()
+    }
+
+    #[test]
+    fn keeps_each_subreddits_best_recent_posts() {
+        let now = 10 * 86_400;
+        let mut rust: Vec<Post> = (1..=8).map(|score| post("Rust post", score, (now - 3600) as f64)).collect();
+        rust.push(post("Last month", 500, (now - 30 * 86_400) as f64));
+        let fetched = vec![
+            ("rust".to_string(), rust),
+            ("pics".to_string(), vec![post("A <b>cat</b> & [dog]", 50, (now - 60) as f64)]),
+            ("quiet".to_string(), Vec::new()),
+        ];
+        let digest = Digest::new(Period::Day, now, fetched, Vec::new());
+        let subreddits: Vec<&str> = digest.sections.iter().map(|section| section.subreddit.as_str()).collect();
+        assert_eq!(subreddits, ["pics", "rust"]);
+        let scores: Vec<i32> = digest.sections[1].posts.iter().map(|post| post.score).collect();
+        assert_eq!(scores, [8, 7, 6, 5, 4]);
+
+        let markdown = digest.render(Format::Markdown);
+        assert!(markdown.starts_with("# Daily digest\n"));
+        assert!(markdown.contains("## r/pics\n\n- [A <b>cat</b> & \\[dog\\]](https://www.reddit.com/r/test/comments/50/) — 50 points, 0 comments\n"));
+        let html = digest.render(Format::Html);
+        assert!(html.contains("<li><a href=\"https://www.reddit.com/r/test/comments/50/\">A &lt;b&gt;cat&lt;/b&gt; &amp; [dog]</a>"));
+        assert_eq!(digest.file_name(Format::Html), format!("digest-day-{}.html", now));
+    }
+}
@@ -0,0 +1,100 @@
+// Discover screen: directories of subreddits to join, for building a home feed inside Rustle.
+//
+// Popular and new come from the paged /subreddits/{where} listings. Trending is a short list of
+// names from /api/trending_subreddits, looked up through /api/info so every tab gets the same cards.
+use crate::subreddit::SubredditAbout;
+use serde::Deserialize;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Source {
+    #[default]
+    Trending,
+    Popular,
+    New,
+}
+
+impl Source {
+    pub const ALL: [Source; 3] = [Source::Trending, Source::Popular, Source::New];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Source::Trending => "Trending",
+            Source::Popular => "Popular",
+            Source::New => "New",
+        }
+    }
+
+    // The directory listing under /subreddits; trending isn't one
+    pub fn directory(self) -> Option<&'static str> {
+        match self {
+            Source::Trending => None,
+            Source::Popular => Some("popular"),
+            Source::New => Some("new"),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Listing {
+    pub data: ListingData,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ListingData {
+    pub children: Vec<ListingChild>,
+    #[serde(default)]
+    pub after: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ListingChild {
+    pub data: SubredditAbout,
+}
+
+impl Listing {
+    pub fn into_page(self) -> (Vec<SubredditAbout>, Option<String>) {
+        (self.data.children.into_iter().map(|child| child.data).collect(), self.data.after)
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Trending {
+    pub subreddit_names: Vec<String>,
+}
+
+// Screen state; `after` is None once the directory has been read to the end
+#[derive(Clone)]
+pub struct Discover {
+    pub source: Source,
+    pub subreddits: Vec<SubredditAbout>,
+    pub after: Option<String>,
+    pub loading: bool,
+}
+
+impl Discover {
+    pub fn new(source: Source) -> Self {
+        Discover { source, subreddits: Vec::new(), after: None, loading: true }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_directory_and_trending() {
+        let listing: Listing = serde_json::from_str(r#"{"kind": "Listing", "data": {"after": "t5_2", "children": [
+            {"kind": "t5", "data": {"display_name": "rust", "title": "Rust", "subscribers": 345678,
+                "public_description": "A place for all things & Rust", "over18": false,
+                "community_icon": "https://styles.redditmedia.com/icon.png?s=1&x=2"}}
+        ]}}"#).unwrap();
+        let (subreddits, after) = listing.into_page();
+        assert_eq!(after.as_deref(), Some("t5_2"));
+        assert_eq!(subreddits[0].public_description, "A place for all things & Rust");
+        assert_eq!(subreddits[0].icon_url(), Some("https://styles.redditmedia.com/icon.png?s=1&x=2"));
+
+        let trending: Trending = serde_json::from_str(r#"{"subreddit_names": ["rust", "pics"], "comment_count": 12}"#).unwrap();
+        assert_eq!(trending.subreddit_names, ["rust", "pics"]);
+    }
+}
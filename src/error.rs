@@ -0,0 +1,171 @@
+// Failures shown to the reader, sorted by what they can do about them.
+//
+// Worker threads report through `RedditApp::error_message`. Keeping the kind rather than a
+// flattened string lets the UI offer the right way out: sign-in problems get their own screen
+// with a shortcut to the credentials, dropped connections a retry button, rate limiting a
+// countdown until Reddit will take requests again, and refused subreddits an explanation.
+use crate::subreddit::Restriction;
+use reqwest::{header::HeaderMap, StatusCode};
+use std::{
+    fmt,
+    time::{Duration, Instant},
+};
+
+// Wait used when a 429 doesn't say how long to back off
+const DEFAULT_RETRY_AFTER: Duration = Duration::from_secs(60);
+// Longest wait taken from a header, in seconds; anything beyond is a garbled value
+const LONGEST_RETRY_AFTER: f64 = 3600.0;
+
+#[derive(Debug, Clone)]
+pub enum RustleError {
+    Auth { failure: AuthFailure, message: String },  // Reddit turned down the credentials or ended the session
+    RateLimited { retry_after: Instant },
+    Network(String),  // Reddit couldn't be reached at all
+    Parse(String),    // Reddit answered with something we couldn't read
+    Api { code: u16, message: String },  // Any other failed response
+    Other(String),    // Local trouble: settings, video playback and the like
+    Stalled(String),  // A load that never finished, given up on by the watchdog
+    Restricted { subreddit: String, restriction: Restriction },  // Quarantined, private or banned
+}
+
+// What Reddit said when it turned the sign-in down, so the recovery screen can give the right advice
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AuthFailure {
+    Grant,    // invalid_grant: a wrong password, or a revoked refresh token
+    App,      // The client ID or secret was refused
+    Ended,    // A token that had been working stopped being accepted
+    Unknown,
+}
+
+impl RustleError {
+    // A failed response from an endpoint, e.g. "Failed to fetch comments: 404 Not Found"
+    pub fn api(status: StatusCode, action: impl fmt::Display) -> Self {
+        RustleError::Api { code: status.as_u16(), message: format!("{}: {}", action, status) }
+    }
+
+    // From a 429, honouring Retry-After or else the rate limit window's reset
+    pub fn rate_limited(headers: &HeaderMap) -> Self {
+        let header = |name: &str| headers.get(name)?.to_str().ok()?.trim().parse::<f64>().ok().filter(|seconds| seconds.is_finite());
+        let wait = header("retry-after").or_else(|| header("x-ratelimit-reset"))
+            .map_or(DEFAULT_RETRY_AFTER, |seconds| Duration::from_secs_f64(seconds.clamp(1.0, LONGEST_RETRY_AFTER)));
+        RustleError::RateLimited { retry_after: Instant::now() + wait }
+    }
+
+    // Work out the kind from whatever ended up in the error chain
+    pub fn classify(error: &anyhow::Error) -> Self {
+        let message = format!("{:#}", error);
+        if let Some(known) = error.chain().find_map(|cause| cause.downcast_ref::<RustleError>()) {
+            return match known {
+                RustleError::RateLimited { .. } | RustleError::Restricted { .. } => known.clone(),
+                RustleError::Auth { failure, .. } => RustleError::Auth { failure: *failure, message },
+                RustleError::Network(_) => RustleError::Network(message),
+                RustleError::Parse(_) => RustleError::Parse(message),
+                RustleError::Api { code, .. } => RustleError::Api { code: *code, message },
+                RustleError::Other(_) => RustleError::Other(message),
+                RustleError::Stalled(_) => RustleError::Stalled(message),
+            };
+        }
+        for cause in error.chain() {
+            if let Some(error) = cause.downcast_ref::<reqwest::Error>() {
+                if error.is_connect() || error.is_timeout() || error.is_request() {
+                    return RustleError::Network(message);
+                }
+                if error.is_decode() {
+                    return RustleError::Parse(message);
+                }
+            }
+            if cause.is::<serde_json::Error>() {
+                return RustleError::Parse(message);
+            }
+        }
+        RustleError::Other(message)
+    }
+
+    // Prefix the message with what was being attempted, e.g. "Error fetching posts"
+    pub fn context(self, action: &str) -> Self {
+        let prefixed = |message: String| format!("{}: {}", action, message);
+        match self {
+            RustleError::Network(message) => RustleError::Network(prefixed(message)),
+            RustleError::Parse(message) => RustleError::Parse(prefixed(message)),
+            RustleError::Api { code, message } => RustleError::Api { code, message: prefixed(message) },
+            RustleError::Other(message) => RustleError::Other(prefixed(message)),
+            RustleError::Stalled(message) => RustleError::Stalled(prefixed(message)),
+            // Sign-in failures and refused subreddits have their own screens, and rate limits their own wording
+            error @ (RustleError::Auth { .. } | RustleError::RateLimited { .. } | RustleError::Restricted { .. }) => error,
+        }
+    }
+
+    // Whether trying the same thing again could help
+    pub fn retryable(&self) -> bool {
+        match self {
+            RustleError::Network(_) | RustleError::Parse(_) | RustleError::Stalled(_) => true,
+            RustleError::Api { code, .. } => *code == 408 || *code >= 500,
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for RustleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RustleError::RateLimited { .. } => write!(f, "Reddit is limiting how often Rustle can make requests"),
+            RustleError::Restricted { subreddit, restriction } => write!(f, "{}", restriction.describe(subreddit)),
+            RustleError::Auth { message, .. }
+            | RustleError::Network(message)
+            | RustleError::Parse(message)
+            | RustleError::Api { message, .. }
+            | RustleError::Other(message)
+            | RustleError::Stalled(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for RustleError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Context;
+    use reqwest::header::HeaderValue;
+
+    #[test]
+    fn classifies_failures() {
+        let api: anyhow::Error = RustleError::api(StatusCode::NOT_FOUND, "Failed to fetch comments").into();
+        let error = RustleError::classify(&api.context("Loading thread")).context("Error fetching comments");
+        assert!(matches!(error, RustleError::Api { code: 404, .. }));
+        assert_eq!(error.to_string(), "Error fetching comments: Loading thread: Failed to fetch comments: 404 Not Found");
+        assert!(!error.retryable());
+
+        let parse = serde_json::from_str::<Vec<u32>>("{").context("Failed to parse posts").unwrap_err();
+        assert!(matches!(RustleError::classify(&parse), RustleError::Parse(_)));
+
+        let auth: anyhow::Error = RustleError::Auth { failure: AuthFailure::Grant, message: "Authentication error: Reddit API error: invalid_grant".to_string() }.into();
+        assert!(matches!(RustleError::classify(&auth.context("Signing in")), RustleError::Auth { failure: AuthFailure::Grant, message } if message.contains("invalid_grant")));
+        // Only the kind counts, not what the message happens to say
+        let mentioned = anyhow::anyhow!("Authentication error while parsing the sidebar");
+        assert!(matches!(RustleError::classify(&mentioned), RustleError::Other(_)));
+
+        let other = anyhow::anyhow!("No keyring available");
+        assert!(matches!(RustleError::classify(&other).context("Failed to save settings"), RustleError::Other(message) if message == "Failed to save settings: No keyring available"));
+    }
+
+    #[test]
+    fn waits_as_long_as_reddit_asks() {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-ratelimit-reset", HeaderValue::from_static("30"));
+        let RustleError::RateLimited { retry_after } = RustleError::rate_limited(&headers) else { panic!() };
+        assert!(retry_after > Instant::now() + Duration::from_secs(25));
+
+        headers.insert("retry-after", HeaderValue::from_static("5"));
+        let RustleError::RateLimited { retry_after } = RustleError::rate_limited(&headers) else { panic!() };
+        assert!(retry_after <= Instant::now() + Duration::from_secs(5));
+
+        // Values no clock can hold fall back or are capped instead of panicking
+        headers.insert("retry-after", HeaderValue::from_static("inf"));
+        let RustleError::RateLimited { retry_after } = RustleError::rate_limited(&headers) else { panic!() };
+        assert!(retry_after <= Instant::now() + Duration::from_secs(30));
+        headers.insert("retry-after", HeaderValue::from_static("1e300"));
+        let RustleError::RateLimited { retry_after } = RustleError::rate_limited(&headers) else { panic!() };
+        assert!(retry_after <= Instant::now() + Duration::from_secs(3600));
+    }
+}
@@ -0,0 +1,180 @@
+// Exports of a feed's posts, Saved included, as JSON, CSV or a Markdown link list, for keeping or
+// sharing outside Rustle.
+//
+// Either the posts already loaded are written or the whole feed is paged through first, as far
+// as Reddit lets a listing go. Each post keeps its title, author, subreddit, score, link,
+// permalink and when it was posted; the reader picks where the file goes in the system's save
+// dialog.
+use crate::{profile, Post};
+use anyhow::Result;
+use serde::Serialize;
+
+pub const MAX_POSTS: usize = 1000;  // Reddit stops paging a listing around here
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    Json,
+    Csv,
+    Markdown,
+}
+
+impl Format {
+    pub const ALL: [Format; 3] = [Format::Json, Format::Csv, Format::Markdown];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Format::Json => "JSON",
+            Format::Csv => "CSV",
+            Format::Markdown => "Markdown links",
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Format::Json => "json",
+            Format::Csv => "csv",
+            Format::Markdown => "md",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Scope {
+    Loaded,  // What's in the feed now
+    All,     // Every page, up to MAX_POSTS
+}
+
+// Shown in the feed header while a whole-feed export pages through
+#[derive(Debug, Default)]
+pub struct Progress {
+    pub fetched: usize,
+    pub cancelled: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct Record<'a> {
+    title: &'a str,
+    author: &'a str,
+    subreddit: &'a str,
+    score: i32,
+    url: &'a str,
+    permalink: String,  // Absolute, so it opens from anywhere
+    created_utc: i64,
+    created: String,    // ISO 8601, for spreadsheets
+}
+
+impl<'a> Record<'a> {
+    fn new(post: &'a Post) -> Self {
+        Record {
+            title: &post.title,
+            author: &post.author,
+            subreddit: &post.subreddit,
+            score: post.score,
+            url: &post.url,
+            permalink: format!("https://www.reddit.com{}", post.permalink),
+            created_utc: post.created_utc as i64,
+            created: iso_time(post.created_utc),
+        }
+    }
+}
+
+// e.g. "2024-03-21T14:05:00Z"
+fn iso_time(created_utc: f64) -> String {
+    let (year, month, day) = profile::civil_date(created_utc);
+    let seconds = (created_utc as i64).rem_euclid(86_400);
+    format!("{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z", year, month, day, seconds / 3600, seconds % 3600 / 60, seconds % 60)
+}
+
+// e.g. "rustle-r-rust-1711029900.csv"
+pub fn file_name(feed: &str, format: Format, now: i64) -> String {
+    let feed: String = feed.chars().map(|c| if c.is_ascii_alphanumeric() || c == '_' { c } else { '-' }).collect();
+    format!("rustle-{}-{}.{}", feed, now, format.extension())
+}
+
+// `title` heads the Markdown list, e.g. "r/rust" or "Saved"
+pub fn render(posts: &[Post], format: Format, title: &str) -> Result<String> {
+    let records: Vec<Record> = posts.iter().map(Record::new).collect();
+    Ok(match format {
+        Format::Json => serde_json::to_string_pretty(&records)? + "\n",
+        Format::Csv => {
+            let mut text = "title,author,subreddit,score,url,permalink,created_utc,created\n".to_string();
+            for record in &records {
+                let fields = [
+                    csv_field(record.title),
+                    csv_field(record.author),
+                    csv_field(record.subreddit),
+                    record.score.to_string(),
+                    csv_field(record.url),
+                    csv_field(&record.permalink),
+                    record.created_utc.to_string(),
+                    record.created.clone(),
+                ];
+                text.push_str(&fields.join(","));
+                text.push('\n');
+            }
+            text
+        }
+        Format::Markdown => {
+            let mut text = format!("# {}\n\n", title);
+            for record in &records {
+                text.push_str(&format!(
+                    "- [{}]({}) · r/{} · u/{} · {} points · {} · [comments]({})\n",
+                    markdown_text(record.title), record.url, record.subreddit, markdown_text(record.author),
+                    record.score, &record.created[..10], record.permalink,
+                ));
+            }
+            text
+        }
+    })
+}
+
+// Quoted when it holds a comma, quote or line break, with quotes doubled (RFC 4180)
+fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+// Titles can hold brackets and other markup that would break the link
+fn markdown_text(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '\\' | '[' | ']' | '*' | '_' | '`') {
+            escaped.push('\\');
+        }
+        escaped.push(if c == '\n' { ' ' } else { c });
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn writes_each_format() {
+        let post: Post = serde_json::from_value(serde_json::json!({
+            "title": "Rust 1.0, \"finally\" [release]", "author": "steveklabnik1", "subreddit": "rust", "score": 1523,
+            "url": "https://blog.rust-lang.org/2015/05/15/Rust-1.0.html", "permalink": "/r/rust/comments/36ey7j/rust_10/",
+            "created_utc": 1431694800.0
+        })).unwrap();
+        let posts = [post];
+
+        let json: serde_json::Value = serde_json::from_str(&render(&posts, Format::Json, "r/rust").unwrap()).unwrap();
+        assert_eq!(json[0]["permalink"], "https://www.reddit.com/r/rust/comments/36ey7j/rust_10/");
+        assert_eq!(json[0]["created"], "2015-05-15T13:00:00Z");
+        assert_eq!(json[0]["score"], 1523);
+
+        let csv = render(&posts, Format::Csv, "r/rust").unwrap();
+        assert_eq!(csv.lines().nth(1).unwrap(), concat!(
+            r#""Rust 1.0, ""finally"" [release]",steveklabnik1,rust,1523,https://blog.rust-lang.org/2015/05/15/Rust-1.0.html,"#,
+            "https://www.reddit.com/r/rust/comments/36ey7j/rust_10/,1431694800,2015-05-15T13:00:00Z",
+        ));
+
+        let markdown = render(&posts, Format::Markdown, "r/rust").unwrap();
+        assert!(markdown.starts_with("# r/rust\n\n- [Rust 1.0, \"finally\" \\[release\\]](https://blog.rust-lang.org/2015/05/15/Rust-1.0.html) · r/rust · u/steveklabnik1 · 1523 points · 2015-05-15 · [comments]("));
+        assert_eq!(file_name("me/saved", Format::Markdown, 7), "rustle-me-saved-7.md");
+    }
+}
@@ -0,0 +1,379 @@
+// Reader-defined rules that keep posts out of the feed.
+//
+// Each page is run through the rules as it arrives, before its posts are appended, so a filtered
+// post never takes up a row, starts an image download or counts towards the rows left before the
+// next page loads. A rule can be limited to one subreddit; otherwise it applies in every feed,
+// matching on the subreddit each post was made in rather than the feed it turned up in.
+//
+// Media mutes work the other way round: they belong to a feed, so muting videos in r/news or link
+// posts in the home feed leaves other feeds alone, whichever subreddits their posts come from.
+//
+// A rule can highlight matching posts instead of hiding them. The rules can be written out to a
+// standalone JSON file and read back in, merged with the reader's own or replacing them, so
+// communities can pass around blocklists and highlight packs.
+use crate::Post;
+use anyhow::{bail, Context, Result};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RuleKind {
+    #[default]
+    Keyword,  // Anywhere in the title or text, ignoring case
+    Regex,    // Against the title and the text
+    Flair,    // The whole link flair, ignoring case
+    Domain,   // The link's site, subdomains included
+}
+
+impl RuleKind {
+    pub const ALL: [RuleKind; 4] = [RuleKind::Keyword, RuleKind::Regex, RuleKind::Flair, RuleKind::Domain];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            RuleKind::Keyword => "Keyword",
+            RuleKind::Regex => "Regex",
+            RuleKind::Flair => "Flair",
+            RuleKind::Domain => "Domain",
+        }
+    }
+
+    pub fn hint(self) -> &'static str {
+        match self {
+            RuleKind::Keyword => "spoiler",
+            RuleKind::Regex => r"(?i)\bmegathread\b",
+            RuleKind::Flair => "Meme",
+            RuleKind::Domain => "youtube.com",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RuleAction {
+    #[default]
+    Hide,
+    Highlight,  // Outline matching cards instead
+}
+
+impl RuleAction {
+    pub const ALL: [RuleAction; 2] = [RuleAction::Hide, RuleAction::Highlight];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            RuleAction::Hide => "Hide",
+            RuleAction::Highlight => "Highlight",
+        }
+    }
+}
+
+// What a post mainly is, for muting whole kinds of post in a feed
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MediaKind {
+    Image,
+    Video,
+    Link,
+    Text,
+}
+
+impl MediaKind {
+    pub const ALL: [MediaKind; 4] = [MediaKind::Image, MediaKind::Video, MediaKind::Link, MediaKind::Text];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            MediaKind::Image => "Images and GIFs",
+            MediaKind::Video => "Videos",
+            MediaKind::Link => "Links",
+            MediaKind::Text => "Text posts",
+        }
+    }
+
+    pub fn of(post: &Post) -> MediaKind {
+        if post.is_self || post.domain.starts_with("self.") {
+            return MediaKind::Text;
+        }
+        match post.post_hint.as_deref() {
+            _ if post.is_video => MediaKind::Video,
+            Some("hosted:video") | Some("rich:video") => MediaKind::Video,
+            Some("image") => MediaKind::Image,
+            _ if post.domain == "v.redd.it" => MediaKind::Video,
+            _ if post.domain == "i.redd.it" || post.domain == "i.imgur.com" => MediaKind::Image,
+            _ => MediaKind::Link,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FilterRule {
+    pub kind: RuleKind,
+    pub pattern: String,
+    pub subreddit: String,  // Empty for every feed
+    pub enabled: bool,
+    pub action: RuleAction,
+}
+
+impl Default for FilterRule {
+    fn default() -> Self {
+        FilterRule { kind: RuleKind::default(), pattern: String::new(), subreddit: String::new(), enabled: true, action: RuleAction::Hide }
+    }
+}
+
+impl FilterRule {
+    // Why a regex rule can't be used, for the settings screen
+    pub fn error(&self) -> Option<String> {
+        if self.kind != RuleKind::Regex || self.pattern.trim().is_empty() {
+            return None;
+        }
+        Regex::new(self.pattern.trim()).err().map(|e| e.to_string())
+    }
+}
+
+enum Matcher {
+    Keyword(String),  // Lowercased
+    Regex(Regex),
+    Flair(String),    // Lowercased
+    Domain(String),   // Lowercased, without "www."
+}
+
+// The enabled rules, compiled once per page rather than once per post
+pub struct Filters {
+    rules: Vec<(Matcher, Option<String>)>,  // With the lowercase subreddit it's limited to
+    muted: Vec<MediaKind>,  // Muted in the feed being loaded
+}
+
+impl Filters {
+    // The hiding rules. Disabled and blank rules are left out, as are regexes that don't compile
+    pub fn new(rules: &[FilterRule]) -> Self {
+        Filters::compile(rules, RuleAction::Hide)
+    }
+
+    // The highlighting rules instead, for `matches`
+    pub fn highlights(rules: &[FilterRule]) -> Self {
+        Filters::compile(rules, RuleAction::Highlight)
+    }
+
+    fn compile(rules: &[FilterRule], action: RuleAction) -> Self {
+        let rules = rules.iter()
+            .filter(|rule| rule.enabled && rule.action == action && !rule.pattern.trim().is_empty())
+            .filter_map(|rule| {
+                let pattern = rule.pattern.trim();
+                let matcher = match rule.kind {
+                    RuleKind::Keyword => Matcher::Keyword(pattern.to_lowercase()),
+                    RuleKind::Regex => Matcher::Regex(Regex::new(pattern).ok()?),
+                    RuleKind::Flair => Matcher::Flair(pattern.to_lowercase()),
+                    RuleKind::Domain => Matcher::Domain(pattern.to_lowercase().trim_start_matches("www.").to_string()),
+                };
+                let subreddit = rule.subreddit.trim().trim_start_matches('/').trim_start_matches("r/").to_lowercase();
+                Some((matcher, (!subreddit.is_empty()).then_some(subreddit)))
+            })
+            .collect();
+        Filters { rules, muted: Vec::new() }
+    }
+
+    // Also hide these kinds of post, as muted in the feed the page is for
+    pub fn muting(mut self, kinds: &[MediaKind]) -> Self {
+        self.muted = kinds.to_vec();
+        self
+    }
+
+    pub fn hides(&self, post: &Post) -> bool {
+        (!self.muted.is_empty() && self.muted.contains(&MediaKind::of(post))) || self.matches(post)
+    }
+
+    // Whether any rule matches, leaving media mutes aside
+    pub fn matches(&self, post: &Post) -> bool {
+        self.rules.iter()
+            .filter(|(_, subreddit)| subreddit.as_ref().is_none_or(|subreddit| post.subreddit.eq_ignore_ascii_case(subreddit)))
+            .any(|(matcher, _)| match matcher {
+                Matcher::Keyword(keyword) => post.title.to_lowercase().contains(keyword) || post.selftext.to_lowercase().contains(keyword),
+                Matcher::Regex(regex) => regex.is_match(&post.title) || regex.is_match(&post.selftext),
+                Matcher::Flair(flair) => post.link_flair_text.as_ref().is_some_and(|text| text.trim().to_lowercase() == *flair),
+                Matcher::Domain(domain) => {
+                    let site = post.domain.to_lowercase();
+                    site == *domain || site.ends_with(&format!(".{}", domain))
+                }
+            })
+    }
+
+    // The posts that get through, and how many didn't
+    pub fn apply(&self, posts: Vec<Post>) -> (Vec<Post>, usize) {
+        let count = posts.len();
+        let kept: Vec<Post> = posts.into_iter().filter(|post| !self.hides(post)).collect();
+        let hidden = count - kept.len();
+        (kept, hidden)
+    }
+}
+
+// Highlight rules compiled for drawing the feed, and compiled again only when they change
+#[derive(Default)]
+pub struct Highlighter {
+    rules: Vec<FilterRule>,
+    compiled: Option<Filters>,
+}
+
+impl Highlighter {
+    pub fn matches(&mut self, rules: &[FilterRule], post: &Post) -> bool {
+        if !rules.iter().any(|rule| rule.action == RuleAction::Highlight) {
+            return false;
+        }
+        if self.compiled.is_none() || self.rules != rules {
+            self.rules = rules.to_vec();
+            self.compiled = Some(Filters::highlights(rules));
+        }
+        self.compiled.as_ref().is_some_and(|compiled| compiled.matches(post))
+    }
+}
+
+// Rules read from a shared file, until the reader merges them in or replaces theirs
+#[derive(Clone)]
+pub struct Import {
+    pub file: String,
+    pub rules: Vec<FilterRule>,
+}
+
+const SET_VERSION: u32 = 1;
+
+// A shared rule file: {"rustle_rules": 1, "rules": [...]}
+#[derive(Serialize, Deserialize)]
+struct RuleSet {
+    rustle_rules: u32,  // Format version
+    rules: Vec<FilterRule>,
+}
+
+pub fn write_set(rules: &[FilterRule]) -> Result<String> {
+    let set = RuleSet { rustle_rules: SET_VERSION, rules: rules.to_vec() };
+    Ok(serde_json::to_string_pretty(&set)? + "\n")
+}
+
+// Blank rules are dropped; fields a rule leaves out take their defaults
+pub fn read_set(text: &str) -> Result<Vec<FilterRule>> {
+    let set: RuleSet = serde_json::from_str(text).context("Not a Rustle rule file")?;
+    if set.rustle_rules > SET_VERSION {
+        bail!("This rule file is from a newer version of Rustle");
+    }
+    Ok(set.rules.into_iter().filter(|rule| !rule.pattern.trim().is_empty()).collect())
+}
+
+// Adds the rules not already there, whether or not they're switched on; returns how many
+pub fn merge(existing: &mut Vec<FilterRule>, incoming: Vec<FilterRule>) -> usize {
+    // Only regexes are case-sensitive
+    let same = |a: &FilterRule, b: &FilterRule| {
+        let (x, y) = (a.pattern.trim(), b.pattern.trim());
+        a.kind == b.kind && a.action == b.action
+            && if a.kind == RuleKind::Regex { x == y } else { x.to_lowercase() == y.to_lowercase() }
+            && a.subreddit.trim().eq_ignore_ascii_case(b.subreddit.trim())
+    };
+    let before = existing.len();
+    for rule in incoming {
+        if !existing.iter().any(|kept| same(kept, &rule)) {
+            existing.push(rule);
+        }
+    }
+    existing.len() - before
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn post(title: &str, subreddit: &str, domain: &str, flair: Option<&str>) -> Post {
+        serde_json::from_value(serde_json::json!({
+            "title": title,
+            "subreddit": subreddit,
+            "domain": domain,
+            "link_flair_text": flair,
+        })).unwrap()
+    }
+
+    fn rule(kind: RuleKind, pattern: &str, subreddit: &str) -> FilterRule {
+        FilterRule { kind, pattern: pattern.to_string(), subreddit: subreddit.to_string(), ..FilterRule::default() }
+    }
+
+    #[test]
+    fn matches_each_kind_of_rule() {
+        let posts = vec![
+            post("Finale SPOILERS inside", "television", "self.television", None),
+            post("Weekly Megathread", "rust", "self.rust", None),
+            post("Look at this", "pics", "i.redd.it", Some(" meme ")),
+            post("A talk", "rust", "m.youtube.com", None),
+            post("Release notes", "rust", "blog.rust-lang.org", Some("News")),
+        ];
+        let filters = Filters::new(&[
+            rule(RuleKind::Keyword, "spoiler", ""),
+            rule(RuleKind::Regex, r"(?i)\bmegathread\b", ""),
+            rule(RuleKind::Flair, "Meme", ""),
+            rule(RuleKind::Domain, "www.youtube.com", ""),
+        ]);
+        let (kept, hidden) = filters.apply(posts);
+        assert_eq!(hidden, 4);
+        assert_eq!(kept.len(), 1);
+        assert_eq!(kept[0].title, "Release notes");
+    }
+
+    #[test]
+    fn limits_rules_to_their_subreddit() {
+        let filters = Filters::new(&[rule(RuleKind::Keyword, "meme", "r/Rust")]);
+        assert!(filters.hides(&post("Meme Monday", "rust", "self.rust", None)));
+        assert!(!filters.hides(&post("Meme Monday", "ProgrammerHumor", "i.redd.it", None)));
+
+        // Disabled, blank and broken rules hide nothing
+        let mut disabled = rule(RuleKind::Keyword, "meme", "");
+        disabled.enabled = false;
+        let broken = rule(RuleKind::Regex, "(unclosed", "");
+        assert!(broken.error().is_some());
+        let filters = Filters::new(&[disabled, broken, rule(RuleKind::Domain, "  ", "")]);
+        assert!(!filters.hides(&post("Meme Monday", "rust", "self.rust", None)));
+    }
+
+    #[test]
+    fn mutes_kinds_of_post() {
+        let clip: Post = serde_json::from_value(serde_json::json!({"domain": "v.redd.it", "is_video": true})).unwrap();
+        let embed: Post = serde_json::from_value(serde_json::json!({"domain": "youtube.com", "post_hint": "rich:video"})).unwrap();
+        let photo: Post = serde_json::from_value(serde_json::json!({"domain": "i.redd.it"})).unwrap();
+        let article = post("Bill passes", "news", "apnews.com", None);
+        let question = post("Why?", "AskReddit", "self.AskReddit", None);
+        assert_eq!(MediaKind::of(&clip), MediaKind::Video);
+        assert_eq!(MediaKind::of(&embed), MediaKind::Video);
+        assert_eq!(MediaKind::of(&photo), MediaKind::Image);
+        assert_eq!(MediaKind::of(&article), MediaKind::Link);
+        assert_eq!(MediaKind::of(&question), MediaKind::Text);
+
+        let (kept, hidden) = Filters::new(&[]).muting(&[MediaKind::Video, MediaKind::Link]).apply(vec![clip, embed, photo, article, question]);
+        assert_eq!(hidden, 3);
+        assert_eq!(kept.iter().map(MediaKind::of).collect::<Vec<_>>(), [MediaKind::Image, MediaKind::Text]);
+    }
+    #[test]
+    fn highlights_instead_of_hiding() {
+        let highlight = FilterRule { action: RuleAction::Highlight, ..rule(RuleKind::Keyword, "release", "") };
+        let rules = [highlight, rule(RuleKind::Keyword, "meme", "")];
+        let release = post("Release notes", "rust", "blog.rust-lang.org", None);
+        assert!(!Filters::new(&rules).hides(&release));
+        assert!(Filters::highlights(&rules).matches(&release));
+        assert!(!Filters::highlights(&rules).matches(&post("Meme Monday", "rust", "self.rust", None)));
+    }
+
+    #[test]
+    fn shares_rule_sets_as_files() {
+        let mine = vec![rule(RuleKind::Keyword, "spoiler", ""), rule(RuleKind::Domain, "youtube.com", "rust")];
+        let text = write_set(&mine).unwrap();
+        assert!(text.starts_with("{\n  \"rustle_rules\": 1,"));
+        assert_eq!(read_set(&text).unwrap(), mine);
+
+        let shared = read_set(r#"{"rustle_rules": 1, "rules": [
+            {"kind": "keyword", "pattern": "Spoiler ", "enabled": false},
+            {"kind": "domain", "pattern": "youtube.com", "subreddit": "Rust"},
+            {"kind": "flair", "pattern": "Meme", "action": "highlight"},
+            {"kind": "regex", "pattern": "  "}
+        ]}"#).unwrap();
+        assert_eq!(shared.len(), 3);
+        assert_eq!(shared[2].action, RuleAction::Highlight);
+        let mut merged = mine.clone();
+        assert_eq!(merge(&mut merged, shared), 1);
+        assert_eq!(merged[2].pattern, "Meme");
+
+        assert!(read_set(r#"{"rustle_rules": 2, "rules": []}"#).is_err());
+        assert!(read_set("[]").is_err());
+    }
+}
@@ -0,0 +1,190 @@
+// Soft daily time limits, for the whole app or for particular feeds ("20 min/day on r/all").
+//
+// Time comes from the usage tracker (usage.rs), counted per feed and added up since local
+// midnight. Going over a limit brings up a full-screen nudge. It can be snoozed for a while or
+// let go for the rest of the day, so nothing is ever blocked outright.
+use crate::usage;
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+
+pub const SNOOZE: Duration = Duration::from_secs(10 * 60);
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TimeLimit {
+    pub feed: String,  // Subreddit or feed name; empty for the whole app
+    pub minutes: u32,  // Per day
+}
+
+impl Default for TimeLimit {
+    fn default() -> Self {
+        TimeLimit { feed: String::new(), minutes: 30 }
+    }
+}
+
+impl TimeLimit {
+    // Lowercase feed name as the tracker counts it, e.g. "all" for "r/All"
+    fn key(&self) -> String {
+        let feed = self.feed.trim();
+        feed.strip_prefix("r/").or_else(|| feed.strip_prefix("/r/")).unwrap_or(feed).to_lowercase()
+    }
+}
+
+// Over a limit and not snoozed or let go
+#[derive(Debug, PartialEq)]
+pub struct Nudge {
+    key: String,
+    spent_minutes: u32,
+    limit_minutes: u32,
+}
+
+impl Nudge {
+    fn place(&self) -> String {
+        match self.key.as_str() {
+            "" => crate::APP_NAME.to_string(),
+            "home" => "your home feed".to_string(),
+            feed => format!("r/{}", feed),
+        }
+    }
+
+    pub fn is_app_wide(&self) -> bool {
+        self.key.is_empty()
+    }
+
+    // Only a subreddit limit can be walked away from without closing the app
+    fn can_leave(&self) -> bool {
+        !self.is_app_wide() && self.key != "home"
+    }
+}
+
+pub enum Choice {
+    Snooze,
+    KeepGoing,  // No more reminders about this limit until midnight
+    Leave,      // Back to the home feed, or close the app for the app-wide limit
+}
+
+#[derive(Default)]
+pub struct Focus {
+    day_start: i64,
+    today: HashMap<String, f64>,  // Seconds per feed since midnight, as of the last write to the store
+    snoozed: HashMap<String, Instant>,
+    let_go: HashSet<String>,      // Limits overridden for the rest of the day
+}
+
+impl Focus {
+    // With the store's totals after each autosave; a new day starts every limit afresh
+    pub fn set_today(&mut self, day_start: i64, today: HashMap<String, f64>) {
+        if day_start != self.day_start {
+            self.day_start = day_start;
+            self.snoozed.clear();
+            self.let_go.clear();
+        }
+        self.today = today;
+    }
+
+    // The first limit that's been gone over while in `feed`
+    pub fn check(&self, limits: &[TimeLimit], feed: &str, pending: &usage::Pending, now: Instant) -> Option<Nudge> {
+        let feed = feed.to_lowercase();
+        limits.iter().filter(|limit| limit.minutes > 0).find_map(|limit| {
+            let key = limit.key();
+            let seconds = if key.is_empty() {
+                self.today.values().sum::<f64>() + pending.seconds
+            } else if key == feed {
+                self.today.get(&key).copied().unwrap_or_default() + pending.feeds.get(&key).copied().unwrap_or_default()
+            } else {
+                return None;
+            };
+            let quiet = self.let_go.contains(&key) || self.snoozed.get(&key).is_some_and(|until| now < *until);
+            (seconds >= limit.minutes as f64 * 60.0 && !quiet)
+                .then_some(Nudge { key, spent_minutes: (seconds / 60.0) as u32, limit_minutes: limit.minutes })
+        })
+    }
+
+    pub fn snooze(&mut self, nudge: &Nudge, now: Instant) {
+        self.snoozed.insert(nudge.key.clone(), now + SNOOZE);
+    }
+
+    pub fn let_go(&mut self, nudge: &Nudge) {
+        self.let_go.insert(nudge.key.clone());
+    }
+}
+
+// Covers the whole window, over everything else, until one of the choices is taken
+pub fn show(ctx: &egui::Context, nudge: &Nudge) -> Option<Choice> {
+    let screen = ctx.screen_rect();
+    let mut choice = None;
+    egui::Area::new("focus_nudge")
+        .order(egui::Order::Foreground)
+        .fixed_pos(screen.min)
+        .show(ctx, |ui| {
+            ui.painter().rect_filled(screen, 0.0, ui.visuals().extreme_bg_color.gamma_multiply(0.96));
+            // Swallows clicks and scrolling meant for what's underneath
+            ui.allocate_rect(screen, egui::Sense::click_and_drag());
+            let mut content = ui.child_ui(screen, egui::Layout::top_down(egui::Align::Center));
+            content.add_space(screen.height() * 0.3);
+            content.label(egui::RichText::new("Time for a break?").size(24.0).strong());
+            content.add_space(8.0);
+            content.label(format!(
+                "You've spent {} minutes in {} today. Your limit is {} minutes.",
+                nudge.spent_minutes, nudge.place(), nudge.limit_minutes,
+            ));
+            content.add_space(16.0);
+            let leave = if nudge.can_leave() {
+                Some("Back to the home feed".to_string())
+            } else {
+                nudge.is_app_wide().then(|| format!("Close {}", crate::APP_NAME))
+            };
+            if leave.is_some_and(|label| content.button(label).clicked()) {
+                choice = Some(Choice::Leave);
+            }
+            if content.button(format!("Snooze for {} minutes", SNOOZE.as_secs() / 60)).clicked() {
+                choice = Some(Choice::Snooze);
+            }
+            if content.button("Keep going today").on_hover_text("No more reminders about this limit until tomorrow").clicked() {
+                choice = Some(Choice::KeepGoing);
+            }
+        });
+    choice
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nudges_once_over_a_limit_until_snoozed_or_let_go() {
+        let limits = vec![
+            TimeLimit { feed: "r/All".to_string(), minutes: 20 },
+            TimeLimit { feed: String::new(), minutes: 60 },
+        ];
+        let mut focus = Focus::default();
+        focus.set_today(100, HashMap::from([("all".to_string(), 1140.0), ("rust".to_string(), 1800.0)]));
+        let mut pending = usage::Pending::default();
+        let now = Instant::now();
+        assert_eq!(focus.check(&limits, "all", &pending, now), None);
+
+        pending.seconds = 60.0;
+        pending.feeds.insert("all".to_string(), 60.0);
+        let nudge = focus.check(&limits, "All", &pending, now).unwrap();
+        assert_eq!(nudge, Nudge { key: "all".to_string(), spent_minutes: 20, limit_minutes: 20 });
+        focus.snooze(&nudge, now);
+        assert_eq!(focus.check(&limits, "all", &pending, now), None);
+        assert!(focus.check(&limits, "all", &pending, now + SNOOZE).is_some());
+
+        // Only the app-wide limit applies elsewhere, and it isn't reached yet
+        assert_eq!(focus.check(&limits, "rust", &pending, now), None);
+        pending.seconds = 700.0;
+        let nudge = focus.check(&limits, "rust", &pending, now).unwrap();
+        assert_eq!(nudge.place(), "Rustle");
+        focus.let_go(&nudge);
+        assert_eq!(focus.check(&limits, "rust", &pending, now), None);
+
+        // Tomorrow starts over
+        focus.set_today(100 + 86_400, HashMap::new());
+        assert!(focus.check(&limits, "rust", &pending, now).is_none());
+        pending.seconds = 3600.0;
+        assert!(focus.check(&limits, "rust", &pending, now).is_some());
+    }
+}
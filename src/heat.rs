@@ -0,0 +1,114 @@
+// Cold-to-hot tints for score and comment counts.
+//
+// What counts as popular depends on the subreddit: 500 points is a quiet post on r/pics and a
+// record in a small hobby sub. Each subreddit remembers the scores and comment counts of the
+// posts seen in it recently, and a post is tinted by where it ranks among them.
+use eframe::egui::Color32;
+use std::collections::{HashMap, VecDeque};
+
+const HISTORY_LEN: usize = 500;  // Posts remembered per subreddit
+const MIN_SAMPLES: usize = 10;   // Fewer than this isn't enough to rank against
+
+const COLD: Color32 = Color32::from_rgb(110, 150, 230);
+const WARM: Color32 = Color32::from_rgb(230, 180, 60);
+const HOT: Color32 = Color32::from_rgb(255, 69, 0);
+
+#[derive(Default)]
+pub struct Heat {
+    subreddits: HashMap<String, History>,  // Keyed by lowercase name
+}
+
+#[derive(Default)]
+struct History {
+    order: VecDeque<String>,  // Fullnames, oldest first
+    counts: HashMap<String, (i32, u32)>,  // Latest score and comment count by fullname
+}
+
+impl Heat {
+    // Seeing the same post again just updates its numbers
+    pub fn record(&mut self, subreddit: &str, name: &str, score: i32, comments: u32) {
+        let history = self.subreddits.entry(subreddit.to_lowercase()).or_default();
+        if history.counts.insert(name.to_string(), (score, comments)).is_some() {
+            return;
+        }
+        history.order.push_back(name.to_string());
+        if history.order.len() > HISTORY_LEN {
+            if let Some(oldest) = history.order.pop_front() {
+                history.counts.remove(&oldest);
+            }
+        }
+    }
+
+    pub fn score(&self, subreddit: &str, score: i32) -> Option<f32> {
+        self.rank(subreddit, score as i64, |&(score, _)| score as i64)
+    }
+
+    pub fn comments(&self, subreddit: &str, comments: u32) -> Option<f32> {
+        self.rank(subreddit, comments as i64, |&(_, comments)| comments as i64)
+    }
+
+    // Percentile from 0.0 (coldest seen) to 1.0 (hottest), None until there's enough history
+    fn rank(&self, subreddit: &str, value: i64, field: impl Fn(&(i32, u32)) -> i64) -> Option<f32> {
+        let history = self.subreddits.get(&subreddit.to_lowercase())?;
+        if history.counts.len() < MIN_SAMPLES {
+            return None;
+        }
+        let (below, equal) = history.counts.values().map(field)
+            .fold((0, 0), |(below, equal), other| (below + usize::from(other < value), equal + usize::from(other == value)));
+        Some((below as f32 + equal as f32 / 2.0) / history.counts.len() as f32)
+    }
+}
+
+// Blue for the bottom of the range through amber to Reddit orange at the top
+pub fn color(heat: f32) -> Color32 {
+    let heat = heat.clamp(0.0, 1.0);
+    if heat < 0.5 {
+        lerp(COLD, WARM, heat * 2.0)
+    } else {
+        lerp(WARM, HOT, heat * 2.0 - 1.0)
+    }
+}
+
+fn lerp(from: Color32, to: Color32, t: f32) -> Color32 {
+    let channel = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * t).round() as u8;
+    Color32::from_rgb(channel(from.r(), to.r()), channel(from.g(), to.g()), channel(from.b(), to.b()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ranks_within_each_subreddit() {
+        let mut heat = Heat::default();
+        for i in 0..20 {
+            heat.record("Rust", &format!("t3_{}", i), i * 10, i as u32);
+        }
+        assert_eq!(heat.score("rust", 1000), Some(1.0));
+        assert_eq!(heat.score("rust", -5), Some(0.0));
+        assert_eq!(heat.comments("rust", 10), Some(0.525));
+        // Too little history to judge by
+        heat.record("pics", "t3_x", 50_000, 900);
+        assert_eq!(heat.score("pics", 50_000), None);
+    }
+
+    #[test]
+    fn forgets_the_oldest_posts() {
+        let mut heat = Heat::default();
+        for i in 0..HISTORY_LEN + 5 {
+            heat.record("rust", &format!("t3_{}", i), 1, 1);
+        }
+        heat.record("rust", "t3_10", 2, 2);
+        let history = &heat.subreddits["rust"];
+        assert_eq!(history.counts.len(), HISTORY_LEN);
+        assert!(!history.counts.contains_key("t3_0"));
+        assert_eq!(history.counts["t3_10"], (2, 2));
+    }
+
+    #[test]
+    fn blends_from_cold_to_hot() {
+        assert_eq!(color(0.0), COLD);
+        assert_eq!(color(0.5), WARM);
+        assert_eq!(color(2.0), HOT);
+    }
+}
@@ -0,0 +1,130 @@
+// Link hints: press f and every visible link gets a short label, and typing a label follows that
+// link without the mouse, Vimium style.
+//
+// Links offer themselves through `RedditApp::hint` as they're drawn, and only while hinting is
+// on. Labels go on at the end of the frame, and the next frame's keystrokes are matched against them.
+use crate::Post;
+use eframe::egui;
+
+const ALPHABET: &[u8] = b"sadfjklewcmpgh";  // Home row first
+
+// What following a hinted link does
+pub enum Jump {
+    Link(Post),
+    Comments(Post),
+    Profile(String),
+    Subreddit(String),
+}
+
+struct Target {
+    rect: egui::Rect,
+    jump: Jump,
+}
+
+#[derive(Default)]
+pub struct Hints {
+    active: bool,
+    typed: String,
+    drawing: Vec<Target>,          // Offered so far this frame
+    shown: Vec<(String, Target)>,  // Labelled at the end of the last frame
+}
+
+impl Hints {
+    pub fn active(&self) -> bool {
+        self.active
+    }
+
+    pub fn start(&mut self) {
+        *self = Hints { active: true, ..Default::default() };
+    }
+
+    pub fn cancel(&mut self) {
+        *self = Hints::default();
+    }
+
+    pub fn add(&mut self, rect: egui::Rect, jump: Jump) {
+        self.drawing.push(Target { rect, jump });
+    }
+
+    // Keys that don't continue any label are ignored; finishing one ends hinting with its jump
+    pub fn type_char(&mut self, c: char) -> Option<Jump> {
+        self.typed.push(c.to_ascii_lowercase());
+        if !self.shown.iter().any(|(label, _)| label.starts_with(&self.typed)) {
+            self.typed.pop();
+            return None;
+        }
+        let index = self.shown.iter().position(|(label, _)| *label == self.typed)?;
+        let (_, target) = self.shown.swap_remove(index);
+        self.cancel();
+        Some(target.jump)
+    }
+
+    pub fn backspace(&mut self) {
+        self.typed.pop();
+    }
+
+    // Labels this frame's links and draws them over everything; with nothing to label, hinting ends
+    pub fn show(&mut self, ctx: &egui::Context) {
+        if !self.active {
+            return;
+        }
+        let targets = std::mem::take(&mut self.drawing);
+        if targets.is_empty() {
+            self.cancel();
+            return;
+        }
+        self.shown = labels(targets.len()).into_iter().zip(targets).collect();
+
+        let painter = ctx.layer_painter(egui::LayerId::new(egui::Order::Tooltip, egui::Id::new("link_hints")));
+        for (label, target) in self.shown.iter().filter(|(label, _)| label.starts_with(&self.typed)) {
+            let galley = painter.layout_no_wrap(label.to_uppercase(), egui::FontId::monospace(12.0), egui::Color32::BLACK);
+            let rect = egui::Rect::from_min_size(target.rect.left_top(), galley.size() + egui::vec2(6.0, 2.0));
+            painter.rect(rect, 3.0, egui::Color32::from_rgb(255, 214, 10), egui::Stroke::new(1.0, egui::Color32::from_rgb(160, 120, 0)));
+            painter.galley(rect.min + egui::vec2(3.0, 1.0), galley, egui::Color32::BLACK);
+        }
+    }
+}
+
+// All the same length, so no label is the start of another
+fn labels(count: usize) -> Vec<String> {
+    let base = ALPHABET.len();
+    let mut length = 1;
+    while base.pow(length) < count {
+        length += 1;
+    }
+    (0..count).map(|index| {
+        let mut rest = index;
+        let mut label = Vec::new();
+        for _ in 0..length {
+            label.insert(0, ALPHABET[rest % base]);
+            rest /= base;
+        }
+        String::from_utf8(label).unwrap()
+    }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn labels_and_picks_links() {
+        assert_eq!(labels(3), ["s", "a", "d"]);
+        let many = labels(ALPHABET.len() + 1);
+        assert_eq!(many[0], "ss");
+        assert_eq!(many[ALPHABET.len()], "as");
+
+        let mut hints = Hints::default();
+        hints.start();
+        for name in ["rust", "pics", "aww"] {
+            hints.add(egui::Rect::NOTHING, Jump::Subreddit(name.to_string()));
+        }
+        let _ = egui::Context::default().run(egui::RawInput::default(), |ctx| hints.show(ctx));
+        assert!(hints.type_char('x').is_none());
+        let Some(Jump::Subreddit(name)) = hints.type_char('A') else {
+            panic!("expected the second link");
+        };
+        assert_eq!(name, "pics");
+        assert!(!hints.active());
+    }
+}
@@ -0,0 +1,53 @@
+// Posts the reader has opened, in the comment view, the reader or the browser, newest first.
+//
+// Visits are written to the local database as they happen (`Store::record_visit`), with a copy of
+// the post so the list shows without going back to Reddit. Opening a post again moves it to the top.
+// The list and its search are read back a page at a time (`Store::history`), off the UI thread.
+use crate::Post;
+
+pub const PAGE: u32 = 100;  // Visits read at a time; reaching the end of the list reads the next
+
+#[derive(Debug, Clone)]
+pub struct Visit {
+    pub post: Post,
+    pub visited_at: i64,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct History {
+    pub visits: Vec<Visit>,
+    pub query: String,             // As typed in the search box
+    pub searched: Option<String>,  // Search the loaded visits are for; None until the first page arrives
+    pub more: bool,                // Older matches are still in the database
+    pub loading: bool,
+    pub confirm_clear: bool,
+}
+
+impl History {
+    // True once the search box no longer matches the visits showing
+    pub fn stale(&self) -> bool {
+        self.searched.as_deref() != Some(self.query.trim())
+    }
+}
+
+// A LIKE pattern per word of the search, with LIKE's own wildcards taken literally
+pub fn patterns(query: &str) -> Vec<String> {
+    query.split_whitespace()
+        .map(|word| format!("%{}%", word.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_")))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escapes_wildcards_in_search_words() {
+        assert_eq!(patterns("  r/rust 100%  snake_case "), ["%r/rust%", "%100\\%%", "%snake\\_case%"]);
+        assert!(patterns(" ").is_empty());
+
+        let history = History { query: "rust ".to_string(), searched: Some("rust".to_string()), ..History::default() };
+        assert!(!history.stale());
+        assert!(History::default().stale());
+    }
+}
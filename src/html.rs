@@ -0,0 +1,147 @@
+// HTML entity handling for Reddit text fields.
+//
+// Without `raw_json=1` Reddit's JSON escapes `&`, `<` and `>` in titles, bodies, flair and even
+// URLs. The client always asks for raw JSON, so fields marked `#[serde(with = "html")]` are taken
+// exactly as they arrive; decoding them again would turn a literal "&lt;" someone typed into "<".
+// JSON captured without it, like the test fixtures, is put through `decode` by whoever reads it.
+use serde::{Deserialize, Deserializer, Serializer};
+
+// Longest entity worth looking for, e.g. "&#x1F600;"
+#[cfg(test)]
+const MAX_ENTITY_LEN: usize = 10;
+
+// Only JSON fetched without raw_json needs this, and the app never asks for that
+#[cfg(test)]
+pub fn decode(text: &str) -> String {
+    if !text.contains('&') {
+        return text.to_string();
+    }
+
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let entity = rest.find(';')
+            .filter(|&end| end <= MAX_ENTITY_LEN)
+            .and_then(|end| Some((decode_entity(&rest[1..end])?, end)));
+        match entity {
+            Some((c, end)) => {
+                decoded.push(c);
+                rest = &rest[end + 1..];
+            }
+            // A bare ampersand or something we don't recognise is kept verbatim
+            None => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+#[cfg(test)]
+fn decode_entity(name: &str) -> Option<char> {
+    match name {
+        "amp" => Some('&'),
+        "lt" => Some('<'),
+        "gt" => Some('>'),
+        "quot" => Some('"'),
+        "apos" => Some('\''),
+        "nbsp" => Some('\u{a0}'),
+        _ => {
+            let number = name.strip_prefix('#')?;
+            let code = match number.strip_prefix(['x', 'X']) {
+                Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                None => number.parse().ok()?,
+            };
+            char::from_u32(code)
+        }
+    }
+}
+
+// Inverse of `decode` for the characters Reddit escapes
+pub fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+pub fn deserialize<'de, D>(deserializer: D) -> Result<String, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    // Reddit sends null for some text fields, so treat it like an empty string
+    let text = Option::<String>::deserialize(deserializer)?;
+    Ok(text.unwrap_or_default())
+}
+
+// Written as it is, so the local cache reads back unchanged
+pub fn serialize<S>(text: &str, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    serializer.serialize_str(text)
+}
+
+pub mod option {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Option::<String>::deserialize(deserializer)
+    }
+
+    pub fn serialize<S>(text: &Option<String>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match text {
+            Some(text) => serializer.serialize_some(text),
+            None => serializer.serialize_none(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_named_and_numeric_entities() {
+        assert_eq!(decode("Tom &amp; Jerry"), "Tom & Jerry");
+        assert_eq!(decode("&lt;b&gt; &quot;quoted&quot; &apos;a&apos;"), "<b> \"quoted\" 'a'");
+        assert_eq!(decode("what&#39;s &#x27;up&#X27;"), "what's 'up'");
+        assert_eq!(decode("&#128512;"), "😀");
+    }
+
+    #[derive(serde::Deserialize)]
+    struct Title {
+        #[serde(with = "super")]
+        title: String,
+    }
+
+    #[test]
+    fn keeps_literal_entities_typed_by_users() {
+        // raw_json sends a literal "&lt;" as it was typed, and it must stay that way
+        let raw: Title = serde_json::from_str(r#"{"title": "Use &lt; for <"}"#).unwrap();
+        assert_eq!(raw.title, "Use &lt; for <");
+        // Without raw_json it arrives escaped twice over, and decoding undoes just one
+        assert_eq!(decode("Use &amp;lt; for &lt;"), "Use &lt; for <");
+    }
+
+    #[test]
+    fn keeps_unknown_or_bare_ampersands() {
+        assert_eq!(decode("fish & chips"), "fish & chips");
+        assert_eq!(decode("&bogus; &#xZZ; &"), "&bogus; &#xZZ; &");
+        assert_eq!(decode("a&b;c and a very long &notanentityatall;"), "a&b;c and a very long &notanentityatall;");
+    }
+
+    #[test]
+    fn escape_round_trips_through_decode() {
+        for text in ["a & b", "<tag>", "&lt; literal", "plain"] {
+            assert_eq!(decode(&escape(text)), text);
+        }
+    }
+}
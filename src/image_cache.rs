@@ -0,0 +1,213 @@
+// Disk-backed image cache.
+//
+// Registered as an egui bytes loader ahead of the stock HTTP loader, so every thumbnail and
+// preview the feed asks for is served from the platform cache directory when possible and
+// written there after the first download. The directory is kept under a size budget by
+// evicting the least recently used files.
+use eframe::egui::{
+    self,
+    load::{Bytes, BytesLoadResult, BytesLoader, BytesPoll, LoadError},
+};
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    fs,
+    hash::{Hash, Hasher},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    task::Poll,
+    thread,
+    time::{Instant, SystemTime},
+};
+
+use crate::api_log;
+
+type Entry = Poll<Result<Arc<[u8]>, String>>;
+
+pub struct ImageCache {
+    dir: PathBuf,
+    max_bytes: AtomicU64,
+    usage: AtomicU64,
+    memory: Mutex<HashMap<String, Entry>>,
+}
+
+impl ImageCache {
+    pub const ID: &'static str = egui::generate_loader_id!(ImageCache);
+
+    pub fn new(max_bytes: u64) -> Option<Arc<Self>> {
+        let dirs = directories::ProjectDirs::from("com", "spartanjubilee", "Rustle")?;
+        let dir = dirs.cache_dir().join("images");
+        fs::create_dir_all(&dir).ok()?;
+
+        let usage = cached_files(&dir).iter().map(|(_, size, _)| size).sum();
+        Some(Arc::new(ImageCache {
+            dir,
+            max_bytes: AtomicU64::new(max_bytes),
+            usage: AtomicU64::new(usage),
+            memory: Mutex::new(HashMap::new()),
+        }))
+    }
+
+    pub fn usage(&self) -> u64 {
+        self.usage.load(Ordering::Relaxed)
+    }
+
+    pub fn set_max_bytes(&self, max_bytes: u64) {
+        self.max_bytes.store(max_bytes, Ordering::Relaxed);
+        self.evict();
+    }
+
+    pub fn clear(&self) {
+        for (path, _, _) in cached_files(&self.dir) {
+            let _ = fs::remove_file(path);
+        }
+        self.usage.store(0, Ordering::Relaxed);
+    }
+
+    fn path_for(&self, uri: &str) -> PathBuf {
+        let mut hasher = DefaultHasher::new();
+        uri.hash(&mut hasher);
+        self.dir.join(format!("{:016x}", hasher.finish()))
+    }
+
+    fn store(&self, path: &Path, bytes: &[u8]) {
+        if fs::write(path, bytes).is_ok() {
+            self.usage.fetch_add(bytes.len() as u64, Ordering::Relaxed);
+            self.evict();
+        }
+    }
+
+    // Drop the least recently used files until we're comfortably under the budget
+    fn evict(&self) {
+        let max_bytes = self.max_bytes.load(Ordering::Relaxed);
+        if self.usage() <= max_bytes {
+            return;
+        }
+
+        let mut files = cached_files(&self.dir);
+        files.sort_by_key(|(_, _, used)| *used);
+        let mut usage: u64 = files.iter().map(|(_, size, _)| size).sum();
+        let target = max_bytes / 10 * 9;
+        for (path, size, _) in files {
+            if usage <= target {
+                break;
+            }
+            if fs::remove_file(path).is_ok() {
+                usage = usage.saturating_sub(size);
+            }
+        }
+        self.usage.store(usage, Ordering::Relaxed);
+    }
+}
+
+// Every file in the cache with its size and last-use time (tracked through mtime)
+fn cached_files(dir: &Path) -> Vec<(PathBuf, u64, SystemTime)> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let metadata = entry.metadata().ok()?;
+            metadata.is_file().then(|| {
+                (entry.path(), metadata.len(), metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH))
+            })
+        })
+        .collect()
+}
+
+fn read_and_touch(path: &Path) -> Option<Vec<u8>> {
+    let bytes = fs::read(path).ok()?;
+    // Bump the modification time so eviction treats this file as recently used
+    if let Ok(file) = fs::File::options().write(true).open(path) {
+        let _ = file.set_modified(SystemTime::now());
+    }
+    Some(bytes)
+}
+
+// Wraps `Arc<ImageCache>` so the loader can hand clones of itself to worker threads
+pub struct DiskCacheLoader(pub Arc<ImageCache>);
+
+impl BytesLoader for DiskCacheLoader {
+    fn id(&self) -> &str {
+        ImageCache::ID
+    }
+
+    fn load(&self, ctx: &egui::Context, uri: &str) -> BytesLoadResult {
+        if !uri.starts_with("http://") && !uri.starts_with("https://") {
+            return Err(LoadError::NotSupported);
+        }
+
+        let cache = &self.0;
+        let mut memory = cache.memory.lock().unwrap();
+        if let Some(entry) = memory.get(uri).cloned() {
+            return match entry {
+                Poll::Ready(Ok(bytes)) => Ok(BytesPoll::Ready { size: None, bytes: Bytes::Shared(bytes), mime: None }),
+                Poll::Ready(Err(err)) => Err(LoadError::Loading(err)),
+                Poll::Pending => Ok(BytesPoll::Pending { size: None }),
+            };
+        }
+        memory.insert(uri.to_owned(), Poll::Pending);
+        drop(memory);
+
+        let uri = uri.to_owned();
+        let cache = cache.clone();
+        let ctx = ctx.clone();
+        thread::spawn(move || {
+            let started = Instant::now();
+            let path = cache.path_for(&uri);
+            if let Some(bytes) = read_and_touch(&path) {
+                let mut call = api_log::Call::new("GET", &uri, started.elapsed());
+                call.cached = Some(true);
+                api_log::log().record(call);
+                cache.memory.lock().unwrap().insert(uri, Poll::Ready(Ok(bytes.into())));
+                ctx.request_repaint();
+                return;
+            }
+
+            let response = ehttp::fetch_blocking(&ehttp::Request::get(&uri));
+            let mut call = api_log::Call::new("GET", &uri, started.elapsed());
+            call.cached = Some(false);
+            let result = match response {
+                Ok(response) if response.ok => {
+                    call.status = Some(response.status);
+                    cache.store(&path, &response.bytes);
+                    Ok(response.bytes.into())
+                }
+                Ok(response) => {
+                    call.status = Some(response.status);
+                    Err(format!("Failed to load {:?}: {} {}", uri, response.status, response.status_text))
+                }
+                Err(err) => {
+                    call.error = Some(err.clone());
+                    Err(format!("Failed to load {:?}: {}", uri, err))
+                }
+            };
+            api_log::log().record(call);
+            cache.memory.lock().unwrap().insert(uri, Poll::Ready(result));
+            ctx.request_repaint();
+        });
+
+        Ok(BytesPoll::Pending { size: None })
+    }
+
+    fn forget(&self, uri: &str) {
+        self.0.memory.lock().unwrap().remove(uri);
+    }
+
+    fn forget_all(&self) {
+        self.0.memory.lock().unwrap().clear();
+    }
+
+    fn byte_size(&self) -> usize {
+        self.0.memory.lock().unwrap().values()
+            .map(|entry| match entry {
+                Poll::Ready(Ok(bytes)) => bytes.len(),
+                Poll::Ready(Err(err)) => err.len(),
+                Poll::Pending => 0,
+            })
+            .sum()
+    }
+}
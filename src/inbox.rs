@@ -0,0 +1,303 @@
+// Unread inbox items, and telling the reader about new ones while Rustle is in the background.
+//
+// `RedditApp::check_inbox` polls /message/unread every few minutes. The header shows how many
+// items are waiting. With the `notifications` feature, anything that turned up since the last
+// check raises a desktop notification, and a tray icon carries the unread count and brings the
+// window back when clicked. The first check after signing in only takes note of what's there,
+// so starting Rustle doesn't replay the whole backlog as notifications.
+#[cfg(feature = "notifications")]
+use eframe::egui;
+use serde::Deserialize;
+use std::collections::HashSet;
+
+const PREVIEW_CHARS: usize = 160;  // Of each item's text in a notification
+#[cfg(feature = "notifications")]
+const NOTIFY_AT_MOST: usize = 3;   // Beyond this, one notification sums the batch up instead
+
+#[derive(Debug, Deserialize)]
+pub struct InboxListing {
+    pub data: InboxData,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct InboxData {
+    pub children: Vec<InboxChild>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct InboxChild {
+    pub data: Message,
+}
+
+// A private message (t4), or a comment reply or username mention (t1)
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Message {
+    pub name: String,
+    pub author: Option<String>,  // None for messages from Reddit itself
+    pub subject: String,  // "comment reply", "post reply" and "username mention" for comments
+    pub body: String,
+    pub context: String,  // Permalink of a comment; empty for messages
+    pub subreddit: Option<String>,
+    pub was_comment: bool,
+}
+
+impl Message {
+    // e.g. "u/ferris replied to your comment"
+    pub fn summary(&self) -> String {
+        let from = match (&self.author, &self.subreddit) {
+            (Some(author), _) => format!("u/{}", author),
+            (None, Some(subreddit)) => format!("r/{}", subreddit),
+            (None, None) => "Reddit".to_string(),
+        };
+        if !self.was_comment {
+            return format!("Message from {}", from);
+        }
+        match self.subject.as_str() {
+            "comment reply" => format!("{} replied to your comment", from),
+            "post reply" => format!("{} replied to your post", from),
+            "username mention" => format!("{} mentioned you", from),
+            subject => format!("{}: {}", from, subject),
+        }
+    }
+
+    // The start of the text on one line, with a message's subject in front
+    pub fn preview(&self) -> String {
+        let text = if self.was_comment { self.body.clone() } else { format!("{}: {}", self.subject, self.body) };
+        let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+        match text.char_indices().nth(PREVIEW_CHARS) {
+            Some((end, _)) => format!("{}…", text[..end].trim_end()),
+            None => text,
+        }
+    }
+
+    pub fn link(&self) -> String {
+        if self.context.is_empty() {
+            format!("https://www.reddit.com/message/messages/{}", self.name.trim_start_matches("t4_"))
+        } else {
+            format!("https://www.reddit.com{}", self.context)
+        }
+    }
+}
+
+// For the header and the tray, e.g. "3 unread messages"
+pub fn describe(unread: usize) -> String {
+    match unread {
+        0 => "No unread messages".to_string(),
+        1 => "1 unread message".to_string(),
+        count => format!("{} unread messages", count),
+    }
+}
+
+#[derive(Default)]
+pub struct Inbox {
+    pub unread: Vec<Message>,  // Newest first, as of the last check
+    seen: HashSet<String>,     // Names already counted as unread, so they aren't announced twice
+    checked: bool,
+}
+
+impl Inbox {
+    // Takes the latest unread items and hands back the ones that are new since the last check
+    pub fn update(&mut self, unread: Vec<Message>) -> Vec<Message> {
+        let fresh = if self.checked {
+            unread.iter().filter(|message| !self.seen.contains(&message.name)).cloned().collect()
+        } else {
+            Vec::new()
+        };
+        self.seen.extend(unread.iter().map(|message| message.name.clone()));
+        self.checked = true;
+        self.unread = unread;
+        fresh
+    }
+}
+
+// Best effort: without a notification server the items still show in the header and tray
+#[cfg(feature = "notifications")]
+pub fn notify(fresh: &[Message]) {
+    let show = |summary: &str, body: &str| {
+        let _ = notify_rust::Notification::new()
+            .appname(crate::APP_NAME)
+            .summary(summary)
+            .body(body)
+            .show();
+    };
+    if fresh.len() > NOTIFY_AT_MOST {
+        show(&format!("{} new inbox items", fresh.len()), &fresh[0].summary());
+        return;
+    }
+    for message in fresh {
+        show(&message.summary(), &message.preview());
+    }
+}
+
+// Un-minimizes and raises the main window; used from the tray's own thread
+#[cfg(feature = "notifications")]
+fn show_window(ctx: &egui::Context) {
+    ctx.send_viewport_cmd(egui::ViewportCommand::Visible(true));
+    ctx.send_viewport_cmd(egui::ViewportCommand::Minimized(false));
+    ctx.send_viewport_cmd(egui::ViewportCommand::Focus);
+    ctx.request_repaint();
+}
+
+// The app icon, scaled down for the tray, as RGBA
+#[cfg(feature = "notifications")]
+fn tray_icon() -> Option<(Vec<u8>, u32, u32)> {
+    const SIZE: u32 = 64;
+    let image = image::load_from_memory(include_bytes!("../assets/icon.png")).ok()?.into_rgba8();
+    let image = image::imageops::resize(&image, SIZE, SIZE, image::imageops::FilterType::Lanczos3);
+    Some((image.into_raw(), SIZE, SIZE))
+}
+
+// A StatusNotifierItem over D-Bus, which needs no GTK
+#[cfg(all(feature = "notifications", target_os = "linux"))]
+pub struct Tray {
+    runtime: tokio::runtime::Runtime,  // Runs the D-Bus service
+    handle: ksni::Handle<TrayItem>,
+    unread: usize,
+}
+
+#[cfg(all(feature = "notifications", target_os = "linux"))]
+pub struct TrayItem {
+    ctx: egui::Context,
+    icon: Option<ksni::Icon>,
+    unread: usize,
+}
+
+#[cfg(all(feature = "notifications", target_os = "linux"))]
+impl ksni::Tray for TrayItem {
+    fn id(&self) -> String {
+        crate::APP_NAME.to_lowercase()
+    }
+
+    fn title(&self) -> String {
+        crate::APP_NAME.to_string()
+    }
+
+    fn icon_pixmap(&self) -> Vec<ksni::Icon> {
+        self.icon.iter().cloned().collect()
+    }
+
+    fn tool_tip(&self) -> ksni::ToolTip {
+        ksni::ToolTip { title: crate::APP_NAME.to_string(), description: describe(self.unread), ..Default::default() }
+    }
+
+    fn activate(&mut self, _x: i32, _y: i32) {
+        show_window(&self.ctx);
+    }
+
+    // Some hosts only ever open the menu
+    fn menu(&self) -> Vec<ksni::MenuItem<Self>> {
+        vec![ksni::menu::StandardItem {
+            label: format!("Show {}", crate::APP_NAME),
+            activate: Box::new(|tray: &mut Self| show_window(&tray.ctx)),
+            ..Default::default()
+        }.into()]
+    }
+}
+
+#[cfg(all(feature = "notifications", target_os = "linux"))]
+impl Tray {
+    pub fn new(ctx: &egui::Context) -> anyhow::Result<Self> {
+        use ksni::TrayMethods;
+        // SNI pixmaps are ARGB in network byte order
+        let icon = tray_icon().map(|(rgba, width, height)| ksni::Icon {
+            width: width as i32,
+            height: height as i32,
+            data: rgba.chunks_exact(4).flat_map(|pixel| [pixel[3], pixel[0], pixel[1], pixel[2]]).collect(),
+        });
+        let runtime = tokio::runtime::Runtime::new()?;
+        let handle = runtime.block_on(TrayItem { ctx: ctx.clone(), icon, unread: 0 }.spawn())?;
+        Ok(Tray { runtime, handle, unread: 0 })
+    }
+
+    pub fn set_unread(&mut self, unread: usize) {
+        if unread != self.unread {
+            self.unread = unread;
+            self.runtime.block_on(self.handle.update(|tray| tray.unread = unread));
+        }
+    }
+}
+
+// Windows and macOS; created on the main thread once the event loop is running
+#[cfg(all(feature = "notifications", not(target_os = "linux")))]
+pub struct Tray {
+    icon: tray_icon::TrayIcon,
+    unread: usize,
+}
+
+#[cfg(all(feature = "notifications", not(target_os = "linux")))]
+impl Tray {
+    pub fn new(ctx: &egui::Context) -> anyhow::Result<Self> {
+        use anyhow::Context;
+        let (rgba, width, height) = tray_icon().context("Failed to load the tray icon")?;
+        let icon = tray_icon::TrayIconBuilder::new()
+            .with_icon(tray_icon::Icon::from_rgba(rgba, width, height)?)
+            .with_tooltip(format!("{}: {}", crate::APP_NAME, describe(0)))
+            .build()?;
+        let ctx = ctx.clone();
+        tray_icon::TrayIconEvent::set_event_handler(Some(move |event| {
+            if let tray_icon::TrayIconEvent::Click { button: tray_icon::MouseButton::Left, button_state: tray_icon::MouseButtonState::Up, .. } = event {
+                show_window(&ctx);
+            }
+        }));
+        Ok(Tray { icon, unread: 0 })
+    }
+
+    pub fn set_unread(&mut self, unread: usize) {
+        if unread != self.unread {
+            self.unread = unread;
+            let _ = self.icon.set_tooltip(Some(format!("{}: {}", crate::APP_NAME, describe(unread))));
+            // Shown beside the icon in the macOS menu bar
+            self.icon.set_title((unread > 0).then(|| unread.to_string()));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const UNREAD: &str = r#"{"kind": "Listing", "data": {"children": [
+        {"kind": "t1", "data": {"name": "t1_reply", "author": "ferris", "subject": "comment reply",
+            "body": "Agreed,\n\nbut   see the docs", "context": "/r/rust/comments/abc/title/reply/?context=3",
+            "subreddit": "rust", "was_comment": true}},
+        {"kind": "t4", "data": {"name": "t4_dm", "author": null, "subject": "Welcome", "body": "Hello there",
+            "context": "", "subreddit": null, "was_comment": false}}
+    ]}}"#;
+
+    fn unread() -> Vec<Message> {
+        serde_json::from_str::<InboxListing>(UNREAD).unwrap().data.children.into_iter().map(|child| child.data).collect()
+    }
+
+    #[test]
+    fn describes_inbox_items() {
+        let unread = unread();
+        assert_eq!(unread[0].summary(), "u/ferris replied to your comment");
+        assert_eq!(unread[0].preview(), "Agreed, but see the docs");
+        assert_eq!(unread[0].link(), "https://www.reddit.com/r/rust/comments/abc/title/reply/?context=3");
+        assert_eq!(unread[1].summary(), "Message from Reddit");
+        assert_eq!(unread[1].preview(), "Welcome: Hello there");
+        assert_eq!(unread[1].link(), "https://www.reddit.com/message/messages/dm");
+
+        let long = Message { body: "é".repeat(PREVIEW_CHARS + 1), was_comment: true, ..Default::default() };
+        assert_eq!(long.preview().chars().count(), PREVIEW_CHARS + 1);
+        assert!(long.preview().ends_with('…'));
+    }
+
+    #[test]
+    fn announces_only_new_items() {
+        let mut inbox = Inbox::default();
+        let mut unread = unread();
+        // The first check just takes note of the backlog
+        assert!(inbox.update(unread.clone()).is_empty());
+        assert_eq!(inbox.unread.len(), 2);
+        assert!(inbox.update(unread.clone()).is_empty());
+
+        unread.insert(0, Message { name: "t1_new".to_string(), ..Default::default() });
+        let fresh = inbox.update(unread);
+        assert_eq!(fresh.len(), 1);
+        assert_eq!(fresh[0].name, "t1_new");
+        assert!(inbox.update(Vec::new()).is_empty());
+        assert!(inbox.unread.is_empty());
+    }
+}
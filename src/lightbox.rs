@@ -0,0 +1,94 @@
+// Full-window media viewer that steps through a queue of posts.
+//
+// The feed's "open all media" button fills the queue with every image, GIF and video post
+// loaded so far, so an image-heavy subreddit can be paged through without going back to the
+// feed between items.
+use crate::Post;
+
+pub struct Lightbox {
+    queue: Vec<Post>,
+    index: usize,
+}
+
+impl Lightbox {
+    // None when there's nothing to show
+    pub fn new(queue: Vec<Post>) -> Option<Self> {
+        if queue.is_empty() {
+            return None;
+        }
+        Some(Lightbox { queue, index: 0 })
+    }
+
+    pub fn current(&self) -> &Post {
+        &self.queue[self.index]
+    }
+
+    // The item after the current one, for loading its image ahead of time
+    pub fn upcoming(&self) -> Option<&Post> {
+        self.queue.get(self.index + 1)
+    }
+
+    pub fn has_next(&self) -> bool {
+        self.index + 1 < self.queue.len()
+    }
+
+    pub fn has_previous(&self) -> bool {
+        self.index > 0
+    }
+
+    pub fn next(&mut self) {
+        if self.has_next() {
+            self.index += 1;
+        }
+    }
+
+    pub fn previous(&mut self) {
+        if self.has_previous() {
+            self.index -= 1;
+        }
+    }
+
+    // e.g. "3 / 12"
+    pub fn position(&self) -> String {
+        format!("{} / {}", self.index + 1, self.queue.len())
+    }
+}
+
+// Posts worth a place in the queue: images, GIFs and videos, but not links or text
+pub fn is_media(post: &Post) -> bool {
+    let hinted = matches!(post.post_hint.as_deref(), Some("image") | Some("hosted:video") | Some("rich:video"));
+    (hinted || post.reddit_video().is_some() || post.animated_preview().is_some()) && post.preview.is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn steps_through_the_queue() {
+        let posts: Vec<Post> = serde_json::from_str(r#"[{"id": "a"}, {"id": "b"}, {"id": "c"}]"#).unwrap();
+        assert!(Lightbox::new(Vec::new()).is_none());
+
+        let mut lightbox = Lightbox::new(posts).unwrap();
+        assert!(!lightbox.has_previous());
+        lightbox.previous();
+        assert_eq!(lightbox.current().id, "a");
+        lightbox.next();
+        lightbox.next();
+        lightbox.next();
+        assert_eq!(lightbox.current().id, "c");
+        assert_eq!(lightbox.position(), "3 / 3");
+        assert!(lightbox.upcoming().is_none());
+    }
+
+    #[test]
+    fn queues_only_media_posts() {
+        let preview = r#"{"images": [{"source": {"url": "https://preview.redd.it/a.jpg", "width": 640, "height": 480}, "resolutions": []}]}"#;
+        let image: Post = serde_json::from_str(&format!(r#"{{"post_hint": "image", "preview": {}}}"#, preview)).unwrap();
+        let link: Post = serde_json::from_str(&format!(r#"{{"post_hint": "link", "preview": {}}}"#, preview)).unwrap();
+        let text: Post = serde_json::from_str(r#"{"is_self": true, "selftext": "hi"}"#).unwrap();
+        assert!(is_media(&image));
+        assert!(!is_media(&link));
+        assert!(!is_media(&text));
+    }
+}
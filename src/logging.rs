@@ -0,0 +1,242 @@
+// Structured logging, and the debug console that tails it.
+//
+// The client and background tasks emit `tracing` events: requests with their status and timing,
+// token refreshes, rate limit changes and the errors the reader is shown. They go to a log file
+// rotated daily, with a week of files kept, and the most recent ones are also held in memory for
+// the console (Ctrl+Shift+D) and bug report bundles.
+use eframe::egui;
+use std::{
+    collections::VecDeque,
+    fmt::Debug,
+    path::PathBuf,
+    sync::{Mutex, OnceLock},
+    time::{Duration, Instant},
+};
+use tracing::{field::{Field, Visit}, Level};
+use tracing_appender::{non_blocking::WorkerGuard, rolling::{Builder, Rotation}};
+use tracing_subscriber::{filter::Targets, layer::{Context, SubscriberExt}, Layer};
+
+const CAPACITY: usize = 1000;  // Events kept for the console
+const LOG_FILES_KEPT: usize = 7;
+const LEVELS: [Level; 4] = [Level::ERROR, Level::WARN, Level::INFO, Level::DEBUG];
+
+static RECENT: Recent = Recent::new();
+static STARTED: OnceLock<Instant> = OnceLock::new();
+
+#[derive(Debug, Clone)]
+pub struct Entry {
+    pub at: Instant,
+    pub level: Level,
+    pub target: String,   // Module the event came from, e.g. "rate_limit"
+    pub message: String,  // With the event's fields after it as key=value
+}
+
+impl Entry {
+    // e.g. "   12.345s  WARN rate_limit: Throttling requests wait_ms=850"
+    pub fn line(&self) -> String {
+        let since = self.at.saturating_duration_since(*STARTED.get_or_init(Instant::now));
+        format!("{:>9.3}s {:>5} {}: {}", since.as_secs_f64(), self.level, self.target, self.message)
+    }
+}
+
+pub struct Recent {
+    entries: Mutex<VecDeque<Entry>>,
+}
+
+impl Recent {
+    const fn new() -> Self {
+        Recent { entries: Mutex::new(VecDeque::new()) }
+    }
+
+    fn push(&self, entry: Entry) {
+        let mut entries = self.entries.lock().unwrap();
+        entries.push_back(entry);
+        if entries.len() > CAPACITY {
+            entries.pop_front();
+        }
+    }
+
+    // Oldest first
+    pub fn entries(&self) -> Vec<Entry> {
+        self.entries.lock().unwrap().iter().cloned().collect()
+    }
+
+    pub fn clear(&self) {
+        self.entries.lock().unwrap().clear();
+    }
+}
+
+pub fn recent() -> &'static Recent {
+    &RECENT
+}
+
+pub fn dir() -> Option<PathBuf> {
+    let dirs = directories::ProjectDirs::from("com", "spartanjubilee", "Rustle")?;
+    Some(dirs.data_dir().join("logs"))
+}
+
+// Installs the subscriber for the whole process. The file is written from a background thread
+// that flushes when the returned guard is dropped, so main holds on to it until exit.
+pub fn init() -> Option<WorkerGuard> {
+    STARTED.get_or_init(Instant::now);
+    let file = dir().and_then(|dir| {
+        Builder::new()
+            .rotation(Rotation::DAILY)
+            .filename_prefix("rustle")
+            .filename_suffix("log")
+            .max_log_files(LOG_FILES_KEPT)
+            .build(dir)
+            .ok()
+    });
+    let (writer, guard) = file.map(tracing_appender::non_blocking).unzip();
+    // Debug events from Rustle itself; only warnings from the libraries underneath
+    let filter = Targets::new()
+        .with_target(env!("CARGO_CRATE_NAME"), Level::DEBUG)
+        .with_default(Level::WARN);
+    let subscriber = tracing_subscriber::registry()
+        .with(ConsoleLayer(&RECENT))
+        .with(writer.map(|writer| tracing_subscriber::fmt::layer().with_writer(writer)))
+        .with(filter);
+    tracing::subscriber::set_global_default(subscriber).ok()?;
+    guard
+}
+
+// Keeps each event in `Recent` for the console
+pub struct ConsoleLayer(&'static Recent);
+
+impl<S: tracing::Subscriber> Layer<S> for ConsoleLayer {
+    fn on_event(&self, event: &tracing::Event<'_>, _ctx: Context<'_, S>) {
+        let mut fields = Fields::default();
+        event.record(&mut fields);
+        let metadata = event.metadata();
+        let target = metadata.target().strip_prefix(concat!(env!("CARGO_CRATE_NAME"), "::")).unwrap_or(metadata.target());
+        self.0.push(Entry {
+            at: Instant::now(),
+            level: *metadata.level(),
+            target: if target == env!("CARGO_CRATE_NAME") { "app".to_string() } else { target.to_string() },
+            message: fields.message + &fields.rest,
+        });
+    }
+}
+
+#[derive(Default)]
+struct Fields {
+    message: String,
+    rest: String,  // " key=value" for each other field
+}
+
+impl Visit for Fields {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.message = value.to_string();
+        } else {
+            self.rest.push_str(&format!(" {}={}", field.name(), value));
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        if field.name() == "message" {
+            self.message = format!("{:?}", value);
+        } else {
+            self.rest.push_str(&format!(" {}={:?}", field.name(), value));
+        }
+    }
+}
+
+// Debug window tailing the log, newest at the bottom
+pub struct Console {
+    level: Level,   // Least severe level shown
+    search: String,
+}
+
+impl Default for Console {
+    fn default() -> Self {
+        Console { level: Level::DEBUG, search: String::new() }
+    }
+}
+
+impl Console {
+    // False once the window is closed
+    pub fn show(&mut self, ctx: &egui::Context) -> bool {
+        let mut open = true;
+        egui::Window::new("Debug console")
+            .open(&mut open)
+            .default_size([760.0, 420.0])
+            .show(ctx, |ui| {
+                let entries: Vec<Entry> = RECENT.entries().into_iter()
+                    .filter(|entry| self.shows(entry))
+                    .collect();
+                ui.horizontal(|ui| {
+                    egui::ComboBox::from_id_source("console-level")
+                        .selected_text(self.level.as_str())
+                        .show_ui(ui, |ui| {
+                            for level in LEVELS {
+                                ui.selectable_value(&mut self.level, level, level.as_str());
+                            }
+                        });
+                    ui.add(egui::TextEdit::singleline(&mut self.search).hint_text("Search").desired_width(180.0));
+                    if ui.button("Copy").on_hover_text("Copy the events shown").clicked() {
+                        let text: String = entries.iter().map(|entry| entry.line() + "\n").collect();
+                        ui.output_mut(|output| output.copied_text = text);
+                    }
+                    if ui.button("Clear").clicked() {
+                        RECENT.clear();
+                    }
+                    if let Some(dir) = dir() {
+                        ui.label(egui::RichText::new(format!("Log files: {}", dir.display())).weak());
+                    }
+                });
+                ui.separator();
+
+                if entries.is_empty() {
+                    ui.label(egui::RichText::new("Nothing logged yet").weak());
+                    return;
+                }
+                egui::ScrollArea::vertical().auto_shrink([false, false]).stick_to_bottom(true).show(ui, |ui| {
+                    for entry in &entries {
+                        let text = egui::RichText::new(entry.line()).monospace();
+                        let text = match entry.level {
+                            Level::ERROR => text.color(ui.visuals().error_fg_color),
+                            Level::WARN => text.color(ui.visuals().warn_fg_color),
+                            Level::DEBUG | Level::TRACE => text.weak(),
+                            _ => text,
+                        };
+                        ui.add(egui::Label::new(text).wrap(false));
+                    }
+                });
+            });
+        ctx.request_repaint_after(Duration::from_secs(1));
+        open
+    }
+
+    fn shows(&self, entry: &Entry) -> bool {
+        // Levels order from least to most verbose
+        entry.level <= self.level
+            && (self.search.is_empty() || entry.line().to_lowercase().contains(&self.search.to_lowercase()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_events_with_their_fields() {
+        static LOG: Recent = Recent::new();
+        let subscriber = tracing_subscriber::registry().with(ConsoleLayer(&LOG));
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::debug!(remaining = 12, "Rate limit updated");
+            tracing::warn!(url = "https://oauth.reddit.com/hot", "Request failed");
+        });
+        let entries = LOG.entries();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].message, "Rate limit updated remaining=12");
+        assert_eq!(entries[0].target, "logging::tests");
+        assert_eq!(entries[1].message, "Request failed url=https://oauth.reddit.com/hot");
+        assert!(entries[1].line().ends_with(" WARN logging::tests: Request failed url=https://oauth.reddit.com/hot"));
+
+        let console = Console { level: Level::INFO, search: "FAILED".to_string() };
+        assert!(!console.shows(&entries[0]));
+        assert!(console.shows(&entries[1]));
+    }
+}
@@ -5,20 +5,126 @@ use egui_extras::install_image_loaders;
 use reqwest::{Client, header};
 use serde::{Deserialize, Serialize};
 use std::{
+    collections::{HashMap, HashSet},
     sync::{Arc, Mutex},
     thread,
+    time::{Duration, Instant},
 };
 use keyring::Entry;
+use error::{AuthFailure, RustleError};
+use filters::{FilterRule, Filters, MediaKind};
+
+mod animation;
+mod api_log;
+mod archive;
+mod bug_report;
+mod comments;
+mod demo;
+mod digest;
+mod discover;
+mod error;
+mod export;
+mod filters;
+mod focus;
+mod heat;
+mod hints;
+mod history;
+mod html;
+mod image_cache;
+mod inbox;
+mod lightbox;
+mod logging;
+mod markdown;
+mod moderation;
+mod oauth;
+mod onboarding;
+mod open_with;
+mod palette;
+mod profile;
+mod rate_limit;
+mod reader;
+mod replay;
+mod sidebar;
+mod spellcheck;
+#[cfg(test)]
+mod client_tests;
+mod store;
+mod submit;
+mod subreddit;
+mod theme;
+mod translate;
+mod usage;
+#[cfg(feature = "video")]
+mod video;
+mod watchdog;
 
 // Constants for application
 const APP_NAME: &str = "Rustle";
 const APP_VERSION: &str = "v0.1.0";
 const APP_USER_AGENT: &str = concat!("Rustle:", env!("CARGO_PKG_VERSION"), " (by /u/SpartanJubilee)");
+const FEED_CACHE_KEY: &str = "cached_feed";
+// Starts the message of every RustleError::Auth, the failures that get a recovery screen
+const AUTH_ERROR: &str = "Authentication error";
+const OAUTH_HOST: &str = "https://oauth.reddit.com";
+const WWW_HOST: &str = "https://www.reddit.com";
+// Tokens are renewed this long before Reddit says they expire
+const TOKEN_RENEW_MARGIN: Duration = Duration::from_secs(60);
+const COMMENT_INDENT: f32 = 16.0;
+const ESTIMATED_COMMENT_HEIGHT: f32 = 60.0;
+const MINIMAP_WIDTH: f32 = 12.0;
+const MINIMAP_BAND: f32 = 3.0;  // Height of each shaded slice of the minimap
+// How often to try reconnecting while showing offline content
+const SYNC_RETRY: Duration = Duration::from_secs(30);
+// How often scheduled posts are checked for being due
+const SCHEDULE_CHECK: Duration = Duration::from_secs(30);
+// Pause in typing before the post composer's draft is written to disk
+const AUTOSAVE_DELAY: Duration = Duration::from_secs(1);
+// How often the header's karma and unread count are refreshed
+const ACCOUNT_REFRESH: Duration = Duration::from_secs(5 * 60);
+// How long posts marked read wait in memory before going to the store together
+const READ_SAVE_DELAY: Duration = Duration::from_secs(2);
+// How often local midnight is looked up again for the feed's day headers
+const DAY_CHECK: Duration = Duration::from_secs(60);
+// How often a feed kept from the last session is checked for having gone stale
+const STALE_CHECK: Duration = Duration::from_secs(60);
 
 // API response models
 #[derive(Debug, Deserialize)]
 struct AuthResponse {
     access_token: String,
+    #[serde(default)]
+    expires_in: u64,  // Seconds; Reddit's tokens last an hour
+    #[serde(default)]
+    refresh_token: Option<String>,  // Only for the browser sign-in's authorization code grant
+}
+
+// The signed-in account, from /api/v1/me
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+struct Me {
+    name: String,
+    icon_img: String,
+    snoovatar_img: String,  // Set when the account has an avatar from the builder
+    link_karma: i64,
+    comment_karma: i64,
+    inbox_count: usize,     // Unread messages
+}
+
+impl Me {
+    fn avatar_url(&self) -> Option<&str> {
+        [&self.snoovatar_img, &self.icon_img].into_iter()
+            .find(|url| url.starts_with("http"))
+            .map(String::as_str)
+    }
+
+    // e.g. "1.2k · 340", post karma then comment karma
+    fn karma(&self) -> String {
+        let compact = |karma: i64| {
+            let count = subreddit::compact_count(karma.unsigned_abs());
+            if karma < 0 { format!("-{}", count) } else { count }
+        };
+        format!("{} · {}", compact(self.link_karma), compact(self.comment_karma))
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -29,40 +135,421 @@ struct RedditListing {
 #[derive(Debug, Deserialize)]
 struct ListingData {
     children: Vec<PostChild>,
+    #[serde(default, deserialize_with = "deserialize_cursor")]
     after: Option<String>,
 }
 
+// Reddit sometimes ends a listing with an empty cursor rather than null; both mean no more pages
+fn deserialize_cursor<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    Ok(Option::<String>::deserialize(deserializer)?.filter(|after| !after.is_empty()))
+}
+
 #[derive(Debug, Deserialize)]
 struct PostChild {
     data: Post,
 }
 
-#[derive(Debug, Deserialize)]
+// Mirrors Reddit's link ("t3") object. Every field falls back to its default so
+// keys missing from the API response don't fail the whole listing; those Reddit
+// sends as null are Options.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+#[allow(dead_code)]  // Not every field is surfaced in the UI yet
 struct Post {
+    id: String,
+    name: String,  // Fullname, e.g. "t3_abc123"
+    #[serde(with = "html")]
     title: String,
     author: String,
     subreddit: String,
-    score: i32,
+    permalink: String,
+    #[serde(with = "html")]
     url: String,
+    domain: String,  // "i.redd.it", "github.com", or "self.{subreddit}" for text posts
+    post_hint: Option<String>,  // Reddit's guess at the content: "image", "link", "hosted:video", ...
+    crosspost_parent_list: Vec<Post>,  // The original post, when this one is a crosspost
+    score: i32,
+    upvote_ratio: f32,  // Share of votes that are upvotes, 0.0-1.0
+    num_comments: u32,
+    total_awards_received: u32,
+    all_awardings: Vec<Award>,  // One entry per award type, most given first
+    created_utc: f64,
+    #[serde(with = "html")]
+    selftext: String,
+    is_self: bool,
+    is_video: bool,
+    over_18: bool,
+    spoiler: bool,
+    stickied: bool,
+    locked: bool,
+    distinguished: Option<String>,  // "moderator" when a mod posted it as one
+    removed_by_category: Option<String>,  // Why the content is gone ("moderator", "deleted", ...)
+    #[serde(with = "html::option")]
+    link_flair_text: Option<String>,
+    link_flair_background_color: Option<String>,  // "#rrggbb", or empty for the subreddit default
+    link_flair_text_color: Option<String>,        // "dark" or "light"
+    #[serde(with = "html::option")]
+    author_flair_text: Option<String>,
+    author_flair_background_color: Option<String>,
+    author_flair_text_color: Option<String>,
+    likes: Option<bool>,  // Some(true) upvoted, Some(false) downvoted, None no vote
+    saved: bool,
+    hidden: bool,
+    visited: bool,  // Opened on any device; only reported for accounts with history sync (Premium)
+    clicked: bool,
+    #[serde(with = "html")]
     thumbnail: String,
     preview: Option<Preview>,
+    media: Option<Media>,
+    secure_media: Option<Media>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 struct Preview {
     images: Vec<Image>,
+    #[serde(default)]
+    reddit_video_preview: Option<RedditVideo>,  // Reddit's MP4 rendition of GIF-like links
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 struct Image {
     source: ImageSource,
     resolutions: Vec<ImageSource>,
+    #[serde(default)]
+    variants: Variants,
 }
 
-#[derive(Debug, Deserialize)]
+// Alternate renditions Reddit generates for animated and NSFW previews
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct Variants {
+    gif: Option<ImageVariant>,
+    mp4: Option<ImageVariant>,
+    nsfw: Option<ImageVariant>,  // Blurred
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ImageVariant {
+    source: ImageSource,
+    #[serde(default)]
+    resolutions: Vec<ImageSource>,
+}
+
+// An award type given to a post, and how many times
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct Award {
+    name: String,
+    count: u32,
+    #[serde(with = "html")]
+    icon_url: String,
+    resized_icons: Vec<ImageSource>,
+}
+
+impl Award {
+    // Smallest rendition that's still crisp at badge size
+    fn icon(&self) -> &str {
+        self.resized_icons.iter()
+            .filter(|icon| icon.width >= 32)
+            .min_by_key(|icon| icon.width)
+            .map_or(&self.icon_url, |icon| &icon.url)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 struct ImageSource {
+    #[serde(with = "html")]
     url: String,
+    #[serde(default)]
+    width: u32,
+    height: u32,
+}
+
+impl Post {
+    // Find the preview resolution closest to our target size (100px), falling back to the thumbnail
+    fn thumbnail_url(&self) -> String {
+        self.preview.as_ref()
+            .and_then(|preview| preview.images.first())
+            .map(|image| closest_to_thumbnail(&image.source, &image.resolutions).url.clone())
+            .unwrap_or_else(|| self.thumbnail.clone())
+    }
+
+    // The one thumbnail that may be fetched for this post. NSFW posts that are hidden get none
+    // and covered ones only Reddit's blurred rendition, so their images never hit the network.
+    fn thumbnail_to_load(&self, settings: &Settings, revealed: bool) -> Option<String> {
+        if self.over_18 && !settings.show_nsfw {
+            return None;
+        }
+        if self.over_18 && settings.blur_nsfw && !revealed {
+            return self.blurred_thumbnail_url();
+        }
+        Some(self.thumbnail_url()).filter(|url| url.starts_with("http"))
+    }
+
+    // Reddit's pre-blurred rendition of an NSFW preview, if it made one
+    fn blurred_thumbnail_url(&self) -> Option<String> {
+        let variant = self.preview.as_ref()?.images.first()?.variants.nsfw.as_ref()?;
+        Some(closest_to_thumbnail(&variant.source, &variant.resolutions).url.clone())
+    }
+
+    // Reddit keeps the post around but blanks the author once the account is gone
+    fn author_deleted(&self) -> bool {
+        is_deleted_marker(&self.author)
+    }
+
+    // Human readable reason the post's content is gone, if it is
+    fn removal_notice(&self) -> Option<&'static str> {
+        match self.removed_by_category.as_deref() {
+            Some("deleted") | Some("author") => Some("deleted by author"),
+            Some("moderator") | Some("automod_filtered") => Some("removed by moderators"),
+            Some("reddit") | Some("anti_evil_ops") | Some("community_ops") | Some("copyright_takedown") => Some("removed by Reddit"),
+            Some(_) => Some("removed"),
+            None if self.selftext == "[deleted]" => Some("deleted by author"),
+            None if self.selftext == "[removed]" => Some("removed"),
+            None => None,
+        }
+    }
+
+    // MP4 rendition of an animated preview, which is a fraction of the size of the GIF
+    fn animated_preview(&self) -> Option<&ImageSource> {
+        self.preview.as_ref()?
+            .images.first()?
+            .variants.mp4.as_ref()
+            .map(|variant| &variant.source)
+    }
+
+    // What plays inline for a GIF post: Reddit's GIF rendition at about card size, or else the link
+    // itself when it's a GIF or WebP file. Both are decoded by content, so either may be animated WebP.
+    fn animation(&self) -> Option<(&str, u32, u32)> {
+        let image = self.preview.as_ref().and_then(|preview| preview.images.first());
+        if let Some(variant) = image.and_then(|image| image.variants.gif.as_ref()) {
+            let rendition = variant.resolutions.iter()
+                .filter(|resolution| resolution.width >= 640)
+                .min_by_key(|resolution| resolution.width)
+                .unwrap_or(&variant.source);
+            return Some((&rendition.url, rendition.width, rendition.height));
+        }
+        let path = self.url.split(['?', '#']).next()?.to_lowercase();
+        if !path.ends_with(".gif") && !path.ends_with(".webp") {
+            return None;
+        }
+        let (width, height) = image.map_or((0, 0), |image| (image.source.width, image.source.height));
+        Some((&self.url, width, height))
+    }
+
+    // Crossposts carry no media of their own, so fall back to the original's
+    fn reddit_video(&self) -> Option<&RedditVideo> {
+        self.secure_media.as_ref()
+            .or(self.media.as_ref())
+            .and_then(|media| media.reddit_video.as_ref())
+            .or_else(|| self.crosspost_parent()?.reddit_video())
+    }
+
+    fn crosspost_parent(&self) -> Option<&Post> {
+        self.crosspost_parent_list.first()
+    }
+
+    // Muted clip to loop while the pointer rests on the thumbnail: the GIF's MP4 variant, the
+    // preview rendition Reddit made of a GIF link, or else the video itself
+    #[cfg_attr(not(feature = "video"), allow(dead_code))]
+    fn hover_clip(&self) -> Option<(&str, u32, u32)> {
+        if let Some(animation) = self.animated_preview() {
+            return Some((&animation.url, animation.width, animation.height));
+        }
+        self.preview.as_ref()
+            .and_then(|preview| preview.reddit_video_preview.as_ref())
+            .or_else(|| self.reddit_video())
+            .map(|video| (video.fallback_url.as_str(), video.width, video.height))
+    }
+
+    // Videos get an inline player when built with video support
+    fn has_player(&self) -> bool {
+        cfg!(feature = "video") && self.reddit_video().is_some()
+    }
+
+    // Largest rendition of the preview image
+    fn full_preview(&self) -> Option<&ImageSource> {
+        self.preview.as_ref()?.images.first().map(|image| &image.source)
+    }
+
+    // Full-size preview for an expanded card; posts with a player show that instead, and GIFs
+    // play in its place
+    fn expanded_image(&self) -> Option<&ImageSource> {
+        if self.has_player() {
+            return None;
+        }
+        self.full_preview()
+    }
+
+    // Self text for an expanded card, unless it's gone
+    fn expanded_text(&self) -> Option<&str> {
+        Some(self.selftext.as_str()).filter(|text| !text.trim().is_empty() && self.removal_notice().is_none())
+    }
+
+    fn can_expand(&self) -> bool {
+        self.expanded_image().is_some() || self.expanded_text().is_some() || self.animation().is_some()
+    }
+
+    // Reflect a vote locally the way Reddit will count it
+    fn apply_vote(&mut self, likes: Option<bool>) {
+        let weight = |likes: Option<bool>| match likes {
+            Some(true) => 1,
+            Some(false) => -1,
+            None => 0,
+        };
+        self.score += weight(likes) - weight(self.likes);
+        self.likes = likes;
+    }
+
+    fn link(&self) -> String {
+        format!("https://www.reddit.com{}", self.permalink)
+    }
+
+    // Reddit stopped itemizing awards, so older posts may only have the total
+    fn award_count(&self) -> u32 {
+        self.total_awards_received.max(self.all_awardings.iter().map(|award| award.count).sum())
+    }
+
+    // e.g. "87%"; listings from some endpoints leave the ratio out
+    fn upvoted_percent(&self) -> Option<String> {
+        (self.upvote_ratio > 0.0).then(|| format!("{:.0}%", self.upvote_ratio * 100.0))
+    }
+
+    // Label/value pairs for the metadata panel on the post detail screen
+    fn details(&self) -> Vec<(&'static str, String)> {
+        let mut rows = vec![("Score", self.score.to_string())];
+        if let Some(percent) = self.upvoted_percent() {
+            rows.push(("Upvoted", percent));
+        }
+        match self.likes {
+            Some(true) => rows.push(("Your vote", "Upvoted".to_string())),
+            Some(false) => rows.push(("Your vote", "Downvoted".to_string())),
+            None => {}
+        }
+        rows.push(("Posted", format!("{} ({})", absolute_time(self.created_utc), relative_time(self.created_utc, store::now()))));
+        rows.push(("Comments", self.num_comments.to_string()));
+        if self.award_count() > 0 {
+            rows.push(("Awards", self.award_count().to_string()));
+        }
+        rows.push(("Author", format!("u/{}", self.author)));
+        rows.push(("Domain", self.domain.clone()));
+        rows
+    }
+
+    // Where the post's link points, with an icon for the kind of content and an arrow when it
+    // leaves Reddit, e.g. "🔗 github.com ⬈" or "📄 self.rust"
+    fn domain_chip(&self) -> Option<String> {
+        if self.domain.is_empty() {
+            return None;
+        }
+        let icon = match self.post_hint.as_deref() {
+            _ if self.is_self || self.domain.starts_with("self.") => "📄",
+            Some("image") => "🖼",
+            Some("hosted:video") | Some("rich:video") => "▶",
+            _ => "🔗",
+        };
+        let arrow = if self.opens_externally() { " ⬈" } else { "" };
+        Some(format!("{} {}{}", icon, self.domain, arrow))
+    }
+
+    // True when clicking the title leaves Reddit for another site
+    fn opens_externally(&self) -> bool {
+        let domain = self.domain.to_lowercase();
+        let on_reddit = ["reddit.com", "redd.it"].iter()
+            .any(|host| domain == *host || domain.ends_with(&format!(".{}", host)));
+        !self.is_self && !domain.starts_with("self.") && !on_reddit
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Media {
+    #[serde(default)]
+    reddit_video: Option<RedditVideo>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(not(feature = "video"), allow(dead_code))]
+struct RedditVideo {
+    #[serde(with = "html")]
+    fallback_url: String,
+    #[serde(default, with = "html::option")]
+    hls_url: Option<String>,
+    width: u32,
     height: u32,
+    #[serde(default)]
+    has_audio: bool,
+}
+
+fn closest_to_thumbnail<'a>(source: &'a ImageSource, resolutions: &'a [ImageSource]) -> &'a ImageSource {
+    let target_height = 100.0;
+    resolutions.iter()
+        .min_by_key(|res| {
+            // Calculate distance from target height
+            ((res.height as f32 - target_height).abs() * 100.0) as i32
+        })
+        .unwrap_or(source)
+}
+
+// Fill and text colors for a flair pill; None when the subreddit leaves it uncolored
+fn flair_colors(background: Option<&str>, text_color: Option<&str>) -> Option<(egui::Color32, egui::Color32)> {
+    let hex = background?.strip_prefix('#')?;
+    let fill = egui::Color32::from_hex(&format!("#{}", hex)).ok()?;
+    let text = if text_color == Some("light") { egui::Color32::WHITE } else { egui::Color32::BLACK };
+    Some((fill, text))
+}
+
+// Compact age for cards, e.g. "5m ago", "3h ago", "2y ago"
+fn relative_time(created_utc: f64, now: i64) -> String {
+    let seconds = (now - created_utc as i64).max(0);
+    let (count, unit) = match seconds {
+        0..=59 => return "just now".to_string(),
+        60..=3599 => (seconds / 60, "m"),
+        3600..=86_399 => (seconds / 3600, "h"),
+        86_400..=2_591_999 => (seconds / 86_400, "d"),
+        2_592_000..=31_535_999 => (seconds / 2_592_000, "mo"),
+        _ => (seconds / 31_536_000, "y"),
+    };
+    format!("{}{} ago", count, unit)
+}
+
+// Day bucket for section headers in newest-first feeds, by local calendar day counted back from
+// `today_start`, the local midnight that began today
+fn time_section(created_utc: f64, today_start: i64) -> &'static str {
+    let days_ago = (today_start + 86_399 - created_utc as i64).div_euclid(86_400);
+    match days_ago {
+        ..=0 => "Today",
+        1 => "Yesterday",
+        2..=6 => "This week",
+        _ => "Earlier",
+    }
+}
+
+// e.g. "March 21, 2024 at 14:05 UTC"
+fn absolute_time(created_utc: f64) -> String {
+    let seconds_of_day = (created_utc as i64).rem_euclid(86_400);
+    format!("{} at {:02}:{:02} UTC", profile::cake_day(created_utc), seconds_of_day / 3600, seconds_of_day % 3600 / 60)
+}
+
+// Rounded flair label; clickable so link flair can filter the feed
+fn flair_pill(ui: &mut egui::Ui, text: &str, background: Option<&str>, text_color: Option<&str>, size: f32) -> egui::Response {
+    let (fill, color) = flair_colors(background, text_color)
+        .unwrap_or((ui.visuals().faint_bg_color, ui.visuals().text_color()));
+    egui::Frame::none()
+        .fill(fill)
+        .rounding(8.0)
+        .inner_margin(egui::Margin::symmetric(6.0, 1.0))
+        .show(ui, |ui| {
+            ui.add(egui::Label::new(egui::RichText::new(text).size(size).color(color)).sense(egui::Sense::click()))
+        })
+        .inner
+}
+
+// Placeholder Reddit substitutes for authors and bodies that no longer exist
+fn is_deleted_marker(text: &str) -> bool {
+    text == "[deleted]" || text == "[removed]"
 }
 
 // New structs for subreddit data
@@ -74,6 +561,8 @@ struct SubredditListing {
 #[derive(Debug, Deserialize)]
 struct SubredditListingData {
     children: Vec<SubredditChild>,
+    #[serde(default)]
+    after: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -86,11 +575,113 @@ struct SubredditData {
     display_name: String,  // This is the subreddit name without the /r/ prefix
 }
 
+// The subset of /api/v1/me/prefs that has a Rustle equivalent
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+struct AccountPrefs {
+    over_18: bool,
+    label_nsfw: bool,
+    default_comment_sort: String,
+}
+
+impl Default for AccountPrefs {
+    fn default() -> Self {
+        AccountPrefs {
+            over_18: false,
+            label_nsfw: true,
+            default_comment_sort: "confidence".to_string(),
+        }
+    }
+}
+
+// Users blocked on reddit.com, from /prefs/blocked
+#[derive(Debug, Deserialize)]
+struct BlockedListing {
+    data: BlockedListingData,
+}
+
+#[derive(Debug, Deserialize)]
+struct BlockedListingData {
+    children: Vec<BlockedUser>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BlockedUser {
+    name: String,
+}
+
+// Matches from /api/subreddit_autocomplete_v2: subreddits, and users when profiles are included
+#[derive(Debug, Deserialize)]
+struct AutocompleteListing {
+    data: AutocompleteListingData,
+}
+
+#[derive(Debug, Deserialize)]
+struct AutocompleteListingData {
+    children: Vec<AutocompleteMatch>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AutocompleteMatch {
+    kind: String,  // "t5" subreddit or "t2" user
+    data: AutocompleteMatchData,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct AutocompleteMatchData {
+    display_name: String,  // Subreddits
+    name: String,          // Users
+}
+
+// Reply from /api/comment with api_type=json
+#[derive(Debug, Deserialize)]
+struct CommentResponse {
+    json: CommentResponseJson,
+}
+
+#[derive(Debug, Deserialize)]
+struct CommentResponseJson {
+    #[serde(default)]
+    errors: Vec<Vec<serde_json::Value>>,  // [code, message, field] triples
+    data: Option<CommentResponseData>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CommentResponseData {
+    things: Vec<comments::CommentThing>,
+}
+
+impl CommentResponse {
+    fn into_comment(self) -> Result<comments::Comment> {
+        if let Some(error) = self.json.errors.first() {
+            let message = error.get(1).and_then(|m| m.as_str())
+                .or_else(|| error.first().and_then(|c| c.as_str()))
+                .unwrap_or("unknown error");
+            return Err(anyhow::anyhow!("Reddit rejected the comment: {}", message));
+        }
+        self.json.data.into_iter()
+            .flat_map(|data| data.things)
+            .find_map(|thing| match thing {
+                comments::CommentThing::Comment(comment) => Some(comment),
+                comments::CommentThing::More(_) => None,
+            })
+            .context("Reddit did not return the posted comment")
+    }
+}
+
 // Reddit API client
 #[derive(Clone)]
 struct RedditClient {
     client: Client,
     access_token: Option<String>,
+    expires_at: Option<Instant>,
+    rate_limit: Arc<rate_limit::RateLimiter>,  // Shared by every clone of this client
+    front_page: String,  // Path of the "home" feed; a multireddit of picks when browsing without an account
+    username: String,    // Whose saved posts make up the Saved feed
+    public: bool,  // No token at all: read-only requests go to the public .json endpoints instead
+    oauth_host: String,  // Where API and token requests go; a local mock server in tests
+    www_host: String,
 }
 
 impl RedditClient {
@@ -100,63 +691,184 @@ impl RedditClient {
                 .user_agent(APP_USER_AGENT)
                 .build()?,
             access_token: None,
+            expires_at: None,
+            rate_limit: Arc::new(rate_limit::RateLimiter::default()),
+            front_page: "/".to_string(),
+            username: String::new(),
+            public: false,
+            oauth_host: OAUTH_HOST.to_string(),
+            www_host: WWW_HOST.to_string(),
         })
     }
 
+    // Authenticated request against the OAuth API. raw_json=1 asks Reddit not to HTML-escape
+    // text fields, so they're used as they arrive
+    fn api_get(&self, path: &str) -> Result<reqwest::RequestBuilder> {
+        self.api_request(reqwest::Method::GET, path)
+    }
+
+    fn api_post(&self, path: &str) -> Result<reqwest::RequestBuilder> {
+        self.api_request(reqwest::Method::POST, path)
+    }
+
+    // Send through the rate limiter, which may hold the request back when the budget is low
+    async fn send(&self, request: reqwest::RequestBuilder) -> Result<reqwest::Response> {
+        self.rate_limit.acquire().await;
+        let response = api_log::send(&self.client, request).await?;
+        self.rate_limit.record(response.headers());
+        // Tokens are renewed before they expire, so a 401 means access was taken away
+        if response.status() == reqwest::StatusCode::UNAUTHORIZED {
+            StoredSession::forget();
+            let message = format!("{}: Reddit no longer accepts this session", AUTH_ERROR);
+            return Err(RustleError::Auth { failure: AuthFailure::Ended, message }.into());
+        }
+        if response.status() == reqwest::StatusCode::TOO_MANY_REQUESTS {
+            return Err(RustleError::rate_limited(response.headers()).into());
+        }
+        Ok(response)
+    }
+
+    fn api_request(&self, method: reqwest::Method, path: &str) -> Result<reqwest::RequestBuilder> {
+        if self.public && self.access_token.is_none() {
+            return Ok(self.client
+                .request(method, public_url(&self.www_host, path))
+                .query(&[("raw_json", "1")]));
+        }
+        let access_token = self.access_token.as_ref()
+            .context("Not authenticated")?;
+
+        Ok(self.client
+            .request(method, format!("{}{}", self.oauth_host, path))
+            .header(header::AUTHORIZATION, format!("Bearer {}", access_token))
+            .query(&[("raw_json", "1")]))
+    }
+
+    // Script apps sign in with the account's own username and password
     async fn authenticate(&mut self, client_id: &str, client_secret: &str, username: &str, password: &str) -> Result<()> {
+        self.request_token(client_id, client_secret, &[
+            ("grant_type", "password"),
+            ("username", username),
+            ("password", password),
+        ]).await?;
+        Ok(())
+    }
+
+    // Installed apps signed in through the browser keep a refresh token instead
+    async fn authenticate_with_refresh_token(&mut self, client_id: &str, refresh_token: &str) -> Result<()> {
+        self.request_token(client_id, "", &[
+            ("grant_type", "refresh_token"),
+            ("refresh_token", refresh_token),
+        ]).await?;
+        Ok(())
+    }
+
+    // Application-only token for browsing without an account
+    async fn authenticate_anonymously(&mut self, client_id: &str, device_id: &str) -> Result<()> {
+        self.request_token(client_id, "", &[
+            ("grant_type", "https://oauth.reddit.com/grants/installed_client"),
+            ("device_id", device_id),
+        ]).await?;
+        Ok(())
+    }
+
+    // Trade the code from the browser redirect for a token pair, returning the refresh token
+    async fn redeem_authorization_code(&mut self, client_id: &str, code: &str) -> Result<String> {
+        let auth_response = self.request_token(client_id, "", &[
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("redirect_uri", oauth::REDIRECT_URI),
+        ]).await?;
+        auth_response.refresh_token.context("Reddit didn't issue a refresh token")
+    }
+
+    // Fetch a token and start using it
+    async fn request_token(&mut self, client_id: &str, client_secret: &str, form: &[(&str, &str)]) -> Result<AuthResponse> {
         let auth = base64::engine::general_purpose::STANDARD.encode(format!("{}:{}", client_id, client_secret));
         
         // Create a more reusable header builder
-        let response = self.client
-            .post("https://www.reddit.com/api/v1/access_token")
+        let request = self.client
+            .post(format!("{}/api/v1/access_token", self.www_host))
             .header(header::AUTHORIZATION, format!("Basic {}", auth))
             .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
-            .form(&[
-                ("grant_type", "password"),
-                ("username", username),
-                ("password", password),
-            ])
-            .send()
-            .await?;
+            .form(form);
+        let response = api_log::send(&self.client, request).await?;
 
         // Error handling
         let status = response.status();
         let response_text = response.text().await?;
 
-        if !status.is_success() {
+        // A wrong password still comes back as 200 with an "error" field
         if let Ok(error_response) = serde_json::from_str::<serde_json::Value>(&response_text) {
-                if let Some(error) = error_response.get("error").and_then(|e| e.as_str()) {
-                    return Err(anyhow::anyhow!("Reddit API error: {}", error));
-                }
+            if let Some(error) = error_response.get("error").and_then(|e| e.as_str()) {
+                tracing::warn!(error, "Token request refused");
+                let failure = match error {
+                    "invalid_grant" => AuthFailure::Grant,
+                    "invalid_client" | "unauthorized_client" => AuthFailure::App,
+                    _ => AuthFailure::Unknown,
+                };
+                let message = format!("{}: Reddit API error: {}", AUTH_ERROR, error);
+                return Err(RustleError::Auth { failure, message }.into());
             }
-            return Err(anyhow::anyhow!("Authentication failed with status: {}", status));
+        }
+        if !status.is_success() {
+            // The token endpoint answers 401 when it doesn't recognise the app
+            let failure = if status == reqwest::StatusCode::UNAUTHORIZED { AuthFailure::App } else { AuthFailure::Unknown };
+            let message = format!("{}: Authentication failed with status: {}", AUTH_ERROR, status);
+            return Err(RustleError::Auth { failure, message }.into());
         }
 
         // Parse successful response
         let auth_response: AuthResponse = serde_json::from_str(&response_text)
             .context("Failed to parse authentication response")?;
-            
-        self.access_token = Some(auth_response.access_token);
-        Ok(())
+        let grant_type = form.iter().find(|(name, _)| *name == "grant_type").map_or("", |(_, value)| *value);
+        tracing::info!(grant_type, expires_in = auth_response.expires_in, "Token issued");
+        self.access_token = Some(auth_response.access_token.clone());
+        self.expires_at = (auth_response.expires_in > 0)
+            .then(|| Instant::now() + Duration::from_secs(auth_response.expires_in));
+        Ok(auth_response)
     }
 
-    async fn get_home_feed(&self, after: Option<&str>) -> Result<(Vec<Post>, Option<String>)> {
-        let access_token = self.access_token.as_ref()
-            .context("Not authenticated")?;
+    // The current token in a form that outlives the process; None without one
+    fn session(&self, account: &Account) -> Option<StoredSession> {
+        let expires_in = self.expires_at?.saturating_duration_since(Instant::now());
+        Some(StoredSession {
+            account: account.session_key(),
+            access_token: self.access_token.clone()?,
+            expires_at: store::now() + expires_in.as_secs() as i64,
+        })
+    }
+
+    fn resume(&mut self, session: StoredSession) {
+        let expires_in = Duration::from_secs((session.expires_at - store::now()).max(0) as u64);
+        self.access_token = Some(session.access_token);
+        self.expires_at = Some(Instant::now() + expires_in);
+    }
+
+    fn token_expiring(&self) -> bool {
+        self.expires_at.is_some_and(|expires_at| Instant::now() + TOKEN_RENEW_MARGIN >= expires_at)
+    }
+
+    async fn get_me(&self) -> Result<Me> {
+        let request = self.api_get("/api/v1/me")?;
+        let response = self.send(request).await?;
 
-        let mut url = "https://oauth.reddit.com/".to_string();
-        if let Some(after_token) = after {
-            url = format!("{}?after={}", url, after_token);
+        if !response.status().is_success() {
+            return Err(RustleError::api(response.status(), "Failed to fetch account").into());
         }
 
-        let response = self.client
-            .get(&url)
-            .header(header::AUTHORIZATION, format!("Bearer {}", access_token))
-            .send()
-            .await?;
+        response.json().await
+            .context("Failed to parse account")
+    }
+
+    // `limit` is posts per page, up to Reddit's 100
+    async fn get_home_feed(&self, after: Option<&str>, limit: u32) -> Result<(Vec<Post>, Option<String>)> {
+        let request = self.api_get(&self.front_page)?
+            .query(&[("after", after)])
+            .query(&[("limit", limit)]);
+        let response = self.send(request).await?;
 
         if !response.status().is_success() {
-            return Err(anyhow::anyhow!("Failed to fetch home feed: {}", response.status()));
+            return Err(RustleError::api(response.status(), "Failed to fetch home feed").into());
         }
 
         let listing: RedditListing = response.json().await
@@ -165,355 +877,7182 @@ impl RedditClient {
         Ok((listing.data.children.into_iter().map(|child| child.data).collect(), listing.data.after))
     }
 
-    async fn get_subreddit_posts(&self, subreddit: &str, after: Option<&str>) -> Result<(Vec<Post>, Option<String>)> {
-        let access_token = self.access_token.as_ref()
-            .context("Not authenticated")?;
+    async fn get_subreddit_posts(&self, subreddit: &str, view: Option<&FeedView>, after: Option<&str>, limit: u32) -> Result<(Vec<Post>, Option<String>)> {
+        let (path, params) = match view {
+            Some(view) => view.endpoint(subreddit),
+            None => (format!("/r/{}", subreddit), Vec::new()),
+        };
+
+        let request = self.api_get(&path)?
+            .query(&params)
+            .query(&[("after", after)])
+            .query(&[("limit", limit)]);
+        let response = self.send(request).await?;
 
-        let mut url = format!("https://oauth.reddit.com/r/{}", subreddit);
-        if let Some(after_token) = after {
-            url = format!("{}?after={}", url, after_token);
+        let status = response.status();
+        if status == reqwest::StatusCode::FORBIDDEN || status == reqwest::StatusCode::NOT_FOUND {
+            // The body says whether it's quarantined, private or banned
+            let body = response.text().await.unwrap_or_default();
+            return Err(match subreddit::restriction(&body) {
+                Some(restriction) => RustleError::Restricted { subreddit: subreddit.to_string(), restriction },
+                None => RustleError::api(status, "Failed to fetch subreddit posts"),
+            }.into());
+        }
+        if !status.is_success() {
+            return Err(RustleError::api(status, "Failed to fetch subreddit posts").into());
         }
 
-        let response = self.client
-            .get(&url)
-            .header(header::AUTHORIZATION, format!("Bearer {}", access_token))
-            .send()
-            .await?;
+        let listing: RedditListing = response.json().await
+            .context("Failed to parse Reddit listing")?;
+            
+        Ok((listing.data.children.into_iter().map(|child| child.data).collect(), listing.data.after))
+    }
+
+    // "home" is the front page, `moderation::QUEUE` the mod queue and `SAVED` the account's saved
+    // posts; anything else is treated as a subreddit name
+    async fn get_feed(&self, feed: &str, view: Option<&FeedView>, after: Option<&str>, limit: u32) -> Result<(Vec<Post>, Option<String>)> {
+        if feed == "home" {
+            self.get_home_feed(after, limit).await
+        } else if feed == moderation::QUEUE {
+            self.get_mod_queue(after, limit).await
+        } else if feed == SAVED {
+            self.get_saved_posts(after, limit).await
+        } else {
+            self.get_subreddit_posts(feed, view, after, limit).await
+        }
+    }
+
+    // Posts the account has saved, newest save first; saved comments stay on reddit.com
+    async fn get_saved_posts(&self, after: Option<&str>, limit: u32) -> Result<(Vec<Post>, Option<String>)> {
+        if self.username.is_empty() {
+            return Err(anyhow::anyhow!("Sign in to see saved posts"));
+        }
+        let request = self.api_get(&format!("/user/{}/saved", self.username))?
+            .query(&[("type", Some("links")), ("after", after)])
+            .query(&[("limit", limit)]);
+        let response = self.send(request).await?;
 
         if !response.status().is_success() {
-            return Err(anyhow::anyhow!("Failed to fetch subreddit posts: {}", response.status()));
+            return Err(RustleError::api(response.status(), "Failed to fetch saved posts").into());
         }
 
         let listing: RedditListing = response.json().await
             .context("Failed to parse Reddit listing")?;
-            
+
         Ok((listing.data.children.into_iter().map(|child| child.data).collect(), listing.data.after))
     }
 
-    async fn get_subscribed_subreddits(&self) -> Result<Vec<String>> {
-        let access_token = self.access_token.as_ref()
-            .context("Not authenticated")?;
+    // Posts waiting for review in every subreddit the account moderates
+    async fn get_mod_queue(&self, after: Option<&str>, limit: u32) -> Result<(Vec<Post>, Option<String>)> {
+        let request = self.api_get("/r/mod/about/modqueue")?
+            .query(&[("only", Some("links")), ("after", after)])
+            .query(&[("limit", limit)]);
+        let response = self.send(request).await?;
 
-        let url = "https://oauth.reddit.com/subreddits/mine/subscriber";
+        if !response.status().is_success() {
+            return Err(RustleError::api(response.status(), "Failed to fetch the mod queue").into());
+        }
 
-        let response = self.client
-            .get(url)
-            .header(header::AUTHORIZATION, format!("Bearer {}", access_token))
-            .send()
-            .await?;
+        let listing: RedditListing = response.json().await
+            .context("Failed to parse Reddit listing")?;
+
+        Ok((listing.data.children.into_iter().map(|child| child.data).collect(), listing.data.after))
+    }
+
+    // A subreddit's best posts over `period` ("day", "week", ...)
+    async fn get_top_posts(&self, subreddit: &str, period: &str, limit: usize) -> Result<Vec<Post>> {
+        let request = self.api_get(&format!("/r/{}/top", subreddit))?
+            .query(&[("t", period.to_string()), ("limit", limit.to_string())]);
+        let response = self.send(request).await?;
 
         if !response.status().is_success() {
-            return Err(anyhow::anyhow!("Failed to fetch subscribed subreddits: {}", response.status()));
+            return Err(RustleError::api(response.status(), format!("Failed to fetch top posts in r/{}", subreddit)).into());
         }
 
-        let listing: SubredditListing = response.json().await
-            .context("Failed to parse subreddits listing")?;
-            
-        Ok(listing.data.children.into_iter()
-            .map(|child| child.data.display_name)
-            .collect())
+        let listing: RedditListing = response.json().await
+            .context("Failed to parse Reddit listing")?;
+
+        Ok(listing.data.children.into_iter().map(|child| child.data).collect())
     }
-}
 
-// App state and UI
-struct RedditApp {
-    posts: Arc<Mutex<Vec<Post>>>,
-    loading: Arc<Mutex<bool>>,
-    error_message: Arc<Mutex<Option<String>>>,
-    reddit_client: Arc<Mutex<Option<RedditClient>>>,
-    after: Arc<Mutex<Option<String>>>,
-    initial_load: Arc<Mutex<bool>>,
-    scroll_to_top: Arc<Mutex<bool>>,
-    show_settings: bool,
-    settings: Settings,
-    settings_modified: bool,
-    has_credentials: bool,
-    current_subreddit: Arc<Mutex<String>>,  // "home" for home feed, or subreddit name
-    subreddits: Arc<Mutex<Vec<String>>>,    // List of user's subscribed subreddits
-    loading_subreddits: Arc<Mutex<bool>>,   // Whether we're currently loading the subreddit list
-    last_scroll_pos: Arc<Mutex<f32>>,       // Track the last scroll position
-    is_loading_more: Arc<Mutex<bool>>,      // Track if we're in the process of loading more posts
+    async fn get_comments(&self, permalink: &str, sort: &str) -> Result<Vec<comments::CommentThing>> {
+        let request = self.api_get(permalink.trim_end_matches('/'))?
+            .query(&[("sort", sort)]);
+        let response = self.send(request).await?;
+
+        if !response.status().is_success() {
+            return Err(RustleError::api(response.status(), "Failed to fetch comments").into());
+        }
+
+        // The response is a pair of listings: the post itself, then its comment tree
+        let (_post, listing): (RedditListing, comments::CommentListing) = response.json().await
+            .context("Failed to parse comments")?;
+
+        Ok(listing.data.children)
+    }
+
+    // Replies behind a "load more" row, a batch at a time, as the flat list Reddit sends
+    async fn get_more_children(&self, link_id: &str, more: &comments::MoreComments, sort: &str) -> Result<Vec<comments::CommentThing>> {
+        let children = more.children.iter().take(comments::MORE_CHILDREN_BATCH).cloned().collect::<Vec<_>>().join(",");
+        let request = self.api_get("/api/morechildren")?
+            .query(&[("api_type", "json"), ("link_id", link_id), ("children", &children), ("sort", sort), ("limit_children", "false")]);
+        let response = self.send(request).await?;
+
+        if !response.status().is_success() {
+            return Err(RustleError::api(response.status(), "Failed to load more replies").into());
+        }
+
+        let response: comments::MoreChildrenResponse = response.json().await
+            .context("Failed to parse more replies")?;
+
+        response.into_things()
+    }
+
+    async fn get_account_prefs(&self) -> Result<AccountPrefs> {
+        let request = self.api_get("/api/v1/me/prefs")?;
+        let response = self.send(request).await?;
+
+        if !response.status().is_success() {
+            return Err(RustleError::api(response.status(), "Failed to fetch account preferences").into());
+        }
+
+        response.json().await
+            .context("Failed to parse account preferences")
+    }
+
+    async fn get_blocked_users(&self) -> Result<Vec<String>> {
+        let request = self.api_get("/prefs/blocked")?;
+        let response = self.send(request).await?;
+
+        if !response.status().is_success() {
+            return Err(RustleError::api(response.status(), "Failed to fetch blocked users").into());
+        }
+
+        let listing: BlockedListing = response.json().await
+            .context("Failed to parse blocked users")?;
+
+        Ok(listing.data.children.into_iter().map(|user| user.name).collect())
+    }
+
+    // Unread messages, comment replies and mentions, newest first
+    async fn get_unread(&self) -> Result<Vec<inbox::Message>> {
+        // Polling mustn't mark anything read on the reader's behalf
+        let request = self.api_get("/message/unread")?
+            .query(&[("mark", "false"), ("limit", "100")]);
+        let response = self.send(request).await?;
+
+        if !response.status().is_success() {
+            return Err(RustleError::api(response.status(), "Failed to fetch the inbox").into());
+        }
+
+        let listing: inbox::InboxListing = response.json().await
+            .context("Failed to parse the inbox")?;
+
+        Ok(listing.data.children.into_iter().map(|child| child.data).collect())
+    }
+
+    // Users, or subreddits, whose names start with `query`
+    async fn autocomplete(&self, query: &str, users: bool, nsfw: bool) -> Result<Vec<String>> {
+        let request = self.api_get("/api/subreddit_autocomplete_v2")?
+            .query(&[("query", query), ("include_profiles", if users { "true" } else { "false" }), ("include_over_18", if nsfw { "true" } else { "false" }), ("limit", "10")]);
+        let response = self.send(request).await?;
+
+        if !response.status().is_success() {
+            return Err(RustleError::api(response.status(), "Failed to look up names").into());
+        }
+
+        let listing: AutocompleteListing = response.json().await
+            .context("Failed to parse name suggestions")?;
+
+        let kind = if users { "t2" } else { "t5" };
+        Ok(listing.data.children.into_iter()
+            .filter(|found| found.kind == kind)
+            .map(|found| if users { found.data.name } else { found.data.display_name })
+            // Profiles also show up as "u_name" subreddits
+            .filter(|name| !name.is_empty() && !name.starts_with("u_"))
+            .collect())
+    }
+
+    async fn submit_comment(&self, parent_fullname: &str, text: &str) -> Result<comments::Comment> {
+        let request = self.api_post("/api/comment")?
+            .form(&[
+                ("api_type", "json"),
+                ("thing_id", parent_fullname),
+                ("text", text),
+            ]);
+        let response = self.send(request).await?;
+
+        if !response.status().is_success() {
+            return Err(RustleError::api(response.status(), "Failed to post comment").into());
+        }
+
+        let response: CommentResponse = response.json().await
+            .context("Failed to parse comment response")?;
+
+        response.into_comment()
+    }
+
+    async fn get_post_requirements(&self, subreddit: &str) -> Result<submit::Requirements> {
+        let request = self.api_get(&format!("/api/v1/{}/post_requirements", subreddit))?;
+        let response = self.send(request).await?;
+
+        if !response.status().is_success() {
+            return Err(RustleError::api(response.status(), format!("Failed to fetch r/{}'s posting rules", subreddit)).into());
+        }
+
+        response.json().await.context("Failed to parse posting rules")
+    }
+
+    // Post flairs anyone can pick; moderators' own are left out
+    async fn get_link_flairs(&self, subreddit: &str) -> Result<Vec<submit::FlairTemplate>> {
+        let request = self.api_get(&format!("/r/{}/api/link_flair_v2", subreddit))?;
+        let response = self.send(request).await?;
+
+        if !response.status().is_success() {
+            return Err(RustleError::api(response.status(), format!("Failed to fetch r/{}'s flairs", subreddit)).into());
+        }
+
+        let flairs: Vec<submit::FlairTemplate> = response.json().await
+            .context("Failed to parse flairs")?;
+        Ok(flairs.into_iter().filter(|flair| !flair.mod_only).collect())
+    }
+
+    // Returns the new post's URL
+    async fn submit_post(&self, draft: &submit::Saved) -> Result<String> {
+        let mut form = vec![
+            ("api_type", "json"),
+            ("sr", draft.subreddit.as_str()),
+            ("kind", draft.kind.api_name()),
+            ("title", draft.title.trim()),
+            ("sendreplies", "true"),
+        ];
+        match draft.kind {
+            submit::Kind::Text => form.push(("text", draft.text.as_str())),
+            submit::Kind::Link => form.push(("url", draft.url.trim())),
+        }
+        if let Some(flair) = &draft.flair {
+            form.push(("flair_id", flair.id.as_str()));
+            form.push(("flair_text", flair.text.as_str()));
+        }
+        let request = self.api_post("/api/submit")?.form(&form);
+        let response = self.send(request).await?;
+
+        if !response.status().is_success() {
+            return Err(RustleError::api(response.status(), "Failed to submit post").into());
+        }
+
+        let response: submit::SubmitResponse = response.json().await
+            .context("Failed to parse submit response")?;
+
+        response.into_url()
+    }
+
+    async fn get_user_about(&self, name: &str) -> Result<profile::UserAbout> {
+        let request = self.api_get(&format!("/user/{}/about", name))?;
+        let response = self.send(request).await?;
+
+        if !response.status().is_success() {
+            return Err(RustleError::api(response.status(), format!("Failed to fetch user u/{}", name)).into());
+        }
+
+        let about: profile::UserAboutResponse = response.json().await
+            .context("Failed to parse user profile")?;
+
+        Ok(about.data)
+    }
+
+    async fn get_subreddit_about(&self, name: &str) -> Result<subreddit::SubredditAbout> {
+        let request = self.api_get(&format!("/r/{}/about", name))?;
+        let response = self.send(request).await?;
+
+        if !response.status().is_success() {
+            return Err(RustleError::api(response.status(), format!("Failed to fetch r/{}", name)).into());
+        }
+
+        let about: subreddit::SubredditAboutResponse = response.json().await
+            .context("Failed to parse subreddit details")?;
+
+        Ok(about.data)
+    }
+
+    async fn get_subreddit_rules(&self, name: &str) -> Result<Vec<subreddit::Rule>> {
+        let request = self.api_get(&format!("/r/{}/about/rules", name))?;
+        let response = self.send(request).await?;
+
+        if !response.status().is_success() {
+            return Err(RustleError::api(response.status(), format!("Failed to fetch the rules of r/{}", name)).into());
+        }
+
+        let rules: subreddit::RulesResponse = response.json().await
+            .context("Failed to parse subreddit rules")?;
+
+        Ok(rules.rules)
+    }
+
+    // A page of the Discover screen's popular or new directory
+    async fn get_subreddit_directory(&self, directory: &str, after: Option<&str>) -> Result<(Vec<subreddit::SubredditAbout>, Option<String>)> {
+        let request = self.api_get(&format!("/subreddits/{}", directory))?
+            .query(&[("limit", Some("50")), ("after", after)]);
+        let response = self.send(request).await?;
+
+        if !response.status().is_success() {
+            return Err(RustleError::api(response.status(), format!("Failed to fetch {} subreddits", directory)).into());
+        }
+
+        let listing: discover::Listing = response.json().await
+            .context("Failed to parse subreddit directory")?;
+
+        Ok(listing.into_page())
+    }
+
+    // Reddit only hands out the names, so the details come from a second lookup
+    async fn get_trending_subreddits(&self) -> Result<Vec<subreddit::SubredditAbout>> {
+        let request = self.api_get("/api/trending_subreddits")?;
+        let response = self.send(request).await?;
+
+        if !response.status().is_success() {
+            return Err(RustleError::api(response.status(), "Failed to fetch trending subreddits").into());
+        }
+
+        let trending: discover::Trending = response.json().await
+            .context("Failed to parse trending subreddits")?;
+        if trending.subreddit_names.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let request = self.api_get("/api/info")?
+            .query(&[("sr_name", trending.subreddit_names.join(","))]);
+        let response = self.send(request).await?;
+
+        if !response.status().is_success() {
+            return Err(RustleError::api(response.status(), "Failed to fetch trending subreddits").into());
+        }
+
+        let listing: discover::Listing = response.json().await
+            .context("Failed to parse subreddit details")?;
+
+        Ok(listing.into_page().0)
+    }
+
+    async fn get_my_trophies(&self) -> Result<Vec<profile::Trophy>> {
+        let request = self.api_get("/api/v1/me/trophies")?;
+        let response = self.send(request).await?;
+
+        if !response.status().is_success() {
+            return Err(RustleError::api(response.status(), "Failed to fetch trophies").into());
+        }
+
+        let list: profile::TrophyList = response.json().await
+            .context("Failed to parse trophies")?;
+
+        Ok(list.data.trophies.into_iter().map(|child| child.data).collect())
+    }
+
+    async fn get_user_overview(&self, name: &str, after: Option<&str>) -> Result<(Vec<profile::ProfileItem>, Option<String>)> {
+        let request = self.api_get(&format!("/user/{}/overview", name))?
+            .query(&[("after", after)]);
+        let response = self.send(request).await?;
+
+        if !response.status().is_success() {
+            return Err(RustleError::api(response.status(), format!("Failed to fetch history for u/{}", name)).into());
+        }
+
+        let listing: profile::OverviewListing = response.json().await
+            .context("Failed to parse user history")?;
+
+        Ok((listing.data.children, listing.data.after))
+    }
+
+    async fn get_subreddit_autocomplete(&self, query: &str) -> Result<Vec<String>> {
+        let request = self.api_get("/api/subreddit_autocomplete_v2")?
+            .query(&[
+                ("query", query),
+                ("include_profiles", "false"),
+                ("include_over_18", "true"),
+                ("limit", "10"),
+            ]);
+        let response = self.send(request).await?;
+
+        if !response.status().is_success() {
+            return Err(RustleError::api(response.status(), "Failed to fetch subreddit suggestions").into());
+        }
+
+        let listing: SubredditListing = response.json().await
+            .context("Failed to parse subreddit suggestions")?;
+
+        Ok(listing.data.children.into_iter()
+            .map(|child| child.data.display_name)
+            .collect())
+    }
+
+    // Both take a batch; Reddit accepts a comma-separated list of fullnames
+    async fn hide(&self, fullnames: &[String]) -> Result<()> {
+        self.update_hidden(fullnames, "/api/hide").await
+    }
+
+    async fn unhide(&self, fullnames: &[String]) -> Result<()> {
+        self.update_hidden(fullnames, "/api/unhide").await
+    }
+
+    async fn update_hidden(&self, fullnames: &[String], path: &str) -> Result<()> {
+        let request = self.api_post(path)?
+            .form(&[("id", fullnames.join(","))]);
+        let response = self.send(request).await?;
+
+        if !response.status().is_success() {
+            return Err(RustleError::api(response.status(), "Failed to update hidden posts").into());
+        }
+
+        Ok(())
+    }
+
+    async fn save(&self, fullname: &str) -> Result<()> {
+        let request = self.api_post("/api/save")?
+            .form(&[("id", fullname)]);
+        let response = self.send(request).await?;
+
+        if !response.status().is_success() {
+            return Err(RustleError::api(response.status(), "Failed to save post").into());
+        }
+
+        Ok(())
+    }
+
+    async fn unsave(&self, fullname: &str) -> Result<()> {
+        let request = self.api_post("/api/unsave")?
+            .form(&[("id", fullname)]);
+        let response = self.send(request).await?;
+
+        if !response.status().is_success() {
+            return Err(RustleError::api(response.status(), "Failed to unsave post").into());
+        }
+
+        Ok(())
+    }
+
+    // Some(true) upvotes, Some(false) downvotes, None clears the vote
+    async fn vote(&self, fullname: &str, likes: Option<bool>) -> Result<()> {
+        let dir = match likes {
+            Some(true) => "1",
+            Some(false) => "-1",
+            None => "0",
+        };
+        let request = self.api_post("/api/vote")?
+            .form(&[("id", fullname), ("dir", dir)]);
+        let response = self.send(request).await?;
+
+        if !response.status().is_success() {
+            return Err(RustleError::api(response.status(), "Failed to vote").into());
+        }
+
+        Ok(())
+    }
+
+    // Moderator actions; Reddit refuses them outside subreddits the account moderates
+    async fn remove(&self, fullname: &str, spam: bool) -> Result<()> {
+        self.moderate("/api/remove", &[("id", fullname), ("spam", if spam { "true" } else { "false" })], "Failed to remove").await
+    }
+
+    async fn approve(&self, fullname: &str) -> Result<()> {
+        self.moderate("/api/approve", &[("id", fullname)], "Failed to approve").await
+    }
+
+    async fn lock(&self, fullname: &str, locked: bool) -> Result<()> {
+        let path = if locked { "/api/lock" } else { "/api/unlock" };
+        self.moderate(path, &[("id", fullname)], if locked { "Failed to lock" } else { "Failed to unlock" }).await
+    }
+
+    async fn distinguish(&self, fullname: &str, distinguished: bool) -> Result<()> {
+        let how = if distinguished { "yes" } else { "no" };
+        self.moderate("/api/distinguish", &[("api_type", "json"), ("id", fullname), ("how", how)], "Failed to distinguish").await
+    }
+
+    async fn moderate(&self, path: &str, form: &[(&str, &str)], failure: &str) -> Result<()> {
+        let request = self.api_post(path)?
+            .form(form);
+        let response = self.send(request).await?;
+
+        if !response.status().is_success() {
+            return Err(RustleError::api(response.status(), failure).into());
+        }
+
+        Ok(())
+    }
+
+    // Agree to see a quarantined subreddit; Reddit remembers it for the account
+    async fn opt_in_quarantine(&self, subreddit: &str) -> Result<()> {
+        let request = self.api_post("/api/quarantine_optin")?
+            .form(&[
+                ("accept", "true"),
+                ("sr_name", subreddit),
+            ]);
+        let response = self.send(request).await?;
+
+        if !response.status().is_success() {
+            return Err(RustleError::api(response.status(), "Failed to opt in to the quarantined subreddit").into());
+        }
+
+        Ok(())
+    }
+
+    async fn subscribe(&self, subreddit: &str) -> Result<()> {
+        self.update_subscription(subreddit, "sub").await
+    }
+
+    async fn unsubscribe(&self, subreddit: &str) -> Result<()> {
+        self.update_subscription(subreddit, "unsub").await
+    }
+
+    async fn update_subscription(&self, subreddit: &str, action: &str) -> Result<()> {
+        let request = self.api_post("/api/subscribe")?
+            .form(&[
+                ("action", action),
+                ("sr_name", subreddit),
+            ]);
+        let response = self.send(request).await?;
+
+        if !response.status().is_success() {
+            return Err(RustleError::api(response.status(), "Failed to update subscription").into());
+        }
+
+        Ok(())
+    }
+
+    // Follows the `after` cursor until every subscription is fetched, reporting the running
+    // total after each page since large accounts take a while
+    async fn get_subscribed_subreddits(&self, progress: impl Fn(usize)) -> Result<Vec<String>> {
+        self.get_my_subreddits("subscriber", progress).await
+    }
+
+    async fn get_moderated_subreddits(&self) -> Result<Vec<String>> {
+        self.get_my_subreddits("moderator", |_| {}).await
+    }
+
+    // `relation` is "subscriber" or "moderator"
+    async fn get_my_subreddits(&self, relation: &str, progress: impl Fn(usize)) -> Result<Vec<String>> {
+        let mut subreddits = Vec::new();
+        let mut after = None;
+        loop {
+            let (page, next_after) = self.get_my_subreddits_page(relation, after.as_deref()).await?;
+            let empty = page.is_empty();
+            subreddits.extend(page);
+            progress(subreddits.len());
+
+            // An empty page or a repeated cursor would otherwise loop forever
+            if empty || next_after.is_none() || next_after == after {
+                return Ok(subreddits);
+            }
+            after = next_after;
+        }
+    }
+
+    async fn get_my_subreddits_page(&self, relation: &str, after: Option<&str>) -> Result<(Vec<String>, Option<String>)> {
+        let request = self.api_get(&format!("/subreddits/mine/{}", relation))?
+            .query(&[("limit", Some("100")), ("after", after)]);
+        let response = self.send(request).await?;
+
+        if !response.status().is_success() {
+            let what = if relation == "moderator" { "moderated" } else { "subscribed" };
+            return Err(RustleError::api(response.status(), format!("Failed to fetch {} subreddits", what)).into());
+        }
+
+        let listing: SubredditListing = response.json().await
+            .context("Failed to parse subreddits listing")?;
+            
+        Ok((listing.data.children.into_iter()
+            .map(|child| child.data.display_name)
+            .collect(), listing.data.after))
+    }
+}
+
+// Page in the full subscription list, publishing the running count for the sidebar. Each page
+// counts as progress, so a long list isn't taken for a hung load; the caller clears the flag.
+async fn fetch_subreddit_list(
+    client: &RedditClient,
+    load: &watchdog::Load,
+    subreddits: &Arc<Mutex<Vec<String>>>,
+    subreddits_fetched: &Arc<Mutex<usize>>,
+    error_message: &Arc<Mutex<Option<RustleError>>>,
+    store: &Option<Arc<store::Store>>,
+    egui_ctx: &Option<egui::Context>,
+) {
+    *subreddits_fetched.lock().unwrap() = 0;
+
+    let result = client.get_subscribed_subreddits(|count| {
+        load.progress();
+        if load.current() {
+            *subreddits_fetched.lock().unwrap() = count;
+        }
+        if let Some(ctx) = egui_ctx {
+            ctx.request_repaint();
+        }
+    }).await;
+    if !load.current() {
+        return;
+    }
+
+    match result {
+        Ok(fetched_subreddits) => {
+            if let Some(store) = store {
+                let _ = store.save_subreddits(&fetched_subreddits);
+            }
+            *subreddits.lock().unwrap() = fetched_subreddits;
+        }
+        Err(e) if store::is_network_error(&e) && load_offline_subreddits(store, subreddits) => {}
+        Err(e) => {
+            *error_message.lock().unwrap() = Some(RustleError::classify(&e).context("Error fetching subreddits"));
+        }
+    }
+    if let Some(ctx) = egui_ctx {
+        ctx.request_repaint();
+    }
+}
+
+// Fall back to the last synced subscription list; false if there isn't one
+fn load_offline_subreddits(store: &Option<Arc<store::Store>>, subreddits: &Arc<Mutex<Vec<String>>>) -> bool {
+    let Some(stored) = store.as_ref().and_then(|store| store.load_subreddits().ok().flatten()) else {
+        return false;
+    };
+    *subreddits.lock().unwrap() = stored.data;
+    if let Some(store) = store {
+        store.mark_offline(stored.synced_at);
+    }
+    true
+}
+
+// Fall back to the last synced first page of a feed; false if there isn't one
+fn load_offline_feed(
+    store: &Option<Arc<store::Store>>,
+    subreddit: &str,
+    view: Option<&FeedView>,
+    posts: &Arc<Mutex<Vec<Post>>>,
+    after: &Arc<Mutex<Option<String>>>,
+) -> bool {
+    let Some(store) = store else {
+        return false;
+    };
+    let Some(stored) = store.load_feed(subreddit, view).ok().flatten() else {
+        // An archived subreddit's newest posts will do for its main feed
+        let Some(archived) = store.archived_feed(subreddit, archive::LIMIT).ok().flatten().filter(|_| view.is_none()) else {
+            return false;
+        };
+        *posts.lock().unwrap() = archived.data;
+        *after.lock().unwrap() = None;
+        store.mark_offline(archived.synced_at);
+        return true;
+    };
+    *posts.lock().unwrap() = stored.data.posts;
+    *after.lock().unwrap() = stored.data.after;
+    store.mark_offline(stored.synced_at);
+    true
+}
+
+// Remember a freshly fetched first page and leave offline mode
+fn save_feed(store: &Option<Arc<store::Store>>, subreddit: &str, view: Option<&FeedView>, posts: &[Post], after: Option<&str>) {
+    let Some(store) = store else {
+        return;
+    };
+    let feed = CachedFeed {
+        subreddit: subreddit.to_string(),
+        view: view.cloned(),
+        posts: posts.to_vec(),
+        after: after.map(str::to_string),
+    };
+    let _ = store.save_feed(&feed);
+    store.mark_online();
+}
+
+// A page of a feed with the reader's filters applied, and how many posts they took out
+async fn fetch_filtered(client: &RedditClient, settings: &Settings, feed: &str, view: Option<&FeedView>, after: Option<&str>) -> Result<(Vec<Post>, Option<String>, usize)> {
+    let (posts, after) = client.get_feed(feed, view, after, settings.page_size.clamp(1, 100)).await?;
+    // Moderators need to see everything waiting for review, and readers everything they saved
+    if is_unfiltered_feed(feed) {
+        return Ok((posts, after, 0));
+    }
+    let (posts, hidden) = Filters::new(&settings.filters).muting(settings.muted_media(feed)).apply(posts);
+    tracing::debug!(feed, posts = posts.len(), hidden, more = after.is_some(), "Loaded a page");
+    Ok((posts, after, hidden))
+}
+
+fn profile_posts(items: &[profile::ProfileItem]) -> Vec<Post> {
+    items.iter()
+        .filter_map(|item| match item {
+            profile::ProfileItem::Post(post) => Some(post.as_ref().clone()),
+            profile::ProfileItem::Comment(_) => None,
+        })
+        .collect()
 }
 
-#[derive(Clone, Serialize, Deserialize)]
-struct Settings {
-    client_id: String,
-    client_secret: String,
-    username: String,
-    password: String,
-    dark_mode: bool,  // Add theme preference
-    font_size: f32,   // Add font size preference
-}
+// Returns the shared client, authenticating a fresh one only if none exists yet
+async fn ensure_client(reddit_client: &Arc<Mutex<Option<RedditClient>>>, settings: &Settings) -> Result<RedditClient> {
+    let existing = reddit_client.lock().unwrap().clone();
+    match &existing {
+        Some(client) if !client.token_expiring() => return Ok(client.clone()),
+        Some(_) => tracing::info!("Token about to expire, signing in again"),
+        None => {}
+    }
+
+    // A replayed session signs in against the recording, so it needs no account
+    if !settings.has_credentials() && !replay::replaying() {
+        return Err(anyhow::anyhow!("Connect Rustle to Reddit first"));
+    }
+    let mut client = RedditClient::new().context("Failed to create client")?;
+    client.front_page = settings.front_page();
+    client.username = settings.account.username.clone();
+    // Reddit keeps counting the budget across a renewed token, so the limiter carries over
+    if let Some(existing) = existing {
+        client.rate_limit = existing.rate_limit;
+    }
+
+    // A token from the last run is reused while it has time left, saving a round trip at startup
+    let stored = StoredSession::load().filter(|session| session.usable_for(&settings.account, store::now()));
+    if let Some(session) = stored {
+        tracing::info!(expires_at = session.expires_at, "Resuming the stored session");
+        client.resume(session);
+        *reddit_client.lock().unwrap() = Some(client.clone());
+        return Ok(client);
+    }
+
+    match settings.account.auth_method {
+        AuthMethod::Script => client.authenticate(&settings.account.client_id, &settings.account.client_secret,
+            &settings.account.username, &settings.account.password).await,
+        AuthMethod::Browser => client.authenticate_with_refresh_token(&settings.account.client_id, &settings.account.refresh_token).await,
+        AuthMethod::Anonymous => {
+            if settings.account.client_id.is_empty() {
+                // Without an app of their own, readers get Reddit's public feeds at a lower rate limit
+                client.public = true;
+                Ok(())
+            } else {
+                client.authenticate_anonymously(&settings.account.client_id, &settings.account.device_id).await
+            }
+        }
+    }?;
+
+    if let Some(session) = client.session(&settings.account) {
+        session.save();
+    }
+    *reddit_client.lock().unwrap() = Some(client.clone());
+    Ok(client)
+}
+
+// www.reddit.com serves the same listings without a token when ".json" is added to the path,
+// e.g. "/r/rust/hot?t=day" becomes "https://www.reddit.com/r/rust/hot.json?t=day"
+fn public_url(host: &str, path: &str) -> String {
+    let (path, query) = path.split_once('?').map_or((path, None), |(path, query)| (path, Some(query)));
+    let path = match path.trim_end_matches('/') {
+        "" => "/",
+        trimmed => trimmed,
+    };
+    match query {
+        Some(query) => format!("{}{}.json?{}", host, path, query),
+        None => format!("{}{}.json", host, path),
+    }
+}
+
+// Feed name for the account's saved posts; the slash keeps it from clashing with a real subreddit
+const SAVED: &str = "me/saved";
+
+// r/all, r/popular, the mod queue and Saved mix many subreddits, so there's nothing to join or favorite
+fn is_aggregate_feed(subreddit: &str) -> bool {
+    subreddit.eq_ignore_ascii_case("all") || subreddit.eq_ignore_ascii_case("popular") || is_unfiltered_feed(subreddit)
+}
+
+// Feeds shown in full, without the reader's filters or media mutes
+fn is_unfiltered_feed(feed: &str) -> bool {
+    feed == moderation::QUEUE || feed == SAVED
+}
+
+// Only single subreddits get their own sort and layout, like their saved views
+fn has_feed_prefs(feed: &str) -> bool {
+    feed != "home" && !feed.is_empty() && !is_aggregate_feed(feed)
+}
+
+// e.g. "r/rust", or "Saved"
+fn feed_title(feed: &str) -> String {
+    match feed {
+        "home" => "Home".to_string(),
+        moderation::QUEUE => "Mod queue".to_string(),
+        SAVED => "Saved".to_string(),
+        subreddit => format!("r/{}", subreddit),
+    }
+}
+
+// Kick off image downloads for freshly fetched posts so thumbnails are ready when cards render
+fn prefetch_images(ctx: &egui::Context, posts: &[Post], settings: &Settings) {
+    for post in posts {
+        if let Some(url) = post.thumbnail_to_load(settings, false) {
+            let _ = ctx.try_load_image(&url, egui::SizeHint::default());
+        }
+    }
+}
+
+// Headline and advice for the auth failure screen, based on what Reddit said
+fn explain_auth_failure(failure: AuthFailure, method: AuthMethod) -> (&'static str, &'static str) {
+    match (failure, method) {
+        (AuthFailure::Grant, AuthMethod::Browser) => ("Your browser sign-in was revoked",
+            "Rustle's access may have been removed from your Reddit account. Sign in again to continue."),
+        (AuthFailure::Grant, _) => ("Reddit didn't accept your username or password",
+            "Your Reddit password may have changed. Update it in settings, then retry."),
+        (AuthFailure::Ended, _) => ("Reddit ended this session",
+            "Access for Rustle may have been revoked, or the account's password changed. Retry to sign in again, or switch accounts."),
+        (AuthFailure::App, _) => ("Reddit rejected Rustle's app credentials",
+            "The client ID or secret may be wrong, or the app was deleted on reddit.com/prefs/apps."),
+        (AuthFailure::Unknown, _) => ("Couldn't sign in to Reddit", "Check your credentials in settings, then retry."),
+    }
+}
+
+// Comment sorts Reddit accepts, with the names its own UI uses
+const COMMENT_SORTS: [(&str, &str); 6] = [
+    ("confidence", "Best"),
+    ("top", "Top"),
+    ("new", "New"),
+    ("controversial", "Controversial"),
+    ("old", "Old"),
+    ("qa", "Q&A"),
+];
+
+// Auto-refresh intervals offered in settings, in minutes; 0 is off
+const AUTO_REFRESH_MINUTES: [u32; 5] = [0, 2, 5, 15, 30];
+
+// Whether a feed restored from the last session is fetched again at launch
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+enum StartupRefresh {
+    #[default]
+    Immediately,
+    Manual,           // Only when the reader refreshes, for metered connections
+    WhenStale(u32),   // Once it was fetched more than this many minutes ago
+}
+
+impl StartupRefresh {
+    // `age` is seconds since the feed was fetched
+    fn wants_refresh(self, age: i64) -> bool {
+        match self {
+            StartupRefresh::Immediately => true,
+            StartupRefresh::Manual => false,
+            StartupRefresh::WhenStale(minutes) => age >= minutes as i64 * 60,
+        }
+    }
+}
+
+// Inbox check intervals offered in settings, in minutes; 0 is off
+const INBOX_CHECK_MINUTES: [u32; 5] = [0, 1, 5, 15, 30];
+
+// Undo windows offered in settings, in seconds; 0 posts straight away
+const UNDO_SEND_SECONDS: [u32; 5] = [0, 3, 5, 10, 20];
+
+// Listing sorts offered when saving a view
+const VIEW_SORTS: [&str; 5] = ["hot", "new", "top", "rising", "controversial"];
+// Reddit's `t` values for top and controversial
+const TIME_RANGES: [(&str, &str); 6] = [
+    ("hour", "Past hour"),
+    ("day", "Past day"),
+    ("week", "Past week"),
+    ("month", "Past month"),
+    ("year", "Past year"),
+    ("all", "All time"),
+];
+
+// Drag-and-drop payload while reordering favorites in the sidebar: the dragged favorite's index
+struct FavoriteDrag(usize);
+
+// Spacing of post cards in the feed, in points
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+struct CardLayout {
+    padding: f32,     // Inside each card
+    image_size: f32,  // Thumbnail bounding box
+    spacing: f32,     // Between cards
+}
+
+impl CardLayout {
+    const COMPACT: CardLayout = CardLayout { padding: 3.0, image_size: 60.0, spacing: 4.0 };
+    const COZY: CardLayout = CardLayout { padding: 6.0, image_size: 100.0, spacing: 10.0 };
+    const COMFORTABLE: CardLayout = CardLayout { padding: 12.0, image_size: 140.0, spacing: 18.0 };
+    const PRESETS: [(&'static str, CardLayout); 3] = [
+        ("Compact", CardLayout::COMPACT),
+        ("Cozy", CardLayout::COZY),
+        ("Comfortable", CardLayout::COMFORTABLE),
+    ];
+}
+
+impl Default for CardLayout {
+    fn default() -> Self {
+        CardLayout::COZY
+    }
+}
+
+// How much of each post the feed shows; the spacing presets above apply within the chosen one
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+enum Density {
+    Compact,  // One line per post with a tiny thumbnail
+    #[default]
+    Cozy,     // Thumbnail beside the title and actions
+    Card,     // Wide preview under the title
+}
+
+impl Density {
+    const ALL: [(&'static str, Density); 3] = [
+        ("Compact", Density::Compact),
+        ("Cozy", Density::Cozy),
+        ("Card", Density::Card),
+    ];
+
+    fn next(self) -> Density {
+        match self {
+            Density::Compact => Density::Cozy,
+            Density::Cozy => Density::Card,
+            Density::Card => Density::Compact,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        Density::ALL.iter().find(|(_, density)| *density == self).map_or("", |(name, _)| name)
+    }
+
+    // Stand-in height for a card that hasn't been drawn yet
+    fn estimated_height(self, layout: CardLayout, font_size: f32) -> f32 {
+        let chrome = 2.0 * layout.padding + layout.spacing;
+        match self {
+            Density::Compact => 24.0 * font_size + chrome,
+            Density::Cozy => layout.image_size.max(80.0 * font_size) + chrome,
+            Density::Card => 360.0 + 80.0 * font_size + chrome,
+        }
+    }
+}
+
+// Quick actions that get a button on each card; all of them stay in the right-click menu
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+struct CardActions {
+    vote: bool,
+    save: bool,
+    hide: bool,
+    share: bool,  // Copy the post's link
+    comments: bool,
+}
+
+impl Default for CardActions {
+    fn default() -> Self {
+        CardActions { vote: true, save: false, hide: true, share: false, comments: true }
+    }
+}
+
+// Reusable text for the reply box: a signature, a stock answer, a Markdown template
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+struct Snippet {
+    name: String,
+    text: String,
+}
+
+impl Snippet {
+    // What to type after a slash to insert it, e.g. "stock-answer" for "Stock answer"
+    fn trigger(&self) -> String {
+        self.name.split_whitespace().collect::<Vec<_>>().join("-").to_lowercase()
+    }
+}
+
+// A named sort and flair filter saved for one subreddit, shown as a tab under its header
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+struct FeedView {
+    name: String,   // Empty for a sort picked in the header rather than a saved view
+    sort: String,
+    time: String,   // Range for top and controversial, e.g. "week"; empty for Reddit's default
+    flair: String,  // Empty for any flair
+}
+
+impl Default for FeedView {
+    fn default() -> Self {
+        FeedView {
+            name: String::new(),
+            sort: "hot".to_string(),
+            time: String::new(),
+            flair: String::new(),
+        }
+    }
+}
+
+impl FeedView {
+    // API path and query parameters for a subreddit listing seen through this view
+    fn endpoint(&self, subreddit: &str) -> (String, Vec<(&'static str, String)>) {
+        let time = self.timed().then(|| ("t", self.time.clone()));
+        if self.flair.is_empty() {
+            return (format!("/r/{}/{}", subreddit, self.sort), time.into_iter().collect());
+        }
+        // Flair filters only work through search, which has no rising/controversial sort
+        let sort = match self.sort.as_str() {
+            "rising" | "controversial" => "hot",
+            sort => sort,
+        };
+        let mut params = vec![
+            ("q", format!("flair_name:\"{}\"", self.flair.replace('"', ""))),
+            ("restrict_sr", "1".to_string()),
+            ("sort", sort.to_string()),
+        ];
+        params.extend(time.filter(|_| sort == "top"));
+        (format!("/r/{}/search", subreddit), params)
+    }
+
+    // Whether the time range applies to the sort
+    fn timed(&self) -> bool {
+        !self.time.is_empty() && is_timed_sort(&self.sort)
+    }
+
+    fn describe(&self) -> String {
+        let mut description = format!("Sorted by {}", self.sort);
+        if self.timed() {
+            description.push_str(&format!(", {}", time_range_label(&self.time).to_lowercase()));
+        }
+        if !self.flair.is_empty() {
+            description.push_str(&format!(", flair \"{}\"", self.flair));
+        }
+        description
+    }
+}
+
+fn is_timed_sort(sort: &str) -> bool {
+    sort == "top" || sort == "controversial"
+}
+
+fn time_range_label(time: &str) -> &'static str {
+    TIME_RANGES.iter().find(|(name, _)| *name == time).map_or("Past day", |(_, label)| label)
+}
+
+// Time range picker for a sort; nothing shows for sorts without one
+fn time_range_combo(ui: &mut egui::Ui, id: &str, sort: &str, time: &mut String) -> bool {
+    if !is_timed_sort(sort) {
+        return false;
+    }
+    let mut changed = false;
+    egui::ComboBox::from_id_source(id)
+        .selected_text(time_range_label(time))
+        .show_ui(ui, |ui| {
+            for (name, label) in TIME_RANGES {
+                // Reddit's own default is the past day, so that's stored as no range at all
+                let value = if name == "day" { String::new() } else { name.to_string() };
+                changed |= ui.selectable_value(time, value, label).changed();
+            }
+        });
+    changed
+}
+
+// A subreddit's own sort and layout, remembered from the last time either was changed there
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+struct FeedPrefs {
+    sort: String,  // Empty for Reddit's default, hot
+    time: String,
+    density: Option<Density>,  // None follows the global setting
+}
+
+impl FeedPrefs {
+    // The view a subreddit opens with
+    fn view(&self) -> Option<FeedView> {
+        if self.sort.is_empty() {
+            return None;
+        }
+        Some(FeedView { sort: self.sort.clone(), time: self.time.clone(), ..FeedView::default() })
+    }
+}
+
+// A page fetched ahead of the scroll position, waiting to be appended
+struct PrefetchedPage {
+    subreddit: String,
+    view: Option<FeedView>,
+    after: Option<String>,  // The token the page was requested with
+    posts: Vec<Post>,
+    next_after: Option<String>,
+    hidden: usize,  // Posts the filters took out of it
+}
+
+// A submitted reply or post held back until its undo window runs out
+struct PendingSend {
+    sending: Sending,
+    send_at: Instant,
+}
+
+enum Sending {
+    Reply { parent: String, text: String },
+    Post { id: Option<i64>, saved: submit::Saved, scheduled: bool },  // `id` is its row in Drafts, if it has one
+}
+
+impl PendingSend {
+    fn reply_to(&self) -> Option<&str> {
+        match &self.sending {
+            Sending::Reply { parent, .. } => Some(parent),
+            Sending::Post { .. } => None,
+        }
+    }
+}
+
+// Waits out `pending`'s undo window; false if it was taken back in the meantime
+async fn wait_to_send(pending_sends: &Mutex<Vec<Arc<PendingSend>>>, pending: &Arc<PendingSend>) -> bool {
+    tokio::time::sleep(pending.send_at.saturating_duration_since(Instant::now())).await;
+    // Whichever of this and Undo takes it off the queue first wins
+    let mut queue = pending_sends.lock().unwrap();
+    let Some(index) = queue.iter().position(|queued| Arc::ptr_eq(queued, pending)) else {
+        return false;
+    };
+    queue.remove(index);
+    true
+}
+
+// Names the API offered for u/ and r/ references typed in the reply box
+#[derive(Default)]
+struct MentionLookup {
+    typing: String,  // The reference being typed, e.g. "r/ru"
+    results: HashMap<String, Vec<String>>,  // Keyed by what was typed
+    requested: HashSet<String>,
+}
+
+// Posts found at the head of the feed by an auto-refresh, waiting for the reader to pull them in
+struct NewPosts {
+    subreddit: String,
+    view: Option<FeedView>,
+    posts: Vec<Post>,
+}
+
+// Fetched posts the feed isn't showing yet, in the order Reddit ranks them
+fn unseen_posts(fetched: Vec<Post>, shown: &[Post]) -> Vec<Post> {
+    let mut shown: HashSet<String> = shown.iter().map(|post| post.name.clone()).collect();
+    fetched.into_iter().filter(|post| shown.insert(post.name.clone())).collect()
+}
+
+// Adds the next page to the feed. Rankings shift between requests, so a post can come back on
+// a later page; it keeps its first spot.
+fn append_page(posts: &mut Vec<Post>, page: Vec<Post>) {
+    let page = unseen_posts(page, posts);
+    posts.extend(page);
+}
+
+// Posts opened or scrolled past. In "hide read" mode the ones read since the feed loaded stay
+// visible so the list doesn't shift under the reader; they're gone on the next visit.
+#[derive(Default)]
+struct ReadPosts {
+    names: HashSet<String>,
+    since_load: HashSet<String>,
+    unsaved: Vec<String>,  // Marked since the last write to the store
+}
+
+impl ReadPosts {
+    // True if the post wasn't already marked
+    fn mark(&mut self, name: &str) -> bool {
+        self.since_load.insert(name.to_string());
+        let fresh = self.names.insert(name.to_string());
+        if fresh {
+            self.unsaved.push(name.to_string());
+        }
+        fresh
+    }
+
+    fn hides(&self, name: &str) -> bool {
+        self.names.contains(name) && !self.since_load.contains(name)
+    }
+
+    fn new_feed(&mut self) {
+        self.since_load.clear();
+    }
+
+    // Posts Reddit reports as opened elsewhere count as read before this visit
+    fn merge_visited(&mut self, posts: &[Post]) -> Vec<String> {
+        posts.iter()
+            .filter(|post| post.visited || post.clicked)
+            .filter(|post| self.names.insert(post.name.clone()))
+            .map(|post| post.name.clone())
+            .collect()
+    }
+}
+
+// Reads a page of visits for the open history screen's search, on a worker thread. A page that
+// no longer lines up with the screen (the search changed, or it was closed) is dropped.
+fn fetch_history(store: &store::Store, history: &Mutex<Option<history::History>>, egui_ctx: &Option<egui::Context>, more: bool) {
+    let Some((query, offset)) = history.lock().unwrap().as_ref()
+        .map(|history| (history.query.trim().to_string(), if more { history.visits.len() } else { 0 })) else {
+        return;
+    };
+    let result = store.history(&query, offset, history::PAGE);
+
+    let mut open = history.lock().unwrap();
+    let Some(history) = open.as_mut() else {
+        return;
+    };
+    history.loading = false;
+    match result {
+        Ok(_) if more && (history.searched.as_deref() != Some(query.as_str()) || history.visits.len() != offset) => {}
+        Ok(visits) => {
+            if !more {
+                history.visits.clear();
+            }
+            history.more = visits.len() == history::PAGE as usize;
+            history.visits.extend(visits);
+            history.searched = Some(query);
+        }
+        Err(e) => {
+            tracing::warn!(error = %format!("{:#}", e), "Couldn't read history");
+            history.searched = Some(query);
+            history.more = false;
+        }
+    }
+    drop(open);
+    if let Some(ctx) = egui_ctx {
+        ctx.request_repaint();
+    }
+}
+
+// Folds Reddit's own visit history for freshly fetched posts into the local read store
+fn merge_visited(settings: &Settings, read_posts: &Mutex<ReadPosts>, store: &Option<Arc<store::Store>>, posts: &[Post]) {
+    if !settings.sync_visits {
+        return;
+    }
+    let fresh = read_posts.lock().unwrap().merge_visited(posts);
+    if let Some(store) = store.as_ref().filter(|_| !fresh.is_empty()) {
+        let _ = store.mark_read(&fresh);
+    }
+}
+
+// Posts picked with Ctrl/Shift-click for bulk actions
+#[derive(Default)]
+struct Selection {
+    names: HashSet<String>,
+    anchor: Option<String>,  // Last post Ctrl-clicked; Shift-click selects from here
+}
+
+impl Selection {
+    // `order` is the feed's post names, top to bottom
+    fn click(&mut self, name: &str, range: bool, order: &[String]) {
+        let anchor = self.anchor.as_ref().and_then(|anchor| order.iter().position(|other| other == anchor));
+        let target = order.iter().position(|other| other == name);
+        if let (true, Some(anchor), Some(target)) = (range, anchor, target) {
+            self.names.extend(order[anchor.min(target)..=anchor.max(target)].iter().cloned());
+            return;
+        }
+        if !self.names.remove(name) {
+            self.names.insert(name.to_string());
+        }
+        self.anchor = Some(name.to_string());
+    }
+
+    fn clear(&mut self) {
+        self.names.clear();
+        self.anchor = None;
+    }
+
+    // The selected posts in feed order
+    fn pick(&self, posts: &[Post]) -> Vec<Post> {
+        posts.iter().filter(|post| self.names.contains(&post.name)).cloned().collect()
+    }
+}
+
+// Selected posts as a Markdown list of links, for pasting elsewhere
+fn export_markdown(posts: &[Post]) -> String {
+    posts.iter()
+        .map(|post| format!("- [{}](https://www.reddit.com{}) (r/{})\n", post.title.replace(']', "\\]"), post.permalink, post.subreddit))
+        .collect()
+}
+
+// How long a confirmation stays under the header
+const NOTICE_DURATION: Duration = Duration::from_secs(6);
+
+// Typing pause before asking Reddit for names, and how many names the suggestion list shows
+const MENTION_LOOKUP_DELAY: Duration = Duration::from_millis(300);
+const MENTION_SUGGESTIONS: usize = 8;
+
+// Names to offer for a u/ or r/ reference: ones from the thread or subscriptions first, then
+// the API's, without repeats
+fn mention_candidates(query: &str, local: &[String], remote: &[String]) -> Vec<String> {
+    let query = query.to_lowercase();
+    let mut names: Vec<String> = Vec::new();
+    for name in local.iter().filter(|name| name.to_lowercase().starts_with(&query)).chain(remote) {
+        if !names.iter().any(|seen| seen.eq_ignore_ascii_case(name)) {
+            names.push(name.clone());
+        }
+    }
+    names.truncate(MENTION_SUGGESTIONS);
+    names
+}
+
+// Everyone who has commented in the thread, most recent first, then the poster, leaving out
+// the reader and deleted accounts
+fn thread_users(thread: &[comments::CommentThing], poster: Option<&str>, me: &str) -> Vec<String> {
+    let commenters = comments::chronological(thread).into_iter().rev()
+        .filter(|(comment, _)| !comment.author_deleted())
+        .map(|(comment, _)| comment.author.as_str());
+    let mut users: Vec<String> = Vec::new();
+    for user in commenters.chain(poster.filter(|poster| !is_deleted_marker(poster))) {
+        if !user.eq_ignore_ascii_case(me) && !users.iter().any(|seen| seen == user) {
+            users.push(user.to_string());
+        }
+    }
+    users
+}
+
+// Suggestions for a u/ or r/ reference, under the cursor at char index `at`; the one clicked, if any
+fn render_mention_list(ui: &egui::Ui, output: &egui::text_edit::TextEditOutput, at: usize, mention: &comments::Mention, names: &[String], choice: usize) -> Option<String> {
+    let cursor = output.galley.pos_from_ccursor(egui::text::CCursor::new(at)).translate(output.galley_pos.to_vec2());
+    let prefix = if mention.user { "u/" } else { "r/" };
+    let mut picked = None;
+    egui::Area::new(output.response.id.with("mentions"))
+        .order(egui::Order::Foreground)
+        .fixed_pos(cursor.left_bottom() + egui::vec2(0.0, 4.0))
+        .show(ui.ctx(), |ui| {
+            egui::Frame::popup(ui.style()).show(ui, |ui| {
+                for (index, name) in names.iter().enumerate() {
+                    if ui.selectable_label(index == choice, format!("{}{}", prefix, name)).clicked() {
+                        picked = Some(name.clone());
+                    }
+                }
+            });
+        });
+    picked
+}
+
+fn exports_dir() -> Result<std::path::PathBuf> {
+    let dirs = directories::ProjectDirs::from("com", "spartanjubilee", "Rustle").context("No home directory to export to")?;
+    let dir = dirs.data_dir().join("exports");
+    std::fs::create_dir_all(&dir).context("Failed to create the exports folder")?;
+    Ok(dir)
+}
+
+// Theme files here are offered alongside the built-in presets
+fn themes_dir() -> Result<std::path::PathBuf> {
+    let dirs = directories::ProjectDirs::from("com", "spartanjubilee", "Rustle").context("No home directory for themes")?;
+    let dir = dirs.data_dir().join("themes");
+    std::fs::create_dir_all(&dir).context("Failed to create the themes folder")?;
+    Ok(dir)
+}
+
+// e.g. "feed-rust-1711029900.json"; multireddit paths flattened to something filesystem-safe
+fn export_file_name(feed: &str, now: i64) -> String {
+    let feed: String = feed.chars().map(|c| if c.is_ascii_alphanumeric() || c == '_' { c } else { '-' }).collect();
+    format!("feed-{}-{}.json", feed, now)
+}
+
+// Feed snapshot persisted between sessions so startup can render immediately
+#[derive(Serialize, Deserialize)]
+struct CachedFeed {
+    subreddit: String,
+    #[serde(default)]
+    view: Option<FeedView>,
+    posts: Vec<Post>,
+    after: Option<String>,
+}
+
+// Measured card heights for the virtualized feed, keyed by post fullname. They only hold for the
+// width and layout they were measured at.
+#[derive(Default)]
+struct FeedRows {
+    heights: std::collections::HashMap<String, f32>,
+    measured_for: Option<(f32, Density, CardLayout, f32)>,  // Width, density, spacing, font size
+    passed: HashSet<String>,  // Already scrolled past and marked read
+}
+
+// Whether to load more and whether to prefetch, given the last card on screen. The next page is
+// fetched once that card is `load_ahead` posts from the end, and buffered a page before that. A
+// feed with nothing visible (everything filtered out) always wants more.
+fn page_triggers(last_visible: Option<usize>, len: usize, load_ahead: u32, page_size: u32) -> (bool, bool) {
+    let remaining = last_visible.map_or(0, |index| len.saturating_sub(index + 1));
+    let load_ahead = load_ahead as usize;
+    (remaining < load_ahead.max(1), remaining < load_ahead.max(1) + page_size as usize)
+}
+
+// A feed left for another one, kept so switching back picks up where it was
+struct FeedState {
+    view: Option<FeedView>,
+    posts: Vec<Post>,
+    after: Option<String>,
+    scroll: f32,
+    hidden: Vec<usize>,
+}
+
+// App state and UI
+struct RedditApp {
+    posts: Arc<Mutex<Vec<Post>>>,
+    loading: Arc<Mutex<bool>>,
+    error_message: Arc<Mutex<Option<RustleError>>>,
+    reddit_client: Arc<Mutex<Option<RedditClient>>>,
+    after: Arc<Mutex<Option<String>>>,
+    initial_load: Arc<Mutex<bool>>,
+    scroll_to_top: Arc<Mutex<bool>>,
+    show_settings: bool,
+    onboarding: Option<onboarding::Wizard>,  // Setup wizard, shown in place of everything else
+    lightbox: Option<lightbox::Lightbox>,  // Media viewer, shown in place of the feed
+    inspector: Option<api_log::Inspector>,  // Request log window, open while Some
+    console: Option<logging::Console>,  // Debug console, open while Some; only reachable by shortcut
+    digest_viewer: Option<digest::Viewer>,  // Digest window, open while Some
+    digest: Arc<Mutex<Option<digest::Digest>>>,  // The latest one generated this session
+    digest_progress: Arc<Mutex<Option<digest::Progress>>>,  // While the job runs
+    usage: Arc<Mutex<usage::Tracker>>,  // Counted since the last autosave
+    usage_dashboard: Option<usage::Dashboard>,  // Stats window, open while Some
+    installed_themes: Vec<theme::Theme>,  // Read from the themes folder at startup and on import
+    applied_scale: Option<f32>,  // The UI scale last handed to egui
+    error_log: bug_report::ErrorLog,  // For bug report bundles
+    hints: Arc<Mutex<hints::Hints>>,  // Link hint labels, while f-hinting is on
+    focus: focus::Focus,  // Today's time against the limits in settings
+    speller: spellcheck::Speller,
+    demo: bool,  // Browsing the bundled sample feed without credentials
+    settings: Settings,
+    settings_modified: bool,
+    has_credentials: bool,
+    current_subreddit: Arc<Mutex<String>>,  // "home" for home feed, or subreddit name
+    current_view: Arc<Mutex<Option<FeedView>>>,  // Saved view applied to the subreddit, if any
+    view_editor: Option<FeedView>,          // "Save view" form under the subreddit header
+    subreddits: Arc<Mutex<Vec<String>>>,    // List of user's subscribed subreddits
+    loading_subreddits: Arc<Mutex<bool>>,   // Whether we're currently loading the subreddit list
+    subreddits_fetched: Arc<Mutex<usize>>,  // Running total while the list is paged in
+    last_scroll_pos: Arc<Mutex<f32>>,       // Track the last scroll position
+    feed_rows: Arc<Mutex<FeedRows>>,        // Card heights for drawing only the visible part of the feed
+    feed_scroll_return: Arc<Mutex<Option<f32>>>,  // Feed offset to restore after leaving a post
+    feed_states: Arc<Mutex<std::collections::HashMap<String, FeedState>>>,  // Feeds switched away from, keyed by lowercase name
+    authenticating: Arc<Mutex<bool>>,       // Background authentication in progress
+    account_prefs: Arc<Mutex<Option<AccountPrefs>>>,  // Fetched at sign-in, applied on the next frame
+    blocked_users: Arc<Mutex<HashSet<String>>>,  // Lowercased names blocked on reddit.com
+    moderated: Arc<Mutex<Vec<String>>>,  // Subreddits the account moderates
+    read_posts: Arc<Mutex<ReadPosts>>,
+    read_saved_at: Instant,  // Last time marked posts were handed to the store
+    today_start: Option<(i64, Instant)>,  // Local midnight and when it was looked up
+    hidden_posts: Arc<Mutex<HashSet<String>>>,  // Hidden from this feed, shown as an undo row until it reloads
+    flair_request: Arc<Mutex<Option<(String, String)>>>,  // Flair pill clicked this frame: (subreddit, flair)
+    subreddit_request: Arc<Mutex<Option<String>>>,  // Picked from a post's menu this frame
+    viewer_request: Arc<Mutex<Option<Post>>>,       // Opened in the media viewer this frame
+    opting_in: Arc<Mutex<bool>>,  // Agreeing to see a quarantined subreddit
+    selection: Arc<Mutex<Selection>>,
+    selection_click: Arc<Mutex<Option<(String, bool)>>>,  // Card Ctrl/Shift-clicked this frame: (name, shift)
+    prefetched: Arc<Mutex<Option<PrefetchedPage>>>,  // One page buffered ahead of the scroll position
+    hidden_by_filters: Arc<Mutex<Vec<usize>>>,  // Posts the filters took out of each loaded page
+    prefetching: Arc<Mutex<bool>>,          // Whether a prefetch request is in flight
+    egui_ctx: Option<egui::Context>,        // Lets worker threads wake the UI as soon as data lands
+    transparent_window: bool,  // Only asked for when the theme was translucent at launch
+    image_cache: Option<Arc<image_cache::ImageCache>>,  // None if the platform has no cache dir
+    store: Option<Arc<store::Store>>,  // Offline copy of fetched content; None if it couldn't be opened
+    sync_retry_at: Option<Instant>,  // Next reconnect attempt while offline
+    refresh_at: Option<Instant>,     // Next background check for new posts
+    notice: Option<(String, Instant)>,  // Brief confirmation and when it goes away
+    new_posts: Arc<Mutex<Option<NewPosts>>>,  // Found by that check, not yet shown
+    keep_cached_feed: Arc<Mutex<bool>>,  // Skip fetching the restored feed at the next sign-in
+    kept_feed_synced_at: Arc<Mutex<Option<i64>>>,  // While that feed is still showing, when it was fetched
+    stale_check_at: Option<Instant>,
+    inbox: Arc<Mutex<inbox::Inbox>>,
+    inbox_check_at: Option<Instant>,  // Next poll of the inbox
+    me: Arc<Mutex<Option<Me>>>,       // The signed-in account, for the header
+    me_check_at: Option<Instant>,     // Next refresh of it
+    watchdog: Arc<watchdog::Watchdog>,  // Clears loading flags that never came back down
+    #[cfg(feature = "notifications")]
+    tray: Option<Option<inbox::Tray>>,  // None until inbox checks are on; Some(None) on a desktop without a tray
+    open_post: Arc<Mutex<Option<Post>>>,    // Post whose comment thread is being viewed
+    comments: Arc<Mutex<Vec<comments::CommentThing>>>,
+    loading_comments: Arc<Mutex<bool>>,
+    comment_tree: Arc<Mutex<comments::TreeView>>,  // Which parts of the thread are expanded
+    thread_sort: Arc<Mutex<String>>,  // Sort of the open thread; starts out as the one in settings
+    reply_draft: Arc<Mutex<Option<comments::Draft>>>,  // Open reply box, if any
+    post_draft: Arc<Mutex<Option<submit::Draft>>>,  // New post composer, open while Some
+    draft_edited_at: Option<Instant>,  // First edit to the composer not yet written to disk
+    drafts: Arc<Mutex<Vec<(i64, submit::Saved)>>>,  // Saved and scheduled posts, as in the store
+    drafts_open: bool,
+    schedule_check_at: Option<Instant>,  // Next look for scheduled posts that are due
+    sending_scheduled: Arc<Mutex<Vec<i64>>>,  // Drafts being submitted now
+    archive_at: Option<Instant>,  // Next archive run
+    archive_status: Arc<Mutex<archive::Status>>,
+    archive_open: bool,
+    archive_input: String,  // Subreddit being added in settings
+    scheduled_sent: Arc<Mutex<Vec<String>>>,  // How scheduled posts went, for a notice
+    pending_sends: Arc<Mutex<Vec<Arc<PendingSend>>>>,  // Replies and posts in their undo window, oldest first
+    mention_lookup: Arc<Mutex<MentionLookup>>,
+    revealed_nsfw: Arc<Mutex<HashSet<String>>>,  // Blurred posts the reader clicked to show
+    expanded_posts: Arc<Mutex<HashSet<String>>>,  // Cards showing their full preview and text inline
+    heat: Arc<Mutex<heat::Heat>>,  // Recent scores per subreddit, for the heat tints
+    subreddit_about: Arc<Mutex<Option<subreddit::SubredditAbout>>>,  // Banner and counts for the header
+    glosses: Arc<Mutex<translate::Glosses>>,  // Machine translations this session, by original text
+    export_progress: Arc<Mutex<Option<export::Progress>>>,  // While an export is being picked or fetched
+    export_saved: Arc<Mutex<Option<(std::path::PathBuf, usize)>>>,  // Where the last export went and how many posts, for a notice
+    rules_exported: Arc<Mutex<Option<(std::path::PathBuf, usize)>>>,  // Same for filter rules
+    rule_import: Arc<Mutex<Option<filters::Import>>>,
+    highlighter: Arc<Mutex<filters::Highlighter>>,
+    accents: Arc<palette::Accents>,
+    discover: Arc<Mutex<Option<discover::Discover>>>,  // Subreddit directory, shown in place of the feed
+    history: Arc<Mutex<Option<history::History>>>,     // Opened posts, likewise
+    profile: Arc<Mutex<Option<profile::Profile>>>,  // User profile being viewed
+    reader: Arc<Mutex<Option<reader::Reader>>>,  // Article open in reader mode, over everything else in the main panel
+    updating_subscription: Arc<Mutex<bool>>,  // Join/leave request in flight
+    go_to_query: String,                    // Contents of the "Go to subreddit" box
+    sidebar_query: String,                  // Filters the sidebar's subreddit list
+    subreddit_suggestions: Arc<Mutex<(String, Vec<String>)>>,  // Autocomplete results and the query they answer
+    animations: Arc<Mutex<animation::Animations>>,  // GIFs playing inline
+    #[cfg(feature = "video")]
+    videos: Arc<Mutex<std::collections::HashMap<String, video::VideoPlayer>>>,  // Active inline players keyed by post URL
+    #[cfg(feature = "video")]
+    hover_preview: Arc<Mutex<Option<HoverPreview>>>,  // Clip playing under the pointer, if any
+}
+
+// At most one thumbnail previews at a time; it stops once a frame goes by without the hover
+#[cfg(feature = "video")]
+struct HoverPreview {
+    post: String,  // Fullname
+    player: Option<video::VideoPlayer>,  // None when it failed to start, so it isn't retried every frame
+    frame: u64,    // Last frame the thumbnail was hovered
+}
+
+// How Rustle connects to Reddit, chosen in the onboarding wizard
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum AuthMethod {
+    #[default]
+    Script,     // Personal "script" app with the account's username and password
+    Browser,    // "Installed app" approved on reddit.com, kept signed in with a refresh token
+    Anonymous,  // "Installed app" token with no account; subreddits are picked locally
+}
+
+// Credentials for one way into Reddit; several can be saved and switched between
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct Account {
+    auth_method: AuthMethod,
+    client_id: String,
+    #[serde(skip_serializing)]  // Secrets are kept in the keyring, see AccountSecrets
+    client_secret: String,
+    username: String,
+    #[serde(skip_serializing)]
+    password: String,
+    #[serde(skip_serializing)]
+    refresh_token: String,  // Browser sign-in only
+    device_id: String,      // Identifies this install to Reddit when browsing without an account
+    subreddit_picks: Vec<String>,  // Stand-in for subscriptions when browsing without an account
+    spell_language: String,  // Reply box dictionary, e.g. "en_US"; empty follows the system
+}
+
+impl Account {
+    fn has_credentials(&self) -> bool {
+        match self.auth_method {
+            AuthMethod::Script => !self.client_id.is_empty() && !self.client_secret.is_empty()
+                && !self.username.is_empty() && !self.password.is_empty(),
+            AuthMethod::Browser => !self.client_id.is_empty() && !self.refresh_token.is_empty(),
+            // The app is optional here; without one the public endpoints are used
+            AuthMethod::Anonymous => self.client_id.is_empty() || !self.device_id.is_empty(),
+        }
+    }
+
+    // Dictionary the reply box is checked against; None with spellchecking off
+    fn spelling_language(&self) -> Option<String> {
+        match self.spell_language.as_str() {
+            "" => Some(spellcheck::system_language()),
+            spellcheck::OFF => None,
+            language => Some(language.to_string()),
+        }
+    }
+
+    // Name shown in the account switcher
+    fn label(&self) -> String {
+        match self.auth_method {
+            AuthMethod::Anonymous => "Guest".to_string(),
+            _ => format!("u/{}", self.username),
+        }
+    }
+
+    // Identifies whose token a stored session holds
+    fn session_key(&self) -> String {
+        format!("{:?}/{}/{}", self.auth_method, self.client_id, self.username.to_lowercase())
+    }
+
+    // Signing in again as the same user or guest replaces the saved entry rather than adding one
+    fn same_identity(&self, other: &Account) -> bool {
+        self.auth_method == other.auth_method && match self.auth_method {
+            AuthMethod::Anonymous => self.client_id == other.client_id,
+            _ => self.username.eq_ignore_ascii_case(&other.username),
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]  // Settings saved by older versions are missing newer fields
+struct Settings {
+    // Flattened so settings saved before multiple accounts still load as the active one
+    #[serde(flatten)]
+    account: Account,
+    accounts: Vec<Account>,  // The other saved accounts, for the switcher
+    favorite_subreddits: Vec<String>,  // Pinned to the top of the sidebar, in the user's order
+    sidebar_open: bool,   // Subreddit list beside the feed; closed leaves the feed the full width
+    #[serde(alias = "dark_mode", deserialize_with = "theme::deserialize_setting")]
+    theme: theme::Theme,
+    font_size: f32,   // Multiplies every text size; 1.0 is a 12.5 pt body
+    ui_scale: f32,    // Zoom on top of the display's own scaling, for HiDPI screens
+    image_cache_mb: u32,  // Upper bound for the on-disk image cache
+    image_accents: bool,  // Tint each card's frame with its thumbnail's dominant color
+    stills_only: bool,    // Never play animated previews inline
+    autoplay_animations: bool,  // GIFs in wide and expanded cards play without a click; off by default, as frames take memory
+    hover_previews: bool,  // Loop a muted clip while hovering a video/GIF thumbnail
+    show_nsfw: bool,      // Off hides over_18 posts entirely
+    blur_nsfw: bool,      // Cover NSFW thumbnails until clicked
+    label_nsfw: bool,     // Tag over_18 posts in the feed
+    comment_sort: String,  // Reddit sort name: confidence, top, new, ...
+    local_prefs_only: bool,  // Ignore the account's preferences on reddit.com
+    ignored_users: Vec<String>,  // Hidden locally, on top of the account's blocked list
+    snippets: Vec<Snippet>,  // Offered in the reply box
+    filters: Vec<FilterRule>,  // Keep matching posts out of feeds
+    muted_media: std::collections::HashMap<String, Vec<MediaKind>>,  // Kinds of post hidden per feed, keyed by lowercase feed name
+    card_layout: CardLayout,
+    density: Density,
+    card_actions: CardActions,
+    hide_read: bool,      // Leave posts read on an earlier visit out of the feed
+    sync_visits: bool,    // Count posts Reddit says were opened elsewhere as read
+    auto_refresh_minutes: u32,  // How often to look for new posts in the background; 0 is never
+    page_size: u32,       // Posts per feed request; more means fewer requests, each slower
+    load_ahead: u32,      // Load the next page once this many posts are left below the last one on screen
+    startup_refresh: StartupRefresh,
+    reader_mode: bool,    // Open links to articles in the built-in reader rather than the browser
+    open_with: open_with::OpenWith,  // Where each kind of link goes
+    link_opening: std::collections::HashMap<String, reader::LinkOpen>,  // Per-domain overrides, keyed by reader::domain_key
+    translation_url: String,  // LibreTranslate-compatible server for glosses of flairs and rules; empty is off
+    #[serde(skip_serializing)]  // In the keyring with the account secrets
+    translation_key: String,
+    translate_to: String,     // Language code of the glosses
+    archive_subreddits: Vec<String>,  // Archived in the background; empty is off
+    archive_hours: u32,               // Between archive runs
+    inbox_check_minutes: u32,   // How often to look for unread messages; 0 is never
+    undo_send_seconds: u32,  // How long a submitted reply or post waits before it's sent, so it can be taken back
+    heat_colors: bool,    // Tint scores and comment counts by how they rank in their subreddit
+    absolute_timestamps: bool,  // Dates instead of "3h ago" on cards
+    track_usage: bool,    // Keep local usage stats; they never leave the machine
+    time_limits: Vec<focus::TimeLimit>,  // Soft daily limits, which need usage stats on
+    saved_views: std::collections::HashMap<String, Vec<FeedView>>,  // Keyed by lowercase subreddit name
+    feed_prefs: std::collections::HashMap<String, FeedPrefs>,  // Likewise
+}
+
+impl Default for Settings {
+    // Default empty settings with the dark theme
+    fn default() -> Self {
+        Settings {
+            account: Account::default(),
+            accounts: Vec::new(),
+            favorite_subreddits: Vec::new(),
+            sidebar_open: true,
+            theme: theme::Theme::default(),
+            font_size: 1.0,   // Default font size
+            ui_scale: 1.0,
+            image_cache_mb: 200,
+            image_accents: false,
+            stills_only: false,
+            autoplay_animations: false,
+            hover_previews: true,
+            show_nsfw: false,
+            blur_nsfw: true,
+            label_nsfw: true,
+            comment_sort: "confidence".to_string(),
+            local_prefs_only: false,
+            ignored_users: Vec::new(),
+            snippets: Vec::new(),
+            filters: Vec::new(),
+            card_layout: CardLayout::default(),
+            density: Density::default(),
+            card_actions: CardActions::default(),
+            hide_read: false,
+            sync_visits: false,
+            auto_refresh_minutes: 0,
+            page_size: 25,  // Reddit's own default
+            load_ahead: 10,
+            startup_refresh: StartupRefresh::default(),
+            reader_mode: false,
+            open_with: open_with::OpenWith::default(),
+            translation_url: String::new(),
+            translation_key: String::new(),
+            translate_to: "en".to_string(),
+            archive_subreddits: Vec::new(),
+            archive_hours: 6,
+            link_opening: std::collections::HashMap::new(),
+            inbox_check_minutes: 0,
+            undo_send_seconds: 5,
+            heat_colors: false,
+            absolute_timestamps: false,
+            track_usage: true,
+            time_limits: Vec::new(),
+            saved_views: std::collections::HashMap::new(),
+            feed_prefs: std::collections::HashMap::new(),
+            muted_media: std::collections::HashMap::new(),
+        }
+    }
+}
+
+// Where secrets are kept. Tests get keyring's in-memory mock instead, so running them can't
+// overwrite or delete the real entries.
+fn keyring_entry(name: &str) -> keyring::Result<Entry> {
+    #[cfg(test)]
+    keyring::set_default_credential_builder(keyring::mock::default_credential_builder());
+    Entry::new("Rustle", name)
+}
+
+// Windows' credential manager holds at most this many UTF-16 units per entry
+const KEYRING_LIMIT: usize = 1280;
+
+// The parts of an account that go in the keyring, one entry per account, so the settings file
+// never holds them and no single entry grows with the number of accounts
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+struct AccountSecrets {
+    client_secret: String,
+    password: String,
+    refresh_token: String,
+}
+
+fn account_entry(index: usize) -> String {
+    format!("account-{}", index)
+}
+
+// Settings other than secrets, next to the other data files
+fn settings_path() -> Result<std::path::PathBuf> {
+    let dirs = directories::ProjectDirs::from("com", "spartanjubilee", "Rustle").context("No home directory for settings")?;
+    std::fs::create_dir_all(dirs.data_dir()).context("Failed to create the data folder")?;
+    Ok(dirs.data_dir().join("settings.json"))
+}
+
+// The settings file, or None when there isn't one yet. One that can't be read (a partial write,
+// or a newer version's) is renamed to settings.json.bak before anything can save over it.
+fn read_settings(path: &std::path::Path) -> Option<Settings> {
+    let json = std::fs::read_to_string(path).ok()?;
+    match serde_json::from_str(&json) {
+        Ok(settings) => Some(settings),
+        Err(e) => {
+            let backup = path.with_extension("json.bak");
+            tracing::error!(error = %e, backup = %backup.display(), "Settings file couldn't be read; starting from defaults");
+            if let Err(e) = std::fs::rename(path, &backup) {
+                tracing::error!(error = %e, "Couldn't move the unreadable settings file aside");
+            }
+            Some(Settings::default())
+        }
+    }
+}
+
+// Written next to the file and renamed over it, so a crash mid-write leaves the old one whole
+fn write_settings(path: &std::path::Path, json: &str) -> Result<()> {
+    let temp = path.with_extension("json.tmp");
+    std::fs::write(&temp, json).context("Failed to write settings")?;
+    std::fs::rename(&temp, path).context("Failed to replace the settings file")?;
+    Ok(())
+}
+
+impl Settings {
+    fn load() -> Self {
+        let Some(mut settings) = settings_path().ok().and_then(|path| read_settings(&path)) else {
+            return Settings::load_legacy().unwrap_or_default();
+        };
+        for (name, account) in settings.keyring_accounts_mut() {
+            let secrets = keyring_entry(&name).and_then(|entry| entry.get_password()).ok()
+                .and_then(|json| serde_json::from_str::<AccountSecrets>(&json).ok());
+            if let Some(secrets) = secrets {
+                account.client_secret = secrets.client_secret;
+                account.password = secrets.password;
+                account.refresh_token = secrets.refresh_token;
+            }
+        }
+        settings.translation_key = keyring_entry("translation").and_then(|entry| entry.get_password()).unwrap_or_default();
+        settings
+    }
+
+    // Earlier versions kept all the settings in one keyring entry, which Windows can't hold once
+    // there are a few accounts. It's read once more and deleted on the first save.
+    fn load_legacy() -> Option<Self> {
+        let stored = keyring_entry("credentials").ok()?.get_password().ok()?;
+        serde_json::from_str(&stored).ok()
+    }
+
+    // Keyring entry name for each account, the active one first
+    fn keyring_accounts_mut(&mut self) -> impl Iterator<Item = (String, &mut Account)> {
+        std::iter::once(&mut self.account).chain(&mut self.accounts)
+            .enumerate()
+            .map(|(index, account)| (account_entry(index), account))
+    }
+
+    // Entry name and payload for everything kept in the keyring
+    fn keyring_entries(&self) -> Result<Vec<(String, String)>> {
+        let mut entries = Vec::new();
+        for (index, account) in std::iter::once(&self.account).chain(&self.accounts).enumerate() {
+            let secrets = AccountSecrets {
+                client_secret: account.client_secret.clone(),
+                password: account.password.clone(),
+                refresh_token: account.refresh_token.clone(),
+            };
+            entries.push((account_entry(index), serde_json::to_string(&secrets)?));
+        }
+        entries.push(("translation".to_string(), self.translation_key.clone()));
+        Ok(entries)
+    }
+
+    // Take the account's reddit.com preferences as the starting point unless told not to
+    fn apply_account_prefs(&mut self, prefs: &AccountPrefs) {
+        if self.local_prefs_only {
+            return;
+        }
+        self.show_nsfw = prefs.over_18;
+        self.label_nsfw = prefs.label_nsfw;
+        if COMMENT_SORTS.iter().any(|(sort, _)| *sort == prefs.default_comment_sort) {
+            self.comment_sort = prefs.default_comment_sort.clone();
+        }
+    }
+
+    fn has_credentials(&self) -> bool {
+        self.account.has_credentials()
+    }
+
+    // False when browsing without an account, where voting, replying and the like aren't possible
+    fn signed_in(&self) -> bool {
+        self.account.auth_method != AuthMethod::Anonymous
+    }
+
+    // "/" for an account's own front page, or the picked subreddits combined into one feed
+    fn front_page(&self) -> String {
+        if self.signed_in() || self.account.subreddit_picks.is_empty() {
+            "/".to_string()
+        } else {
+            format!("/r/{}", self.account.subreddit_picks.join("+"))
+        }
+    }
+
+    // Park the active account in the saved list and start a blank one for the wizard to fill in
+    fn begin_new_account(&mut self) {
+        let current = std::mem::take(&mut self.account);
+        if current.has_credentials() {
+            self.accounts.insert(0, current);
+        }
+    }
+
+    // Make a saved account the active one; the previous one takes its place in the list
+    fn switch_account(&mut self, index: usize) {
+        if let Some(account) = self.accounts.get_mut(index) {
+            std::mem::swap(&mut self.account, account);
+        }
+    }
+
+    // Drop saved entries for whoever just signed in
+    fn forget_duplicate_accounts(&mut self) {
+        let active = &self.account;
+        self.accounts.retain(|account| !account.same_identity(active));
+    }
+
+    fn translation(&self) -> Option<translate::Backend> {
+        let url = self.translation_url.trim();
+        let target = self.translate_to.trim();
+        (!url.is_empty() && !target.is_empty()).then(|| translate::Backend {
+            url: url.to_string(),
+            api_key: self.translation_key.trim().to_string(),
+            target: target.to_lowercase(),
+        })
+    }
+
+    fn views_for(&self, subreddit: &str) -> &[FeedView] {
+        self.saved_views.get(&subreddit.to_lowercase()).map_or(&[], Vec::as_slice)
+    }
+
+    fn feed_prefs(&self, feed: &str) -> FeedPrefs {
+        self.feed_prefs.get(&feed.to_lowercase()).cloned().unwrap_or_default()
+    }
+
+    fn update_feed_prefs(&mut self, feed: &str, change: impl FnOnce(&mut FeedPrefs)) {
+        let prefs = self.feed_prefs.entry(feed.to_lowercase()).or_default();
+        change(prefs);
+        if *prefs == FeedPrefs::default() {
+            self.feed_prefs.remove(&feed.to_lowercase());
+        }
+    }
+
+    fn density_for(&self, feed: &str) -> Density {
+        self.feed_prefs.get(&feed.to_lowercase()).and_then(|prefs| prefs.density).unwrap_or(self.density)
+    }
+
+    fn muted_media(&self, feed: &str) -> &[MediaKind] {
+        self.muted_media.get(&feed.to_lowercase()).map_or(&[], Vec::as_slice)
+    }
+
+    fn set_muted(&mut self, feed: &str, kind: MediaKind, muted: bool) {
+        let kinds = self.muted_media.entry(feed.to_lowercase()).or_default();
+        kinds.retain(|muted| *muted != kind);
+        if muted {
+            kinds.push(kind);
+        }
+        if kinds.is_empty() {
+            self.muted_media.remove(&feed.to_lowercase());
+        }
+    }
+
+    fn is_favorite(&self, subreddit: &str) -> bool {
+        self.favorite_subreddits.iter().any(|name| name.eq_ignore_ascii_case(subreddit))
+    }
+
+    // New favorites go to the end of the list
+    fn set_favorite(&mut self, subreddit: &str, favorite: bool) {
+        if favorite == self.is_favorite(subreddit) {
+            return;
+        }
+        if favorite {
+            self.favorite_subreddits.push(subreddit.to_string());
+        } else {
+            self.favorite_subreddits.retain(|name| !name.eq_ignore_ascii_case(subreddit));
+        }
+    }
+
+    // Drag-to-reorder: the favorite at `from` takes the place of the one at `to`
+    fn move_favorite(&mut self, from: usize, to: usize) {
+        if from >= self.favorite_subreddits.len() {
+            return;
+        }
+        let name = self.favorite_subreddits.remove(from);
+        self.favorite_subreddits.insert(to.min(self.favorite_subreddits.len()), name);
+    }
+
+    fn save(&self) -> Result<()> {
+        for (name, payload) in self.keyring_entries()? {
+            anyhow::ensure!(payload.encode_utf16().count() <= KEYRING_LIMIT, "Too long for the keyring: {}", name);
+            let entry = keyring_entry(&name)?;
+            if payload.is_empty() {
+                // Some platforms refuse empty passwords, and there's nothing to keep anyway
+                let _ = entry.delete_password();
+            } else {
+                entry.set_password(&payload)?;
+            }
+        }
+        // Accounts removed since the last save leave entries past the end
+        for index in 1 + self.accounts.len().. {
+            if keyring_entry(&account_entry(index))?.delete_password().is_err() {
+                break;
+            }
+        }
+
+        let path = settings_path()?;
+        let migrating = !path.exists();
+        write_settings(&path, &serde_json::to_string_pretty(self)?)?;
+        if migrating {
+            let _ = keyring_entry("credentials")?.delete_password();
+        }
+        Ok(())
+    }
+}
+
+// The last access token, kept next to the credentials so a restart within the hour can skip
+// signing in again. Browser sign-ins' refresh token is already saved with the account itself.
+#[derive(Serialize, Deserialize)]
+struct StoredSession {
+    account: String,  // Account::session_key of whoever the token belongs to
+    access_token: String,
+    expires_at: i64,  // Unix time
+}
+
+// Replays sign in with a placeholder token, which mustn't replace the real one
+impl StoredSession {
+    fn load() -> Option<Self> {
+        if replay::replaying() {
+            return None;
+        }
+        let keyring = keyring_entry("session").ok()?;
+        serde_json::from_str(&keyring.get_password().ok()?).ok()
+    }
+
+    // Best effort; losing it only means signing in again next launch
+    fn save(&self) {
+        if replay::replaying() {
+            return;
+        }
+        if let (Ok(keyring), Ok(json)) = (keyring_entry("session"), serde_json::to_string(self)) {
+            let _ = keyring.set_password(&json);
+        }
+    }
+
+    fn forget() {
+        if replay::replaying() {
+            return;
+        }
+        if let Ok(keyring) = keyring_entry("session") {
+            let _ = keyring.delete_password();
+        }
+    }
+
+    // Belongs to this account and won't need renewing straight away
+    fn usable_for(&self, account: &Account, now: i64) -> bool {
+        self.account == account.session_key() && self.expires_at - now > TOKEN_RENEW_MARGIN.as_secs() as i64
+    }
+}
+
+impl RedditApp {
+    fn with_settings(settings: Settings) -> Self {
+        let has_credentials = settings.has_credentials();
+        let onboarding = (!has_credentials).then(|| onboarding::Wizard::new(settings.clone(), false));
+
+        Self { 
+            posts: Arc::new(Mutex::new(Vec::new())),
+            loading: Arc::new(Mutex::new(has_credentials)),
+            error_message: Arc::new(Mutex::new(None)),
+            reddit_client: Arc::new(Mutex::new(None)),
+            after: Arc::new(Mutex::new(None)),
+            initial_load: Arc::new(Mutex::new(has_credentials)),
+            scroll_to_top: Arc::new(Mutex::new(true)),
+            show_settings: false,
+            onboarding,
+            lightbox: None,
+            inspector: None,
+            console: None,
+            digest_viewer: None,
+            digest: Arc::new(Mutex::new(None)),
+            digest_progress: Arc::new(Mutex::new(None)),
+            usage: Arc::new(Mutex::new(usage::Tracker::default())),
+            usage_dashboard: None,
+            installed_themes: themes_dir().map(|dir| theme::load_dir(&dir)).unwrap_or_default(),
+            applied_scale: None,
+            error_log: bug_report::ErrorLog::default(),
+            hints: Arc::new(Mutex::new(hints::Hints::default())),
+            focus: focus::Focus::default(),
+            speller: spellcheck::Speller::default(),
+            demo: false,
+            settings,
+            settings_modified: false,
+            has_credentials,
+            current_subreddit: Arc::new(Mutex::new("home".to_string())),
+            current_view: Arc::new(Mutex::new(None)),
+            view_editor: None,
+            subreddits: Arc::new(Mutex::new(Vec::new())),
+            loading_subreddits: Arc::new(Mutex::new(false)),
+            subreddits_fetched: Arc::new(Mutex::new(0)),
+            last_scroll_pos: Arc::new(Mutex::new(0.0)),
+            feed_rows: Arc::new(Mutex::new(FeedRows::default())),
+            feed_scroll_return: Arc::new(Mutex::new(None)),
+            feed_states: Arc::new(Mutex::new(std::collections::HashMap::new())),
+            authenticating: Arc::new(Mutex::new(false)),
+            account_prefs: Arc::new(Mutex::new(None)),
+            blocked_users: Arc::new(Mutex::new(HashSet::new())),
+            moderated: Arc::new(Mutex::new(Vec::new())),
+            read_posts: Arc::new(Mutex::new(ReadPosts::default())),
+            read_saved_at: Instant::now(),
+            today_start: None,
+            hidden_posts: Arc::new(Mutex::new(HashSet::new())),
+            flair_request: Arc::new(Mutex::new(None)),
+            subreddit_request: Arc::new(Mutex::new(None)),
+            viewer_request: Arc::new(Mutex::new(None)),
+            opting_in: Arc::new(Mutex::new(false)),
+            selection: Arc::new(Mutex::new(Selection::default())),
+            selection_click: Arc::new(Mutex::new(None)),
+            prefetched: Arc::new(Mutex::new(None)),
+            hidden_by_filters: Arc::new(Mutex::new(Vec::new())),
+            prefetching: Arc::new(Mutex::new(false)),
+            egui_ctx: None,
+            transparent_window: false,
+            image_cache: None,
+            store: None,
+            sync_retry_at: None,
+            refresh_at: None,
+            notice: None,
+            new_posts: Arc::new(Mutex::new(None)),
+            keep_cached_feed: Arc::new(Mutex::new(false)),
+            kept_feed_synced_at: Arc::new(Mutex::new(None)),
+            stale_check_at: None,
+            inbox: Arc::new(Mutex::new(inbox::Inbox::default())),
+            inbox_check_at: None,
+            me: Arc::new(Mutex::new(None)),
+            me_check_at: None,
+            watchdog: Arc::new(watchdog::Watchdog::default()),
+            #[cfg(feature = "notifications")]
+            tray: None,
+            open_post: Arc::new(Mutex::new(None)),
+            comments: Arc::new(Mutex::new(Vec::new())),
+            loading_comments: Arc::new(Mutex::new(false)),
+            comment_tree: Arc::new(Mutex::new(comments::TreeView::default())),
+            thread_sort: Arc::new(Mutex::new("confidence".to_string())),
+            reply_draft: Arc::new(Mutex::new(None)),
+            post_draft: Arc::new(Mutex::new(None)),
+            draft_edited_at: None,
+            drafts: Arc::new(Mutex::new(Vec::new())),
+            drafts_open: false,
+            schedule_check_at: None,
+            sending_scheduled: Arc::new(Mutex::new(Vec::new())),
+            archive_at: None,
+            archive_status: Arc::new(Mutex::new(archive::Status::default())),
+            archive_open: false,
+            archive_input: String::new(),
+            scheduled_sent: Arc::new(Mutex::new(Vec::new())),
+            pending_sends: Arc::new(Mutex::new(Vec::new())),
+            mention_lookup: Arc::new(Mutex::new(MentionLookup::default())),
+            revealed_nsfw: Arc::new(Mutex::new(HashSet::new())),
+            expanded_posts: Arc::new(Mutex::new(HashSet::new())),
+            heat: Arc::new(Mutex::new(heat::Heat::default())),
+            subreddit_about: Arc::new(Mutex::new(None)),
+            glosses: Arc::new(Mutex::new(translate::Glosses::default())),
+            export_progress: Arc::new(Mutex::new(None)),
+            export_saved: Arc::new(Mutex::new(None)),
+            rules_exported: Arc::new(Mutex::new(None)),
+            rule_import: Arc::new(Mutex::new(None)),
+            highlighter: Arc::new(Mutex::new(filters::Highlighter::default())),
+            accents: Arc::new(palette::Accents::default()),
+            discover: Arc::new(Mutex::new(None)),
+            history: Arc::new(Mutex::new(None)),
+            profile: Arc::new(Mutex::new(None)),
+            reader: Arc::new(Mutex::new(None)),
+            updating_subscription: Arc::new(Mutex::new(false)),
+            go_to_query: String::new(),
+            sidebar_query: String::new(),
+            subreddit_suggestions: Arc::new(Mutex::new((String::new(), Vec::new()))),
+            animations: Arc::new(Mutex::new(animation::Animations::default())),
+            #[cfg(feature = "video")]
+            videos: Arc::new(Mutex::new(std::collections::HashMap::new())),
+            #[cfg(feature = "video")]
+            hover_preview: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    fn restore_cached_feed(&mut self, storage: &dyn eframe::Storage) {
+        if let Some(cached) = eframe::get_value::<CachedFeed>(storage, FEED_CACHE_KEY) {
+            if cached.posts.is_empty() {
+                return;
+            }
+            if let Some(ctx) = &self.egui_ctx {
+                prefetch_images(ctx, &cached.posts, &self.settings);
+            }
+            // Its age is when the offline store last fetched it; without a record it counts as stale
+            let synced_at = self.store.as_ref()
+                .and_then(|store| store.load_feed(&cached.subreddit, cached.view.as_ref()).ok().flatten())
+                .map(|synced| synced.synced_at);
+            let age = synced_at.map_or(i64::MAX, |at| store::now() - at);
+            if !self.settings.startup_refresh.wants_refresh(age) {
+                *self.keep_cached_feed.lock().unwrap() = true;
+                *self.kept_feed_synced_at.lock().unwrap() = synced_at;
+                let fetched = synced_at.map_or("earlier".to_string(), |at| relative_time(at as f64, store::now()));
+                self.notice = Some((format!("Showing the feed from {}; refresh for new posts", fetched), Instant::now() + NOTICE_DURATION));
+            }
+            *self.current_subreddit.lock().unwrap() = cached.subreddit;
+            *self.current_view.lock().unwrap() = cached.view;
+            *self.posts.lock().unwrap() = cached.posts;
+            *self.after.lock().unwrap() = cached.after;
+            // Show the cached posts right away instead of the full-screen spinner
+            *self.initial_load.lock().unwrap() = false;
+        }
+    }
+
+    fn restore_read_posts(&self) {
+        if let Some(names) = self.store.as_ref().and_then(|store| store.read_posts().ok()) {
+            self.read_posts.lock().unwrap().names = names;
+        }
+    }
+
+    // Only remembered here; save_read_posts writes them out in batches
+    fn mark_read(&self, names: &[String]) {
+        let mut read_posts = self.read_posts.lock().unwrap();
+        for name in names {
+            read_posts.mark(name);
+        }
+    }
+
+    // Hands whatever was marked since the last call to a background thread
+    fn save_read_posts(&mut self) -> Option<thread::JoinHandle<()>> {
+        self.read_saved_at = Instant::now();
+        let unsaved = std::mem::take(&mut self.read_posts.lock().unwrap().unsaved);
+        let store = self.store.clone().filter(|_| !unsaved.is_empty())?;
+        Some(thread::spawn(move || {
+            if let Err(e) = store.mark_read(&unsaved) {
+                tracing::warn!(error = %format!("{:#}", e), "Couldn't save read posts");
+            }
+        }))
+    }
+
+    // Sample posts aren't the reader's own browsing, and without a store there's nowhere to keep stats
+    fn tracking_usage(&self) -> bool {
+        self.settings.track_usage && !self.demo && self.store.is_some()
+    }
+
+    fn note_opened(&self, post: &Post) {
+        if self.tracking_usage() {
+            self.usage.lock().unwrap().opened(post);
+        }
+        let Some(store) = self.store.clone() else {
+            return;
+        };
+        // Written on a worker, since the archiver can hold the database for a while; coming back
+        // to the history then shows the visit at the top
+        let reload = self.history.lock().unwrap().as_mut().map(|history| history.loading = true).is_some();
+        let post = post.clone();
+        let history = self.history.clone();
+        let egui_ctx = self.egui_ctx.clone();
+        thread::spawn(move || {
+            if let Err(e) = store.record_visit(&post) {
+                tracing::warn!(error = %format!("{:#}", e), "Couldn't record history");
+            }
+            if reload {
+                fetch_history(&store, &history, &egui_ctx, false);
+            }
+        });
+    }
+
+    // The first page for the current search, or the next one after those showing
+    fn load_history(&self, more: bool) {
+        let Some(store) = self.store.clone() else {
+            return;
+        };
+        match self.history.lock().unwrap().as_mut() {
+            Some(history) => history.loading = true,
+            None => return,
+        }
+        let history = self.history.clone();
+        let egui_ctx = self.egui_ctx.clone();
+        thread::spawn(move || fetch_history(&store, &history, &egui_ctx, more));
+    }
+
+    // Wherever settings send this kind of link
+    fn open_link(&self, ctx: &egui::Context, post: &Post) {
+        let (content, opener) = self.link_target(post);
+        match (content, opener) {
+            (open_with::Content::Article, open_with::Opener::Internal) => self.open_reader(post),
+            (open_with::Content::Reddit, open_with::Opener::Internal) => self.open_comments(post),
+            (_, open_with::Opener::Internal) => {
+                // The media viewer is drawn outside the feed, so it opens at the end of the frame
+                *self.viewer_request.lock().unwrap() = Some(post.clone());
+                self.mark_read(std::slice::from_ref(&post.name));
+                self.note_opened(post);
+            }
+            (_, open_with::Opener::Command(command)) => match open_with::run(&command, &post.url) {
+                Ok(()) => {
+                    self.mark_read(std::slice::from_ref(&post.name));
+                    self.note_opened(post);
+                }
+                Err(e) => *self.error_message.lock().unwrap() = Some(RustleError::classify(&e)),
+            },
+            _ => self.open_in_browser(ctx, post),
+        }
+    }
+
+    // Where a post's link goes, never Automatic: the reader's per-domain choices come first for
+    // articles, then the setting for the kind of content
+    fn link_target(&self, post: &Post) -> (open_with::Content, open_with::Opener) {
+        let content = open_with::classify(post);
+        let per_domain = self.settings.link_opening.contains_key(&reader::domain_key(&post.domain));
+        let media = matches!(content, open_with::Content::Image | open_with::Content::Video);
+        let opener = match self.settings.open_with.get(content) {
+            opener if *opener == open_with::Opener::Automatic || (content == open_with::Content::Article && per_domain) => {
+                if self.opens_in_reader(post) { open_with::Opener::Internal } else { open_with::Opener::Browser }
+            }
+            // Only posts with a preview can go in the viewer
+            open_with::Opener::Internal if media && !lightbox::is_media(post) => open_with::Opener::Browser,
+            opener => opener.clone(),
+        };
+        (content, opener)
+    }
+
+    // Hover text for a post's title
+    fn describe_link(&self, post: &Post) -> String {
+        let (content, opener) = self.link_target(post);
+        match opener {
+            open_with::Opener::Internal => format!("Opens in the {}", content.internal_label().to_lowercase()),
+            open_with::Opener::Command(command) => format!("Opens with {}", command.split_whitespace().next().unwrap_or_default()),
+            _ => format!("Opens {} in your browser", post.domain),
+        }
+    }
+
+    fn open_in_browser(&self, ctx: &egui::Context, post: &Post) {
+        ctx.open_url(egui::OpenUrl::new_tab(&post.url));
+        self.mark_read(std::slice::from_ref(&post.name));
+        self.note_opened(post);
+    }
+
+    fn opens_in_reader(&self, post: &Post) -> bool {
+        reader::opens_in_reader(post, self.settings.reader_mode, &self.settings.link_opening)
+    }
+
+    fn open_reader(&self, post: &Post) {
+        *self.reader.lock().unwrap() = Some(reader::Reader { post: post.clone(), article: None });
+        self.mark_read(std::slice::from_ref(&post.name));
+        self.note_opened(post);
+
+        let reader = self.reader.clone();
+        let name = post.name.clone();
+        let url = post.url.clone();
+        let egui_ctx = self.egui_ctx.clone();
+        thread::spawn(move || {
+            let rt = tokio::runtime::Runtime::new().unwrap();
+            rt.block_on(async {
+                let result = reader::fetch(&url).await.map_err(|e| format!("{:#}", e));
+                // Unless the reader was closed or moved on to another link meanwhile
+                if let Some(open) = reader.lock().unwrap().as_mut().filter(|open| open.post.name == name) {
+                    open.article = Some(result);
+                }
+                if let Some(ctx) = &egui_ctx {
+                    ctx.request_repaint();
+                }
+            });
+        });
+    }
+
+    // Offers a link to hint mode while it's on
+    fn hint(&self, ui: &egui::Ui, response: &egui::Response, jump: impl FnOnce() -> hints::Jump) {
+        let mut hints = self.hints.lock().unwrap();
+        if hints.active() && ui.clip_rect().intersects(response.rect) {
+            hints.add(response.rect, jump());
+        }
+    }
+
+    // f starts hinting; while it's on, every keystroke goes to picking a label
+    fn handle_hint_keys(&mut self, ctx: &egui::Context) {
+        let mut hints = self.hints.lock().unwrap();
+        if !hints.active() {
+            if self.onboarding.is_none() && !ctx.wants_keyboard_input()
+                && ctx.input_mut(|i| i.consume_key(egui::Modifiers::NONE, egui::Key::F)) {
+                hints.start();
+            }
+            return;
+        }
+        let (keys, rest): (Vec<_>, Vec<_>) = ctx.input_mut(|i| std::mem::take(&mut i.events))
+            .into_iter()
+            .partition(|event| matches!(event, egui::Event::Key { .. } | egui::Event::Text(_)));
+        ctx.input_mut(|i| i.events = rest);
+        let mut jump = None;
+        for event in keys {
+            match event {
+                egui::Event::Key { key: egui::Key::Escape, pressed: true, .. } => hints.cancel(),
+                egui::Event::Key { key: egui::Key::Backspace, pressed: true, .. } => hints.backspace(),
+                egui::Event::Text(text) => jump = text.chars().find_map(|c| hints.type_char(c)).or(jump),
+                _ => {}
+            }
+        }
+        drop(hints);
+        match jump {
+            Some(hints::Jump::Link(post)) => self.open_link(ctx, &post),
+            Some(hints::Jump::Comments(post)) => self.open_comments(&post),
+            Some(hints::Jump::Profile(name)) => self.open_profile(name),
+            Some(hints::Jump::Subreddit(name)) => self.switch_subreddit(name),
+            None => {}
+        }
+    }
+
+    // Called on every autosave; what was counted is dropped if tracking got turned off meanwhile
+    fn flush_usage(&mut self) {
+        let pending = self.usage.lock().unwrap().take();
+        if let Some(store) = self.store.as_ref().filter(|_| self.tracking_usage()) {
+            let _ = store.record_usage(&pending);
+            self.refresh_focus();
+        }
+    }
+
+    // Today's time per feed for the limits; also where a new day gets noticed
+    fn refresh_focus(&mut self) {
+        let Some(store) = &self.store else {
+            return;
+        };
+        let today = store.day_start().and_then(|day_start| Ok((day_start, store.feed_time(day_start)?)));
+        if let Ok((day_start, feeds)) = today {
+            self.focus.set_today(day_start, feeds);
+        }
+    }
+
+    fn show_focus_nudge(&mut self, ctx: &egui::Context) {
+        if !self.tracking_usage() || self.settings.time_limits.is_empty() || self.onboarding.is_some() {
+            return;
+        }
+        let feed = self.current_subreddit.lock().unwrap().clone();
+        let now = Instant::now();
+        let Some(nudge) = self.focus.check(&self.settings.time_limits, &feed, self.usage.lock().unwrap().pending(), now) else {
+            return;
+        };
+        match focus::show(ctx, &nudge) {
+            Some(focus::Choice::Snooze) => self.focus.snooze(&nudge, now),
+            Some(focus::Choice::KeepGoing) => self.focus.let_go(&nudge),
+            Some(focus::Choice::Leave) if nudge.is_app_wide() => ctx.send_viewport_cmd(egui::ViewportCommand::Close),
+            Some(focus::Choice::Leave) => self.switch_subreddit("home".to_string()),
+            None => {}
+        }
+    }
+
+    // Optimistically hides (or restores) the post, putting it back if Reddit refuses
+    fn set_hidden(&self, fullnames: Vec<String>, hidden: bool) {
+        let mut hidden_posts = self.hidden_posts.lock().unwrap();
+        for fullname in &fullnames {
+            if hidden {
+                hidden_posts.insert(fullname.clone());
+            } else {
+                hidden_posts.remove(fullname);
+            }
+        }
+        drop(hidden_posts);
+
+        let reddit_client = self.reddit_client.clone();
+        let settings = self.settings.clone();
+        let hidden_posts = self.hidden_posts.clone();
+        let error_message = self.error_message.clone();
+        let egui_ctx = self.egui_ctx.clone();
+
+        thread::spawn(move || {
+            let rt = tokio::runtime::Runtime::new().unwrap();
+            rt.block_on(async {
+                let result = match ensure_client(&reddit_client, &settings).await {
+                    Ok(client) if hidden => client.hide(&fullnames).await,
+                    Ok(client) => client.unhide(&fullnames).await,
+                    Err(e) => Err(e),
+                };
+
+                if let Err(e) = result {
+                    let mut hidden_posts = hidden_posts.lock().unwrap();
+                    for fullname in fullnames {
+                        if hidden {
+                            hidden_posts.remove(&fullname);
+                        } else {
+                            hidden_posts.insert(fullname);
+                        }
+                    }
+                    *error_message.lock().unwrap() = Some(RustleError::classify(&e));
+                    if let Some(ctx) = &egui_ctx {
+                        ctx.request_repaint();
+                    }
+                }
+            });
+        });
+    }
+
+    // Saves one at a time, since Reddit's save endpoint takes a single post
+    fn set_saved(&self, fullnames: Vec<String>, saved: bool) {
+        let reddit_client = self.reddit_client.clone();
+        let settings = self.settings.clone();
+        let posts = self.posts.clone();
+        let open_post = self.open_post.clone();
+        let error_message = self.error_message.clone();
+        let egui_ctx = self.egui_ctx.clone();
+
+        thread::spawn(move || {
+            let rt = tokio::runtime::Runtime::new().unwrap();
+            rt.block_on(async {
+                let result = async {
+                    let client = ensure_client(&reddit_client, &settings).await?;
+                    for fullname in &fullnames {
+                        if saved {
+                            client.save(fullname).await?;
+                        } else {
+                            client.unsave(fullname).await?;
+                        }
+                        if let Some(post) = posts.lock().unwrap().iter_mut().find(|post| &post.name == fullname) {
+                            post.saved = saved;
+                        }
+                        if let Some(post) = open_post.lock().unwrap().as_mut().filter(|post| &post.name == fullname) {
+                            post.saved = saved;
+                        }
+                    }
+                    Ok::<_, anyhow::Error>(())
+                }.await;
+
+                if let Err(e) = result {
+                    *error_message.lock().unwrap() = Some(RustleError::classify(&e));
+                }
+                if let Some(ctx) = &egui_ctx {
+                    ctx.request_repaint();
+                }
+            });
+        });
+    }
+
+    fn vote(&self, fullname: String, likes: Option<bool>) {
+        let reddit_client = self.reddit_client.clone();
+        let settings = self.settings.clone();
+        let posts = self.posts.clone();
+        let open_post = self.open_post.clone();
+        let error_message = self.error_message.clone();
+        let egui_ctx = self.egui_ctx.clone();
+
+        thread::spawn(move || {
+            let rt = tokio::runtime::Runtime::new().unwrap();
+            rt.block_on(async {
+                let result = match ensure_client(&reddit_client, &settings).await {
+                    Ok(client) => client.vote(&fullname, likes).await,
+                    Err(e) => Err(e),
+                };
+
+                match result {
+                    Ok(()) => {
+                        if let Some(post) = posts.lock().unwrap().iter_mut().find(|post| post.name == fullname) {
+                            post.apply_vote(likes);
+                        }
+                        if let Some(post) = open_post.lock().unwrap().as_mut().filter(|post| post.name == fullname) {
+                            post.apply_vote(likes);
+                        }
+                    }
+                    Err(e) => {
+                        *error_message.lock().unwrap() = Some(RustleError::classify(&e));
+                    }
+                }
+                if let Some(ctx) = &egui_ctx {
+                    ctx.request_repaint();
+                }
+            });
+        });
+    }
+
+    // Post or comment alike. Approving or removing a post in the mod queue takes it out of the feed.
+    fn moderate(&self, fullname: String, action: moderation::Action) {
+        let reddit_client = self.reddit_client.clone();
+        let settings = self.settings.clone();
+        let posts = self.posts.clone();
+        let open_post = self.open_post.clone();
+        let thread = self.comments.clone();
+        let current_subreddit = self.current_subreddit.clone();
+        let error_message = self.error_message.clone();
+        let egui_ctx = self.egui_ctx.clone();
+
+        thread::spawn(move || {
+            let rt = tokio::runtime::Runtime::new().unwrap();
+            rt.block_on(async {
+                let result = async {
+                    let client = ensure_client(&reddit_client, &settings).await?;
+                    match action {
+                        moderation::Action::Approve => client.approve(&fullname).await,
+                        moderation::Action::Remove => client.remove(&fullname, false).await,
+                        moderation::Action::Spam => client.remove(&fullname, true).await,
+                        moderation::Action::Lock | moderation::Action::Unlock => client.lock(&fullname, action == moderation::Action::Lock).await,
+                        moderation::Action::Distinguish | moderation::Action::Undistinguish => {
+                            client.distinguish(&fullname, action == moderation::Action::Distinguish).await
+                        }
+                    }
+                }.await;
+
+                match result {
+                    Ok(()) => {
+                        let in_queue = *current_subreddit.lock().unwrap() == moderation::QUEUE;
+                        let mut posts = posts.lock().unwrap();
+                        if in_queue && action.settles() {
+                            posts.retain(|post| post.name != fullname);
+                        } else if let Some(post) = posts.iter_mut().find(|post| post.name == fullname) {
+                            moderation::apply_to_post(post, action);
+                        }
+                        if let Some(post) = open_post.lock().unwrap().as_mut().filter(|post| post.name == fullname) {
+                            moderation::apply_to_post(post, action);
+                        }
+                        if let Some(comment) = comments::find_mut(&mut thread.lock().unwrap(), &fullname) {
+                            moderation::apply_to_comment(comment, action);
+                        }
+                    }
+                    Err(e) => {
+                        *error_message.lock().unwrap() = Some(RustleError::classify(&e));
+                    }
+                }
+                if let Some(ctx) = &egui_ctx {
+                    ctx.request_repaint();
+                }
+            });
+        });
+    }
+
+    fn render_mod_actions(&self, ui: &mut egui::Ui, fullname: &str, locked: bool, distinguished: bool) {
+        for action in moderation::Action::offered(locked, distinguished) {
+            if ui.button(action.label()).clicked() {
+                self.moderate(fullname.to_string(), action);
+                ui.close_menu();
+            }
+        }
+    }
+
+    // Every quick action for a post, whether or not it also has a button on the card
+    fn render_post_menu(&self, ui: &mut egui::Ui, post: &Post) {
+        if self.settings.signed_in() {
+            if ui.add_enabled(post.likes != Some(true), egui::Button::new("⬆ Upvote")).clicked() {
+                self.vote(post.name.clone(), Some(true));
+                ui.close_menu();
+            }
+            if ui.add_enabled(post.likes != Some(false), egui::Button::new("⬇ Downvote")).clicked() {
+                self.vote(post.name.clone(), Some(false));
+                ui.close_menu();
+            }
+            if post.likes.is_some() && ui.button("Clear vote").clicked() {
+                self.vote(post.name.clone(), None);
+                ui.close_menu();
+            }
+            ui.separator();
+            if ui.button(if post.saved { "Unsave" } else { "Save" }).clicked() {
+                self.set_saved(vec![post.name.clone()], !post.saved);
+                ui.close_menu();
+            }
+            if ui.button("Hide").clicked() {
+                self.set_hidden(vec![post.name.clone()], true);
+                ui.close_menu();
+            }
+        }
+        if self.moderates(&post.subreddit) {
+            ui.menu_button("Moderate", |ui| {
+                self.render_mod_actions(ui, &post.name, post.locked, post.distinguished.is_some());
+            });
+        }
+        if ui.button("Copy link").on_hover_text(&post.url).clicked() {
+            ui.output_mut(|output| output.copied_text = post.url.clone());
+            ui.close_menu();
+        }
+        if ui.button("Copy Reddit permalink").clicked() {
+            ui.output_mut(|output| output.copied_text = post.link());
+            ui.close_menu();
+        }
+        if ui.button("Copy title").clicked() {
+            ui.output_mut(|output| output.copied_text = post.title.clone());
+            ui.close_menu();
+        }
+        ui.separator();
+        if post.opens_externally() && ui.button("Open in reader").clicked() {
+            self.open_reader(post);
+            ui.close_menu();
+        }
+        if ui.button("Open in browser").clicked() {
+            self.open_in_browser(ui.ctx(), post);
+            ui.close_menu();
+        }
+        if ui.button(format!("Open r/{}", post.subreddit)).clicked() {
+            // The feed may be locked while this menu is drawn, so the switch waits for the frame's end
+            *self.subreddit_request.lock().unwrap() = Some(post.subreddit.clone());
+            ui.close_menu();
+        }
+        if ui.button("Open comments").clicked() {
+            self.open_comments(post);
+            ui.close_menu();
+        }
+    }
+
+    fn render_selection_toolbar(&mut self, ui: &mut egui::Ui) {
+        let selected = self.selection.lock().unwrap().pick(&self.posts.lock().unwrap());
+        let names: Vec<String> = selected.iter().map(|post| post.name.clone()).collect();
+        let mut clear = ui.input(|i| i.key_pressed(egui::Key::Escape));
+        ui.horizontal(|ui| {
+            ui.label(egui::RichText::new(format!("{} selected", selected.len())).strong());
+            ui.add_space(8.0);
+            let signed_in = self.settings.signed_in();
+            if ui.add_enabled(signed_in, egui::Button::new("Save all")).clicked() {
+                self.set_saved(names.clone(), true);
+                clear = true;
+            }
+            if ui.add_enabled(signed_in, egui::Button::new("Hide all")).clicked() {
+                self.set_hidden(names.clone(), true);
+                clear = true;
+            }
+            if ui.button("Open all in browser").clicked() {
+                for post in &selected {
+                    ui.ctx().open_url(egui::OpenUrl::new_tab(&post.url));
+                }
+                self.mark_read(&names);
+                clear = true;
+            }
+            ui.menu_button("Export ⏷", |ui| {
+                if ui.button("Copy as Markdown").clicked() {
+                    ui.output_mut(|output| output.copied_text = export_markdown(&selected));
+                    ui.close_menu();
+                }
+                if ui.button("Copy as JSON").clicked() {
+                    if let Ok(json) = serde_json::to_string_pretty(&selected) {
+                        ui.output_mut(|output| output.copied_text = json);
+                    }
+                    ui.close_menu();
+                }
+            });
+            if ui.button("✖ Clear").on_hover_text("Clear the selection (Esc)").clicked() {
+                clear = true;
+            }
+        });
+        ui.add_space(4.0);
+        if clear {
+            self.selection.lock().unwrap().clear();
+        }
+    }
+
+    // Posts the feed leaves out entirely
+    fn is_filtered(&self, post: &Post) -> bool {
+        (post.over_18 && !self.settings.show_nsfw)
+            || self.is_ignored(&post.author)
+            || (self.settings.hide_read && self.read_posts.lock().unwrap().hides(&post.name))
+    }
+
+    // The current subreddit's own layout, if it has one
+    fn density(&self) -> Density {
+        self.settings.density_for(&self.current_subreddit.lock().unwrap())
+    }
+
+    // Covered NSFW cards keep the plain frame rather than hint at what's under the blur
+    fn accent(&self, post: &Post, covered: bool) -> Option<egui::Color32> {
+        if !self.settings.image_accents || covered {
+            return None;
+        }
+        let url = post.thumbnail_to_load(&self.settings, false)?;
+        self.accents.get(&url)
+    }
+
+    fn is_highlighted(&self, post: &Post) -> bool {
+        self.highlighter.lock().unwrap().matches(&self.settings.filters, post)
+    }
+
+    // Every media post the feed shows, in order; blurred ones wait until they're revealed
+    fn open_media_queue(&mut self) {
+        let revealed = self.revealed_nsfw.lock().unwrap().clone();
+        let hidden = self.hidden_posts.lock().unwrap().clone();
+        let queue: Vec<Post> = self.posts.lock().unwrap().iter()
+            .filter(|post| lightbox::is_media(post) && !self.is_filtered(post) && !hidden.contains(&post.name))
+            .filter(|post| !(post.over_18 && self.settings.blur_nsfw && !revealed.contains(&post.name)))
+            .cloned()
+            .collect();
+        self.lightbox = lightbox::Lightbox::new(queue);
+    }
+
+    fn render_lightbox(&mut self, ui: &mut egui::Ui) {
+        let Some(lightbox) = &self.lightbox else {
+            return;
+        };
+        let post = lightbox.current().clone();
+        let (has_previous, has_next, position) = (lightbox.has_previous(), lightbox.has_next(), lightbox.position());
+        if let Some(url) = lightbox.upcoming().and_then(Post::full_preview).map(|image| image.url.clone()) {
+            let _ = ui.ctx().try_load_image(&url, egui::SizeHint::default());
+        }
+        self.mark_read(std::slice::from_ref(&post.name));
+
+        let mut close = false;
+        let mut step = None;
+        let mut comments = false;
+        if !ui.ctx().wants_keyboard_input() {
+            ui.input(|i| {
+                if i.key_pressed(egui::Key::Escape) {
+                    close = true;
+                } else if i.key_pressed(egui::Key::ArrowLeft) || i.key_pressed(egui::Key::K) {
+                    step = Some(false);
+                } else if i.key_pressed(egui::Key::ArrowRight) || i.key_pressed(egui::Key::J) {
+                    step = Some(true);
+                }
+            });
+        }
+
+        let font_size = self.settings.font_size;
+        ui.horizontal(|ui| {
+            close |= ui.button("⏴ Back to feed").on_hover_text("Esc").clicked();
+            ui.add_space(8.0);
+            if ui.add_enabled(has_previous, egui::Button::new("◀")).on_hover_text("Previous (Left arrow)").clicked() {
+                step = Some(false);
+            }
+            ui.label(position);
+            if ui.add_enabled(has_next, egui::Button::new("▶")).on_hover_text("Next (Right arrow)").clicked() {
+                step = Some(true);
+            }
+            ui.add_space(8.0);
+            let title = egui::RichText::new(&post.title).size(14.0 * font_size).strong();
+            // The viewer is already showing the post, so its link goes out to the browser
+            let (content, opener) = self.link_target(&post);
+            let media = matches!(content, open_with::Content::Image | open_with::Content::Video);
+            if opener == open_with::Opener::Browser || (media && opener == open_with::Opener::Internal) {
+                ui.hyperlink_to(title, &post.url);
+            } else if ui.link(title).on_hover_text(self.describe_link(&post)).clicked() {
+                self.open_link(ui.ctx(), &post);
+            }
+            ui.label(egui::RichText::new(format!("r/{}", post.subreddit)).size(12.0 * font_size).weak());
+            comments = ui.button(format!("💬 {}", post.num_comments)).on_hover_text("Open the comments").clicked();
+        });
+        ui.separator();
+
+        #[cfg(feature = "video")]
+        let playing = if let Some(reddit_video) = post.reddit_video() {
+            self.render_video(ui, &post, reddit_video);
+            true
+        } else {
+            false
+        };
+        #[cfg(not(feature = "video"))]
+        let playing = false;
+
+        // Opening the lightbox is asking to see it move, so GIFs play here even without autoplay
+        let animation = post.animation().filter(|_| !playing && !self.settings.stills_only);
+        let playing = if let Some((url, width, height)) = animation {
+            let still = post.full_preview().map(|image| image.url.as_str());
+            ui.vertical_centered(|ui| {
+                let max_size = ui.available_size();
+                self.animations.lock().unwrap().show(ui, url, still, [width, height], true, max_size);
+            });
+            true
+        } else {
+            playing
+        };
+
+        if !playing {
+            if let Some(image) = post.full_preview() {
+                ui.vertical_centered(|ui| {
+                    ui.add(
+                        egui::widgets::Image::new(&image.url)
+                            .fit_to_original_size(1.0)
+                            .max_size(ui.available_size())
+                    );
+                });
+            }
+            if !cfg!(feature = "video") && post.reddit_video().is_some() {
+                ui.label(egui::RichText::new("Videos play in your browser; click the title to open it.").weak());
+            }
+        }
+
+        if close || comments {
+            self.lightbox = None;
+        }
+        if comments {
+            self.open_comments(&post);
+        }
+        if let (Some(forward), Some(lightbox)) = (step, self.lightbox.as_mut()) {
+            if forward {
+                lightbox.next();
+            } else {
+                lightbox.previous();
+            }
+        }
+    }
+
+    // Blocked on reddit.com or ignored locally
+    fn is_ignored(&self, author: &str) -> bool {
+        self.blocked_users.lock().unwrap().contains(&author.to_lowercase())
+            || self.settings.ignored_users.iter().any(|name| name.eq_ignore_ascii_case(author))
+    }
+
+    fn set_ignored(&mut self, author: &str, ignored: bool) {
+        self.settings.ignored_users.retain(|name| !name.eq_ignore_ascii_case(author));
+        if ignored {
+            self.settings.ignored_users.push(author.to_string());
+        }
+        if let Err(e) = self.settings.save() {
+            *self.error_message.lock().unwrap() = Some(RustleError::classify(&e).context("Failed to save settings"));
+        }
+    }
+
+    fn render_post(&self, ui: &mut egui::Ui, post: &Post) {
+        if self.is_filtered(post) {
+            return;
+        }
+        let layout = self.settings.card_layout;
+        if self.hidden_posts.lock().unwrap().contains(&post.name) {
+            ui.add_space(layout.spacing);
+            ui.horizontal(|ui| {
+                ui.label(egui::RichText::new("Post hidden.").size(12.0 * self.settings.font_size).weak());
+                if ui.link(egui::RichText::new("Undo").size(12.0 * self.settings.font_size)).clicked() {
+                    self.set_hidden(vec![post.name.clone()], false);
+                }
+            });
+            return;
+        }
+        if self.tracking_usage() {
+            self.usage.lock().unwrap().viewed(post);
+        }
+        let covered = post.over_18 && self.settings.blur_nsfw
+            && !self.revealed_nsfw.lock().unwrap().contains(&post.name);
+        // The post heading its own detail screen always shows everything
+        let detail = self.open_post.lock().unwrap().as_ref().is_some_and(|open| open.name == post.name);
+        let expanded = detail || self.expanded_posts.lock().unwrap().contains(&post.name);
+        // Only feed cards can be selected, not the post heading a comment thread or a profile
+        let selectable = self.open_post.lock().unwrap().is_none() && self.profile.lock().unwrap().is_none()
+            && self.history.lock().unwrap().is_none();
+        let selected = selectable && self.selection.lock().unwrap().names.contains(&post.name);
+        let density = if detail { Density::Cozy } else { self.density() };
+        // Card density trades the side thumbnail for the full preview underneath
+        let wide = density == Density::Card && !covered && post.expanded_image().is_some();
+
+        ui.add_space(layout.spacing);
+        let card = egui::Frame::group(ui.style())
+            .fill(self.settings.theme.card)
+            .inner_margin(layout.padding)
+            .outer_margin(0.0)  // Remove outer margin
+            .stroke(if selected {
+                ui.visuals().selection.stroke
+            } else if self.is_highlighted(post) {
+                egui::Stroke::new(2.0, ui.visuals().warn_fg_color)
+            } else if let Some(accent) = self.accent(post, covered) {
+                palette::stroke(accent)
+            } else {
+                ui.visuals().widgets.noninteractive.bg_stroke
+            })
+            .show(ui, |ui| {
+                // Use the full width
+                ui.set_min_width(ui.available_width());
+                if density == Density::Compact {
+                    self.render_compact_row(ui, post, covered);
+                    return;
+                }
+
+                ui.horizontal(|ui| {
+                    if covered {
+                        ui.add_space(5.0);
+                        let reveal = match post.blurred_thumbnail_url() {
+                            Some(blurred) => ui.add(
+                                egui::widgets::Image::new(blurred)
+                                    .fit_to_original_size(1.0)
+                                    .max_size(egui::Vec2::splat(layout.image_size))
+                                    .sense(egui::Sense::click())
+                            ),
+                            None => ui.add_sized(egui::Vec2::splat(layout.image_size), egui::Button::new("NSFW")),
+                        };
+                        if reveal.on_hover_text("Click to show").clicked() {
+                            self.revealed_nsfw.lock().unwrap().insert(post.name.clone());
+                        }
+                        ui.add_space(10.0);
+                    } else if let Some(image_url) = post.thumbnail_to_load(&self.settings, true).filter(|_| !wide) {
+                        ui.add_space(5.0);
+                        let image = egui::widgets::Image::new(image_url)
+                            .fit_to_original_size(1.0)
+                            .max_size(egui::Vec2::splat(layout.image_size));
+                        let _thumbnail = ui.add(image);
+                        #[cfg(feature = "video")]
+                        self.render_hover_preview(post, _thumbnail);
+                        ui.add_space(10.0);
+                    }
+
+                    ui.vertical(|ui| {
+                        // Make the vertical content take remaining width
+                        ui.set_min_width(ui.available_width());
+                        
+                        // Post title with link
+                        let removal_notice = post.removal_notice();
+                        let mut title = egui::RichText::new(&post.title)
+                            .size(16.0 * self.settings.font_size)
+                            .strong();
+                        if removal_notice.is_some() {
+                            title = title.weak().italics();
+                        }
+                        ui.horizontal_wrapped(|ui| {
+                            if post.over_18 && self.settings.label_nsfw {
+                                ui.label(
+                                    egui::RichText::new("NSFW")
+                                        .size(12.0 * self.settings.font_size)
+                                        .strong()
+                                        .color(ui.style().visuals.error_fg_color)
+                                );
+                            }
+                            if let Some(flair) = post.link_flair_text.as_deref().filter(|flair| !flair.is_empty()) {
+                                let pill = flair_pill(ui, flair, post.link_flair_background_color.as_deref(),
+                                    post.link_flair_text_color.as_deref(), 11.0 * self.settings.font_size);
+                                if pill.on_hover_text(format!("Show only \"{}\" posts in r/{}", flair, post.subreddit)).clicked() {
+                                    *self.flair_request.lock().unwrap() = Some((post.subreddit.clone(), flair.to_string()));
+                                }
+                                if let Some(gloss) = self.gloss(flair) {
+                                    translate::show(ui, &gloss, 11.0 * self.settings.font_size);
+                                }
+                            }
+                            if post.locked {
+                                ui.label(egui::RichText::new("🔒").size(12.0 * self.settings.font_size))
+                                    .on_hover_text("Locked: no new comments");
+                            }
+                            if self.link_target(post).1 != open_with::Opener::Browser {
+                                let link = ui.link(title).on_hover_text(self.describe_link(post));
+                                self.hint(ui, &link, || hints::Jump::Link(post.clone()));
+                                if link.clicked() {
+                                    self.open_link(ui.ctx(), post);
+                                }
+                            } else {
+                                let link = ui.add(egui::Hyperlink::from_label_and_url(title, &post.url));
+                                self.hint(ui, &link, || hints::Jump::Link(post.clone()));
+                                if link.clicked() {
+                                    self.mark_read(std::slice::from_ref(&post.name));
+                                    self.note_opened(post);
+                                }
+                            }
+                            if let Some(chip) = post.domain_chip() {
+                                let hover = if post.opens_externally() {
+                                    format!("Opens {} in your browser", post.domain)
+                                } else {
+                                    "Stays on Reddit".to_string()
+                                };
+                                egui::Frame::none()
+                                    .fill(ui.visuals().faint_bg_color)
+                                    .rounding(8.0)
+                                    .inner_margin(egui::Margin::symmetric(6.0, 1.0))
+                                    .show(ui, |ui| {
+                                        ui.label(egui::RichText::new(chip).size(11.0 * self.settings.font_size).weak());
+                                    })
+                                    .response
+                                    .on_hover_text(hover);
+                            }
+                        });
+
+                        if let Some(parent) = post.crosspost_parent() {
+                            let badge = egui::RichText::new(format!("🔀 Crossposted from r/{}", parent.subreddit))
+                                .size(12.0 * self.settings.font_size);
+                            let badge = ui.link(badge);
+                            self.hint(ui, &badge, || hints::Jump::Comments(parent.clone()));
+                            if badge.on_hover_text(format!("Open the original post by u/{}", parent.author)).clicked() {
+                                self.open_comments(parent);
+                            }
+                        }
+
+                        if let Some(notice) = removal_notice {
+                            ui.label(
+                                egui::RichText::new(format!("[{}]", notice))
+                                    .size(12.0 * self.settings.font_size)
+                                    .italics()
+                                    .color(ui.style().visuals.warn_fg_color)
+                            );
+                        }
+                        
+                        // Post metadata
+                        let (posted, posted_hover) = if self.settings.absolute_timestamps {
+                            (absolute_time(post.created_utc), relative_time(post.created_utc, store::now()))
+                        } else {
+                            (relative_time(post.created_utc, store::now()), absolute_time(post.created_utc))
+                        };
+                        if post.author_deleted() {
+                            ui.label(
+                                egui::RichText::new(format!("Posted by {} in r/{} · {}", post.author, post.subreddit, posted))
+                                    .italics()
+                                    .size(12.0 * self.settings.font_size)
+                                    .weak()
+                            ).on_hover_text(posted_hover);
+                        } else {
+                            ui.horizontal(|ui| {
+                                ui.spacing_mut().item_spacing.x = 0.0;
+                                let meta = |text: String| egui::RichText::new(text).size(12.0 * self.settings.font_size).weak();
+                                ui.label(meta("Posted by ".to_string()));
+                                let author = ui.link(meta(format!("u/{}", post.author)));
+                                self.hint(ui, &author, || hints::Jump::Profile(post.author.clone()));
+                                if author.on_hover_text("View profile").clicked() {
+                                    self.open_profile(post.author.clone());
+                                }
+                                if let Some(flair) = post.author_flair_text.as_deref().filter(|flair| !flair.is_empty()) {
+                                    ui.add_space(4.0);
+                                    flair_pill(ui, flair, post.author_flair_background_color.as_deref(),
+                                        post.author_flair_text_color.as_deref(), 10.0 * self.settings.font_size);
+                                }
+                                ui.label(meta(format!(" in r/{} · ", post.subreddit)));
+                                ui.label(meta(posted)).on_hover_text(posted_hover);
+                            });
+                        }
+                        
+                        let (score_heat, comments_heat) = {
+                            let mut heat = self.heat.lock().unwrap();
+                            heat.record(&post.subreddit, &post.name, post.score, post.num_comments);
+                            if self.settings.heat_colors {
+                                (heat.score(&post.subreddit, post.score), heat.comments(&post.subreddit, post.num_comments))
+                            } else {
+                                (None, None)
+                            }
+                        };
+                        let tint = |text: egui::RichText, heat: Option<f32>| match heat {
+                            Some(heat) => text.color(heat::color(heat)),
+                            None => text,
+                        };
+                        let actions = self.settings.card_actions;
+                        let signed_in = self.settings.signed_in();
+                        ui.horizontal(|ui| {
+                            let small = |text: &str| egui::RichText::new(text).size(12.0 * self.settings.font_size);
+                            if actions.vote && signed_in {
+                                let upvote = small("⬆").color(if post.likes == Some(true) {
+                                    self.settings.theme.upvote
+                                } else {
+                                    ui.visuals().weak_text_color()
+                                });
+                                if ui.add(egui::Button::new(upvote).frame(false)).on_hover_text("Upvote").clicked() {
+                                    self.vote(post.name.clone(), if post.likes == Some(true) { None } else { Some(true) });
+                                }
+                                // The vote's own colour wins over the heat tint, so it's clear the vote counted
+                                let score = match post.likes {
+                                    Some(true) => small(&post.score.to_string()).color(self.settings.theme.upvote),
+                                    Some(false) => small(&post.score.to_string()).color(self.settings.theme.downvote),
+                                    None => tint(small(&post.score.to_string()), score_heat),
+                                };
+                                let score = ui.label(score);
+                                if let Some(percent) = post.upvoted_percent() {
+                                    score.on_hover_text(format!("{} upvoted", percent));
+                                }
+                                let downvote = small("⬇").color(if post.likes == Some(false) {
+                                    self.settings.theme.downvote
+                                } else {
+                                    ui.visuals().weak_text_color()
+                                });
+                                if ui.add(egui::Button::new(downvote).frame(false)).on_hover_text("Downvote").clicked() {
+                                    self.vote(post.name.clone(), if post.likes == Some(false) { None } else { Some(false) });
+                                }
+                            } else {
+                                let score = ui.label(tint(small(&format!("Score: {}", post.score)), score_heat));
+                                if let Some(percent) = post.upvoted_percent() {
+                                    score.on_hover_text(format!("{} upvoted", percent));
+                                }
+                            }
+                            if actions.comments {
+                                ui.add_space(8.0);
+                                let comments_button = egui::Button::new(tint(
+                                    small(&format!("💬 {} comments", post.num_comments)),
+                                    comments_heat,
+                                )).frame(false);
+                                let comments_button = ui.add(comments_button);
+                                self.hint(ui, &comments_button, || hints::Jump::Comments(post.clone()));
+                                if comments_button.clicked() {
+                                    self.open_comments(post);
+                                }
+                            }
+                            if post.award_count() > 0 {
+                                ui.add_space(8.0);
+                                let icon_size = egui::Vec2::splat(14.0 * self.settings.font_size);
+                                for award in post.all_awardings.iter().take(4) {
+                                    ui.add(egui::widgets::Image::new(award.icon()).fit_to_exact_size(icon_size))
+                                        .on_hover_text(format!("{} × {}", award.name, award.count));
+                                }
+                                ui.label(
+                                    egui::RichText::new(format!("🏆 {}", post.award_count()))
+                                        .size(12.0 * self.settings.font_size)
+                                        .weak()
+                                ).on_hover_text("Awards");
+                            }
+                            if actions.save && signed_in {
+                                ui.add_space(8.0);
+                                let save_button = egui::Button::new(small(if post.saved { "★ Saved" } else { "☆ Save" })).frame(false);
+                                if ui.add(save_button).clicked() {
+                                    self.set_saved(vec![post.name.clone()], !post.saved);
+                                }
+                            }
+                            if actions.hide && signed_in {
+                                ui.add_space(8.0);
+                                let hide_button = egui::Button::new(small("Hide")).frame(false);
+                                if ui.add(hide_button).on_hover_text("Hide this post on Reddit").clicked() {
+                                    self.set_hidden(vec![post.name.clone()], true);
+                                }
+                            }
+                            if actions.share {
+                                ui.add_space(8.0);
+                                let share_button = egui::Button::new(small("🔗 Share")).frame(false);
+                                if ui.add(share_button).on_hover_text("Copy the link to this post").clicked() {
+                                    ui.output_mut(|output| output.copied_text = post.link());
+                                }
+                            }
+                            ui.add_space(8.0);
+                            ui.menu_button(small("…"), |ui| self.render_post_menu(ui, post))
+                                .response
+                                .on_hover_text("Share and more");
+                            if post.can_expand() && !covered && !detail {
+                                ui.add_space(8.0);
+                                let expand_button = egui::Button::new(
+                                    egui::RichText::new(if expanded { "⏶" } else { "⏷" })
+                                        .size(12.0 * self.settings.font_size)
+                                ).frame(false);
+                                if ui.add(expand_button).on_hover_text(if expanded { "Collapse" } else { "Expand" }).clicked() {
+                                    let mut expanded_posts = self.expanded_posts.lock().unwrap();
+                                    if !expanded_posts.remove(&post.name) {
+                                        expanded_posts.insert(post.name.clone());
+                                    }
+                                }
+                            }
+                        });
+                    });
+                });
+
+                if expanded && !covered {
+                    if let Some(text) = post.expanded_text() {
+                        ui.add_space(4.0);
+                        markdown::show(ui, text, 14.0 * self.settings.font_size);
+                    }
+                }
+                // Playback stays hidden along with a covered thumbnail
+                let animation = post.animation().filter(|_| !covered && !self.settings.stills_only);
+                if (expanded && !covered) || wide {
+                    let max_size = egui::Vec2::new(ui.available_width(), if expanded { 600.0 } else { 400.0 });
+                    if let Some((url, width, height)) = animation {
+                        ui.add_space(4.0);
+                        let still = post.expanded_image().map(|image| image.url.as_str());
+                        self.animations.lock().unwrap()
+                            .show(ui, url, still, [width, height], self.settings.autoplay_animations, max_size);
+                    } else if let Some(image) = post.expanded_image() {
+                        ui.add_space(4.0);
+                        ui.add(egui::widgets::Image::new(&image.url).fit_to_original_size(1.0).max_size(max_size));
+                    }
+                } else if let Some((url, _, _)) = animation {
+                    // Plays in the expanded card, whatever autoplay says
+                    if ui.button("▶ Play GIF").clicked() {
+                        self.animations.lock().unwrap().play(url);
+                        self.expanded_posts.lock().unwrap().insert(post.name.clone());
+                    }
+                }
+
+                #[cfg(feature = "video")]
+                if !covered {
+                    if let Some(reddit_video) = post.reddit_video() {
+                        self.render_video(ui, post, reddit_video);
+                    }
+                }
+            });
+
+        // Clicks on the card's own background; links and buttons inside keep theirs
+        let response = card.response.interact(egui::Sense::click());
+        response.context_menu(|ui| self.render_post_menu(ui, post));
+        let modifiers = ui.input(|i| i.modifiers);
+        if selectable && response.clicked() {
+            if modifiers.command || modifiers.shift {
+                *self.selection_click.lock().unwrap() = Some((post.name.clone(), modifiers.shift));
+            } else {
+                self.open_comments(post);
+            }
+        }
+    }
+
+    // Compact density: thumbnail, title and a line of stats, all on one row. Actions live in the
+    // right-click menu and a click on the row opens the post.
+    fn render_compact_row(&self, ui: &mut egui::Ui, post: &Post, covered: bool) {
+        let font_size = self.settings.font_size;
+        ui.horizontal(|ui| {
+            let size = egui::Vec2::splat(24.0 * font_size);
+            let thumbnail = post.thumbnail_to_load(&self.settings, !covered);
+            match thumbnail {
+                Some(url) => {
+                    ui.add(egui::widgets::Image::new(url).fit_to_exact_size(size).rounding(3.0));
+                }
+                None => {
+                    ui.allocate_space(size);
+                }
+            }
+
+            let stats = format!("r/{} · {} · {} points · {} comments",
+                post.subreddit, relative_time(post.created_utc, store::now()), post.score, post.num_comments);
+            let stats = egui::RichText::new(stats).size(12.0 * font_size).weak();
+            let stats_width = egui::WidgetText::from(stats.clone())
+                .into_galley(ui, Some(false), f32::INFINITY, egui::TextStyle::Body)
+                .size().x;
+
+            // The title gets whatever the stats leave over and is cut off with an ellipsis
+            let mut title = egui::RichText::new(&post.title).size(14.0 * font_size).strong();
+            if post.removal_notice().is_some() {
+                title = title.weak().italics();
+            }
+            let title_width = (ui.available_width() - stats_width - 2.0 * ui.spacing().item_spacing.x).max(40.0);
+            ui.allocate_ui_with_layout(egui::vec2(title_width, size.y), egui::Layout::left_to_right(egui::Align::Center), |ui| {
+                let title = ui.add(egui::Label::new(title).truncate(true).sense(egui::Sense::click()));
+                self.hint(ui, &title, || hints::Jump::Link(post.clone()));
+                if title.on_hover_text(&post.title).clicked() {
+                    self.open_link(ui.ctx(), post);
+                }
+            });
+            ui.label(stats);
+        });
+    }
+
+    #[cfg(feature = "video")]
+    fn render_video(&self, ui: &mut egui::Ui, post: &Post, reddit_video: &RedditVideo) {
+        self.render_player(ui, post, "▶ Play video", |ctx| {
+            let audio_url = reddit_video.hls_url.clone().filter(|_| reddit_video.has_audio);
+            video::VideoPlayer::start(ctx, &reddit_video.fallback_url,
+                audio_url, reddit_video.width, reddit_video.height, false)
+        });
+    }
+
+    #[cfg(feature = "video")]
+    fn render_hover_preview(&self, post: &Post, thumbnail: egui::Response) {
+        if !self.settings.hover_previews || self.settings.stills_only {
+            return;
+        }
+        let Some((url, width, height)) = post.hover_clip() else {
+            return;
+        };
+        // Started from the tooltip, so merely sweeping the pointer across the feed costs nothing
+        thumbnail.on_hover_ui(|ui| {
+            let mut hover_preview = self.hover_preview.lock().unwrap();
+            if hover_preview.as_ref().is_none_or(|preview| preview.post != post.name) {
+                let player = video::VideoPlayer::start(ui.ctx(), url, None, width, height, true)
+                    .map_err(|e| *self.error_message.lock().unwrap() = Some(RustleError::classify(&e).context("Failed to play preview")))
+                    .ok();
+                *hover_preview = Some(HoverPreview { post: post.name.clone(), player, frame: 0 });
+            }
+            let Some(preview) = hover_preview.as_mut() else {
+                return;
+            };
+            preview.frame = ui.ctx().frame_nr();
+            if let Some(player) = &mut preview.player {
+                player.show(ui, 320.0);
+            }
+        });
+    }
+
+    #[cfg(feature = "video")]
+    fn render_player(&self, ui: &mut egui::Ui, post: &Post, play_label: &str,
+        start: impl FnOnce(&egui::Context) -> Result<video::VideoPlayer>) {
+        let mut videos = self.videos.lock().unwrap();
+
+        let Some(player) = videos.get_mut(&post.url) else {
+            if ui.button(play_label).clicked() {
+                match start(ui.ctx()) {
+                    Ok(player) => {
+                        videos.insert(post.url.clone(), player);
+                    }
+                    Err(e) => {
+                        *self.error_message.lock().unwrap() = Some(RustleError::classify(&e).context("Failed to play video"));
+                    }
+                }
+            }
+            return;
+        };
+
+        player.show(ui, ui.available_width());
+        let mut close = false;
+        ui.horizontal(|ui| {
+            let play_label = if player.is_paused() || player.is_finished() { "▶" } else { "⏸" };
+            if ui.add_enabled(!player.is_finished(), egui::Button::new(play_label)).clicked() {
+                player.toggle_pause();
+            }
+            if player.has_audio() {
+                let mute_label = if player.is_muted() { "🔇" } else { "🔊" };
+                if ui.button(mute_label).clicked() {
+                    player.toggle_mute();
+                }
+            }
+            if ui.button("✖").on_hover_text("Stop video").clicked() {
+                close = true;
+            }
+        });
+        if close {
+            videos.remove(&post.url);
+        }
+    }
+
+    fn open_profile(&self, name: String) {
+        *self.open_post.lock().unwrap() = None;
+        *self.profile.lock().unwrap() = Some(profile::Profile::new(name.clone()));
+
+        let reddit_client = self.reddit_client.clone();
+        let settings = self.settings.clone();
+        let profile = self.profile.clone();
+        let error_message = self.error_message.clone();
+        let egui_ctx = self.egui_ctx.clone();
+        // Reddit only hands out the reader's own trophy case
+        let mine = self.settings.signed_in() && name.eq_ignore_ascii_case(&self.settings.account.username);
+
+        thread::spawn(move || {
+            let rt = tokio::runtime::Runtime::new().unwrap();
+            rt.block_on(async {
+                let (result, trophies) = match ensure_client(&reddit_client, &settings).await {
+                    Ok(client) => {
+                        let trophies = async {
+                            if mine { client.get_my_trophies().await } else { Ok(Vec::new()) }
+                        };
+                        let (about, overview, trophies) = tokio::join!(client.get_user_about(&name), client.get_user_overview(&name, None), trophies);
+                        (about.and_then(|about| overview.map(|overview| (about, overview))), trophies)
+                    }
+                    Err(e) => (Err(e), Ok(Vec::new())),
+                };
+
+                let mut profile = profile.lock().unwrap();
+                // The reader may have backed out or opened someone else in the meantime
+                let Some(profile) = profile.as_mut().filter(|profile| profile.name == name) else {
+                    return;
+                };
+                match result {
+                    Ok((about, (items, after))) => {
+                        if let Some(ctx) = &egui_ctx {
+                            prefetch_images(ctx, &profile_posts(&items), &settings);
+                        }
+                        profile.about = Some(about);
+                        profile.items = items;
+                        profile.after = after;
+                    }
+                    Err(e) => {
+                        *error_message.lock().unwrap() = Some(RustleError::classify(&e));
+                    }
+                }
+                match trophies {
+                    Ok(trophies) => profile.trophies = trophies,
+                    Err(e) => *error_message.lock().unwrap() = Some(RustleError::classify(&e)),
+                }
+                profile.loading = false;
+                if let Some(ctx) = &egui_ctx {
+                    ctx.request_repaint();
+                }
+            });
+        });
+    }
+
+    fn load_more_profile(&self) {
+        let (name, after) = {
+            let mut profile = self.profile.lock().unwrap();
+            let Some(profile) = profile.as_mut() else {
+                return;
+            };
+            let Some(after) = profile.after.clone().filter(|_| !profile.loading) else {
+                return;
+            };
+            profile.loading = true;
+            (profile.name.clone(), after)
+        };
+
+        let reddit_client = self.reddit_client.clone();
+        let settings = self.settings.clone();
+        let profile = self.profile.clone();
+        let error_message = self.error_message.clone();
+        let egui_ctx = self.egui_ctx.clone();
+
+        thread::spawn(move || {
+            let rt = tokio::runtime::Runtime::new().unwrap();
+            rt.block_on(async {
+                let result = match ensure_client(&reddit_client, &settings).await {
+                    Ok(client) => client.get_user_overview(&name, Some(&after)).await,
+                    Err(e) => Err(e),
+                };
+
+                let mut profile = profile.lock().unwrap();
+                let Some(profile) = profile.as_mut().filter(|profile| profile.name == name) else {
+                    return;
+                };
+                match result {
+                    Ok((items, next_after)) => {
+                        if let Some(ctx) = &egui_ctx {
+                            prefetch_images(ctx, &profile_posts(&items), &settings);
+                        }
+                        profile.items.extend(items);
+                        profile.after = next_after;
+                    }
+                    Err(e) => {
+                        *error_message.lock().unwrap() = Some(RustleError::classify(&e).context("Error fetching user history"));
+                    }
+                }
+                profile.loading = false;
+                if let Some(ctx) = &egui_ctx {
+                    ctx.request_repaint();
+                }
+            });
+        });
+    }
+
+    fn render_reader(&mut self, ui: &mut egui::Ui) {
+        let font_size = self.settings.font_size;
+        let Some(open) = self.reader.lock().unwrap().clone() else {
+            return;
+        };
+        let post = &open.post;
+        let domain = reader::domain_key(&post.domain);
+        let mut close = !ui.ctx().wants_keyboard_input() && ui.input(|i| i.key_pressed(egui::Key::Escape));
+        ui.horizontal(|ui| {
+            close |= ui.button("⏴ Back").on_hover_text("Esc").clicked();
+            ui.add_space(8.0);
+            if ui.button("Open in browser").clicked() {
+                ui.ctx().open_url(egui::OpenUrl::new_tab(&post.url));
+            }
+            if ui.button(format!("💬 {}", post.num_comments)).on_hover_text("Open the comments").clicked() {
+                self.open_comments(post);
+                close = true;
+            }
+            ui.add_space(8.0);
+            let choice = self.settings.link_opening.get(&domain).copied();
+            let mut always_reader = choice == Some(reader::LinkOpen::Reader);
+            if ui.checkbox(&mut always_reader, format!("Always read {} here", domain)).changed() {
+                self.settings.link_opening.insert(domain.clone(), if always_reader { reader::LinkOpen::Reader } else { reader::LinkOpen::Browser });
+                self.settings_modified = true;
+            }
+        });
+        ui.separator();
+        if close {
+            *self.reader.lock().unwrap() = None;
+            return;
+        }
+
+        let article = match &open.article {
+            None => {
+                ui.vertical_centered(|ui| {
+                    ui.add_space(20.0);
+                    ui.spinner();
+                    ui.label(egui::RichText::new(format!("Loading {}", post.domain)).weak());
+                });
+                return;
+            }
+            Some(Err(e)) => {
+                ui.vertical_centered(|ui| {
+                    ui.add_space(20.0);
+                    ui.label(egui::RichText::new("Couldn't make a readable version of this page").size(16.0 * font_size));
+                    ui.label(egui::RichText::new(e).weak());
+                    ui.add_space(8.0);
+                    if ui.button("Open in browser instead").clicked() {
+                        ui.ctx().open_url(egui::OpenUrl::new_tab(&post.url));
+                    }
+                });
+                return;
+            }
+            Some(Ok(article)) => article,
+        };
+
+        egui::ScrollArea::vertical()
+            .id_source(("reader", &post.name))
+            .auto_shrink([false; 2])
+            .show(ui, |ui| {
+                // A comfortable line length, centred in wide windows
+                let width = ui.available_width().min(720.0 * font_size);
+                let margin = (ui.available_width() - width) / 2.0;
+                ui.horizontal(|ui| {
+                    ui.add_space(margin);
+                    ui.vertical(|ui| {
+                        ui.set_width(width);
+                        ui.add_space(10.0);
+                        ui.label(egui::RichText::new(&article.title).size(22.0 * font_size).strong());
+                        let site = article.site.clone().unwrap_or_else(|| post.domain.clone());
+                        ui.hyperlink_to(egui::RichText::new(site).size(12.0 * font_size).weak(), &post.url);
+                        ui.add_space(12.0);
+                        for block in &article.blocks {
+                            match block {
+                                reader::Block::Heading(text) => {
+                                    ui.add_space(6.0);
+                                    ui.label(egui::RichText::new(text).size(18.0 * font_size).strong());
+                                }
+                                reader::Block::Paragraph(text) => {
+                                    ui.label(egui::RichText::new(text).size(15.0 * font_size));
+                                }
+                                reader::Block::Quote(text) => {
+                                    egui::Frame::none()
+                                        .inner_margin(egui::Margin { left: 12.0, ..Default::default() })
+                                        .stroke(egui::Stroke::new(2.0, ui.visuals().weak_text_color()))
+                                        .show(ui, |ui| ui.label(egui::RichText::new(text).size(15.0 * font_size).italics()));
+                                }
+                                reader::Block::ListItem(text) => {
+                                    ui.label(egui::RichText::new(format!("• {}", text)).size(15.0 * font_size));
+                                }
+                                reader::Block::Code(text) => {
+                                    ui.label(egui::RichText::new(text).monospace().size(13.0 * font_size));
+                                }
+                                reader::Block::Image(url) => {
+                                    ui.add(egui::Image::new(url.as_str()).max_width(width).max_height(480.0).rounding(4.0));
+                                }
+                                reader::Block::Caption(text) => {
+                                    ui.label(egui::RichText::new(text).size(12.0 * font_size).weak());
+                                }
+                            }
+                            ui.add_space(8.0);
+                        }
+                        ui.add_space(20.0);
+                    });
+                });
+            });
+    }
+
+    fn render_profile(&mut self, ui: &mut egui::Ui) {
+        let font_size = self.settings.font_size;
+        if ui.button("⏴ Back").clicked() {
+            *self.profile.lock().unwrap() = None;
+            return;
+        }
+
+        // Work from a snapshot so clicking an author in the list can replace the profile
+        let Some(profile) = self.profile.lock().unwrap().clone() else {
+            return;
+        };
+        let blocked = self.blocked_users.lock().unwrap().contains(&profile.name.to_lowercase());
+        let ignored_locally = self.settings.ignored_users.iter().any(|name| name.eq_ignore_ascii_case(&profile.name));
+        ui.horizontal(|ui| {
+            ui.label(egui::RichText::new(format!("u/{}", profile.name)).size(18.0 * font_size).strong());
+            ui.add_space(8.0);
+            if blocked {
+                ui.label(egui::RichText::new("Blocked on reddit.com").size(12.0 * font_size).weak());
+            } else if ui.button(if ignored_locally { "Unignore" } else { "Ignore" })
+                .on_hover_text("Hide this user's posts and comments in Rustle")
+                .clicked() {
+                self.set_ignored(&profile.name, !ignored_locally);
+            }
+        });
+        if let Some(about) = &profile.about {
+            ui.label(
+                egui::RichText::new(format!("{} post karma · {} comment karma", about.link_karma, about.comment_karma))
+                    .size(12.0 * font_size)
+            );
+            ui.label(
+                egui::RichText::new(format!("🍰 Cake day: {}", profile::cake_day(about.created_utc)))
+                    .size(12.0 * font_size)
+                    .weak()
+            );
+        }
+        if !profile.trophies.is_empty() {
+            ui.add_space(4.0);
+            ui.label(egui::RichText::new("Trophy case").size(14.0 * font_size).strong());
+            ui.horizontal_wrapped(|ui| {
+                for trophy in &profile.trophies {
+                    let badge = ui.allocate_ui(egui::vec2(96.0 * font_size, 72.0 * font_size), |ui| {
+                        ui.vertical_centered(|ui| {
+                            ui.add(egui::widgets::Image::new(&trophy.icon_70).fit_to_exact_size(egui::Vec2::splat(36.0 * font_size)));
+                            ui.label(egui::RichText::new(&trophy.name).size(11.0 * font_size));
+                            if let Some(granted_at) = trophy.granted_at {
+                                ui.label(egui::RichText::new(profile::cake_day(granted_at)).size(10.0 * font_size).weak());
+                            }
+                        });
+                    }).response;
+                    if let Some(description) = trophy.description.as_deref().filter(|text| !text.is_empty()) {
+                        badge.on_hover_text(description);
+                    }
+                }
+            });
+        }
+        ui.separator();
+
+        if profile.loading && profile.items.is_empty() {
+            ui.vertical_centered(|ui| {
+                ui.add_space(20.0);
+                ui.spinner();
+            });
+            return;
+        }
+        if profile.items.is_empty() {
+            ui.vertical_centered(|ui| {
+                ui.add_space(20.0);
+                ui.label(egui::RichText::new("No posts or comments yet.").size(16.0));
+            });
+            return;
+        }
+
+        let mut wants_more = false;
+        egui::ScrollArea::vertical()
+            .id_source(("profile", &profile.name))
+            .auto_shrink([false; 2])
+            .show(ui, |ui| {
+                for item in &profile.items {
+                    match item {
+                        profile::ProfileItem::Post(post) => self.render_post(ui, post),
+                        profile::ProfileItem::Comment(_) if self.is_ignored(&profile.name) => {}
+                        profile::ProfileItem::Comment(comment) => self.render_profile_comment(ui, comment),
+                    }
+                }
+                if profile.after.is_some() {
+                    ui.add_space(10.0);
+                    ui.vertical_centered(|ui| {
+                        if profile.loading {
+                            ui.spinner();
+                        } else {
+                            // Reaching the end of the list loads the next page, like the main feed
+                            let response = ui.button("Load more");
+                            wants_more = response.clicked() || ui.is_rect_visible(response.rect);
+                        }
+                    });
+                }
+            });
+
+        if wants_more {
+            self.load_more_profile();
+        }
+    }
+
+    fn open_history(&self) {
+        if self.store.is_none() {
+            return;
+        }
+        *self.open_post.lock().unwrap() = None;
+        *self.profile.lock().unwrap() = None;
+        *self.discover.lock().unwrap() = None;
+        *self.history.lock().unwrap() = Some(history::History::default());
+        self.load_history(false);
+    }
+
+    fn render_history(&mut self, ui: &mut egui::Ui) {
+        let font_size = self.settings.font_size;
+        if ui.button("⏴ Back").clicked() {
+            *self.history.lock().unwrap() = None;
+            return;
+        }
+
+        // Work from a snapshot so opening a post can record the visit
+        let Some(mut history) = self.history.lock().unwrap().clone() else {
+            return;
+        };
+        let mut clear = false;
+        ui.horizontal(|ui| {
+            ui.label(egui::RichText::new("History").size(18.0 * font_size).strong());
+            ui.add_space(8.0);
+            ui.add(egui::TextEdit::singleline(&mut history.query).hint_text("Search history").desired_width(200.0));
+            if history.visits.is_empty() && history.query.trim().is_empty() {
+                return;
+            }
+            if history.confirm_clear {
+                ui.label("Forget every visit?");
+                clear = ui.button("Clear").clicked();
+                if ui.button("Keep").clicked() {
+                    history.confirm_clear = false;
+                }
+            } else if ui.button("🗑 Clear history").clicked() {
+                history.confirm_clear = true;
+            }
+        });
+        if clear {
+            if let Some(store) = self.store.clone() {
+                let error_message = self.error_message.clone();
+                thread::spawn(move || {
+                    if let Err(e) = store.clear_history() {
+                        *error_message.lock().unwrap() = Some(RustleError::classify(&e));
+                    }
+                });
+            }
+            history.confirm_clear = false;
+        }
+        if let Some(current) = self.history.lock().unwrap().as_mut() {
+            current.query = history.query.clone();
+            current.confirm_clear = history.confirm_clear;
+            if clear {
+                current.visits.clear();
+                current.more = false;
+            }
+        }
+        // Searching goes back to the database, which has every visit rather than the pages read so far
+        if history.stale() && !history.loading {
+            self.load_history(false);
+        }
+        ui.separator();
+
+        if history.visits.is_empty() || clear {
+            ui.vertical_centered(|ui| {
+                ui.add_space(20.0);
+                if history.searched.is_none() {
+                    ui.spinner();
+                    return;
+                }
+                let empty = if history.query.trim().is_empty() { "Posts you open show up here." } else { "Nothing in your history matches." };
+                ui.label(egui::RichText::new(empty).size(16.0));
+            });
+            return;
+        }
+        let now = store::now();
+        let mut wants_more = false;
+        egui::ScrollArea::vertical()
+            .id_source("history")
+            .auto_shrink([false; 2])
+            .show(ui, |ui| {
+                for visit in &history.visits {
+                    ui.add_space(4.0);
+                    ui.label(egui::RichText::new(format!("Visited {}", relative_time(visit.visited_at as f64, now)))
+                        .size(11.0 * font_size)
+                        .weak());
+                    self.render_post(ui, &visit.post);
+                }
+                if history.more {
+                    ui.add_space(10.0);
+                    ui.vertical_centered(|ui| {
+                        if history.loading {
+                            ui.spinner();
+                        } else {
+                            // Reaching the end of the list loads the next page, like the main feed
+                            let response = ui.button("Load more");
+                            wants_more = response.clicked() || ui.is_rect_visible(response.rect);
+                        }
+                    });
+                }
+            });
+
+        if wants_more {
+            self.load_history(true);
+        }
+    }
+
+    fn open_discover(&self, source: discover::Source) {
+        *self.open_post.lock().unwrap() = None;
+        *self.profile.lock().unwrap() = None;
+        *self.history.lock().unwrap() = None;
+        *self.discover.lock().unwrap() = Some(discover::Discover::new(source));
+        self.fetch_discover(source, None);
+    }
+
+    fn load_more_discover(&self) {
+        let (source, after) = {
+            let mut discover = self.discover.lock().unwrap();
+            let Some(discover) = discover.as_mut() else {
+                return;
+            };
+            let Some(after) = discover.after.clone().filter(|_| !discover.loading) else {
+                return;
+            };
+            discover.loading = true;
+            (discover.source, after)
+        };
+        self.fetch_discover(source, Some(after));
+    }
+
+    fn fetch_discover(&self, source: discover::Source, after: Option<String>) {
+        let reddit_client = self.reddit_client.clone();
+        let settings = self.settings.clone();
+        let discover = self.discover.clone();
+        let error_message = self.error_message.clone();
+        let egui_ctx = self.egui_ctx.clone();
+
+        thread::spawn(move || {
+            let rt = tokio::runtime::Runtime::new().unwrap();
+            rt.block_on(async {
+                let result = match ensure_client(&reddit_client, &settings).await {
+                    Ok(client) => match source.directory() {
+                        Some(directory) => client.get_subreddit_directory(directory, after.as_deref()).await,
+                        None => client.get_trending_subreddits().await.map(|subreddits| (subreddits, None)),
+                    },
+                    Err(e) => Err(e),
+                };
+
+                let mut discover = discover.lock().unwrap();
+                // The reader may have left or switched tabs in the meantime
+                let Some(discover) = discover.as_mut().filter(|discover| discover.source == source) else {
+                    return;
+                };
+                match result {
+                    Ok((subreddits, next_after)) => {
+                        let show_nsfw = settings.show_nsfw;
+                        discover.subreddits.extend(subreddits.into_iter().filter(|about| show_nsfw || !about.over18));
+                        discover.after = next_after;
+                    }
+                    Err(e) => {
+                        *error_message.lock().unwrap() = Some(RustleError::classify(&e).context("Error fetching subreddits"));
+                    }
+                }
+                discover.loading = false;
+                if let Some(ctx) = &egui_ctx {
+                    ctx.request_repaint();
+                }
+            });
+        });
+    }
+
+    fn render_discover(&mut self, ui: &mut egui::Ui) {
+        let font_size = self.settings.font_size;
+        if ui.button("⏴ Back").clicked() {
+            *self.discover.lock().unwrap() = None;
+            return;
+        }
+
+        // Work from a snapshot so joining or switching tabs can update the state
+        let Some(discover) = self.discover.lock().unwrap().clone() else {
+            return;
+        };
+        ui.horizontal(|ui| {
+            ui.label(egui::RichText::new("Discover").size(18.0 * font_size).strong());
+            ui.add_space(8.0);
+            for source in discover::Source::ALL {
+                if ui.selectable_label(discover.source == source, source.label()).clicked() && discover.source != source {
+                    self.open_discover(source);
+                }
+            }
+        });
+        ui.label(egui::RichText::new("Join subreddits to fill your home feed").size(12.0 * font_size).weak());
+        ui.separator();
+
+        if discover.loading && discover.subreddits.is_empty() {
+            ui.vertical_centered(|ui| {
+                ui.add_space(20.0);
+                ui.spinner();
+            });
+            return;
+        }
+        if discover.subreddits.is_empty() {
+            ui.vertical_centered(|ui| {
+                ui.add_space(20.0);
+                ui.label(egui::RichText::new("Nothing here right now.").size(16.0));
+            });
+            return;
+        }
+
+        let mut wants_more = false;
+        let mut open = None;
+        egui::ScrollArea::vertical()
+            .id_source(("discover", discover.source.label()))
+            .auto_shrink([false; 2])
+            .show(ui, |ui| {
+                for about in &discover.subreddits {
+                    if self.render_subreddit_card(ui, about) {
+                        open = Some(about.display_name.clone());
+                    }
+                    ui.add_space(4.0);
+                }
+                if discover.after.is_some() {
+                    ui.add_space(10.0);
+                    ui.vertical_centered(|ui| {
+                        if discover.loading {
+                            ui.spinner();
+                        } else {
+                            // Reaching the end of the list loads the next page, like the main feed
+                            let response = ui.button("Load more");
+                            wants_more = response.clicked() || ui.is_rect_visible(response.rect);
+                        }
+                    });
+                }
+            });
+
+        if let Some(subreddit) = open {
+            self.switch_subreddit(subreddit);
+        } else if wants_more {
+            self.load_more_discover();
+        }
+    }
+
+    // True when the name was clicked to open the subreddit
+    fn render_subreddit_card(&mut self, ui: &mut egui::Ui, about: &subreddit::SubredditAbout) -> bool {
+        let font_size = self.settings.font_size;
+        let mut open = false;
+        egui::Frame::group(ui.style()).rounding(5.0).show(ui, |ui| {
+            ui.set_width(ui.available_width());
+            ui.horizontal(|ui| {
+                match about.icon_url() {
+                    Some(icon) => {
+                        ui.add(egui::widgets::Image::new(icon).fit_to_exact_size(egui::Vec2::splat(40.0)).rounding(20.0));
+                    }
+                    None => {
+                        let (rect, _) = ui.allocate_exact_size(egui::Vec2::splat(40.0), egui::Sense::hover());
+                        ui.painter().circle_filled(rect.center(), 20.0, ui.visuals().faint_bg_color);
+                        ui.painter().text(rect.center(), egui::Align2::CENTER_CENTER, "r/", egui::FontId::proportional(14.0), ui.visuals().weak_text_color());
+                    }
+                }
+                ui.vertical(|ui| {
+                    ui.horizontal(|ui| {
+                        let name = egui::RichText::new(format!("r/{}", about.display_name)).size(15.0 * font_size).strong();
+                        let name = ui.link(name);
+                        self.hint(ui, &name, || hints::Jump::Subreddit(about.display_name.clone()));
+                        if name.on_hover_text("Open this subreddit").clicked() {
+                            open = true;
+                        }
+                        if about.over18 {
+                            ui.label(egui::RichText::new("NSFW").size(11.0 * font_size).strong().color(ui.visuals().error_fg_color));
+                        }
+                        if let Some(counts) = about.counts() {
+                            ui.label(egui::RichText::new(counts).size(12.0 * font_size).weak());
+                        }
+                        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                            let subscribed = self.is_subscribed(&about.display_name);
+                            let updating = *self.updating_subscription.lock().unwrap();
+                            let label = if subscribed { "Leave" } else { "Join" };
+                            if ui.add_enabled(!updating, egui::Button::new(label).rounding(5.0)).clicked() {
+                                if self.settings.signed_in() {
+                                    self.set_subscription(about.display_name.clone(), !subscribed);
+                                } else {
+                                    self.set_picked(&about.display_name, !subscribed);
+                                }
+                            }
+                        });
+                    });
+                    if !about.title.is_empty() {
+                        ui.label(egui::RichText::new(&about.title).size(13.0 * font_size));
+                    }
+                    if !about.public_description.is_empty() {
+                        ui.label(egui::RichText::new(&about.public_description).size(12.0 * font_size).weak());
+                    }
+                });
+            });
+        });
+        open
+    }
+
+    fn render_profile_comment(&self, ui: &mut egui::Ui, comment: &profile::ProfileComment) {
+        let font_size = self.settings.font_size;
+        ui.add_space(10.0);
+        egui::Frame::group(ui.style())
+            .outer_margin(0.0)
+            .show(ui, |ui| {
+                ui.set_min_width(ui.available_width());
+                ui.horizontal_wrapped(|ui| {
+                    ui.label(egui::RichText::new(format!("Commented in r/{} on", comment.subreddit)).size(12.0 * font_size).weak());
+                    let title = egui::RichText::new(&comment.link_title).size(12.0 * font_size);
+                    ui.hyperlink_to(title, format!("https://www.reddit.com{}", comment.permalink));
+                });
+                markdown::show(ui, &comment.body, 14.0 * font_size);
+                ui.label(egui::RichText::new(format!("{} points", comment.score)).size(12.0 * font_size).weak());
+            });
+    }
+
+    fn open_comments(&self, post: &Post) {
+        self.mark_read(std::slice::from_ref(&post.name));
+        self.note_opened(post);
+        *self.feed_scroll_return.lock().unwrap() = Some(*self.last_scroll_pos.lock().unwrap());
+        *self.open_post.lock().unwrap() = Some(post.clone());
+        self.comments.lock().unwrap().clear();
+        *self.comment_tree.lock().unwrap() = comments::TreeView::default();
+        *self.thread_sort.lock().unwrap() = self.settings.comment_sort.clone();
+        *self.reply_draft.lock().unwrap() = None;
+        if self.demo {
+            *self.comments.lock().unwrap() = demo::comments(&post.id);
+            return;
+        }
+        self.fetch_comments(post);
+    }
+
+    // Refetches the open thread in another order, keeping the threaded/reader choice
+    fn sort_comments(&self, post: &Post, sort: &str) {
+        *self.thread_sort.lock().unwrap() = sort.to_string();
+        if self.demo {
+            return;
+        }
+        self.comments.lock().unwrap().clear();
+        let mut tree = self.comment_tree.lock().unwrap();
+        let linear = tree.linear;
+        *tree = comments::TreeView::default();
+        tree.linear = linear;
+        drop(tree);
+        self.fetch_comments(post);
+    }
+
+    fn fetch_comments(&self, post: &Post) {
+        *self.loading_comments.lock().unwrap() = true;
+        let load = self.watchdog.begin("comments");
+
+        let permalink = post.permalink.clone();
+        let comment_sort = self.thread_sort.lock().unwrap().clone();
+        let thread_sort = self.thread_sort.clone();
+        let reddit_client = self.reddit_client.clone();
+        let settings = self.settings.clone();
+        let comments = self.comments.clone();
+        let loading_comments = self.loading_comments.clone();
+        let error_message = self.error_message.clone();
+        let store = self.store.clone();
+        let egui_ctx = self.egui_ctx.clone();
+
+        thread::spawn(move || {
+            let rt = tokio::runtime::Runtime::new().unwrap();
+            rt.block_on(async {
+                let result = match ensure_client(&reddit_client, &settings).await {
+                    Ok(client) => client.get_comments(&permalink, &comment_sort).await,
+                    Err(e) => Err(e),
+                };
+                // Another sort was picked while this one loaded, or the watchdog gave up on it
+                if !load.current() || *thread_sort.lock().unwrap() != comment_sort {
+                    return;
+                }
+
+                match result {
+                    Ok(fetched_comments) => {
+                        if let Some(store) = &store {
+                            let _ = store.save_comments(&permalink, &fetched_comments);
+                        }
+                        *comments.lock().unwrap() = fetched_comments;
+                    }
+                    Err(e) if store::is_network_error(&e) => {
+                        match store.as_ref().and_then(|store| Some((store, store.load_comments(&permalink).ok()??))) {
+                            Some((store, stored)) => {
+                                *comments.lock().unwrap() = stored.data;
+                                store.mark_offline(stored.synced_at);
+                            }
+                            None => {
+                                *error_message.lock().unwrap() = Some(RustleError::Network("Offline, and this thread hasn't been saved for offline reading yet".to_string()));
+                            }
+                        }
+                    }
+                    Err(e) => {
+                        *error_message.lock().unwrap() = Some(RustleError::classify(&e).context("Error fetching comments"));
+                    }
+                }
+                *loading_comments.lock().unwrap() = false;
+                if let Some(ctx) = &egui_ctx {
+                    ctx.request_repaint();
+                }
+            });
+        });
+    }
+
+    fn render_comments(&self, ui: &mut egui::Ui, post: &Post) {
+        let mut jump = None;
+        let mut resort = None;
+        let mut back = false;
+        let mut to_subreddit = false;
+        let font_size = self.settings.font_size;
+        let feed = match (self.current_view.lock().unwrap().as_ref(), self.current_subreddit.lock().unwrap().as_str()) {
+            (Some(view), _) => view.name.clone(),
+            (None, feed) => feed_title(feed),
+        };
+        // Breadcrumbs: feed › subreddit › post
+        ui.horizontal(|ui| {
+            back = ui.link(egui::RichText::new(format!("⏴ {}", feed)).size(12.0 * font_size)).on_hover_text("Back to the feed (Esc)").clicked();
+            let subreddit = format!("r/{}", post.subreddit);
+            if subreddit != feed {
+                ui.label(egui::RichText::new("›").size(12.0 * font_size).weak());
+                to_subreddit = ui.link(egui::RichText::new(subreddit).size(12.0 * font_size)).clicked();
+            }
+            ui.label(egui::RichText::new("›").size(12.0 * font_size).weak());
+            let title: String = post.title.chars().take(60).collect();
+            let title = if title.len() < post.title.len() { format!("{}…", title.trim_end()) } else { title };
+            ui.label(egui::RichText::new(title).size(12.0 * font_size).weak());
+        });
+        ui.horizontal(|ui| {
+            if ui.button("⏶ Previous").on_hover_text("Previous top-level comment (K)").clicked() {
+                jump = Some(false);
+            }
+            if ui.button("⏷ Next").on_hover_text("Next top-level comment (J)").clicked() {
+                jump = Some(true);
+            }
+            ui.add_space(8.0);
+            let mut tree = self.comment_tree.lock().unwrap();
+            let linear = tree.linear;
+            if ui.selectable_label(!linear, "Threaded").clicked() && linear {
+                tree.linear = false;
+                tree.row_heights.clear();
+            }
+            if ui.selectable_label(linear, "Reader").on_hover_text("Every comment in posting order, without nesting").clicked() && !linear {
+                tree.linear = true;
+                tree.row_heights.clear();
+            }
+            drop(tree);
+            ui.add_space(8.0);
+            let current = self.thread_sort.lock().unwrap().clone();
+            let label = COMMENT_SORTS.iter().find(|(sort, _)| *sort == current).map_or("Best", |(_, label)| label);
+            egui::ComboBox::from_id_source("thread_sort")
+                .selected_text(label)
+                .show_ui(ui, |ui| {
+                    for (sort, label) in COMMENT_SORTS {
+                        if ui.selectable_label(current == sort, label).clicked() && current != sort {
+                            resort = Some(sort);
+                        }
+                    }
+                });
+        });
+        if let Some(sort) = resort {
+            self.sort_comments(post, sort);
+        }
+        if !ui.ctx().wants_keyboard_input() && ui.input(|i| i.key_pressed(egui::Key::Escape)) {
+            back = true;
+        }
+        if back || to_subreddit {
+            *self.open_post.lock().unwrap() = None;
+            if to_subreddit {
+                self.switch_subreddit(post.subreddit.clone());
+            }
+            return;
+        }
+        if !ui.ctx().wants_keyboard_input() {
+            ui.input(|i| {
+                if i.key_pressed(egui::Key::J) {
+                    jump = Some(true);
+                } else if i.key_pressed(egui::Key::K) {
+                    jump = Some(false);
+                }
+            });
+        }
+        self.render_post(ui, post);
+        self.render_post_details(ui, post);
+        let participants = self.thread_participants(post);
+        if !post.locked && !*self.loading_comments.lock().unwrap() {
+            self.render_reply_link(ui, &post.name, "Add a comment");
+            self.render_composer(ui, &post.name, &participants);
+        }
+        ui.add_space(5.0);
+        ui.separator();
+
+        if *self.loading_comments.lock().unwrap() {
+            ui.vertical_centered(|ui| {
+                ui.add_space(20.0);
+                ui.spinner();
+            });
+            return;
+        }
+
+        let comments = self.comments.lock().unwrap();
+        let mut tree = self.comment_tree.lock().unwrap();
+        let rows = tree.rows(&comments);
+        if rows.is_empty() {
+            ui.vertical_centered(|ui| {
+                ui.add_space(20.0);
+                ui.label(egui::RichText::new("No comments yet.").size(16.0));
+            });
+            return;
+        }
+
+        // Only rows intersecting the viewport are laid out; the rest are stand-in spaces
+        // sized from the height each row had the last time it was drawn
+        let mut actions = Vec::new();
+        let mut root_offsets = Vec::new();
+        let mut comment_offsets = Vec::new();
+        let mut scroll_area = egui::ScrollArea::vertical()
+            .id_source("comments")
+            .auto_shrink([false; 2]);
+        if let Some(offset) = tree.scroll_to.take() {
+            scroll_area = scroll_area.vertical_scroll_offset(offset);
+        }
+        // Long threads leave a strip on the right for the minimap
+        let area = ui.available_rect_before_wrap();
+        let minimap = rows.len() >= comments::MINIMAP_MIN_ROWS;
+        let (content_rect, minimap_rect) = if minimap {
+            let split = area.right() - MINIMAP_WIDTH - 4.0;
+            (area.with_max_x(split), area.with_min_x(split + 4.0))
+        } else {
+            (area, egui::Rect::NOTHING)
+        };
+        let output = ui.allocate_ui_at_rect(content_rect, |ui| scroll_area
+            .show_viewport(ui, |ui, viewport| {
+                ui.set_width(ui.available_width());
+                let spacing = ui.spacing().item_spacing.y;
+                let mut y = 0.0;
+                for row in &rows {
+                    if matches!(row, comments::Row::Comment { depth: 0, .. } | comments::Row::Linear { parent: None, .. }) {
+                        root_offsets.push(y);
+                    }
+                    if matches!(row, comments::Row::Comment { .. } | comments::Row::Linear { .. }) {
+                        comment_offsets.push(y);
+                    }
+                    let key = row.key();
+                    let mut height = tree.row_heights.get(&key).copied().unwrap_or(ESTIMATED_COMMENT_HEIGHT);
+                    if y + height < viewport.min.y || y > viewport.max.y {
+                        ui.allocate_space(egui::vec2(ui.available_width(), height));
+                    } else {
+                        height = ui.scope(|ui| self.render_comment_row(ui, row, &participants, &tree, post, &mut actions))
+                            .response.rect.height();
+                        tree.row_heights.insert(key, height);
+                    }
+                    y += height + spacing;
+                }
+            })).inner;
+
+        if minimap {
+            let content_height = output.content_size.y;
+            let viewport_height = output.inner_rect.height();
+            let response = ui.allocate_rect(minimap_rect, egui::Sense::click_and_drag())
+                .on_hover_text("Comment density; click or drag to jump");
+            if let Some(pointer) = response.interact_pointer_pos() {
+                let fraction = (pointer.y - minimap_rect.top()) / minimap_rect.height();
+                tree.scroll_to = Some(comments::minimap_offset(fraction, content_height, viewport_height));
+                ui.ctx().request_repaint();
+            }
+            self.paint_minimap(ui, minimap_rect, &comment_offsets, &root_offsets, &output);
+        }
+
+        if let Some(forward) = jump {
+            tree.scroll_to = comments::jump_target(&root_offsets, output.state.offset.y, forward);
+            ui.ctx().request_repaint();
+        }
+
+        for action in actions {
+            if let comments::TreeAction::LoadMore(more) = &action {
+                self.load_more_comments(post, more.clone());
+            }
+            tree.apply(action);
+        }
+    }
+
+    fn load_more_comments(&self, post: &Post, more: comments::MoreComments) {
+        let link_id = post.name.clone();
+        let permalink = post.permalink.clone();
+        let sort = self.thread_sort.lock().unwrap().clone();
+        let reddit_client = self.reddit_client.clone();
+        let settings = self.settings.clone();
+        let comments = self.comments.clone();
+        let comment_tree = self.comment_tree.clone();
+        let error_message = self.error_message.clone();
+        let store = self.store.clone();
+        let egui_ctx = self.egui_ctx.clone();
+
+        thread::spawn(move || {
+            let rt = tokio::runtime::Runtime::new().unwrap();
+            let result = rt.block_on(async {
+                ensure_client(&reddit_client, &settings).await?.get_more_children(&link_id, &more, &sort).await
+            });
+            // Same order as the comment view takes them
+            let mut comments = comments.lock().unwrap();
+            let mut tree = comment_tree.lock().unwrap();
+            tree.finish_loading_more(&more.name);
+            match result {
+                Ok(things) => {
+                    if comments::expand_more(&mut comments, &more, things) {
+                        if let Some(store) = &store {
+                            let _ = store.save_comments(&permalink, &comments);
+                        }
+                    }
+                }
+                Err(e) => {
+                    *error_message.lock().unwrap() = Some(RustleError::classify(&e).context("Error loading more replies"));
+                }
+            }
+            drop((comments, tree));
+            if let Some(ctx) = &egui_ctx {
+                ctx.request_repaint();
+            }
+        });
+    }
+
+    // Shaded by how many comments start in each band, with a tick per top-level comment and a
+    // frame over the part on screen
+    fn paint_minimap(&self, ui: &egui::Ui, rect: egui::Rect, comment_offsets: &[f32], root_offsets: &[f32], scroll: &egui::scroll_area::ScrollAreaOutput<()>) {
+        let (content_height, offset, viewport_height) = (scroll.content_size.y, scroll.state.offset.y, scroll.inner_rect.height());
+        let visuals = ui.visuals();
+        let painter = ui.painter_at(rect);
+        painter.rect_filled(rect, 2.0, visuals.extreme_bg_color);
+        if content_height <= 0.0 {
+            return;
+        }
+        let bands = comments::density(comment_offsets, content_height, (rect.height() / MINIMAP_BAND).max(1.0) as usize);
+        let busiest = bands.iter().copied().max().unwrap_or(0).max(1) as f32;
+        let band_height = rect.height() / bands.len() as f32;
+        for (i, &count) in bands.iter().enumerate() {
+            if count > 0 {
+                let top = rect.top() + i as f32 * band_height;
+                let band = egui::Rect::from_min_max(egui::pos2(rect.left(), top), egui::pos2(rect.right(), top + band_height));
+                painter.rect_filled(band, 0.0, visuals.selection.bg_fill.gamma_multiply(0.2 + 0.8 * count as f32 / busiest));
+            }
+        }
+        let to_y = |y: f32| rect.top() + y / content_height * rect.height();
+        for &y in root_offsets {
+            painter.hline(rect.x_range(), to_y(y), egui::Stroke::new(1.0, visuals.weak_text_color()));
+        }
+        let seen = egui::Rect::from_min_max(
+            egui::pos2(rect.left(), to_y(offset)),
+            egui::pos2(rect.right(), to_y((offset + viewport_height).min(content_height))),
+        );
+        painter.rect_stroke(seen, 2.0, egui::Stroke::new(1.5, visuals.strong_text_color()));
+    }
+
+    fn render_post_details(&self, ui: &mut egui::Ui, post: &Post) {
+        let font_size = self.settings.font_size;
+        ui.add_space(4.0);
+        egui::CollapsingHeader::new(egui::RichText::new("Details").size(12.0 * font_size))
+            .id_source("post_details")
+            .default_open(true)
+            .show(ui, |ui| {
+                egui::Grid::new("post_details_grid").num_columns(2).spacing([16.0, 2.0]).show(ui, |ui| {
+                    for (label, value) in post.details() {
+                        ui.label(egui::RichText::new(label).size(12.0 * font_size).weak());
+                        ui.label(egui::RichText::new(value).size(12.0 * font_size));
+                        ui.end_row();
+                    }
+                });
+            });
+    }
+
+    fn render_comment_row(&self, ui: &mut egui::Ui, row: &comments::Row, participants: &[String], tree: &comments::TreeView, post: &Post, actions: &mut Vec<comments::TreeAction>) {
+        let depth = match row {
+            comments::Row::Comment { depth, .. }
+            | comments::Row::ShowMore { depth, .. }
+            | comments::Row::ContinueThread { depth, .. }
+            | comments::Row::NotLoaded { depth, .. } => *depth,
+            comments::Row::Linear { .. } => 0,
+        };
+        let font_size = self.settings.font_size;
+
+        ui.horizontal(|ui| {
+            ui.add_space(depth as f32 * COMMENT_INDENT);
+            ui.vertical(|ui| {
+                match row {
+                    comments::Row::Comment { comment, .. } | comments::Row::Linear { comment, .. } if self.is_ignored(&comment.author) => {
+                        ui.label(egui::RichText::new("[comment from a blocked user]").size(12.0 * font_size).italics().weak());
+                    }
+                    comments::Row::Comment { comment, .. } => {
+                        let collapsed = tree.is_collapsed(&comment.id);
+                        ui.horizontal(|ui| {
+                            let toggle = if collapsed { "[+]" } else { "[–]" };
+                            if ui.add(egui::Button::new(egui::RichText::new(toggle).size(12.0 * font_size).weak()).frame(false)).clicked() {
+                                actions.push(comments::TreeAction::ToggleCollapsed(comment.id.clone()));
+                            }
+                            self.render_comment_header(ui, comment);
+                            self.render_comment_mod_menu(ui, comment, &post.subreddit);
+                        });
+                        if !collapsed {
+                            self.render_comment_body(ui, comment);
+                            if !post.locked && !comment.locked && !comment.body_deleted() {
+                                self.render_reply_link(ui, &comment.name, "Reply");
+                            }
+                            self.render_composer(ui, &comment.name, participants);
+                        }
+                    }
+                    comments::Row::Linear { comment, parent } => {
+                        ui.horizontal(|ui| {
+                            self.render_comment_header(ui, comment);
+                            self.render_comment_mod_menu(ui, comment, &post.subreddit);
+                            if let Some(parent) = parent {
+                                let reply_to = egui::RichText::new(format!("↩ reply to {}", parent.author)).size(12.0 * font_size);
+                                if ui.link(reply_to).on_hover_text("Show the comment being replied to").clicked() {
+                                    actions.push(comments::TreeAction::ToggleQuote(comment.id.clone()));
+                                }
+                            }
+                        });
+                        if let Some(parent) = parent.filter(|parent| tree.is_quoted(&comment.id) && !self.is_ignored(&parent.author)) {
+                            egui::Frame::none()
+                                .stroke(ui.style().visuals.widgets.noninteractive.bg_stroke)
+                                .inner_margin(6.0)
+                                .rounding(4.0)
+                                .show(ui, |ui| {
+                                    ui.label(egui::RichText::new(format!("u/{}", parent.author)).size(12.0 * font_size).weak().strong());
+                                    ui.label(self.comment_body_text(parent).weak());
+                                });
+                        }
+                        self.render_comment_body(ui, comment);
+                        if !post.locked && !comment.locked && !comment.body_deleted() {
+                            self.render_reply_link(ui, &comment.name, "Reply");
+                        }
+                        self.render_composer(ui, &comment.name, participants);
+                    }
+                    comments::Row::ShowMore { parent, hidden, .. } => {
+                        if ui.link(egui::RichText::new(format!("show {} more replies", hidden)).size(12.0 * font_size)).clicked() {
+                            actions.push(comments::TreeAction::ShowMore(parent.clone()));
+                        }
+                    }
+                    comments::Row::ContinueThread { id, hidden, .. } => {
+                        if ui.link(egui::RichText::new(format!("continue this thread ⏵ ({} more)", hidden)).size(12.0 * font_size)).clicked() {
+                            actions.push(comments::TreeAction::ContinueThread(id.clone()));
+                        }
+                    }
+                    comments::Row::NotLoaded { more, .. } if self.demo => {
+                        ui.label(egui::RichText::new(format!("{} more replies not loaded", more.count)).size(12.0 * font_size).weak());
+                    }
+                    comments::Row::NotLoaded { more, .. } if tree.is_loading_more(&more.name) => {
+                        ui.horizontal(|ui| {
+                            ui.spinner();
+                            ui.label(egui::RichText::new("Loading replies...").size(12.0 * font_size).weak());
+                        });
+                    }
+                    comments::Row::NotLoaded { more, .. } => {
+                        let label = format!("load {} more repl{}", more.count, if more.count == 1 { "y" } else { "ies" });
+                        if ui.link(egui::RichText::new(label).size(12.0 * font_size)).clicked() {
+                            actions.push(comments::TreeAction::LoadMore((*more).clone()));
+                        }
+                    }
+                }
+            });
+        });
+    }
+
+    fn render_comment_header(&self, ui: &mut egui::Ui, comment: &comments::Comment) {
+        let font_size = self.settings.font_size;
+        let author = if comment.author_deleted() {
+            egui::RichText::new(&comment.author).italics().weak()
+        } else if comment.is_submitter {
+            egui::RichText::new(format!("u/{} (OP)", comment.author)).strong()
+        } else {
+            egui::RichText::new(format!("u/{}", comment.author)).strong()
+        };
+        ui.label(author.size(12.0 * font_size));
+        let score = if comment.score_hidden {
+            "score hidden".to_string()
+        } else {
+            format!("{} points", comment.score)
+        };
+        ui.label(egui::RichText::new(score).size(12.0 * font_size).weak());
+        if comment.stickied {
+            ui.label(egui::RichText::new("📌").size(12.0 * font_size));
+        }
+        if comment.distinguished.as_deref() == Some("moderator") {
+            ui.label(egui::RichText::new("[M]").size(12.0 * font_size).strong()).on_hover_text("Speaking as a moderator");
+        }
+        if comment.locked {
+            ui.label(egui::RichText::new("🔒").size(12.0 * font_size)).on_hover_text("Locked: no new replies");
+        }
+        if comment.removed {
+            ui.label(egui::RichText::new("removed").size(12.0 * font_size).color(ui.style().visuals.error_fg_color))
+                .on_hover_text("Removed by moderators; only they can still see it");
+        }
+    }
+
+    fn render_comment_mod_menu(&self, ui: &mut egui::Ui, comment: &comments::Comment, subreddit: &str) {
+        if !self.moderates(subreddit) || comment.author_deleted() {
+            return;
+        }
+        ui.menu_button(egui::RichText::new("Moderate").size(12.0 * self.settings.font_size).weak(), |ui| {
+            self.render_mod_actions(ui, &comment.name, comment.locked, comment.distinguished.is_some());
+        });
+    }
+
+    fn render_comment_body(&self, ui: &mut egui::Ui, comment: &comments::Comment) {
+        if comment.body_deleted() {
+            ui.label(self.comment_body_text(comment));
+        } else {
+            markdown::show(ui, &comment.body, 14.0 * self.settings.font_size);
+        }
+    }
+
+    fn render_reply_link(&self, ui: &mut egui::Ui, parent: &str, label: &str) {
+        if !self.settings.signed_in() {
+            return;
+        }
+        let text = egui::RichText::new(label).size(12.0 * self.settings.font_size);
+        if ui.link(text).clicked() {
+            let mut draft = self.reply_draft.lock().unwrap();
+            // Keep whatever is already typed if the same box is reopened
+            if draft.as_ref().is_none_or(|draft| draft.parent != parent) {
+                *draft = Some(comments::Draft::new(parent.to_string()));
+            }
+        }
+    }
+
+    // Who's in the open thread, offered when typing u/. Worked out only while a reply box is open,
+    // and taken before drawing so the composer never needs the comments lock.
+    fn thread_participants(&self, post: &Post) -> Vec<String> {
+        if self.reply_draft.lock().unwrap().is_none() {
+            return Vec::new();
+        }
+        let thread = self.comments.lock().unwrap();
+        thread_users(&thread, Some(&post.author), &self.settings.account.username)
+    }
+
+    // Inline reply box with a write/preview toggle, shown under whatever it replies to
+    fn render_composer(&self, ui: &mut egui::Ui, parent: &str, participants: &[String]) {
+        let mut draft_guard = self.reply_draft.lock().unwrap();
+        let Some(draft) = draft_guard.as_mut().filter(|draft| draft.parent == parent) else {
+            return;
+        };
+        let font_size = self.settings.font_size;
+        let mut submit = false;
+        let mut cancel = false;
+        let mut undo = None;
+
+        let editor_id = ui.make_persistent_id(("composer", parent));
+        let mut cursor = None;  // Where to put the cursor after a snippet goes in
+        egui::Frame::group(ui.style()).show(ui, |ui| {
+            ui.horizontal(|ui| {
+                ui.selectable_value(&mut draft.preview, false, "Write");
+                ui.selectable_value(&mut draft.preview, true, "Preview");
+                if !draft.preview && !draft.submitting {
+                    ui.menu_button("Snippets ⏷", |ui| {
+                        if self.settings.snippets.is_empty() {
+                            ui.label(egui::RichText::new("Add snippets in settings").weak());
+                        }
+                        for snippet in &self.settings.snippets {
+                            let button = ui.button(&snippet.name).on_hover_text(format!("Or type /{} and a space", snippet.trigger()));
+                            if button.clicked() {
+                                let at = egui::TextEdit::load_state(ui.ctx(), editor_id)
+                                    .and_then(|state| state.cursor.char_range())
+                                    .map_or(usize::MAX, |range| range.primary.index);
+                                cursor = Some(draft.insert(at, &snippet.text));
+                                ui.close_menu();
+                            }
+                        }
+                    });
+                }
+            });
+            if draft.preview {
+                if draft.text.trim().is_empty() {
+                    ui.label(egui::RichText::new("Nothing to preview").size(14.0 * font_size).weak());
+                } else {
+                    markdown::show(ui, &draft.text, 14.0 * font_size);
+                }
+            } else {
+                let dictionary = self.settings.account.spelling_language()
+                    .and_then(|language| self.speller.dictionary(&language, ui.ctx()));
+                let mut layouter = |ui: &egui::Ui, text: &str, wrap_width: f32| spellcheck::layout(ui, dictionary.as_deref(), text, wrap_width);
+                // Arrow keys and Enter pick from the u/ and r/ suggestions while they're up
+                let mut accept = false;
+                if let Some(choice) = draft.mention_choice.filter(|_| ui.memory(|memory| memory.has_focus(editor_id))) {
+                    ui.input_mut(|i| {
+                        if i.consume_key(egui::Modifiers::NONE, egui::Key::ArrowDown) {
+                            draft.mention_choice = Some(choice + 1);
+                        } else if i.consume_key(egui::Modifiers::NONE, egui::Key::ArrowUp) {
+                            draft.mention_choice = Some(choice.saturating_sub(1));
+                        } else if i.consume_key(egui::Modifiers::NONE, egui::Key::Enter) {
+                            accept = true;
+                        }
+                    });
+                }
+                let output = egui::TextEdit::multiline(&mut draft.text)
+                    .id(editor_id)
+                    .desired_rows(4)
+                    .desired_width(f32::INFINITY)
+                    .hint_text("Markdown is supported; type / and a snippet's name to insert it")
+                    .interactive(!draft.submitting)
+                    .layouter(&mut layouter)
+                    .show(ui);
+                // Right-clicking an underlined word offers replacements for it
+                if output.response.changed() {
+                    draft.correction = None;
+                }
+                if output.response.secondary_clicked() {
+                    draft.correction = output.response.interact_pointer_pos().zip(dictionary.as_ref()).and_then(|(pos, dictionary)| {
+                        let index = output.galley.cursor_from_pos(pos - output.galley_pos).ccursor.index;
+                        let at = draft.text.char_indices().nth(index).map_or(draft.text.len(), |(at, _)| at);
+                        dictionary.misspelled(&draft.text).into_iter().find(|range| range.start <= at && at <= range.end)
+                    });
+                }
+                if draft.correction.is_some() {
+                    output.response.context_menu(|ui| {
+                        let (Some(range), Some(dictionary)) = (draft.correction.clone(), &dictionary) else {
+                            return;
+                        };
+                        let Some(suggestions) = dictionary.suggestions(&draft.text[range.clone()], ui.ctx()) else {
+                            ui.label(egui::RichText::new("Looking for suggestions…").weak());
+                            return;
+                        };
+                        if suggestions.is_empty() {
+                            ui.label(egui::RichText::new("No suggestions").weak());
+                        }
+                        for suggestion in suggestions {
+                            if ui.button(&suggestion).clicked() {
+                                cursor = Some(draft.replace(range.clone(), &suggestion));
+                                draft.correction = None;
+                                ui.close_menu();
+                            }
+                        }
+                    });
+                }
+                // A click on a suggestion takes focus from the editor first, so the list stays up
+                // for the frame focus is lost in
+                let showing = output.response.has_focus() || (output.response.lost_focus() && draft.mention_choice.is_some());
+                let at = output.state.cursor.char_range().map(|range| range.primary.index);
+                let mention = at.filter(|_| showing && !draft.submitting).and_then(|at| Some((at, draft.mention(at)?)));
+                let choice = draft.mention_choice.take();
+                if let Some((at, mention)) = mention {
+                    self.lookup_mention(&mention.typed());
+                    let local = if mention.user {
+                        participants.to_vec()
+                    } else {
+                        self.subreddit_names()
+                    };
+                    let remote = self.mention_lookup.lock().unwrap().results.get(&mention.typed()).cloned().unwrap_or_default();
+                    let names = mention_candidates(&mention.query, &local, &remote);
+                    if !names.is_empty() {
+                        let choice = choice.unwrap_or(0).min(names.len() - 1);
+                        let picked = if accept {
+                            Some(names[choice].clone())
+                        } else {
+                            render_mention_list(ui, &output, at, &mention, &names, choice)
+                        };
+                        match picked {
+                            Some(name) => cursor = Some(draft.complete_mention(&mention, at, &name)),
+                            None => draft.mention_choice = Some(choice),
+                        }
+                    }
+                }
+                if let Some(range) = output.cursor_range.filter(|_| output.response.changed()) {
+                    let snippets = &self.settings.snippets;
+                    let lookup = |trigger: &str| snippets.iter()
+                        .find(|snippet| snippet.trigger() == trigger.to_lowercase())
+                        .map(|snippet| snippet.text.as_str());
+                    cursor = draft.expand_snippet(range.primary.ccursor.index, lookup).or(cursor);
+                }
+            }
+            if let Some(index) = cursor {
+                let mut state = egui::TextEdit::load_state(ui.ctx(), editor_id).unwrap_or_default();
+                let at = egui::text::CCursor::new(index);
+                state.cursor.set_char_range(Some(egui::text::CCursorRange::one(at)));
+                state.store(ui.ctx(), editor_id);
+                ui.ctx().memory_mut(|memory| memory.request_focus(editor_id));
+            }
+            if let Some(error) = &draft.error {
+                ui.colored_label(ui.style().visuals.error_fg_color, error);
+            }
+            ui.horizontal(|ui| {
+                let queued = self.pending_sends.lock().unwrap().iter()
+                    .find(|queued| queued.reply_to() == Some(draft.parent.as_str()))
+                    .cloned();
+                if let Some(queued) = queued.filter(|_| draft.submitting) {
+                    let left = queued.send_at.saturating_duration_since(Instant::now());
+                    ui.label(format!("Posting in {}s", left.as_secs() + 1));
+                    if ui.button("Undo").clicked() {
+                        undo = Some(queued);
+                    }
+                } else if draft.submitting {
+                    ui.spinner();
+                    ui.label("Posting...");
+                } else {
+                    submit = ui.add_enabled(!draft.text.trim().is_empty(), egui::Button::new("Post")).clicked();
+                    cancel = ui.button("Cancel").clicked();
+                }
+            });
+        });
+
+        if let Some(queued) = undo {
+            drop(draft_guard);
+            self.undo_send(&queued);
+        } else if cancel {
+            *draft_guard = None;
+        } else if submit {
+            draft.submitting = true;
+            draft.error = None;
+            let text = draft.text.clone();
+            drop(draft_guard);
+            self.submit_reply(parent.to_string(), text);
+        }
+    }
+
+    fn submit_reply(&self, parent: String, text: String) {
+        let reddit_client = self.reddit_client.clone();
+        let settings = self.settings.clone();
+        let comments = self.comments.clone();
+        let reply_draft = self.reply_draft.clone();
+        let pending_sends = self.pending_sends.clone();
+        let egui_ctx = self.egui_ctx.clone();
+
+        let pending = self.hold_send(Sending::Reply { parent: parent.clone(), text: text.clone() });
+
+        thread::spawn(move || {
+            let rt = tokio::runtime::Runtime::new().unwrap();
+            rt.block_on(async {
+                if let Some(pending) = &pending {
+                    if !wait_to_send(&pending_sends, pending).await {
+                        return;
+                    }
+                }
+
+                let result = match ensure_client(&reddit_client, &settings).await {
+                    Ok(client) => client.submit_comment(&parent, &text).await,
+                    Err(e) => Err(e),
+                };
+
+                // One lock at a time: the comment view holds the comments while it draws the composer
+                match result {
+                    Ok(comment) => {
+                        comments::insert_reply(&mut comments.lock().unwrap(), &parent, comment);
+                        let mut draft = reply_draft.lock().unwrap();
+                        if draft.as_ref().is_some_and(|draft| draft.parent == parent) {
+                            *draft = None;
+                        }
+                    }
+                    Err(e) => {
+                        if let Some(draft) = reply_draft.lock().unwrap().as_mut().filter(|draft| draft.parent == parent) {
+                            draft.submitting = false;
+                            draft.error = Some(format!("{:#}", e));
+                        }
+                    }
+                }
+                if let Some(ctx) = &egui_ctx {
+                    ctx.request_repaint();
+                }
+            });
+        });
+    }
+
+    // Subscriptions and favorites, for r/ suggestions
+    fn subreddit_names(&self) -> Vec<String> {
+        let subreddits = self.subreddits.lock().unwrap();
+        self.settings.favorite_subreddits.iter().chain(subreddits.iter()).cloned().collect()
+    }
+
+    // Ask Reddit for names matching a u/ or r/ reference once typing pauses on it
+    fn lookup_mention(&self, typed: &str) {
+        let mut lookup = self.mention_lookup.lock().unwrap();
+        typed.clone_into(&mut lookup.typing);
+        // Two letters at least, or Reddit has too much to choose from
+        if self.demo || typed.chars().count() < 4 || lookup.results.contains_key(typed) || !lookup.requested.insert(typed.to_string()) {
+            return;
+        }
+        drop(lookup);
+
+        let reddit_client = self.reddit_client.clone();
+        let settings = self.settings.clone();
+        let mention_lookup = self.mention_lookup.clone();
+        let egui_ctx = self.egui_ctx.clone();
+        let typed = typed.to_string();
+
+        thread::spawn(move || {
+            let rt = tokio::runtime::Runtime::new().unwrap();
+            rt.block_on(async {
+                tokio::time::sleep(MENTION_LOOKUP_DELAY).await;
+                if mention_lookup.lock().unwrap().typing != typed {
+                    // Typing moved on; ask again if it comes back to this
+                    mention_lookup.lock().unwrap().requested.remove(&typed);
+                    return;
+                }
+                let (kind, query) = typed.split_at(2);
+                let result = match ensure_client(&reddit_client, &settings).await {
+                    Ok(client) => client.autocomplete(query, kind == "u/", settings.show_nsfw).await,
+                    Err(e) => Err(e),
+                };
+                // Suggestions are a nicety; without these the local names still show
+                mention_lookup.lock().unwrap().results.insert(typed, result.unwrap_or_default());
+                if let Some(ctx) = &egui_ctx {
+                    ctx.request_repaint();
+                }
+            });
+        });
+    }
+
+    // Queue a reply or post for the undo window; None when undo send is off and it goes right away
+    fn hold_send(&self, sending: Sending) -> Option<Arc<PendingSend>> {
+        let delay = Duration::from_secs(self.settings.undo_send_seconds as u64);
+        if delay.is_zero() {
+            return None;
+        }
+        let pending = Arc::new(PendingSend { sending, send_at: Instant::now() + delay });
+        self.pending_sends.lock().unwrap().push(pending.clone());
+        Some(pending)
+    }
+
+    // A reply being written to something else, which taking `pending` back would replace. A post
+    // never is: opening one in the composer keeps whatever was there in Drafts.
+    fn draft_in_the_way(&self, pending: &PendingSend) -> bool {
+        let Some(parent) = pending.reply_to() else {
+            return false;
+        };
+        self.reply_draft.lock().unwrap().as_ref()
+            .is_some_and(|draft| draft.parent != parent && !draft.text.trim().is_empty())
+    }
+
+    // Take a reply or post back during its undo window and reopen it for editing; a reply is
+    // refused while another is being written. A scheduled post goes back to Drafts, unscheduled.
+    fn undo_send(&self, pending: &Arc<PendingSend>) {
+        if self.draft_in_the_way(pending) {
+            return;
+        }
+        let mut queue = self.pending_sends.lock().unwrap();
+        let Some(index) = queue.iter().position(|queued| Arc::ptr_eq(queued, pending)) else {
+            return;  // Too late, it's already on its way
+        };
+        queue.remove(index);
+        drop(queue);
+
+        match &pending.sending {
+            Sending::Reply { parent, text } => {
+                let mut draft = self.reply_draft.lock().unwrap();
+                match draft.as_mut().filter(|draft| draft.parent == *parent) {
+                    Some(draft) => draft.submitting = false,
+                    None => {
+                        let mut restored = comments::Draft::new(parent.clone());
+                        restored.text = text.clone();
+                        *draft = Some(restored);
+                    }
+                }
+            }
+            Sending::Post { id, saved, scheduled: true } => {
+                self.keep_draft(*id, &submit::Saved { post_at: None, ..saved.clone() });
+            }
+            Sending::Post { id, saved, scheduled: false } => {
+                let mut draft = self.post_draft.lock().unwrap();
+                if let Some(draft) = draft.as_mut().filter(|draft| draft.submitting && draft.saved() == *saved) {
+                    draft.submitting = false;
+                    return;
+                }
+                drop(draft);
+                self.open_draft(submit::Draft::restore(*id, saved.clone()));
+            }
+        }
+    }
+
+    // "Posting reply in 4s · Undo" along the bottom of the window for each held reply or post
+    fn render_undo_toasts(&self, ctx: &egui::Context) {
+        let pending = self.pending_sends.lock().unwrap().clone();
+        if pending.is_empty() {
+            return;
+        }
+        egui::Area::new(egui::Id::new("undo_send"))
+            .anchor(egui::Align2::CENTER_BOTTOM, [0.0, -16.0])
+            .show(ctx, |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    for queued in &pending {
+                        let left = queued.send_at.saturating_duration_since(Instant::now());
+                        ui.horizontal(|ui| {
+                            let what = match &queued.sending {
+                                Sending::Reply { .. } => "reply".to_string(),
+                                Sending::Post { saved, .. } => saved.summary(),
+                            };
+                            ui.label(format!("Posting {} in {}s", what, left.as_secs() + 1));
+                            let undo = ui.add_enabled(!self.draft_in_the_way(queued), egui::Button::new("Undo"))
+                                .on_disabled_hover_text("Send or discard the reply you're writing first");
+                            if undo.clicked() {
+                                self.undo_send(queued);
+                            }
+                        });
+                    }
+                });
+            });
+        ctx.request_repaint_after(Duration::from_millis(250));
+    }
+
+    fn comment_body_text(&self, comment: &comments::Comment) -> egui::RichText {
+        let body = if comment.body_deleted() {
+            egui::RichText::new(&comment.body).italics().weak()
+        } else {
+            egui::RichText::new(&comment.body)
+        };
+        body.size(14.0 * self.settings.font_size)
+    }
+
+    fn moderates(&self, subreddit: &str) -> bool {
+        self.settings.signed_in() && self.moderated.lock().unwrap().iter().any(|name| name.eq_ignore_ascii_case(subreddit))
+    }
+
+    fn is_subscribed(&self, subreddit: &str) -> bool {
+        self.subreddits.lock().unwrap().iter().any(|name| name.eq_ignore_ascii_case(subreddit))
+    }
+
+    fn set_subscription(&self, subreddit: String, subscribe: bool) {
+        if *self.updating_subscription.lock().unwrap() {
+            return;
+        }
+        *self.updating_subscription.lock().unwrap() = true;
+        let load = self.watchdog.begin("subscription");
+
+        let reddit_client = self.reddit_client.clone();
+        let settings = self.settings.clone();
+        let subreddits = self.subreddits.clone();
+        let updating_subscription = self.updating_subscription.clone();
+        let error_message = self.error_message.clone();
+        let egui_ctx = self.egui_ctx.clone();
+
+        thread::spawn(move || {
+            let rt = tokio::runtime::Runtime::new().unwrap();
+            rt.block_on(async {
+                let result = async {
+                    let client = ensure_client(&reddit_client, &settings).await?;
+                    if subscribe {
+                        client.subscribe(&subreddit).await?;
+                    } else {
+                        client.unsubscribe(&subreddit).await?;
+                    }
+                    // Refetch so the sidebar matches Reddit's view, including its ordering
+                    client.get_subscribed_subreddits(|_| load.progress()).await
+                }.await;
+                if !load.current() {
+                    return;
+                }
+
+                match result {
+                    Ok(fetched_subreddits) => {
+                        *subreddits.lock().unwrap() = fetched_subreddits;
+                    }
+                    Err(e) => {
+                        *error_message.lock().unwrap() = Some(RustleError::classify(&e));
+                    }
+                }
+                *updating_subscription.lock().unwrap() = false;
+                if let Some(ctx) = &egui_ctx {
+                    ctx.request_repaint();
+                }
+            });
+        });
+    }
+
+    // Joining and leaving without an account only changes the local picks
+    fn set_picked(&mut self, subreddit: &str, picked: bool) {
+        self.settings.account.subreddit_picks.retain(|pick| !pick.eq_ignore_ascii_case(subreddit));
+        if picked {
+            self.settings.account.subreddit_picks.push(subreddit.to_string());
+        }
+        if let Err(e) = self.settings.save() {
+            *self.error_message.lock().unwrap() = Some(RustleError::classify(&e).context("Failed to save settings"));
+        }
+        *self.subreddits.lock().unwrap() = self.settings.account.subreddit_picks.clone();
+        // The client's front page is built from the picks, so start a fresh one
+        *self.reddit_client.lock().unwrap() = None;
+    }
+
+    fn update_favorites(&mut self, change: impl FnOnce(&mut Settings)) {
+        change(&mut self.settings);
+        if let Err(e) = self.settings.save() {
+            *self.error_message.lock().unwrap() = Some(RustleError::classify(&e).context("Failed to save settings"));
+        }
+    }
+
+    fn fetch_subreddit_suggestions(&self, query: String) {
+        let reddit_client = self.reddit_client.clone();
+        let settings = self.settings.clone();
+        let subreddit_suggestions = self.subreddit_suggestions.clone();
+        let egui_ctx = self.egui_ctx.clone();
+
+        thread::spawn(move || {
+            let rt = tokio::runtime::Runtime::new().unwrap();
+            rt.block_on(async {
+                // Suggestions are best-effort, so failures just leave the list empty
+                let Ok(client) = ensure_client(&reddit_client, &settings).await else {
+                    return;
+                };
+                if let Ok(names) = client.get_subreddit_autocomplete(&query).await {
+                    *subreddit_suggestions.lock().unwrap() = (query, names);
+                    if let Some(ctx) = &egui_ctx {
+                        ctx.request_repaint();
+                    }
+                }
+            });
+        });
+    }
+
+    // Favorites pinned at the top in their own order, then every other subscription A to Z
+    fn render_sidebar(&mut self, ctx: &egui::Context, loading: bool) {
+        egui::SidePanel::left("subreddit_sidebar")
+            .resizable(true)
+            .default_width(200.0)
+            .width_range(140.0..=360.0)
+            .show(ctx, |ui| {
+                let current = self.current_subreddit.lock().unwrap().clone();
+                let subreddits = self.subreddits.lock().unwrap().clone();
+
+                ui.add_space(6.0);
+                ui.add(
+                    egui::TextEdit::singleline(&mut self.sidebar_query)
+                        .hint_text("Filter subreddits")
+                        .desired_width(f32::INFINITY)
+                );
+                if *self.loading_subreddits.lock().unwrap() {
+                    ui.horizontal(|ui| {
+                        ui.spinner();
+                        let fetched = *self.subreddits_fetched.lock().unwrap();
+                        if fetched > 0 {
+                            ui.label(egui::RichText::new(format!("Loading subreddits ({})...", fetched)).weak());
+                        }
+                    });
+                }
+                ui.add_space(4.0);
+
+                let query = self.sidebar_query.clone();
+                let matches = |name: &str| name.to_lowercase().contains(&query.trim().trim_start_matches("r/").to_lowercase());
+                let favorites = self.settings.favorite_subreddits.clone();
+                let others: Vec<&String> = subreddits.iter().filter(|name| !self.settings.is_favorite(name)).collect();
+                let groups = sidebar::by_letter(others, &query);
+                let mut moved = None;
+                let mut toggled = None;
+                egui::ScrollArea::vertical().auto_shrink([false; 2]).show(ui, |ui| {
+                    // Draggable to reorder, between the rows the filter leaves showing
+                    let mut pinned = 0;
+                    for (index, subreddit) in favorites.iter().enumerate().filter(|(_, name)| matches(name)) {
+                        pinned += 1;
+                        let response = ui.add(
+                            egui::Button::new(
+                                egui::RichText::new(format!("★ /r/{}", subreddit))
+                                    .color(if current.eq_ignore_ascii_case(subreddit) {
+                                        ui.style().visuals.text_color()
+                                    } else {
+                                        ui.style().visuals.weak_text_color()
+                                    })
+                            ).frame(false).sense(egui::Sense::click_and_drag())
+                        ).on_hover_text("Drag to reorder");
+                        self.hint(ui, &response, || hints::Jump::Subreddit(subreddit.clone()));
+                        response.dnd_set_drag_payload(FavoriteDrag(index));
+                        if response.dnd_hover_payload::<FavoriteDrag>().is_some() {
+                            let rect = response.rect;
+                            ui.painter().hline(rect.x_range(), rect.top() - 1.0, ui.style().visuals.selection.stroke);
+                        }
+                        if let Some(dragged) = response.dnd_release_payload::<FavoriteDrag>() {
+                            moved = Some((dragged.0, index));
+                        }
+                        response.context_menu(|ui| {
+                            if ui.button("Remove from favorites").clicked() {
+                                toggled = Some((subreddit.clone(), false));
+                                ui.close_menu();
+                            }
+                        });
+                        if response.clicked() && !loading && current != *subreddit {
+                            self.switch_subreddit(subreddit.clone());
+                        }
+                    }
+                    if pinned > 0 {
+                        ui.separator();
+                    }
+
+                    for (letter, names) in &groups {
+                        ui.add_space(4.0);
+                        ui.label(egui::RichText::new(letter.to_string()).small().strong().weak());
+                        for subreddit in names {
+                            let response = ui.add(
+                                egui::Button::new(
+                                    egui::RichText::new(format!("/r/{}", subreddit))
+                                        .color(if current == *subreddit {
+                                            ui.style().visuals.text_color()
+                                        } else {
+                                            ui.style().visuals.weak_text_color()
+                                        })
+                                ).frame(false)
+                            );
+                            self.hint(ui, &response, || hints::Jump::Subreddit(subreddit.to_string()));
+                            response.context_menu(|ui| {
+                                if ui.button("Add to favorites").clicked() {
+                                    toggled = Some((subreddit.to_string(), true));
+                                    ui.close_menu();
+                                }
+                            });
+                            if response.clicked() && !loading && current != *subreddit {
+                                self.switch_subreddit(subreddit.to_string());
+                            }
+                        }
+                    }
+                    if pinned == 0 && groups.is_empty() && !query.trim().is_empty() {
+                        ui.label(egui::RichText::new("No subscriptions match").weak());
+                    }
+                });
+
+                if let Some((from, to)) = moved {
+                    self.update_favorites(|settings| settings.move_favorite(from, to));
+                }
+                if let Some((subreddit, favorite)) = toggled {
+                    self.update_favorites(|settings| settings.set_favorite(&subreddit, favorite));
+                }
+            });
+    }
+
+    fn render_go_to_subreddit(&mut self, ui: &mut egui::Ui, loading: bool) {
+        let response = ui.add(
+            egui::TextEdit::singleline(&mut self.go_to_query)
+                .hint_text("Go to r/...")
+                .desired_width(140.0)
+        );
+        let query = self.go_to_query.trim().trim_start_matches("r/").trim_start_matches("/r/").to_string();
+
+        if response.changed() && query.len() >= 2 {
+            self.fetch_subreddit_suggestions(query.clone());
+        }
+
+        let popup_id = ui.make_persistent_id("go_to_subreddit_suggestions");
+        let suggestions = {
+            let (answered, names) = &*self.subreddit_suggestions.lock().unwrap();
+            if answered.eq_ignore_ascii_case(&query) { names.clone() } else { Vec::new() }
+        };
+        if response.has_focus() && !suggestions.is_empty() {
+            ui.memory_mut(|memory| memory.open_popup(popup_id));
+        }
+
+        let mut target = None;
+        egui::popup_below_widget(ui, popup_id, &response, |ui| {
+            ui.set_min_width(200.0);
+            for name in &suggestions {
+                if ui.selectable_label(false, format!("r/{}", name)).clicked() {
+                    target = Some(name.clone());
+                }
+            }
+        });
+        if response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) && !query.is_empty() {
+            target = Some(query);
+        }
+
+        if let Some(name) = target {
+            if !loading {
+                self.go_to_query.clear();
+                ui.memory_mut(|memory| memory.close_popup());
+                self.switch_subreddit(name);
+            }
+        }
+    }
+
+    // Header details are a nicety, so failures (private or banned subreddits) leave them out quietly
+    fn load_subreddit_about(&self, subreddit: &str) {
+        *self.subreddit_about.lock().unwrap() = None;
+        if self.demo || subreddit == "home" || is_aggregate_feed(subreddit) {
+            return;
+        }
+
+        let name = subreddit.to_string();
+        let reddit_client = self.reddit_client.clone();
+        let settings = self.settings.clone();
+        let current_subreddit = self.current_subreddit.clone();
+        let subreddit_about = self.subreddit_about.clone();
+        let egui_ctx = self.egui_ctx.clone();
+
+        thread::spawn(move || {
+            let rt = tokio::runtime::Runtime::new().unwrap();
+            rt.block_on(async {
+                let result = match ensure_client(&reddit_client, &settings).await {
+                    Ok(client) => {
+                        // The header still shows without rules if they can't be had
+                        let (about, rules) = tokio::join!(client.get_subreddit_about(&name), client.get_subreddit_rules(&name));
+                        about.map(|about| subreddit::SubredditAbout { rules: rules.unwrap_or_default(), ..about })
+                    }
+                    Err(e) => Err(e),
+                };
+                // Drop it if the reader has moved on to another feed
+                if let Some(about) = result.ok().filter(|_| *current_subreddit.lock().unwrap() == name) {
+                    *subreddit_about.lock().unwrap() = Some(about);
+                    if let Some(ctx) = &egui_ctx {
+                        ctx.request_repaint();
+                    }
+                }
+            });
+        });
+    }
+
+    fn render_subreddit_header(&mut self, ui: &mut egui::Ui, subreddit: &str) {
+        let about = self.subreddit_about.lock().unwrap().clone()
+            .filter(|about| about.display_name.eq_ignore_ascii_case(subreddit));
+        if let Some(banner) = about.as_ref().and_then(|about| about.banner_url()) {
+            ui.add(
+                egui::widgets::Image::new(banner)
+                    .max_size(egui::vec2(ui.available_width(), 80.0))
+                    .rounding(5.0)
+            );
+        }
+        ui.horizontal(|ui| {
+            if let Some(icon) = about.as_ref().and_then(|about| about.icon_url()) {
+                ui.add(egui::widgets::Image::new(icon).fit_to_exact_size(egui::Vec2::splat(28.0)).rounding(14.0));
+            }
+            ui.label(
+                egui::RichText::new(feed_title(subreddit))
+                    .size(18.0 * self.settings.font_size)
+                    .strong()
+            );
+            if !is_unfiltered_feed(subreddit) {
+                self.render_media_menu(ui, subreddit);
+            }
+            self.render_export_menu(ui, subreddit);
+            if is_aggregate_feed(subreddit) {
+                return;
+            }
+            if let Some(about) = &about {
+                if !about.title.is_empty() {
+                    ui.label(egui::RichText::new(&about.title).size(14.0 * self.settings.font_size).weak());
+                    if let Some(gloss) = self.gloss(&about.title) {
+                        translate::show(ui, &gloss, 12.0 * self.settings.font_size);
+                    }
+                }
+                if let Some(counts) = about.counts() {
+                    ui.label(egui::RichText::new(counts).size(12.0 * self.settings.font_size).weak());
+                }
+            }
+            ui.add_space(8.0);
+            let subscribed = self.is_subscribed(subreddit);
+            let updating = *self.updating_subscription.lock().unwrap();
+            let label = if subscribed { "Leave" } else { "Join" };
+            if ui.add_enabled(!updating, egui::Button::new(label).rounding(5.0)).clicked() {
+                if self.settings.signed_in() {
+                    self.set_subscription(subreddit.to_string(), !subscribed);
+                } else {
+                    self.set_picked(subreddit, !subscribed);
+                }
+            }
+            if updating {
+                ui.spinner();
+            }
+            let favorite = self.settings.is_favorite(subreddit);
+            let star = egui::Button::new(if favorite { "★" } else { "☆" }).rounding(5.0);
+            if ui.add(star).on_hover_text(if favorite { "Remove from favorites" } else { "Add to favorites" }).clicked() {
+                self.update_favorites(|settings| settings.set_favorite(subreddit, !favorite));
+            }
+            if self.settings.signed_in() && ui.add(egui::Button::new("✏ Post").rounding(5.0)).on_hover_text(format!("Submit a post to r/{}", subreddit)).clicked() {
+                self.open_post_composer(subreddit);
+            }
+        });
+        if let Some(about) = about.filter(|about| !about.rules.is_empty()) {
+            self.render_rules(ui, &about);
+        }
+        self.render_view_tabs(ui, subreddit);
+        ui.separator();
+    }
+
+    fn render_rules(&self, ui: &mut egui::Ui, about: &subreddit::SubredditAbout) {
+        egui::CollapsingHeader::new(format!("Rules ({})", about.rules.len()))
+            .id_source(("rules", &about.display_name))
+            .show(ui, |ui| {
+                for (number, rule) in about.rules.iter().enumerate() {
+                    ui.horizontal_wrapped(|ui| {
+                        ui.label(egui::RichText::new(format!("{}. {}", number + 1, rule.short_name)).strong());
+                        if let Some(gloss) = self.gloss(&rule.short_name) {
+                            translate::show(ui, &gloss, 12.0 * self.settings.font_size);
+                        }
+                    });
+                    if !rule.description.is_empty() {
+                        ui.label(egui::RichText::new(&rule.description).weak());
+                        if let Some(gloss) = self.gloss(&rule.description) {
+                            translate::show(ui, &gloss, 11.0 * self.settings.font_size);
+                        }
+                    }
+                }
+            });
+    }
+
+    // A machine translation to show beside `text` once the translation server has answered;
+    // None when translation is off or the text is already in the reader's language
+    fn gloss(&self, text: &str) -> Option<translate::Gloss> {
+        let backend = self.settings.translation()?;
+        match self.glosses.lock().unwrap().lookup(text, &backend.target) {
+            translate::Lookup::Ready(gloss) => return gloss,
+            translate::Lookup::Wait => return None,
+            translate::Lookup::Fetch => {}
+        }
+
+        let text = text.to_string();
+        let glosses = self.glosses.clone();
+        let egui_ctx = self.egui_ctx.clone();
+        thread::spawn(move || {
+            let rt = tokio::runtime::Runtime::new().unwrap();
+            let result = rt.block_on(async {
+                let client = Client::builder().user_agent(APP_USER_AGENT).build()?;
+                translate::translate(&client, &backend, &text).await
+            });
+            // A failure isn't retried this session; the original text is still there to read
+            let gloss = result.unwrap_or_else(|e| {
+                tracing::warn!(error = %format!("{:#}", e), "Translation failed");
+                None
+            });
+            glosses.lock().unwrap().finish(&text, gloss);
+            if let Some(ctx) = &egui_ctx {
+                ctx.request_repaint();
+            }
+        });
+        None
+    }
+
+    // The home feed has no subreddit to describe, only its media mutes and exports
+    fn render_home_header(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label(egui::RichText::new("Home").size(18.0 * self.settings.font_size).strong());
+            self.render_media_menu(ui, "home");
+            self.render_export_menu(ui, "home");
+        });
+        ui.separator();
+    }
+
+    // Kinds of post to hide from this feed, e.g. videos in r/news; other feeds are unaffected
+    fn render_media_menu(&mut self, ui: &mut egui::Ui, feed: &str) {
+        let muted = self.settings.muted_media(feed).to_vec();
+        let mut toggled = None;
+        let label = if muted.is_empty() { "Media ⏷".to_string() } else { format!("Media ({} hidden) ⏷", muted.len()) };
+        ui.menu_button(label, |ui| {
+            for kind in MediaKind::ALL {
+                let mut hidden = muted.contains(&kind);
+                if ui.checkbox(&mut hidden, format!("Hide {}", kind.label().to_lowercase())).changed() {
+                    toggled = Some((kind, hidden));
+                }
+            }
+        }).response.on_hover_text("Hide kinds of post from this feed");
+        if let Some((kind, hidden)) = toggled {
+            self.update_favorites(|settings| settings.set_muted(feed, kind, hidden));
+            self.refresh_posts();
+        }
+    }
+
+    // Writes the feed's posts to a file; while a whole feed is paged through, shows how far it's got
+    fn render_export_menu(&mut self, ui: &mut egui::Ui, feed: &str) {
+        let fetched = self.export_progress.lock().unwrap().as_ref().map(|progress| progress.fetched);
+        if let Some(fetched) = fetched {
+            ui.spinner();
+            ui.label(egui::RichText::new(format!("Exporting {} posts...", fetched)).weak());
+            if ui.small_button("Cancel").clicked() {
+                if let Some(progress) = self.export_progress.lock().unwrap().as_mut() {
+                    progress.cancelled = true;
+                }
+            }
+            return;
+        }
+        let loaded = self.posts.lock().unwrap().len();
+        let mut export = None;
+        ui.menu_button("Export ⏷", |ui| {
+            ui.label(egui::RichText::new(format!("The {} posts loaded", loaded)).weak());
+            for format in export::Format::ALL {
+                if ui.add_enabled(loaded > 0, egui::Button::new(format.label())).clicked() {
+                    export = Some((format, export::Scope::Loaded));
+                    ui.close_menu();
+                }
+            }
+            // Sample posts have no more pages to fetch
+            if !self.demo {
+                ui.separator();
+                ui.label(egui::RichText::new(format!("The whole feed, up to {} posts", export::MAX_POSTS)).weak());
+                for format in export::Format::ALL {
+                    if ui.button(format.label()).clicked() {
+                        export = Some((format, export::Scope::All));
+                        ui.close_menu();
+                    }
+                }
+            }
+        }).response.on_hover_text("Save this feed's posts as JSON, CSV or a Markdown link list");
+        if let Some((format, scope)) = export {
+            self.export_posts(feed, format, scope);
+        }
+    }
+
+    // Asks where to save, fetching every page first for a whole-feed export
+    fn export_posts(&self, feed: &str, format: export::Format, scope: export::Scope) {
+        let feed = feed.to_string();
+        let view = self.current_view.lock().unwrap().clone();
+        let title = view.as_ref().map_or_else(|| feed_title(&feed), |view| view.name.clone());
+        let loaded = self.posts.lock().unwrap().clone();
+        let reddit_client = self.reddit_client.clone();
+        let settings = self.settings.clone();
+        let export_progress = self.export_progress.clone();
+        let export_saved = self.export_saved.clone();
+        let error_message = self.error_message.clone();
+        let egui_ctx = self.egui_ctx.clone();
+        *export_progress.lock().unwrap() = Some(export::Progress::default());
+
+        thread::spawn(move || {
+            let rt = tokio::runtime::Runtime::new().unwrap();
+            let repaint = || {
+                if let Some(ctx) = &egui_ctx {
+                    ctx.request_repaint();
+                }
+            };
+            let result = rt.block_on(async {
+                let mut dialog = rfd::AsyncFileDialog::new()
+                    .set_file_name(export::file_name(&feed, format, store::now()))
+                    .add_filter(format.label(), &[format.extension()]);
+                if let Ok(dir) = exports_dir() {
+                    dialog = dialog.set_directory(dir);
+                }
+                let Some(file) = dialog.save_file().await else {
+                    return Ok(None);
+                };
+
+                let posts = match scope {
+                    export::Scope::Loaded => loaded,
+                    export::Scope::All => {
+                        let client = ensure_client(&reddit_client, &settings).await?;
+                        let mut posts: Vec<Post> = Vec::new();
+                        let mut after = None;
+                        loop {
+                            let (page, next_after, _) = fetch_filtered(&client, &settings, &feed, view.as_ref(), after.as_deref()).await?;
+                            posts.extend(page);
+                            let cancelled = match export_progress.lock().unwrap().as_mut() {
+                                Some(progress) => {
+                                    progress.fetched = posts.len();
+                                    progress.cancelled
+                                }
+                                None => true,
+                            };
+                            repaint();
+                            if cancelled {
+                                return Ok(None);
+                            }
+                            if next_after.is_none() || next_after == after || posts.len() >= export::MAX_POSTS {
+                                break;
+                            }
+                            after = next_after;
+                        }
+                        posts.truncate(export::MAX_POSTS);
+                        posts
+                    }
+                };
+                let path = file.path().to_path_buf();
+                std::fs::write(&path, export::render(&posts, format, &title)?)
+                    .with_context(|| format!("Failed to write {}", path.display()))?;
+                Ok::<_, anyhow::Error>(Some((path, posts.len())))
+            });
+            *export_progress.lock().unwrap() = None;
+            match result {
+                Ok(saved) => *export_saved.lock().unwrap() = saved,
+                Err(e) => *error_message.lock().unwrap() = Some(RustleError::classify(&e).context("Failed to export posts")),
+            }
+            repaint();
+        });
+    }
+
+    // Filter and highlight rules to a file of their own, for sharing
+    fn export_rules(&self) {
+        let rules = self.settings.filters.clone();
+        let rules_exported = self.rules_exported.clone();
+        let error_message = self.error_message.clone();
+        let egui_ctx = self.egui_ctx.clone();
+
+        thread::spawn(move || {
+            let rt = tokio::runtime::Runtime::new().unwrap();
+            let result = rt.block_on(async {
+                let mut dialog = rfd::AsyncFileDialog::new()
+                    .set_file_name("rustle-rules.json")
+                    .add_filter("Rustle rules", &["json"]);
+                if let Ok(dir) = exports_dir() {
+                    dialog = dialog.set_directory(dir);
+                }
+                let Some(file) = dialog.save_file().await else {
+                    return Ok(None);
+                };
+                let path = file.path().to_path_buf();
+                std::fs::write(&path, filters::write_set(&rules)?)
+                    .with_context(|| format!("Failed to write {}", path.display()))?;
+                Ok::<_, anyhow::Error>(Some(path))
+            });
+            match result {
+                Ok(saved) => *rules_exported.lock().unwrap() = saved.map(|path| (path, rules.len())),
+                Err(e) => *error_message.lock().unwrap() = Some(RustleError::classify(&e).context("Failed to export rules")),
+            }
+            if let Some(ctx) = &egui_ctx {
+                ctx.request_repaint();
+            }
+        });
+    }
+
+    // Reads a shared rule file; the settings panel then asks whether to merge or replace
+    fn pick_rule_file(&self) {
+        let rule_import = self.rule_import.clone();
+        let error_message = self.error_message.clone();
+        let egui_ctx = self.egui_ctx.clone();
+
+        thread::spawn(move || {
+            let rt = tokio::runtime::Runtime::new().unwrap();
+            let result = rt.block_on(async {
+                let Some(file) = rfd::AsyncFileDialog::new().add_filter("Rustle rules", &["json"]).pick_file().await else {
+                    return Ok(None);
+                };
+                let text = std::fs::read_to_string(file.path())
+                    .with_context(|| format!("Failed to read {}", file.path().display()))?;
+                Ok::<_, anyhow::Error>(Some((file.file_name(), filters::read_set(&text)?)))
+            });
+            match result {
+                Ok(Some((file, rules))) => *rule_import.lock().unwrap() = Some(filters::Import { file, rules }),
+                Ok(None) => {}
+                Err(e) => *error_message.lock().unwrap() = Some(RustleError::classify(&e).context("Failed to import rules")),
+            }
+            if let Some(ctx) = &egui_ctx {
+                ctx.request_repaint();
+            }
+        });
+    }
+
+    fn render_view_tabs(&mut self, ui: &mut egui::Ui, subreddit: &str) {
+        let views = self.settings.views_for(subreddit).to_vec();
+        let active = self.current_view.lock().unwrap().clone();
+        let mut select = None;
+        let mut delete = None;
+        let mut filter = None;
+
+        let prefs = self.settings.feed_prefs(subreddit);
+        let mut sorted = None;
+        let mut forget = false;
+
+        ui.horizontal_wrapped(|ui| {
+            // A sort picked here, without a flair, is still all posts
+            let all_posts = active.as_ref().is_none_or(|view| view.name.is_empty() && view.flair.is_empty());
+            if ui.selectable_label(all_posts, "All posts").clicked() && !all_posts {
+                select = Some(prefs.view());
+            }
+            // A flair filter picked from a pill or the menu rather than a saved view
+            if let Some(filter) = active.as_ref().filter(|active| !views.contains(active) && !active.flair.is_empty()) {
+                if ui.selectable_label(true, format!("Flair: {} ✖", filter.flair)).on_hover_text("Clear the flair filter").clicked() {
+                    select = Some(prefs.view());
+                }
+            }
+            for view in &views {
+                let selected = active.as_ref() == Some(view);
+                let response = ui.selectable_label(selected, &view.name).on_hover_text(view.describe());
+                if response.clicked() && !selected {
+                    select = Some(Some(view.clone()));
+                }
+                response.context_menu(|ui| {
+                    if ui.button("Delete view").clicked() {
+                        delete = Some(view.name.clone());
+                        ui.close_menu();
+                    }
+                });
+            }
+            let mut flairs: Vec<String> = Vec::new();
+            for post in self.posts.lock().unwrap().iter().filter(|post| post.subreddit.eq_ignore_ascii_case(subreddit)) {
+                if let Some(flair) = post.link_flair_text.as_ref().filter(|flair| !flair.is_empty() && !flairs.contains(flair)) {
+                    flairs.push(flair.clone());
+                }
+            }
+            if !flairs.is_empty() {
+                ui.menu_button("Flair ⏷", |ui| {
+                    for flair in flairs {
+                        if ui.button(&flair).clicked() {
+                            filter = Some(flair);
+                            ui.close_menu();
+                        }
+                    }
+                });
+            }
+            if self.view_editor.is_none() && ui.button("+ Save view").on_hover_text("Save a sort and flair filter for this subreddit").clicked() {
+                self.view_editor = Some(FeedView { name: String::new(), ..active.clone().unwrap_or_default() });
+            }
+
+            ui.add_space(8.0);
+            let mut view = active.clone().unwrap_or_default();
+            let mut changed = false;
+            egui::ComboBox::from_id_source("feed_sort")
+                .selected_text(format!("Sort: {}", view.sort))
+                .show_ui(ui, |ui| {
+                    for sort in VIEW_SORTS {
+                        changed |= ui.selectable_value(&mut view.sort, sort.to_string(), sort).changed();
+                    }
+                });
+            changed |= time_range_combo(ui, "feed_time", &view.sort.clone(), &mut view.time);
+            if changed {
+                sorted = Some(view);
+            }
+            if prefs != FeedPrefs::default() && ui.small_button("↺")
+                .on_hover_text(format!("Go back to the usual sort and layout in r/{}", subreddit))
+                .clicked() {
+                forget = true;
+            }
+        });
+
+        let mut saved = None;
+        if let Some(editor) = &mut self.view_editor {
+            let mut cancel = false;
+            ui.horizontal_wrapped(|ui| {
+                ui.add(egui::TextEdit::singleline(&mut editor.name).hint_text("View name").desired_width(140.0));
+                egui::ComboBox::from_id_source("view_sort")
+                    .selected_text(&editor.sort)
+                    .show_ui(ui, |ui| {
+                        for sort in VIEW_SORTS {
+                            ui.selectable_value(&mut editor.sort, sort.to_string(), sort);
+                        }
+                    });
+                time_range_combo(ui, "view_time", &editor.sort.clone(), &mut editor.time);
+                ui.add(egui::TextEdit::singleline(&mut editor.flair).hint_text("Flair (optional)").desired_width(140.0));
+                if ui.add_enabled(!editor.name.trim().is_empty(), egui::Button::new("Save")).clicked() {
+                    saved = Some(FeedView {
+                        name: editor.name.trim().to_string(),
+                        sort: editor.sort.clone(),
+                        time: editor.time.clone(),
+                        flair: editor.flair.trim().to_string(),
+                    });
+                }
+                cancel = ui.button("Cancel").clicked();
+            });
+            if cancel {
+                self.view_editor = None;
+            }
+        }
+
+        if let Some(flair) = filter {
+            self.filter_by_flair(subreddit.to_string(), flair);
+            return;
+        }
+        if sorted.is_some() || forget {
+            let view = sorted.clone().unwrap_or_default();
+            self.settings.update_feed_prefs(subreddit, |prefs| {
+                if forget {
+                    *prefs = FeedPrefs::default();
+                    return;
+                }
+                prefs.sort = if view.sort == "hot" { String::new() } else { view.sort.clone() };
+                prefs.time = if is_timed_sort(&view.sort) { view.time.clone() } else { String::new() };
+            });
+            if let Err(e) = self.settings.save() {
+                *self.error_message.lock().unwrap() = Some(RustleError::classify(&e).context("Failed to save settings"));
+            }
+            // A sort change keeps any flair filter; the sort alone becomes the subreddit's default
+            match sorted {
+                Some(view) if !view.flair.is_empty() => self.select_view(Some(FeedView { name: String::new(), ..view })),
+                Some(_) => self.select_view(self.settings.feed_prefs(subreddit).view()),
+                None => self.select_view(None),
+            }
+            return;
+        }
+        if saved.is_none() && delete.is_none() {
+            if let Some(view) = select {
+                self.select_view(view);
+            }
+            return;
+        }
+
+        let views = self.settings.saved_views.entry(subreddit.to_lowercase()).or_default();
+        if let Some(name) = &delete {
+            views.retain(|view| &view.name != name);
+        }
+        if let Some(view) = &saved {
+            // Saving under an existing name replaces that view
+            match views.iter_mut().find(|existing| existing.name == view.name) {
+                Some(existing) => *existing = view.clone(),
+                None => views.push(view.clone()),
+            }
+        }
+        if views.is_empty() {
+            self.settings.saved_views.remove(&subreddit.to_lowercase());
+        }
+        if let Err(e) = self.settings.save() {
+            *self.error_message.lock().unwrap() = Some(RustleError::classify(&e).context("Failed to save settings"));
+        }
+
+        if let Some(view) = saved {
+            self.view_editor = None;
+            self.select_view(Some(view));
+        } else if active.is_some_and(|view| Some(&view.name) == delete.as_ref()) {
+            self.select_view(None);
+        }
+    }
+
+    fn load_more_posts(&self) {
+        if *self.loading.lock().unwrap() || self.demo {
+            return;
+        }
+
+        let after_token = self.after.lock().unwrap().clone();
+        // Reddit stops handing out cursors at the end of a listing
+        if after_token.is_none() && !self.posts.lock().unwrap().is_empty() {
+            return;
+        }
+        let current_subreddit = self.current_subreddit.lock().unwrap().clone();
+        let current_view = self.current_view.lock().unwrap().clone();
+
+        // Serve the next page from the prefetch buffer when it's already waiting
+        let buffered = {
+            let mut prefetched = self.prefetched.lock().unwrap();
+            if prefetched.as_ref().is_some_and(|page| page.subreddit == current_subreddit && page.view == current_view && page.after == after_token) {
+                prefetched.take()
+            } else {
+                None
+            }
+        };
+        if let Some(page) = buffered {
+            append_page(&mut self.posts.lock().unwrap(), page.posts);
+            self.hidden_by_filters.lock().unwrap().push(page.hidden);
+            *self.after.lock().unwrap() = page.next_after;
+            self.prefetch_next_page();
+            return;
+        }
+
+        // The page we need is already on its way; the next scroll check will pick it up
+        if *self.prefetching.lock().unwrap() {
+            return;
+        }
+
+        *self.loading.lock().unwrap() = true;
+        let load = self.watchdog.begin("posts");
+
+        let posts = self.posts.clone();
+        let read_posts = self.read_posts.clone();
+        let store = self.store.clone();
+        let loading = self.loading.clone();
+        let error_message = self.error_message.clone();
+        let scroll_to_top = self.scroll_to_top.clone();
+        let reddit_client = self.reddit_client.clone();
+        let after = self.after.clone();
+        let initial_load = self.initial_load.clone();
+        let settings = self.settings.clone();
+        let hidden_by_filters = self.hidden_by_filters.clone();
+        let egui_ctx = self.egui_ctx.clone();
+
+        thread::spawn(move || {
+            let rt = tokio::runtime::Runtime::new().unwrap();
+            rt.block_on(async {
+                let client = match ensure_client(&reddit_client, &settings).await {
+                    Ok(client) => client,
+                    Err(_) if !load.current() => return,
+                    Err(e) => {
+                        *error_message.lock().unwrap() = Some(RustleError::classify(&e));
+                        *loading.lock().unwrap() = false;
+                        *initial_load.lock().unwrap() = false;
+                        return;
+                    }
+                };
+
+                let result = fetch_filtered(&client, &settings, &current_subreddit, current_view.as_ref(), after_token.as_deref()).await;
+                if !load.current() {
+                    return;
+                }
+
+                match result {
+                    Ok((fetched_posts, new_after, hidden)) => {
+                        if let Some(ctx) = &egui_ctx {
+                            prefetch_images(ctx, &fetched_posts, &settings);
+                        }
+                        merge_visited(&settings, &read_posts, &store, &fetched_posts);
+                        append_page(&mut posts.lock().unwrap(), fetched_posts);
+                        hidden_by_filters.lock().unwrap().push(hidden);
+                        *after.lock().unwrap() = new_after;
+                        *loading.lock().unwrap() = false;
+                        *initial_load.lock().unwrap() = false;
+                        if let Some(ctx) = &egui_ctx {
+                            ctx.request_repaint();
+                        }
+                    }
+                    Err(e) => {
+                        *error_message.lock().unwrap() = Some(RustleError::classify(&e).context("Error fetching posts"));
+                        *scroll_to_top.lock().unwrap() = true;
+                        *loading.lock().unwrap() = false;
+                        *initial_load.lock().unwrap() = false;
+                    }
+                }
+            });
+        });
+    }
+
+    fn toggle_inspector(&mut self) {
+        self.inspector = match self.inspector {
+            Some(_) => None,
+            None => Some(api_log::Inspector::default()),
+        };
+    }
+
+    // Ctrl+Shift+E: every loaded post exactly as parsed, for debugging filters and the parser
+    fn export_feed(&mut self) {
+        match self.write_feed_export() {
+            Ok(path) => self.notice = Some((format!("Exported the feed to {}", path.display()), Instant::now() + NOTICE_DURATION)),
+            Err(e) => *self.error_message.lock().unwrap() = Some(RustleError::classify(&e).context("Failed to export feed")),
+        }
+    }
+
+    fn write_feed_export(&self) -> Result<std::path::PathBuf> {
+        let dir = exports_dir()?;
+        let feed = self.current_subreddit.lock().unwrap().clone();
+        let export = serde_json::json!({
+            "feed": feed,
+            "view": *self.current_view.lock().unwrap(),
+            "after": *self.after.lock().unwrap(),
+            "exported_at": store::now(),
+            "posts": *self.posts.lock().unwrap(),
+        });
+        let path = dir.join(export_file_name(&feed, store::now()));
+        std::fs::write(&path, serde_json::to_string_pretty(&export)?).with_context(|| format!("Failed to write {}", path.display()))?;
+        Ok(path)
+    }
+
+    // Opens the composer for `subreddit` and looks up its posting rules and flairs alongside
+    fn open_post_composer(&self, subreddit: &str) {
+        if self.post_draft.lock().unwrap().as_ref().is_some_and(|draft| draft.subreddit == subreddit) {
+            return;
+        }
+        self.open_draft(submit::Draft::new(subreddit.to_string()));
+    }
+
+    fn open_draft(&self, draft: submit::Draft) {
+        let subreddit = draft.subreddit.clone();
+        let mut post_draft = self.post_draft.lock().unwrap();
+        // The one being replaced is already on disk, give or take the last second of typing
+        if let Some(open) = post_draft.as_ref().filter(|open| !open.is_blank()) {
+            self.keep_draft(open.id, &open.saved());
+        }
+        *post_draft = Some(draft);
+        drop(post_draft);
+
+        let reddit_client = self.reddit_client.clone();
+        let settings = self.settings.clone();
+        let post_draft = self.post_draft.clone();
+        let egui_ctx = self.egui_ctx.clone();
+
+        thread::spawn(move || {
+            let rt = tokio::runtime::Runtime::new().unwrap();
+            rt.block_on(async {
+                let (requirements, flairs) = match ensure_client(&reddit_client, &settings).await {
+                    Ok(client) => tokio::join!(client.get_post_requirements(&subreddit), client.get_link_flairs(&subreddit)),
+                    Err(e) => (Err(e), Err(anyhow::anyhow!("Not signed in"))),
+                };
+
+                let mut post_draft = post_draft.lock().unwrap();
+                let Some(draft) = post_draft.as_mut().filter(|draft| draft.subreddit == subreddit) else {
+                    return;
+                };
+                // Without the rules the draft is still checked for what Reddit requires of every post
+                draft.requirements = requirements.ok();
+                match flairs {
+                    Ok(flairs) => draft.flairs = flairs,
+                    Err(e) => draft.flair_error = Some(format!("Couldn't load flairs: {}", RustleError::classify(&e))),
+                }
+                draft.loading = false;
+                if let Some(ctx) = &egui_ctx {
+                    ctx.request_repaint();
+                }
+            });
+        });
+    }
+
+    fn show_post_composer(&mut self, ctx: &egui::Context) {
+        let mut post_draft = self.post_draft.lock().unwrap();
+        let Some(draft) = post_draft.as_mut() else {
+            return;
+        };
+        if let Some(url) = draft.posted.take() {
+            self.notice = Some((format!("Posted to r/{}: {}", draft.subreddit, url), Instant::now() + NOTICE_DURATION));
+            if let Some(id) = draft.id {
+                self.forget_draft(id);
+            }
+            *post_draft = None;
+            self.draft_edited_at = None;
+            return;
+        }
+        let mut submit = false;
+        match submit::show(ctx, draft) {
+            Some(submit::Action::Close) => {
+                if !draft.is_blank() {
+                    self.keep_draft(draft.id, &draft.saved());
+                }
+                *post_draft = None;
+            }
+            Some(submit::Action::Discard) => {
+                if let Some(id) = draft.id {
+                    self.forget_draft(id);
+                }
+                *post_draft = None;
+            }
+            Some(submit::Action::Schedule) => {
+                let post_at = draft.post_at(store::now());
+                let saved = submit::Saved { post_at: Some(post_at), ..draft.saved() };
+                if self.keep_draft(draft.id, &saved).is_some() {
+                    let message = format!("Scheduled to post to r/{} {}", draft.subreddit, submit::until(post_at, store::now()));
+                    self.notice = Some((message, Instant::now() + NOTICE_DURATION));
+                    *post_draft = None;
+                } else {
+                    draft.error = Some("Couldn't save the scheduled post".to_string());
+                }
+            }
+            Some(submit::Action::Submit) => {
+                draft.submitting = true;
+                draft.error = None;
+                submit = true;
+            }
+            None => {}
+        }
+        drop(post_draft);
+        if submit {
+            self.submit_post();
+        }
 
-impl Settings {
-    fn load() -> Self {
-        let keyring = Entry::new("Rustle", "credentials").unwrap();
-        let stored = keyring.get_password().unwrap_or_default();
-        if !stored.is_empty() {
-            if let Ok(settings) = serde_json::from_str(&stored) {
-                return settings;
+        // Written to disk once typing pauses
+        let mut post_draft = self.post_draft.lock().unwrap();
+        let Some(draft) = post_draft.as_mut().filter(|draft| !draft.is_blank() && !draft.submitting) else {
+            self.draft_edited_at = None;
+            return;
+        };
+        let saved = draft.saved();
+        let unchanged = draft.id.is_some_and(|id| self.drafts.lock().unwrap().iter().any(|(kept, kept_saved)| *kept == id && *kept_saved == saved));
+        if unchanged {
+            self.draft_edited_at = None;
+            return;
+        }
+        let edited_at = *self.draft_edited_at.get_or_insert_with(Instant::now);
+        if edited_at.elapsed() < AUTOSAVE_DELAY {
+            ctx.request_repaint_after(AUTOSAVE_DELAY - edited_at.elapsed());
+            return;
+        }
+        if let Some(id) = self.keep_draft(draft.id, &saved) {
+            draft.id = Some(id);
+        }
+        self.draft_edited_at = None;
+    }
+
+    fn reload_drafts(&self) {
+        if let Some(drafts) = self.store.as_ref().and_then(|store| store.drafts().ok()) {
+            *self.drafts.lock().unwrap() = drafts;
+        }
+    }
+
+    // Writes a draft to disk; returns the row it's under, or None when it couldn't be kept
+    fn keep_draft(&self, id: Option<i64>, saved: &submit::Saved) -> Option<i64> {
+        let store = self.store.as_ref()?;
+        match store.save_draft(id, saved) {
+            Ok(id) => {
+                self.reload_drafts();
+                Some(id)
+            }
+            Err(e) => {
+                tracing::warn!(error = %format!("{:#}", e), "Couldn't save a draft");
+                None
             }
         }
-        
-        // Default empty settings with dark mode enabled by default
-        Settings {
-            client_id: String::new(),
-            client_secret: String::new(),
-            username: String::new(),
-            password: String::new(),
-            dark_mode: true,  // Default to dark mode
-            font_size: 1.0,   // Default font size
+    }
+
+    fn forget_draft(&self, id: i64) {
+        if let Some(store) = &self.store {
+            if let Err(e) = store.delete_draft(id) {
+                tracing::warn!(error = %format!("{:#}", e), "Couldn't delete a draft");
+            }
         }
+        self.reload_drafts();
     }
 
-    fn save(&self) -> Result<()> {
-        let keyring = Entry::new("Rustle", "credentials")?;
-        let json = serde_json::to_string(self)?;
-        keyring.set_password(&json)?;
-        Ok(())
+    fn show_drafts(&mut self, ctx: &egui::Context) {
+        if !self.drafts_open {
+            return;
+        }
+        let drafts = self.drafts.lock().unwrap().clone();
+        let mut actions = Vec::new();
+        self.drafts_open = submit::show_drafts(ctx, &drafts, store::now(), &mut actions);
+        // Scheduled times count down in the list
+        if drafts.iter().any(|(_, saved)| saved.post_at.is_some()) {
+            ctx.request_repaint_after(Duration::from_secs(30));
+        }
+        for action in actions {
+            match action {
+                submit::DraftsAction::Open(id) => {
+                    let Some((_, saved)) = drafts.iter().find(|(kept, _)| *kept == id) else {
+                        continue;
+                    };
+                    let draft = submit::Draft::restore(Some(id), saved.clone());
+                    // Taken off the schedule while it's being edited
+                    self.keep_draft(Some(id), &draft.saved());
+                    self.open_draft(draft);
+                }
+                submit::DraftsAction::Delete(id) => {
+                    let mut post_draft = self.post_draft.lock().unwrap();
+                    if post_draft.as_ref().is_some_and(|draft| draft.id == Some(id)) {
+                        *post_draft = None;
+                    }
+                    drop(post_draft);
+                    self.forget_draft(id);
+                }
+            }
+        }
     }
-}
 
-impl RedditApp {
-    fn new() -> Self {
-        let settings = Settings::load();
-        let has_credentials = !settings.client_id.is_empty() 
-            && !settings.client_secret.is_empty()
-            && !settings.username.is_empty()
-            && !settings.password.is_empty();
+    // Submits scheduled posts once they're due, while the app is running. One that Reddit turns
+    // down stays in Drafts marked as failed; one that couldn't reach Reddit is tried again later.
+    fn send_scheduled_posts(&mut self, ctx: &egui::Context) {
+        if let Some(message) = self.scheduled_sent.lock().unwrap().drain(..).next_back() {
+            self.notice = Some((message, Instant::now() + NOTICE_DURATION));
+        }
+        if self.demo || !self.has_credentials || !self.settings.signed_in() || *self.authenticating.lock().unwrap() {
+            return;
+        }
+        let now = Instant::now();
+        if let Some(check_at) = self.schedule_check_at.filter(|check_at| now < *check_at) {
+            ctx.request_repaint_after(check_at - now);
+            return;
+        }
+        self.schedule_check_at = Some(now + SCHEDULE_CHECK);
+        let Some(store) = self.store.clone() else {
+            return;
+        };
+        let drafts = self.drafts.lock().unwrap().clone();
+        let due = submit::due(&drafts, &self.sending_scheduled.lock().unwrap(), store::now());
 
-        Self { 
-            posts: Arc::new(Mutex::new(Vec::new())),
-            loading: Arc::new(Mutex::new(has_credentials)),
-            error_message: Arc::new(Mutex::new(None)),
-            reddit_client: Arc::new(Mutex::new(None)),
-            after: Arc::new(Mutex::new(None)),
-            initial_load: Arc::new(Mutex::new(has_credentials)),
-            scroll_to_top: Arc::new(Mutex::new(true)),
-            show_settings: !has_credentials,
-            settings,
-            settings_modified: false,
-            has_credentials,
-            current_subreddit: Arc::new(Mutex::new("home".to_string())),
-            subreddits: Arc::new(Mutex::new(Vec::new())),
-            loading_subreddits: Arc::new(Mutex::new(false)),
-            last_scroll_pos: Arc::new(Mutex::new(0.0)),
-            is_loading_more: Arc::new(Mutex::new(false)),
+        for (id, saved) in drafts.into_iter().filter(|(id, _)| due.contains(id)) {
+            self.sending_scheduled.lock().unwrap().push(id);
+            let pending = self.hold_send(Sending::Post { id: Some(id), saved: saved.clone(), scheduled: true });
+            let reddit_client = self.reddit_client.clone();
+            let settings = self.settings.clone();
+            let store = store.clone();
+            let drafts = self.drafts.clone();
+            let sending = self.sending_scheduled.clone();
+            let scheduled_sent = self.scheduled_sent.clone();
+            let pending_sends = self.pending_sends.clone();
+            let egui_ctx = self.egui_ctx.clone();
+
+            thread::spawn(move || {
+                let rt = tokio::runtime::Runtime::new().unwrap();
+                let result = rt.block_on(async {
+                    if let Some(pending) = &pending {
+                        if !wait_to_send(&pending_sends, pending).await {
+                            return None;  // Undo already put it back in Drafts
+                        }
+                    }
+                    Some(async { ensure_client(&reddit_client, &settings).await?.submit_post(&saved).await }.await)
+                });
+                let Some(result) = result else {
+                    sending.lock().unwrap().retain(|sent| *sent != id);
+                    return;
+                };
+                let outcome = match result {
+                    Ok(url) => {
+                        tracing::info!(draft = id, "Sent a scheduled post");
+                        let _ = store.delete_draft(id);
+                        Some(Ok(url))
+                    }
+                    Err(e) if store::is_network_error(&e) => {
+                        tracing::warn!(draft = id, error = %format!("{:#}", e), "Scheduled post waits for the network");
+                        None
+                    }
+                    Err(e) => {
+                        tracing::warn!(draft = id, error = %format!("{:#}", e), "Scheduled post failed");
+                        let failure = format!("{:#}", e);
+                        let _ = store.save_draft(Some(id), &submit::Saved { failure: Some(failure.clone()), ..saved.clone() });
+                        Some(Err(failure))
+                    }
+                };
+                if let Ok(kept) = store.drafts() {
+                    *drafts.lock().unwrap() = kept;
+                }
+                sending.lock().unwrap().retain(|sent| *sent != id);
+                if let Some(outcome) = outcome {
+                    #[cfg(feature = "notifications")]
+                    match &outcome {
+                        Ok(_) => submit::notify("Scheduled post sent", &saved.summary()),
+                        Err(e) => submit::notify("Scheduled post failed", &format!("{}\n{}", saved.summary(), e)),
+                    }
+                    let message = match outcome {
+                        Ok(url) => format!("Posted {}: {}", saved.summary(), url),
+                        Err(e) => format!("Scheduled post {} failed: {}; it's kept in Drafts", saved.summary(), e),
+                    };
+                    scheduled_sent.lock().unwrap().push(message);
+                }
+                if let Some(ctx) = &egui_ctx {
+                    ctx.request_repaint();
+                }
+            });
         }
+        ctx.request_repaint_after(SCHEDULE_CHECK);
     }
-    
-    fn render_post(&self, ui: &mut egui::Ui, post: &Post) {
-        ui.add_space(10.0);
-        egui::Frame::group(ui.style())
-            .fill(if self.settings.dark_mode {
-                egui::Color32::from_rgb(20, 20, 20)
-            } else {
-                egui::Color32::from_rgb(240, 240, 240)
-            })
-            .outer_margin(0.0)  // Remove outer margin
-            .show(ui, |ui| {
-                // Use the full width
-                ui.set_min_width(ui.available_width());
-                
-                ui.horizontal(|ui| {
-                    // Find the resolution closest to our target size (100px)
-                    let target_height = 100.0;
-                    let image_url = post.preview.as_ref()
-                        .and_then(|preview| preview.images.first())
-                        .and_then(|image| {
-                            image.resolutions.iter()
-                                .min_by_key(|res| {
-                                    // Calculate distance from target height
-                                    ((res.height as f32 - target_height).abs() * 100.0) as i32
-                                })
-                                .or_else(|| image.resolutions.first())
-                                .or(Some(&image.source))
-                        })
-                        .map(|img| img.url.replace("&amp;", "&"))
-                        .unwrap_or_else(|| post.thumbnail.clone());
-
-                    if image_url.starts_with("http") {
-                        ui.add_space(5.0);
-                        let image = egui::widgets::Image::new(image_url)
-                            .fit_to_original_size(1.0)
-                            .max_size(egui::Vec2::new(100.0, 100.0));
-                        ui.add(image);
-                        ui.add_space(10.0);
+
+    fn submit_post(&self) {
+        let reddit_client = self.reddit_client.clone();
+        let settings = self.settings.clone();
+        let post_draft = self.post_draft.clone();
+        let pending_sends = self.pending_sends.clone();
+        let egui_ctx = self.egui_ctx.clone();
+        // Nothing in the window can be edited while it's submitting
+        let Some(draft) = self.post_draft.lock().unwrap().clone() else {
+            return;
+        };
+        let pending = self.hold_send(Sending::Post { id: draft.id, saved: draft.saved(), scheduled: false });
+
+        thread::spawn(move || {
+            let rt = tokio::runtime::Runtime::new().unwrap();
+            rt.block_on(async {
+                if let Some(pending) = &pending {
+                    if !wait_to_send(&pending_sends, pending).await {
+                        return;
                     }
+                }
+                let result = match ensure_client(&reddit_client, &settings).await {
+                    Ok(client) => client.submit_post(&draft.saved()).await,
+                    Err(e) => Err(e),
+                };
 
-                    ui.vertical(|ui| {
-                        // Make the vertical content take remaining width
-                        ui.set_min_width(ui.available_width());
-                        
-                        // Post title with link
-                        ui.add(
-                            egui::Hyperlink::from_label_and_url(
-                                egui::RichText::new(&post.title)
-                                    .size(16.0 * self.settings.font_size)
-                                    .strong(),
-                                &post.url
-                            )
-                        );
-                        
-                        // Post metadata
-                        ui.label(
-                            egui::RichText::new(format!("Posted by u/{} in r/{}", post.author, post.subreddit))
-                                .size(12.0 * self.settings.font_size)
-                                .weak()
-                        );
-                        
-                        ui.label(
-                            egui::RichText::new(format!("Score: {}", post.score))
-                                .size(12.0 * self.settings.font_size)
-                        );
+                let mut post_draft = post_draft.lock().unwrap();
+                let Some(open) = post_draft.as_mut().filter(|open| open.subreddit == draft.subreddit) else {
+                    return;
+                };
+                open.submitting = false;
+                match result {
+                    Ok(url) => open.posted = Some(url),
+                    Err(e) => open.error = Some(format!("{:#}", e)),
+                }
+                drop(post_draft);
+                if let Some(ctx) = &egui_ctx {
+                    ctx.request_repaint();
+                }
+            });
+        });
+    }
+
+    fn show_digest(&mut self, ctx: &egui::Context) {
+        let Some(viewer) = &mut self.digest_viewer else {
+            return;
+        };
+        let mut requests = Vec::new();
+        let subscriptions = self.subreddits.lock().unwrap().len();
+        let open = viewer.show(ctx, self.digest.lock().unwrap().as_ref(), self.digest_progress.lock().unwrap().as_ref(), subscriptions, &mut requests);
+        if !open {
+            self.digest_viewer = None;
+        }
+        for request in requests {
+            match request {
+                digest::Request::Generate(period) => self.generate_digest(period),
+                digest::Request::Cancel => {
+                    if let Some(progress) = self.digest_progress.lock().unwrap().as_mut() {
+                        progress.cancelled = true;
+                    }
+                }
+                digest::Request::Export(format) => match self.write_digest_export(format) {
+                    Ok(path) => self.notice = Some((format!("Exported the digest to {}", path.display()), Instant::now() + NOTICE_DURATION)),
+                    Err(e) => *self.error_message.lock().unwrap() = Some(RustleError::classify(&e).context("Failed to export digest")),
+                },
+            }
+        }
+    }
+
+    // Waits for the mouse to be let go, so the scale slider doesn't move under the pointer while
+    // dragging. Ctrl+plus/minus zooms egui directly, and that's kept as the new setting.
+    fn apply_ui_scale(&mut self, ctx: &egui::Context) {
+        if ctx.input(|i| i.pointer.any_down()) {
+            return;
+        }
+        if self.applied_scale != Some(self.settings.ui_scale) {
+            ctx.set_pixels_per_point(ctx.native_pixels_per_point().unwrap_or(1.0) * self.settings.ui_scale);
+            self.applied_scale = Some(self.settings.ui_scale);
+        } else if (ctx.zoom_factor() - self.settings.ui_scale).abs() > 0.001 {
+            self.settings.ui_scale = ctx.zoom_factor().clamp(0.5, 3.0);
+            self.settings_modified = true;
+        }
+    }
+
+    fn create_bug_report(&mut self) {
+        let written = exports_dir().and_then(|dir| {
+            let path = dir.join(bug_report::file_name(store::now()));
+            bug_report::write(&path, &self.error_log, &api_log::log().calls(), &logging::recent().entries(), store::now())?;
+            Ok(path)
+        });
+        match written {
+            Ok(path) => self.notice = Some((format!("Saved a bug report to {}", path.display()), Instant::now() + NOTICE_DURATION)),
+            Err(e) => *self.error_message.lock().unwrap() = Some(RustleError::classify(&e).context("Failed to create bug report")),
+        }
+    }
+
+    // Picks up theme files added to the themes folder since startup
+    fn import_themes(&mut self) {
+        match themes_dir() {
+            Ok(dir) => {
+                self.installed_themes = theme::load_dir(&dir);
+                let message = format!("Found {} themes in {}", self.installed_themes.len(), dir.display());
+                self.notice = Some((message, Instant::now() + NOTICE_DURATION));
+            }
+            Err(e) => *self.error_message.lock().unwrap() = Some(RustleError::classify(&e).context("Failed to import themes")),
+        }
+    }
+
+    fn export_theme(&mut self) {
+        match exports_dir().and_then(|dir| self.settings.theme.export(&dir)) {
+            Ok(path) => self.notice = Some((format!("Exported the theme to {}", path.display()), Instant::now() + NOTICE_DURATION)),
+            Err(e) => *self.error_message.lock().unwrap() = Some(RustleError::classify(&e).context("Failed to export theme")),
+        }
+    }
+
+    fn show_usage_dashboard(&mut self, ctx: &egui::Context) {
+        let Some(dashboard) = &mut self.usage_dashboard else {
+            return;
+        };
+        let mut requests = Vec::new();
+        if !dashboard.show(ctx, self.settings.track_usage, &mut requests) {
+            self.usage_dashboard = None;
+        }
+        for request in requests {
+            let Some(store) = self.store.clone() else {
+                return;
+            };
+            let range = match request {
+                usage::Request::Load(range) => {
+                    self.flush_usage();
+                    range
+                }
+                usage::Request::Purge => {
+                    self.usage.lock().unwrap().take();
+                    if let Err(e) = store.purge_usage() {
+                        *self.error_message.lock().unwrap() = Some(RustleError::classify(&e).context("Failed to delete usage data"));
+                        continue;
+                    }
+                    self.notice = Some(("Usage data deleted".to_string(), Instant::now() + NOTICE_DURATION));
+                    usage::Range::All
+                }
+            };
+            match store.usage(range.since(store::now())) {
+                Ok(stats) => {
+                    if let Some(dashboard) = &mut self.usage_dashboard {
+                        dashboard.set_stats(stats);
+                    }
+                }
+                Err(e) => *self.error_message.lock().unwrap() = Some(RustleError::classify(&e).context("Failed to load usage stats")),
+            }
+        }
+    }
+
+    fn write_digest_export(&self, format: digest::Format) -> Result<std::path::PathBuf> {
+        let digest = self.digest.lock().unwrap();
+        let digest = digest.as_ref().context("No digest to export")?;
+        let path = exports_dir()?.join(digest.file_name(format));
+        std::fs::write(&path, digest.render(format)).with_context(|| format!("Failed to write {}", path.display()))?;
+        Ok(path)
+    }
+
+    // Archives the chosen subreddits every few hours. The first run after a restart waits out
+    // whatever is left of the interval since the last one.
+    fn run_archiver(&mut self, ctx: &egui::Context) {
+        if self.settings.archive_subreddits.is_empty() || self.demo || !self.has_credentials || *self.authenticating.lock().unwrap() {
+            self.archive_at = None;
+            return;
+        }
+        let Some(store) = &self.store else {
+            return;
+        };
+        let interval = Duration::from_secs(u64::from(self.settings.archive_hours.max(1)) * 3600);
+        if self.archive_at.is_none() {
+            let since = store.last_archived().ok().flatten().map(|at| Duration::from_secs((store::now() - at).max(0) as u64));
+            self.archive_at = Some(Instant::now() + since.map_or(Duration::ZERO, |since| interval.saturating_sub(since)));
+        }
+        let now = Instant::now();
+        if let Some(archive_at) = self.archive_at.filter(|archive_at| now < *archive_at) {
+            ctx.request_repaint_after(archive_at - now);
+            return;
+        }
+        self.archive_at = Some(now + interval);
+        self.start_archive();
+    }
+
+    // One listing at a time, through the same client and rate limiter as the feed
+    fn start_archive(&self) {
+        let jobs: Vec<(String, archive::Listing)> = self.settings.archive_subreddits.iter()
+            .flat_map(|subreddit| archive::Listing::ALL.map(|listing| (subreddit.clone(), listing)))
+            .collect();
+        let Some(store) = self.store.clone() else {
+            return;
+        };
+        {
+            let mut status = self.archive_status.lock().unwrap();
+            if status.running || jobs.is_empty() {
+                return;
+            }
+            status.running = true;
+            status.cancelled = false;
+            status.done = 0;
+            status.total = jobs.len();
+        }
+
+        let reddit_client = self.reddit_client.clone();
+        let settings = self.settings.clone();
+        let archive_status = self.archive_status.clone();
+        let egui_ctx = self.egui_ctx.clone();
+
+        thread::spawn(move || {
+            let rt = tokio::runtime::Runtime::new().unwrap();
+            rt.block_on(async {
+                let repaint = || {
+                    if let Some(ctx) = &egui_ctx {
+                        ctx.request_repaint();
+                    }
+                };
+                let cancelled = || archive_status.lock().unwrap().cancelled;
+                let mut changes = Vec::new();
+                match ensure_client(&reddit_client, &settings).await {
+                    Ok(client) => {
+                        for (index, (subreddit, listing)) in jobs.iter().enumerate() {
+                            client.rate_limit.keep_reserve(archive::RESERVE, cancelled, |resumes_at| {
+                                archive_status.lock().unwrap().resumes_at = resumes_at;
+                                repaint();
+                            }).await;
+                            if cancelled() {
+                                break;
+                            }
+                            let posts = match listing {
+                                archive::Listing::Top => client.get_top_posts(subreddit, "day", archive::LIMIT as usize).await,
+                                archive::Listing::New => {
+                                    let view = FeedView { sort: listing.api_name().to_string(), ..FeedView::default() };
+                                    client.get_subreddit_posts(subreddit, Some(&view), None, archive::LIMIT).await.map(|(posts, _)| posts)
+                                }
+                            };
+                            match posts.and_then(|posts| store.archive(subreddit, *listing, &posts)) {
+                                Ok(change) => changes.push(change),
+                                Err(e) if matches!(RustleError::classify(&e), RustleError::Auth { .. }) => {
+                                    tracing::warn!(error = %format!("{:#}", e), "Archive run stopped");
+                                    break;
+                                }
+                                // Private, banned or quarantined subreddits are tried again next run
+                                Err(e) => tracing::warn!(subreddit, listing = listing.api_name(), error = %format!("{:#}", e), "Couldn't archive a listing"),
+                            }
+                            archive_status.lock().unwrap().done = index + 1;
+                            repaint();
+                            tokio::time::sleep(archive::PAUSE).await;
+                        }
+                    }
+                    Err(e) => tracing::warn!(error = %format!("{:#}", e), "Archive run couldn't sign in"),
+                }
+                tracing::info!(listings = changes.len(), "Archived subreddits");
+                let mut status = archive_status.lock().unwrap();
+                status.running = false;
+                status.finished_at = Some(store::now());
+                status.archived = store.archive_size().unwrap_or(status.archived);
+                if !changes.is_empty() {
+                    status.changes = changes;
+                }
+                drop(status);
+                repaint();
+            });
+        });
+    }
+
+    fn render_archive_settings(&mut self, ui: &mut egui::Ui) {
+        ui.vertical(|ui| {
+            ui.horizontal_wrapped(|ui| {
+                let mut remove = None;
+                for name in &self.settings.archive_subreddits {
+                    if ui.button(format!("r/{} ✖", name)).on_hover_text("Stop archiving").clicked() {
+                        remove = Some(name.clone());
+                    }
+                }
+                if let Some(name) = remove {
+                    self.settings.archive_subreddits.retain(|archived| *archived != name);
+                    self.settings_modified = true;
+                }
+                let input = ui.add(egui::TextEdit::singleline(&mut self.archive_input).hint_text("Subreddit").desired_width(120.0));
+                let name = self.archive_input.trim().trim_start_matches('/').trim_start_matches("r/").to_string();
+                let entered = input.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                if (ui.button("Add").clicked() || entered) && !name.is_empty() {
+                    if !self.settings.archive_subreddits.iter().any(|archived| archived.eq_ignore_ascii_case(&name)) {
+                        self.settings.archive_subreddits.push(name);
+                        self.settings_modified = true;
+                    }
+                    self.archive_input.clear();
+                }
+            });
+            if self.settings.archive_subreddits.is_empty() {
+                ui.label(egui::RichText::new("Off. Subreddits added here have their top and new posts saved every few hours, for offline reading and a record of your own.").weak());
+                return;
+            }
+            ui.horizontal(|ui| {
+                ui.label("Every");
+                for hours in archive::INTERVALS {
+                    if ui.selectable_value(&mut self.settings.archive_hours, hours, format!("{} h", hours)).changed() {
+                        self.settings_modified = true;
+                        self.archive_at = None;
+                    }
+                }
+                let status = self.archive_status.lock().unwrap();
+                if status.running {
+                    ui.spinner();
+                    let waiting = status.resumes_at.map_or(String::new(), |at| {
+                        format!(", waiting {}s for the rate limit", at.saturating_duration_since(Instant::now()).as_secs())
                     });
-                });
+                    ui.label(egui::RichText::new(format!("Archiving {}/{}{}", status.done, status.total, waiting)).weak());
+                } else {
+                    let last = status.finished_at.map_or("not run yet".to_string(), |at| format!("last run {}", relative_time(at as f64, store::now())));
+                    ui.label(egui::RichText::new(format!("{} posts, {}", status.archived, last)).weak());
+                }
+                let running = status.running;
+                drop(status);
+                if running {
+                    if ui.button("Stop").on_hover_text("Ends the run after the listing being fetched").clicked() {
+                        self.archive_status.lock().unwrap().cancelled = true;
+                    }
+                } else if ui.add_enabled(!self.demo, egui::Button::new("Run now")).clicked() {
+                    self.start_archive();
+                }
+                if ui.button("Changes").on_hover_text("What turned up and dropped off in the last run").clicked() {
+                    self.archive_open = true;
+                }
+            });
+        });
+    }
+
+    // One subscription at a time, through the same client and rate limiter as the feed
+    fn generate_digest(&self, period: digest::Period) {
+        let subreddits = self.subreddits.lock().unwrap().clone();
+        {
+            let mut progress = self.digest_progress.lock().unwrap();
+            if progress.is_some() || subreddits.is_empty() {
+                return;
+            }
+            *progress = Some(digest::Progress { total: subreddits.len(), ..Default::default() });
+        }
+
+        let reddit_client = self.reddit_client.clone();
+        let settings = self.settings.clone();
+        let digest = self.digest.clone();
+        let digest_progress = self.digest_progress.clone();
+        let error_message = self.error_message.clone();
+        let egui_ctx = self.egui_ctx.clone();
+
+        thread::spawn(move || {
+            let rt = tokio::runtime::Runtime::new().unwrap();
+            rt.block_on(async {
+                let repaint = || {
+                    if let Some(ctx) = &egui_ctx {
+                        ctx.request_repaint();
+                    }
+                };
+                let cancelled = || digest_progress.lock().unwrap().as_ref().is_none_or(|progress| progress.cancelled);
+                let client = match ensure_client(&reddit_client, &settings).await {
+                    Ok(client) => client,
+                    Err(e) => {
+                        *error_message.lock().unwrap() = Some(RustleError::classify(&e).context("Failed to make a digest"));
+                        *digest_progress.lock().unwrap() = None;
+                        repaint();
+                        return;
+                    }
+                };
+                let filters = Filters::new(&settings.filters);
+                let mut fetched = Vec::new();
+                let mut skipped = Vec::new();
+                for (index, subreddit) in subreddits.iter().enumerate() {
+                    client.rate_limit.keep_reserve(digest::RESERVE, cancelled, |resumes_at| {
+                        if let Some(progress) = digest_progress.lock().unwrap().as_mut() {
+                            progress.resumes_at = resumes_at;
+                        }
+                        repaint();
+                    }).await;
+                    if cancelled() {
+                        break;
+                    }
+                    // A few spares, since stickied and filtered posts are dropped
+                    match client.get_top_posts(subreddit, period.api_name(), digest::PER_SUBREDDIT * 2).await {
+                        Ok(posts) => {
+                            let (posts, _) = filters.apply(posts);
+                            let posts = posts.into_iter().filter(|post| settings.show_nsfw || !post.over_18).collect();
+                            fetched.push((subreddit.clone(), posts));
+                        }
+                        Err(e) if matches!(RustleError::classify(&e), RustleError::Auth { .. }) => {
+                            *error_message.lock().unwrap() = Some(RustleError::classify(&e).context("Failed to make a digest"));
+                            break;
+                        }
+                        // Private, banned or quarantined subreddits just get left out
+                        Err(_) => skipped.push(subreddit.clone()),
+                    }
+                    if let Some(progress) = digest_progress.lock().unwrap().as_mut() {
+                        progress.done = index + 1;
+                    }
+                    repaint();
+                }
+                let finished = digest_progress.lock().unwrap().take()
+                    .is_some_and(|progress| !progress.cancelled && progress.done == progress.total);
+                if finished {
+                    *digest.lock().unwrap() = Some(digest::Digest::new(period, store::now(), fetched, skipped));
+                }
+                repaint();
             });
+        });
     }
 
-    fn load_more_posts(&self) {
-        if *self.loading.lock().unwrap() {
+    fn prefetch_next_page(&self) {
+        if *self.prefetching.lock().unwrap() || self.demo {
             return;
         }
 
-        *self.loading.lock().unwrap() = true;
         let after_token = self.after.lock().unwrap().clone();
         let current_subreddit = self.current_subreddit.lock().unwrap().clone();
+        let current_view = self.current_view.lock().unwrap().clone();
+
+        // Keep a buffered page that still lines up with the feed, discard a stale one
+        {
+            let mut prefetched = self.prefetched.lock().unwrap();
+            if prefetched.as_ref().is_some_and(|page| page.subreddit == current_subreddit && page.view == current_view && page.after == after_token) {
+                return;
+            }
+            *prefetched = None;
+        }
+
+        // Nothing left to prefetch once Reddit stops handing out cursors
+        if after_token.is_none() {
+            return;
+        }
+
+        *self.prefetching.lock().unwrap() = true;
+        let load = self.watchdog.begin("prefetch");
 
-        let posts = self.posts.clone();
-        let loading = self.loading.clone();
-        let error_message = self.error_message.clone();
         let reddit_client = self.reddit_client.clone();
-        let after = self.after.clone();
-        let initial_load = self.initial_load.clone();
         let settings = self.settings.clone();
+        let prefetched = self.prefetched.clone();
+        let prefetching = self.prefetching.clone();
+        let read_posts = self.read_posts.clone();
+        let store = self.store.clone();
+        let subreddit_now = self.current_subreddit.clone();
+        let view_now = self.current_view.clone();
+        let after_now = self.after.clone();
+        let egui_ctx = self.egui_ctx.clone();
 
         thread::spawn(move || {
             let rt = tokio::runtime::Runtime::new().unwrap();
             rt.block_on(async {
-                let client = {
-                    let mut client_guard = reddit_client.lock().unwrap();
-                    if let Some(client) = client_guard.as_ref() {
-                        client.clone()
-                    } else {
-                        let mut client = match RedditClient::new() {
-                            Ok(client) => client,
-                            Err(e) => {
-                                *error_message.lock().unwrap() = Some(format!("Failed to create client: {}", e));
-                                *loading.lock().unwrap() = false;
-                                *initial_load.lock().unwrap() = false;
-                                return;
+                // Prefetch failures are silent; the regular load path will surface any real error
+                if let Ok(client) = ensure_client(&reddit_client, &settings).await {
+                    if let Ok((fetched_posts, next_after, hidden)) = fetch_filtered(&client, &settings, &current_subreddit, current_view.as_ref(), after_token.as_deref()).await {
+                        // Drop the page if the user switched feeds or paged past it in the meantime
+                        let still_current = load.current()
+                            && *subreddit_now.lock().unwrap() == current_subreddit
+                            && *view_now.lock().unwrap() == current_view
+                            && *after_now.lock().unwrap() == after_token;
+                        if still_current {
+                            if let Some(ctx) = &egui_ctx {
+                                prefetch_images(ctx, &fetched_posts, &settings);
                             }
-                        };
-                        
-                        if let Err(e) = client.authenticate(&settings.client_id, &settings.client_secret, 
-                            &settings.username, &settings.password).await {
-                            *error_message.lock().unwrap() = Some(format!("Authentication error: {}", e));
-                            *loading.lock().unwrap() = false;
-                            *initial_load.lock().unwrap() = false;
-                            return;
-                        }
-                        
-                        *client_guard = Some(client.clone());
-                        client
-                    }
-                };
-
-                let result = if current_subreddit == "home" {
-                    client.get_home_feed(after_token.as_deref()).await
-                } else {
-                    client.get_subreddit_posts(&current_subreddit, after_token.as_deref()).await
-                };
-
-                match result {
-                    Ok((fetched_posts, new_after)) => {
-                        let mut posts_lock = posts.lock().unwrap();
-                        if after_token.is_none() && posts_lock.is_empty() {
-                            // Only replace posts if we're starting fresh with no posts
-                            *posts_lock = fetched_posts;
-                        } else {
-                            // Otherwise always append
-                            posts_lock.extend(fetched_posts);
+                            merge_visited(&settings, &read_posts, &store, &fetched_posts);
+                            *prefetched.lock().unwrap() = Some(PrefetchedPage {
+                                subreddit: current_subreddit,
+                                view: current_view,
+                                after: after_token,
+                                posts: fetched_posts,
+                                next_after,
+                                hidden,
+                            });
                         }
-                        *after.lock().unwrap() = new_after;
-                        *loading.lock().unwrap() = false;
-                        *initial_load.lock().unwrap() = false;
-                    }
-                    Err(e) => {
-                        *error_message.lock().unwrap() = Some(format!("Error fetching posts: {}", e));
-                        *loading.lock().unwrap() = false;
-                        *initial_load.lock().unwrap() = false;
                     }
                 }
+                load.finish(&prefetching);
             });
         });
     }
 
     fn authenticate_and_load(&self) {
+        *self.authenticating.lock().unwrap() = true;
+        // Claimed up front so the nav bar doesn't start its own fetch while posts load
+        *self.loading_subreddits.lock().unwrap() = true;
+        let sign_in = self.watchdog.begin("sign-in");
+        let posts_load = self.watchdog.begin("posts");
+        let subreddits_load = self.watchdog.begin("subreddits");
+
         let settings = self.settings.clone();
         let posts = self.posts.clone();
         let loading = self.loading.clone();
         let error_message = self.error_message.clone();
+        let scroll_to_top = self.scroll_to_top.clone();
         let reddit_client = self.reddit_client.clone();
         let initial_load = self.initial_load.clone();
         let subreddits = self.subreddits.clone();
         let loading_subreddits = self.loading_subreddits.clone();
+        let subreddits_fetched = self.subreddits_fetched.clone();
+        let current_subreddit = self.current_subreddit.clone();
+        let current_view = self.current_view.clone();
+        let after = self.after.clone();
+        let authenticating = self.authenticating.clone();
+        let account_prefs = self.account_prefs.clone();
+        let blocked_users = self.blocked_users.clone();
+        let moderated = self.moderated.clone();
+        let read_posts = self.read_posts.clone();
+        let store = self.store.clone();
+        let hidden_by_filters = self.hidden_by_filters.clone();
+        let egui_ctx = self.egui_ctx.clone();
+        let keep_feed = std::mem::take(&mut *self.keep_cached_feed.lock().unwrap());
 
         thread::spawn(move || {
             let rt = tokio::runtime::Runtime::new().unwrap();
             rt.block_on(async {
-                let mut client = match RedditClient::new() {
+                // Always start from a fresh client since the credentials may have changed
+                *reddit_client.lock().unwrap() = None;
+                let client = match ensure_client(&reddit_client, &settings).await {
                     Ok(client) => client,
+                    Err(_) if !sign_in.current() => {
+                        posts_load.finish(&loading);
+                        subreddits_load.finish(&loading_subreddits);
+                        return;
+                    }
+                    Err(e) if store::is_network_error(&e) => {
+                        // No network: show whatever was synced last and let the retry timer reconnect
+                        let subreddit = current_subreddit.lock().unwrap().clone();
+                        let view = current_view.lock().unwrap().clone();
+                        let restored = load_offline_feed(&store, &subreddit, view.as_ref(), &posts, &after);
+                        if !restored && posts.lock().unwrap().is_empty() {
+                            *error_message.lock().unwrap() = Some(RustleError::classify(&e));
+                        } else if let Some(store) = store.as_ref().filter(|_| !restored) {
+                            // Only the session cache had this feed, so its age is unknown
+                            store.mark_offline(store::now());
+                        }
+                        if subreddits.lock().unwrap().is_empty() {
+                            load_offline_subreddits(&store, &subreddits);
+                        }
+                        if posts_load.current() {
+                            *loading.lock().unwrap() = false;
+                            *initial_load.lock().unwrap() = false;
+                        }
+                        sign_in.finish(&authenticating);
+                        subreddits_load.finish(&loading_subreddits);
+                        if let Some(ctx) = &egui_ctx {
+                            ctx.request_repaint();
+                        }
+                        return;
+                    }
                     Err(e) => {
-                        *error_message.lock().unwrap() = Some(format!("Failed to create client: {}", e));
-                        *loading.lock().unwrap() = false;
-                        *initial_load.lock().unwrap() = false;
+                        *error_message.lock().unwrap() = Some(RustleError::classify(&e));
+                        if posts_load.current() {
+                            *loading.lock().unwrap() = false;
+                            *initial_load.lock().unwrap() = false;
+                        }
+                        sign_in.finish(&authenticating);
+                        subreddits_load.finish(&loading_subreddits);
                         return;
                     }
                 };
-                
-                // Authenticate
-                if let Err(e) = client.authenticate(&settings.client_id, &settings.client_secret, 
-                    &settings.username, &settings.password).await {
-                    *error_message.lock().unwrap() = Some(format!("Authentication error: {}", e));
-                    *loading.lock().unwrap() = false;
-                    *initial_load.lock().unwrap() = false;
+                sign_in.finish(&authenticating);
+
+                // Preferences are a nicety; without the account scope we just keep the local ones
+                if settings.signed_in() && !settings.local_prefs_only {
+                    if let Ok(prefs) = client.get_account_prefs().await {
+                        *account_prefs.lock().unwrap() = Some(prefs);
+                    }
+                }
+                if settings.signed_in() {
+                    if let Ok(blocked) = client.get_blocked_users().await {
+                        *blocked_users.lock().unwrap() = blocked.iter().map(|name| name.to_lowercase()).collect();
+                    }
+                    if let Ok(subreddits) = client.get_moderated_subreddits().await {
+                        *moderated.lock().unwrap() = subreddits;
+                    }
+                }
+
+                // Fetch posts for whichever feed is showing (possibly restored from cache), unless
+                // the startup refresh setting keeps the restored one until the reader refreshes
+                if keep_feed {
+                    posts_load.finish(&loading);
+                } else {
+                    let subreddit = current_subreddit.lock().unwrap().clone();
+                    let view = current_view.lock().unwrap().clone();
+                    let result = fetch_filtered(&client, &settings, &subreddit, view.as_ref(), None).await;
+
+                    match result {
+                        // The watchdog gave up on this load, and a retry owns the feed now
+                        _ if !posts_load.current() => {}
+                        Ok((fetched_posts, new_after, hidden)) => {
+                            if let Some(ctx) = &egui_ctx {
+                                prefetch_images(ctx, &fetched_posts, &settings);
+                            }
+                            merge_visited(&settings, &read_posts, &store, &fetched_posts);
+                            save_feed(&store, &subreddit, view.as_ref(), &fetched_posts, new_after.as_deref());
+                            *error_message.lock().unwrap() = None;
+                            *posts.lock().unwrap() = fetched_posts;
+                            *hidden_by_filters.lock().unwrap() = vec![hidden];
+                            *after.lock().unwrap() = new_after;
+                            *loading.lock().unwrap() = false;
+                            *initial_load.lock().unwrap() = false;
+                            if let Some(ctx) = &egui_ctx {
+                                ctx.request_repaint();
+                            }
+                        }
+                        Err(e) => {
+                            *error_message.lock().unwrap() = Some(RustleError::classify(&e).context("Error fetching posts"));
+                            *scroll_to_top.lock().unwrap() = true;
+                            *loading.lock().unwrap() = false;
+                            *initial_load.lock().unwrap() = false;
+                        }
+                    }
+                }
+
+                // Then the subscription list, which can take many pages on large accounts
+                if settings.signed_in() {
+                    fetch_subreddit_list(&client, &subreddits_load, &subreddits, &subreddits_fetched, &error_message, &store, &egui_ctx).await;
+                    subreddits_load.finish(&loading_subreddits);
+                } else if subreddits_load.current() {
+                    *subreddits.lock().unwrap() = settings.account.subreddit_picks.clone();
+                    *loading_subreddits.lock().unwrap() = false;
+                }
+            });
+        });
+    }
+
+    // Reconnect in the background while offline; a successful sign-in replaces the stored content
+    fn retry_sync(&mut self, ctx: &egui::Context) {
+        if !self.has_credentials || *self.authenticating.lock().unwrap() || *self.loading.lock().unwrap() {
+            return;
+        }
+        let retry_at = *self.sync_retry_at.get_or_insert_with(|| Instant::now() + SYNC_RETRY);
+        let now = Instant::now();
+        if now < retry_at {
+            ctx.request_repaint_after(retry_at - now);
+            return;
+        }
+        self.sync_retry_at = None;
+        self.authenticate_and_load();
+    }
+
+    // A feed kept from the last session because it was fresh enough is fetched once it no longer
+    // is, checked every minute and whenever the window comes back into focus
+    fn refresh_stale_feed(&mut self, ctx: &egui::Context) {
+        let Some(synced_at) = *self.kept_feed_synced_at.lock().unwrap() else {
+            self.stale_check_at = None;
+            return;
+        };
+        let refocused = ctx.input(|i| i.events.iter().any(|event| matches!(event, egui::Event::WindowFocused(true))));
+        let check_at = *self.stale_check_at.get_or_insert_with(|| Instant::now() + STALE_CHECK);
+        let now = Instant::now();
+        if now < check_at && !refocused {
+            ctx.request_repaint_after(check_at - now);
+            return;
+        }
+        self.stale_check_at = Some(now + STALE_CHECK);
+        ctx.request_repaint_after(STALE_CHECK);
+        if *self.loading.lock().unwrap() || *self.authenticating.lock().unwrap() {
+            return;
+        }
+        if self.settings.startup_refresh.wants_refresh(store::now() - synced_at) {
+            self.refresh_posts();
+        }
+    }
+
+    // Look for new posts at the head of the feed every few minutes. They wait in a banner rather
+    // than going straight into the feed, which would shift everything under the reader.
+    fn check_for_new_posts(&mut self, ctx: &egui::Context) {
+        let minutes = self.settings.auto_refresh_minutes;
+        if minutes == 0 || self.demo || !self.has_credentials || *self.loading.lock().unwrap() {
+            self.refresh_at = None;
+            return;
+        }
+        let interval = Duration::from_secs(minutes as u64 * 60);
+        let refresh_at = *self.refresh_at.get_or_insert_with(|| Instant::now() + interval);
+        let now = Instant::now();
+        if now < refresh_at {
+            ctx.request_repaint_after(refresh_at - now);
+            return;
+        }
+        self.refresh_at = None;
+
+        let subreddit = self.current_subreddit.lock().unwrap().clone();
+        let view = self.current_view.lock().unwrap().clone();
+        let reddit_client = self.reddit_client.clone();
+        let settings = self.settings.clone();
+        let current_subreddit = self.current_subreddit.clone();
+        let current_view = self.current_view.clone();
+        let posts = self.posts.clone();
+        let new_posts = self.new_posts.clone();
+        let egui_ctx = self.egui_ctx.clone();
+
+        thread::spawn(move || {
+            let rt = tokio::runtime::Runtime::new().unwrap();
+            rt.block_on(async {
+                let result = match ensure_client(&reddit_client, &settings).await {
+                    Ok(client) => fetch_filtered(&client, &settings, &subreddit, view.as_ref(), None).await,
+                    Err(e) => Err(e),
+                };
+                // A failed check just waits for the next one
+                let Ok((fetched, _, _)) = result else {
+                    return;
+                };
+                if *current_subreddit.lock().unwrap() != subreddit || *current_view.lock().unwrap() != view {
                     return;
                 }
-                
-                *reddit_client.lock().unwrap() = Some(client.clone());
-                
-                // Load subreddits first
-                *loading_subreddits.lock().unwrap() = true;
-                match client.get_subscribed_subreddits().await {
-                    Ok(fetched_subreddits) => {
-                        *subreddits.lock().unwrap() = fetched_subreddits;
-                        *loading_subreddits.lock().unwrap() = false;
+                let unseen = unseen_posts(fetched, &posts.lock().unwrap());
+                if unseen.is_empty() {
+                    return;
+                }
+                if let Some(ctx) = &egui_ctx {
+                    prefetch_images(ctx, &unseen, &settings);
+                    ctx.request_repaint();
+                }
+                *new_posts.lock().unwrap() = Some(NewPosts { subreddit, view, posts: unseen });
+            });
+        });
+    }
+
+    // Poll the inbox every few minutes for the header's unread count. Built with the notifications
+    // feature, anything new since the last poll also gets a desktop notification.
+    fn check_inbox(&mut self, ctx: &egui::Context) {
+        let minutes = self.settings.inbox_check_minutes;
+        if minutes == 0 || self.demo || !self.has_credentials || !self.settings.signed_in() || *self.authenticating.lock().unwrap() {
+            self.inbox_check_at = None;
+            return;
+        }
+        // The first poll goes out straight away so the count is there from the start
+        let check_at = *self.inbox_check_at.get_or_insert_with(Instant::now);
+        let now = Instant::now();
+        if now < check_at {
+            ctx.request_repaint_after(check_at - now);
+            return;
+        }
+        self.inbox_check_at = Some(now + Duration::from_secs(minutes as u64 * 60));
+
+        let reddit_client = self.reddit_client.clone();
+        let settings = self.settings.clone();
+        let inbox = self.inbox.clone();
+        let egui_ctx = self.egui_ctx.clone();
+
+        thread::spawn(move || {
+            let rt = tokio::runtime::Runtime::new().unwrap();
+            let result = rt.block_on(async {
+                ensure_client(&reddit_client, &settings).await?.get_unread().await
+            });
+            // A failed poll just waits for the next one
+            let unread = match result {
+                Ok(unread) => unread,
+                Err(e) => {
+                    tracing::warn!(error = %format!("{:#}", e), "Inbox check failed");
+                    return;
+                }
+            };
+            tracing::debug!(unread = unread.len(), "Checked the inbox");
+            let fresh = inbox.lock().unwrap().update(unread);
+            #[cfg(feature = "notifications")]
+            inbox::notify(&fresh);
+            #[cfg(not(feature = "notifications"))]
+            let _ = fresh;
+            if let Some(ctx) = &egui_ctx {
+                ctx.request_repaint();
+            }
+        });
+    }
+
+    // A flag still set after watchdog::TIMEOUT without progress means its worker died or its
+    // request hung; clear it so the screen isn't stuck on a spinner, and offer a retry
+    fn watch_loads(&mut self, ctx: &egui::Context) {
+        let now = Instant::now();
+        // Requests held back by the rate limiter are waiting their turn, not hung
+        let throttled = self.reddit_client.lock().unwrap().as_ref().is_some_and(|client| client.rate_limit.holding(now));
+        // The prefetch has no description: nobody is waiting on it, and the next scroll asks again
+        let flags = [
+            ("posts", Some("Loading posts"), self.loading.clone()),
+            ("subreddits", Some("Loading your subreddits"), self.loading_subreddits.clone()),
+            ("sign-in", Some("Signing in"), self.authenticating.clone()),
+            ("comments", Some("Loading comments"), self.loading_comments.clone()),
+            ("subscription", Some("Joining or leaving the subreddit"), self.updating_subscription.clone()),
+            ("prefetch", None, self.prefetching.clone()),
+        ];
+        for (name, description, flag) in flags {
+            let busy = *flag.lock().unwrap();
+            if throttled {
+                self.watchdog.progress(name, now);
+            }
+            if !self.watchdog.stalled(name, busy, now) {
+                continue;
+            }
+            tracing::error!(load = name, timeout_secs = watchdog::TIMEOUT.as_secs(), "Load stalled; resetting it");
+            *flag.lock().unwrap() = false;
+            if name == "posts" {
+                *self.initial_load.lock().unwrap() = false;
+            }
+            if let Some(description) = description {
+                *self.error_message.lock().unwrap() = Some(RustleError::Stalled(format!("{} took too long and was stopped", description)));
+            }
+        }
+        if let Some(deadline) = self.watchdog.deadline() {
+            ctx.request_repaint_after(deadline.saturating_duration_since(now));
+        }
+    }
+
+    // Fetch the signed-in account for the header, then again every few minutes for fresh karma
+    // and unread counts
+    fn load_me(&mut self, ctx: &egui::Context) {
+        if self.demo || !self.has_credentials || !self.settings.signed_in() || *self.authenticating.lock().unwrap() {
+            self.me_check_at = None;
+            return;
+        }
+        let check_at = *self.me_check_at.get_or_insert_with(Instant::now);
+        let now = Instant::now();
+        if now < check_at {
+            ctx.request_repaint_after(check_at - now);
+            return;
+        }
+        self.me_check_at = Some(now + ACCOUNT_REFRESH);
+
+        let reddit_client = self.reddit_client.clone();
+        let settings = self.settings.clone();
+        let me = self.me.clone();
+        let egui_ctx = self.egui_ctx.clone();
+
+        thread::spawn(move || {
+            let rt = tokio::runtime::Runtime::new().unwrap();
+            let result = rt.block_on(async {
+                ensure_client(&reddit_client, &settings).await?.get_me().await
+            });
+            // The header keeps what it had until the next refresh
+            match result {
+                Ok(fetched) => *me.lock().unwrap() = Some(fetched),
+                Err(e) => {
+                    tracing::warn!(error = %format!("{:#}", e), "Account refresh failed");
+                    return;
+                }
+            }
+            if let Some(ctx) = &egui_ctx {
+                ctx.request_repaint();
+            }
+        });
+    }
+
+    // Avatar, name and karma of the signed-in account, linking to its profile
+    fn render_account_header(&mut self, ui: &mut egui::Ui) {
+        let username = self.settings.account.username.clone();
+        // A refresh for the previous account can land after a switch
+        let me = self.me.lock().unwrap().clone().filter(|me| me.name.eq_ignore_ascii_case(&username));
+        ui.add_space(8.0);
+        if let Some(avatar) = me.as_ref().and_then(Me::avatar_url) {
+            ui.add(egui::Image::new(avatar).fit_to_exact_size(egui::vec2(20.0, 20.0)).rounding(10.0));
+        }
+        let link = ui.link(egui::RichText::new(format!("u/{}", username)).size(12.0));
+        if link.on_hover_text("Your profile and trophies").clicked() {
+            self.open_profile(username);
+        }
+        if let Some(me) = &me {
+            ui.label(egui::RichText::new(me.karma()).weak().size(12.0))
+                .on_hover_text(format!("{} post karma · {} comment karma", me.link_karma, me.comment_karma));
+        }
+    }
+
+    // The tray icon is up while inbox checks are on, carrying the unread count
+    #[cfg(feature = "notifications")]
+    fn update_tray(&mut self, ctx: &egui::Context) {
+        if self.settings.inbox_check_minutes == 0 || !self.settings.signed_in() {
+            self.tray = None;
+            return;
+        }
+        // Desktops without a tray are only tried once; notifications still work there
+        let tray = self.tray.get_or_insert_with(|| inbox::Tray::new(ctx).ok());
+        if let Some(tray) = tray {
+            tray.set_unread(self.inbox.lock().unwrap().unread.len());
+        }
+    }
+
+    fn render_new_posts_banner(&self, ui: &mut egui::Ui) {
+        let current = (self.current_subreddit.lock().unwrap().clone(), self.current_view.lock().unwrap().clone());
+        let mut new_posts = self.new_posts.lock().unwrap();
+        // Left over from a feed that's no longer showing
+        if new_posts.as_ref().is_some_and(|new| (&new.subreddit, &new.view) != (&current.0, &current.1)) {
+            *new_posts = None;
+        }
+        let Some(count) = new_posts.as_ref().map(|new| new.posts.len()) else {
+            return;
+        };
+        let label = format!("⬆ {} new post{}", count, if count == 1 { "" } else { "s" });
+        let clicked = ui.vertical_centered(|ui| {
+            ui.add(egui::Button::new(label).rounding(12.0)).on_hover_text("Add them to the top of the feed").clicked()
+        }).inner;
+        if clicked {
+            if let Some(new) = new_posts.take() {
+                let mut posts = self.posts.lock().unwrap();
+                let unseen = unseen_posts(new.posts, &posts);
+                posts.splice(0..0, unseen);
+                *self.scroll_to_top.lock().unwrap() = true;
+            }
+        }
+    }
+
+    // How many posts the filters have kept out of the feed, per page on hover
+    fn render_filter_count(&self, ui: &mut egui::Ui) {
+        let pages = self.hidden_by_filters.lock().unwrap().clone();
+        let total: usize = pages.iter().sum();
+        if total == 0 {
+            return;
+        }
+        let per_page: Vec<String> = pages.iter().enumerate()
+            .map(|(page, hidden)| format!("Page {}: {}", page + 1, hidden))
+            .collect();
+        ui.label(egui::RichText::new(format!("🚫 Filters hid {} post{}", total, if total == 1 { "" } else { "s" })).weak())
+            .on_hover_text(per_page.join("\n"));
+    }
+
+    // Full-page explanation when Reddit stops accepting the credentials, with ways out
+    fn render_auth_failure(&mut self, ui: &mut egui::Ui, failure: AuthFailure, error: &str) {
+        let (title, explanation) = explain_auth_failure(failure, self.settings.account.auth_method);
+        ui.vertical_centered(|ui| {
+            ui.add_space(40.0);
+            ui.heading(format!("🔒 {}", title));
+            ui.add_space(10.0);
+            ui.label(explanation);
+            ui.add_space(5.0);
+            ui.label(egui::RichText::new(error).size(12.0).weak());
+            ui.add_space(15.0);
+            ui.horizontal(|ui| {
+                // Center the row of buttons under the text
+                ui.add_space((ui.available_width() - 300.0).max(0.0) / 2.0);
+                if ui.button("Retry").clicked() {
+                    *self.error_message.lock().unwrap() = None;
+                    *self.loading.lock().unwrap() = true;
+                    *self.initial_load.lock().unwrap() = true;
+                    self.authenticate_and_load();
+                }
+                if ui.button("Fix credentials").on_hover_text("Open settings to update how Rustle signs in").clicked() {
+                    *self.error_message.lock().unwrap() = None;
+                    self.show_settings = true;
+                }
+                if ui.button("Switch account").clicked() {
+                    self.onboarding = Some(onboarding::Wizard::new(self.settings.clone(), true));
+                }
+            });
+        });
+    }
+
+    // In place of the feed when Reddit won't list a subreddit; quarantined ones can be opted in to
+    fn render_restricted(&mut self, ui: &mut egui::Ui, subreddit: &str, restriction: &subreddit::Restriction) {
+        let font_size = self.settings.font_size;
+        ui.vertical_centered(|ui| {
+            ui.add_space(40.0);
+            ui.heading(format!("🔒 {}", restriction.describe(subreddit)));
+            ui.add_space(10.0);
+            ui.label(restriction.explain());
+            if let subreddit::Restriction::Quarantined { message } = restriction {
+                if !message.trim().is_empty() {
+                    ui.add_space(10.0);
+                    egui::Frame::group(ui.style()).show(ui, |ui| {
+                        ui.set_max_width(560.0);
+                        markdown::show(ui, message, 13.0 * font_size);
+                    });
+                }
+            }
+            ui.add_space(15.0);
+            ui.horizontal(|ui| {
+                ui.add_space((ui.available_width() - 300.0).max(0.0) / 2.0);
+                if let subreddit::Restriction::Quarantined { .. } = restriction {
+                    let opting_in = *self.opting_in.lock().unwrap();
+                    let signed_in = self.settings.signed_in();
+                    let view = ui.add_enabled(signed_in && !opting_in, egui::Button::new("View this community"));
+                    let view = if signed_in { view } else { view.on_disabled_hover_text("Sign in to an account to choose to see it") };
+                    if view.clicked() {
+                        self.opt_in_quarantine(subreddit);
                     }
-                    Err(e) => {
-                        *error_message.lock().unwrap() = Some(format!("Error fetching subreddits: {}", e));
-                        *loading_subreddits.lock().unwrap() = false;
-                        *loading.lock().unwrap() = false;
-                        *initial_load.lock().unwrap() = false;
-                        return;
+                    if opting_in {
+                        ui.spinner();
                     }
                 }
-                
-                // Then fetch posts
-                match client.get_home_feed(None).await {
-                    Ok((fetched_posts, _after)) => {
-                        *posts.lock().unwrap() = fetched_posts;
-                        *loading.lock().unwrap() = false;
-                        *initial_load.lock().unwrap() = false;
+                if ui.button("Go home").clicked() {
+                    *self.error_message.lock().unwrap() = None;
+                    self.switch_subreddit("home".to_string());
+                }
+            });
+        });
+    }
+
+    fn opt_in_quarantine(&self, subreddit: &str) {
+        *self.opting_in.lock().unwrap() = true;
+        let name = subreddit.to_string();
+        let reddit_client = self.reddit_client.clone();
+        let settings = self.settings.clone();
+        let opting_in = self.opting_in.clone();
+        let error_message = self.error_message.clone();
+        let subreddit_request = self.subreddit_request.clone();
+        let egui_ctx = self.egui_ctx.clone();
+
+        thread::spawn(move || {
+            let rt = tokio::runtime::Runtime::new().unwrap();
+            let result = rt.block_on(async {
+                ensure_client(&reddit_client, &settings).await?.opt_in_quarantine(&name).await
+            });
+            match result {
+                Ok(()) => {
+                    tracing::info!(subreddit = %name, "Opted in to quarantined subreddit");
+                    // Loading it again now gets the posts
+                    *error_message.lock().unwrap() = None;
+                    *subreddit_request.lock().unwrap() = Some(name);
+                }
+                Err(e) => *error_message.lock().unwrap() = Some(RustleError::classify(&e).context("Couldn't opt in")),
+            }
+            *opting_in.lock().unwrap() = false;
+            if let Some(ctx) = &egui_ctx {
+                ctx.request_repaint();
+            }
+        });
+    }
+
+    // The error line under the header, with a way forward where there is one
+    fn render_error_banner(&mut self, ui: &mut egui::Ui, error: &RustleError) {
+        ui.horizontal_wrapped(|ui| {
+            ui.colored_label(ui.style().visuals.error_fg_color, error.to_string());
+            let can_retry = match error {
+                RustleError::RateLimited { retry_after } => {
+                    let wait = retry_after.saturating_duration_since(Instant::now());
+                    if !wait.is_zero() {
+                        ui.label(egui::RichText::new(format!("Try again in {}s", wait.as_secs() + 1)).weak());
+                        ui.ctx().request_repaint_after(Duration::from_millis(250));
+                    }
+                    wait.is_zero()
+                }
+                error => error.retryable(),
+            };
+            if can_retry && ui.button("Retry").clicked() {
+                self.retry();
+            }
+            if ui.small_button("✖").on_hover_text("Dismiss").clicked() {
+                *self.error_message.lock().unwrap() = None;
+            }
+        });
+    }
+
+    // Try again at whatever the screen in front of the reader was loading
+    fn retry(&mut self) {
+        *self.error_message.lock().unwrap() = None;
+        let open_post = self.open_post.lock().unwrap().clone();
+        if let Some(post) = open_post {
+            // Reopening the thread shouldn't lose the feed position saved on the way in
+            let feed_scroll = *self.feed_scroll_return.lock().unwrap();
+            self.open_comments(&post);
+            *self.feed_scroll_return.lock().unwrap() = feed_scroll;
+        } else if self.reddit_client.lock().unwrap().is_none() {
+            *self.loading.lock().unwrap() = true;
+            *self.initial_load.lock().unwrap() = true;
+            self.authenticate_and_load();
+        } else {
+            self.refresh_posts();
+        }
+    }
+
+    fn start_demo(&mut self) {
+        self.demo = true;
+        *self.subreddits.lock().unwrap() = demo::subreddits();
+        *self.current_subreddit.lock().unwrap() = String::new();
+        *self.loading.lock().unwrap() = false;
+        self.switch_subreddit("home".to_string());
+    }
+
+    // Nothing fetched for one account carries over to the next
+    fn forget_account_state(&self) {
+        *self.reddit_client.lock().unwrap() = None;
+        self.subreddits.lock().unwrap().clear();
+        self.blocked_users.lock().unwrap().clear();
+        self.moderated.lock().unwrap().clear();
+        *self.account_prefs.lock().unwrap() = None;
+        self.posts.lock().unwrap().clear();
+        *self.after.lock().unwrap() = None;
+        *self.prefetched.lock().unwrap() = None;
+        *self.open_post.lock().unwrap() = None;
+        *self.profile.lock().unwrap() = None;
+        *self.current_subreddit.lock().unwrap() = "home".to_string();
+        *self.current_view.lock().unwrap() = None;
+        self.feed_states.lock().unwrap().clear();
+        *self.inbox.lock().unwrap() = inbox::Inbox::default();
+        *self.me.lock().unwrap() = None;
+    }
+
+    fn switch_account(&mut self, index: usize) {
+        if *self.authenticating.lock().unwrap() {
+            return;
+        }
+        self.settings.switch_account(index);
+        self.forget_account_state();
+        self.save_settings_and_sign_in();
+    }
+
+    // The wizard fills in a blank account; the current one stays in the switcher
+    fn add_account(&mut self) {
+        let mut settings = self.settings.clone();
+        settings.begin_new_account();
+        self.onboarding = Some(onboarding::Wizard::new(settings, true));
+    }
+
+    fn render_account_menu(&mut self, ui: &mut egui::Ui) {
+        let mut switch_to = None;
+        let mut remove = None;
+        let mut add = false;
+        ui.menu_button(self.settings.account.label(), |ui| {
+            ui.add_enabled(false, egui::SelectableLabel::new(true, self.settings.account.label()));
+            for (index, account) in self.settings.accounts.iter().enumerate() {
+                ui.horizontal(|ui| {
+                    if ui.selectable_label(false, account.label()).clicked() {
+                        switch_to = Some(index);
+                        ui.close_menu();
                     }
-                    Err(e) => {
-                        *error_message.lock().unwrap() = Some(format!("Error fetching posts: {}", e));
-                        *loading.lock().unwrap() = false;
-                        *initial_load.lock().unwrap() = false;
+                    if ui.small_button("✖").on_hover_text("Remove this account from Rustle").clicked() {
+                        remove = Some(index);
                     }
-                }
-            });
-        });
+                });
+            }
+            ui.separator();
+            if ui.button("Add account...").clicked() {
+                add = true;
+                ui.close_menu();
+            }
+        }).response.on_hover_text("Switch account");
+
+        if let Some(index) = remove {
+            self.settings.accounts.remove(index);
+            if let Err(e) = self.settings.save() {
+                *self.error_message.lock().unwrap() = Some(RustleError::classify(&e).context("Failed to save settings"));
+            }
+        } else if let Some(index) = switch_to {
+            self.switch_account(index);
+        } else if add {
+            self.add_account();
+        }
+    }
+
+    // Persist the settings and sign in again, since the credentials may have changed
+    fn save_settings_and_sign_in(&mut self) {
+        if let Some(cache) = &self.image_cache {
+            cache.set_max_bytes(self.settings.image_cache_mb as u64 * 1024 * 1024);
+        }
+        if let Err(e) = self.settings.save() {
+            *self.error_message.lock().unwrap() = Some(RustleError::classify(&e).context("Failed to save settings"));
+            return;
+        }
+        self.settings_modified = false;
+        self.show_settings = false;
+        self.has_credentials = true;
+        self.demo = false;
+        *self.error_message.lock().unwrap() = None;
+        *self.loading.lock().unwrap() = true;
+        *self.initial_load.lock().unwrap() = true;
+        *self.scroll_to_top.lock().unwrap() = true;
+        self.authenticate_and_load();
+    }
+
+    // Narrow a subreddit to one link flair, keeping the current sort
+    fn filter_by_flair(&self, subreddit: String, flair: String) {
+        if *self.loading.lock().unwrap() {
+            return;
+        }
+        let current = self.current_view.lock().unwrap().clone().unwrap_or_default();
+        if !self.current_subreddit.lock().unwrap().eq_ignore_ascii_case(&subreddit) {
+            self.stash_feed();
+        }
+        *self.current_subreddit.lock().unwrap() = subreddit;
+        *self.current_view.lock().unwrap() = Some(FeedView { name: flair.clone(), flair, ..current });
+        self.refresh_posts();
+    }
+
+    fn select_view(&self, view: Option<FeedView>) {
+        if *self.loading.lock().unwrap() {
+            return;
+        }
+        *self.current_view.lock().unwrap() = view;
+        self.refresh_posts();
     }
 
     fn refresh_posts(&self) {
@@ -522,11 +8061,10 @@ impl RedditApp {
     }
 
     fn handle_scroll_state(&self, ctx: &egui::Context) {
-        // Check if the scroll position seems invalid or if we're in an error state
-        if let Some(error) = self.error_message.lock().unwrap().as_ref() {
-            if error.contains("Error fetching posts") || error.contains("Authentication error") {
-                *self.scroll_to_top.lock().unwrap() = true;
-            }
+        // Check if the scroll position seems invalid or if we're in an error state; failed feed
+        // loads ask for the top themselves
+        if matches!(self.error_message.lock().unwrap().as_ref(), Some(RustleError::Auth { .. })) {
+            *self.scroll_to_top.lock().unwrap() = true;
         }
         
         // Reset scroll if we have no posts but are not in settings
@@ -544,126 +8082,178 @@ impl RedditApp {
         if *self.loading_subreddits.lock().unwrap() {
             return;
         }
+        if !self.settings.signed_in() {
+            *self.subreddits.lock().unwrap() = self.settings.account.subreddit_picks.clone();
+            return;
+        }
 
         *self.loading_subreddits.lock().unwrap() = true;
+        let load = self.watchdog.begin("subreddits");
         let reddit_client = self.reddit_client.clone();
         let subreddits = self.subreddits.clone();
         let loading_subreddits = self.loading_subreddits.clone();
+        let subreddits_fetched = self.subreddits_fetched.clone();
         let error_message = self.error_message.clone();
         let settings = self.settings.clone();
+        let store = self.store.clone();
+        let egui_ctx = self.egui_ctx.clone();
 
         thread::spawn(move || {
             let rt = tokio::runtime::Runtime::new().unwrap();
             rt.block_on(async {
-                let client = {
-                    let mut client_guard = reddit_client.lock().unwrap();
-                    if let Some(client) = client_guard.as_ref() {
-                        client.clone()
-                    } else {
-                        let mut client = match RedditClient::new() {
-                            Ok(client) => client,
-                            Err(e) => {
-                                *error_message.lock().unwrap() = Some(format!("Failed to create client: {}", e));
-                                *loading_subreddits.lock().unwrap() = false;
-                                return;
-                            }
-                        };
-                        
-                        if let Err(e) = client.authenticate(&settings.client_id, &settings.client_secret, 
-                            &settings.username, &settings.password).await {
-                            *error_message.lock().unwrap() = Some(format!("Authentication error: {}", e));
-                            *loading_subreddits.lock().unwrap() = false;
-                            return;
+                let client = match ensure_client(&reddit_client, &settings).await {
+                    Ok(client) => client,
+                    Err(_) if !load.current() => return,
+                    Err(e) => {
+                        if !(store::is_network_error(&e) && load_offline_subreddits(&store, &subreddits)) {
+                            *error_message.lock().unwrap() = Some(RustleError::classify(&e));
                         }
-                        
-                        *client_guard = Some(client.clone());
-                        client
+                        *loading_subreddits.lock().unwrap() = false;
+                        return;
                     }
                 };
 
-                match client.get_subscribed_subreddits().await {
-                    Ok(fetched_subreddits) => {
-                        *subreddits.lock().unwrap() = fetched_subreddits;
-                        *loading_subreddits.lock().unwrap() = false;
-                    }
-                    Err(e) => {
-                        *error_message.lock().unwrap() = Some(format!("Error fetching subreddits: {}", e));
-                        *loading_subreddits.lock().unwrap() = false;
-                    }
-                }
+                fetch_subreddit_list(&client, &load, &subreddits, &subreddits_fetched, &error_message, &store, &egui_ctx).await;
+                load.finish(&loading_subreddits);
             });
         });
     }
 
+    // Keep the current feed's posts, page and scroll offset for when it's opened again
+    fn stash_feed(&self) {
+        let current = self.current_subreddit.lock().unwrap().to_lowercase();
+        let posts = std::mem::take(&mut *self.posts.lock().unwrap());
+        if current.is_empty() || posts.is_empty() {
+            return;
+        }
+        self.feed_states.lock().unwrap().insert(current, FeedState {
+            view: self.current_view.lock().unwrap().clone(),
+            posts,
+            after: self.after.lock().unwrap().clone(),
+            scroll: *self.last_scroll_pos.lock().unwrap(),
+            hidden: std::mem::take(&mut *self.hidden_by_filters.lock().unwrap()),
+        });
+    }
+
+    // Switching to another feed restores it as it was left; switching to the current one
+    // (the refresh button, a new view) always fetches it again
     fn switch_subreddit(&self, subreddit: String) {
         if *self.loading.lock().unwrap() {
             return;
         }
 
+        let switching = *self.current_subreddit.lock().unwrap() != subreddit;
+        if switching {
+            self.stash_feed();
+            // Views belong to one subreddit, which opens with its own remembered sort; a refresh
+            // keeps the active one
+            *self.current_view.lock().unwrap() = self.settings.feed_prefs(&subreddit).view().filter(|_| has_feed_prefs(&subreddit));
+        }
         *self.current_subreddit.lock().unwrap() = subreddit.clone();
+        *self.prefetched.lock().unwrap() = None;
+        *self.open_post.lock().unwrap() = None;
+        *self.profile.lock().unwrap() = None;
+        *self.discover.lock().unwrap() = None;
+        *self.history.lock().unwrap() = None;
+        *self.reader.lock().unwrap() = None;
+        *self.error_message.lock().unwrap() = None;
+        self.read_posts.lock().unwrap().new_feed();
+        self.hidden_posts.lock().unwrap().clear();
+        self.selection.lock().unwrap().clear();
+        *self.new_posts.lock().unwrap() = None;
+        *self.kept_feed_synced_at.lock().unwrap() = None;
+        self.load_subreddit_about(&subreddit);
+
+        let saved = self.feed_states.lock().unwrap().remove(&subreddit.to_lowercase());
+        if let Some(saved) = saved.filter(|_| switching) {
+            *self.current_view.lock().unwrap() = saved.view;
+            *self.posts.lock().unwrap() = saved.posts;
+            *self.after.lock().unwrap() = saved.after;
+            *self.hidden_by_filters.lock().unwrap() = saved.hidden;
+            *self.last_scroll_pos.lock().unwrap() = saved.scroll;
+            *self.feed_scroll_return.lock().unwrap() = Some(saved.scroll);
+            *self.scroll_to_top.lock().unwrap() = false;
+            return;
+        }
+
+        let view = self.current_view.lock().unwrap().clone();
         *self.loading.lock().unwrap() = true;
         *self.after.lock().unwrap() = None;  // Reset pagination
-        *self.error_message.lock().unwrap() = None;
         *self.initial_load.lock().unwrap() = true;
+        self.hidden_by_filters.lock().unwrap().clear();
+
+        if self.demo {
+            let (posts, hidden) = Filters::new(&self.settings.filters)
+                .muting(self.settings.muted_media(&subreddit))
+                .apply(demo::posts(&subreddit));
+            *self.posts.lock().unwrap() = posts;
+            *self.hidden_by_filters.lock().unwrap() = vec![hidden];
+            *self.loading.lock().unwrap() = false;
+            *self.initial_load.lock().unwrap() = false;
+            return;
+        }
         *self.scroll_to_top.lock().unwrap() = true;
+        let load = self.watchdog.begin("posts");
         
         let reddit_client = self.reddit_client.clone();
         let posts = self.posts.clone();
         let loading = self.loading.clone();
         let error_message = self.error_message.clone();
+        let scroll_to_top = self.scroll_to_top.clone();
         let initial_load = self.initial_load.clone();
         let after = self.after.clone();
         let settings = self.settings.clone();
+        let read_posts = self.read_posts.clone();
+        let store = self.store.clone();
+        let hidden_by_filters = self.hidden_by_filters.clone();
+        let egui_ctx = self.egui_ctx.clone();
 
         thread::spawn(move || {
             let rt = tokio::runtime::Runtime::new().unwrap();
             rt.block_on(async {
-                let client = {
-                    let mut client_guard = reddit_client.lock().unwrap();
-                    if let Some(client) = client_guard.as_ref() {
-                        client.clone()
-                    } else {
-                        let mut client = match RedditClient::new() {
-                            Ok(client) => client,
-                            Err(e) => {
-                                *error_message.lock().unwrap() = Some(format!("Failed to create client: {}", e));
-                                *loading.lock().unwrap() = false;
-                                *initial_load.lock().unwrap() = false;
-                                return;
-                            }
-                        };
-                        
-                        if let Err(e) = client.authenticate(&settings.client_id, &settings.client_secret, 
-                            &settings.username, &settings.password).await {
-                            *error_message.lock().unwrap() = Some(format!("Authentication error: {}", e));
-                            *loading.lock().unwrap() = false;
-                            *initial_load.lock().unwrap() = false;
-                            return;
-                        }
-                        
-                        *client_guard = Some(client.clone());
-                        client
-                    }
-                };
-
-                let result = if subreddit == "home" {
-                    client.get_home_feed(None).await
-                } else {
-                    client.get_subreddit_posts(&subreddit, None).await
+                let result = match ensure_client(&reddit_client, &settings).await {
+                    Ok(client) => fetch_filtered(&client, &settings, &subreddit, view.as_ref(), None).await,
+                    Err(e) => Err(e),
                 };
+                // The watchdog gave up on this load, and a retry owns the feed now
+                if !load.current() {
+                    return;
+                }
 
                 match result {
-                    Ok((fetched_posts, new_after)) => {
+                    Ok((fetched_posts, new_after, hidden)) => {
+                        // Start image downloads before the cards are first drawn
+                        if let Some(ctx) = &egui_ctx {
+                            prefetch_images(ctx, &fetched_posts, &settings);
+                        }
+                        merge_visited(&settings, &read_posts, &store, &fetched_posts);
+                        save_feed(&store, &subreddit, view.as_ref(), &fetched_posts, new_after.as_deref());
                         let mut posts_lock = posts.lock().unwrap();
                         *posts_lock = fetched_posts;
                         drop(posts_lock);
+                        *hidden_by_filters.lock().unwrap() = vec![hidden];
                         
                         *after.lock().unwrap() = new_after;
                         *loading.lock().unwrap() = false;
                         *initial_load.lock().unwrap() = false;
+                        // Wake the UI immediately rather than waiting for the next input event
+                        if let Some(ctx) = &egui_ctx {
+                            ctx.request_repaint();
+                        }
+                    }
+                    Err(e) if store::is_network_error(&e) => {
+                        if !load_offline_feed(&store, &subreddit, view.as_ref(), &posts, &after) {
+                            *error_message.lock().unwrap() = Some(RustleError::Network(format!("Offline, and r/{} hasn't been saved for offline reading yet", subreddit)));
+                        }
+                        *loading.lock().unwrap() = false;
+                        *initial_load.lock().unwrap() = false;
+                        if let Some(ctx) = &egui_ctx {
+                            ctx.request_repaint();
+                        }
                     }
                     Err(e) => {
-                        *error_message.lock().unwrap() = Some(format!("Error fetching posts: {}", e));
+                        *error_message.lock().unwrap() = Some(RustleError::classify(&e).context("Error fetching posts"));
+                        *scroll_to_top.lock().unwrap() = true;
                         *loading.lock().unwrap() = false;
                         *initial_load.lock().unwrap() = false;
                     }
@@ -674,47 +8264,191 @@ impl RedditApp {
 }
 
 impl eframe::App for RedditApp {
-    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        // Set the theme based on settings
-        let visuals = if self.settings.dark_mode {
-            egui::Visuals::dark()
+    // A transparent window with an opaque theme is covered up again here
+    fn clear_color(&self, visuals: &egui::Visuals) -> [f32; 4] {
+        if self.transparent_window && self.settings.theme.translucent() {
+            [0.0; 4]
         } else {
-            egui::Visuals::light()
-        };
-        ctx.set_visuals(visuals);
+            visuals.panel_fill.to_normalized_gamma_f32()
+        }
+    }
 
-        // Set longer tooltip delay
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        // Set the theme and text sizes based on settings, with a longer tooltip delay
         let mut style = (*ctx.style()).clone();
+        self.settings.theme.apply(&mut style);
+        if !self.transparent_window {
+            // Nothing would show through, so translucent panels would only look washed out
+            style.visuals.panel_fill = self.settings.theme.background;
+        }
+        style.text_styles = theme::text_styles(self.settings.font_size);
         style.interaction.tooltip_delay = 1.0;
         ctx.set_style(style);
+        self.apply_ui_scale(ctx);
+        let showing = self.error_message.lock().unwrap().as_ref().map(|error| error.to_string());
+        self.error_log.note(showing, store::now());
+
+        self.animations.lock().unwrap().drop_unseen(ctx.frame_nr());
+
+        // Hover previews stop as soon as the pointer leaves the thumbnail
+        #[cfg(feature = "video")]
+        {
+            let mut hover_preview = self.hover_preview.lock().unwrap();
+            if hover_preview.as_ref().is_some_and(|preview| preview.frame + 1 < ctx.frame_nr()) {
+                *hover_preview = None;
+            }
+        }
+
+        if self.notice.as_ref().is_some_and(|(_, expires_at)| Instant::now() >= *expires_at) {
+            self.notice = None;
+        }
+        if self.read_saved_at.elapsed() >= READ_SAVE_DELAY {
+            self.save_read_posts();
+        }
+        if self.today_start.is_none_or(|(_, checked_at)| checked_at.elapsed() >= DAY_CHECK) {
+            // UTC midnight is close enough if SQLite can't tell the timezone
+            let now = store::now();
+            let today_start = store::local_day_start().unwrap_or(now - now.rem_euclid(86_400));
+            self.today_start = Some((today_start, Instant::now()));
+        }
+        if let Some((path, count)) = self.export_saved.lock().unwrap().take() {
+            self.notice = Some((format!("Exported {} posts to {}", count, path.display()), Instant::now() + NOTICE_DURATION));
+        }
+        if let Some((path, count)) = self.rules_exported.lock().unwrap().take() {
+            self.notice = Some((format!("Exported {} rules to {}", count, path.display()), Instant::now() + NOTICE_DURATION));
+        }
+        self.handle_hint_keys(ctx);
+        if self.tracking_usage() {
+            let feed = self.current_subreddit.lock().unwrap().clone();
+            self.usage.lock().unwrap().tick(Instant::now(), ctx.input(|i| i.focused), &feed);
+        }
+
+        // Unsaved edits in the settings panel win over freshly synced account preferences
+        let synced_prefs = self.account_prefs.lock().unwrap().take();
+        if let Some(prefs) = synced_prefs.filter(|_| !self.settings_modified) {
+            self.settings.apply_account_prefs(&prefs);
+        }
 
         // Handle scroll state
         self.handle_scroll_state(ctx);
 
-        // Install image loaders (this only needs to happen once)
-        static LOADERS_INSTALLED: std::sync::Once = std::sync::Once::new();
-        LOADERS_INSTALLED.call_once(|| {
-            install_image_loaders(ctx);
-        });
+        let export_shortcut = egui::KeyboardShortcut::new(egui::Modifiers::COMMAND | egui::Modifiers::SHIFT, egui::Key::E);
+        if ctx.input_mut(|i| i.consume_shortcut(&export_shortcut)) {
+            self.export_feed();
+        }
+        let inspector_shortcut = egui::KeyboardShortcut::new(egui::Modifiers::COMMAND | egui::Modifiers::SHIFT, egui::Key::I);
+        if ctx.input_mut(|i| i.consume_shortcut(&inspector_shortcut)) {
+            self.toggle_inspector();
+        }
+        if let Some(inspector) = &mut self.inspector {
+            if !inspector.show(ctx) {
+                self.inspector = None;
+            }
+        }
+        let console_shortcut = egui::KeyboardShortcut::new(egui::Modifiers::COMMAND | egui::Modifiers::SHIFT, egui::Key::D);
+        if ctx.input_mut(|i| i.consume_shortcut(&console_shortcut)) {
+            self.console = match self.console {
+                Some(_) => None,
+                None => Some(logging::Console::default()),
+            };
+        }
+        if let Some(console) = &mut self.console {
+            if !console.show(ctx) {
+                self.console = None;
+            }
+        }
+        self.show_digest(ctx);
+        self.show_post_composer(ctx);
+        self.show_drafts(ctx);
+        if self.archive_open {
+            self.archive_open = archive::show(ctx, &self.archive_status.lock().unwrap());
+        }
+        self.show_usage_dashboard(ctx);
+        self.show_focus_nudge(ctx);
 
+        self.watch_loads(ctx);
         let loading = *self.loading.lock().unwrap();
         if loading {
             ctx.request_repaint();
         }
 
         // Load subreddits if we haven't yet and we're authenticated
-        if self.has_credentials && self.subreddits.lock().unwrap().is_empty() && !*self.loading_subreddits.lock().unwrap() {
+        let authenticating = *self.authenticating.lock().unwrap();
+        if self.has_credentials && !authenticating && self.subreddits.lock().unwrap().is_empty() && !*self.loading_subreddits.lock().unwrap() {
             self.load_subreddits();
         }
 
+        self.check_inbox(ctx);
+        self.load_me(ctx);
+        self.send_scheduled_posts(ctx);
+        self.run_archiver(ctx);
+        #[cfg(feature = "notifications")]
+        self.update_tray(ctx);
+
+        // Before the central panel, which takes whatever room is left
+        if (self.has_credentials || self.demo) && !self.show_settings && self.settings.sidebar_open {
+            self.render_sidebar(ctx, loading);
+        }
+
         egui::CentralPanel::default().show(ctx, |ui| {
             ui.horizontal(|ui| {
                 ui.heading(
                     egui::RichText::new(APP_NAME)
                         .strong()
                         .size(24.0)  // Keep Rustle title at fixed size
-                );
-                ui.label(egui::RichText::new(APP_VERSION).weak().size(12.0));
+                ).on_hover_text(format!("{} {}", APP_NAME, APP_VERSION));
+                if replay::replaying() {
+                    ui.label(egui::RichText::new("Replaying a recorded session").weak().size(12.0));
+                }
+                if authenticating {
+                    ui.add_space(8.0);
+                    ui.spinner();
+                    ui.label(egui::RichText::new("Signing in...").weak().size(12.0));
+                }
+                let quota = self.reddit_client.lock().unwrap().as_ref().and_then(|client| client.rate_limit.quota());
+                if let Some(quota) = quota {
+                    ui.add_space(8.0);
+                    let mut text = egui::RichText::new(format!("API {}", quota.remaining)).size(12.0);
+                    text = if quota.is_low() { text.color(ui.style().visuals.warn_fg_color) } else { text.weak() };
+                    ui.label(text).on_hover_text(format!(
+                        "Reddit API quota: {} requests left, {} used, resets in {}s{}",
+                        quota.remaining,
+                        quota.used,
+                        quota.resets_in.as_secs(),
+                        if quota.is_low() { " (requests are being throttled)" } else { "" },
+                    ));
+                }
+                if self.settings.signed_in() && !self.settings.account.username.is_empty() {
+                    self.render_account_header(ui);
+                }
+                let unread = self.inbox.lock().unwrap().unread.clone();
+                // Without inbox checks there are no messages to list, just the account's count
+                let unread_count = self.me.lock().unwrap().as_ref()
+                    .filter(|me| me.name.eq_ignore_ascii_case(&self.settings.account.username))
+                    .map_or(0, |me| me.inbox_count);
+                if self.settings.inbox_check_minutes == 0 && unread_count > 0 {
+                    ui.add_space(8.0);
+                    let inbox = ui.link(egui::RichText::new(format!("✉ {}", unread_count)).size(12.0));
+                    if inbox.on_hover_text(inbox::describe(unread_count)).clicked() {
+                        ui.ctx().open_url(egui::OpenUrl::new_tab("https://www.reddit.com/message/unread"));
+                    }
+                }
+                if !unread.is_empty() {
+                    ui.add_space(8.0);
+                    ui.menu_button(egui::RichText::new(format!("✉ {}", unread.len())).size(12.0), |ui| {
+                        for message in unread.iter().take(10) {
+                            if ui.button(message.summary()).on_hover_text(message.preview()).clicked() {
+                                ui.ctx().open_url(egui::OpenUrl::new_tab(message.link()));
+                                ui.close_menu();
+                            }
+                        }
+                        ui.separator();
+                        if ui.button("Open inbox on Reddit").clicked() {
+                            ui.ctx().open_url(egui::OpenUrl::new_tab("https://www.reddit.com/message/unread"));
+                            ui.close_menu();
+                        }
+                    }).response.on_hover_text(inbox::describe(unread.len()));
+                }
                 ui.with_layout(egui::Layout::right_to_left(egui::Align::RIGHT), |ui| {
                     // Font size controls and buttons
                     ui.add_space(4.0); // Add a small space at the right edge
@@ -742,6 +8476,113 @@ impl eframe::App for RedditApp {
                         }
                     );
                     
+                    // Hide read toggle
+                    ui.allocate_ui_with_layout(
+                        egui::vec2(32.0, 32.0),
+                        egui::Layout::centered_and_justified(egui::Direction::LeftToRight),
+                        |ui| {
+                            let hide_read_button = ui.add_enabled(
+                                !self.show_settings,
+                                egui::Button::new(
+                                    egui::RichText::new("👁")
+                                        .size(16.0)
+                                )
+                                .selected(self.settings.hide_read)
+                                .min_size(egui::vec2(28.0, 28.0))
+                                .rounding(5.0)
+                            ).on_hover_text(if self.settings.hide_read {
+                                "Hiding posts you've already read"
+                            } else {
+                                "Hide posts you've already read"
+                            });
+                            if hide_read_button.clicked() {
+                                self.settings.hide_read = !self.settings.hide_read;
+                                self.settings_modified = true;
+                            }
+                        }
+                    );
+
+                    // Density toggle
+                    ui.allocate_ui_with_layout(
+                        egui::vec2(32.0, 32.0),
+                        egui::Layout::centered_and_justified(egui::Direction::LeftToRight),
+                        |ui| {
+                            // In a subreddit the layout is remembered for that subreddit alone
+                            let feed = self.current_subreddit.lock().unwrap().clone();
+                            let per_feed = has_feed_prefs(&feed);
+                            let density = self.density();
+                            let next = density.next();
+                            let density_button = ui.add_enabled(
+                                !self.show_settings,
+                                egui::Button::new(
+                                    egui::RichText::new("☰")
+                                        .size(16.0)
+                                )
+                                .min_size(egui::vec2(28.0, 28.0))
+                                .rounding(5.0)
+                            ).on_hover_text(if per_feed {
+                                format!("{} layout in r/{}; click for {}", density.name(), feed, next.name())
+                            } else {
+                                format!("{} layout; click for {}", density.name(), next.name())
+                            });
+                            if density_button.clicked() {
+                                if per_feed {
+                                    self.settings.update_feed_prefs(&feed, |prefs| prefs.density = Some(next));
+                                } else {
+                                    self.settings.density = next;
+                                }
+                                self.settings_modified = true;
+                            }
+                        }
+                    );
+
+                    // Digest of the subscriptions' top posts
+                    ui.allocate_ui_with_layout(
+                        egui::vec2(32.0, 32.0),
+                        egui::Layout::centered_and_justified(egui::Direction::LeftToRight),
+                        |ui| {
+                            let digest_button = ui.add_enabled(
+                                self.has_credentials && !self.demo,
+                                egui::Button::new(
+                                    egui::RichText::new("📰")
+                                        .size(16.0)
+                                )
+                                .selected(self.digest_viewer.is_some())
+                                .min_size(egui::vec2(28.0, 28.0))
+                                .rounding(5.0)
+                            ).on_hover_text("Digest of the top posts in your subscriptions");
+                            if digest_button.clicked() {
+                                self.digest_viewer = match self.digest_viewer {
+                                    Some(_) => None,
+                                    None => Some(digest::Viewer::default()),
+                                };
+                            }
+                        }
+                    );
+
+                    // Media queue
+                    ui.allocate_ui_with_layout(
+                        egui::vec2(32.0, 32.0),
+                        egui::Layout::centered_and_justified(egui::Direction::LeftToRight),
+                        |ui| {
+                            let media_button = ui.add_enabled(
+                                !self.show_settings,
+                                egui::Button::new(
+                                    egui::RichText::new("🖼")
+                                        .size(16.0)
+                                )
+                                .min_size(egui::vec2(28.0, 28.0))
+                                .rounding(5.0)
+                            ).on_hover_text("View every loaded image and video, one after another");
+                            if media_button.clicked() {
+                                self.open_media_queue();
+                                if self.lightbox.is_none() {
+                                    *self.error_message.lock().unwrap() = Some(RustleError::Other("No images or videos loaded in this feed".to_string()));
+                                }
+                            }
+                        }
+                    );
+
                     // Refresh button
                     ui.allocate_ui_with_layout(
                         egui::vec2(32.0, 32.0),
@@ -778,7 +8619,7 @@ impl eframe::App for RedditApp {
                                 self.settings.font_size = (self.settings.font_size + 0.1).min(2.0);
                                 self.settings_modified = true;
                                 if let Err(e) = self.settings.save() {
-                                    *self.error_message.lock().unwrap() = Some(format!("Failed to save settings: {}", e));
+                                    *self.error_message.lock().unwrap() = Some(RustleError::classify(&e).context("Failed to save settings"));
                                 }
                             }
                         }
@@ -800,61 +8641,221 @@ impl eframe::App for RedditApp {
                                 self.settings.font_size = (self.settings.font_size - 0.1).max(0.5);
                                 self.settings_modified = true;
                                 if let Err(e) = self.settings.save() {
-                                    *self.error_message.lock().unwrap() = Some(format!("Failed to save settings: {}", e));
+                                    *self.error_message.lock().unwrap() = Some(RustleError::classify(&e).context("Failed to save settings"));
                                 }
                             }
                         }
-                    );
-                });
-            });
-            ui.add_space(2.0);
-
-            // Subreddit navigation bar
-            if self.has_credentials && !self.show_settings {
-                ui.horizontal_wrapped(|ui| {
-                    let current = self.current_subreddit.lock().unwrap().clone();
-                    let subreddits = self.subreddits.lock().unwrap().clone();
-                    
-                    // Home feed link
-                    if ui.add(
-                        egui::Button::new(
-                            egui::RichText::new("/r/home")
-                                .color(if current == "home" {
-                                    ui.style().visuals.text_color()
-                                } else {
-                                    ui.style().visuals.weak_text_color()
-                                })
-                        ).frame(false)
-                    ).clicked() && !loading && current != "home" {
-                        self.switch_subreddit("home".to_string());
+                    );
+
+                    if self.has_credentials && !self.demo {
+                        ui.add_space(4.0);
+                        self.render_account_menu(ui);
+                    }
+                });
+            });
+            ui.add_space(2.0);
+
+            // Subreddit navigation bar
+            if (self.has_credentials || self.demo) && !self.show_settings {
+                ui.horizontal_wrapped(|ui| {
+                    let current = self.current_subreddit.lock().unwrap().clone();
+
+                    let (icon, tip) = if self.settings.sidebar_open {
+                        ("⏴", "Hide the subreddit list")
+                    } else {
+                        ("⏵", "Show the subreddit list")
+                    };
+                    if ui.add(egui::Button::new(icon).frame(false)).on_hover_text(tip).clicked() {
+                        self.settings.sidebar_open = !self.settings.sidebar_open;
+                        if let Err(e) = self.settings.save() {
+                            *self.error_message.lock().unwrap() = Some(RustleError::classify(&e).context("Failed to save settings"));
+                        }
+                    }
+                    self.render_go_to_subreddit(ui, loading);
+                    ui.add_space(8.0);
+                    
+                    // Home feed and the site-wide feeds
+                    for (index, feed) in ["home", "popular", "all"].into_iter().enumerate() {
+                        if index > 0 {
+                            ui.add_space(8.0);
+                        }
+                        let response = ui.add(
+                            egui::Button::new(
+                                egui::RichText::new(format!("/r/{}", feed))
+                                    .color(if current == feed {
+                                        ui.style().visuals.text_color()
+                                    } else {
+                                        ui.style().visuals.weak_text_color()
+                                    })
+                            ).frame(false)
+                        );
+                        self.hint(ui, &response, || hints::Jump::Subreddit(feed.to_string()));
+                        if response.clicked() && !loading && current != feed {
+                            self.switch_subreddit(feed.to_string());
+                        }
+                    }
+                    // Sample mode has no directory to browse
+                    if !self.demo {
+                        ui.add_space(8.0);
+                        let discovering = self.discover.lock().unwrap().is_some();
+                        if ui.add(
+                            egui::Button::new(
+                                egui::RichText::new("🔭 Discover")
+                                    .color(if discovering {
+                                        ui.style().visuals.text_color()
+                                    } else {
+                                        ui.style().visuals.weak_text_color()
+                                    })
+                            ).frame(false)
+                        ).on_hover_text("Trending, popular and new subreddits to join").clicked() && !discovering {
+                            self.open_discover(discover::Source::default());
+                        }
+                    }
+                    if self.settings.signed_in() && !self.demo {
+                        ui.add_space(8.0);
+                        if ui.add(
+                            egui::Button::new(
+                                egui::RichText::new("🔖 Saved")
+                                    .color(if current == SAVED {
+                                        ui.style().visuals.text_color()
+                                    } else {
+                                        ui.style().visuals.weak_text_color()
+                                    })
+                            ).frame(false)
+                        ).on_hover_text("Posts you've saved").clicked() && !loading && current != SAVED {
+                            self.switch_subreddit(SAVED.to_string());
+                        }
+                    }
+                    if self.store.is_some() && !self.demo {
+                        ui.add_space(8.0);
+                        let browsing_history = self.history.lock().unwrap().is_some();
+                        if ui.add(
+                            egui::Button::new(
+                                egui::RichText::new("🕓 History")
+                                    .color(if browsing_history {
+                                        ui.style().visuals.text_color()
+                                    } else {
+                                        ui.style().visuals.weak_text_color()
+                                    })
+                            ).frame(false)
+                        ).on_hover_text("Posts you've opened, newest first").clicked() && !browsing_history {
+                            self.open_history();
+                        }
                     }
-
-                    // Add subreddits with spacing
-                    for subreddit in subreddits.iter() {
+                    let drafts = self.drafts.lock().unwrap().len();
+                    if self.settings.signed_in() && !self.demo && drafts > 0 {
                         ui.add_space(8.0);
                         if ui.add(
                             egui::Button::new(
-                                egui::RichText::new(format!("/r/{}", subreddit))
-                                    .color(if current == *subreddit {
+                                egui::RichText::new(format!("📝 Drafts ({})", drafts))
+                                    .color(if self.drafts_open {
                                         ui.style().visuals.text_color()
                                     } else {
                                         ui.style().visuals.weak_text_color()
                                     })
                             ).frame(false)
-                        ).clicked() && !loading && current != *subreddit {
-                            self.switch_subreddit(subreddit.clone());
+                        ).on_hover_text("Unfinished and scheduled posts").clicked() {
+                            self.drafts_open = !self.drafts_open;
+                        }
+                    }
+                    if self.settings.signed_in() && !self.moderated.lock().unwrap().is_empty() {
+                        ui.add_space(8.0);
+                        if ui.add(
+                            egui::Button::new(
+                                egui::RichText::new("🛡 Mod queue")
+                                    .color(if current == moderation::QUEUE {
+                                        ui.style().visuals.text_color()
+                                    } else {
+                                        ui.style().visuals.weak_text_color()
+                                    })
+                            ).frame(false)
+                        ).on_hover_text("Posts waiting for review in the subreddits you moderate").clicked() && !loading && current != moderation::QUEUE {
+                            self.switch_subreddit(moderation::QUEUE.to_string());
                         }
                     }
                 });
                 ui.separator();
+
+                let current = self.current_subreddit.lock().unwrap().clone();
+                if self.open_post.lock().unwrap().is_none() && self.profile.lock().unwrap().is_none() {
+                    if current == "home" {
+                        self.render_home_header(ui);
+                    } else {
+                        self.render_subreddit_header(ui, &current);
+                    }
+                }
             }
             
-            // Error message display (if any)
-            if let Some(error) = self.error_message.lock().unwrap().as_ref() {
+            // Offline banner while showing synced content; a reconnect is attempted every SYNC_RETRY
+            if let Some(synced_at) = self.store.as_ref().and_then(|store| store.offline()) {
                 ui.add_space(5.0);
-                ui.colored_label(ui.style().visuals.error_fg_color, error);
+                ui.horizontal(|ui| {
+                    ui.colored_label(ui.style().visuals.warn_fg_color,
+                        format!("Offline: showing content synced {} ago", store::age(synced_at)));
+                    let busy = *self.authenticating.lock().unwrap() || *self.loading.lock().unwrap();
+                    if busy {
+                        ui.spinner();
+                    } else if ui.small_button("Retry now").clicked() {
+                        self.sync_retry_at = Some(Instant::now());
+                    }
+                });
+                ui.add_space(5.0);
+                self.retry_sync(ctx);
+            } else {
+                self.sync_retry_at = None;
+                if self.open_post.lock().unwrap().is_none() && self.profile.lock().unwrap().is_none() {
+                    self.render_new_posts_banner(ui);
+                    self.render_filter_count(ui);
+                }
+                self.check_for_new_posts(ctx);
+                self.refresh_stale_feed(ctx);
+            }
+
+            if self.demo && self.onboarding.is_none() {
+                ui.add_space(5.0);
+                ui.horizontal(|ui| {
+                    ui.label(egui::RichText::new("You're browsing sample posts.").weak());
+                    if ui.link("Connect to Reddit").clicked() {
+                        self.onboarding = Some(onboarding::Wizard::new(self.settings.clone(), true));
+                    }
+                });
+                ui.add_space(5.0);
+            }
+
+            // Error message display (if any); credential failures get their own screen below
+            let error = self.error_message.lock().unwrap().clone()
+                .filter(|error| !matches!(error, RustleError::Auth { .. } | RustleError::Restricted { .. }));
+            if let Some(error) = error {
+                ui.add_space(5.0);
+                self.render_error_banner(ui, &error);
                 ui.add_space(5.0);
             }
+            if let Some((notice, expires_at)) = &self.notice {
+                ui.add_space(5.0);
+                ui.label(egui::RichText::new(notice).weak());
+                ui.add_space(5.0);
+                ctx.request_repaint_after(expires_at.saturating_duration_since(Instant::now()));
+            }
+
+            // First-run setup, or a change of sign-in method from settings
+            if let Some(wizard) = &mut self.onboarding {
+                match wizard.show(ui) {
+                    Some(onboarding::Outcome::Finished(settings)) => {
+                        self.onboarding = None;
+                        self.settings = *settings;
+                        self.settings.forget_duplicate_accounts();
+                        self.forget_account_state();
+                        self.save_settings_and_sign_in();
+                    }
+                    Some(onboarding::Outcome::Cancelled) => self.onboarding = None,
+                    Some(onboarding::Outcome::Demo) => {
+                        self.onboarding = None;
+                        self.start_demo();
+                    }
+                    None => {}
+                }
+                return;
+            }
 
             // Settings section when visible
             if self.show_settings {
@@ -865,103 +8866,683 @@ impl eframe::App for RedditApp {
                     
                     let settings_width = 400.0;
                     egui::Frame::group(ui.style())
-                        .fill(if self.settings.dark_mode {
-                            egui::Color32::from_rgb(20, 20, 20)
-                        } else {
-                            egui::Color32::from_rgb(240, 240, 240)
-                        })
+                        .fill(self.settings.theme.card)
                         .rounding(8.0)  // Add some rounded corners
                         .show(ui, |ui| {
                             ui.set_width(settings_width);
                             ui.vertical_centered(|ui| {
                                 ui.add_space(20.0);  // Add some padding at the top
-                                if !self.has_credentials {
-                                    ui.heading("Welcome to Rustle!");
-                                    ui.label("To get started, please enter your Reddit API credentials:");
-                                    ui.add_space(10.0);
-                                }
 
                                 let label_width = 100.0;
                                 let input_width = settings_width - label_width - 40.0;
 
-                                // Add theme toggle at the top
+                                // Add theme picker at the top
                                 ui.horizontal(|ui| {
                                     ui.add_sized([label_width, 20.0], egui::Label::new("Theme:"));
-                                    if ui.add_sized([input_width / 2.0, 20.0], 
-                                        egui::SelectableLabel::new(!self.settings.dark_mode, "Light")).clicked() {
-                                        self.settings.dark_mode = false;
+                                    let mut picked = None;
+                                    egui::ComboBox::from_id_source("theme_preset")
+                                        .selected_text(self.settings.theme.name.as_str())
+                                        .width(input_width / 2.0)
+                                        .show_ui(ui, |ui| {
+                                            for preset in theme::Theme::presets() {
+                                                if ui.selectable_label(self.settings.theme == preset, &preset.name).clicked() {
+                                                    picked = Some(preset);
+                                                }
+                                            }
+                                            if !self.installed_themes.is_empty() {
+                                                ui.separator();
+                                            }
+                                            for installed in &self.installed_themes {
+                                                if ui.selectable_label(self.settings.theme == *installed, &installed.name).clicked() {
+                                                    picked = Some(installed.clone());
+                                                }
+                                            }
+                                        });
+                                    if let Some(picked) = picked {
+                                        self.settings.theme = picked;
+                                        self.settings_modified = true;
+                                    }
+                                    let import = ui.button("Import").on_hover_text(match themes_dir() {
+                                        Ok(dir) => format!("Reads the theme files in {}", dir.display()),
+                                        Err(e) => e.to_string(),
+                                    });
+                                    if import.clicked() {
+                                        self.import_themes();
+                                    }
+                                    if ui.button("Export").clicked() {
+                                        self.export_theme();
+                                    }
+                                });
+                                ui.horizontal(|ui| {
+                                    ui.add_space(label_width + ui.spacing().item_spacing.x);
+                                    let theme = &mut self.settings.theme;
+                                    let mut changed = false;
+                                    for (name, color) in [
+                                        ("Accent", &mut theme.accent),
+                                        ("Background", &mut theme.background),
+                                        ("Cards", &mut theme.card),
+                                        ("Text", &mut theme.text),
+                                    ] {
+                                        changed |= ui.color_edit_button_srgba(color).on_hover_text(name).changed();
+                                    }
+                                    changed |= ui.checkbox(&mut theme.dark, "Dark base").changed();
+                                    changed |= ui.add(egui::DragValue::new(&mut theme.rounding)
+                                        .clamp_range(0.0..=16.0).prefix("Corners ")).changed();
+                                    changed |= ui.add(egui::DragValue::new(&mut theme.spacing)
+                                        .clamp_range(0.5..=3.0).speed(0.05).prefix("Spacing ").suffix("×")).changed();
+                                    if changed {
                                         self.settings_modified = true;
                                     }
-                                    if ui.add_sized([input_width / 2.0, 20.0], 
-                                        egui::SelectableLabel::new(self.settings.dark_mode, "Dark")).clicked() {
-                                        self.settings.dark_mode = true;
+                                });
+                                ui.horizontal(|ui| {
+                                    ui.add_space(label_width + ui.spacing().item_spacing.x);
+                                    if ui.checkbox(&mut self.settings.image_accents, "Tint cards with their image's color")
+                                        .on_hover_text("Each card's frame takes the main color of its thumbnail")
+                                        .changed() {
                                         self.settings_modified = true;
                                     }
                                 });
-                                ui.add_space(5.0);
-                                ui.separator();
-                                ui.add_space(5.0);
-
                                 ui.horizontal(|ui| {
-                                    ui.add_sized([label_width, 20.0], egui::Label::new("Client ID:"));
-                                    if ui.add_sized([input_width, 20.0], egui::TextEdit::singleline(&mut self.settings.client_id)).changed() {
+                                    ui.add_space(label_width + ui.spacing().item_spacing.x);
+                                    let mut percent = self.settings.theme.opacity * 100.0;
+                                    if ui.add(egui::Slider::new(&mut percent, 30.0..=100.0).step_by(5.0).suffix("%").text("Window opacity"))
+                                        .on_hover_text("Below 100% the desktop shows through, on systems whose window manager supports it. Switching between see-through and opaque takes a restart.")
+                                        .changed() {
+                                        self.settings.theme.opacity = percent / 100.0;
                                         self.settings_modified = true;
                                     }
                                 });
-
                                 ui.horizontal(|ui| {
-                                    ui.add_sized([label_width, 20.0], egui::Label::new("Client Secret:"));
-                                    if ui.add_sized([input_width, 20.0], 
-                                        egui::TextEdit::singleline(&mut self.settings.client_secret).password(true)).changed() {
+                                    ui.add_sized([label_width, 20.0], egui::Label::new("Text size:"));
+                                    let mut points = theme::BODY_SIZE * self.settings.font_size;
+                                    if ui.add(egui::Slider::new(&mut points, theme::BODY_SIZE * 0.5..=theme::BODY_SIZE * 2.0)
+                                        .step_by(0.5).suffix(" pt")).changed() {
+                                        self.settings.font_size = points / theme::BODY_SIZE;
                                         self.settings_modified = true;
                                     }
                                 });
-
                                 ui.horizontal(|ui| {
-                                    ui.add_sized([label_width, 20.0], egui::Label::new("Username:"));
-                                    if ui.add_sized([input_width, 20.0], egui::TextEdit::singleline(&mut self.settings.username)).changed() {
+                                    ui.add_sized([label_width, 20.0], egui::Label::new("UI scale:"));
+                                    if ui.add(egui::Slider::new(&mut self.settings.ui_scale, 0.5..=3.0)
+                                        .step_by(0.05).suffix("×"))
+                                        .on_hover_text("Applied when you let go; Ctrl+plus and Ctrl+minus also zoom")
+                                        .changed() {
+                                        self.settings_modified = true;
+                                    }
+                                });
+                                ui.horizontal(|ui| {
+                                    ui.add_sized([label_width, 20.0], egui::Label::new("Image cache:"));
+                                    if ui.add(egui::DragValue::new(&mut self.settings.image_cache_mb)
+                                        .clamp_range(10..=5000)
+                                        .suffix(" MB max")).changed() {
+                                        self.settings_modified = true;
+                                    }
+                                    if let Some(cache) = &self.image_cache {
+                                        ui.label(egui::RichText::new(format!("{:.1} MB used", cache.usage() as f64 / (1024.0 * 1024.0))).weak());
+                                        if ui.button("Clear cache").clicked() {
+                                            cache.clear();
+                                        }
+                                    }
+                                });
+                                ui.horizontal(|ui| {
+                                    ui.add_sized([label_width, 20.0], egui::Label::new("Usage stats:"));
+                                    if ui.add_enabled(self.store.is_some(),
+                                        egui::Checkbox::new(&mut self.settings.track_usage, "Keep private usage stats")).changed() {
+                                        self.settings_modified = true;
+                                    }
+                                    if ui.add_enabled(self.store.is_some(), egui::SelectableLabel::new(self.usage_dashboard.is_some(), "Show stats")).clicked() {
+                                        self.usage_dashboard = match self.usage_dashboard {
+                                            Some(_) => None,
+                                            None => Some(usage::Dashboard::default()),
+                                        };
+                                    }
+                                }).response.on_hover_text("Time in the app and posts viewed and opened per subreddit, kept on this machine only");
+                                ui.horizontal(|ui| {
+                                    ui.add_sized([label_width, 20.0], egui::Label::new("Time limits:"));
+                                    let tracking = self.tracking_usage();
+                                    ui.vertical(|ui| {
+                                        ui.add_enabled_ui(tracking, |ui| {
+                                            let mut remove = None;
+                                            for (index, limit) in self.settings.time_limits.iter_mut().enumerate() {
+                                                ui.horizontal(|ui| {
+                                                    let feed = ui.add(egui::TextEdit::singleline(&mut limit.feed)
+                                                        .desired_width(140.0)
+                                                        .hint_text("Whole app"))
+                                                        .on_hover_text("A subreddit, \"home\", or empty for all of Rustle");
+                                                    let minutes = ui.add(egui::DragValue::new(&mut limit.minutes)
+                                                        .clamp_range(1..=1440)
+                                                        .suffix(" min/day"));
+                                                    if feed.changed() || minutes.changed() {
+                                                        self.settings_modified = true;
+                                                    }
+                                                    if ui.button("✖").on_hover_text("Remove limit").clicked() {
+                                                        remove = Some(index);
+                                                    }
+                                                });
+                                            }
+                                            if let Some(index) = remove {
+                                                self.settings.time_limits.remove(index);
+                                                self.settings_modified = true;
+                                            }
+                                            if ui.button("Add limit").on_hover_text("A reminder to take a break once the day's time is up").clicked() {
+                                                self.settings.time_limits.push(focus::TimeLimit::default());
+                                                self.settings_modified = true;
+                                            }
+                                        });
+                                        if !tracking {
+                                            ui.label(egui::RichText::new("Needs usage stats turned on").weak());
+                                        }
+                                    });
+                                });
+                                ui.horizontal(|ui| {
+                                    ui.add_sized([label_width, 20.0], egui::Label::new("Debugging:"));
+                                    if ui.selectable_label(self.inspector.is_some(), "Request inspector")
+                                        .on_hover_text("Recent API calls and image loads (Ctrl+Shift+I)")
+                                        .clicked() {
+                                        self.toggle_inspector();
+                                    }
+                                    if ui.button("Create bug report bundle")
+                                        .on_hover_text("Zips up this session's errors and requests, with secrets removed, to attach to a GitHub issue")
+                                        .clicked() {
+                                        self.create_bug_report();
+                                    }
+                                });
+                                ui.horizontal(|ui| {
+                                    ui.add_sized([label_width, 20.0], egui::Label::new("Cards:"));
+                                    for (name, preset) in CardLayout::PRESETS {
+                                        if ui.selectable_label(self.settings.card_layout == preset, name).clicked() {
+                                            self.settings.card_layout = preset;
+                                            self.settings_modified = true;
+                                        }
+                                    }
+                                });
+                                ui.horizontal(|ui| {
+                                    ui.add_space(label_width + ui.spacing().item_spacing.x);
+                                    let layout = &mut self.settings.card_layout;
+                                    let mut changed = false;
+                                    changed |= ui.add(egui::DragValue::new(&mut layout.padding)
+                                        .clamp_range(0.0..=30.0).prefix("Padding ")).changed();
+                                    changed |= ui.add(egui::DragValue::new(&mut layout.image_size)
+                                        .clamp_range(32.0..=240.0).prefix("Images ")).changed();
+                                    changed |= ui.add(egui::DragValue::new(&mut layout.spacing)
+                                        .clamp_range(0.0..=40.0).prefix("Spacing ")).changed();
+                                    if changed {
+                                        self.settings_modified = true;
+                                    }
+                                });
+                                ui.horizontal(|ui| {
+                                    ui.add_space(label_width + ui.spacing().item_spacing.x);
+                                    ui.label("Buttons:");
+                                    let actions = &mut self.settings.card_actions;
+                                    let mut changed = false;
+                                    for (enabled, name) in [
+                                        (&mut actions.vote, "Vote"),
+                                        (&mut actions.save, "Save"),
+                                        (&mut actions.hide, "Hide"),
+                                        (&mut actions.share, "Share"),
+                                        (&mut actions.comments, "Comments"),
+                                    ] {
+                                        changed |= ui.checkbox(enabled, name).changed();
+                                    }
+                                    if changed {
+                                        self.settings_modified = true;
+                                    }
+                                }).response.on_hover_text("Unchecked actions are still in the card's right-click menu");
+                                ui.horizontal(|ui| {
+                                    ui.add_space(label_width + ui.spacing().item_spacing.x);
+                                    let heat = ui.checkbox(&mut self.settings.heat_colors, "Color scores and comment counts by popularity")
+                                        .on_hover_text("Blue for a quiet post, orange for one far above what the subreddit usually sees");
+                                    if heat.changed() {
+                                        self.settings_modified = true;
+                                    }
+                                });
+                                ui.horizontal(|ui| {
+                                    ui.add_sized([label_width, 20.0], egui::Label::new("Timestamps:"));
+                                    if ui.checkbox(&mut self.settings.absolute_timestamps, "Always show the full date and time").changed() {
+                                        self.settings_modified = true;
+                                    }
+                                });
+                                ui.horizontal(|ui| {
+                                    ui.add_sized([label_width, 20.0], egui::Label::new("NSFW:"));
+                                    if ui.checkbox(&mut self.settings.show_nsfw, "Show NSFW content").changed() {
+                                        self.settings_modified = true;
+                                    }
+                                    if ui.add_enabled(self.settings.show_nsfw,
+                                        egui::Checkbox::new(&mut self.settings.blur_nsfw, "Blur NSFW thumbnails")).changed() {
+                                        self.settings_modified = true;
+                                    }
+                                    if ui.add_enabled(self.settings.show_nsfw,
+                                        egui::Checkbox::new(&mut self.settings.label_nsfw, "Label NSFW posts")).changed() {
                                         self.settings_modified = true;
                                     }
                                 });
-
                                 ui.horizontal(|ui| {
-                                    ui.add_sized([label_width, 20.0], egui::Label::new("Password:"));
-                                    if ui.add_sized([input_width, 20.0], 
-                                        egui::TextEdit::singleline(&mut self.settings.password).password(true)).changed() {
+                                    ui.add_sized([label_width, 20.0], egui::Label::new("Auto-refresh:"));
+                                    for minutes in AUTO_REFRESH_MINUTES {
+                                        let label = if minutes == 0 { "Off".to_string() } else { format!("{} min", minutes) };
+                                        if ui.selectable_value(&mut self.settings.auto_refresh_minutes, minutes, label).changed() {
+                                            self.settings_modified = true;
+                                        }
+                                    }
+                                }).response.on_hover_text("Check the feed for new posts in the background");
+                                ui.horizontal(|ui| {
+                                    ui.add_sized([label_width, 20.0], egui::Label::new("Paging:"));
+                                    if ui.add(egui::DragValue::new(&mut self.settings.page_size)
+                                        .clamp_range(10..=100)
+                                        .suffix(" posts per page"))
+                                        .on_hover_text("Bigger pages mean fewer API requests, each taking longer")
+                                        .changed() {
+                                        self.settings_modified = true;
+                                    }
+                                    if ui.add(egui::DragValue::new(&mut self.settings.load_ahead)
+                                        .clamp_range(1..=100)
+                                        .prefix("load more ")
+                                        .suffix(" posts from the end"))
+                                        .on_hover_text("Fast scrollers can raise this so the next page is ready before they reach it")
+                                        .changed() {
+                                        self.settings_modified = true;
+                                    }
+                                });
+                                ui.horizontal(|ui| {
+                                    ui.add_sized([label_width, 20.0], egui::Label::new("Articles:"));
+                                    ui.vertical(|ui| {
+                                        if ui.checkbox(&mut self.settings.reader_mode, "Open article links in the reader")
+                                            .on_hover_text("News and blog links open as clean text inside Rustle; right-click a post for the other way")
+                                            .changed() {
+                                            self.settings_modified = true;
+                                        }
+                                        let mut domains: Vec<(String, reader::LinkOpen)> = self.settings.link_opening.iter()
+                                            .map(|(domain, choice)| (domain.clone(), *choice))
+                                            .collect();
+                                        domains.sort_by(|a, b| a.0.cmp(&b.0));
+                                        for (domain, choice) in domains {
+                                            ui.horizontal(|ui| {
+                                                let place = if choice == reader::LinkOpen::Reader { "reader" } else { "browser" };
+                                                ui.label(format!("{} always opens in the {}", domain, place));
+                                                if ui.small_button("✖").on_hover_text("Go back to the default").clicked() {
+                                                    self.settings.link_opening.remove(&domain);
+                                                    self.settings_modified = true;
+                                                }
+                                            });
+                                        }
+                                    });
+                                });
+                                ui.horizontal(|ui| {
+                                    ui.add_sized([label_width, 20.0], egui::Label::new("Open with:"));
+                                    ui.vertical(|ui| {
+                                        for content in open_with::Content::ALL {
+                                            ui.horizontal(|ui| {
+                                                ui.add_sized([110.0, 20.0], egui::Label::new(content.label()));
+                                                let opener = self.settings.open_with.get_mut(content);
+                                                let before = opener.clone();
+                                                egui::ComboBox::from_id_source(("open_with", content.label()))
+                                                    .selected_text(opener.label(content))
+                                                    .show_ui(ui, |ui| {
+                                                        for choice in [open_with::Opener::Automatic, open_with::Opener::Internal, open_with::Opener::Browser] {
+                                                            let label = choice.label(content);
+                                                            ui.selectable_value(opener, choice, label);
+                                                        }
+                                                        let command = matches!(opener, open_with::Opener::Command(_));
+                                                        if ui.selectable_label(command, "Program…").clicked() && !command {
+                                                            *opener = open_with::Opener::Command(String::new());
+                                                        }
+                                                    })
+                                                    .response
+                                                    .on_hover_text("Automatic opens articles that look readable in the reader and everything else in the browser");
+                                                if let open_with::Opener::Command(command) = opener {
+                                                    ui.add(egui::TextEdit::singleline(command)
+                                                        .hint_text("e.g. firefox, or mpv --loop {url}")
+                                                        .desired_width(200.0));
+                                                }
+                                                if *opener != before {
+                                                    self.settings_modified = true;
+                                                }
+                                            });
+                                        }
+                                    });
+                                });
+                                ui.horizontal(|ui| {
+                                    ui.add_sized([label_width, 20.0], egui::Label::new("Translation:"));
+                                    let url = ui.add(egui::TextEdit::singleline(&mut self.settings.translation_url)
+                                        .hint_text("LibreTranslate server URL")
+                                        .desired_width(220.0))
+                                        .on_hover_text("Shows machine-translated glosses beside flairs, subreddit titles and rules in other languages. Only that text goes to this server; nothing is sent to Reddit.");
+                                    let into = ui.add(egui::TextEdit::singleline(&mut self.settings.translate_to)
+                                        .hint_text("en")
+                                        .desired_width(32.0))
+                                        .on_hover_text("Language code to translate into");
+                                    let key = ui.add(egui::TextEdit::singleline(&mut self.settings.translation_key)
+                                        .hint_text("API key, if needed")
+                                        .password(true)
+                                        .desired_width(140.0));
+                                    if url.changed() || into.changed() || key.changed() {
+                                        self.settings_modified = true;
+                                    }
+                                });
+                                ui.horizontal(|ui| {
+                                    ui.add_sized([label_width, 20.0], egui::Label::new("Archive:"));
+                                    self.render_archive_settings(ui);
+                                });
+                                ui.horizontal(|ui| {
+                                    ui.add_sized([label_width, 20.0], egui::Label::new("On launch:"));
+                                    let refresh = &mut self.settings.startup_refresh;
+                                    let mut changed = ui.selectable_value(refresh, StartupRefresh::Immediately, "Refresh").changed();
+                                    changed |= ui.selectable_value(refresh, StartupRefresh::Manual, "Keep cached").changed();
+                                    let stale = matches!(refresh, StartupRefresh::WhenStale(_));
+                                    if ui.selectable_label(stale, "If older than").clicked() && !stale {
+                                        *refresh = StartupRefresh::WhenStale(60);
+                                        changed = true;
+                                    }
+                                    if let StartupRefresh::WhenStale(minutes) = refresh {
+                                        changed |= ui.add(egui::DragValue::new(minutes).clamp_range(1..=1440).suffix(" min")).changed();
+                                    }
+                                    if changed {
+                                        self.settings_modified = true;
+                                    }
+                                }).response.on_hover_text("Whether the feed saved from last time is fetched again when Rustle starts");
+                                if self.settings.signed_in() {
+                                    ui.horizontal(|ui| {
+                                        ui.add_sized([label_width, 20.0], egui::Label::new("Inbox check:"));
+                                        for minutes in INBOX_CHECK_MINUTES {
+                                            let label = if minutes == 0 { "Off".to_string() } else { format!("{} min", minutes) };
+                                            if ui.selectable_value(&mut self.settings.inbox_check_minutes, minutes, label).changed() {
+                                                self.settings_modified = true;
+                                            }
+                                        }
+                                    }).response.on_hover_text(if cfg!(feature = "notifications") {
+                                        "Look for unread messages in the background, with a desktop notification and a tray icon"
+                                    } else {
+                                        "Look for unread messages in the background and show how many are waiting"
+                                    });
+                                }
+                                ui.horizontal(|ui| {
+                                    ui.add_sized([label_width, 20.0], egui::Label::new("Undo send:"));
+                                    for seconds in UNDO_SEND_SECONDS {
+                                        let label = if seconds == 0 { "Off".to_string() } else { format!("{}s", seconds) };
+                                        if ui.selectable_value(&mut self.settings.undo_send_seconds, seconds, label).changed() {
+                                            self.settings_modified = true;
+                                        }
+                                    }
+                                }).response.on_hover_text("Hold replies and posts this long before sending them, with a chance to undo");
+                                ui.horizontal(|ui| {
+                                    ui.add_sized([label_width, 20.0], egui::Label::new("Comment sort:"));
+                                    let selected = COMMENT_SORTS.iter()
+                                        .find(|(sort, _)| *sort == self.settings.comment_sort)
+                                        .map_or("Best", |(_, label)| *label);
+                                    egui::ComboBox::from_id_source("comment_sort")
+                                        .selected_text(selected)
+                                        .show_ui(ui, |ui| {
+                                            for (sort, label) in COMMENT_SORTS {
+                                                if ui.selectable_value(&mut self.settings.comment_sort, sort.to_string(), label).changed() {
+                                                    self.settings_modified = true;
+                                                }
+                                            }
+                                        });
+                                });
+                                ui.horizontal(|ui| {
+                                    ui.add_sized([label_width, 20.0], egui::Label::new("Ignored users:"));
+                                    ui.horizontal_wrapped(|ui| {
+                                        let mut unignore = None;
+                                        for name in &self.settings.ignored_users {
+                                            if ui.button(format!("u/{} ✖", name)).on_hover_text("Stop ignoring").clicked() {
+                                                unignore = Some(name.clone());
+                                            }
+                                        }
+                                        if let Some(name) = unignore {
+                                            self.settings.ignored_users.retain(|ignored| *ignored != name);
+                                            self.settings_modified = true;
+                                        }
+                                        let blocked = self.blocked_users.lock().unwrap().len();
+                                        if self.settings.ignored_users.is_empty() && blocked == 0 {
+                                            ui.label(egui::RichText::new("None").weak());
+                                        } else if blocked > 0 {
+                                            ui.label(egui::RichText::new(format!("+ {} blocked on reddit.com", blocked)).weak());
+                                        }
+                                    });
+                                });
+                                ui.horizontal(|ui| {
+                                    ui.add_sized([label_width, 20.0], egui::Label::new("Filters:"));
+                                    ui.vertical(|ui| {
+                                        let mut remove = None;
+                                        for (index, rule) in self.settings.filters.iter_mut().enumerate() {
+                                            ui.horizontal(|ui| {
+                                                if ui.checkbox(&mut rule.enabled, "").on_hover_text("Apply this rule").changed() {
+                                                    self.settings_modified = true;
+                                                }
+                                                egui::ComboBox::from_id_source(("filter_kind", index))
+                                                    .width(80.0)
+                                                    .selected_text(rule.kind.label())
+                                                    .show_ui(ui, |ui| {
+                                                        for kind in filters::RuleKind::ALL {
+                                                            if ui.selectable_value(&mut rule.kind, kind, kind.label()).changed() {
+                                                                self.settings_modified = true;
+                                                            }
+                                                        }
+                                                    });
+                                                egui::ComboBox::from_id_source(("filter_action", index))
+                                                    .width(80.0)
+                                                    .selected_text(rule.action.label())
+                                                    .show_ui(ui, |ui| {
+                                                        for action in filters::RuleAction::ALL {
+                                                            if ui.selectable_value(&mut rule.action, action, action.label()).changed() {
+                                                                self.settings_modified = true;
+                                                            }
+                                                        }
+                                                    })
+                                                    .response.on_hover_text("Hide matching posts, or outline them in the feed");
+                                                let pattern = ui.add(egui::TextEdit::singleline(&mut rule.pattern)
+                                                    .desired_width(180.0)
+                                                    .hint_text(rule.kind.hint()));
+                                                let subreddit = ui.add(egui::TextEdit::singleline(&mut rule.subreddit)
+                                                    .desired_width(120.0)
+                                                    .hint_text("All subreddits"))
+                                                    .on_hover_text("Only filter posts from this subreddit");
+                                                if pattern.changed() || subreddit.changed() {
+                                                    self.settings_modified = true;
+                                                }
+                                                if ui.button("✖").on_hover_text("Remove filter").clicked() {
+                                                    remove = Some(index);
+                                                }
+                                            });
+                                            if let Some(error) = rule.error() {
+                                                ui.colored_label(ui.visuals().error_fg_color, format!("Invalid regex: {}", error.lines().last().unwrap_or_default()));
+                                            }
+                                        }
+                                        if let Some(index) = remove {
+                                            self.settings.filters.remove(index);
+                                            self.settings_modified = true;
+                                        }
+                                        ui.horizontal(|ui| {
+                                            if ui.button("Add filter").on_hover_text("Hide or highlight posts by keyword, regex, flair or link domain").clicked() {
+                                                self.settings.filters.push(FilterRule::default());
+                                                self.settings_modified = true;
+                                            }
+                                            if ui.add_enabled(!self.settings.filters.is_empty(), egui::Button::new("Export…"))
+                                                .on_hover_text("Save these rules to a file to share").clicked() {
+                                                self.export_rules();
+                                            }
+                                            if ui.button("Import…").on_hover_text("Add rules from a shared file").clicked() {
+                                                self.pick_rule_file();
+                                            }
+                                        });
+                                        let import = self.rule_import.lock().unwrap().clone();
+                                        if let Some(filters::Import { file, rules }) = import {
+                                            ui.horizontal(|ui| {
+                                                ui.label(format!("{} rule{} in {}:", rules.len(), if rules.len() == 1 { "" } else { "s" }, file));
+                                                let mut done = false;
+                                                if ui.button("Merge").on_hover_text("Add the ones you don't have yet").clicked() {
+                                                    let added = filters::merge(&mut self.settings.filters, rules.clone());
+                                                    self.notice = Some((format!("Added {} new rules", added), Instant::now() + NOTICE_DURATION));
+                                                    done = true;
+                                                }
+                                                if ui.button("Replace").on_hover_text("Use these instead of your rules").clicked() {
+                                                    self.settings.filters = rules;
+                                                    done = true;
+                                                }
+                                                let cancelled = ui.button("Cancel").clicked();
+                                                if done {
+                                                    self.settings_modified = true;
+                                                }
+                                                if done || cancelled {
+                                                    *self.rule_import.lock().unwrap() = None;
+                                                }
+                                            });
+                                        }
+                                    });
+                                });
+                                ui.horizontal(|ui| {
+                                    ui.add_sized([label_width, 20.0], egui::Label::new("Snippets:"));
+                                    ui.vertical(|ui| {
+                                        let mut remove = None;
+                                        for (index, snippet) in self.settings.snippets.iter_mut().enumerate() {
+                                            ui.horizontal(|ui| {
+                                                let name = ui.add(egui::TextEdit::singleline(&mut snippet.name)
+                                                    .desired_width(120.0)
+                                                    .hint_text("Name"));
+                                                let text = ui.add(egui::TextEdit::multiline(&mut snippet.text)
+                                                    .desired_rows(2)
+                                                    .desired_width(260.0)
+                                                    .hint_text("Text to insert"));
+                                                if name.changed() || text.changed() {
+                                                    self.settings_modified = true;
+                                                }
+                                                if ui.button("✖").on_hover_text("Remove snippet").clicked() {
+                                                    remove = Some(index);
+                                                }
+                                            });
+                                        }
+                                        if let Some(index) = remove {
+                                            self.settings.snippets.remove(index);
+                                            self.settings_modified = true;
+                                        }
+                                        if ui.button("Add snippet").on_hover_text("Insert from the reply box's Snippets menu, or type / and its name").clicked() {
+                                            self.settings.snippets.push(Snippet::default());
+                                            self.settings_modified = true;
+                                        }
+                                    });
+                                });
+                                ui.horizontal(|ui| {
+                                    ui.add_sized([label_width, 20.0], egui::Label::new("Spellcheck:"));
+                                    let choice = |language: &str| match language {
+                                        "" => format!("System language ({})", spellcheck::system_language()),
+                                        spellcheck::OFF => "Off".to_string(),
+                                        language => language.to_string(),
+                                    };
+                                    let installed = self.speller.installed();
+                                    egui::ComboBox::from_id_source("spell_language")
+                                        .selected_text(choice(&self.settings.account.spell_language))
+                                        .show_ui(ui, |ui| {
+                                            for language in ["", spellcheck::OFF].into_iter().chain(installed.iter().map(String::as_str)) {
+                                                if ui.selectable_value(&mut self.settings.account.spell_language, language.to_string(), choice(language)).changed() {
+                                                    self.settings_modified = true;
+                                                }
+                                            }
+                                        })
+                                        .response
+                                        .on_hover_text("Dictionary for the reply box, kept per account");
+                                    if let Some(language) = self.settings.account.spelling_language().filter(|language| !installed.contains(language)) {
+                                        let folder = spellcheck::search_path().first().map(|dir| dir.display().to_string()).unwrap_or_default();
+                                        ui.label(egui::RichText::new(format!("No {} dictionary found", language)).weak())
+                                            .on_hover_text(format!("Put hunspell's {0}.dic and {0}.aff in {1}", language, folder));
+                                    }
+                                });
+                                ui.horizontal(|ui| {
+                                    ui.add_sized([label_width, 20.0], egui::Label::new("Preferences:"));
+                                    if ui.checkbox(&mut self.settings.local_prefs_only, "Ignore reddit.com account preferences")
+                                        .on_hover_text("When off, NSFW and comment sort settings follow your Reddit account each time you sign in")
+                                        .changed() {
+                                        self.settings_modified = true;
+                                    }
+                                });
+                                ui.horizontal(|ui| {
+                                    ui.add_space(label_width + ui.spacing().item_spacing.x);
+                                    if ui.checkbox(&mut self.settings.sync_visits, "Treat posts opened on other devices as read")
+                                        .on_hover_text("Uses Reddit's visit history, which is only kept for Premium accounts with history sync on")
+                                        .changed() {
+                                        self.settings_modified = true;
+                                    }
+                                });
+                                ui.horizontal(|ui| {
+                                    ui.add_sized([label_width, 20.0], egui::Label::new("Animations:"));
+                                    if ui.checkbox(&mut self.settings.stills_only, "Show stills only").changed() {
+                                        self.settings_modified = true;
+                                    }
+                                    let autoplay = ui.add_enabled(!self.settings.stills_only,
+                                        egui::Checkbox::new(&mut self.settings.autoplay_animations, "Play automatically"));
+                                    if autoplay.on_hover_text("GIFs in wide and expanded cards start on their own. When off, they wait for ▶.").changed() {
                                         self.settings_modified = true;
                                     }
+                                    #[cfg(feature = "video")]
+                                    {
+                                        let hover = ui.add_enabled(!self.settings.stills_only,
+                                            egui::Checkbox::new(&mut self.settings.hover_previews, "Preview on hover"));
+                                        if hover.on_hover_text("Loop a muted clip while the pointer rests on a video or GIF thumbnail. Turn off to save data.").changed() {
+                                            self.settings_modified = true;
+                                        }
+                                    }
+                                });
+                                ui.add_space(5.0);
+                                ui.separator();
+                                ui.add_space(5.0);
+
+                                ui.horizontal(|ui| {
+                                    ui.add_sized([label_width, 20.0], egui::Label::new("Account:"));
+                                    ui.label(match self.settings.account.auth_method {
+                                        AuthMethod::Script => "Script app".to_string(),
+                                        AuthMethod::Browser => format!("u/{} (signed in through the browser)", self.settings.account.username),
+                                        AuthMethod::Anonymous => "Browsing without an account".to_string(),
+                                    });
+                                    if ui.button("Change...").on_hover_text("Run the setup wizard again").clicked() {
+                                        // Unsaved edits here are dropped, as with Cancel
+                                        self.settings = Settings::load();
+                                        self.onboarding = Some(onboarding::Wizard::new(self.settings.clone(), true));
+                                        self.settings_modified = false;
+                                        self.show_settings = false;
+                                    }
                                 });
 
-                                ui.add_space(10.0);
-                                if !self.has_credentials {
-                                    ui.label("You can get your Reddit API credentials by:");
-                                    ui.label("1. Going to https://www.reddit.com/prefs/apps");
-                                    ui.label("2. Scrolling to the bottom and clicking 'create another app...'");
-                                    ui.label("3. Selecting 'script' and filling in the required information");
-            ui.add_space(10.0);
+                                if self.settings.account.auth_method == AuthMethod::Script {
+                                    ui.horizontal(|ui| {
+                                        ui.add_sized([label_width, 20.0], egui::Label::new("Client ID:"));
+                                        if ui.add_sized([input_width, 20.0], egui::TextEdit::singleline(&mut self.settings.account.client_id)).changed() {
+                                            self.settings_modified = true;
+                                        }
+                                    });
+
+                                    ui.horizontal(|ui| {
+                                        ui.add_sized([label_width, 20.0], egui::Label::new("Client Secret:"));
+                                        if ui.add_sized([input_width, 20.0], 
+                                            egui::TextEdit::singleline(&mut self.settings.account.client_secret).password(true)).changed() {
+                                            self.settings_modified = true;
+                                        }
+                                    });
+
+                                    ui.horizontal(|ui| {
+                                        ui.add_sized([label_width, 20.0], egui::Label::new("Username:"));
+                                        if ui.add_sized([input_width, 20.0], egui::TextEdit::singleline(&mut self.settings.account.username)).changed() {
+                                            self.settings_modified = true;
+                                        }
+                                    });
+
+                                    ui.horizontal(|ui| {
+                                        ui.add_sized([label_width, 20.0], egui::Label::new("Password:"));
+                                        if ui.add_sized([input_width, 20.0], 
+                                            egui::TextEdit::singleline(&mut self.settings.account.password).password(true)).changed() {
+                                            self.settings_modified = true;
+                                        }
+                                    });
                                 }
+
+                                ui.add_space(10.0);
                                 ui.horizontal(|ui| {
                                     ui.with_layout(egui::Layout::right_to_left(egui::Align::RIGHT), |ui| {
-                                        if self.has_credentials {
-                                            if ui.button("Cancel").clicked() {
-                                                self.settings = Settings::load();
-                                                self.settings_modified = false;
-                                                self.show_settings = false;
-                                            }
+                                        if self.has_credentials && ui.button("Cancel").clicked() {
+                                            self.settings = Settings::load();
+                                            self.settings_modified = false;
+                                            self.show_settings = false;
                                         }
                                         if ui.button("Save").clicked() {
-                                            if let Err(e) = self.settings.save() {
-                                                *self.error_message.lock().unwrap() = Some(format!("Failed to save settings: {}", e));
-                                            } else {
-                                                self.settings_modified = false;
-                                                self.show_settings = false;
-                                                self.has_credentials = true;
-                                                *self.error_message.lock().unwrap() = None;
-                                                *self.loading.lock().unwrap() = true;
-                                                *self.initial_load.lock().unwrap() = true;
-                                                *self.scroll_to_top.lock().unwrap() = true;
-                                                self.authenticate_and_load();
-                                            }
+                                            self.save_settings_and_sign_in();
                                         }
                                     });
                                 });
@@ -972,6 +9553,54 @@ impl eframe::App for RedditApp {
                 return;  // Don't show posts while settings are open
             }
             
+            let auth_failure = self.error_message.lock().unwrap().clone();
+            if let Some(RustleError::Auth { failure, message }) = auth_failure {
+                self.render_auth_failure(ui, failure, &message);
+                return;
+            }
+
+            if self.lightbox.is_some() {
+                self.render_lightbox(ui);
+                return;
+            }
+
+            if self.reader.lock().unwrap().is_some() {
+                self.render_reader(ui);
+                return;
+            }
+
+            // Comment thread replaces the feed while open
+            let open_post = self.open_post.lock().unwrap().clone();
+            if let Some(post) = open_post {
+                self.render_comments(ui, &post);
+                return;
+            }
+            if self.profile.lock().unwrap().is_some() {
+                self.render_profile(ui);
+                return;
+            }
+            if self.discover.lock().unwrap().is_some() {
+                self.render_discover(ui);
+                return;
+            }
+            if self.history.lock().unwrap().is_some() {
+                self.render_history(ui);
+                return;
+            }
+            let restricted = self.error_message.lock().unwrap().clone();
+            if let Some(RustleError::Restricted { subreddit, restriction }) = restricted {
+                // Only while still on that subreddit; it's forgotten once the reader moves on
+                if self.current_subreddit.lock().unwrap().eq_ignore_ascii_case(&subreddit) {
+                    self.render_restricted(ui, &subreddit, &restriction);
+                    return;
+                }
+                *self.error_message.lock().unwrap() = None;
+            }
+
+            if !self.selection.lock().unwrap().names.is_empty() {
+                self.render_selection_toolbar(ui);
+            }
+
             // Main content
             let initial_load = *self.initial_load.lock().unwrap();
             
@@ -997,60 +9626,66 @@ impl eframe::App for RedditApp {
                         .auto_shrink([false; 2]);
                     
                     // Reset scroll position if needed
+                    let scroll_return = self.feed_scroll_return.lock().unwrap().take();
                     if *self.scroll_to_top.lock().unwrap() {
                         scroll_area = scroll_area.scroll_offset(egui::vec2(0.0, 0.0));
                         *self.scroll_to_top.lock().unwrap() = false;
+                    } else if let Some(offset) = scroll_return {
+                        scroll_area = scroll_area.vertical_scroll_offset(offset);
+                    }
+
+                    let mut scrolled_past = Vec::new();
+                    let mut last_visible = None;
+                    // Newest-first feeds get a header wherever the day bucket changes
+                    let by_new = self.current_view.lock().unwrap().as_ref().is_some_and(|view| view.sort == "new");
+                    let today_start = self.today_start.map_or(0, |(today_start, _)| today_start);
+                    let mut section = None;
+                    let density = self.density();
+                    let mut rows = self.feed_rows.lock().unwrap();
+                    let measured_for = (ui.available_width(), density, self.settings.card_layout, self.settings.font_size);
+                    if rows.measured_for != Some(measured_for) {
+                        rows.heights.clear();
+                        rows.measured_for = Some(measured_for);
                     }
+                    let estimate = density.estimated_height(self.settings.card_layout, self.settings.font_size);
 
-                    scroll_area.show(ui, |ui| {
-                        for post in posts.iter() {
-                            self.render_post(ui, post);
-                        }
-                        
-                        // Check if we're near the bottom and should load more
-                        let rect = ui.clip_rect();
-                        let max_rect = ui.max_rect();
-                        
-                        // Get current scroll position from the scroll area
-                        let scroll_y = ui.clip_rect().top() - ui.min_rect().top();
-                        let mut last_scroll_pos = self.last_scroll_pos.lock().unwrap();
-                        
-                        // Calculate how far we are from the bottom
-                        let distance_from_bottom = max_rect.bottom() - rect.bottom();
-                        
-                        // Load more posts when we're within 1500px of the bottom
-                        // This is much earlier than before to ensure posts are preloaded
-                        if !loading && 
-                           distance_from_bottom < 1500.0 && 
-                           !*self.is_loading_more.lock().unwrap() {
-                            
-                            // Mark that we're loading more posts
-                            *self.is_loading_more.lock().unwrap() = true;
-                            
-                            // Make sure we have the current after token before loading more
-                            let current_after = self.after.lock().unwrap().clone();
-                            if current_after.is_none() {
-                                // If after is None but we have posts, something is wrong
-                                // Reset the after token to ensure we don't replace existing posts
-                                if !posts.is_empty() {
-                                    *self.after.lock().unwrap() = Some("".to_string());
-                                }
+                    // Only cards intersecting the viewport are laid out; the rest are stand-in
+                    // spaces sized from the height each card had the last time it was drawn
+                    let output = scroll_area.show_viewport(ui, |ui, viewport| {
+                        ui.set_width(ui.available_width());
+                        let spacing = ui.spacing().item_spacing.y;
+                        let mut y = 0.0;
+                        for (index, post) in posts.iter().enumerate() {
+                            if self.is_filtered(post) {
+                                continue;
                             }
-                            
-                            self.load_more_posts();
-                            
-                            // Schedule a delayed reset of the loading more flag
-                            let is_loading_more = self.is_loading_more.clone();
-                            let repaint_after = std::time::Duration::from_millis(500);
-                            std::thread::spawn(move || {
-                                std::thread::sleep(repaint_after);
-                                *is_loading_more.lock().unwrap() = false;
-                            });
+                            let header = by_new.then(|| time_section(post.created_utc, today_start))
+                                .filter(|current| section != Some(*current));
+                            if header.is_some() {
+                                section = header;
+                            }
+                            let mut height = rows.heights.get(&post.name).copied().unwrap_or(estimate);
+                            if y + height < viewport.min.y || y > viewport.max.y {
+                                ui.allocate_space(egui::vec2(ui.available_width(), height));
+                            } else {
+                                height = ui.scope(|ui| {
+                                    if let Some(header) = header {
+                                        ui.add_space(6.0);
+                                        ui.label(egui::RichText::new(header).size(12.0 * self.settings.font_size).strong().weak());
+                                        ui.separator();
+                                    }
+                                    self.render_post(ui, post);
+                                }).response.rect.height();
+                                rows.heights.insert(post.name.clone(), height);
+                                last_visible = Some(index);
+                            }
+                            if y + height < viewport.min.y && !rows.passed.contains(&post.name) {
+                                rows.passed.insert(post.name.clone());
+                                scrolled_past.push(post.name.clone());
+                            }
+                            y += height + spacing;
                         }
-                        
-                        // Update the last scroll position
-                        *last_scroll_pos = scroll_y;
-                        
+
                         // Show a small loading indicator at the bottom while loading more posts
                         if loading && !initial_load {
                             ui.vertical_centered(|ui| {
@@ -1060,13 +9695,64 @@ impl eframe::App for RedditApp {
                             });
                         }
                     });
+                    drop(rows);
+                    *self.last_scroll_pos.lock().unwrap() = output.state.offset.y;
+                    let (wants_more, wants_prefetch) = match loading {
+                        true => (false, false),
+                        false => page_triggers(last_visible, posts.len(), self.settings.load_ahead, self.settings.page_size),
+                    };
+
+                    // Pagination appends to the post list, so release our lock on it first
+                    drop(posts);
+                    if !scrolled_past.is_empty() {
+                        self.mark_read(&scrolled_past);
+                    }
+                    let flair_request = self.flair_request.lock().unwrap().take();
+                    if let Some((subreddit, flair)) = flair_request {
+                        self.filter_by_flair(subreddit, flair);
+                    }
+                    let selection_click = self.selection_click.lock().unwrap().take();
+                    if let Some((name, range)) = selection_click {
+                        let order: Vec<String> = self.posts.lock().unwrap().iter().map(|post| post.name.clone()).collect();
+                        self.selection.lock().unwrap().click(&name, range, &order);
+                    }
+                    if wants_more {
+                        self.load_more_posts();
+                    }
+                    if wants_prefetch {
+                        self.prefetch_next_page();
+                    }
                 }
             }
         });
+        let subreddit_request = self.subreddit_request.lock().unwrap().take();
+        if let Some(subreddit) = subreddit_request {
+            self.switch_subreddit(subreddit);
+        }
+        let viewer_request = self.viewer_request.lock().unwrap().take();
+        if let Some(post) = viewer_request {
+            self.lightbox = lightbox::Lightbox::new(vec![post]);
+        }
+        self.render_undo_toasts(ctx);
+        self.hints.lock().unwrap().show(ctx);
     }
 
     fn save(&mut self, storage: &mut dyn eframe::Storage) {
-        eframe::set_value(storage, eframe::APP_KEY, self);
+        self.flush_usage();
+        // Waited on so the last few marks aren't lost when this is the save on exit
+        if let Some(saving) = self.save_read_posts() {
+            let _ = saving.join();
+        }
+        if self.demo || replay::replaying() {
+            return;  // Sample and replayed posts aren't worth restoring next launch
+        }
+        let cached = CachedFeed {
+            subreddit: self.current_subreddit.lock().unwrap().clone(),
+            view: self.current_view.lock().unwrap().clone(),
+            posts: self.posts.lock().unwrap().clone(),
+            after: self.after.lock().unwrap().clone(),
+        };
+        eframe::set_value(storage, FEED_CACHE_KEY, &cached);
     }
 
     fn auto_save_interval(&self) -> std::time::Duration {
@@ -1074,85 +9760,21 @@ impl eframe::App for RedditApp {
     }
 }
 
-// Add serde support for RedditApp
-impl serde::Serialize for RedditApp {
-    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
-    where
-        S: serde::Serializer,
-    {
-        use serde::ser::SerializeStruct;
-        let mut state = serializer.serialize_struct("RedditApp", 2)?;
-        state.serialize_field("settings", &self.settings)?;
-        state.end()
-    }
-}
-
-impl<'de> serde::Deserialize<'de> for RedditApp {
-    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
-    where
-        D: serde::Deserializer<'de>,
-    {
-        #[derive(Deserialize)]
-        #[serde(field_identifier, rename_all = "snake_case")]
-        enum Field { Settings }
-
-        struct RedditAppVisitor;
-
-        impl<'de> serde::de::Visitor<'de> for RedditAppVisitor {
-            type Value = RedditApp;
-
-            fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
-                formatter.write_str("struct RedditApp")
-            }
-
-            fn visit_map<V>(self, mut map: V) -> Result<RedditApp, V::Error>
-            where
-                V: serde::de::MapAccess<'de>,
-            {
-                let mut settings = None;
-                while let Some(key) = map.next_key()? {
-                    match key {
-                        Field::Settings => {
-                            settings = Some(map.next_value()?);
-                        }
-                    }
-                }
-
-                let settings = settings.unwrap_or_else(Settings::load);
-                let has_credentials = !settings.client_id.is_empty() 
-                    && !settings.client_secret.is_empty()
-                    && !settings.username.is_empty()
-                    && !settings.password.is_empty();
-
-                Ok(RedditApp {
-                    posts: Arc::new(Mutex::new(Vec::new())),
-                    loading: Arc::new(Mutex::new(has_credentials)),
-                    error_message: Arc::new(Mutex::new(None)),
-                    reddit_client: Arc::new(Mutex::new(None)),
-                    after: Arc::new(Mutex::new(None)),
-                    initial_load: Arc::new(Mutex::new(has_credentials)),
-                    scroll_to_top: Arc::new(Mutex::new(true)),
-                    show_settings: !has_credentials,
-                    settings,
-                    settings_modified: false,
-                    has_credentials,
-                    current_subreddit: Arc::new(Mutex::new("home".to_string())),
-                    subreddits: Arc::new(Mutex::new(Vec::new())),
-                    loading_subreddits: Arc::new(Mutex::new(false)),
-                    last_scroll_pos: Arc::new(Mutex::new(0.0)),
-                    is_loading_more: Arc::new(Mutex::new(false)),
-                })
-            }
-        }
-
-        const FIELDS: &[&str] = &["settings"];
-        deserializer.deserialize_struct("RedditApp", FIELDS, RedditAppVisitor)
+fn main() -> Result<(), eframe::Error> {
+    let _log_guard = logging::init();
+    tracing::info!(version = env!("CARGO_PKG_VERSION"), os = std::env::consts::OS, "Starting");
+    let args: Vec<String> = std::env::args().collect();
+    let path_after = |flag: &str| args.iter().position(|arg| arg == flag).and_then(|i| args.get(i + 1)).map(std::path::Path::new);
+    let session = match (path_after("--record"), path_after("--replay")) {
+        (_, Some(path)) => replay::start_replay(path),
+        (Some(path), None) => replay::start_recording(path),
+        (None, None) => Ok(()),
+    };
+    if let Err(e) = session {
+        eprintln!("{:#}", e);
+        std::process::exit(1);
     }
-}
 
-fn main() -> Result<(), eframe::Error> {
-    let _args: Vec<String> = std::env::args().collect();
-    
     // Load and set the icon
     let icon_data = include_bytes!("../assets/icon.png");
     let (icon_rgba, icon_width, icon_height) = {
@@ -1170,74 +9792,970 @@ fn main() -> Result<(), eframe::Error> {
         height: icon_height,
     };
     
+    // Transparent windows cost more to draw on some systems, so only translucent themes get one
+    let settings = Settings::load();
+    let transparent = settings.theme.translucent();
     let options = eframe::NativeOptions {
         viewport: egui::ViewportBuilder::default()
             .with_inner_size([800.0, 600.0])
             .with_min_inner_size([300.0, 200.0])
             .with_title(APP_NAME)
-            .with_icon(icon),
+            .with_icon(icon)
+            .with_transparent(transparent),
         persist_window: true,  // Enable window position/size persistence
         ..Default::default()
     };
 
-    // Create the application state
-    let app = RedditApp::new();
-
-    // Only proceed with authentication if we have credentials
-    if app.has_credentials {
-        let settings = app.settings.clone();
-    let posts = app.posts.clone();
-    let loading = app.loading.clone();
-        let error_message = app.error_message.clone();
-        let reddit_client = app.reddit_client.clone();
-        let initial_load = app.initial_load.clone();
-
-    // Spawn a thread to handle the async operations
-    thread::spawn(move || {
-        let rt = tokio::runtime::Runtime::new().unwrap();
-        rt.block_on(async {
-                let mut client = match RedditClient::new() {
-                    Ok(client) => client,
-                    Err(e) => {
-                        *error_message.lock().unwrap() = Some(format!("Failed to create client: {}", e));
-                *loading.lock().unwrap() = false;
-                        *initial_load.lock().unwrap() = false;
-                return;
+    // Run the GUI in the main thread
+    eframe::run_native(
+        APP_NAME,
+        options,
+        Box::new(move |cc| {
+            // Install image loaders up front so prefetching works before the first frame
+            install_image_loaders(&cc.egui_ctx);
+
+            let mut app = RedditApp::with_settings(settings);
+            app.egui_ctx = Some(cc.egui_ctx.clone());
+            app.transparent_window = transparent;
+            palette::AccentLoader::install(&cc.egui_ctx, app.accents.clone());
+
+            // Loaders added later take priority, so the disk cache sits in front of plain HTTP
+            app.image_cache = image_cache::ImageCache::new(app.settings.image_cache_mb as u64 * 1024 * 1024);
+            if let Some(cache) = &app.image_cache {
+                cc.egui_ctx.add_bytes_loader(Arc::new(image_cache::DiskCacheLoader(cache.clone())));
             }
-                };
-                
-                // Authenticate
-                if let Err(e) = client.authenticate(&settings.client_id, &settings.client_secret, 
-                    &settings.username, &settings.password).await {
-                    *error_message.lock().unwrap() = Some(format!("Authentication error: {}", e));
-                    *loading.lock().unwrap() = false;
-                    *initial_load.lock().unwrap() = false;
-                    return;
+            // Replays start from a blank slate and leave the offline copy of real sessions alone
+            if replay::replaying() {
+                app.has_credentials = true;
+                app.onboarding = None;
+            } else {
+                app.store = store::Store::new();
+                app.restore_read_posts();
+                app.reload_drafts();
+                if let Some(store) = &app.store {
+                    let mut status = app.archive_status.lock().unwrap();
+                    status.archived = store.archive_size().unwrap_or_default();
+                    status.finished_at = store.last_archived().ok().flatten();
                 }
-                
-                *reddit_client.lock().unwrap() = Some(client);
-                
-                // Fetch posts
-                match reddit_client.lock().unwrap().as_ref().unwrap().get_home_feed(None).await {
-                    Ok((fetched_posts, _after)) => {
-                        *posts.lock().unwrap() = fetched_posts;
-                        *loading.lock().unwrap() = false;
-                        *initial_load.lock().unwrap() = false;
+                app.refresh_focus();
+
+                // Render the last session's feed immediately and sign in behind it
+                if let Some(storage) = cc.storage {
+                    app.restore_cached_feed(storage);
                 }
-                Err(e) => {
-                        *error_message.lock().unwrap() = Some(format!("Error fetching posts: {}", e));
-                    *loading.lock().unwrap() = false;
-                        *initial_load.lock().unwrap() = false;
-                    }
+            }
+            if app.has_credentials {
+                app.authenticate_and_load();
+            }
+
+            Box::new(app)
+        }),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LISTING_FIXTURE: &str = include_str!("../tests/fixtures/listing.json");
+
+    // Captured without raw_json, so every string in it is decoded the way raw_json would have sent it
+    fn unescaped(value: &mut serde_json::Value) {
+        match value {
+            serde_json::Value::String(text) => *text = html::decode(text),
+            serde_json::Value::Array(items) => items.iter_mut().for_each(unescaped),
+            serde_json::Value::Object(fields) => fields.values_mut().for_each(unescaped),
+            _ => {}
+        }
+    }
+
+    fn fixture_posts() -> (Vec<Post>, Option<String>) {
+        let mut json: serde_json::Value = serde_json::from_str(LISTING_FIXTURE).expect("fixture should parse");
+        unescaped(&mut json);
+        let listing: RedditListing = serde_json::from_value(json).expect("fixture should parse");
+        (listing.data.children.into_iter().map(|child| child.data).collect(), listing.data.after)
+    }
+
+    #[test]
+    fn parses_listing_fixture() {
+        let (posts, after) = fixture_posts();
+        assert_eq!(posts.len(), 3);
+        assert_eq!(after.as_deref(), Some("t3_1c2v3b4"));
+    }
+
+    #[test]
+    fn parses_link_post_fields() {
+        let (posts, _) = fixture_posts();
+        let post = &posts[0];
+        assert_eq!(post.id, "1bk2x9q");
+        assert_eq!(post.name, "t3_1bk2x9q");
+        assert_eq!(post.subreddit, "rust");
+        assert_eq!(post.author, "ehuss");
+        assert_eq!(post.domain, "blog.rust-lang.org");
+        assert_eq!(post.permalink, "/r/rust/comments/1bk2x9q/announcing_rust_1770/");
+        assert_eq!(post.score, 812);
+        assert_eq!(post.num_comments, 143);
+        assert_eq!(post.created_utc, 1710979200.0);
+        assert_eq!(post.link_flair_text.as_deref(), Some("📡 official blog"));
+        assert_eq!(post.link_flair_background_color.as_deref(), Some("#ff4500"));
+        assert_eq!(post.link_flair_text_color.as_deref(), Some("dark"));
+        assert_eq!(post.likes, Some(true));
+        assert!(!post.is_self && !post.is_video && !post.over_18);
+        assert!(post.media.is_none() && post.secure_media.is_none());
+    }
+
+    #[test]
+    fn parses_self_post_fields() {
+        let (posts, _) = fixture_posts();
+        let post = &posts[1];
+        assert!(post.is_self);
+        assert!(post.stickied && post.locked && post.saved);
+        assert_eq!(post.likes, None);
+        assert!(post.selftext.starts_with("I keep fighting"));
+        assert_eq!(post.author_flair_text.as_deref(), Some("rust · ferrocene"));
+        assert!(post.preview.is_none());
+        assert_eq!(post.thumbnail_url(), "self");
+    }
+
+    #[test]
+    fn parses_video_post_fields() {
+        let (posts, _) = fixture_posts();
+        let post = &posts[2];
+        assert!(post.is_video && post.over_18 && post.spoiler);
+        assert_eq!(post.likes, Some(false));
+        // Integer timestamps are accepted as well as floats
+        assert_eq!(post.created_utc, 1710981000.0);
+        let video = post.reddit_video().expect("video post should carry reddit_video");
+        assert_eq!(video.width, 1280);
+        assert_eq!(video.height, 720);
+        assert!(video.has_audio);
+    }
+
+    #[test]
+    fn detects_deleted_and_removed_content() {
+        let (posts, _) = fixture_posts();
+        assert!(!posts[0].author_deleted());
+        assert_eq!(posts[0].removal_notice(), None);
+        assert!(posts[2].author_deleted());
+
+        let removed: Post = serde_json::from_str(r#"{"author": "[deleted]", "selftext": "[removed]"}"#).unwrap();
+        assert_eq!(removed.removal_notice(), Some("removed"));
+        let moderated: Post = serde_json::from_str(r#"{"removed_by_category": "moderator"}"#).unwrap();
+        assert_eq!(moderated.removal_notice(), Some("removed by moderators"));
+    }
+
+    #[test]
+    fn decodes_html_entities_in_text_and_urls() {
+        let (posts, _) = fixture_posts();
+        assert_eq!(posts[0].title, "Announcing Rust 1.77.0 & what's next");
+        let video = posts[2].reddit_video().unwrap();
+        assert_eq!(video.hls_url.as_deref(), Some("https://v.redd.it/k3j2h1/HLSPlaylist.m3u8?a=1&v=1&f=sd"));
+
+        // The feed cache is written with Serialize and must read back unchanged
+        let cached = serde_json::to_string(&posts).unwrap();
+        let restored: Vec<Post> = serde_json::from_str(&cached).unwrap();
+        assert_eq!(restored[0].title, posts[0].title);
+        assert_eq!(restored[0].thumbnail_url(), posts[0].thumbnail_url());
+    }
+
+    #[test]
+    fn picks_preview_resolution_closest_to_thumbnail_size() {
+        let (posts, _) = fixture_posts();
+        assert_eq!(posts[0].thumbnail_url(), "https://external-preview.redd.it/medium.png?width=216&crop=smart&s=3");
+    }
+
+    #[test]
+    fn tolerates_missing_and_null_fields() {
+        let post: Post = serde_json::from_str(r#"{"title": "Bare", "link_flair_text": null, "secure_media": null}"#).unwrap();
+        assert_eq!(post.title, "Bare");
+        assert_eq!(post.score, 0);
+        assert!(post.preview.is_none());
+        assert!(post.reddit_video().is_none());
+    }
+
+    #[test]
+    fn ignores_non_reddit_media_embeds() {
+        let post: Post = serde_json::from_str(r#"{
+            "title": "A YouTube link",
+            "secure_media": {"type": "youtube.com", "oembed": {"provider_name": "YouTube"}}
+        }"#).unwrap();
+        assert!(post.reddit_video().is_none());
+    }
+
+    #[test]
+    fn prefers_mp4_variant_for_animated_previews() {
+        let post: Post = serde_json::from_str(r#"{
+            "title": "A GIF",
+            "preview": {"images": [{
+                "source": {"url": "https://preview.redd.it/a.gif?s=1", "width": 480, "height": 270},
+                "resolutions": [],
+                "variants": {
+                    "gif": {"source": {"url": "https://preview.redd.it/a.gif?format=gif", "width": 480, "height": 270}},
+                    "mp4": {"source": {"url": "https://preview.redd.it/a.gif?format=mp4", "width": 480, "height": 270}, "resolutions": []}
                 }
-            });
+            }]}
+        }"#).unwrap();
+        let animation = post.animated_preview().unwrap();
+        assert_eq!(animation.url, "https://preview.redd.it/a.gif?format=mp4");
+        assert_eq!((animation.width, animation.height), (480, 270));
+
+        let (posts, _) = fixture_posts();
+        assert!(posts.iter().all(|post| post.animated_preview().is_none()));
+    }
+
+    #[test]
+    fn picks_gif_renditions_to_play_inline() {
+        let post: Post = serde_json::from_str(r#"{
+            "title": "A GIF",
+            "preview": {"images": [{
+                "source": {"url": "https://preview.redd.it/a.gif?s=1", "width": 480, "height": 270},
+                "resolutions": [],
+                "variants": {
+                    "gif": {"source": {"url": "https://preview.redd.it/a.gif?format=gif", "width": 1280, "height": 720}, "resolutions": [
+                        {"url": "https://preview.redd.it/a.gif?width=320&format=gif", "width": 320, "height": 180},
+                        {"url": "https://preview.redd.it/a.gif?width=640&format=gif", "width": 640, "height": 360},
+                        {"url": "https://preview.redd.it/a.gif?width=960&format=gif", "width": 960, "height": 540}
+                    ]},
+                    "mp4": {"source": {"url": "https://preview.redd.it/a.gif?format=mp4", "width": 480, "height": 270}, "resolutions": []}
+                }
+            }]}
+        }"#).unwrap();
+        assert_eq!(post.animation(), Some(("https://preview.redd.it/a.gif?width=640&format=gif", 640, 360)));
+        assert!(post.can_expand());
+
+        let (posts, _) = fixture_posts();
+        assert!(posts.iter().all(|post| post.animation().is_none()));
+        let link: Post = serde_json::from_str(r#"{"title": "A WebP", "url": "https://i.redd.it/b.WEBP?x=1"}"#).unwrap();
+        assert_eq!(link.animation(), Some(("https://i.redd.it/b.WEBP?x=1", 0, 0)));
+    }
+
+    #[test]
+    fn uses_blurred_variant_for_nsfw_thumbnails() {
+        let post: Post = serde_json::from_str(r#"{
+            "title": "Spicy",
+            "over_18": true,
+            "thumbnail": "nsfw",
+            "preview": {"images": [{
+                "source": {"url": "https://preview.redd.it/raw.jpg", "width": 640, "height": 480},
+                "resolutions": [{"url": "https://preview.redd.it/raw-108.jpg", "width": 108, "height": 81}],
+                "variants": {"nsfw": {
+                    "source": {"url": "https://preview.redd.it/blur.jpg", "width": 640, "height": 480},
+                    "resolutions": [{"url": "https://preview.redd.it/blur-108.jpg", "width": 108, "height": 81}]
+                }}
+            }]}
+        }"#).unwrap();
+        assert_eq!(post.thumbnail_url(), "https://preview.redd.it/raw-108.jpg");
+        assert_eq!(post.blurred_thumbnail_url().as_deref(), Some("https://preview.redd.it/blur-108.jpg"));
+
+        let (posts, _) = fixture_posts();
+        assert!(posts[0].blurred_thumbnail_url().is_none());
+    }
+
+    #[test]
+    fn encodes_api_queries() {
+        let mut client = RedditClient::new().unwrap();
+        assert!(client.api_get("/").is_err());
+        client.access_token = Some("token".into());
+
+        let request = client.api_get("/r/rust/search").unwrap()
+            .query(&[("q", "flair_name:\"Q&A\"")])
+            .query(&[("after", Some("t3_abc"))])
+            .build()
+            .unwrap();
+        assert_eq!(request.url().as_str(),
+            "https://oauth.reddit.com/r/rust/search?raw_json=1&q=flair_name%3A%22Q%26A%22&after=t3_abc");
+
+        // A missing cursor leaves the parameter out entirely
+        let request = client.api_get("/").unwrap().query(&[("after", None::<&str>)]).build().unwrap();
+        assert_eq!(request.url().as_str(), "https://oauth.reddit.com/?raw_json=1");
+    }
+
+    #[test]
+    fn applies_account_prefs_unless_local_wins() {
+        let prefs: AccountPrefs = serde_json::from_str(r#"{
+            "over_18": true, "label_nsfw": false, "default_comment_sort": "new", "show_trending": true
+        }"#).unwrap();
+
+        let mut settings = Settings::default();
+        settings.apply_account_prefs(&prefs);
+        assert!(settings.show_nsfw && !settings.label_nsfw);
+        assert_eq!(settings.comment_sort, "new");
+
+        let mut local = Settings { local_prefs_only: true, ..Settings::default() };
+        local.apply_account_prefs(&prefs);
+        assert!(!local.show_nsfw && local.label_nsfw);
+        assert_eq!(local.comment_sort, "confidence");
+
+        // Sorts Rustle doesn't know about leave the current one alone
+        let odd: AccountPrefs = serde_json::from_str(r#"{"default_comment_sort": "random"}"#).unwrap();
+        settings.apply_account_prefs(&odd);
+        assert_eq!(settings.comment_sort, "new");
+    }
+
+    #[test]
+    fn builds_view_endpoints() {
+        let sorted = FeedView { name: "Latest".into(), sort: "new".into(), time: "week".into(), flair: String::new() };
+        let (url, params) = sorted.endpoint("rust");
+        assert_eq!(url, "/r/rust/new");
+        assert!(params.is_empty());
+
+        let top = FeedView { sort: "top".into(), time: "month".into(), ..FeedView::default() };
+        assert_eq!(top.endpoint("rust"), ("/r/rust/top".to_string(), vec![("t", "month".to_string())]));
+        assert_eq!(top.describe(), "Sorted by top, past month");
+
+        let flaired = FeedView { name: "Show-and-tell".into(), sort: "rising".into(), time: String::new(), flair: "Show and tell".into() };
+        let (url, params) = flaired.endpoint("rust");
+        assert_eq!(url, "/r/rust/search");
+        assert!(params.contains(&("q", "flair_name:\"Show and tell\"".to_string())));
+        assert!(params.contains(&("restrict_sr", "1".to_string())));
+        assert!(params.contains(&("sort", "hot".to_string())));
+    }
+
+    #[test]
+    fn remembers_sort_and_layout_per_subreddit() {
+        let mut settings = Settings { density: Density::Cozy, ..Settings::default() };
+        settings.update_feed_prefs("Rust", |prefs| {
+            prefs.sort = "top".into();
+            prefs.time = "week".into();
+            prefs.density = Some(Density::Compact);
         });
+        let view = settings.feed_prefs("rust").view().unwrap();
+        assert_eq!((view.sort.as_str(), view.time.as_str(), view.name.as_str()), ("top", "week", ""));
+        assert_eq!((settings.density_for("RUST"), settings.density_for("golang")), (Density::Compact, Density::Cozy));
+
+        settings.update_feed_prefs("rust", |prefs| *prefs = FeedPrefs::default());
+        assert!(settings.feed_prefs.is_empty());
+        assert!(!has_feed_prefs("home") && !has_feed_prefs("popular") && has_feed_prefs("rust"));
+    }
+
+    #[test]
+    fn parses_posted_comment_or_api_error() {
+        let posted: CommentResponse = serde_json::from_str(r#"{"json": {"errors": [], "data": {"things": [
+            {"kind": "t1", "data": {"id": "k1", "name": "t1_k1", "body": "Nice", "replies": ""}}
+        ]}}}"#).unwrap();
+        let comment = posted.into_comment().unwrap();
+        assert_eq!(comment.name, "t1_k1");
+        assert_eq!(comment.body, "Nice");
+
+        let rejected: CommentResponse = serde_json::from_str(r#"{"json": {"errors": [
+            ["RATELIMIT", "you are doing that too much", "ratelimit"]
+        ]}}"#).unwrap();
+        let error = rejected.into_comment().unwrap_err();
+        assert!(error.to_string().contains("you are doing that too much"));
+    }
+
+    #[test]
+    fn hides_only_posts_read_before_the_feed_loaded() {
+        let mut read = ReadPosts::default();
+        assert!(read.mark("t3_a"));
+        assert!(!read.mark("t3_a"));
+        // Marked once, so it's written to the store once
+        assert_eq!(read.unsaved, ["t3_a"]);
+        // Read during this visit, so it stays put
+        assert!(!read.hides("t3_a"));
+
+        read.new_feed();
+        assert!(read.hides("t3_a"));
+        assert!(!read.hides("t3_b"));
+    }
+
+    #[test]
+    fn merges_posts_visited_elsewhere() {
+        let posts: Vec<Post> = serde_json::from_str(r#"[
+            {"name": "t3_a", "visited": true},
+            {"name": "t3_b", "clicked": true},
+            {"name": "t3_c"}
+        ]"#).unwrap();
+        let mut read = ReadPosts::default();
+        read.mark("t3_a");
+        assert_eq!(read.merge_visited(&posts), ["t3_b"]);
+        // Counts as read before this visit, so hide read mode drops it right away
+        assert!(read.hides("t3_b"));
+        assert!(!read.hides("t3_c"));
+    }
+
+    #[test]
+    fn parses_crosspost_parent_and_domain() {
+        let post: Post = serde_json::from_str(r#"{
+            "id": "x1", "subreddit": "videos", "domain": "v.redd.it", "post_hint": "hosted:video",
+            "crosspost_parent_list": [{
+                "id": "o1", "subreddit": "aww", "author": "op", "domain": "v.redd.it",
+                "secure_media": {"reddit_video": {"fallback_url": "https://v.redd.it/o1/DASH_720.mp4",
+                    "width": 1280, "height": 720}}
+            }]
+        }"#).unwrap();
+
+        let parent = post.crosspost_parent().unwrap();
+        assert_eq!((parent.id.as_str(), parent.subreddit.as_str()), ("o1", "aww"));
+        assert_eq!(post.reddit_video().unwrap().fallback_url, "https://v.redd.it/o1/DASH_720.mp4");
+        assert_eq!(post.domain_chip().as_deref(), Some("▶ v.redd.it"));
+        assert!(!post.opens_externally());
+
+        let text: Post = serde_json::from_str(r#"{"is_self": true, "domain": "self.rust"}"#).unwrap();
+        assert_eq!(text.domain_chip().as_deref(), Some("📄 self.rust"));
+        let link: Post = serde_json::from_str(r#"{"domain": "github.com", "post_hint": "link"}"#).unwrap();
+        assert_eq!(link.domain_chip().as_deref(), Some("🔗 github.com ⬈"));
+        let image: Post = serde_json::from_str(r#"{"domain": "i.redd.it", "post_hint": "image"}"#).unwrap();
+        assert_eq!(image.domain_chip().as_deref(), Some("🖼 i.redd.it"));
+        let bare: Post = serde_json::from_str(r#"{"title": "No domain"}"#).unwrap();
+        assert!(bare.domain_chip().is_none());
+    }
+
+    #[test]
+    fn expands_to_text_or_full_preview() {
+        let text: Post = serde_json::from_str(r#"{"is_self": true, "selftext": "Some **details**"}"#).unwrap();
+        assert_eq!(text.expanded_text(), Some("Some **details**"));
+        assert!(text.expanded_image().is_none());
+
+        let image: Post = serde_json::from_str(r#"{"preview": {"images": [{
+            "source": {"url": "https://preview.redd.it/full.jpg", "width": 3000, "height": 2000},
+            "resolutions": [{"url": "https://preview.redd.it/108.jpg", "width": 108, "height": 72}]
+        }]}}"#).unwrap();
+        assert_eq!(image.expanded_image().unwrap().url, "https://preview.redd.it/full.jpg");
+
+        let removed: Post = serde_json::from_str(r#"{"selftext": "[removed]"}"#).unwrap();
+        assert!(!removed.can_expand());
+        let bare: Post = serde_json::from_str(r#"{"title": "Link"}"#).unwrap();
+        assert!(!bare.can_expand());
+    }
+
+    #[test]
+    fn reuses_stored_sessions_until_they_expire() {
+        let account = Account { auth_method: AuthMethod::Script, client_id: "app".to_string(), username: "Ferris".to_string(), ..Default::default() };
+        let mut client = RedditClient::new().unwrap();
+        assert!(client.session(&account).is_none());
+        client.access_token = Some("token".to_string());
+        client.expires_at = Some(Instant::now() + Duration::from_secs(3600));
+
+        let session = client.session(&account).unwrap();
+        let now = store::now();
+        assert!(session.usable_for(&account, now));
+        assert!(!session.usable_for(&account, now + 3590));
+        let other = Account { username: "someone_else".to_string(), ..account.clone() };
+        assert!(!session.usable_for(&other, now));
+
+        let mut resumed = RedditClient::new().unwrap();
+        resumed.resume(session);
+        assert_eq!(resumed.access_token.as_deref(), Some("token"));
+        assert!(!resumed.token_expiring());
+    }
+
+    #[test]
+    fn undoes_held_replies() {
+        let app = RedditApp::with_settings(Settings::default());
+        let pending = Arc::new(PendingSend { sending: Sending::Reply { parent: "t1_a".to_string(), text: "Typo".to_string() }, send_at: Instant::now() });
+        app.pending_sends.lock().unwrap().push(pending.clone());
+
+        app.undo_send(&pending);
+        assert!(app.pending_sends.lock().unwrap().is_empty());
+        let draft = app.reply_draft.lock().unwrap();
+        let draft = draft.as_ref().unwrap();
+        assert_eq!((draft.parent.as_str(), draft.text.as_str(), draft.submitting), ("t1_a", "Typo", false));
+    }
+
+    #[test]
+    fn keeps_other_open_replies_when_undoing() {
+        let app = RedditApp::with_settings(Settings::default());
+        let pending = Arc::new(PendingSend { sending: Sending::Reply { parent: "t1_a".to_string(), text: "Typo".to_string() }, send_at: Instant::now() });
+        app.pending_sends.lock().unwrap().push(pending.clone());
+        let mut writing = comments::Draft::new("t1_b".to_string());
+        writing.text = "Half a thought".to_string();
+        *app.reply_draft.lock().unwrap() = Some(writing);
+
+        app.undo_send(&pending);
+        assert_eq!(app.pending_sends.lock().unwrap().len(), 1);
+        assert_eq!(app.reply_draft.lock().unwrap().as_ref().unwrap().text, "Half a thought");
+
+        // An empty box has nothing to lose
+        app.reply_draft.lock().unwrap().as_mut().unwrap().text.clear();
+        app.undo_send(&pending);
+        assert!(app.pending_sends.lock().unwrap().is_empty());
+        assert_eq!(app.reply_draft.lock().unwrap().as_ref().unwrap().text, "Typo");
+    }
+
+    #[test]
+    fn undoes_held_posts() {
+        let app = RedditApp::with_settings(Settings::default());
+        let mut draft = submit::Draft::new("rust".to_string());
+        draft.title = "Borrow checker question".to_string();
+        draft.submitting = true;
+        let pending = Arc::new(PendingSend { sending: Sending::Post { id: None, saved: draft.saved(), scheduled: false }, send_at: Instant::now() });
+        app.pending_sends.lock().unwrap().push(pending.clone());
+        *app.post_draft.lock().unwrap() = Some(draft);
+
+        assert!(!app.draft_in_the_way(&pending));
+        app.undo_send(&pending);
+        assert!(app.pending_sends.lock().unwrap().is_empty());
+        let draft = app.post_draft.lock().unwrap();
+        let draft = draft.as_ref().unwrap();
+        assert_eq!((draft.title.as_str(), draft.submitting), ("Borrow checker question", false));
+    }
+
+    #[test]
+    fn refreshes_kept_feeds_once_they_go_stale() {
+        let mut app = RedditApp::with_settings(Settings { startup_refresh: StartupRefresh::WhenStale(30), ..Settings::default() });
+        app.demo = true;
+        let ctx = egui::Context::default();
+        *app.kept_feed_synced_at.lock().unwrap() = Some(store::now() - 10 * 60);
+        app.stale_check_at = Some(Instant::now());
+        app.refresh_stale_feed(&ctx);
+        assert!(app.kept_feed_synced_at.lock().unwrap().is_some());
+        assert!(app.stale_check_at.unwrap() > Instant::now());
+
+        *app.kept_feed_synced_at.lock().unwrap() = Some(store::now() - 31 * 60);
+        app.stale_check_at = Some(Instant::now());
+        app.refresh_stale_feed(&ctx);
+        assert!(app.kept_feed_synced_at.lock().unwrap().is_none());
+        assert!(!app.posts.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn restores_feeds_when_switching_back() {
+        let mut app = RedditApp::with_settings(Settings::default());
+        app.demo = true;
+        app.switch_subreddit("rust".to_string());
+        *app.after.lock().unwrap() = Some("t3_next".to_string());
+        *app.last_scroll_pos.lock().unwrap() = 240.0;
+
+        app.switch_subreddit("science".to_string());
+        assert!(app.posts.lock().unwrap().iter().all(|post| post.subreddit == "science"));
+        assert_eq!(*app.after.lock().unwrap(), None);
+
+        app.switch_subreddit("rust".to_string());
+        assert!(app.posts.lock().unwrap().iter().all(|post| post.subreddit == "rust"));
+        assert_eq!(app.after.lock().unwrap().as_deref(), Some("t3_next"));
+        assert_eq!(*app.feed_scroll_return.lock().unwrap(), Some(240.0));
+        assert!(app.feed_states.lock().unwrap().contains_key("science"));
+    }
+
+    #[test]
+    fn picks_a_clip_for_hover_previews() {
+        let gif_link: Post = serde_json::from_str(r#"{"preview": {"images": [{
+            "source": {"url": "https://preview.redd.it/a.gif", "width": 400, "height": 300}, "resolutions": []
+        }], "reddit_video_preview": {"fallback_url": "https://v.redd.it/a/DASH_360.mp4", "width": 480, "height": 360}}}"#).unwrap();
+        assert_eq!(gif_link.hover_clip(), Some(("https://v.redd.it/a/DASH_360.mp4", 480, 360)));
+
+        let image: Post = serde_json::from_str(r#"{"preview": {"images": [{
+            "source": {"url": "https://preview.redd.it/b.jpg", "width": 400, "height": 300}, "resolutions": []
+        }]}}"#).unwrap();
+        assert_eq!(image.hover_clip(), None);
+    }
+
+    #[test]
+    fn parses_awards() {
+        let post: Post = serde_json::from_str(r#"{"total_awards_received": 3, "all_awardings": [
+            {"name": "Helpful", "count": 2, "icon_url": "https://i.redd.it/award_images/helpful.png?a=1&b=2",
+             "resized_icons": [{"url": "https://preview.redd.it/h16.png", "width": 16, "height": 16},
+                               {"url": "https://preview.redd.it/h48.png", "width": 48, "height": 48},
+                               {"url": "https://preview.redd.it/h32.png", "width": 32, "height": 32}]},
+            {"name": "Wholesome", "count": 1, "icon_url": "https://i.redd.it/award_images/wholesome.png"}
+        ]}"#).unwrap();
+        assert_eq!(post.award_count(), 3);
+        assert_eq!(post.all_awardings[0].icon(), "https://preview.redd.it/h32.png");
+        assert_eq!(post.all_awardings[1].icon(), "https://i.redd.it/award_images/wholesome.png");
+
+        // Only the total survives on newer listings
+        let total_only: Post = serde_json::from_str(r#"{"total_awards_received": 5, "all_awardings": []}"#).unwrap();
+        assert_eq!(total_only.award_count(), 5);
+    }
+
+    #[test]
+    fn loads_only_permitted_nsfw_thumbnails() {
+        let post: Post = serde_json::from_str(r#"{"over_18": true, "thumbnail": "nsfw", "preview": {"images": [{
+            "source": {"url": "https://preview.redd.it/full.jpg", "width": 640, "height": 480}, "resolutions": [],
+            "variants": {"nsfw": {"source": {"url": "https://preview.redd.it/blurred.jpg", "width": 640, "height": 480}, "resolutions": []}}
+        }]}}"#).unwrap();
+        let mut settings = Settings::default();
+        assert_eq!(post.thumbnail_to_load(&settings, false), None);
+
+        settings.show_nsfw = true;
+        assert_eq!(post.thumbnail_to_load(&settings, false).as_deref(), Some("https://preview.redd.it/blurred.jpg"));
+        assert_eq!(post.thumbnail_to_load(&settings, true).as_deref(), Some("https://preview.redd.it/full.jpg"));
+
+        settings.blur_nsfw = false;
+        assert_eq!(post.thumbnail_to_load(&settings, false).as_deref(), Some("https://preview.redd.it/full.jpg"));
+    }
+
+    #[test]
+    fn finds_posts_not_yet_shown() {
+        let posts = |names: &[&str]| -> Vec<Post> {
+            names.iter().map(|name| Post { name: name.to_string(), ..Default::default() }).collect()
+        };
+        let unseen = unseen_posts(posts(&["t3_new", "t3_a", "t3_newer", "t3_b"]), &posts(&["t3_a", "t3_b", "t3_c"]));
+        let names: Vec<&str> = unseen.iter().map(|post| post.name.as_str()).collect();
+        assert_eq!(names, ["t3_new", "t3_newer"]);
+    }
+
+    #[test]
+    fn names_snippet_triggers() {
+        let snippet = Snippet { name: "Stock  Answer".to_string(), text: String::new() };
+        assert_eq!(snippet.trigger(), "stock-answer");
+    }
+
+    #[test]
+    fn suggests_names_for_mentions() {
+        let thread: Vec<comments::CommentThing> = serde_json::from_str(r#"[
+            {"kind": "t1", "data": {"author": "ferris", "created_utc": 1.0, "replies": [
+                {"kind": "t1", "data": {"author": "me", "created_utc": 2.0}},
+                {"kind": "t1", "data": {"author": "[deleted]", "created_utc": 3.0}}
+            ]}},
+            {"kind": "t1", "data": {"author": "Fernando", "created_utc": 4.0}}
+        ]"#).unwrap();
+        let users = thread_users(&thread, Some("op"), "Me");
+        assert_eq!(users, ["Fernando", "ferris", "op"]);
+
+        let remote = ["ferris".to_string(), "fermat".to_string()];
+        assert_eq!(mention_candidates("FER", &users, &remote), ["Fernando", "ferris", "fermat"]);
+        assert_eq!(mention_candidates("", &users, &[]), users);
+    }
+
+    #[test]
+    fn names_feed_exports() {
+        assert_eq!(export_file_name("rust", 1711029900), "feed-rust-1711029900.json");
+        assert_eq!(export_file_name("user/spez/m/tech", 5), "feed-user-spez-m-tech-5.json");
+    }
+
+    #[test]
+    fn skips_posts_repeated_across_pages() {
+        let posts = |names: &[&str]| -> Vec<Post> {
+            names.iter().map(|name| Post { name: name.to_string(), ..Default::default() }).collect()
+        };
+        let mut feed = posts(&["t3_a", "t3_b"]);
+        append_page(&mut feed, posts(&["t3_b", "t3_c", "t3_c", "t3_d"]));
+        let names: Vec<&str> = feed.iter().map(|post| post.name.as_str()).collect();
+        assert_eq!(names, ["t3_a", "t3_b", "t3_c", "t3_d"]);
+
+        let listing: RedditListing = serde_json::from_str(r#"{"data": {"children": [], "after": ""}}"#).unwrap();
+        assert_eq!(listing.data.after, None);
+    }
+
+    #[test]
+    fn pages_by_last_visible_row() {
+        assert_eq!(page_triggers(Some(5), 100, 10, 25), (false, false));
+        assert_eq!(page_triggers(Some(75), 100, 10, 25), (false, true));
+        assert_eq!(page_triggers(Some(95), 100, 10, 25), (true, true));
+        assert_eq!(page_triggers(None, 100, 10, 25), (true, true));
+        assert_eq!(page_triggers(Some(50), 100, 60, 100), (true, true));
+        assert_eq!(page_triggers(Some(50), 100, 0, 10), (false, false));
+    }
+
+    #[test]
+    fn cycles_feed_density() {
+        assert_eq!(Density::default().next(), Density::Card);
+        assert_eq!(Density::Card.next(), Density::Compact);
+        assert_eq!(Density::Compact.name(), "Compact");
+
+        // Older settings keep the layout they had
+        let settings: Settings = serde_json::from_str("{}").unwrap();
+        assert_eq!(settings.density, Density::Cozy);
+        let settings: Settings = serde_json::from_str(r#"{"density": "Card"}"#).unwrap();
+        assert_eq!(settings.density, Density::Card);
+    }
+
+    #[test]
+    fn counts_votes_locally() {
+        let mut post: Post = serde_json::from_str(r#"{"score": 10, "likes": null}"#).unwrap();
+        post.apply_vote(Some(true));
+        assert_eq!((post.score, post.likes), (11, Some(true)));
+        post.apply_vote(Some(false));
+        assert_eq!((post.score, post.likes), (9, Some(false)));
+        post.apply_vote(None);
+        assert_eq!((post.score, post.likes), (10, None));
+
+        let settings: Settings = serde_json::from_str(r#"{"card_actions": {"share": true}}"#).unwrap();
+        assert!(settings.card_actions.share && settings.card_actions.vote && !settings.card_actions.save);
+    }
+
+    #[test]
+    fn formats_post_times() {
+        let posted = 1710979200.0;  // March 21, 2024 00:00 UTC
+        assert_eq!(relative_time(posted, 1710979200 + 30), "just now");
+        assert_eq!(relative_time(posted, 1710979200 + 3 * 3600 + 59), "3h ago");
+        assert_eq!(relative_time(posted, 1710979200 + 45 * 86_400), "1mo ago");
+        assert_eq!(relative_time(posted, 1710979200 - 100), "just now");
+        assert_eq!(absolute_time(posted + 14.0 * 3600.0 + 5.0 * 60.0), "March 21, 2024 at 14:05 UTC");
+
+        // Calendar days, so a post from just before midnight is already yesterday
+        let midnight = 1710979200;
+        assert_eq!(time_section(posted + 60.0, midnight), "Today");
+        assert_eq!(time_section(posted - 60.0, midnight), "Yesterday");
+        assert_eq!(time_section(posted - 86_400.0, midnight), "Yesterday");
+        assert_eq!(time_section(posted - 5.0 * 86_400.0, midnight), "This week");
+        assert_eq!(time_section(posted - 30.0 * 86_400.0, midnight), "Earlier");
+    }
+
+    #[test]
+    fn lists_post_details() {
+        let (posts, _) = fixture_posts();
+        let details = posts[0].details();
+        let value = |label: &str| details.iter().find(|(name, _)| *name == label).map(|(_, value)| value.as_str());
+        assert_eq!(value("Upvoted"), Some("98%"));
+        assert!(value("Posted").unwrap().starts_with("March 21, 2024 at 00:00 UTC ("));
+        assert_eq!(value("Comments"), Some("143"));
+        // No awards row when there aren't any
+        assert_eq!(value("Awards"), None);
+        assert_eq!(value("Your vote"), Some("Upvoted"));
+
+        let mut voted = posts[0].clone();
+        voted.apply_vote(Some(false));
+        assert!(voted.details().contains(&("Your vote", "Downvoted".to_string())));
+        voted.upvote_ratio = 0.0;
+        assert_eq!(voted.upvoted_percent(), None);
+    }
+
+    #[test]
+    fn selects_posts_with_ctrl_and_shift() {
+        let order: Vec<String> = ["t3_a", "t3_b", "t3_c", "t3_d"].map(String::from).to_vec();
+        let mut selection = Selection::default();
+        selection.click("t3_b", false, &order);
+        selection.click("t3_d", true, &order);
+        assert_eq!(selection.names, HashSet::from(["t3_b", "t3_c", "t3_d"].map(String::from)));
+
+        // Ctrl-click toggles a single post off again
+        selection.click("t3_c", false, &order);
+        assert!(!selection.names.contains("t3_c"));
+        // Without an anchor in the feed, Shift-click behaves like Ctrl-click
+        selection.clear();
+        selection.click("t3_a", true, &order);
+        assert_eq!(selection.names.len(), 1);
+    }
+
+    #[test]
+    fn exports_selection_as_markdown() {
+        let posts: Vec<Post> = serde_json::from_str(r#"[
+            {"title": "A [tagged] title", "permalink": "/r/rust/comments/a/", "subreddit": "rust"}
+        ]"#).unwrap();
+        assert_eq!(export_markdown(&posts), "- [A [tagged\\] title](https://www.reddit.com/r/rust/comments/a/) (r/rust)\n");
+    }
+
+    #[test]
+    fn picks_flair_colors() {
+        assert_eq!(flair_colors(Some("#ff4500"), Some("light")),
+            Some((egui::Color32::from_rgb(0xff, 0x45, 0x00), egui::Color32::WHITE)));
+        assert_eq!(flair_colors(Some("#0079d3"), Some("dark")).map(|(_, text)| text), Some(egui::Color32::BLACK));
+        assert_eq!(flair_colors(Some(""), Some("dark")), None);
+        assert_eq!(flair_colors(None, None), None);
+        assert_eq!(flair_colors(Some("#nothex"), None), None);
+    }
+
+    #[test]
+    fn explains_auth_failures() {
+        let (title, _) = explain_auth_failure(AuthFailure::Grant, AuthMethod::Script);
+        assert!(title.contains("password"));
+        let (title, _) = explain_auth_failure(AuthFailure::Grant, AuthMethod::Browser);
+        assert!(title.contains("revoked"));
+        let (title, _) = explain_auth_failure(AuthFailure::Ended, AuthMethod::Script);
+        assert_eq!(title, "Reddit ended this session");
+        let (title, _) = explain_auth_failure(AuthFailure::App, AuthMethod::Script);
+        assert!(title.contains("app credentials"));
+    }
+
+    #[test]
+    fn checks_credentials_for_each_auth_method() {
+        let mut account = Account { client_id: "id".to_string(), ..Default::default() };
+        assert!(!account.has_credentials());
+        account.client_secret = "secret".to_string();
+        account.username = "user".to_string();
+        account.password = "hunter2".to_string();
+        assert!(account.has_credentials());
+
+        account.auth_method = AuthMethod::Browser;
+        assert!(!account.has_credentials());
+        account.refresh_token = "token".to_string();
+        assert!(account.has_credentials());
+
+        account.auth_method = AuthMethod::Anonymous;
+        assert!(!account.has_credentials());
+        account.device_id = "device".to_string();
+        assert!(account.has_credentials());
+        // No app at all means the public endpoints
+        let public = Account { auth_method: AuthMethod::Anonymous, ..Default::default() };
+        assert!(public.has_credentials());
+    }
+
+    #[test]
+    fn builds_public_json_urls() {
+        assert_eq!(public_url(WWW_HOST, "/"), "https://www.reddit.com/.json");
+        assert_eq!(public_url(WWW_HOST, "/r/rust/hot"), "https://www.reddit.com/r/rust/hot.json");
+        assert_eq!(public_url(WWW_HOST, "/r/rust/comments/abc/"), "https://www.reddit.com/r/rust/comments/abc.json");
+        assert_eq!(public_url(WWW_HOST, "/r/rust/top?t=week"), "https://www.reddit.com/r/rust/top.json?t=week");
+    }
+
+    #[test]
+    fn older_settings_get_the_cozy_card_layout() {
+        let settings: Settings = serde_json::from_str(r#"{"font_size": 1.2}"#).unwrap();
+        assert_eq!(settings.card_layout, CardLayout::COZY);
+        let settings: Settings = serde_json::from_str(r#"{"card_layout": {"spacing": 2.0}}"#).unwrap();
+        assert_eq!(settings.card_layout, CardLayout { spacing: 2.0, ..CardLayout::COZY });
+    }
+
+    #[test]
+    fn refreshes_restored_feeds_per_setting() {
+        assert!(StartupRefresh::Immediately.wants_refresh(0));
+        assert!(!StartupRefresh::Manual.wants_refresh(i64::MAX));
+        assert!(!StartupRefresh::WhenStale(30).wants_refresh(29 * 60));
+        assert!(StartupRefresh::WhenStale(30).wants_refresh(30 * 60));
+
+        let settings: Settings = serde_json::from_str(r#"{"startup_refresh": {"WhenStale": 90}}"#).unwrap();
+        assert_eq!(settings.startup_refresh, StartupRefresh::WhenStale(90));
+        let settings: Settings = serde_json::from_str("{}").unwrap();
+        assert_eq!(settings.startup_refresh, StartupRefresh::Immediately);
+    }
+
+    #[test]
+    fn older_settings_keep_their_light_or_dark_theme() {
+        let settings: Settings = serde_json::from_str(r#"{"dark_mode": false}"#).unwrap();
+        assert_eq!(settings.theme, theme::Theme::light());
+        let settings: Settings = serde_json::from_str("{}").unwrap();
+        assert_eq!(settings.theme, theme::Theme::dark());
+
+        let json = serde_json::to_value(Settings { theme: theme::Theme::presets()[3].clone(), ..Default::default() }).unwrap();
+        assert_eq!(json["theme"]["name"], "Nord");
+        let restored: Settings = serde_json::from_value(json).unwrap();
+        assert_eq!(restored.theme.rounding, 6.0);
+    }
+
+    #[test]
+    fn orders_favorite_subreddits() {
+        let mut settings = Settings::default();
+        for name in ["rust", "programming", "science"] {
+            settings.set_favorite(name, true);
+        }
+        settings.set_favorite("Rust", true);
+        assert_eq!(settings.favorite_subreddits, ["rust", "programming", "science"]);
+
+        settings.move_favorite(2, 0);
+        assert_eq!(settings.favorite_subreddits, ["science", "rust", "programming"]);
+        settings.move_favorite(0, 2);
+        assert_eq!(settings.favorite_subreddits, ["rust", "programming", "science"]);
+        settings.move_favorite(5, 0);
+
+        settings.set_favorite("PROGRAMMING", false);
+        assert_eq!(settings.favorite_subreddits, ["rust", "science"]);
+        assert!(settings.is_favorite("Science"));
+    }
+
+    #[test]
+    fn combines_picks_into_front_page() {
+        let mut settings = Settings::default();
+        settings.account.auth_method = AuthMethod::Anonymous;
+        assert_eq!(settings.front_page(), "/");
+        settings.account.subreddit_picks = vec!["rust".to_string(), "science".to_string()];
+        assert_eq!(settings.front_page(), "/r/rust+science");
+        settings.account.auth_method = AuthMethod::Browser;
+        assert_eq!(settings.front_page(), "/");
+    }
+
+    #[test]
+    fn older_settings_default_to_script_auth() {
+        let settings: Settings = serde_json::from_str(r#"{"client_id": "id", "client_secret": "s",
+            "username": "u", "password": "p"}"#).unwrap();
+        assert_eq!(settings.account.auth_method, AuthMethod::Script);
+        assert!(settings.has_credentials());
+        assert!(settings.accounts.is_empty());
+    }
+
+    #[test]
+    fn keeps_secrets_in_small_keyring_entries() {
+        let account = |username: String| Account {
+            auth_method: AuthMethod::Browser,
+            client_id: "i".repeat(22),
+            client_secret: "s".repeat(30),
+            username,
+            password: "p".repeat(128),
+            refresh_token: "t".repeat(200),
+            ..Default::default()
+        };
+        let settings = Settings {
+            account: account("ferris".to_string()),
+            accounts: (0..20).map(|n| account(format!("alt{}", n))).collect(),
+            translation_key: "k".repeat(64),
+            ..Default::default()
+        };
+        let entries = settings.keyring_entries().unwrap();
+        assert_eq!(entries.len(), 22);
+        assert!(entries.iter().all(|(_, payload)| payload.encode_utf16().count() <= KEYRING_LIMIT));
+
+        // The settings file has everything else and nothing secret
+        let file = serde_json::to_string(&settings).unwrap();
+        assert!(["sss", "ppp", "ttt", "kkk"].iter().all(|secret| !file.contains(secret)));
+        let loaded: Settings = serde_json::from_str(&file).unwrap();
+        assert_eq!((loaded.accounts.len(), loaded.account.client_id.len()), (20, 22));
+    }
+
+    #[test]
+    fn sets_unreadable_settings_aside() {
+        let dir = std::env::temp_dir().join(format!("rustle-settings-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("settings.json");
+        let settings = Settings { page_size: 50, ..Default::default() };
+        write_settings(&path, &serde_json::to_string_pretty(&settings).unwrap()).unwrap();
+        assert_eq!(read_settings(&path).map(|settings| settings.page_size), Some(50));
+        assert!(!path.with_extension("json.tmp").exists());
+
+        // A cut-off file is kept as it was rather than saved over with defaults
+        std::fs::write(&path, "{\"page_size\": 5").unwrap();
+        assert_eq!(read_settings(&path).map(|settings| settings.page_size), Some(Settings::default().page_size));
+        assert!(!path.exists());
+        assert_eq!(std::fs::read_to_string(path.with_extension("json.bak")).unwrap(), "{\"page_size\": 5");
+        assert!(read_settings(&path).is_none());
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn switches_between_saved_accounts() {
+        let account = |method, username: &str| Account {
+            auth_method: method,
+            client_id: "id".to_string(),
+            username: username.to_string(),
+            password: "p".to_string(),
+            client_secret: "s".to_string(),
+            refresh_token: "t".to_string(),
+            ..Default::default()
+        };
+        let mut settings = Settings { account: account(AuthMethod::Script, "alice"), ..Default::default() };
+
+        settings.begin_new_account();
+        assert!(!settings.has_credentials());
+        settings.account = account(AuthMethod::Browser, "bob");
+        settings.forget_duplicate_accounts();
+        let labels = |settings: &Settings| settings.accounts.iter().map(Account::label).collect::<Vec<_>>();
+        assert_eq!(labels(&settings), ["u/alice"]);
+
+        settings.switch_account(0);
+        assert_eq!(settings.account.label(), "u/alice");
+        assert_eq!(labels(&settings), ["u/bob"]);
+
+        // Signing in as alice again replaces her saved entry
+        settings.begin_new_account();
+        settings.account = account(AuthMethod::Script, "Alice");
+        settings.forget_duplicate_accounts();
+        assert_eq!(labels(&settings), ["u/bob"]);
+
+        // Round trips through the keyring JSON with the active account still flat
+        let json = serde_json::to_value(&settings).unwrap();
+        assert_eq!(json["username"], "Alice");
+        let restored: Settings = serde_json::from_value(json).unwrap();
+        assert_eq!(labels(&restored), ["u/bob"]);
+    }
+
+    #[test]
+    fn parses_blocked_user_list() {
+        let listing: BlockedListing = serde_json::from_str(r#"{"kind": "UserList", "data": {"children": [
+            {"date": 1700000000.0, "rel_id": "r9_1", "name": "SpamBot", "id": "t2_abc"},
+            {"date": 1700000100.0, "rel_id": "r9_2", "name": "troll42", "id": "t2_def"}
+        ]}}"#).unwrap();
+        let names: Vec<_> = listing.data.children.into_iter().map(|user| user.name).collect();
+        assert_eq!(names, ["SpamBot", "troll42"]);
+    }
+
+    #[test]
+    fn summarizes_the_signed_in_account() {
+        let me: Me = serde_json::from_str(r#"{"name": "Ferris", "icon_img": "https://styles.redditmedia.com/t5_1/icon.png",
+            "snoovatar_img": "", "link_karma": 1250, "comment_karma": -3, "inbox_count": 2}"#).unwrap();
+        assert_eq!(me.avatar_url(), Some("https://styles.redditmedia.com/t5_1/icon.png"));
+        assert_eq!(me.karma(), "1.2k · -3");
+        assert_eq!(me.inbox_count, 2);
+        assert_eq!(Me::default().avatar_url(), None);
     }
-    
-    // Run the GUI in the main thread
-    eframe::run_native(
-        APP_NAME,
-        options,
-        Box::new(|_cc| Box::new(app)),
-    )
 }
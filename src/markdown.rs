@@ -0,0 +1,180 @@
+// Minimal Markdown rendering for comment bodies and the reply preview.
+//
+// Reddit comments use a small subset of Markdown, so rather than pulling in a full widget
+// library each block (paragraph, heading, list item, code block) is laid out as a single
+// `LayoutJob` with inline styles applied per run of text.
+use eframe::egui::{self, text::LayoutJob, FontFamily, FontId, TextFormat};
+use pulldown_cmark::{Event, HeadingLevel, Options, Parser, Tag, TagEnd};
+
+const QUOTE_INDENT: f32 = 12.0;
+
+pub fn show(ui: &mut egui::Ui, text: &str, size: f32) {
+    let mut renderer = Renderer::new(size);
+    ui.vertical(|ui| {
+        for event in Parser::new_ext(text, Options::ENABLE_STRIKETHROUGH) {
+            renderer.event(ui, event);
+        }
+        renderer.flush(ui);
+    });
+}
+
+#[derive(Default)]
+struct Renderer {
+    job: LayoutJob,
+    size: f32,
+    strong: bool,
+    emphasis: bool,
+    strikethrough: bool,
+    link: bool,
+    code_block: bool,
+    heading: Option<HeadingLevel>,
+    quote_depth: usize,
+    lists: Vec<Option<u64>>,  // Next number for ordered lists, None for bullets
+}
+
+impl Renderer {
+    fn new(size: f32) -> Self {
+        Renderer { size, ..Default::default() }
+    }
+
+    fn event(&mut self, ui: &mut egui::Ui, event: Event) {
+        match event {
+            Event::Start(tag) => match tag {
+                Tag::Paragraph => {}
+                Tag::Heading { level, .. } => {
+                    self.flush(ui);
+                    self.heading = Some(level);
+                }
+                Tag::BlockQuote => {
+                    self.flush(ui);
+                    self.quote_depth += 1;
+                }
+                Tag::CodeBlock(_) => {
+                    self.flush(ui);
+                    self.code_block = true;
+                }
+                Tag::List(start) => {
+                    self.flush(ui);
+                    self.lists.push(start);
+                }
+                Tag::Item => {
+                    self.flush(ui);
+                    let indent = "    ".repeat(self.lists.len().saturating_sub(1));
+                    let marker = match self.lists.last_mut() {
+                        Some(Some(number)) => {
+                            *number += 1;
+                            format!("{}{}. ", indent, *number - 1)
+                        }
+                        _ => format!("{}• ", indent),
+                    };
+                    self.append(ui, &marker);
+                }
+                Tag::Emphasis => self.emphasis = true,
+                Tag::Strong => self.strong = true,
+                Tag::Strikethrough => self.strikethrough = true,
+                Tag::Link { .. } => self.link = true,
+                _ => {}
+            },
+            Event::End(tag) => match tag {
+                TagEnd::Paragraph | TagEnd::Item => self.flush(ui),
+                TagEnd::Heading(_) => {
+                    self.flush(ui);
+                    self.heading = None;
+                }
+                TagEnd::BlockQuote => {
+                    self.flush(ui);
+                    self.quote_depth = self.quote_depth.saturating_sub(1);
+                }
+                TagEnd::CodeBlock => {
+                    // The last line of a fenced block always ends in a newline
+                    if self.job.text.ends_with('\n') {
+                        self.job.text.pop();
+                        if let Some(section) = self.job.sections.last_mut() {
+                            section.byte_range.end = section.byte_range.end.min(self.job.text.len());
+                        }
+                    }
+                    self.flush(ui);
+                    self.code_block = false;
+                }
+                TagEnd::List(_) => {
+                    self.flush(ui);
+                    self.lists.pop();
+                }
+                TagEnd::Emphasis => self.emphasis = false,
+                TagEnd::Strong => self.strong = false,
+                TagEnd::Strikethrough => self.strikethrough = false,
+                TagEnd::Link => self.link = false,
+                _ => {}
+            },
+            Event::Text(text) => self.append(ui, &text),
+            Event::Code(code) => {
+                let format = TextFormat {
+                    background: ui.visuals().code_bg_color,
+                    ..self.format(ui, true)
+                };
+                self.job.append(&code, 0.0, format);
+            }
+            Event::SoftBreak => self.append(ui, " "),
+            Event::HardBreak => self.append(ui, "\n"),
+            Event::Rule => {
+                self.flush(ui);
+                ui.separator();
+            }
+            _ => {}
+        }
+    }
+
+    fn append(&mut self, ui: &egui::Ui, text: &str) {
+        let format = self.format(ui, self.code_block);
+        self.job.append(text, 0.0, format);
+    }
+
+    fn format(&self, ui: &egui::Ui, monospace: bool) -> TextFormat {
+        let visuals = ui.visuals();
+        let size = match self.heading {
+            Some(HeadingLevel::H1) => self.size * 1.5,
+            Some(HeadingLevel::H2) => self.size * 1.3,
+            Some(_) => self.size * 1.15,
+            None => self.size,
+        };
+        let family = if monospace { FontFamily::Monospace } else { FontFamily::Proportional };
+        let color = if self.link {
+            visuals.hyperlink_color
+        } else if self.strong || self.heading.is_some() {
+            visuals.strong_text_color()
+        } else if self.quote_depth > 0 {
+            visuals.weak_text_color()
+        } else {
+            visuals.text_color()
+        };
+        let stroke = egui::Stroke::new(1.0, color);
+        TextFormat {
+            font_id: FontId::new(size, family),
+            color,
+            italics: self.emphasis,
+            underline: if self.link { stroke } else { egui::Stroke::NONE },
+            strikethrough: if self.strikethrough { stroke } else { egui::Stroke::NONE },
+            ..Default::default()
+        }
+    }
+
+    // Lay out whatever text has accumulated as one wrapped block
+    fn flush(&mut self, ui: &mut egui::Ui) {
+        if self.job.text.is_empty() {
+            return;
+        }
+        let job = std::mem::take(&mut self.job);
+        if self.quote_depth == 0 {
+            ui.add(egui::Label::new(job).wrap(true));
+            return;
+        }
+        ui.horizontal(|ui| {
+            let stroke = ui.visuals().widgets.noninteractive.bg_stroke;
+            for _ in 0..self.quote_depth {
+                let (rect, _) = ui.allocate_exact_size(egui::vec2(QUOTE_INDENT, self.size), egui::Sense::hover());
+                ui.painter().vline(rect.left() + 2.0, rect.y_range(), stroke);
+            }
+            ui.add(egui::Label::new(job).wrap(true));
+        });
+    }
+}
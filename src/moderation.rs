@@ -0,0 +1,98 @@
+// Moderator actions on posts and comments, and the mod queue feed.
+//
+// Which subreddits the account moderates comes from /subreddits/mine/moderator at sign-in, and
+// the Moderate menu only shows up on posts and comments in those. The mod queue is Reddit's
+// combined queue for all of them; the feed only draws posts, so reported comments stay on
+// reddit.com for now.
+use crate::{comments::Comment, Post};
+
+// Feed name for the mod queue; the slash keeps it from clashing with a real subreddit
+pub const QUEUE: &str = "mod/queue";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+    Approve,
+    Remove,
+    Spam,  // Removes it and trains the subreddit's spam filter
+    Lock,
+    Unlock,
+    Distinguish,
+    Undistinguish,
+}
+
+impl Action {
+    // What to offer for an item in its current state
+    pub fn offered(locked: bool, distinguished: bool) -> [Action; 5] {
+        [
+            Action::Approve,
+            Action::Remove,
+            Action::Spam,
+            if locked { Action::Unlock } else { Action::Lock },
+            if distinguished { Action::Undistinguish } else { Action::Distinguish },
+        ]
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Action::Approve => "✔ Approve",
+            Action::Remove => "Remove",
+            Action::Spam => "Remove as spam",
+            Action::Lock => "🔒 Lock",
+            Action::Unlock => "Unlock",
+            Action::Distinguish => "Distinguish as moderator",
+            Action::Undistinguish => "Undistinguish",
+        }
+    }
+
+    // Approving or removing takes an item out of the queue
+    pub fn settles(self) -> bool {
+        matches!(self, Action::Approve | Action::Remove | Action::Spam)
+    }
+}
+
+// Mirror an action that went through, so menus and markers update without a refetch
+pub fn apply_to_post(post: &mut Post, action: Action) {
+    match action {
+        Action::Approve => post.removed_by_category = None,
+        Action::Remove | Action::Spam => post.removed_by_category = Some("moderator".to_string()),
+        Action::Lock | Action::Unlock => post.locked = action == Action::Lock,
+        Action::Distinguish => post.distinguished = Some("moderator".to_string()),
+        Action::Undistinguish => post.distinguished = None,
+    }
+}
+
+pub fn apply_to_comment(comment: &mut Comment, action: Action) {
+    match action {
+        Action::Approve => comment.removed = false,
+        Action::Remove | Action::Spam => comment.removed = true,
+        Action::Lock | Action::Unlock => comment.locked = action == Action::Lock,
+        Action::Distinguish => comment.distinguished = Some("moderator".to_string()),
+        Action::Undistinguish => comment.distinguished = None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn offers_and_applies_actions() {
+        assert_eq!(Action::offered(false, false)[3..], [Action::Lock, Action::Distinguish]);
+        assert_eq!(Action::offered(true, true)[3..], [Action::Unlock, Action::Undistinguish]);
+
+        let mut post = Post::default();
+        for action in [Action::Spam, Action::Lock, Action::Distinguish] {
+            apply_to_post(&mut post, action);
+        }
+        assert_eq!(post.removal_notice(), Some("removed by moderators"));
+        assert!(post.locked);
+        assert_eq!(post.distinguished.as_deref(), Some("moderator"));
+        apply_to_post(&mut post, Action::Approve);
+        assert_eq!(post.removal_notice(), None);
+
+        let mut comment = Comment::default();
+        apply_to_comment(&mut comment, Action::Remove);
+        apply_to_comment(&mut comment, Action::Unlock);
+        assert!(comment.removed && !comment.locked);
+    }
+}
@@ -0,0 +1,143 @@
+// Browser sign-in through Reddit's authorization code flow for installed apps.
+//
+// Reddit sends the user back to a loopback redirect URI after they approve Rustle. A one-shot
+// listener on that port reads the code out of the request line, and the code is then traded
+// for a permanent refresh token, so the password never passes through the app.
+use anyhow::{Context, Result};
+use reqwest::Url;
+use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hasher},
+    io::{BufRead, BufReader, ErrorKind, Write},
+    net::TcpListener,
+    thread,
+    time::{Duration, Instant, SystemTime},
+};
+
+// Must match the redirect URI registered with the app on reddit.com/prefs/apps
+pub const REDIRECT_URI: &str = "http://127.0.0.1:65010/authorize_callback";
+const LISTEN_ADDR: &str = "127.0.0.1:65010";
+const CALLBACK_PATH: &str = "/authorize_callback";
+const SCOPES: &str = "identity read mysubreddits subscribe submit flair history save vote report edit account privatemessages modposts";
+// How long to wait for the user to finish approving in the browser
+const CALLBACK_TIMEOUT: Duration = Duration::from_secs(300);
+
+pub fn authorize_url(client_id: &str, state: &str) -> String {
+    Url::parse_with_params("https://www.reddit.com/api/v1/authorize", &[
+        ("client_id", client_id),
+        ("response_type", "code"),
+        ("state", state),
+        ("redirect_uri", REDIRECT_URI),
+        ("duration", "permanent"),
+        ("scope", SCOPES),
+    ]).map(String::from).unwrap_or_default()
+}
+
+// Hex string for OAuth state and device ids; unpredictable enough without a dedicated RNG
+pub fn random_token() -> String {
+    (0..2).map(|_| {
+        let mut hasher = RandomState::new().build_hasher();
+        hasher.write_u128(SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap_or_default().as_nanos());
+        format!("{:016x}", hasher.finish())
+    }).collect()
+}
+
+pub struct CallbackListener(TcpListener);
+
+impl CallbackListener {
+    // Bound before the browser opens so a busy port is reported instead of a hung sign-in
+    pub fn bind() -> Result<Self> {
+        let listener = TcpListener::bind(LISTEN_ADDR)
+            .with_context(|| format!("Couldn't listen on {} for the sign-in redirect", LISTEN_ADDR))?;
+        listener.set_nonblocking(true)?;
+        Ok(CallbackListener(listener))
+    }
+
+    // Block until Reddit redirects back with a code for this `state`
+    pub fn wait_for_code(&self, state: &str) -> Result<String> {
+        let started = Instant::now();
+        loop {
+            let mut stream = match self.0.accept() {
+                Ok((stream, _)) => stream,
+                Err(e) if e.kind() == ErrorKind::WouldBlock => {
+                    if started.elapsed() > CALLBACK_TIMEOUT {
+                        return Err(anyhow::anyhow!("Timed out waiting for Reddit to redirect back"));
+                    }
+                    thread::sleep(Duration::from_millis(100));
+                    continue;
+                }
+                Err(e) => return Err(e).context("Sign-in redirect failed"),
+            };
+
+            stream.set_nonblocking(false)?;
+            stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+            let mut request_line = String::new();
+            BufReader::new(&stream).read_line(&mut request_line)?;
+
+            // Browsers also ask for things like /favicon.ico; only the callback ends the wait
+            let Some(result) = parse_callback(&request_line, state) else {
+                let _ = stream.write_all(b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n");
+                continue;
+            };
+            let message = match &result {
+                Ok(_) => "Rustle is signed in. You can close this tab.".to_string(),
+                Err(e) => format!("Sign-in failed: {}", e),
+            };
+            let body = format!("<html><body><p>{}</p></body></html>", crate::html::escape(&message));
+            let _ = write!(stream, "HTTP/1.1 200 OK\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}", body.len(), body);
+            return result;
+        }
+    }
+}
+
+// None if the request isn't for the callback path, otherwise the code or Reddit's error
+fn parse_callback(request_line: &str, state: &str) -> Option<Result<String>> {
+    let target = request_line.split_whitespace().nth(1)?;
+    let url = Url::parse("http://localhost").ok()?.join(target).ok()?;
+    if url.path() != CALLBACK_PATH {
+        return None;
+    }
+
+    let param = |name: &str| url.query_pairs().find(|(key, _)| key == name).map(|(_, value)| value.into_owned());
+    if let Some(error) = param("error") {
+        let reason = if error == "access_denied" { "access was declined".to_string() } else { error };
+        return Some(Err(anyhow::anyhow!("Reddit reported: {}", reason)));
+    }
+    if param("state").as_deref() != Some(state) {
+        return Some(Err(anyhow::anyhow!("The sign-in response didn't match this request")));
+    }
+    Some(param("code").context("Reddit didn't send an authorization code"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builds_authorize_url() {
+        let url = Url::parse(&authorize_url("abc123", "xyz")).unwrap();
+        let param = |name: &str| url.query_pairs().find(|(key, _)| key == name).unwrap().1.into_owned();
+        assert_eq!(param("client_id"), "abc123");
+        assert_eq!(param("redirect_uri"), REDIRECT_URI);
+        assert_eq!(param("duration"), "permanent");
+        assert!(param("scope").split(' ').any(|scope| scope == "read"));
+    }
+
+    #[test]
+    fn parses_callback_requests() {
+        let code = parse_callback("GET /authorize_callback?state=s1&code=c0de-_x HTTP/1.1\r\n", "s1");
+        assert_eq!(code.unwrap().unwrap(), "c0de-_x");
+
+        assert!(parse_callback("GET /favicon.ico HTTP/1.1\r\n", "s1").is_none());
+        assert!(parse_callback("GET /authorize_callback?state=other&code=c HTTP/1.1", "s1").unwrap().is_err());
+        let denied = parse_callback("GET /authorize_callback?state=s1&error=access_denied HTTP/1.1", "s1");
+        assert!(denied.unwrap().unwrap_err().to_string().contains("declined"));
+    }
+
+    #[test]
+    fn random_tokens_differ() {
+        let token = random_token();
+        assert_eq!(token.len(), 32);
+        assert_ne!(token, random_token());
+    }
+}
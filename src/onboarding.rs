@@ -0,0 +1,365 @@
+// First-run setup wizard.
+//
+// Connecting to Reddit means registering an app on reddit.com first, which is easy to get wrong
+// from a single form. The wizard walks through it one step at a time: pick how to connect,
+// register a matching app, check the credentials against Reddit, and, when browsing without an
+// account, pick a few subreddits to fill the front page. Browsing without an account needs no app
+// at all, so registering one is left as an option on the connect step.
+use crate::{ensure_client, oauth, AuthMethod, RedditClient, Settings};
+use eframe::egui;
+use std::{
+    sync::{Arc, Mutex},
+    thread,
+};
+
+// Offered on the last step when browsing without an account
+const STARTER_SUBREDDITS: [&str; 16] = [
+    "AskReddit", "worldnews", "science", "technology", "programming", "rust", "gaming", "movies",
+    "books", "music", "space", "todayilearned", "explainlikeimfive", "aww", "pics", "food",
+];
+
+#[derive(Clone, Copy, PartialEq)]
+enum Step {
+    Method,
+    Register,
+    Connect,
+    Subreddits,
+}
+
+#[derive(Clone)]
+enum Check {
+    Idle,
+    Running(&'static str),
+    Failed(String),
+    Passed { username: Option<String>, refresh_token: Option<String> },
+}
+
+pub enum Outcome {
+    Finished(Box<Settings>),
+    Cancelled,
+    Demo,  // Browse the bundled sample feed instead
+}
+
+pub struct Wizard {
+    step: Step,
+    settings: Settings,  // Working copy; handed back to the app on finish
+    cancellable: bool,   // Only when re-running setup with working credentials
+    check: Arc<Mutex<Check>>,
+    new_subreddit: String,
+}
+
+impl Wizard {
+    pub fn new(settings: Settings, cancellable: bool) -> Self {
+        Wizard {
+            step: Step::Method,
+            settings,
+            cancellable,
+            check: Arc::new(Mutex::new(Check::Idle)),
+            new_subreddit: String::new(),
+        }
+    }
+
+    fn steps(&self) -> &'static [Step] {
+        match self.settings.account.auth_method {
+            AuthMethod::Anonymous => &[Step::Method, Step::Connect, Step::Subreddits],
+            _ => &[Step::Method, Step::Register, Step::Connect],
+        }
+    }
+
+    pub fn show(&mut self, ui: &mut egui::Ui) -> Option<Outcome> {
+        let mut outcome = None;
+        let steps = self.steps();
+        let index = steps.iter().position(|step| *step == self.step).unwrap_or(0);
+
+        ui.vertical_centered_justified(|ui| {
+            ui.add_space(ui.available_height() * 0.1);
+            let width = 460.0;
+            egui::Frame::group(ui.style())
+                .fill(self.settings.theme.card)
+                .rounding(8.0)
+                .show(ui, |ui| {
+                    ui.set_width(width);
+                    ui.add_space(20.0);
+                    ui.vertical_centered(|ui| {
+                        ui.heading("Welcome to Rustle!");
+                        ui.label(egui::RichText::new(format!("Step {} of {}", index + 1, steps.len())).weak());
+                    });
+                    ui.add_space(10.0);
+
+                    ui.vertical(|ui| {
+                        match self.step {
+                            Step::Method => {
+                                self.show_method(ui);
+                                // Nothing to lose by looking around first, unless already set up
+                                if !self.settings.has_credentials() {
+                                    ui.add_space(8.0);
+                                    ui.horizontal(|ui| {
+                                        ui.label(egui::RichText::new("Just looking?").weak());
+                                        if ui.link("Try Rustle with sample posts").clicked() {
+                                            outcome = Some(Outcome::Demo);
+                                        }
+                                    });
+                                }
+                            }
+                            Step::Register => self.show_register(ui),
+                            Step::Connect => self.show_connect(ui),
+                            Step::Subreddits => self.show_subreddits(ui),
+                        }
+                    });
+
+                    ui.add_space(15.0);
+                    ui.horizontal(|ui| {
+                        if index > 0 && ui.button("Back").clicked() {
+                            self.step = steps[index - 1];
+                        }
+                        if self.cancellable && ui.button("Cancel").clicked() {
+                            outcome = Some(Outcome::Cancelled);
+                        }
+                        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                            let last = index + 1 == steps.len();
+                            let ready = self.step != Step::Connect || self.passed() || self.public();
+                            let label = if last { "Finish" } else { "Next" };
+                            if ui.add_enabled(ready, egui::Button::new(label)).clicked() {
+                                if last {
+                                    self.settings.account.client_id = self.settings.account.client_id.trim().to_string();
+                                    outcome = Some(Outcome::Finished(Box::new(self.settings.clone())));
+                                } else {
+                                    self.step = steps[index + 1];
+                                }
+                            }
+                        });
+                    });
+                    ui.add_space(20.0);
+                });
+        });
+        outcome
+    }
+
+    fn show_method(&mut self, ui: &mut egui::Ui) {
+        ui.label("How would you like to connect to Reddit?");
+        ui.add_space(8.0);
+        let choices = [
+            (AuthMethod::Browser, "Sign in with your browser (recommended)",
+                "Approve Rustle on reddit.com. Your password stays with Reddit, and accounts with two-factor authentication work."),
+            (AuthMethod::Script, "Use a script app",
+                "Enter your username and password into Rustle. Doesn't work with two-factor authentication."),
+            (AuthMethod::Anonymous, "Browse without an account",
+                "Read r/popular, r/all and any public subreddit, with nothing to set up. Voting, replying and hiding need an account."),
+        ];
+        for (method, title, description) in choices {
+            let selected = self.settings.account.auth_method == method;
+            if ui.radio(selected, egui::RichText::new(title).strong()).clicked() && !selected {
+                self.settings.account.auth_method = method;
+                *self.check.lock().unwrap() = Check::Idle;
+            }
+            ui.indent(title, |ui| {
+                ui.label(egui::RichText::new(description).weak());
+            });
+            ui.add_space(6.0);
+        }
+    }
+
+    fn show_register(&mut self, ui: &mut egui::Ui) {
+        let script = self.settings.account.auth_method == AuthMethod::Script;
+        ui.label("Rustle talks to Reddit through an app registered to your account. It only takes a minute:");
+        ui.add_space(8.0);
+        ui.horizontal(|ui| {
+            ui.label("1. Sign in on");
+            if ui.link("reddit.com/prefs/apps").clicked() {
+                ui.ctx().open_url(egui::OpenUrl::new_tab("https://www.reddit.com/prefs/apps"));
+            }
+        });
+        ui.label("2. Click \"create another app...\" at the bottom of the page.");
+        ui.label(format!("3. Give it any name and choose \"{}\".", if script { "script" } else { "installed app" }));
+        ui.label("4. Use this as the redirect uri:");
+        ui.indent("redirect_uri", |ui| {
+            ui.horizontal(|ui| {
+                ui.label(egui::RichText::new(oauth::REDIRECT_URI).monospace());
+                if ui.small_button("📋 Copy").on_hover_text("Copy the redirect URI").clicked() {
+                    ui.output_mut(|output| output.copied_text = oauth::REDIRECT_URI.to_string());
+                }
+            });
+        });
+        if script {
+            ui.label("5. Click \"create app\", then note the ID under the app's name and its secret.");
+        } else {
+            ui.label("5. Click \"create app\", then note the ID shown under the app's name.");
+        }
+    }
+
+    fn show_connect(&mut self, ui: &mut egui::Ui) {
+        let label_width = 100.0;
+        let input_width = 300.0;
+        let mut changed = false;
+        let mut field = |ui: &mut egui::Ui, label: &str, value: &mut String, password: bool| {
+            ui.horizontal(|ui| {
+                ui.add_sized([label_width, 20.0], egui::Label::new(label));
+                changed |= ui.add_sized([input_width, 20.0], egui::TextEdit::singleline(value).password(password)).changed();
+            });
+        };
+
+        if self.settings.account.auth_method == AuthMethod::Anonymous {
+            ui.label("Rustle can read Reddit's public feeds without any setup. If you've registered an \"installed app\" \
+                on reddit.com/prefs/apps, enter its ID for a higher rate limit, or leave it empty.");
+            ui.add_space(8.0);
+        }
+        field(ui, "Client ID:", &mut self.settings.account.client_id, false);
+        if self.settings.account.auth_method == AuthMethod::Script {
+            field(ui, "Client Secret:", &mut self.settings.account.client_secret, true);
+            field(ui, "Username:", &mut self.settings.account.username, false);
+            field(ui, "Password:", &mut self.settings.account.password, true);
+        }
+        if changed {
+            *self.check.lock().unwrap() = Check::Idle;
+        }
+
+        ui.add_space(10.0);
+        let check = self.check.lock().unwrap().clone();
+        ui.horizontal(|ui| {
+            let action = match self.settings.account.auth_method {
+                AuthMethod::Browser => "Sign in with Reddit",
+                _ => "Check connection",
+            };
+            let running = matches!(check, Check::Running(_));
+            if ui.add_enabled(!running && !self.settings.account.client_id.trim().is_empty(), egui::Button::new(action)).clicked() {
+                self.start_check(ui.ctx());
+            }
+            match &check {
+                Check::Idle => {}
+                Check::Running(status) => {
+                    ui.spinner();
+                    ui.label(egui::RichText::new(*status).weak());
+                }
+                Check::Failed(error) => {
+                    ui.colored_label(ui.style().visuals.error_fg_color, error);
+                }
+                Check::Passed { username, .. } => {
+                    let text = match username {
+                        Some(name) => format!("✔ Connected as u/{}", name),
+                        None => "✔ Connected".to_string(),
+                    };
+                    ui.label(egui::RichText::new(text).strong());
+                }
+            }
+        });
+    }
+
+    fn show_subreddits(&mut self, ui: &mut egui::Ui) {
+        ui.label("Pick some subreddits to start with. They make up your front page and can be changed any time.");
+        ui.add_space(8.0);
+        ui.horizontal_wrapped(|ui| {
+            for name in STARTER_SUBREDDITS {
+                let picked = self.is_picked(name);
+                if ui.selectable_label(picked, format!("r/{}", name)).clicked() {
+                    self.toggle_pick(name);
+                }
+            }
+            // Anything added by hand that isn't in the starter list
+            let extra: Vec<String> = self.settings.account.subreddit_picks.iter()
+                .filter(|pick| !STARTER_SUBREDDITS.iter().any(|name| name.eq_ignore_ascii_case(pick)))
+                .cloned()
+                .collect();
+            for name in extra {
+                if ui.selectable_label(true, format!("r/{}", name)).clicked() {
+                    self.toggle_pick(&name);
+                }
+            }
+        });
+        ui.add_space(8.0);
+        ui.horizontal(|ui| {
+            let response = ui.add(egui::TextEdit::singleline(&mut self.new_subreddit).hint_text("Another subreddit"));
+            let submitted = response.lost_focus() && ui.input(|input| input.key_pressed(egui::Key::Enter));
+            if (ui.button("Add").clicked() || submitted) && !self.new_subreddit.trim().is_empty() {
+                let name = self.new_subreddit.trim().trim_start_matches("r/").to_string();
+                if !self.is_picked(&name) {
+                    self.settings.account.subreddit_picks.push(name);
+                }
+                self.new_subreddit.clear();
+            }
+        });
+    }
+
+    fn is_picked(&self, name: &str) -> bool {
+        self.settings.account.subreddit_picks.iter().any(|pick| pick.eq_ignore_ascii_case(name))
+    }
+
+    fn toggle_pick(&mut self, name: &str) {
+        if self.is_picked(name) {
+            self.settings.account.subreddit_picks.retain(|pick| !pick.eq_ignore_ascii_case(name));
+        } else {
+            self.settings.account.subreddit_picks.push(name.to_string());
+        }
+    }
+
+    // Browsing without an account or an app needs no check
+    fn public(&self) -> bool {
+        self.settings.account.auth_method == AuthMethod::Anonymous && self.settings.account.client_id.trim().is_empty()
+    }
+
+    fn passed(&mut self) -> bool {
+        let Check::Passed { username, refresh_token } = self.check.lock().unwrap().clone() else {
+            return false;
+        };
+        // Fold what the check learned into the settings being built
+        if let Some(username) = username {
+            self.settings.account.username = username;
+        }
+        if let Some(refresh_token) = refresh_token {
+            self.settings.account.refresh_token = refresh_token;
+        }
+        true
+    }
+
+    fn start_check(&mut self, ctx: &egui::Context) {
+        self.settings.account.client_id = self.settings.account.client_id.trim().to_string();
+        if self.settings.account.auth_method == AuthMethod::Anonymous && self.settings.account.device_id.is_empty() {
+            self.settings.account.device_id = oauth::random_token();
+        }
+
+        // The browser flow needs the redirect listener up before the authorize page opens
+        let browser = match self.settings.account.auth_method {
+            AuthMethod::Browser => match oauth::CallbackListener::bind() {
+                Ok(listener) => {
+                    let state = oauth::random_token();
+                    ctx.open_url(egui::OpenUrl::new_tab(oauth::authorize_url(&self.settings.account.client_id, &state)));
+                    Some((listener, state))
+                }
+                Err(e) => {
+                    *self.check.lock().unwrap() = Check::Failed(format!("{:#}", e));
+                    return;
+                }
+            },
+            _ => None,
+        };
+        *self.check.lock().unwrap() = Check::Running(if browser.is_some() {
+            "Waiting for you to approve Rustle in your browser..."
+        } else {
+            "Checking with Reddit..."
+        });
+
+        let settings = self.settings.clone();
+        let check = self.check.clone();
+        let ctx = ctx.clone();
+
+        thread::spawn(move || {
+            let code = browser.map(|(listener, state)| listener.wait_for_code(&state)).transpose();
+            let rt = tokio::runtime::Runtime::new().unwrap();
+            let result = rt.block_on(async {
+                match code? {
+                    Some(code) => {
+                        let mut client = RedditClient::new()?;
+                        let refresh_token = client.redeem_authorization_code(&settings.account.client_id, &code).await?;
+                        let me = client.get_me().await?;
+                        Ok(Check::Passed { username: Some(me.name), refresh_token: Some(refresh_token) })
+                    }
+                    None => {
+                        ensure_client(&Arc::new(Mutex::new(None)), &settings).await?;
+                        Ok::<_, anyhow::Error>(Check::Passed { username: None, refresh_token: None })
+                    }
+                }
+            });
+            *check.lock().unwrap() = result.unwrap_or_else(|e| Check::Failed(format!("{:#}", e)));
+            ctx.request_repaint();
+        });
+    }
+}
@@ -0,0 +1,155 @@
+// Where each kind of link goes when a post is opened: Rustle's own viewer, the browser, or an
+// external program such as an image viewer or a particular browser.
+//
+// Settings keep one choice per kind of content; `RedditApp::open_link` looks it up for every
+// title click and keyboard jump. The reader's per-domain choices still come first for articles.
+use crate::Post;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Content {
+    Image,
+    Video,
+    Article,
+    Reddit,  // Self posts and links to other threads
+}
+
+impl Content {
+    pub const ALL: [Content; 4] = [Content::Image, Content::Video, Content::Article, Content::Reddit];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Content::Image => "Images",
+            Content::Video => "Videos and GIFs",
+            Content::Article => "Articles",
+            Content::Reddit => "Reddit links",
+        }
+    }
+
+    // What "in Rustle" means for this kind of content
+    pub fn internal_label(self) -> &'static str {
+        match self {
+            Content::Image | Content::Video => "Media viewer",
+            Content::Article => "Reader",
+            Content::Reddit => "Comments",
+        }
+    }
+}
+
+pub fn classify(post: &Post) -> Content {
+    let hint = post.post_hint.as_deref();
+    if post.reddit_video().is_some() || post.animation().is_some() || matches!(hint, Some("hosted:video") | Some("rich:video")) {
+        return Content::Video;
+    }
+    let path = post.url.split(['?', '#']).next().unwrap_or_default().to_lowercase();
+    let image_file = [".jpg", ".jpeg", ".png", ".webp"].iter().any(|extension| path.ends_with(extension));
+    if hint == Some("image") || image_file {
+        return Content::Image;
+    }
+    if post.opens_externally() {
+        Content::Article
+    } else {
+        Content::Reddit
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum Opener {
+    #[default]
+    Automatic,        // The reader for articles that look readable, the browser for the rest
+    Internal,
+    Browser,          // The system's default browser
+    Command(String),  // A program to run; `{url}` marks where the link goes, or it's added last
+}
+
+impl Opener {
+    pub fn label(&self, content: Content) -> String {
+        match self {
+            Opener::Automatic => "Automatic".to_string(),
+            Opener::Internal => content.internal_label().to_string(),
+            Opener::Browser => "Default browser".to_string(),
+            Opener::Command(_) => "Program…".to_string(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OpenWith {
+    pub image: Opener,
+    pub video: Opener,
+    pub article: Opener,
+    pub reddit: Opener,
+}
+
+impl OpenWith {
+    pub fn get(&self, content: Content) -> &Opener {
+        match content {
+            Content::Image => &self.image,
+            Content::Video => &self.video,
+            Content::Article => &self.article,
+            Content::Reddit => &self.reddit,
+        }
+    }
+
+    pub fn get_mut(&mut self, content: Content) -> &mut Opener {
+        match content {
+            Content::Image => &mut self.image,
+            Content::Video => &mut self.video,
+            Content::Article => &mut self.article,
+            Content::Reddit => &mut self.reddit,
+        }
+    }
+}
+
+// Program and arguments for opening `url`, split on whitespace; quoting isn't supported
+pub fn command_line(command: &str, url: &str) -> Option<(String, Vec<String>)> {
+    let mut words = command.split_whitespace().map(str::to_string);
+    let program = words.next()?;
+    let mut args: Vec<String> = words.collect();
+    if args.iter().any(|arg| arg.contains("{url}")) {
+        for arg in &mut args {
+            *arg = arg.replace("{url}", url);
+        }
+    } else {
+        args.push(url.to_string());
+    }
+    Some((program, args))
+}
+
+// Started and left to run on its own
+pub fn run(command: &str, url: &str) -> Result<()> {
+    let (program, args) = command_line(command, url).context("No program set to open this with")?;
+    std::process::Command::new(&program)
+        .args(&args)
+        .spawn()
+        .with_context(|| format!("Couldn't start {}", program))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn post(json: serde_json::Value) -> Post {
+        serde_json::from_value(json).unwrap()
+    }
+
+    #[test]
+    fn sorts_posts_by_what_they_link_to() {
+        assert_eq!(classify(&post(serde_json::json!({"url": "https://i.redd.it/abc.jpeg", "domain": "i.redd.it"}))), Content::Image);
+        assert_eq!(classify(&post(serde_json::json!({"url": "https://youtu.be/x", "domain": "youtu.be", "post_hint": "rich:video"}))), Content::Video);
+        assert_eq!(classify(&post(serde_json::json!({"url": "https://blog.rust-lang.org/post", "domain": "blog.rust-lang.org"}))), Content::Article);
+        assert_eq!(classify(&post(serde_json::json!({"url": "https://www.reddit.com/r/rust/comments/x/", "domain": "self.rust", "is_self": true}))), Content::Reddit);
+    }
+
+    #[test]
+    fn builds_command_lines() {
+        let (program, args) = command_line("firefox --new-window", "https://example.com").unwrap();
+        assert_eq!((program.as_str(), args), ("firefox", vec!["--new-window".to_string(), "https://example.com".to_string()]));
+        let (_, args) = command_line("mpv --title={url} --loop", "u").unwrap();
+        assert_eq!(args, ["--title=u", "--loop"]);
+        assert!(command_line("  ", "u").is_none());
+    }
+}
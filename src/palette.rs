@@ -0,0 +1,140 @@
+// Dominant colors of post thumbnails, for the optional ambient accent on each card's frame.
+//
+// Worked out from the pixels egui already decoded for the thumbnail's texture: `AccentLoader`
+// sits in front of the stock image loaders and samples each image as it's handed over, so nothing
+// is decoded twice. Pixels are grouped into coarse color buckets, skipping the near-greys that
+// make up most backgrounds, and the fullest bucket's average is the accent.
+use eframe::egui::{
+    self,
+    load::{ImageLoadResult, ImageLoader, ImagePoll, LoadError, SizeHint},
+    Color32, ColorImage,
+};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+const SAMPLE_SIZE: usize = 32;  // Pixels per side sampled, spread evenly over the image
+const MIN_SATURATION: u8 = 40;  // Spread between a pixel's brightest and dimmest channel
+const ACCENT_OPACITY: f32 = 0.7;  // Keeps the accent from shouting over the card
+
+#[derive(Default)]
+pub struct Accents {
+    colors: Mutex<HashMap<String, Option<Color32>>>,  // None for greyscale images
+}
+
+impl Accents {
+    // The accent for an image, once it's been decoded for drawing
+    pub fn get(&self, uri: &str) -> Option<Color32> {
+        self.colors.lock().unwrap().get(uri).copied().flatten()
+    }
+}
+
+// Hands out what the image loaders installed before it decode, noting each image's accent on
+// the way through
+pub struct AccentLoader {
+    accents: Arc<Accents>,
+    inner: Vec<Arc<dyn ImageLoader + Send + Sync>>,  // Most recently added first, the order egui tries them in
+}
+
+impl AccentLoader {
+    pub const ID: &'static str = egui::generate_loader_id!(AccentLoader);
+
+    // Goes after the stock loaders, which it then stands in front of
+    pub fn install(ctx: &egui::Context, accents: Arc<Accents>) {
+        let inner = ctx.loaders().image.lock().iter().rev().cloned().collect();
+        ctx.add_image_loader(Arc::new(AccentLoader { accents, inner }));
+    }
+}
+
+impl ImageLoader for AccentLoader {
+    fn id(&self) -> &str {
+        Self::ID
+    }
+
+    fn load(&self, ctx: &egui::Context, uri: &str, size_hint: SizeHint) -> ImageLoadResult {
+        for loader in &self.inner {
+            match loader.load(ctx, uri, size_hint) {
+                Err(LoadError::NotSupported) => continue,
+                Ok(ImagePoll::Ready { image }) => {
+                    let mut colors = self.accents.colors.lock().unwrap();
+                    if !colors.contains_key(uri) {
+                        colors.insert(uri.to_string(), dominant_rgb(sample(&image)));
+                    }
+                    return Ok(ImagePoll::Ready { image });
+                }
+                result => return result,
+            }
+        }
+        Err(LoadError::NotSupported)
+    }
+
+    // The wrapped loaders are still registered themselves, so egui has them forget on its own
+    fn forget(&self, uri: &str) {
+        self.accents.colors.lock().unwrap().remove(uri);
+    }
+
+    fn forget_all(&self) {
+        self.accents.colors.lock().unwrap().clear();
+    }
+
+    fn byte_size(&self) -> usize {
+        self.accents.colors.lock().unwrap().keys().map(|uri| uri.len() + std::mem::size_of::<Option<Color32>>()).sum()
+    }
+}
+
+// Up to SAMPLE_SIZE × SAMPLE_SIZE opaque pixels, spread evenly over the image
+fn sample(image: &ColorImage) -> impl Iterator<Item = [u8; 3]> + '_ {
+    let [width, height] = image.size;
+    let (step_x, step_y) = ((width / SAMPLE_SIZE).max(1), (height / SAMPLE_SIZE).max(1));
+    (0..height).step_by(step_y)
+        .flat_map(move |y| (0..width).step_by(step_x).map(move |x| image.pixels[y * width + x]))
+        .filter(|pixel| pixel.a() == 255)
+        .map(|pixel| [pixel.r(), pixel.g(), pixel.b()])
+}
+
+fn dominant_rgb(pixels: impl Iterator<Item = [u8; 3]>) -> Option<Color32> {
+    // 3 bits a channel: 512 buckets, each with a count and running channel sums
+    let mut buckets: HashMap<u16, (u32, [u32; 3])> = HashMap::new();
+    for [r, g, b] in pixels {
+        let (high, low) = (r.max(g).max(b), r.min(g).min(b));
+        if high - low < MIN_SATURATION {
+            continue;
+        }
+        let key = ((r >> 5) as u16) << 6 | ((g >> 5) as u16) << 3 | (b >> 5) as u16;
+        let (count, sums) = buckets.entry(key).or_default();
+        *count += 1;
+        for (sum, channel) in sums.iter_mut().zip([r, g, b]) {
+            *sum += channel as u32;
+        }
+    }
+    let (count, [r, g, b]) = buckets.into_values().max_by_key(|(count, _)| *count)?;
+    Some(Color32::from_rgb((r / count) as u8, (g / count) as u8, (b / count) as u8))
+}
+
+// The card frame in an image's color
+pub fn stroke(accent: Color32) -> egui::Stroke {
+    egui::Stroke::new(1.5, accent.gamma_multiply(ACCENT_OPACITY))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn picks_the_commonest_color_over_greys() {
+        let teal = [20, 140, 150];
+        let pixels = [[250, 250, 250]; 60].into_iter()    // White background
+            .chain([teal; 30])
+            .chain([[200, 40, 40]; 10]);
+        let accent = dominant_rgb(pixels).unwrap();
+        assert_eq!((accent.r(), accent.g(), accent.b()), (20, 140, 150));
+        assert_eq!(dominant_rgb([[90, 90, 95]; 10].into_iter()), None);
+
+        let image = ColorImage::new([100, 60], Color32::from_rgb(20, 140, 150));
+        assert_eq!(sample(&image).count(), 34 * 60);  // Every third column, every row
+        let accent = dominant_rgb(sample(&image)).unwrap();
+        assert_eq!((accent.r(), accent.g(), accent.b()), (20, 140, 150));
+        assert_eq!(sample(&ColorImage::new([8, 8], Color32::TRANSPARENT)).count(), 0);
+    }
+}
@@ -0,0 +1,167 @@
+// User profile models: the account summary from /user/{name}/about, the mixed
+// post/comment listing from /user/{name}/overview, and the reader's own trophy case.
+use crate::Post;
+use serde::Deserialize;
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct UserAbout {
+    pub name: String,
+    pub link_karma: i64,
+    pub comment_karma: i64,
+    pub created_utc: f64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UserAboutResponse {
+    pub data: UserAbout,
+}
+
+// A trophy from /api/v1/me/trophies, e.g. "Verified Email" or "Five-Year Club"
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct Trophy {
+    pub name: String,
+    pub icon_70: String,  // 70px icon on redditstatic.com
+    pub description: Option<String>,
+    pub granted_at: Option<f64>,  // Unset for the oldest trophies
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TrophyList {
+    pub data: TrophyListData,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TrophyListData {
+    pub trophies: Vec<TrophyChild>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TrophyChild {
+    pub data: Trophy,
+}
+
+// A comment as it appears in someone's history, with a little context about its thread
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct ProfileComment {
+    pub id: String,
+    pub subreddit: String,
+    #[serde(deserialize_with = "crate::html::deserialize")]
+    pub body: String,
+    #[serde(deserialize_with = "crate::html::deserialize")]
+    pub link_title: String,
+    pub permalink: String,
+    pub score: i32,
+    pub created_utc: f64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "kind", content = "data")]
+pub enum ProfileItem {
+    #[serde(rename = "t3")]
+    Post(Box<Post>),
+    #[serde(rename = "t1")]
+    Comment(ProfileComment),
+}
+
+#[derive(Debug, Deserialize)]
+pub struct OverviewListing {
+    pub data: OverviewListingData,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct OverviewListingData {
+    pub children: Vec<ProfileItem>,
+    pub after: Option<String>,
+}
+
+// Profile screen state; `after` is None once the whole history has been loaded
+#[derive(Clone)]
+pub struct Profile {
+    pub name: String,
+    pub about: Option<UserAbout>,
+    pub items: Vec<ProfileItem>,
+    pub after: Option<String>,
+    pub loading: bool,
+    pub trophies: Vec<Trophy>,  // Only ever filled in on the reader's own profile
+}
+
+impl Profile {
+    pub fn new(name: String) -> Self {
+        Profile { name, about: None, items: Vec::new(), after: None, loading: true, trophies: Vec::new() }
+    }
+}
+
+// Account creation date, e.g. "March 16, 2012"
+pub fn cake_day(created_utc: f64) -> String {
+    const MONTHS: [&str; 12] = [
+        "January", "February", "March", "April", "May", "June",
+        "July", "August", "September", "October", "November", "December",
+    ];
+    let (year, month, day) = civil_date(created_utc);
+    format!("{} {}, {}", MONTHS[month as usize - 1], day, year)
+}
+
+// Year, month and day in UTC
+pub fn civil_date(created_utc: f64) -> (i64, i64, i64) {
+    // Civil-from-days conversion (Howard Hinnant's algorithm) on the proleptic Gregorian calendar
+    let days = (created_utc / 86_400.0).floor() as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 { shifted_month + 3 } else { shifted_month - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_cake_day() {
+        assert_eq!(cake_day(0.0), "January 1, 1970");
+        assert_eq!(cake_day(1331856000.0), "March 16, 2012");
+        assert_eq!(cake_day(951782400.0), "February 29, 2000");
+    }
+
+    #[test]
+    fn parses_trophy_case() {
+        let list: TrophyList = serde_json::from_str(r#"{"kind": "TrophyList", "data": {"trophies": [
+            {"kind": "t6", "data": {"icon_70": "https://www.redditstatic.com/awards2/verified_email-70.png",
+                "granted_at": null, "url": null, "name": "Verified Email", "award_id": "o", "id": null, "description": null}},
+            {"kind": "t6", "data": {"icon_70": "https://www.redditstatic.com/awards2/5_year_club-70.png",
+                "granted_at": 1331856000, "name": "Five-Year Club", "description": "Since 2012"}}
+        ]}}"#).unwrap();
+        let trophies: Vec<Trophy> = list.data.trophies.into_iter().map(|child| child.data).collect();
+        assert_eq!(trophies[0].name, "Verified Email");
+        assert_eq!(trophies[0].granted_at, None);
+        assert_eq!(trophies[1].granted_at.map(cake_day).as_deref(), Some("March 16, 2012"));
+    }
+
+    #[test]
+    fn parses_mixed_overview_listing() {
+        let listing: OverviewListing = serde_json::from_str(r#"{"kind": "Listing", "data": {
+            "after": "t1_c2",
+            "children": [
+                {"kind": "t3", "data": {"id": "p1", "title": "My project & me", "subreddit": "rust"}},
+                {"kind": "t1", "data": {"id": "c2", "body": "I agree > all", "link_title": "Q&A",
+                    "subreddit": "rust", "permalink": "/r/rust/comments/x/q/c2/", "score": 5}}
+            ]
+        }}"#).unwrap();
+
+        assert_eq!(listing.data.after.as_deref(), Some("t1_c2"));
+        let [ProfileItem::Post(post), ProfileItem::Comment(comment)] = listing.data.children.as_slice() else {
+            panic!("expected a post then a comment");
+        };
+        assert_eq!(post.title, "My project & me");
+        assert_eq!(comment.body, "I agree > all");
+        assert_eq!(comment.link_title, "Q&A");
+        assert_eq!(comment.score, 5);
+    }
+}
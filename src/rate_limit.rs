@@ -0,0 +1,216 @@
+// Client-side view of Reddit's API rate limit.
+//
+// Every OAuth response carries `X-Ratelimit-Remaining/Used/Reset` headers describing the current
+// window. The limiter records them and, once the remaining budget runs low, hands out send slots
+// spaced evenly across what's left of the window so bursts (fast scrolling, prefetching, image
+// loads) queue up instead of tipping the account into 429 responses.
+use reqwest::header::HeaderMap;
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+// Below this many requests left in the window, requests are spread out rather than sent at once
+const LOW_BUDGET: f64 = 30.0;
+// Reddit's windows are ten minutes; a reset further out than this is a garbled header
+const LONGEST_RESET: f64 = 3600.0;
+
+#[derive(Default)]
+pub struct RateLimiter {
+    state: Mutex<State>,
+}
+
+#[derive(Default)]
+struct State {
+    remaining: Option<f64>,
+    used: Option<u32>,
+    reset_at: Option<Instant>,
+    next_slot: Option<Instant>,  // Earliest time the next throttled request may go out
+}
+
+// Snapshot for the status indicator
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Quota {
+    pub remaining: u32,
+    pub used: u32,
+    pub resets_in: Duration,
+}
+
+impl Quota {
+    pub fn is_low(&self) -> bool {
+        (self.remaining as f64) < LOW_BUDGET
+    }
+}
+
+impl RateLimiter {
+    // Wait until this request fits in the budget
+    pub async fn acquire(&self) {
+        let wait = self.state.lock().unwrap().reserve(Instant::now());
+        if !wait.is_zero() {
+            tracing::info!(wait_ms = wait.as_millis() as u64, "Throttling request, rate limit budget is low");
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    pub fn record(&self, headers: &HeaderMap) {
+        let header = |name: &str| headers.get(name)?.to_str().ok()?.trim().parse::<f64>().ok();
+        let reset = header("x-ratelimit-reset").filter(|seconds| seconds.is_finite()).map(|seconds| seconds.min(LONGEST_RESET));
+        let (Some(remaining), Some(reset)) = (header("x-ratelimit-remaining"), reset) else {
+            return;
+        };
+        tracing::debug!(remaining, reset_secs = reset, "Rate limit updated");
+        let mut state = self.state.lock().unwrap();
+        state.remaining = Some(remaining);
+        state.used = header("x-ratelimit-used").map(|used| used as u32);
+        state.reset_at = Some(Instant::now() + Duration::from_secs_f64(reset.max(0.0)));
+    }
+
+    // Hold a background job until the window resets once fewer than `reserve` requests are left,
+    // leaving those to browsing. `waiting` hears when it will resume, then None once it does;
+    // `cancelled` is checked every second so a stopped job doesn't sit out the whole window.
+    pub async fn keep_reserve(&self, reserve: u32, cancelled: impl Fn() -> bool, mut waiting: impl FnMut(Option<Instant>)) {
+        let Some(quota) = self.quota().filter(|quota| quota.remaining < reserve) else {
+            return;
+        };
+        let resumes_at = Instant::now() + quota.resets_in;
+        waiting(Some(resumes_at));
+        while !cancelled() {
+            let left = resumes_at.saturating_duration_since(Instant::now());
+            if left.is_zero() {
+                break;
+            }
+            tokio::time::sleep(left.min(Duration::from_secs(1))).await;
+        }
+        waiting(None);
+    }
+
+    // True while a throttled request is still waiting for its slot, so a slow load isn't mistaken
+    // for a hung one
+    pub fn holding(&self, now: Instant) -> bool {
+        self.state.lock().unwrap().next_slot.is_some_and(|slot| slot > now)
+    }
+
+    pub fn quota(&self) -> Option<Quota> {
+        let state = self.state.lock().unwrap();
+        let reset_at = state.reset_at?;
+        Some(Quota {
+            remaining: state.remaining? as u32,
+            used: state.used.unwrap_or(0),
+            resets_in: reset_at.saturating_duration_since(Instant::now()),
+        })
+    }
+}
+
+impl State {
+    // Claim a send slot and return how long to wait for it
+    fn reserve(&mut self, now: Instant) -> Duration {
+        let (Some(remaining), Some(reset_at)) = (self.remaining, self.reset_at) else {
+            return Duration::ZERO;
+        };
+        if reset_at <= now {
+            // The window rolled over; the next response will tell us the new budget
+            *self = State::default();
+            return Duration::ZERO;
+        }
+        if remaining >= LOW_BUDGET {
+            self.remaining = Some(remaining - 1.0);
+            return Duration::ZERO;
+        }
+
+        let earliest = self.next_slot.map_or(now, |slot| slot.max(now));
+        let start = if remaining < 1.0 { earliest.max(reset_at) } else { earliest };
+        self.next_slot = Some(start + (reset_at - now).div_f64(remaining.max(1.0)));
+        self.remaining = Some((remaining - 1.0).max(0.0));
+        start - now
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    fn state(remaining: f64, reset_in: u64, now: Instant) -> State {
+        State {
+            remaining: Some(remaining),
+            reset_at: Some(now + Duration::from_secs(reset_in)),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn sends_immediately_with_plenty_of_budget() {
+        let now = Instant::now();
+        let mut state = state(500.0, 300, now);
+        assert_eq!(state.reserve(now), Duration::ZERO);
+        assert_eq!(state.reserve(now), Duration::ZERO);
+        assert_eq!(state.remaining, Some(498.0));
+    }
+
+    #[test]
+    fn spaces_requests_when_budget_is_low() {
+        let now = Instant::now();
+        let mut state = state(10.0, 100, now);
+        assert_eq!(state.reserve(now), Duration::ZERO);
+        // Ten requests over 100s leaves a 10s gap before the next one
+        assert_eq!(state.reserve(now), Duration::from_secs(10));
+        assert!(state.reserve(now) > Duration::from_secs(20));
+    }
+
+    #[test]
+    fn waits_for_reset_when_exhausted() {
+        let now = Instant::now();
+        let mut state = state(0.0, 42, now);
+        assert_eq!(state.reserve(now), Duration::from_secs(42));
+    }
+
+    #[test]
+    fn forgets_budget_after_window_resets() {
+        let now = Instant::now();
+        let mut state = state(0.0, 5, now);
+        assert_eq!(state.reserve(now + Duration::from_secs(6)), Duration::ZERO);
+        assert!(state.remaining.is_none());
+    }
+
+    #[test]
+    fn records_headers() {
+        let limiter = RateLimiter::default();
+        assert!(limiter.quota().is_none());
+
+        let mut headers = HeaderMap::new();
+        headers.insert("x-ratelimit-remaining", HeaderValue::from_static("587.0"));
+        headers.insert("x-ratelimit-used", HeaderValue::from_static("13"));
+        headers.insert("x-ratelimit-reset", HeaderValue::from_static("412"));
+        limiter.record(&headers);
+
+        let quota = limiter.quota().unwrap();
+        assert_eq!((quota.remaining, quota.used), (587, 13));
+        assert!(quota.resets_in <= Duration::from_secs(412) && quota.resets_in > Duration::from_secs(400));
+
+        // Nonsense resets are ignored or capped rather than overflowing the clock
+        headers.insert("x-ratelimit-reset", HeaderValue::from_static("inf"));
+        limiter.record(&headers);
+        assert!(limiter.quota().unwrap().resets_in <= Duration::from_secs(412));
+        headers.insert("x-ratelimit-reset", HeaderValue::from_static("1e300"));
+        limiter.record(&headers);
+        assert!(limiter.quota().unwrap().resets_in <= Duration::from_secs(3600));
+    }
+
+    #[tokio::test]
+    async fn stops_keeping_the_reserve_when_cancelled() {
+        let limiter = RateLimiter::default();
+        let mut headers = HeaderMap::new();
+        headers.insert("x-ratelimit-remaining", HeaderValue::from_static("50"));
+        headers.insert("x-ratelimit-reset", HeaderValue::from_static("600"));
+        limiter.record(&headers);
+
+        let mut heard = Vec::new();
+        limiter.keep_reserve(10, || false, |resumes_at| heard.push(resumes_at)).await;
+        assert!(heard.is_empty());
+
+        // A ten minute window, ended as soon as the job is stopped
+        limiter.keep_reserve(100, || true, |resumes_at| heard.push(resumes_at)).await;
+        assert!(heard[0].is_some_and(|at| at > Instant::now() + Duration::from_secs(590)));
+        assert_eq!(heard[1], None);
+    }
+}
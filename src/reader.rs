@@ -0,0 +1,307 @@
+// Reader mode: link posts to articles open inside Rustle as cleaned-up text and images, instead
+// of in the browser.
+//
+// The page is fetched directly (not through the Reddit client) and boiled down the way
+// Readability does it. Each paragraph scores points for its parent and grandparent; the
+// highest-scoring element is taken to be the article, and its headings, paragraphs, quotes,
+// lists and images are kept. Whether a domain opens here or in the browser can be set per domain.
+use crate::Post;
+use anyhow::{bail, Context, Result};
+use scraper::{node::Node, ElementRef, Html, Selector};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::Duration;
+
+const TIMEOUT: Duration = Duration::from_secs(20);
+const MIN_PARAGRAPH: usize = 25;   // Characters; shorter ones are usually captions, bylines or buttons
+const MIN_ARTICLE: usize = 250;    // Total characters of text for a page to count as an article
+
+// Hosts whose links are media or apps rather than something to read
+const NOT_ARTICLES: &[&str] = &[
+    "imgur.com", "i.imgur.com", "youtube.com", "youtu.be", "gfycat.com", "streamable.com",
+    "twitter.com", "x.com", "twitch.tv", "clips.twitch.tv", "instagram.com", "tiktok.com", "giphy.com",
+];
+
+// Elements that never hold the article itself
+const SKIPPED: &[&str] = &[
+    "script", "style", "noscript", "nav", "header", "footer", "aside", "form", "button", "iframe", "svg", "select", "template",
+];
+
+// Class and id fragments of page furniture: comment sections, sidebars, share bars, ads
+const UNLIKELY: &[&str] = &[
+    "comment", "sidebar", "footer", "share", "social", "related", "promo", "newsletter", "subscribe", "advert", "cookie", "popup", "menu",
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LinkOpen {
+    Reader,
+    Browser,
+}
+
+// Whether a link post's domain is likely to be something to read
+pub fn looks_like_article(domain: &str, post_hint: Option<&str>) -> bool {
+    let domain = domain.to_lowercase();
+    let domain = domain.strip_prefix("www.").unwrap_or(&domain);
+    !matches!(post_hint, Some("image" | "hosted:video" | "rich:video"))
+        && !NOT_ARTICLES.contains(&domain)
+        && !domain.ends_with(".gif")
+}
+
+// Lowercase, without "www.", so one setting covers both
+pub fn domain_key(domain: &str) -> String {
+    let domain = domain.trim().to_lowercase();
+    domain.strip_prefix("www.").unwrap_or(&domain).to_string()
+}
+
+// Per-domain choice first, then the default for article links
+pub fn opens_in_reader(post: &Post, by_default: bool, per_domain: &HashMap<String, LinkOpen>) -> bool {
+    if !post.opens_externally() {
+        return false;
+    }
+    match per_domain.get(&domain_key(&post.domain)) {
+        Some(choice) => *choice == LinkOpen::Reader,
+        None => by_default && looks_like_article(&post.domain, post.post_hint.as_deref()),
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Block {
+    Heading(String),
+    Paragraph(String),
+    Quote(String),
+    ListItem(String),
+    Code(String),
+    Image(String),  // Absolute URL
+    Caption(String),
+}
+
+#[derive(Clone, Debug)]
+pub struct Article {
+    pub title: String,
+    pub site: Option<String>,
+    pub blocks: Vec<Block>,
+}
+
+// Screen state: the post whose link is being read, and the article once it's been fetched
+#[derive(Clone)]
+pub struct Reader {
+    pub post: Post,
+    pub article: Option<Result<Article, String>>,
+}
+
+pub async fn fetch(url: &str) -> Result<Article> {
+    let client = reqwest::Client::builder()
+        .user_agent(crate::APP_USER_AGENT)
+        .timeout(TIMEOUT)
+        .build()?;
+    let response = client.get(url).send().await.context("Failed to load the page")?;
+    if !response.status().is_success() {
+        bail!("The site answered {}", response.status());
+    }
+    let is_html = response.headers().get(reqwest::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_none_or(|value| value.contains("html"));
+    if !is_html {
+        bail!("This link isn't a web page");
+    }
+    let base = response.url().clone();
+    let page = response.text().await.context("Failed to read the page")?;
+    extract(&page, &base).context("Couldn't find an article on this page")
+}
+
+pub fn extract(page: &str, base: &reqwest::Url) -> Option<Article> {
+    let document = Html::parse_document(page);
+    let meta = |property: &str| {
+        let selector = Selector::parse(&format!(r#"meta[property="{}"]"#, property)).unwrap();
+        document.select(&selector).next()
+            .and_then(|meta| meta.value().attr("content"))
+            .map(clean)
+            .filter(|content| !content.is_empty())
+    };
+    let first_text = |selector: &str| {
+        document.select(&Selector::parse(selector).unwrap()).next().map(|element| text_of(element)).filter(|text| !text.is_empty())
+    };
+    let title = meta("og:title").or_else(|| first_text("title")).or_else(|| first_text("h1")).unwrap_or_default();
+    let site = meta("og:site_name");
+
+    let mut scores = HashMap::new();
+    for paragraph in document.select(&Selector::parse("p, pre").unwrap()) {
+        if !usable(paragraph) {
+            continue;
+        }
+        let text = text_of(paragraph);
+        if text.chars().count() < MIN_PARAGRAPH {
+            continue;
+        }
+        let score = 1.0 + text.matches(',').count() as f32 + (text.chars().count() as f32 / 100.0).min(3.0);
+        let mut ancestors = paragraph.ancestors().filter_map(ElementRef::wrap);
+        if let Some(parent) = ancestors.next() {
+            *scores.entry(parent.id()).or_insert(0.0) += score;
+        }
+        if let Some(grandparent) = ancestors.next() {
+            *scores.entry(grandparent.id()).or_insert(0.0) += score / 2.0;
+        }
+    }
+    let best = scores.into_iter().max_by(|a, b| a.1.total_cmp(&b.1))?.0;
+    let content = ElementRef::wrap(document.tree.get(best)?)?;
+
+    let mut blocks = Vec::new();
+    collect(content, base, &mut blocks);
+    // The page title usually comes first again inside the article
+    if matches!(blocks.first(), Some(Block::Heading(heading)) if *heading == title) {
+        blocks.remove(0);
+    }
+    let length: usize = blocks.iter().map(|block| match block {
+        Block::Paragraph(text) | Block::Quote(text) | Block::ListItem(text) | Block::Code(text) => text.len(),
+        _ => 0,
+    }).sum();
+    (length >= MIN_ARTICLE).then_some(Article { title, site, blocks })
+}
+
+fn collect(element: ElementRef, base: &reqwest::Url, blocks: &mut Vec<Block>) {
+    for child in element.children().filter_map(ElementRef::wrap) {
+        if !usable_here(child) {
+            continue;
+        }
+        let push_text = |blocks: &mut Vec<Block>, make: fn(String) -> Block| {
+            let text = text_of(child);
+            if !text.is_empty() {
+                blocks.push(make(text));
+            }
+        };
+        match child.value().name() {
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => push_text(blocks, Block::Heading),
+            "p" => {
+                // Images inside a paragraph come before its text
+                collect_images(child, base, blocks);
+                push_text(blocks, Block::Paragraph);
+            }
+            "blockquote" => push_text(blocks, Block::Quote),
+            "li" => push_text(blocks, Block::ListItem),
+            "figcaption" => push_text(blocks, Block::Caption),
+            "pre" => {
+                let code: String = child.text().collect();
+                if !code.trim().is_empty() {
+                    blocks.push(Block::Code(code.trim_end().to_string()));
+                }
+            }
+            "img" => collect_images(child, base, blocks),
+            _ => collect(child, base, blocks),
+        }
+    }
+}
+
+fn collect_images(element: ElementRef, base: &reqwest::Url, blocks: &mut Vec<Block>) {
+    let selector = Selector::parse("img").unwrap();
+    let images = std::iter::once(element).filter(|element| element.value().name() == "img")
+        .chain(element.select(&selector));
+    for image in images {
+        // Lazy-loading pages keep the real address in a data attribute
+        let src = ["data-src", "src"].iter().find_map(|name| image.value().attr(name)).unwrap_or_default();
+        if src.starts_with("data:") {
+            continue;
+        }
+        if let Ok(url) = base.join(src.trim()) {
+            blocks.push(Block::Image(url.to_string()));
+        }
+    }
+}
+
+// Not page furniture, and not inside any
+fn usable(element: ElementRef) -> bool {
+    std::iter::once(element).chain(element.ancestors().filter_map(ElementRef::wrap)).all(usable_here)
+}
+
+fn usable_here(element: ElementRef) -> bool {
+    let value = element.value();
+    if SKIPPED.contains(&value.name()) {
+        return false;
+    }
+    let names = format!("{} {}", value.attr("class").unwrap_or_default(), value.id().unwrap_or_default()).to_lowercase();
+    !UNLIKELY.iter().any(|unlikely| names.contains(unlikely))
+}
+
+// Visible text with whitespace collapsed; scripts and the like inside are left out
+fn text_of(element: ElementRef) -> String {
+    let mut text = String::new();
+    for node in element.descendants() {
+        if let Node::Text(chunk) = node.value() {
+            let hidden = node.ancestors().filter_map(ElementRef::wrap)
+                .take_while(|ancestor| ancestor.id() != element.id())
+                .any(|ancestor| SKIPPED.contains(&ancestor.value().name()));
+            if !hidden {
+                text.push_str(chunk);
+                text.push(' ');
+            }
+        }
+    }
+    clean(&text)
+}
+
+fn clean(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+        .replace(" ,", ",").replace(" .", ".")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PAGE: &str = r#"<html><head>
+        <title>Ignored | Example News</title>
+        <meta property="og:title" content="Rust 2.0 announced">
+        <meta property="og:site_name" content="Example News">
+        <script>var tracking = "nope";</script>
+    </head><body>
+        <nav><p>Home, World, Politics, Business, Technology, Science, Sports</p></nav>
+        <div class="sidebar"><p>Sign up for our newsletter, it's great, really, we promise, honestly.</p></div>
+        <article>
+            <h1>Rust 2.0 announced</h1>
+            <p>The Rust project announced today, to some surprise, that a new major version is coming.</p>
+            <figure><img data-src="/images/crab.png" src="data:image/gif;base64,R0lGOD"><figcaption>Ferris, the mascot</figcaption></figure>
+            <h2>What changes</h2>
+            <p>Editions remain the way to evolve the language, but some long-deprecated items, finally, go away.</p>
+            <ul><li>Faster compiles</li><li>Better errors</li></ul>
+            <blockquote>It is a big day for the crab.</blockquote>
+            <p>More details, including a migration guide, are promised for the coming weeks and months.</p>
+            <div class="comments"><p>First! This is a very long comment that should, in fact, not be kept.</p></div>
+        </article>
+    </body></html>"#;
+
+    #[test]
+    fn extracts_the_article() {
+        let base = reqwest::Url::parse("https://news.example.com/2025/rust").unwrap();
+        let article = extract(PAGE, &base).unwrap();
+        assert_eq!(article.title, "Rust 2.0 announced");
+        assert_eq!(article.site.as_deref(), Some("Example News"));
+        assert_eq!(article.blocks[0], Block::Paragraph("The Rust project announced today, to some surprise, that a new major version is coming.".to_string()));
+        assert_eq!(article.blocks[1], Block::Image("https://news.example.com/images/crab.png".to_string()));
+        assert_eq!(article.blocks[2], Block::Caption("Ferris, the mascot".to_string()));
+        assert_eq!(article.blocks[3], Block::Heading("What changes".to_string()));
+        assert!(article.blocks.contains(&Block::ListItem("Better errors".to_string())));
+        assert!(article.blocks.contains(&Block::Quote("It is a big day for the crab.".to_string())));
+        assert!(!article.blocks.iter().any(|block| matches!(block, Block::Paragraph(text) if text.contains("First!"))));
+
+        // Too little text to be an article
+        assert!(extract("<html><body><p>Just a short page with a sentence or two, nothing more.</p></body></html>", &base).is_none());
+    }
+
+    #[test]
+    fn picks_reader_or_browser_per_domain() {
+        let post = |domain: &str, post_hint: Option<&str>| Post {
+            domain: domain.to_string(),
+            post_hint: post_hint.map(str::to_string),
+            ..Default::default()
+        };
+        let mut per_domain = HashMap::new();
+        assert!(opens_in_reader(&post("www.example.com", Some("link")), true, &per_domain));
+        assert!(!opens_in_reader(&post("www.example.com", Some("link")), false, &per_domain));
+        assert!(!opens_in_reader(&post("youtube.com", Some("rich:video")), true, &per_domain));
+        assert!(!opens_in_reader(&post("self.rust", None), true, &per_domain));
+
+        per_domain.insert(domain_key("WWW.Example.com"), LinkOpen::Browser);
+        per_domain.insert(domain_key("youtube.com"), LinkOpen::Reader);
+        assert!(!opens_in_reader(&post("example.com", Some("link")), true, &per_domain));
+        assert!(opens_in_reader(&post("youtube.com", None), false, &per_domain));
+    }
+}
@@ -0,0 +1,152 @@
+// Recorded API sessions, for reproducible bug reports and UI work without a network.
+//
+// `--record <file>` appends every API response the client receives to a JSON Lines file.
+// `--replay <file>` answers requests from such a recording instead of going to Reddit: each
+// request gets the next recorded response for the same method and path, the last one repeating
+// once they run out, and a 404 when the recording never saw it. Sign-in is answered with a
+// placeholder token, so tokens never end up in a recording and a replay needs no account.
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::Write,
+    path::Path,
+    sync::{Mutex, OnceLock},
+};
+
+static MODE: OnceLock<Mode> = OnceLock::new();
+
+enum Mode {
+    Record(Mutex<File>),
+    Replay(Mutex<HashMap<String, Responses>>),
+}
+
+#[derive(Serialize, Deserialize)]
+struct Entry {
+    request: String,  // See `key`
+    status: u16,
+    body: String,
+}
+
+struct Responses {
+    queue: Vec<(u16, String)>,
+    next: usize,
+}
+
+impl Responses {
+    // In recorded order, then the last one again
+    fn take(&mut self) -> (u16, String) {
+        let response = self.queue[self.next].clone();
+        self.next = (self.next + 1).min(self.queue.len() - 1);
+        response
+    }
+}
+
+pub fn start_recording(path: &Path) -> Result<()> {
+    let file = File::options().create(true).append(true).open(path)
+        .with_context(|| format!("Failed to open {} for recording", path.display()))?;
+    let _ = MODE.set(Mode::Record(Mutex::new(file)));
+    Ok(())
+}
+
+pub fn start_replay(path: &Path) -> Result<()> {
+    let text = fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let _ = MODE.set(Mode::Replay(Mutex::new(parse(&text)?)));
+    Ok(())
+}
+
+pub fn recording() -> bool {
+    matches!(MODE.get(), Some(Mode::Record(_)))
+}
+
+pub fn replaying() -> bool {
+    matches!(MODE.get(), Some(Mode::Replay(_)))
+}
+
+// Best effort; a failed write shouldn't take the session down with it
+pub fn record(method: &str, url: &reqwest::Url, status: u16, body: &[u8]) {
+    let Some(Mode::Record(file)) = MODE.get() else {
+        return;
+    };
+    let entry = Entry { request: key(method, url), status, body: String::from_utf8_lossy(body).into_owned() };
+    if let Ok(line) = serde_json::to_string(&entry) {
+        let _ = writeln!(file.lock().unwrap(), "{}", line);
+    }
+}
+
+// The recorded status and body for a request, while replaying
+pub fn answer(method: &str, url: &reqwest::Url) -> Option<(u16, String)> {
+    let Some(Mode::Replay(responses)) = MODE.get() else {
+        return None;
+    };
+    if url.path().ends_with("/access_token") {
+        return Some((200, r#"{"access_token": "replay", "expires_in": 86400}"#.to_string()));
+    }
+    let answer = responses.lock().unwrap().get_mut(&key(method, url))
+        .map_or((404, String::new()), Responses::take);
+    Some(answer)
+}
+
+fn parse(text: &str) -> Result<HashMap<String, Responses>> {
+    let mut responses: HashMap<String, Responses> = HashMap::new();
+    for (number, line) in text.lines().enumerate().filter(|(_, line)| !line.trim().is_empty()) {
+        let entry: Entry = serde_json::from_str(line).with_context(|| format!("Line {} isn't a recorded response", number + 1))?;
+        responses.entry(entry.request)
+            .or_insert_with(|| Responses { queue: Vec::new(), next: 0 })
+            .queue.push((entry.status, entry.body));
+    }
+    Ok(responses)
+}
+
+// e.g. "GET /r/rust/hot?t=day". The host and the public endpoints' ".json" suffix are dropped
+// so a session recorded signed in replays for a guest and the other way around, and the page size
+// so it replays whatever that's set to.
+fn key(method: &str, url: &reqwest::Url) -> String {
+    let path = url.path().trim_end_matches(".json");
+    let path = match path.trim_end_matches('/') {
+        "" => "/",
+        trimmed => trimmed,
+    };
+    let query: Vec<String> = url.query_pairs()
+        .filter(|(name, _)| name != "raw_json" && name != "limit")
+        .map(|(name, value)| format!("{}={}", name, value))
+        .collect();
+    if query.is_empty() {
+        format!("{} {}", method, path)
+    } else {
+        format!("{} {}?{}", method, path, query.join("&"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn url(url: &str) -> reqwest::Url {
+        reqwest::Url::parse(url).unwrap()
+    }
+
+    #[test]
+    fn keys_ignore_host_and_format() {
+        assert_eq!(key("GET", &url("https://oauth.reddit.com/r/rust/hot?raw_json=1&t=day")), "GET /r/rust/hot?t=day");
+        assert_eq!(key("GET", &url("https://www.reddit.com/r/rust/hot.json?t=day&raw_json=1")), "GET /r/rust/hot?t=day");
+        assert_eq!(key("GET", &url("https://www.reddit.com/.json?raw_json=1")), "GET /");
+        assert_eq!(key("GET", &url("https://oauth.reddit.com/?raw_json=1")), "GET /");
+        assert_eq!(key("GET", &url("https://oauth.reddit.com/r/rust?raw_json=1&after=t3_a&limit=50")), "GET /r/rust?after=t3_a");
+    }
+
+    #[test]
+    fn replays_responses_in_order() {
+        let recording = [
+            Entry { request: "GET /r/rust".to_string(), status: 200, body: "first".to_string() },
+            Entry { request: "GET /r/rust".to_string(), status: 200, body: "second".to_string() },
+        ].iter().map(|entry| serde_json::to_string(entry).unwrap()).collect::<Vec<_>>().join("\n");
+        let mut responses = parse(&recording).unwrap();
+        let rust = responses.get_mut("GET /r/rust").unwrap();
+        assert_eq!(rust.take(), (200, "first".to_string()));
+        assert_eq!(rust.take(), (200, "second".to_string()));
+        assert_eq!(rust.take(), (200, "second".to_string()));
+        assert!(parse("not json").is_err());
+    }
+}
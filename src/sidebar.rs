@@ -0,0 +1,38 @@
+// The subscription list beside the feed, filtered by the box at its top and grouped A to Z under
+// first letters so a few hundred subreddits stay easy to scan.
+
+// Names containing `query`, sorted case-insensitively and grouped under their first letter;
+// anything not starting with a letter goes under '#'
+pub fn by_letter<'a>(names: impl IntoIterator<Item = &'a String>, query: &str) -> Vec<(char, Vec<&'a str>)> {
+    let query = query.trim().trim_start_matches("r/").to_lowercase();
+    let mut names: Vec<&str> = names.into_iter()
+        .map(String::as_str)
+        .filter(|name| name.to_lowercase().contains(&query))
+        .collect();
+    names.sort_by_key(|name| name.to_lowercase());
+    let mut groups: Vec<(char, Vec<&str>)> = Vec::new();
+    for name in names {
+        let letter = name.chars().next()
+            .filter(char::is_ascii_alphabetic)
+            .map_or('#', |letter| letter.to_ascii_uppercase());
+        match groups.last_mut() {
+            Some((last, group)) if *last == letter => group.push(name),
+            _ => groups.push((letter, vec![name])),
+        }
+    }
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn groups_subscriptions_by_letter() {
+        let names: Vec<String> = ["rust", "AskReddit", "2007scape", "Rainmeter", "apple"].map(String::from).into();
+        let groups = by_letter(&names, "");
+        assert_eq!(groups, [('#', vec!["2007scape"]), ('A', vec!["apple", "AskReddit"]), ('R', vec!["Rainmeter", "rust"])]);
+        assert_eq!(by_letter(&names, "r/R"), [('A', vec!["AskReddit"]), ('R', vec!["Rainmeter", "rust"])]);
+        assert!(by_letter(&names, "zzz").is_empty());
+    }
+}
@@ -0,0 +1,547 @@
+// Spellchecking for the reply box, using hunspell dictionaries.
+//
+// A dictionary is a `.dic` word list and an `.aff` file of prefix and suffix rules, the format
+// LibreOffice, Firefox and most Linux distributions ship. Rustle looks for them where hunspell
+// does (DICPATH, the system's hunspell and myspell folders, ~/Library/Spelling on macOS) and in a
+// `dictionaries` folder of its own data directory, which is the place to put them on Windows.
+//
+// Only the parts of the format that decide which words exist are read: flags, prefixes and
+// suffixes with their conditions, and the flags that mark a stem as not a word on its own.
+// Compounding and morphology are left out, so a few valid compounds get underlined.
+use eframe::egui;
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    ops::Range,
+    path::PathBuf,
+    sync::{Arc, Mutex, OnceLock},
+    thread,
+};
+
+pub const OFF: &str = "off";  // Account setting that turns spellchecking off
+const SUGGESTIONS: usize = 6;
+
+#[derive(Default)]
+pub struct Dictionary {
+    words: HashSet<String>,  // Every stem and affixed form, lowercased
+    alphabet: Vec<char>,     // Letters to try when suggesting, most likely first
+    checked: Mutex<Option<(String, Vec<Range<usize>>)>>,     // The last text checked, and what was found in it
+    suggested: Mutex<HashMap<String, Option<Vec<String>>>>,  // By word; None while still searching
+}
+
+// One way of writing a condition character: a letter, any letter, or a bracketed set
+enum Condition {
+    Any,
+    Char(char),
+    Set(Vec<char>, bool),  // Negated when true
+}
+
+struct Affix {
+    strip: String,
+    add: String,
+    conditions: Vec<Condition>,
+}
+
+struct AffixClass {
+    prefix: bool,
+    cross: bool,  // May combine with an affix of the other kind
+    rules: Vec<Affix>,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum FlagType {
+    Char,
+    Long,     // Two characters each
+    Numeric,  // Comma separated numbers
+}
+
+impl FlagType {
+    fn split(self, flags: &str) -> Vec<String> {
+        match self {
+            FlagType::Char => flags.chars().map(String::from).collect(),
+            FlagType::Long => flags.chars().collect::<Vec<_>>().chunks(2).map(|pair| pair.iter().collect()).collect(),
+            FlagType::Numeric => flags.split(',').map(|flag| flag.trim().to_string()).filter(|flag| !flag.is_empty()).collect(),
+        }
+    }
+}
+
+impl Affix {
+    fn applies(&self, word: &str, prefix: bool) -> bool {
+        let chars: Vec<char> = word.chars().collect();
+        let (stripped, n) = (self.strip.chars().count(), self.conditions.len());
+        if chars.len() <= stripped || chars.len() < n {
+            return false;
+        }
+        let window = if prefix { &chars[..n] } else { &chars[chars.len() - n..] };
+        let strip_matches = if prefix { word.starts_with(&self.strip) } else { word.ends_with(&self.strip) };
+        strip_matches && window.iter().zip(&self.conditions).all(|(c, condition)| match condition {
+            Condition::Any => true,
+            Condition::Char(expected) => c == expected,
+            Condition::Set(set, negated) => set.contains(c) != *negated,
+        })
+    }
+
+    fn apply(&self, word: &str, prefix: bool) -> String {
+        if prefix {
+            format!("{}{}", self.add, &word[self.strip.len()..])
+        } else {
+            format!("{}{}", &word[..word.len() - self.strip.len()], self.add)
+        }
+    }
+}
+
+fn parse_conditions(condition: &str) -> Vec<Condition> {
+    if condition == "." {
+        return Vec::new();
+    }
+    let mut conditions = Vec::new();
+    let mut chars = condition.chars();
+    while let Some(c) = chars.next() {
+        conditions.push(match c {
+            '.' => Condition::Any,
+            '[' => {
+                let set: String = chars.by_ref().take_while(|&c| c != ']').collect();
+                match set.strip_prefix('^') {
+                    Some(negated) => Condition::Set(negated.chars().collect(), true),
+                    None => Condition::Set(set.chars().collect(), false),
+                }
+            }
+            c => Condition::Char(c),
+        });
+    }
+    conditions
+}
+
+// Dictionaries other than UTF-8 are almost all ISO 8859-1, where each byte is its own code point
+fn decode(bytes: &[u8]) -> String {
+    let head = String::from_utf8_lossy(&bytes[..bytes.len().min(4096)]).to_uppercase();
+    let utf8 = head.lines().any(|line| line.trim() == "SET UTF-8") || std::str::from_utf8(bytes).is_ok();
+    if utf8 {
+        String::from_utf8_lossy(bytes).into_owned()
+    } else {
+        bytes.iter().map(|&byte| byte as char).collect()
+    }
+}
+
+impl Dictionary {
+    pub fn parse(aff: &str, dic: &str) -> Self {
+        let mut flag_type = FlagType::Char;
+        let mut classes: HashMap<String, AffixClass> = HashMap::new();
+        let mut not_words = HashSet::new();  // NEEDAFFIX, ONLYINCOMPOUND and FORBIDDENWORD flags
+        let mut alphabet = Vec::new();
+
+        for line in aff.lines() {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            match fields.as_slice() {
+                ["FLAG", "long", ..] => flag_type = FlagType::Long,
+                ["FLAG", "num", ..] => flag_type = FlagType::Numeric,
+                ["TRY", letters, ..] => alphabet = letters.chars().filter(|c| c.is_lowercase()).collect(),
+                ["NEEDAFFIX" | "ONLYINCOMPOUND" | "FORBIDDENWORD", flag, ..] => {
+                    not_words.insert(flag.to_string());
+                }
+                [kind @ ("PFX" | "SFX"), flag, cross, count] if count.parse::<usize>().is_ok() => {
+                    classes.insert(flag.to_string(), AffixClass { prefix: *kind == "PFX", cross: *cross == "Y", rules: Vec::new() });
+                }
+                ["PFX" | "SFX", flag, strip, add, rest @ ..] => {
+                    let Some(class) = classes.get_mut(*flag) else {
+                        continue;
+                    };
+                    let add = add.split('/').next().unwrap_or_default();
+                    class.rules.push(Affix {
+                        strip: if *strip == "0" { String::new() } else { strip.to_string() },
+                        add: if add == "0" { String::new() } else { add.to_string() },
+                        conditions: parse_conditions(rest.first().copied().unwrap_or(".")),
+                    });
+                }
+                _ => {}
+            }
+        }
+        if alphabet.is_empty() {
+            alphabet = ('a'..='z').collect();
+        }
+
+        let mut words = HashSet::new();
+        // The first line is the word count
+        for line in dic.lines().skip(1) {
+            let entry = line.split(['\t', ' ']).next().unwrap_or_default();
+            let (stem, flags) = entry.split_once('/').unwrap_or((entry, ""));
+            if stem.is_empty() {
+                continue;
+            }
+            let flags = flag_type.split(flags);
+            if !flags.iter().any(|flag| not_words.contains(flag)) {
+                words.insert(stem.to_lowercase());
+            }
+            let affixes: Vec<&AffixClass> = flags.iter().filter_map(|flag| classes.get(flag)).collect();
+            for class in &affixes {
+                for rule in class.rules.iter().filter(|rule| rule.applies(stem, class.prefix)) {
+                    let affixed = rule.apply(stem, class.prefix);
+                    // A prefix and a suffix together, e.g. un+do+ing
+                    if class.cross && !class.prefix {
+                        for other in affixes.iter().filter(|other| other.prefix && other.cross) {
+                            for prefix in other.rules.iter().filter(|prefix| prefix.applies(&affixed, true)) {
+                                words.insert(prefix.apply(&affixed, true).to_lowercase());
+                            }
+                        }
+                    }
+                    words.insert(affixed.to_lowercase());
+                }
+            }
+        }
+        Dictionary { words, alphabet, ..Default::default() }
+    }
+
+    // `misspellings` for the reply box, which is laid out every frame; the text is only scanned
+    // again once it changes
+    pub fn misspelled(&self, text: &str) -> Vec<Range<usize>> {
+        let mut checked = self.checked.lock().unwrap();
+        if let Some((_, found)) = checked.as_ref().filter(|(last, _)| last == text) {
+            return found.clone();
+        }
+        let found = misspellings(self, text);
+        *checked = Some((text.to_string(), found.clone()));
+        found
+    }
+
+    // `suggest`, searched off the UI thread since two edits away can take a while; None until
+    // the search finishes
+    pub fn suggestions(self: &Arc<Self>, word: &str, ctx: &egui::Context) -> Option<Vec<String>> {
+        let mut suggested = self.suggested.lock().unwrap();
+        if let Some(found) = suggested.get(word) {
+            return found.clone();
+        }
+        suggested.insert(word.to_string(), None);
+
+        let dictionary = self.clone();
+        let word = word.to_string();
+        let ctx = ctx.clone();
+        thread::spawn(move || {
+            let found = dictionary.suggest(&word);
+            dictionary.suggested.lock().unwrap().insert(word, Some(found));
+            ctx.request_repaint();
+        });
+        None
+    }
+
+    fn knows(&self, word: &str) -> bool {
+        self.words.contains(word)
+    }
+
+    pub fn check(&self, word: &str) -> bool {
+        let word = word.replace('’', "'").to_lowercase();
+        self.knows(&word) || word.strip_suffix("'s").is_some_and(|stem| self.knows(stem))
+    }
+
+    // Known words one edit away, then two, cased like the word they replace
+    pub fn suggest(&self, word: &str) -> Vec<String> {
+        let lower = word.to_lowercase();
+        let mut found: Vec<String> = Vec::new();
+        let add = |candidate: String, found: &mut Vec<String>| {
+            if candidate != lower && !found.contains(&candidate) {
+                found.push(candidate);
+            }
+        };
+        let near = self.edits(&lower);
+        for candidate in near.iter().filter(|candidate| self.knows(candidate)) {
+            add(candidate.clone(), &mut found);
+        }
+        // Two words run together
+        let chars: Vec<char> = lower.chars().collect();
+        for split in 1..chars.len() {
+            let (left, right): (String, String) = (chars[..split].iter().collect(), chars[split..].iter().collect());
+            if self.knows(&left) && self.knows(&right) {
+                add(format!("{} {}", left, right), &mut found);
+            }
+        }
+        // Two edits away is a lot of candidates for a long word, and long words rarely need it
+        if found.len() < SUGGESTIONS && chars.len() <= 12 {
+            for candidate in near.iter().flat_map(|near| self.edits(near)).filter(|candidate| self.knows(candidate)) {
+                add(candidate, &mut found);
+                if found.len() >= SUGGESTIONS {
+                    break;
+                }
+            }
+        }
+        found.truncate(SUGGESTIONS);
+        found.into_iter().map(|suggestion| match_case(word, &suggestion)).collect()
+    }
+
+    // Every string one deletion, swap, replacement or insertion away
+    fn edits(&self, word: &str) -> Vec<String> {
+        let chars: Vec<char> = word.chars().collect();
+        let joined = |parts: &[&[char]]| parts.iter().flat_map(|part| part.iter()).collect::<String>();
+        let mut edits = Vec::new();
+        for i in 0..chars.len() {
+            if i + 1 < chars.len() {
+                edits.push(joined(&[&chars[..i], &[chars[i + 1], chars[i]], &chars[i + 2..]]));
+            }
+            for &c in &self.alphabet {
+                if c != chars[i] {
+                    edits.push(joined(&[&chars[..i], &[c], &chars[i + 1..]]));
+                }
+            }
+            edits.push(joined(&[&chars[..i], &chars[i + 1..]]));
+        }
+        for i in 0..=chars.len() {
+            for &c in &self.alphabet {
+                edits.push(joined(&[&chars[..i], &[c], &chars[i..]]));
+            }
+        }
+        edits
+    }
+}
+
+fn match_case(original: &str, suggestion: &str) -> String {
+    let letters: Vec<char> = original.chars().filter(|c| c.is_alphabetic()).collect();
+    if letters.len() > 1 && letters.iter().all(|c| c.is_uppercase()) {
+        return suggestion.to_uppercase();
+    }
+    if letters.first().is_some_and(|c| c.is_uppercase()) {
+        let mut chars = suggestion.chars();
+        return chars.next().map_or_else(String::new, |first| first.to_uppercase().chain(chars).collect());
+    }
+    suggestion.to_string()
+}
+
+// Byte ranges of the words in `text` the dictionary doesn't know. Links, subreddit and user
+// references, inline code and acronyms aren't checked.
+pub fn misspellings(dictionary: &Dictionary, text: &str) -> Vec<Range<usize>> {
+    let mut found = Vec::new();
+    let mut in_code = false;
+    for (start, token) in tokens(text) {
+        let ticks = token.matches('`').count();
+        let lower = token.to_lowercase();
+        let skip = in_code || ticks > 0 || lower.contains("://") || lower.starts_with("www.")
+            || ["r/", "u/", "/r/", "/u/"].iter().any(|prefix| lower.starts_with(prefix));
+        if ticks % 2 == 1 {
+            in_code = !in_code;
+        }
+        if skip {
+            continue;
+        }
+        for (offset, word) in words(token) {
+            let acronym = word.chars().all(|c| !c.is_lowercase());
+            if !acronym && !dictionary.check(word) {
+                found.push(start + offset..start + offset + word.len());
+            }
+        }
+    }
+    found
+}
+
+// Whitespace-separated pieces of `text`, with their byte offsets
+fn tokens(text: &str) -> impl Iterator<Item = (usize, &str)> {
+    text.split(char::is_whitespace)
+        .scan(0, |offset, token| {
+            let start = *offset;
+            *offset += token.len() + text[start + token.len()..].chars().next().map_or(0, char::len_utf8);
+            Some((start, token))
+        })
+        .filter(|(_, token)| !token.is_empty())
+}
+
+// Words in a token, with their byte offsets: runs of letters and apostrophes, quotes trimmed,
+// leaving out anything with digits in it
+fn words(token: &str) -> Vec<(usize, &str)> {
+    let mut words = Vec::new();
+    let mut start = None;
+    let boundaries = token.char_indices().map(Some).chain(std::iter::once(None));
+    for item in boundaries {
+        let inside = item.is_some_and(|(_, c)| c.is_alphanumeric() || c == '\'' || c == '’');
+        match (inside, start, item) {
+            (true, None, Some((index, _))) => start = Some(index),
+            (false, Some(from), _) => {
+                let to = item.map_or(token.len(), |(index, _)| index);
+                let raw = &token[from..to];
+                let word = raw.trim_start_matches(['\'', '’']);
+                let offset = from + raw.len() - word.len();
+                let word = word.trim_end_matches(['\'', '’']);
+                if word.chars().count() > 1 && !word.chars().any(|c| c.is_numeric()) {
+                    words.push((offset, word));
+                }
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    words
+}
+
+// The text edit layout for `text` with misspellings underlined
+pub fn layout(ui: &egui::Ui, dictionary: Option<&Dictionary>, text: &str, wrap_width: f32) -> Arc<egui::Galley> {
+    let font_id = egui::FontSelection::default().resolve(ui.style());
+    let color = ui.visuals().override_text_color.unwrap_or_else(|| ui.visuals().widgets.inactive.text_color());
+    let plain = egui::TextFormat::simple(font_id, color);
+    let underlined = egui::TextFormat { underline: egui::Stroke::new(1.5, ui.visuals().error_fg_color), ..plain.clone() };
+
+    let mut job = egui::text::LayoutJob::default();
+    let mut at = 0;
+    for range in dictionary.map(|dictionary| dictionary.misspelled(text)).unwrap_or_default() {
+        job.append(&text[at..range.start], 0.0, plain.clone());
+        job.append(&text[range.clone()], 0.0, underlined.clone());
+        at = range.end;
+    }
+    job.append(&text[at..], 0.0, plain);
+    job.wrap.max_width = wrap_width;
+    ui.fonts(|fonts| fonts.layout_job(job))
+}
+
+// The language the system is set to, e.g. "en_US" from LANG=en_US.UTF-8
+pub fn system_language() -> String {
+    ["LC_ALL", "LC_MESSAGES", "LANG"].iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .map(|value| value.split(['.', '@']).next().unwrap_or_default().to_string())
+        .find(|language| !language.is_empty() && language != "C" && language != "POSIX")
+        .unwrap_or_else(|| "en_US".to_string())
+}
+
+// Rustle's own folder first, so a dictionary put there wins over the system's
+pub fn search_path() -> Vec<PathBuf> {
+    let mut dirs: Vec<PathBuf> = directories::ProjectDirs::from("com", "spartanjubilee", "Rustle")
+        .map(|dirs| dirs.data_dir().join("dictionaries"))
+        .into_iter()
+        .collect();
+    if let Some(dicpath) = std::env::var_os("DICPATH") {
+        dirs.extend(std::env::split_paths(&dicpath));
+    }
+    dirs.extend(["/usr/share/hunspell", "/usr/share/myspell", "/usr/share/myspell/dicts", "/usr/local/share/hunspell", "/Library/Spelling"].map(PathBuf::from));
+    if let Some(home) = directories::BaseDirs::new() {
+        dirs.push(home.home_dir().join("Library/Spelling"));
+    }
+    dirs
+}
+
+// Languages with both halves of a dictionary somewhere on the search path
+fn installed() -> Vec<String> {
+    let mut languages: Vec<String> = search_path().iter()
+        .filter_map(|dir| fs::read_dir(dir).ok())
+        .flat_map(|entries| entries.flatten())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "dic") && path.with_extension("aff").exists())
+        .filter_map(|path| Some(path.file_stem()?.to_string_lossy().into_owned()))
+        .collect();
+    languages.sort();
+    languages.dedup();
+    languages
+}
+
+fn load(language: &str) -> Option<Dictionary> {
+    let dic = search_path().into_iter()
+        .map(|dir| dir.join(format!("{}.dic", language)))
+        .find(|path| path.exists() && path.with_extension("aff").exists())?;
+    let aff = fs::read(dic.with_extension("aff")).ok()?;
+    let words = fs::read(&dic).ok()?;
+    Some(Dictionary::parse(&decode(&aff), &decode(&words)))
+}
+
+// Dictionaries by language, loaded off the UI thread the first time each is needed
+#[derive(Clone, Default)]
+pub struct Speller {
+    loaded: Arc<Mutex<HashMap<String, Option<Arc<Dictionary>>>>>,  // None while loading, or when it isn't installed
+    installed: Arc<OnceLock<Vec<String>>>,
+}
+
+impl Speller {
+    pub fn dictionary(&self, language: &str, ctx: &egui::Context) -> Option<Arc<Dictionary>> {
+        let mut loaded = self.loaded.lock().unwrap();
+        if let Some(dictionary) = loaded.get(language) {
+            return dictionary.clone();
+        }
+        loaded.insert(language.to_string(), None);
+
+        let loaded = self.loaded.clone();
+        let language = language.to_string();
+        let ctx = ctx.clone();
+        thread::spawn(move || {
+            let dictionary = load(&language).map(Arc::new);
+            loaded.lock().unwrap().insert(language, dictionary);
+            ctx.request_repaint();
+        });
+        None
+    }
+
+    // Looked up once; a dictionary installed while Rustle is running shows up on the next start
+    pub fn installed(&self) -> &[String] {
+        self.installed.get_or_init(installed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const AFF: &str = "SET UTF-8\nTRY esiarntolcdugmphbyfvkwz\nNEEDAFFIX !\n\
+        PFX U Y 1\nPFX U 0 un .\n\
+        SFX G Y 2\nSFX G e ing e\nSFX G 0 ing [^e]\n\
+        SFX S Y 2\nSFX S y ies [^aeiou]y\nSFX S 0 s [^y]\n";
+    const DIC: &str = "6\nwrite/GS\ndo/UG\ncity/S\nhello\nworld\nkeep/!G";
+
+    #[test]
+    fn expands_affixes() {
+        let dictionary = Dictionary::parse(AFF, DIC);
+        for word in ["write", "writing", "writes", "undo", "undoing", "doing", "cities", "Hello", "world's", "keeping"] {
+            assert!(dictionary.check(word), "{}", word);
+        }
+        for word in ["writeing", "citys", "unwrite", "keep"] {
+            assert!(!dictionary.check(word), "{}", word);
+        }
+    }
+
+    #[test]
+    fn suggests_nearby_words() {
+        let dictionary = Dictionary::parse(AFF, DIC);
+        assert_eq!(dictionary.suggest("wrold").first().map(String::as_str), Some("world"));
+        assert_eq!(dictionary.suggest("Helo").first().map(String::as_str), Some("Hello"));
+        assert!(dictionary.suggest("helloworld").contains(&"hello world".to_string()));
+    }
+
+    #[test]
+    fn finds_misspellings() {
+        let dictionary = Dictionary::parse(AFF, DIC);
+        let text = "Helo wrold, see https://exmple.com r/wrold `wrold` NASA mp3 ‘hello’";
+        let found: Vec<&str> = misspellings(&dictionary, text).into_iter().map(|range| &text[range]).collect();
+        assert_eq!(found, ["Helo", "wrold", "see"]);
+
+        // The reply box asks again every frame; the answer is kept until the text changes
+        assert_eq!(dictionary.misspelled(text), misspellings(&dictionary, text));
+        assert_eq!(dictionary.checked.lock().unwrap().as_ref().map(|(last, _)| last.as_str()), Some(text));
+        assert_eq!(dictionary.misspelled("hello wrold"), vec![Range { start: 6, end: 11 }]);
+    }
+
+    #[test]
+    fn reads_long_and_numeric_flags() {
+        let aff = "FLAG long\nFORBIDDENWORD Zz\nSFX Aa Y 1\nSFX Aa 0 s .\nSFX Bb Y 1\nSFX Bb 0 ed .\n";
+        let dictionary = Dictionary::parse(aff, "3\nwalk/AaBb\njump/Aa\nthe/Zz");
+        for word in ["walk", "walks", "walked", "jump", "jumps"] {
+            assert!(dictionary.check(word), "{}", word);
+        }
+        for word in ["jumped", "the", "walkAa"] {
+            assert!(!dictionary.check(word), "{}", word);
+        }
+
+        let aff = "FLAG num\nONLYINCOMPOUND 7\nSFX 101 Y 1\nSFX 101 0 er .\n";
+        let dictionary = Dictionary::parse(aff, "2\nfast/101\nfoo/7,101");
+        assert!(dictionary.check("fast") && dictionary.check("faster") && dictionary.check("fooer"));
+        assert!(!dictionary.check("foo"));
+    }
+
+    #[test]
+    fn combines_affixes_only_when_both_cross() {
+        let aff = "PFX R Y 1\nPFX R 0 re .\nPFX N N 1\nPFX N 0 non .\nSFX D Y 1\nSFX D 0 ed .\n";
+        let dictionary = Dictionary::parse(aff, "1\nload/RND");
+        for word in ["reload", "nonload", "loaded", "reloaded"] {
+            assert!(dictionary.check(word), "{}", word);
+        }
+        assert!(!dictionary.check("nonloaded"));
+    }
+
+    #[test]
+    fn decodes_latin1_dictionaries() {
+        let aff = decode(b"SET ISO8859-1\nSFX S Y 1\nSFX S 0 s .\n");
+        let dic = decode(b"2\ncaf\xe9/S\nna\xefve");
+        let dictionary = Dictionary::parse(&aff, &dic);
+        for word in ["café", "cafés", "Naïve"] {
+            assert!(dictionary.check(word), "{}", word);
+        }
+    }
+}
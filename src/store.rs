@@ -0,0 +1,633 @@
+// Persistent copy of fetched content for offline use.
+//
+// Every feed page, comment thread and subscription list the app fetches is written to a small
+// SQLite database as a JSON snapshot keyed by what it is and when it was synced. When the
+// network is unreachable the app reads the last snapshot back, shows an offline banner, and
+// keeps retrying in the background until a fresh sync succeeds.
+//
+// It also remembers which posts have been read, for the feed's "hide read" mode, and holds the
+// local usage statistics (usage.rs), the post composer's drafts (submit.rs) and the subreddit
+// archive (archive.rs).
+use crate::{archive, comments::CommentThing, history, submit, usage, CachedFeed, FeedView, Post};
+use anyhow::{Context, Result};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+    collections::{HashMap, HashSet},
+    path::Path,
+    sync::{Arc, Mutex},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+const FEED: &str = "feed";
+const COMMENTS: &str = "comments";
+const SUBREDDITS: &str = "subreddits";
+// Read markers older than this are dropped, since Reddit rarely resurfaces posts that old
+const READ_RETENTION_SECS: i64 = 60 * 86_400;
+
+pub struct Store {
+    conn: Mutex<Connection>,
+    offline: Mutex<Option<i64>>,  // Oldest snapshot shown since the network went away
+}
+
+// A snapshot read back from the store
+pub struct Synced<T> {
+    pub data: T,
+    pub synced_at: i64,
+}
+
+impl Store {
+    pub fn new() -> Option<Arc<Self>> {
+        let dirs = directories::ProjectDirs::from("com", "spartanjubilee", "Rustle")?;
+        std::fs::create_dir_all(dirs.data_dir()).ok()?;
+        Store::open(&dirs.data_dir().join("offline.sqlite3")).ok().map(Arc::new)
+    }
+
+    pub fn open(path: &Path) -> Result<Self> {
+        let conn = Connection::open(path).context("Failed to open offline store")?;
+        Store::with_connection(conn)
+    }
+
+    fn with_connection(conn: Connection) -> Result<Self> {
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS snapshots (
+                kind TEXT NOT NULL,
+                key TEXT NOT NULL,
+                data TEXT NOT NULL,
+                synced_at INTEGER NOT NULL,
+                PRIMARY KEY (kind, key)
+            );
+            CREATE TABLE IF NOT EXISTS read_posts (
+                name TEXT PRIMARY KEY,
+                read_at INTEGER NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS usage_time (
+                hour INTEGER PRIMARY KEY,
+                seconds REAL NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS feed_time (
+                hour INTEGER NOT NULL,
+                feed TEXT NOT NULL,
+                seconds REAL NOT NULL,
+                PRIMARY KEY (hour, feed)
+            );
+            CREATE TABLE IF NOT EXISTS usage_posts (
+                name TEXT PRIMARY KEY,
+                subreddit TEXT NOT NULL,
+                viewed_at INTEGER NOT NULL,
+                opened INTEGER NOT NULL DEFAULT 0
+            );
+            CREATE TABLE IF NOT EXISTS post_drafts (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                data TEXT NOT NULL,
+                updated_at INTEGER NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS archive_posts (
+                name TEXT PRIMARY KEY,
+                subreddit TEXT NOT NULL,
+                created_utc REAL NOT NULL,
+                data TEXT NOT NULL,
+                first_seen INTEGER NOT NULL,
+                last_seen INTEGER NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS archive_posts_by_subreddit ON archive_posts (subreddit, created_utc);
+            CREATE TABLE IF NOT EXISTS archive_listings (
+                subreddit TEXT NOT NULL,
+                listing TEXT NOT NULL,
+                names TEXT NOT NULL,
+                taken_at INTEGER NOT NULL,
+                PRIMARY KEY (subreddit, listing)
+            );
+            CREATE TABLE IF NOT EXISTS history (
+                name TEXT PRIMARY KEY,
+                data TEXT NOT NULL,
+                visited_at INTEGER NOT NULL
+            );",
+        ).context("Failed to create offline store")?;
+        conn.execute("DELETE FROM read_posts WHERE read_at < ?1", params![now() - READ_RETENTION_SECS])
+            .context("Failed to prune read posts")?;
+        Ok(Store { conn: Mutex::new(conn), offline: Mutex::new(None) })
+    }
+
+    pub fn save_feed(&self, feed: &CachedFeed) -> Result<()> {
+        self.put(FEED, &feed_key(&feed.subreddit, feed.view.as_ref()), feed)
+    }
+
+    pub fn load_feed(&self, subreddit: &str, view: Option<&FeedView>) -> Result<Option<Synced<CachedFeed>>> {
+        self.get(FEED, &feed_key(subreddit, view))
+    }
+
+    pub fn save_comments(&self, permalink: &str, comments: &[CommentThing]) -> Result<()> {
+        self.put(COMMENTS, permalink, comments)
+    }
+
+    pub fn load_comments(&self, permalink: &str) -> Result<Option<Synced<Vec<CommentThing>>>> {
+        self.get(COMMENTS, permalink)
+    }
+
+    pub fn save_subreddits(&self, subreddits: &[String]) -> Result<()> {
+        self.put(SUBREDDITS, "", subreddits)
+    }
+
+    pub fn load_subreddits(&self) -> Result<Option<Synced<Vec<String>>>> {
+        self.get(SUBREDDITS, "")
+    }
+
+    pub fn mark_read(&self, names: &[String]) -> Result<()> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction().context("Failed to write read posts")?;
+        for name in names {
+            tx.execute("INSERT OR REPLACE INTO read_posts (name, read_at) VALUES (?1, ?2)", params![name, now()])
+                .context("Failed to write read posts")?;
+        }
+        tx.commit().context("Failed to write read posts")
+    }
+
+    pub fn read_posts(&self) -> Result<HashSet<String>> {
+        let conn = self.conn.lock().unwrap();
+        let mut statement = conn.prepare("SELECT name FROM read_posts").context("Failed to read read posts")?;
+        let names = statement.query_map([], |row| row.get(0)).context("Failed to read read posts")?;
+        names.collect::<rusqlite::Result<_>>().context("Failed to read read posts")
+    }
+
+    // Time goes into hourly buckets; a post opened without being counted as viewed counts as both
+    pub fn record_usage(&self, pending: &usage::Pending) -> Result<()> {
+        let now = now();
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction().context("Failed to write usage stats")?;
+        if pending.seconds > 0.0 {
+            tx.execute(
+                "INSERT INTO usage_time (hour, seconds) VALUES (?1, ?2)
+                 ON CONFLICT (hour) DO UPDATE SET seconds = seconds + excluded.seconds",
+                params![now / 3600, pending.seconds],
+            ).context("Failed to write usage stats")?;
+        }
+        for (feed, seconds) in &pending.feeds {
+            tx.execute(
+                "INSERT INTO feed_time (hour, feed, seconds) VALUES (?1, ?2, ?3)
+                 ON CONFLICT (hour, feed) DO UPDATE SET seconds = seconds + excluded.seconds",
+                params![now / 3600, feed, seconds],
+            ).context("Failed to write usage stats")?;
+        }
+        for (name, subreddit) in &pending.viewed {
+            tx.execute("INSERT OR IGNORE INTO usage_posts (name, subreddit, viewed_at) VALUES (?1, ?2, ?3)", params![name, subreddit, now])
+                .context("Failed to write usage stats")?;
+        }
+        for (name, subreddit) in &pending.opened {
+            tx.execute(
+                "INSERT INTO usage_posts (name, subreddit, viewed_at, opened) VALUES (?1, ?2, ?3, 1)
+                 ON CONFLICT (name) DO UPDATE SET opened = 1",
+                params![name, subreddit, now],
+            ).context("Failed to write usage stats")?;
+        }
+        tx.commit().context("Failed to write usage stats")
+    }
+
+    pub fn usage(&self, since: i64) -> Result<usage::Stats> {
+        let conn = self.conn.lock().unwrap();
+        let seconds = conn.query_row("SELECT COALESCE(SUM(seconds), 0.0) FROM usage_time WHERE hour >= ?1", params![since / 3600], |row| row.get(0))
+            .context("Failed to read usage stats")?;
+        let mut statement = conn.prepare(
+            "SELECT subreddit, COUNT(*), SUM(opened) FROM usage_posts WHERE viewed_at >= ?1
+             GROUP BY lower(subreddit) ORDER BY COUNT(*) DESC, lower(subreddit)",
+        ).context("Failed to read usage stats")?;
+        let subreddits = statement.query_map(params![since], |row| {
+            Ok(usage::SubredditStats { subreddit: row.get(0)?, viewed: row.get(1)?, opened: row.get(2)? })
+        }).context("Failed to read usage stats")?;
+        let subreddits = subreddits.collect::<rusqlite::Result<_>>().context("Failed to read usage stats")?;
+        Ok(usage::Stats { seconds, subreddits })
+    }
+
+    // Seconds spent in each feed, by lowercase name
+    pub fn feed_time(&self, since: i64) -> Result<HashMap<String, f64>> {
+        let conn = self.conn.lock().unwrap();
+        let mut statement = conn.prepare("SELECT feed, SUM(seconds) FROM feed_time WHERE hour >= ?1 GROUP BY feed")
+            .context("Failed to read usage stats")?;
+        let feeds = statement.query_map(params![since / 3600], |row| Ok((row.get(0)?, row.get(1)?)))
+            .context("Failed to read usage stats")?;
+        feeds.collect::<rusqlite::Result<_>>().context("Failed to read usage stats")
+    }
+
+    // Seconds-since-epoch of the last local midnight; SQLite knows the time zone, std doesn't
+    pub fn day_start(&self) -> Result<i64> {
+        day_start(&self.conn.lock().unwrap())
+    }
+
+    pub fn purge_usage(&self) -> Result<()> {
+        self.conn.lock().unwrap().execute_batch("DELETE FROM usage_time; DELETE FROM feed_time; DELETE FROM usage_posts;")
+            .context("Failed to purge usage stats")
+    }
+
+    // Writes over draft `id`, or adds a new one when it's None; returns the row it's under
+    pub fn save_draft(&self, id: Option<i64>, draft: &submit::Saved) -> Result<i64> {
+        let json = serde_json::to_string(draft).context("Failed to encode draft")?;
+        let conn = self.conn.lock().unwrap();
+        match id {
+            Some(id) => {
+                conn.execute("INSERT OR REPLACE INTO post_drafts (id, data, updated_at) VALUES (?1, ?2, ?3)", params![id, json, now()])
+                    .context("Failed to save draft")?;
+                Ok(id)
+            }
+            None => {
+                conn.execute("INSERT INTO post_drafts (data, updated_at) VALUES (?1, ?2)", params![json, now()])
+                    .context("Failed to save draft")?;
+                Ok(conn.last_insert_rowid())
+            }
+        }
+    }
+
+    // Most recently edited first; any that no longer decode are left out
+    pub fn drafts(&self) -> Result<Vec<(i64, submit::Saved)>> {
+        let conn = self.conn.lock().unwrap();
+        let mut statement = conn.prepare("SELECT id, data FROM post_drafts ORDER BY updated_at DESC, id DESC")
+            .context("Failed to read drafts")?;
+        let rows = statement.query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)))
+            .context("Failed to read drafts")?;
+        let rows = rows.collect::<rusqlite::Result<Vec<_>>>().context("Failed to read drafts")?;
+        Ok(rows.into_iter().filter_map(|(id, json)| Some((id, serde_json::from_str(&json).ok()?))).collect())
+    }
+
+    pub fn delete_draft(&self, id: i64) -> Result<()> {
+        self.conn.lock().unwrap().execute("DELETE FROM post_drafts WHERE id = ?1", params![id])
+            .context("Failed to delete draft")?;
+        Ok(())
+    }
+
+    // Keeps every post in a fetched listing and replaces the listing's snapshot, returning what
+    // changed since the last one
+    pub fn archive(&self, subreddit: &str, listing: archive::Listing, posts: &[Post]) -> Result<archive::Change> {
+        let subreddit_key = subreddit.to_lowercase();
+        let now = now();
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction().context("Failed to write the archive")?;
+        let previous: Option<String> = tx.query_row(
+            "SELECT names FROM archive_listings WHERE subreddit = ?1 AND listing = ?2",
+            params![subreddit_key, listing.api_name()],
+            |row| row.get(0),
+        ).optional().context("Failed to read the archive")?;
+        let previous: Option<Vec<String>> = previous.and_then(|names| serde_json::from_str(&names).ok());
+        for post in posts {
+            let data = serde_json::to_string(post).context("Failed to encode post")?;
+            tx.execute(
+                "INSERT INTO archive_posts (name, subreddit, created_utc, data, first_seen, last_seen) VALUES (?1, ?2, ?3, ?4, ?5, ?5)
+                 ON CONFLICT (name) DO UPDATE SET data = excluded.data, last_seen = excluded.last_seen",
+                params![post.name, subreddit_key, post.created_utc, data, now],
+            ).context("Failed to write the archive")?;
+        }
+        let names: Vec<&str> = posts.iter().map(|post| post.name.as_str()).collect();
+        tx.execute(
+            "INSERT OR REPLACE INTO archive_listings (subreddit, listing, names, taken_at) VALUES (?1, ?2, ?3, ?4)",
+            params![subreddit_key, listing.api_name(), serde_json::to_string(&names)?, now],
+        ).context("Failed to write the archive")?;
+        tx.commit().context("Failed to write the archive")?;
+        Ok(archive::diff(subreddit, listing, previous.as_deref(), posts))
+    }
+
+    // When any listing was last archived
+    pub fn last_archived(&self) -> Result<Option<i64>> {
+        self.conn.lock().unwrap()
+            .query_row("SELECT MAX(taken_at) FROM archive_listings", [], |row| row.get(0))
+            .context("Failed to read the archive")
+    }
+
+    pub fn archive_size(&self) -> Result<usize> {
+        self.conn.lock().unwrap()
+            .query_row("SELECT COUNT(*) FROM archive_posts", [], |row| row.get::<_, i64>(0))
+            .map(|count| count as usize)
+            .context("Failed to read the archive")
+    }
+
+    // A subreddit's newest archived posts, for its feed while offline
+    pub fn archived_feed(&self, subreddit: &str, limit: u32) -> Result<Option<Synced<Vec<Post>>>> {
+        let conn = self.conn.lock().unwrap();
+        let mut statement = conn.prepare(
+            "SELECT data, last_seen FROM archive_posts WHERE subreddit = ?1 ORDER BY created_utc DESC LIMIT ?2",
+        ).context("Failed to read the archive")?;
+        let rows = statement.query_map(params![subreddit.to_lowercase(), limit], |row| Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?)))
+            .context("Failed to read the archive")?;
+        let rows = rows.collect::<rusqlite::Result<Vec<_>>>().context("Failed to read the archive")?;
+        let Some(synced_at) = rows.iter().map(|(_, seen)| *seen).max() else {
+            return Ok(None);
+        };
+        let data = rows.iter().filter_map(|(json, _)| serde_json::from_str(json).ok()).collect();
+        Ok(Some(Synced { data, synced_at }))
+    }
+
+    // Replacing the row rather than updating it gives a revisit a fresh rowid, which orders visits
+    // within the same second
+    pub fn record_visit(&self, post: &Post) -> Result<()> {
+        let data = serde_json::to_string(post).context("Failed to encode post")?;
+        self.conn.lock().unwrap().execute(
+            "INSERT OR REPLACE INTO history (name, data, visited_at) VALUES (?1, ?2, ?3)",
+            params![post.name, data, now()],
+        ).context("Failed to record history")?;
+        Ok(())
+    }
+
+    // A page of visits, newest first, whose title, subreddit or author contain every word of
+    // `query`; matching ignores ASCII case, as LIKE does
+    pub fn history(&self, query: &str, offset: usize, limit: u32) -> Result<Vec<history::Visit>> {
+        let patterns = history::patterns(query);
+        let mut sql = "SELECT data, visited_at FROM history".to_string();
+        for index in 0..patterns.len() {
+            sql.push_str(if index == 0 { " WHERE " } else { " AND " });
+            sql.push_str(&format!(
+                "(ifnull(json_extract(data, '$.title'), '') || ' r/' || ifnull(json_extract(data, '$.subreddit'), '') \
+                 || ' u/' || ifnull(json_extract(data, '$.author'), '')) LIKE ?{} ESCAPE '\\'",
+                index + 3,
+            ));
+        }
+        sql.push_str(" ORDER BY visited_at DESC, rowid DESC LIMIT ?1 OFFSET ?2");
+
+        let offset = offset as i64;
+        let mut values: Vec<&dyn rusqlite::ToSql> = vec![&limit, &offset];
+        values.extend(patterns.iter().map(|pattern| pattern as &dyn rusqlite::ToSql));
+        let conn = self.conn.lock().unwrap();
+        let mut statement = conn.prepare(&sql).context("Failed to read history")?;
+        let rows = statement.query_map(values.as_slice(), |row| Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?)))
+            .context("Failed to read history")?;
+        let rows = rows.collect::<rusqlite::Result<Vec<_>>>().context("Failed to read history")?;
+        Ok(rows.into_iter()
+            .filter_map(|(json, visited_at)| Some(history::Visit { post: serde_json::from_str(&json).ok()?, visited_at }))
+            .collect())
+    }
+
+    pub fn clear_history(&self) -> Result<()> {
+        self.conn.lock().unwrap().execute("DELETE FROM history", [])
+            .context("Failed to clear history")?;
+        Ok(())
+    }
+
+    // Seconds-since-epoch of the oldest snapshot on screen, or None while online
+    pub fn offline(&self) -> Option<i64> {
+        *self.offline.lock().unwrap()
+    }
+
+    pub fn mark_offline(&self, synced_at: i64) {
+        let mut offline = self.offline.lock().unwrap();
+        *offline = Some(offline.map_or(synced_at, |oldest| oldest.min(synced_at)));
+    }
+
+    pub fn mark_online(&self) {
+        *self.offline.lock().unwrap() = None;
+    }
+
+    fn put<T: Serialize + ?Sized>(&self, kind: &str, key: &str, data: &T) -> Result<()> {
+        let json = serde_json::to_string(data).context("Failed to encode snapshot")?;
+        self.conn.lock().unwrap().execute(
+            "INSERT OR REPLACE INTO snapshots (kind, key, data, synced_at) VALUES (?1, ?2, ?3, ?4)",
+            params![kind, key, json, now()],
+        ).context("Failed to write offline store")?;
+        Ok(())
+    }
+
+    fn get<T: DeserializeOwned>(&self, kind: &str, key: &str) -> Result<Option<Synced<T>>> {
+        let row: Option<(String, i64)> = self.conn.lock().unwrap().query_row(
+            "SELECT data, synced_at FROM snapshots WHERE kind = ?1 AND key = ?2",
+            params![kind, key],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        ).optional().context("Failed to read offline store")?;
+
+        let Some((json, synced_at)) = row else {
+            return Ok(None);
+        };
+        let data = serde_json::from_str(&json).context("Failed to decode snapshot")?;
+        Ok(Some(Synced { data, synced_at }))
+    }
+}
+
+// Feeds are stored per subreddit and saved view, e.g. "rust" or "rust#top/Discussion"
+fn feed_key(subreddit: &str, view: Option<&FeedView>) -> String {
+    let subreddit = subreddit.to_lowercase();
+    match view {
+        Some(view) => format!("{}#{}/{}", subreddit, view.sort, view.flair),
+        None => subreddit,
+    }
+}
+
+// Local midnight as a Unix time; SQLite knows the local timezone, so a throwaway connection
+// answers when there's no store open
+pub fn local_day_start() -> Result<i64> {
+    day_start(&Connection::open_in_memory().context("Failed to read the local time")?)
+}
+
+fn day_start(conn: &Connection) -> Result<i64> {
+    conn.query_row(
+        "SELECT CAST(strftime('%s', 'now', 'localtime', 'start of day', 'utc') AS INTEGER)",
+        [],
+        |row| row.get(0),
+    ).context("Failed to read the local time")
+}
+
+pub fn now() -> i64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |since| since.as_secs() as i64)
+}
+
+// How long ago a snapshot was taken, e.g. "5 minutes"
+pub fn age(synced_at: i64) -> String {
+    let seconds = (now() - synced_at).max(0);
+    let (count, unit) = match seconds {
+        0..=59 => return "moments".to_string(),
+        60..=3599 => (seconds / 60, "minute"),
+        3600..=86_399 => (seconds / 3600, "hour"),
+        _ => (seconds / 86_400, "day"),
+    };
+    format!("{} {}{}", count, unit, if count == 1 { "" } else { "s" })
+}
+
+// Connection failures and timeouts mean "offline"; HTTP errors from Reddit itself don't
+pub fn is_network_error(error: &anyhow::Error) -> bool {
+    error.chain()
+        .filter_map(|cause| cause.downcast_ref::<reqwest::Error>())
+        .any(|error| error.is_connect() || error.is_timeout())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::comments::Comment;
+
+    fn store() -> Store {
+        Store::with_connection(Connection::open_in_memory().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn round_trips_snapshots() {
+        let store = store();
+        assert!(store.load_feed("rust", None).unwrap().is_none());
+
+        let feed = CachedFeed {
+            subreddit: "Rust".to_string(),
+            view: None,
+            posts: serde_json::from_str(r#"[{"id": "p1", "title": "Fish & chips &lt;3"}]"#).unwrap(),
+            after: Some("t3_p1".to_string()),
+        };
+        store.save_feed(&feed).unwrap();
+        store.save_subreddits(&["rust".to_string(), "programming".to_string()]).unwrap();
+
+        let stored = store.load_feed("rust", None).unwrap().unwrap();
+        assert_eq!(stored.data.posts[0].title, "Fish & chips &lt;3");
+        assert_eq!(stored.data.after.as_deref(), Some("t3_p1"));
+        assert!(stored.synced_at > 0);
+        assert_eq!(store.load_subreddits().unwrap().unwrap().data, ["rust", "programming"]);
+    }
+
+    #[test]
+    fn keeps_views_separate() {
+        let store = store();
+        let view = FeedView { name: "Top".to_string(), sort: "top".to_string(), ..FeedView::default() };
+        let feed = CachedFeed { subreddit: "rust".to_string(), view: Some(view.clone()), posts: Vec::new(), after: None };
+        store.save_feed(&feed).unwrap();
+
+        assert!(store.load_feed("rust", None).unwrap().is_none());
+        assert!(store.load_feed("rust", Some(&view)).unwrap().is_some());
+    }
+
+    #[test]
+    fn round_trips_comment_trees() {
+        let store = store();
+        let reply = Comment { id: "c2".to_string(), body: "a < b".to_string(), ..Default::default() };
+        let comment = Comment {
+            id: "c1".to_string(),
+            body: "Tom & Jerry".to_string(),
+            replies: vec![CommentThing::Comment(reply)],
+            ..Default::default()
+        };
+        store.save_comments("/r/rust/comments/p1/", &[CommentThing::Comment(comment)]).unwrap();
+
+        let stored = store.load_comments("/r/rust/comments/p1/").unwrap().unwrap();
+        let [CommentThing::Comment(comment)] = stored.data.as_slice() else {
+            panic!("expected one comment");
+        };
+        assert_eq!(comment.body, "Tom & Jerry");
+        let [CommentThing::Comment(reply)] = comment.replies.as_slice() else {
+            panic!("expected one reply");
+        };
+        assert_eq!(reply.body, "a < b");
+    }
+
+    #[test]
+    fn remembers_read_posts() {
+        let store = store();
+        store.mark_read(&["t3_a".to_string(), "t3_b".to_string()]).unwrap();
+        store.mark_read(&["t3_a".to_string()]).unwrap();
+        assert_eq!(store.read_posts().unwrap(), HashSet::from(["t3_a".to_string(), "t3_b".to_string()]));
+    }
+
+    #[test]
+    fn tallies_and_purges_usage() {
+        let store = store();
+        let post = |name: &str, subreddit: &str| (name.to_string(), subreddit.to_string());
+        store.record_usage(&usage::Pending {
+            seconds: 90.0,
+            feeds: HashMap::from([("home".to_string(), 60.0), ("rust".to_string(), 30.0)]),
+            viewed: vec![post("t3_a", "rust"), post("t3_b", "Rust"), post("t3_c", "pics")],
+            opened: vec![post("t3_a", "rust"), post("t3_d", "pics")],
+        }).unwrap();
+        store.record_usage(&usage::Pending {
+            seconds: 30.0,
+            feeds: HashMap::from([("rust".to_string(), 30.0)]),
+            viewed: vec![post("t3_a", "rust")],
+            opened: Vec::new(),
+        }).unwrap();
+
+        let stats = store.usage(0).unwrap();
+        assert_eq!(stats.seconds, 120.0);
+        let rows: Vec<(u64, u64)> = stats.subreddits.iter().map(|row| (row.viewed, row.opened)).collect();
+        assert_eq!(rows, [(2, 1), (2, 1)]);
+        assert_eq!(stats.subreddits[0].subreddit, "pics");
+        assert!(store.usage(now() + 3600).unwrap().subreddits.is_empty());
+        assert_eq!(store.feed_time(0).unwrap(), HashMap::from([("home".to_string(), 60.0), ("rust".to_string(), 60.0)]));
+        let day_start = store.day_start().unwrap();
+        assert!(day_start <= now() && day_start > now() - 86_400 - 3600);
+
+        store.purge_usage().unwrap();
+        let stats = store.usage(0).unwrap();
+        assert_eq!(stats.seconds, 0.0);
+        assert!(stats.subreddits.is_empty());
+        assert!(store.feed_time(0).unwrap().is_empty());
+    }
+
+    #[test]
+    fn tracks_oldest_snapshot_while_offline() {
+        let store = store();
+        assert_eq!(store.offline(), None);
+        store.mark_offline(200);
+        store.mark_offline(100);
+        store.mark_offline(300);
+        assert_eq!(store.offline(), Some(100));
+        store.mark_online();
+        assert_eq!(store.offline(), None);
+    }
+    #[test]
+    fn keeps_drafts_until_deleted() {
+        let store = store();
+        let draft = submit::Saved { subreddit: "rust".to_string(), title: "First".to_string(), ..submit::Saved::default() };
+        let first = store.save_draft(None, &draft).unwrap();
+        let second = store.save_draft(None, &submit::Saved { title: "Second".to_string(), ..draft.clone() }).unwrap();
+        assert_ne!(first, second);
+        assert_eq!(store.save_draft(Some(first), &submit::Saved { post_at: Some(60), ..draft }).unwrap(), first);
+
+        let drafts = store.drafts().unwrap();
+        assert_eq!(drafts.len(), 2);
+        let (_, saved) = drafts.iter().find(|(id, _)| *id == first).unwrap();
+        assert_eq!((saved.title.as_str(), saved.post_at), ("First", Some(60)));
+        store.delete_draft(first).unwrap();
+        assert_eq!(store.drafts().unwrap().iter().map(|(id, _)| *id).collect::<Vec<_>>(), [second]);
+    }
+    #[test]
+    fn archives_listings_and_reports_changes() {
+        let store = store();
+        let posts = |names: &[&str]| -> Vec<Post> {
+            // Posted in name order: t3_a first
+            names.iter()
+                .map(|name| serde_json::from_value(serde_json::json!({"name": name, "title": name, "created_utc": name.as_bytes()[3] as f64})).unwrap())
+                .collect()
+        };
+        assert_eq!(store.last_archived().unwrap(), None);
+        assert!(store.archive("Rust", archive::Listing::New, &posts(&["t3_a", "t3_b"])).unwrap().first);
+        let change = store.archive("Rust", archive::Listing::New, &posts(&["t3_b", "t3_c"])).unwrap();
+        assert_eq!((change.added, change.dropped), (vec!["t3_c".to_string()], 1));
+        assert!(store.archive("rust", archive::Listing::Top, &posts(&["t3_a"])).unwrap().first);
+
+        assert_eq!(store.archive_size().unwrap(), 3);
+        assert!(store.last_archived().unwrap().is_some());
+        let feed = store.archived_feed("RUST", 2).unwrap().unwrap();
+        assert_eq!(feed.data.iter().map(|post| post.name.as_str()).collect::<Vec<_>>(), ["t3_c", "t3_b"]);
+        assert!(store.archived_feed("golang", 2).unwrap().is_none());
+    }
+
+    #[test]
+    fn keeps_history_newest_first() {
+        let store = store();
+        let post = |name: &str| -> Post { serde_json::from_value(serde_json::json!({"name": name, "title": name})).unwrap() };
+        for name in ["t3_a", "t3_b", "t3_a"] {
+            store.record_visit(&post(name)).unwrap();
+        }
+        let names = |visits: Vec<history::Visit>| visits.into_iter().map(|visit| visit.post.name).collect::<Vec<_>>();
+        assert_eq!(names(store.history("", 0, 10).unwrap()), ["t3_a", "t3_b"]);
+        assert_eq!(names(store.history("", 0, 1).unwrap()), ["t3_a"]);
+        assert_eq!(names(store.history("", 1, 1).unwrap()), ["t3_b"]);
+        store.clear_history().unwrap();
+        assert!(store.history("", 0, 10).unwrap().is_empty());
+    }
+
+    #[test]
+    fn searches_titles_subreddits_and_authors() {
+        let store = store();
+        let visit = |name: &str, title: &str, subreddit: &str, author: &str| {
+            let post: Post = serde_json::from_value(serde_json::json!({"name": name, "title": title, "subreddit": subreddit, "author": author})).unwrap();
+            store.record_visit(&post).unwrap();
+        };
+        visit("t3_a", "Borrow checker tips", "rust", "ferris");
+        visit("t3_b", "Sourdough at 100% hydration", "Breadit", "crab");
+        let titles = |query: &str| store.history(query, 0, 10).unwrap().into_iter().map(|visit| visit.post.title).collect::<Vec<_>>();
+        assert_eq!(titles("").len(), 2);
+        assert_eq!(titles("r/breadit"), ["Sourdough at 100% hydration"]);
+        assert_eq!(titles("BORROW ferris"), ["Borrow checker tips"]);
+        assert!(titles("borrow crab").is_empty());
+        // LIKE's wildcards in a search are just characters
+        assert_eq!(titles("100%").len(), 1);
+        assert!(titles("r_st").is_empty());
+    }
+}
@@ -0,0 +1,672 @@
+// New posts: a composer window for text and link posts, checked against the subreddit's own rules
+// before anything is sent.
+//
+// Subreddits publish their rules for posts at /api/v1/{sub}/post_requirements: title length and
+// words it must or mustn't contain, whether a body is needed or allowed, which link domains are
+// banned or the only ones allowed, and whether flair is required. The draft is checked against
+// them as it's written, and each problem shows under the field it's about, so Reddit doesn't turn
+// the post away after the fact.
+//
+// Drafts are kept on disk as they're written, so closing the window or the app loses nothing,
+// and reopened from the Drafts window. A draft can also be scheduled to go out later; while Rustle
+// is running it submits scheduled posts once they're due and says how that went.
+use anyhow::{Context, Result};
+use eframe::egui;
+use regex::RegexBuilder;
+use serde::{Deserialize, Deserializer, Serialize};
+
+const TITLE_MAX: usize = 300;  // Reddit's own limit, whatever the subreddit says
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Kind {
+    #[default]
+    Text,
+    Link,
+}
+
+impl Kind {
+    // The `kind` field of /api/submit
+    pub fn api_name(self) -> &'static str {
+        match self {
+            Kind::Text => "self",
+            Kind::Link => "link",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+pub enum BodyPolicy {
+    #[serde(rename = "required")]
+    Required,
+    #[serde(rename = "notAllowed")]
+    NotAllowed,
+    #[default]
+    #[serde(other)]
+    Optional,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LinkPolicy {
+    Whitelist,  // Only domain_whitelist
+    Blacklist,  // Anything but domain_blacklist
+    #[default]
+    #[serde(other)]
+    None,
+}
+
+// Reddit sends null for most of these when the subreddit hasn't set them
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Requirements {
+    title_text_min_length: Option<usize>,
+    title_text_max_length: Option<usize>,
+    #[serde(deserialize_with = "or_default")]
+    title_required_strings: Vec<String>,  // At least one of them
+    #[serde(deserialize_with = "or_default")]
+    title_blacklisted_strings: Vec<String>,
+    #[serde(deserialize_with = "or_default")]
+    title_regexes: Vec<String>,  // At least one has to match
+    #[serde(deserialize_with = "or_default")]
+    body_restriction_policy: BodyPolicy,
+    body_text_min_length: Option<usize>,
+    body_text_max_length: Option<usize>,
+    #[serde(deserialize_with = "or_default")]
+    body_required_strings: Vec<String>,
+    #[serde(deserialize_with = "or_default")]
+    body_blacklisted_strings: Vec<String>,
+    #[serde(deserialize_with = "or_default")]
+    body_regexes: Vec<String>,
+    #[serde(deserialize_with = "or_default")]
+    link_restriction_policy: LinkPolicy,
+    #[serde(deserialize_with = "or_default")]
+    domain_whitelist: Vec<String>,
+    #[serde(deserialize_with = "or_default")]
+    domain_blacklist: Vec<String>,
+    #[serde(deserialize_with = "or_default")]
+    is_flair_required: bool,
+    pub guidelines_text: Option<String>,
+}
+
+fn or_default<'de, D: Deserializer<'de>, T: Default + Deserialize<'de>>(deserializer: D) -> std::result::Result<T, D::Error> {
+    Ok(Option::<T>::deserialize(deserializer)?.unwrap_or_default())
+}
+
+// One of the subreddit's post flairs, from /r/{sub}/api/link_flair_v2
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FlairTemplate {
+    pub id: String,
+    pub text: String,
+    #[serde(default)]
+    pub mod_only: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Field {
+    Title,
+    Body,
+    Link,
+    Flair,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Problem {
+    pub field: Field,
+    pub message: String,
+}
+
+// How far ahead a post can be scheduled, counted in
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Unit {
+    Minutes,
+    #[default]
+    Hours,
+    Days,
+}
+
+impl Unit {
+    const ALL: [Unit; 3] = [Unit::Minutes, Unit::Hours, Unit::Days];
+
+    fn label(self) -> &'static str {
+        match self {
+            Unit::Minutes => "minutes",
+            Unit::Hours => "hours",
+            Unit::Days => "days",
+        }
+    }
+
+    fn seconds(self) -> i64 {
+        match self {
+            Unit::Minutes => 60,
+            Unit::Hours => 3600,
+            Unit::Days => 86_400,
+        }
+    }
+}
+
+// What's kept on disk for a draft: the post itself and, when scheduled, when it goes out
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Saved {
+    pub subreddit: String,
+    pub kind: Kind,
+    pub title: String,
+    pub text: String,
+    pub url: String,
+    pub flair: Option<FlairTemplate>,
+    pub post_at: Option<i64>,         // Unix time; None for a plain draft
+    pub failure: Option<String>,      // Why the scheduled submission failed; it isn't retried
+}
+
+impl Saved {
+    // e.g. "r/rust: Borrow checker question"
+    pub fn summary(&self) -> String {
+        let title = if self.title.trim().is_empty() { "(untitled)" } else { self.title.trim() };
+        format!("r/{}: {}", self.subreddit, title)
+    }
+}
+
+// Scheduled drafts that are due, have never failed and aren't already being sent
+pub fn due(drafts: &[(i64, Saved)], sending: &[i64], now: i64) -> Vec<i64> {
+    drafts.iter()
+        .filter(|(id, saved)| saved.post_at.is_some_and(|at| at <= now) && saved.failure.is_none() && !sending.contains(id))
+        .map(|(id, _)| *id)
+        .collect()
+}
+
+// e.g. "in 2h 5m", or "due" once the time has come
+pub fn until(post_at: i64, now: i64) -> String {
+    let left = post_at - now;
+    if left <= 0 {
+        return "due".to_string();
+    }
+    // Rounded up to a whole minute before splitting, so 59m 50s carries over to the next hour
+    let minutes = (left + 59) / 60;
+    let (days, hours, minutes) = (minutes / 1440, minutes % 1440 / 60, minutes % 60);
+    match (days, hours) {
+        (0, 0) => format!("in {}m", minutes),
+        (0, _) => format!("in {}h {}m", hours, minutes),
+        _ => format!("in {}d {}h", days, hours),
+    }
+}
+
+#[derive(Clone)]
+pub struct Draft {
+    pub id: Option<i64>,  // Row it's saved under, once there's anything worth keeping
+    pub subreddit: String,
+    pub kind: Kind,
+    pub title: String,
+    pub text: String,
+    pub url: String,
+    pub flair: Option<FlairTemplate>,
+    pub requirements: Option<Requirements>,  // None until loaded, or when they couldn't be
+    pub flairs: Vec<FlairTemplate>,
+    pub flair_error: Option<String>,
+    pub loading: bool,
+    pub attempted: bool,  // Post was pressed, so problems with empty fields show too
+    pub submitting: bool,
+    pub error: Option<String>,
+    pub posted: Option<String>,  // URL of the new post, until the window closes
+    pub scheduling: bool,        // Post later instead of now
+    pub delay: u32,              // How many `unit`s from now
+    pub unit: Unit,
+}
+
+impl Draft {
+    pub fn new(subreddit: String) -> Self {
+        Draft {
+            id: None,
+            subreddit,
+            kind: Kind::Text,
+            title: String::new(),
+            text: String::new(),
+            url: String::new(),
+            flair: None,
+            requirements: None,
+            flairs: Vec::new(),
+            flair_error: None,
+            loading: true,
+            attempted: false,
+            submitting: false,
+            error: None,
+            posted: None,
+            scheduling: false,
+            delay: 1,
+            unit: Unit::Hours,
+        }
+    }
+
+    // Reopened from the Drafts window, or taken back from the undo window; a scheduled one comes
+    // back unscheduled until Schedule is pressed again
+    pub fn restore(id: Option<i64>, saved: Saved) -> Self {
+        Draft {
+            id,
+            kind: saved.kind,
+            title: saved.title,
+            text: saved.text,
+            url: saved.url,
+            flair: saved.flair,
+            ..Draft::new(saved.subreddit)
+        }
+    }
+
+    pub fn saved(&self) -> Saved {
+        Saved {
+            subreddit: self.subreddit.clone(),
+            kind: self.kind,
+            title: self.title.clone(),
+            text: self.text.clone(),
+            url: self.url.clone(),
+            flair: self.flair.clone(),
+            post_at: None,
+            failure: None,
+        }
+    }
+
+    // Nothing written yet, so nothing to keep
+    pub fn is_blank(&self) -> bool {
+        self.title.trim().is_empty() && self.text.trim().is_empty() && self.url.trim().is_empty()
+    }
+
+    // Unix time a scheduled post should go out, counting from `now`
+    pub fn post_at(&self, now: i64) -> i64 {
+        now + i64::from(self.delay.max(1)) * self.unit.seconds()
+    }
+
+    // Everything Reddit or the subreddit would turn the post down for, in field order
+    pub fn problems(&self) -> Vec<Problem> {
+        let mut problems = Vec::new();
+        let mut add = |field, message: String| problems.push(Problem { field, message });
+        let rules = self.requirements.as_ref();
+        let subreddit = &self.subreddit;
+
+        let title = self.title.trim();
+        let length = title.chars().count();
+        let min = rules.and_then(|rules| rules.title_text_min_length).unwrap_or(1);
+        let max = rules.and_then(|rules| rules.title_text_max_length).unwrap_or(TITLE_MAX).min(TITLE_MAX);
+        if length == 0 {
+            add(Field::Title, "A title is required".to_string());
+        } else if length < min {
+            add(Field::Title, format!("r/{} needs titles of at least {} characters", subreddit, min));
+        } else if length > max {
+            add(Field::Title, format!("Titles can be at most {} characters; this one is {}", max, length));
+        }
+        if let Some(rules) = rules {
+            for message in words(title, &rules.title_required_strings, &rules.title_blacklisted_strings, &rules.title_regexes) {
+                add(Field::Title, format!("r/{} requires titles to {}", subreddit, message));
+            }
+        }
+
+        match self.kind {
+            Kind::Text => {
+                let body = self.text.trim();
+                let length = body.chars().count();
+                match rules.map(|rules| rules.body_restriction_policy) {
+                    Some(BodyPolicy::Required) if body.is_empty() => add(Field::Body, format!("r/{} requires text in posts", subreddit)),
+                    Some(BodyPolicy::NotAllowed) if !body.is_empty() => {
+                        add(Field::Body, format!("r/{} only allows a title; leave the text empty", subreddit));
+                    }
+                    _ => {}
+                }
+                if let Some(rules) = rules.filter(|_| !body.is_empty()) {
+                    if let Some(min) = rules.body_text_min_length.filter(|min| length < *min) {
+                        add(Field::Body, format!("r/{} needs at least {} characters of text", subreddit, min));
+                    }
+                    if let Some(max) = rules.body_text_max_length.filter(|max| length > *max) {
+                        add(Field::Body, format!("r/{} allows at most {} characters of text; this is {}", subreddit, max, length));
+                    }
+                    for message in words(body, &rules.body_required_strings, &rules.body_blacklisted_strings, &rules.body_regexes) {
+                        add(Field::Body, format!("r/{} requires the text to {}", subreddit, message));
+                    }
+                }
+            }
+            Kind::Link => match link_domain(&self.url) {
+                None => add(Field::Link, "Enter the full link, starting with https://".to_string()),
+                Some(domain) => {
+                    let listed = |domains: &[String]| domains.iter().any(|listed| on_domain(&domain, listed));
+                    match rules.map(|rules| (rules.link_restriction_policy, rules)) {
+                        Some((LinkPolicy::Whitelist, rules)) if !listed(&rules.domain_whitelist) => add(Field::Link,
+                            format!("r/{} only takes links to {}", subreddit, rules.domain_whitelist.join(", "))),
+                        Some((LinkPolicy::Blacklist, rules)) if listed(&rules.domain_blacklist) => add(Field::Link,
+                            format!("r/{} doesn't allow links to {}", subreddit, domain)),
+                        _ => {}
+                    }
+                }
+            },
+        }
+
+        if self.flair.is_none() && rules.is_some_and(|rules| rules.is_flair_required) {
+            add(Field::Flair, format!("r/{} requires a flair", subreddit));
+        }
+        problems
+    }
+
+    // Whether a field has been written in, so its problems are worth showing before Post is pressed
+    fn touched(&self, field: Field) -> bool {
+        self.attempted || match field {
+            Field::Title => !self.title.trim().is_empty(),
+            Field::Body => !self.text.trim().is_empty(),
+            Field::Link => !self.url.trim().is_empty(),
+            Field::Flair => false,
+        }
+    }
+}
+
+// What's wrong with `text` against required, banned and pattern rules, phrased to follow "to"
+fn words(text: &str, required: &[String], banned: &[String], patterns: &[String]) -> Vec<String> {
+    let lower = text.to_lowercase();
+    let mut messages = Vec::new();
+    if !required.is_empty() && !required.iter().any(|word| lower.contains(&word.to_lowercase())) {
+        messages.push(format!("include one of: {}", required.join(", ")));
+    }
+    let used: Vec<&str> = banned.iter().filter(|word| lower.contains(&word.to_lowercase())).map(String::as_str).collect();
+    if !used.is_empty() {
+        messages.push(format!("leave out: {}", used.join(", ")));
+    }
+    // Reddit's patterns are Python's; any this crate can't read are left to Reddit to check
+    let patterns: Vec<_> = patterns.iter()
+        .filter_map(|pattern| RegexBuilder::new(pattern).case_insensitive(true).build().ok())
+        .collect();
+    if !patterns.is_empty() && !patterns.iter().any(|pattern| pattern.is_match(text)) {
+        messages.push("match the format in the posting guidelines".to_string());
+    }
+    messages
+}
+
+// "https://www.example.com/a" is on example.com
+fn link_domain(url: &str) -> Option<String> {
+    let url = reqwest::Url::parse(url.trim()).ok()?;
+    if url.scheme() != "http" && url.scheme() != "https" {
+        return None;
+    }
+    let host = url.host_str()?.to_lowercase();
+    Some(host.strip_prefix("www.").map(str::to_string).unwrap_or(host))
+}
+
+fn on_domain(domain: &str, listed: &str) -> bool {
+    let listed = listed.trim().trim_start_matches("www.").to_lowercase();
+    domain == listed || domain.ends_with(&format!(".{}", listed))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SubmitResponse {
+    json: SubmitResponseJson,
+}
+
+#[derive(Debug, Deserialize)]
+struct SubmitResponseJson {
+    #[serde(default)]
+    errors: Vec<Vec<serde_json::Value>>,  // [code, message, field] triples
+    data: Option<SubmitResponseData>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SubmitResponseData {
+    url: String,
+}
+
+impl SubmitResponse {
+    // Where the new post lives
+    pub fn into_url(self) -> Result<String> {
+        if let Some(error) = self.json.errors.first() {
+            let message = error.get(1).and_then(|m| m.as_str())
+                .or_else(|| error.first().and_then(|c| c.as_str()))
+                .unwrap_or("unknown error");
+            return Err(anyhow::anyhow!("Reddit rejected the post: {}", message));
+        }
+        self.json.data.map(|data| data.url).context("Reddit did not return the new post")
+    }
+}
+
+pub enum Action {
+    Submit,
+    Schedule,
+    Close,
+    Discard,  // Close and delete the saved draft
+}
+
+// The composer window; returns what the reader asked for this frame
+pub fn show(ctx: &egui::Context, draft: &mut Draft) -> Option<Action> {
+    let mut action = None;
+    let mut open = true;
+    let problems = draft.problems();
+    let error_color = ctx.style().visuals.error_fg_color;
+    let show_problems = |ui: &mut egui::Ui, draft: &Draft, field: Field| {
+        if !draft.touched(field) {
+            return;
+        }
+        for problem in problems.iter().filter(|problem| problem.field == field) {
+            ui.colored_label(error_color, &problem.message);
+        }
+    };
+
+    egui::Window::new(format!("New post in r/{}", draft.subreddit))
+        .open(&mut open)
+        .default_width(520.0)
+        .show(ctx, |ui| {
+            if draft.loading {
+                ui.horizontal(|ui| {
+                    ui.spinner();
+                    ui.label(egui::RichText::new(format!("Checking r/{}'s posting rules...", draft.subreddit)).weak());
+                });
+            }
+            if let Some(guidelines) = draft.requirements.as_ref().and_then(|rules| rules.guidelines_text.as_deref()).filter(|text| !text.trim().is_empty()) {
+                ui.label(egui::RichText::new(guidelines).weak());
+            }
+            ui.add_enabled_ui(!draft.submitting, |ui| {
+                ui.horizontal(|ui| {
+                    ui.selectable_value(&mut draft.kind, Kind::Text, "Text");
+                    ui.selectable_value(&mut draft.kind, Kind::Link, "Link");
+                });
+                ui.add(egui::TextEdit::singleline(&mut draft.title).hint_text("Title").desired_width(f32::INFINITY));
+                ui.label(egui::RichText::new(format!("{}/{}", draft.title.trim().chars().count(), TITLE_MAX)).small().weak());
+                show_problems(ui, draft, Field::Title);
+
+                match draft.kind {
+                    Kind::Text => {
+                        ui.add(egui::TextEdit::multiline(&mut draft.text)
+                            .hint_text("Text (optional); Markdown is supported")
+                            .desired_rows(6)
+                            .desired_width(f32::INFINITY));
+                        show_problems(ui, draft, Field::Body);
+                    }
+                    Kind::Link => {
+                        ui.add(egui::TextEdit::singleline(&mut draft.url).hint_text("https://").desired_width(f32::INFINITY));
+                        show_problems(ui, draft, Field::Link);
+                    }
+                }
+
+                ui.horizontal(|ui| {
+                    ui.label("Flair:");
+                    let selected = draft.flair.as_ref().map_or("None", |flair| flair.text.as_str()).to_string();
+                    egui::ComboBox::from_id_source("post_flair")
+                        .selected_text(selected)
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(&mut draft.flair, None, "None");
+                            for flair in draft.flairs.clone() {
+                                let text = flair.text.clone();
+                                let chosen = draft.flair.as_ref().is_some_and(|chosen| chosen.id == flair.id);
+                                if ui.selectable_label(chosen, text).clicked() {
+                                    draft.flair = Some(flair);
+                                }
+                            }
+                        });
+                    if let Some(error) = &draft.flair_error {
+                        ui.label(egui::RichText::new(error).weak());
+                    }
+                });
+                show_problems(ui, draft, Field::Flair);
+
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut draft.scheduling, "Post later, in");
+                    ui.add_enabled_ui(draft.scheduling, |ui| {
+                        ui.add(egui::DragValue::new(&mut draft.delay).clamp_range(1..=999));
+                        egui::ComboBox::from_id_source("post_delay_unit")
+                            .selected_text(draft.unit.label())
+                            .show_ui(ui, |ui| {
+                                for unit in Unit::ALL {
+                                    ui.selectable_value(&mut draft.unit, unit, unit.label());
+                                }
+                            });
+                    });
+                }).response.on_hover_text("Sent while Rustle is running; if it's closed then, as soon as it's opened again");
+            });
+
+            if let Some(error) = &draft.error {
+                ui.colored_label(error_color, error);
+            }
+            ui.separator();
+            ui.horizontal(|ui| {
+                if draft.submitting {
+                    ui.spinner();
+                    ui.label("Posting...");
+                    return;
+                }
+                // With problems, Post just points them all out
+                let label = if draft.scheduling { "Schedule" } else { "Post" };
+                if ui.add_enabled(!draft.loading, egui::Button::new(label)).clicked() {
+                    draft.attempted = true;
+                    if problems.is_empty() {
+                        action = Some(if draft.scheduling { Action::Schedule } else { Action::Submit });
+                    }
+                }
+                if ui.button("Close").on_hover_text("Kept in Drafts").clicked() {
+                    action = Some(Action::Close);
+                }
+                if draft.id.is_some() && ui.button("Discard").on_hover_text("Delete this draft").clicked() {
+                    action = Some(Action::Discard);
+                }
+            });
+        });
+    if !open {
+        action = Some(Action::Close);
+    }
+    action
+}
+
+pub enum DraftsAction {
+    Open(i64),
+    Delete(i64),
+}
+
+// The Drafts window: saved drafts and scheduled posts, newest first. False once it's closed.
+pub fn show_drafts(ctx: &egui::Context, drafts: &[(i64, Saved)], now: i64, actions: &mut Vec<DraftsAction>) -> bool {
+    let mut open = true;
+    egui::Window::new("Drafts")
+        .open(&mut open)
+        .default_width(480.0)
+        .show(ctx, |ui| {
+            if drafts.is_empty() {
+                ui.label(egui::RichText::new("No drafts. Anything written in the post composer is kept here.").weak());
+                return;
+            }
+            egui::ScrollArea::vertical().max_height(360.0).show(ui, |ui| {
+                for (id, saved) in drafts {
+                    ui.horizontal(|ui| {
+                        if ui.link(saved.summary()).on_hover_text("Open in the composer").clicked() {
+                            actions.push(DraftsAction::Open(*id));
+                        }
+                        match (&saved.failure, saved.post_at) {
+                            (Some(failure), _) => {
+                                ui.colored_label(ui.visuals().error_fg_color, "failed").on_hover_text(failure);
+                            }
+                            (None, Some(at)) => {
+                                ui.label(egui::RichText::new(format!("🕓 {}", until(at, now))).weak());
+                            }
+                            (None, None) => {}
+                        }
+                        if ui.small_button("🗑").on_hover_text("Delete").clicked() {
+                            actions.push(DraftsAction::Delete(*id));
+                        }
+                    });
+                }
+            });
+        });
+    open
+}
+
+// Desktop notification for how a scheduled post went
+#[cfg(feature = "notifications")]
+pub fn notify(summary: &str, body: &str) {
+    let _ = notify_rust::Notification::new()
+        .appname(crate::APP_NAME)
+        .summary(summary)
+        .body(body)
+        .show();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checks_drafts_against_subreddit_rules() {
+        let requirements: Requirements = serde_json::from_str(r#"{
+            "title_text_min_length": 10, "title_text_max_length": null,
+            "title_required_strings": ["[Help]", "[Question]"], "title_blacklisted_strings": ["urgent"],
+            "title_regexes": [], "body_restriction_policy": "required", "body_blacklisted_strings": null,
+            "link_restriction_policy": "whitelist", "domain_whitelist": ["github.com"], "domain_blacklist": [],
+            "is_flair_required": true, "guidelines_text": null
+        }"#).unwrap();
+        let mut draft = Draft::new("rust".to_string());
+        draft.requirements = Some(requirements);
+        draft.title = "URGENT borrow checker".to_string();
+        let fields: Vec<Field> = draft.problems().iter().map(|problem| problem.field).collect();
+        assert_eq!(fields, [Field::Title, Field::Title, Field::Body, Field::Flair]);
+        assert_eq!(draft.problems()[0].message, "r/rust requires titles to include one of: [Help], [Question]");
+
+        draft.title = "[help] borrow checker".to_string();
+        draft.text = "It says no".to_string();
+        draft.flair = Some(FlairTemplate { id: "f1".to_string(), text: "Help".to_string(), mod_only: false });
+        assert!(draft.problems().is_empty());
+
+        draft.kind = Kind::Link;
+        draft.url = "https://www.github.com/rust-lang/rust".to_string();
+        assert!(draft.problems().is_empty());
+        draft.url = "https://gitlab.com/x".to_string();
+        assert_eq!(draft.problems()[0].message, "r/rust only takes links to github.com");
+        draft.url = "github.com".to_string();
+        assert_eq!(draft.problems()[0].field, Field::Link);
+
+        let rejected: SubmitResponse = serde_json::from_str(r#"{"json": {"errors": [["SUBMIT_VALIDATION_FLAIR_REQUIRED", "Your post must contain post flair.", "flair"]]}}"#).unwrap();
+        assert_eq!(rejected.into_url().unwrap_err().to_string(), "Reddit rejected the post: Your post must contain post flair.");
+    }
+
+    #[test]
+    fn finds_scheduled_posts_that_are_due() {
+        let saved = |post_at, failure: Option<&str>| Saved {
+            subreddit: "rust".to_string(),
+            post_at,
+            failure: failure.map(str::to_string),
+            ..Saved::default()
+        };
+        let drafts = [(1, saved(None, None)), (2, saved(Some(100), None)), (3, saved(Some(200), None)),
+            (4, saved(Some(50), Some("Reddit rejected the post"))), (5, saved(Some(90), None))];
+        assert_eq!(due(&drafts, &[], 150), [2, 5]);
+        assert_eq!(due(&drafts, &[5], 150), [2]);
+        assert_eq!(drafts[0].1.summary(), "r/rust: (untitled)");
+
+        assert_eq!(until(100, 100), "due");
+        assert_eq!(until(100 + 30, 100), "in 1m");
+        assert_eq!(until(100 + 2 * 3600 + 300, 100), "in 2h 5m");
+        assert_eq!(until(100 + 86_400 + 3 * 3600, 100), "in 1d 3h");
+
+        let mut draft = Draft::new("rust".to_string());
+        assert!(draft.is_blank());
+        draft.title = "Borrow checker question".to_string();
+        draft.delay = 3;
+        draft.unit = Unit::Days;
+        assert_eq!(draft.post_at(1000), 1000 + 3 * 86_400);
+        let restored = Draft::restore(Some(7), Saved { post_at: Some(5), ..draft.saved() });
+        assert_eq!((restored.id, restored.title.as_str(), restored.scheduling), (Some(7), "Borrow checker question", false));
+        assert_eq!(serde_json::from_str::<Saved>(&serde_json::to_string(&draft.saved()).unwrap()).unwrap(), draft.saved());
+    }
+
+    #[test]
+    fn carries_rounded_up_minutes_into_the_hour() {
+        assert_eq!(until(7190, 0), "in 2h 0m");
+        assert_eq!(until(3599, 0), "in 1h 0m");
+        assert_eq!(until(3601, 0), "in 1h 1m");
+    }
+}
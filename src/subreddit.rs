@@ -0,0 +1,174 @@
+// Subreddit summary from /r/{name}/about, shown in the header strip above its feed and on the
+// Discover screen's cards, and its rules from /r/{name}/about/rules. Also why Reddit refuses to
+// list a subreddit at all, when it's quarantined, private or banned.
+use serde::Deserialize;
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct SubredditAbout {
+    pub display_name: String,
+    #[serde(deserialize_with = "crate::html::deserialize")]
+    pub title: String,
+    #[serde(deserialize_with = "crate::html::deserialize")]
+    pub public_description: String,
+    pub over18: bool,
+    pub subscribers: Option<u64>,        // Null for private subreddits
+    pub active_user_count: Option<u64>,  // Also null when the mods hide it
+    #[serde(deserialize_with = "crate::html::deserialize")]
+    pub banner_background_image: String,  // New-style banner, when set
+    #[serde(deserialize_with = "crate::html::deserialize")]
+    pub banner_img: String,  // Old-style banner
+    #[serde(deserialize_with = "crate::html::deserialize")]
+    pub community_icon: String,
+    #[serde(deserialize_with = "crate::html::deserialize")]
+    pub icon_img: String,
+    #[serde(skip)]
+    pub rules: Vec<Rule>,  // Fetched separately for the feed header; empty on Discover cards
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct Rule {
+    #[serde(deserialize_with = "crate::html::deserialize")]
+    pub short_name: String,
+    #[serde(deserialize_with = "crate::html::deserialize")]
+    pub description: String,  // Markdown, often empty
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RulesResponse {
+    #[serde(default)]
+    pub rules: Vec<Rule>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SubredditAboutResponse {
+    pub data: SubredditAbout,
+}
+
+impl SubredditAbout {
+    pub fn banner_url(&self) -> Option<&str> {
+        [&self.banner_background_image, &self.banner_img].into_iter()
+            .find(|url| url.starts_with("http"))
+            .map(String::as_str)
+    }
+
+    pub fn icon_url(&self) -> Option<&str> {
+        [&self.community_icon, &self.icon_img].into_iter()
+            .find(|url| url.starts_with("http"))
+            .map(String::as_str)
+    }
+
+    // e.g. "1.2M members · 3.4k online"; whichever counts Reddit shares
+    pub fn counts(&self) -> Option<String> {
+        let counts: Vec<String> = [(self.subscribers, "members"), (self.active_user_count, "online")].into_iter()
+            .filter_map(|(count, label)| Some(format!("{} {}", compact_count(count?), label)))
+            .collect();
+        (!counts.is_empty()).then(|| counts.join(" · "))
+    }
+}
+
+// Short form for large counts, e.g. 950, 3.4k, 12k, 1.2M
+pub fn compact_count(count: u64) -> String {
+    let scaled = |divisor: f64, suffix: &str| {
+        let value = count as f64 / divisor;
+        if value < 10.0 {
+            format!("{:.1}{}", (value * 10.0).floor() / 10.0, suffix)
+        } else {
+            format!("{}{}", value.floor(), suffix)
+        }
+    };
+    match count {
+        0..=999 => count.to_string(),
+        1_000..=999_999 => scaled(1e3, "k"),
+        _ => scaled(1e6, "M"),
+    }
+}
+
+// Why a subreddit's listing was refused, from the body of Reddit's 403 (or 404 for banned ones)
+#[derive(Debug, Clone, PartialEq)]
+pub enum Restriction {
+    Quarantined { message: String },  // The subreddit's own warning, in markdown; may be empty
+    Private,
+    Banned,
+}
+
+#[derive(Debug, Deserialize)]
+struct Refusal {
+    reason: String,
+    #[serde(default)]
+    quarantine_message: String,
+}
+
+pub fn restriction(body: &str) -> Option<Restriction> {
+    let refusal: Refusal = serde_json::from_str(body).ok()?;
+    match refusal.reason.as_str() {
+        "quarantined" => Some(Restriction::Quarantined { message: refusal.quarantine_message }),
+        "private" => Some(Restriction::Private),
+        "banned" => Some(Restriction::Banned),
+        _ => None,
+    }
+}
+
+impl Restriction {
+    pub fn describe(&self, subreddit: &str) -> String {
+        match self {
+            Restriction::Quarantined { .. } => format!("r/{} is quarantined", subreddit),
+            Restriction::Private => format!("r/{} is private", subreddit),
+            Restriction::Banned => format!("r/{} has been banned", subreddit),
+        }
+    }
+
+    pub fn explain(&self) -> &'static str {
+        match self {
+            Restriction::Quarantined { .. } => "Reddit has quarantined this community for content many people find shocking or \
+                offensive. Its posts show only to signed-in accounts that choose to see them.",
+            Restriction::Private => "Only approved members can see this community. You can ask its moderators for an invite on reddit.com.",
+            Restriction::Banned => "Reddit has shut this community down, and its posts are no longer available.",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_about_response() {
+        let about: SubredditAboutResponse = serde_json::from_str(r#"{"kind": "t5", "data": {
+            "display_name": "rust", "title": "The Rust Programming Language",
+            "subscribers": 345678, "active_user_count": null,
+            "banner_background_image": "https://styles.redditmedia.com/banner.png?width=4000&s=abc",
+            "banner_img": "", "community_icon": "", "icon_img": "https://b.thumbs.redditmedia.com/icon.png"
+        }}"#).unwrap();
+        let about = about.data;
+        assert_eq!(about.banner_url(), Some("https://styles.redditmedia.com/banner.png?width=4000&s=abc"));
+        assert_eq!(about.icon_url(), Some("https://b.thumbs.redditmedia.com/icon.png"));
+        assert_eq!(about.counts().as_deref(), Some("345k members"));
+        assert_eq!(SubredditAbout::default().counts(), None);
+
+        let rules: RulesResponse = serde_json::from_str(r#"{"rules": [
+            {"kind": "all", "short_name": "Keine Werbung", "description": "Eigenwerbung & Spam", "priority": 0}
+        ], "site_rules": []}"#).unwrap();
+        assert_eq!(rules.rules[0].short_name, "Keine Werbung");
+        assert_eq!(rules.rules[0].description, "Eigenwerbung & Spam");
+    }
+
+    #[test]
+    fn reads_why_a_subreddit_was_refused() {
+        let quarantined = r#"{"reason": "quarantined", "quarantine_message": "Be careful **here**", "message": "Forbidden", "error": 403}"#;
+        assert_eq!(restriction(quarantined), Some(Restriction::Quarantined { message: "Be careful **here**".to_string() }));
+        assert_eq!(restriction(r#"{"reason": "private", "message": "Forbidden", "error": 403}"#), Some(Restriction::Private));
+        assert_eq!(restriction(r#"{"reason": "banned", "message": "Not Found", "error": 404}"#), Some(Restriction::Banned));
+        assert_eq!(restriction(r#"{"message": "Forbidden", "error": 403}"#), None);
+        assert_eq!(restriction("<html>"), None);
+    }
+
+    #[test]
+    fn abbreviates_counts() {
+        assert_eq!(compact_count(950), "950");
+        assert_eq!(compact_count(3_460), "3.4k");
+        assert_eq!(compact_count(12_900), "12k");
+        assert_eq!(compact_count(1_250_000), "1.2M");
+    }
+}
@@ -0,0 +1,280 @@
+// Colours, corner rounding, spacing and window opacity for the whole UI, applied once per frame on
+// top of egui's dark or light visuals.
+//
+// A few presets are built in. Themes are plain JSON files with colours as "#rrggbb" strings:
+// the current one can be exported, and files dropped into the themes folder show up next to the
+// presets, which is how community themes get shared.
+//
+// Text size isn't part of a theme, since it's down to the reader's eyes rather than taste, but its
+// table lives here too so every text style scales from one place.
+use anyhow::{Context, Result};
+use eframe::egui::{self, Color32, FontFamily, FontId, TextStyle};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+pub const BODY_SIZE: f32 = 12.5;  // Body text in points at a font size of 1.0
+
+// egui's text styles, scaled by the font size setting; sizes set directly in the feed use the same factor
+pub fn text_styles(font_size: f32) -> BTreeMap<TextStyle, FontId> {
+    [
+        (TextStyle::Small, 9.0, FontFamily::Proportional),
+        (TextStyle::Body, BODY_SIZE, FontFamily::Proportional),
+        (TextStyle::Button, BODY_SIZE, FontFamily::Proportional),
+        (TextStyle::Monospace, 12.0, FontFamily::Monospace),
+        (TextStyle::Heading, 18.0, FontFamily::Proportional),
+    ]
+    .into_iter()
+    .map(|(style, size, family)| (style, FontId::new(size * font_size, family)))
+    .collect()
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]  // A hand-written theme only needs the colours it changes
+pub struct Theme {
+    pub name: String,
+    pub dark: bool,  // Which of egui's visuals the colours go on top of
+    #[serde(with = "hex")]
+    pub accent: Color32,  // Selected items and text selection
+    #[serde(with = "hex")]
+    pub background: Color32,
+    #[serde(with = "hex")]
+    pub card: Color32,  // Post cards and panels
+    #[serde(with = "hex")]
+    pub text: Color32,
+    #[serde(with = "hex")]
+    pub upvote: Color32,
+    #[serde(with = "hex")]
+    pub downvote: Color32,
+    pub rounding: f32,  // Corner radius of cards, buttons and windows, in points
+    pub spacing: f32,   // Gaps between widgets, as a multiple of egui's
+    pub opacity: f32,   // Of the window background; below 1.0 the desktop shows through where supported
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme::dark()
+    }
+}
+
+impl Theme {
+    pub fn dark() -> Self {
+        Theme {
+            name: "Dark".to_string(),
+            dark: true,
+            accent: Color32::from_rgb(0, 92, 128),
+            background: Color32::from_gray(27),
+            card: Color32::from_rgb(20, 20, 20),
+            text: Color32::from_gray(140),
+            upvote: Color32::from_rgb(255, 69, 0),
+            downvote: Color32::from_rgb(113, 147, 255),
+            rounding: 2.0,
+            spacing: 1.0,
+            opacity: 1.0,
+        }
+    }
+
+    pub fn light() -> Self {
+        Theme {
+            name: "Light".to_string(),
+            dark: false,
+            accent: Color32::from_rgb(144, 209, 255),
+            background: Color32::from_gray(248),
+            card: Color32::from_rgb(240, 240, 240),
+            text: Color32::from_gray(80),
+            ..Theme::dark()
+        }
+    }
+
+    pub fn presets() -> Vec<Theme> {
+        vec![
+            Theme::dark(),
+            Theme::light(),
+            Theme {
+                name: "Midnight".to_string(),
+                accent: Color32::from_rgb(255, 69, 0),
+                background: Color32::BLACK,
+                card: Color32::from_gray(12),
+                text: Color32::from_gray(170),
+                ..Theme::dark()
+            },
+            Theme {
+                name: "Nord".to_string(),
+                accent: Color32::from_rgb(94, 129, 172),
+                background: Color32::from_rgb(46, 52, 64),
+                card: Color32::from_rgb(59, 66, 82),
+                text: Color32::from_rgb(216, 222, 233),
+                downvote: Color32::from_rgb(136, 192, 208),
+                rounding: 6.0,
+                ..Theme::dark()
+            },
+            Theme {
+                name: "Solarized Light".to_string(),
+                accent: Color32::from_rgb(38, 139, 210),
+                background: Color32::from_rgb(253, 246, 227),
+                card: Color32::from_rgb(238, 232, 213),
+                text: Color32::from_rgb(88, 110, 117),
+                upvote: Color32::from_rgb(203, 75, 22),
+                downvote: Color32::from_rgb(108, 113, 196),
+                rounding: 4.0,
+                ..Theme::light()
+            },
+            Theme {
+                name: "Translucent".to_string(),
+                accent: Color32::from_rgb(88, 166, 255),
+                background: Color32::from_rgb(22, 27, 34),
+                card: Color32::from_rgb(33, 38, 45),
+                text: Color32::from_rgb(201, 209, 217),
+                rounding: 6.0,
+                opacity: 0.8,
+                ..Theme::dark()
+            },
+            Theme {
+                name: "Roomy".to_string(),
+                rounding: 8.0,
+                spacing: 1.5,
+                ..Theme::dark()
+            },
+        ]
+    }
+
+    pub fn apply(&self, style: &mut egui::Style) {
+        let mut visuals = if self.dark { egui::Visuals::dark() } else { egui::Visuals::light() };
+        visuals.panel_fill = self.window_background();
+        visuals.window_fill = self.background;
+        visuals.selection.bg_fill = self.accent;
+        visuals.selection.stroke.color = contrasting(self.accent);
+        visuals.widgets.noninteractive.fg_stroke.color = self.text;
+        visuals.widgets.inactive.fg_stroke.color = self.text;
+        let rounding = egui::Rounding::same(self.rounding);
+        for widget in [
+            &mut visuals.widgets.noninteractive,
+            &mut visuals.widgets.inactive,
+            &mut visuals.widgets.hovered,
+            &mut visuals.widgets.active,
+            &mut visuals.widgets.open,
+        ] {
+            widget.rounding = rounding;
+        }
+        visuals.window_rounding = egui::Rounding::same(self.rounding.max(6.0));
+        visuals.menu_rounding = rounding;
+        style.visuals = visuals;
+
+        let spacing = egui::style::Spacing::default();
+        let scale = self.spacing.clamp(0.5, 3.0);
+        style.spacing.item_spacing = spacing.item_spacing * scale;
+        style.spacing.button_padding = spacing.button_padding * scale;
+    }
+
+    pub fn translucent(&self) -> bool {
+        self.opacity < 1.0
+    }
+
+    // Panels are see-through at the theme's opacity; popups and cards stay solid so they're readable
+    fn window_background(&self) -> Color32 {
+        let [r, g, b, _] = self.background.to_array();
+        Color32::from_rgba_unmultiplied(r, g, b, (self.opacity.clamp(0.3, 1.0) * 255.0).round() as u8)
+    }
+
+    // e.g. "theme-solarized-light.json"
+    pub fn file_name(&self) -> String {
+        let slug: String = self.name.trim().to_lowercase().chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+            .collect();
+        format!("theme-{}.json", if slug.is_empty() { "custom" } else { &slug })
+    }
+
+    pub fn export(&self, dir: &Path) -> Result<std::path::PathBuf> {
+        let path = dir.join(self.file_name());
+        std::fs::write(&path, serde_json::to_string_pretty(self)?).with_context(|| format!("Failed to write {}", path.display()))?;
+        Ok(path)
+    }
+}
+
+// Every theme file in `dir` by name; files that aren't themes are left out
+pub fn load_dir(dir: &Path) -> Vec<Theme> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut themes: Vec<Theme> = entries.flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "json"))
+        .filter_map(|path| serde_json::from_str(&std::fs::read_to_string(path).ok()?).ok())
+        .collect();
+    themes.sort_by_key(|theme| theme.name.to_lowercase());
+    themes
+}
+
+// Black or white, whichever reads better on `fill`
+fn contrasting(fill: Color32) -> Color32 {
+    let luma = 0.299 * fill.r() as f32 + 0.587 * fill.g() as f32 + 0.114 * fill.b() as f32;
+    if luma > 140.0 { Color32::BLACK } else { Color32::WHITE }
+}
+
+// Settings from before themes only had a `dark_mode` flag
+pub fn deserialize_setting<'de, D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Theme, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Stored {
+        Theme(Theme),
+        DarkMode(bool),
+    }
+    Ok(match Stored::deserialize(deserializer)? {
+        Stored::Theme(theme) => theme,
+        Stored::DarkMode(true) => Theme::dark(),
+        Stored::DarkMode(false) => Theme::light(),
+    })
+}
+
+// Colours as "#rrggbb", the way people write them by hand
+mod hex {
+    use eframe::egui::Color32;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(color: &Color32, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&format!("#{:02x}{:02x}{:02x}", color.r(), color.g(), color.b()))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Color32, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        Color32::from_hex(&text).map_err(|_| serde::de::Error::custom(format!("not a colour: {}", text)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_theme_files_and_old_settings() {
+        let theme: Theme = serde_json::from_str(r##"{"name": "Ocean", "accent": "#0079d3", "rounding": 5}"##).unwrap();
+        assert_eq!(theme.accent, Color32::from_rgb(0x00, 0x79, 0xd3));
+        assert_eq!(theme.card, Theme::dark().card);
+        assert_eq!(theme.file_name(), "theme-ocean.json");
+        let json = serde_json::to_value(&theme).unwrap();
+        assert_eq!(json["accent"], "#0079d3");
+        assert!(serde_json::from_str::<Theme>(r#"{"accent": "blue"}"#).is_err());
+
+        #[derive(Deserialize)]
+        struct Settings {
+            #[serde(deserialize_with = "deserialize_setting")]
+            theme: Theme,
+        }
+        let old: Settings = serde_json::from_str(r#"{"theme": false}"#).unwrap();
+        assert_eq!(old.theme, Theme::light());
+        let new: Settings = serde_json::from_str(r#"{"theme": {"name": "Nord", "dark": true}}"#).unwrap();
+        assert_eq!(new.theme.name, "Nord");
+
+        let mut style = egui::Style::default();
+        Theme::presets()[2].apply(&mut style);
+        assert_eq!(style.visuals.panel_fill, Color32::BLACK);
+        assert_eq!(style.visuals.selection.stroke.color, Color32::WHITE);
+        let translucent = Theme::presets().into_iter().find(|theme| theme.name == "Translucent").unwrap();
+        translucent.apply(&mut style);
+        assert!(translucent.translucent());
+        assert_eq!(style.visuals.panel_fill.a(), 204);
+        assert!(style.visuals.window_fill.is_opaque());
+        assert_eq!(text_styles(1.0), egui::Style::default().text_styles);
+        assert_eq!(text_styles(2.0)[&TextStyle::Body].size, 25.0);
+    }
+}
@@ -0,0 +1,154 @@
+// Machine-translated glosses for flairs, subreddit titles and rules in other languages.
+//
+// Text is sent to a LibreTranslate-compatible server the reader points Rustle at, never to
+// Reddit, and a gloss is only ever drawn beside the original, marked as machine translation.
+// Each string is asked about once per session; ones already in the reader's language get no gloss.
+use crate::api_log;
+use anyhow::{bail, Context, Result};
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+const MAX_IN_FLIGHT: usize = 6;  // Requests at once; the rest wait for a later frame
+
+// Where to send text, from the translation settings
+#[derive(Debug, Clone)]
+pub struct Backend {
+    pub url: String,      // e.g. "https://libretranslate.example.org"
+    pub api_key: String,  // Only some servers want one
+    pub target: String,   // Language code glosses are in, e.g. "en"
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Gloss {
+    pub text: String,
+    pub from: String,  // Detected language code, when the server says
+}
+
+enum Slot {
+    Pending,
+    Done(Option<Gloss>),  // None when the text needs no gloss or couldn't be translated
+}
+
+pub enum Lookup {
+    Ready(Option<Gloss>),
+    Fetch,  // Not asked about yet; the caller sends the request and reports back with `finish`
+    Wait,
+}
+
+#[derive(Default)]
+pub struct Glosses {
+    slots: HashMap<String, Slot>,
+    target: String,  // What the slots were translated into
+}
+
+impl Glosses {
+    pub fn lookup(&mut self, text: &str, target: &str) -> Lookup {
+        if self.target != target {
+            self.slots.clear();
+            self.target = target.to_string();
+        }
+        match self.slots.get(text) {
+            Some(Slot::Done(gloss)) => Lookup::Ready(gloss.clone()),
+            Some(Slot::Pending) => Lookup::Wait,
+            None if !worth_translating(text) => Lookup::Ready(None),
+            None if self.slots.values().filter(|slot| matches!(slot, Slot::Pending)).count() >= MAX_IN_FLIGHT => Lookup::Wait,
+            None => {
+                self.slots.insert(text.to_string(), Slot::Pending);
+                Lookup::Fetch
+            }
+        }
+    }
+
+    pub fn finish(&mut self, text: &str, gloss: Option<Gloss>) {
+        if let Some(slot) = self.slots.get_mut(text) {
+            *slot = Slot::Done(gloss);
+        }
+    }
+}
+
+// Numbers, emoji and punctuation read the same in any language
+fn worth_translating(text: &str) -> bool {
+    text.chars().any(char::is_alphabetic)
+}
+
+#[derive(Serialize)]
+struct Request<'a> {
+    q: &'a str,
+    source: &'a str,
+    target: &'a str,
+    format: &'a str,
+    #[serde(skip_serializing_if = "str::is_empty")]
+    api_key: &'a str,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Response {
+    translated_text: String,
+    detected_language: Option<DetectedLanguage>,
+}
+
+#[derive(Deserialize)]
+struct DetectedLanguage {
+    language: String,
+}
+
+pub async fn translate(client: &reqwest::Client, backend: &Backend, text: &str) -> Result<Option<Gloss>> {
+    let request = client
+        .post(format!("{}/translate", backend.url.trim().trim_end_matches('/')))
+        .json(&Request { q: text, source: "auto", target: &backend.target, format: "text", api_key: &backend.api_key });
+    let response = api_log::send(client, request).await.context("Failed to reach the translation server")?;
+    if !response.status().is_success() {
+        bail!("The translation server answered {}", response.status());
+    }
+    let response: Response = response.json().await.context("Failed to parse the translation")?;
+    Ok(gloss_from(text, &backend.target, response))
+}
+
+fn gloss_from(original: &str, target: &str, response: Response) -> Option<Gloss> {
+    let from = response.detected_language.map(|detected| detected.language).unwrap_or_default();
+    let text = response.translated_text.trim();
+    if from.eq_ignore_ascii_case(target) || text.is_empty() || text.eq_ignore_ascii_case(original.trim()) {
+        return None;
+    }
+    Some(Gloss { text: text.to_string(), from })
+}
+
+// Drawn after the original, e.g. "≈ Question"
+pub fn show(ui: &mut egui::Ui, gloss: &Gloss, size: f32) {
+    let from = if gloss.from.is_empty() { String::new() } else { format!(" from \"{}\"", gloss.from) };
+    ui.label(egui::RichText::new(format!("≈ {}", gloss.text)).italics().weak().size(size))
+        .on_hover_text(format!("Machine translation{}. Only shown here; nothing is changed or sent on Reddit.", from));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn glosses_text_in_other_languages_once() {
+        let response = |json: &str| serde_json::from_str::<Response>(json).unwrap();
+        assert_eq!(
+            gloss_from("Frage", "en", response(r#"{"translatedText": "Question", "detectedLanguage": {"confidence": 90, "language": "de"}}"#)),
+            Some(Gloss { text: "Question".to_string(), from: "de".to_string() }),
+        );
+        assert_eq!(gloss_from("Question", "en", response(r#"{"translatedText": "Question", "detectedLanguage": {"language": "en"}}"#)), None);
+        assert_eq!(gloss_from("Meme", "en", response(r#"{"translatedText": "meme"}"#)), None);
+        let request = Request { q: "Frage", source: "auto", target: "en", format: "text", api_key: "" };
+        assert_eq!(serde_json::to_string(&request).unwrap(), r#"{"q":"Frage","source":"auto","target":"en","format":"text"}"#);
+
+        let mut glosses = Glosses::default();
+        assert!(matches!(glosses.lookup("Frage", "en"), Lookup::Fetch));
+        assert!(matches!(glosses.lookup("Frage", "en"), Lookup::Wait));
+        assert!(matches!(glosses.lookup("🔥 2024", "en"), Lookup::Ready(None)));
+        glosses.finish("Frage", Some(Gloss { text: "Question".to_string(), from: "de".to_string() }));
+        assert!(matches!(glosses.lookup("Frage", "en"), Lookup::Ready(Some(gloss)) if gloss.text == "Question"));
+        // Switching language starts over
+        assert!(matches!(glosses.lookup("Frage", "fr"), Lookup::Fetch));
+        for i in 1..MAX_IN_FLIGHT {
+            assert!(matches!(glosses.lookup(&format!("Wort {}", i), "fr"), Lookup::Fetch));
+        }
+        assert!(matches!(glosses.lookup("Noch eins", "fr"), Lookup::Wait));
+    }
+}
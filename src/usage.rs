@@ -0,0 +1,270 @@
+// Local usage statistics: time spent in Rustle, posts seen per subreddit, and how many of those
+// were opened.
+//
+// Counting happens in memory while the feed is drawn, and eframe's autosave writes it to the
+// offline store (store.rs). None of it is ever sent anywhere. The dashboard says so, and it can
+// purge everything.
+use crate::Post;
+use eframe::egui;
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+
+// A longer gap between frames means the reader stepped away, so it isn't counted
+const IDLE_AFTER: Duration = Duration::from_secs(120);
+
+// Counted since the last write to the store
+#[derive(Default)]
+pub struct Pending {
+    pub seconds: f64,
+    pub feeds: HashMap<String, f64>,  // The same time, split by the lowercase feed it was spent in
+    pub viewed: Vec<(String, String)>,  // (post name, subreddit)
+    pub opened: Vec<(String, String)>,
+}
+
+#[derive(Default)]
+pub struct Tracker {
+    last_frame: Option<Instant>,  // Previous frame, while the window had focus
+    counted: HashSet<String>,     // Posts already counted as viewed this session
+    pending: Pending,
+}
+
+impl Tracker {
+    // Called every frame; time only adds up while the window has focus
+    pub fn tick(&mut self, now: Instant, focused: bool, feed: &str) {
+        if let Some(last) = self.last_frame {
+            let gap = now.saturating_duration_since(last);
+            if gap <= IDLE_AFTER {
+                self.pending.seconds += gap.as_secs_f64();
+                *self.pending.feeds.entry(feed.to_lowercase()).or_default() += gap.as_secs_f64();
+            }
+        }
+        self.last_frame = focused.then_some(now);
+    }
+
+    // A card was drawn on screen
+    pub fn viewed(&mut self, post: &Post) {
+        if self.counted.insert(post.name.clone()) {
+            self.pending.viewed.push((post.name.clone(), post.subreddit.clone()));
+        }
+    }
+
+    // Its link or comments were opened
+    pub fn opened(&mut self, post: &Post) {
+        self.pending.opened.push((post.name.clone(), post.subreddit.clone()));
+    }
+
+    pub fn pending(&self) -> &Pending {
+        &self.pending
+    }
+
+    pub fn take(&mut self) -> Pending {
+        std::mem::take(&mut self.pending)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Range {
+    Day,
+    #[default]
+    Week,
+    Month,
+    All,
+}
+
+impl Range {
+    pub const ALL: [Range; 4] = [Range::Day, Range::Week, Range::Month, Range::All];
+
+    fn label(self) -> &'static str {
+        match self {
+            Range::Day => "Last day",
+            Range::Week => "Last week",
+            Range::Month => "Last 30 days",
+            Range::All => "All time",
+        }
+    }
+
+    // Seconds-since-epoch the range starts at
+    pub fn since(self, now: i64) -> i64 {
+        match self {
+            Range::Day => now - 86_400,
+            Range::Week => now - 7 * 86_400,
+            Range::Month => now - 30 * 86_400,
+            Range::All => 0,
+        }
+    }
+}
+
+pub struct SubredditStats {
+    pub subreddit: String,
+    pub viewed: u64,
+    pub opened: u64,
+}
+
+pub struct Stats {
+    pub seconds: f64,
+    pub subreddits: Vec<SubredditStats>,  // Most viewed first
+}
+
+impl Stats {
+    fn viewed(&self) -> u64 {
+        self.subreddits.iter().map(|stats| stats.viewed).sum()
+    }
+
+    fn opened(&self) -> u64 {
+        self.subreddits.iter().map(|stats| stats.opened).sum()
+    }
+}
+
+// Share of viewed posts that were opened, e.g. "12%"
+fn click_through(viewed: u64, opened: u64) -> String {
+    if viewed == 0 {
+        return "–".to_string();
+    }
+    format!("{:.0}%", opened as f64 * 100.0 / viewed as f64)
+}
+
+// e.g. "2h 5m"
+fn duration(seconds: f64) -> String {
+    let minutes = (seconds / 60.0) as u64;
+    match minutes {
+        0 => "Under a minute".to_string(),
+        1..=59 => format!("{}m", minutes),
+        _ => format!("{}h {}m", minutes / 60, minutes % 60),
+    }
+}
+
+pub enum Request {
+    Load(Range),
+    Purge,
+}
+
+// Stats window; the numbers are loaded on request rather than queried every frame
+#[derive(Default)]
+pub struct Dashboard {
+    range: Range,
+    stats: Option<Stats>,
+    confirm_purge: bool,
+}
+
+impl Dashboard {
+    pub fn set_stats(&mut self, stats: Stats) {
+        self.stats = Some(stats);
+    }
+
+    // False once the window is closed
+    pub fn show(&mut self, ctx: &egui::Context, tracking: bool, requests: &mut Vec<Request>) -> bool {
+        if self.stats.is_none() {
+            requests.push(Request::Load(self.range));
+        }
+        let mut open = true;
+        egui::Window::new("Usage stats")
+            .open(&mut open)
+            .default_size([420.0, 420.0])
+            .show(ctx, |ui| {
+                ui.label(egui::RichText::new("🔒 This data never leaves your machine").strong());
+                ui.label(egui::RichText::new("It's kept in Rustle's local database and is never sent to Reddit or anyone else.").weak());
+                if !tracking {
+                    ui.label(egui::RichText::new("Tracking is off; turn it on under Usage stats in settings.").weak());
+                }
+                ui.separator();
+
+                ui.horizontal(|ui| {
+                    for range in Range::ALL {
+                        if ui.selectable_value(&mut self.range, range, range.label()).clicked() {
+                            requests.push(Request::Load(range));
+                        }
+                    }
+                    if ui.small_button("Refresh").clicked() {
+                        requests.push(Request::Load(self.range));
+                    }
+                });
+                let Some(stats) = &self.stats else {
+                    ui.spinner();
+                    return;
+                };
+                egui::Grid::new("usage_totals").num_columns(2).show(ui, |ui| {
+                    ui.label("Time in app");
+                    ui.label(duration(stats.seconds));
+                    ui.end_row();
+                    ui.label("Posts viewed");
+                    ui.label(stats.viewed().to_string());
+                    ui.end_row();
+                    ui.label("Posts opened");
+                    ui.label(stats.opened().to_string());
+                    ui.end_row();
+                    ui.label("Click-through rate");
+                    ui.label(click_through(stats.viewed(), stats.opened()));
+                    ui.end_row();
+                });
+                ui.add_space(6.0);
+
+                egui::ScrollArea::vertical().max_height(240.0).auto_shrink([false, true]).show(ui, |ui| {
+                    egui::Grid::new("usage_subreddits").num_columns(4).striped(true).show(ui, |ui| {
+                        for heading in ["Subreddit", "Viewed", "Opened", "Click-through"] {
+                            ui.label(egui::RichText::new(heading).strong());
+                        }
+                        ui.end_row();
+                        for row in &stats.subreddits {
+                            ui.label(format!("r/{}", row.subreddit));
+                            ui.label(row.viewed.to_string());
+                            ui.label(row.opened.to_string());
+                            ui.label(click_through(row.viewed, row.opened));
+                            ui.end_row();
+                        }
+                    });
+                });
+                ui.separator();
+
+                ui.horizontal(|ui| {
+                    if !self.confirm_purge {
+                        if ui.button("Purge usage data").clicked() {
+                            self.confirm_purge = true;
+                        }
+                        return;
+                    }
+                    ui.label("Delete all usage data for good?");
+                    if ui.button("Delete").clicked() {
+                        requests.push(Request::Purge);
+                        self.confirm_purge = false;
+                    }
+                    if ui.button("Keep").clicked() {
+                        self.confirm_purge = false;
+                    }
+                });
+            });
+        open
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_focused_time_and_each_post_once() {
+        let start = Instant::now();
+        let mut tracker = Tracker::default();
+        tracker.tick(start, true, "home");
+        tracker.tick(start + Duration::from_secs(30), false, "home");
+        // Unfocused, then back after a long break
+        tracker.tick(start + Duration::from_secs(600), true, "home");
+        tracker.tick(start + Duration::from_secs(1200), true, "home");
+        tracker.tick(start + Duration::from_secs(1210), true, "Rust");
+
+        let post = Post { name: "t3_a".to_string(), subreddit: "rust".to_string(), ..Default::default() };
+        tracker.viewed(&post);
+        tracker.viewed(&post);
+        tracker.opened(&post);
+        let pending = tracker.take();
+        assert_eq!(pending.seconds, 40.0);
+        assert_eq!(pending.feeds, HashMap::from([("home".to_string(), 30.0), ("rust".to_string(), 10.0)]));
+        assert_eq!(pending.viewed.len(), 1);
+        assert_eq!(pending.opened.len(), 1);
+        tracker.viewed(&post);
+        assert!(tracker.take().viewed.is_empty());
+
+        assert_eq!(click_through(3, 1), "33%");
+        assert_eq!(click_through(0, 0), "–");
+        assert_eq!(duration(7500.0), "2h 5m");
+    }
+}
@@ -0,0 +1,197 @@
+// Inline playback for v.redd.it videos.
+//
+// Decoding is delegated to an external `ffmpeg` process that pipes raw RGBA frames back to us,
+// and audio (when unmuted) is played by a companion `ffplay` process. Both binaries need to be
+// on the PATH when the app is built with `--features video`.
+use anyhow::{Context, Result};
+use eframe::egui;
+use std::{
+    io::Read,
+    process::{Child, Command, Stdio},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+const PLAYBACK_FPS: u64 = 30;
+const MAX_VIDEO_WIDTH: u32 = 640;
+
+pub struct VideoPlayer {
+    size: [usize; 2],
+    frame: Arc<Mutex<Option<egui::ColorImage>>>,
+    texture: Option<egui::TextureHandle>,
+    paused: Arc<AtomicBool>,
+    finished: Arc<AtomicBool>,
+    frames_shown: Arc<AtomicU64>,
+    muted: bool,
+    decoder: Child,
+    audio: Option<Child>,
+    audio_url: Option<String>,
+}
+
+impl VideoPlayer {
+    // `looping` restarts the clip whenever it ends, the way GIFs behave
+    pub fn start(ctx: &egui::Context, video_url: &str, audio_url: Option<String>, width: u32, height: u32, looping: bool) -> Result<Self> {
+        // Scale down to something sensible for a feed card, keeping dimensions even for ffmpeg
+        let out_width = width.clamp(2, MAX_VIDEO_WIDTH) & !1;
+        let out_height = ((height.max(2) as u64 * out_width as u64 / width.max(1) as u64) as u32).max(2) & !1;
+
+        let mut decoder = Command::new("ffmpeg");
+        decoder.args(["-loglevel", "quiet"]);
+        if looping {
+            decoder.args(["-stream_loop", "-1"]);
+        }
+        let mut decoder = decoder
+            .args(["-i", video_url, "-an"])
+            .args(["-vf", &format!("scale={}:{},fps={}", out_width, out_height, PLAYBACK_FPS)])
+            .args(["-f", "rawvideo", "-pix_fmt", "rgba", "-"])
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .context("Failed to start ffmpeg (is it installed?)")?;
+
+        let mut stdout = decoder.stdout.take().context("ffmpeg produced no output")?;
+        let size = [out_width as usize, out_height as usize];
+        let frame = Arc::new(Mutex::new(None));
+        let paused = Arc::new(AtomicBool::new(false));
+        let finished = Arc::new(AtomicBool::new(false));
+        let frames_shown = Arc::new(AtomicU64::new(0));
+
+        {
+            let frame = frame.clone();
+            let paused = paused.clone();
+            let finished = finished.clone();
+            let frames_shown = frames_shown.clone();
+            let ctx = ctx.clone();
+            thread::spawn(move || {
+                let mut buffer = vec![0u8; size[0] * size[1] * 4];
+                loop {
+                    // Not reading while paused lets the pipe fill up and stalls ffmpeg for us
+                    if paused.load(Ordering::Relaxed) {
+                        thread::sleep(Duration::from_millis(50));
+                        continue;
+                    }
+                    if stdout.read_exact(&mut buffer).is_err() {
+                        break;
+                    }
+                    *frame.lock().unwrap() = Some(egui::ColorImage::from_rgba_unmultiplied(size, &buffer));
+                    frames_shown.fetch_add(1, Ordering::Relaxed);
+                    ctx.request_repaint();
+                    thread::sleep(Duration::from_millis(1000 / PLAYBACK_FPS));
+                }
+                finished.store(true, Ordering::Relaxed);
+                ctx.request_repaint();
+            });
+        }
+
+        Ok(VideoPlayer {
+            size,
+            frame,
+            texture: None,
+            paused,
+            finished,
+            frames_shown,
+            muted: true,
+            decoder,
+            audio: None,
+            audio_url,
+        })
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::Relaxed)
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.finished.load(Ordering::Relaxed)
+    }
+
+    pub fn is_muted(&self) -> bool {
+        self.muted
+    }
+
+    pub fn has_audio(&self) -> bool {
+        self.audio_url.is_some()
+    }
+
+    pub fn toggle_pause(&mut self) {
+        let paused = !self.is_paused();
+        self.paused.store(paused, Ordering::Relaxed);
+        self.sync_audio();
+    }
+
+    pub fn toggle_mute(&mut self) {
+        self.muted = !self.muted;
+        self.sync_audio();
+    }
+
+    // Start or stop the audio process to match the current pause/mute state
+    fn sync_audio(&mut self) {
+        let should_play = !self.muted && !self.is_paused() && !self.is_finished();
+        if !should_play {
+            self.stop_audio();
+            return;
+        }
+        if self.audio.is_some() {
+            return;
+        }
+        if let Some(url) = &self.audio_url {
+            // Resume from where the picture is so sound stays roughly in sync
+            let position = self.frames_shown.load(Ordering::Relaxed) as f64 / PLAYBACK_FPS as f64;
+            self.audio = Command::new("ffplay")
+                .args(["-nodisp", "-autoexit", "-loglevel", "quiet"])
+                .args(["-ss", &format!("{:.2}", position), "-i", url])
+                .stdin(Stdio::null())
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .spawn()
+                .ok();
+        }
+    }
+
+    fn stop_audio(&mut self) {
+        if let Some(mut audio) = self.audio.take() {
+            let _ = audio.kill();
+            let _ = audio.wait();
+        }
+    }
+
+    pub fn show(&mut self, ui: &mut egui::Ui, max_width: f32) {
+        if let Some(image) = self.frame.lock().unwrap().take() {
+            match &mut self.texture {
+                Some(texture) => texture.set(image, egui::TextureOptions::LINEAR),
+                None => {
+                    self.texture = Some(ui.ctx().load_texture("video-frame", image, egui::TextureOptions::LINEAR));
+                }
+            }
+        }
+        if self.is_finished() {
+            self.stop_audio();
+        }
+
+        let scale = (max_width / self.size[0] as f32).min(1.0);
+        let display_size = egui::vec2(self.size[0] as f32 * scale, self.size[1] as f32 * scale);
+        match &self.texture {
+            Some(texture) => {
+                ui.add(egui::Image::new((texture.id(), display_size)));
+            }
+            None => {
+                ui.allocate_ui(display_size, |ui| {
+                    ui.centered_and_justified(|ui| ui.spinner());
+                });
+            }
+        }
+    }
+}
+
+impl Drop for VideoPlayer {
+    fn drop(&mut self) {
+        self.stop_audio();
+        let _ = self.decoder.kill();
+        let _ = self.decoder.wait();
+    }
+}
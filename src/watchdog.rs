@@ -0,0 +1,142 @@
+// Catches loading flags that stay set long after any request should have finished, as happens
+// when a worker thread panics or a connection hangs without ever failing. The app checks its
+// flags every frame (`RedditApp::watch_loads`), clears a stalled one and offers a retry.
+//
+// Each load started under a flag takes a `Load` ticket. Resetting a stalled flag moves its
+// generation on, so a worker that was only slow finds its ticket out of date when it finally
+// returns and drops its results instead of overwriting the retry's or clearing its flag.
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+pub const TIMEOUT: Duration = Duration::from_secs(90);
+
+#[derive(Default)]
+pub struct Watchdog {
+    loads: Mutex<HashMap<&'static str, Track>>,
+}
+
+#[derive(Default)]
+struct Track {
+    generation: u64,
+    since: Option<Instant>,  // When the flag was first seen set, or last made progress
+}
+
+// Held by a worker for the load it's running
+pub struct Load {
+    watchdog: Arc<Watchdog>,
+    name: &'static str,
+    generation: u64,
+}
+
+impl Watchdog {
+    // Ticket for a load starting under `name`'s flag
+    pub fn begin(self: &Arc<Self>, name: &'static str) -> Load {
+        let generation = self.loads.lock().unwrap().entry(name).or_default().generation;
+        Load { watchdog: self.clone(), name, generation }
+    }
+
+    // True once `name` has been busy for longer than TIMEOUT without progress; its clock then
+    // starts over and any load still running under it is no longer current
+    pub fn stalled(&self, name: &'static str, busy: bool, now: Instant) -> bool {
+        let mut loads = self.loads.lock().unwrap();
+        let track = loads.entry(name).or_default();
+        if !busy {
+            track.since = None;
+            return false;
+        }
+        let since = *track.since.get_or_insert(now);
+        if now.duration_since(since) < TIMEOUT {
+            return false;
+        }
+        track.since = None;
+        track.generation += 1;
+        true
+    }
+
+    // Restart `name`'s clock if it's busy; a load that's still moving isn't stalled
+    pub fn progress(&self, name: &'static str, now: Instant) {
+        if let Some(since) = self.loads.lock().unwrap().get_mut(name).and_then(|track| track.since.as_mut()) {
+            *since = now;
+        }
+    }
+
+    // When the longest-running load will have stalled, for scheduling the next look
+    pub fn deadline(&self) -> Option<Instant> {
+        self.loads.lock().unwrap().values().filter_map(|track| track.since).min().map(|since| since + TIMEOUT)
+    }
+}
+
+impl Load {
+    // False once the watchdog gave up on this load; its results are stale and the flag now
+    // belongs to whatever load came next
+    pub fn current(&self) -> bool {
+        self.watchdog.loads.lock().unwrap().get(self.name).is_some_and(|track| track.generation == self.generation)
+    }
+
+    // Clear the load's flag, unless the watchdog already did and a newer load has set it since
+    pub fn finish(&self, flag: &Mutex<bool>) {
+        if self.current() {
+            *flag.lock().unwrap() = false;
+        }
+    }
+
+    pub fn progress(&self) {
+        if self.current() {
+            self.watchdog.progress(self.name, Instant::now());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flags_loads_that_outlast_the_timeout() {
+        let watchdog = Watchdog::default();
+        let start = Instant::now();
+        assert!(!watchdog.stalled("posts", true, start));
+        assert_eq!(watchdog.deadline(), Some(start + TIMEOUT));
+        assert!(!watchdog.stalled("posts", true, start + TIMEOUT / 2));
+        assert!(watchdog.stalled("posts", true, start + TIMEOUT));
+        assert_eq!(watchdog.deadline(), None);
+
+        // Finishing in time resets the clock
+        assert!(!watchdog.stalled("comments", true, start));
+        assert!(!watchdog.stalled("comments", false, start + TIMEOUT / 2));
+        assert!(!watchdog.stalled("comments", true, start + TIMEOUT));
+    }
+
+    #[test]
+    fn keeps_loads_that_make_progress() {
+        let watchdog = Watchdog::default();
+        let start = Instant::now();
+        assert!(!watchdog.stalled("subreddits", true, start));
+        watchdog.progress("subreddits", start + TIMEOUT / 2);
+        assert!(!watchdog.stalled("subreddits", true, start + TIMEOUT));
+        assert_eq!(watchdog.deadline(), Some(start + TIMEOUT / 2 + TIMEOUT));
+        assert!(watchdog.stalled("subreddits", true, start + TIMEOUT / 2 + TIMEOUT));
+    }
+
+    #[test]
+    fn retires_loads_that_stalled() {
+        let watchdog = Arc::new(Watchdog::default());
+        let start = Instant::now();
+        let slow = watchdog.begin("posts");
+        assert!(slow.current());
+        assert!(!watchdog.stalled("posts", true, start));
+        assert!(watchdog.stalled("posts", true, start + TIMEOUT));
+        assert!(!slow.current());
+
+        // The retry's ticket is current, and the old worker's progress doesn't keep it alive
+        let retry = watchdog.begin("posts");
+        assert!(retry.current());
+        assert!(!watchdog.stalled("posts", true, start + TIMEOUT));
+        slow.progress();
+        assert_eq!(watchdog.deadline(), Some(start + TIMEOUT * 2));
+        assert!(watchdog.begin("comments").current());
+    }
+}